@@ -0,0 +1,5 @@
+CREATE TABLE crate_freshness (
+  crate_id INTEGER NOT NULL PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+  freshness DOUBLE PRECISION NOT NULL,
+  computed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
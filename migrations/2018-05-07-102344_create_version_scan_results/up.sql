@@ -0,0 +1,9 @@
+CREATE TABLE version_scan_results (
+  id SERIAL PRIMARY KEY,
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  scanner VARCHAR NOT NULL,
+  blocked BOOLEAN NOT NULL,
+  detail VARCHAR,
+  scanned_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX version_scan_results_version_id ON version_scan_results (version_id);
@@ -0,0 +1,10 @@
+CREATE TABLE background_migrations (
+  name VARCHAR NOT NULL PRIMARY KEY,
+  last_id INTEGER NOT NULL DEFAULT 0,
+  processed BIGINT NOT NULL DEFAULT 0,
+  total BIGINT NOT NULL,
+  started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at TIMESTAMP
+);
+SELECT diesel_manage_updated_at('background_migrations');
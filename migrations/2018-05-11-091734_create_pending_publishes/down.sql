@@ -0,0 +1,2 @@
+DROP TABLE pending_publishes;
+ALTER TABLE users DROP COLUMN confirm_new_crates;
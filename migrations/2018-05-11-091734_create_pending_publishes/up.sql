@@ -0,0 +1,11 @@
+ALTER TABLE users ADD COLUMN confirm_new_crates BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE pending_publishes (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  crate_name VARCHAR NOT NULL,
+  token TEXT NOT NULL UNIQUE DEFAULT random_string(26),
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  confirmed_at TIMESTAMP
+);
+CREATE INDEX pending_publishes_user_id_crate_name ON pending_publishes (user_id, crate_name);
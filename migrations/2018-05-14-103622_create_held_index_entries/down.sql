@@ -0,0 +1,3 @@
+DROP TABLE held_index_entries;
+ALTER TABLE crates DROP COLUMN publish_hold;
+ALTER TABLE users DROP COLUMN created_at;
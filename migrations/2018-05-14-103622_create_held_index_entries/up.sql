@@ -0,0 +1,13 @@
+-- Existing accounts are considered established, only accounts created from
+-- now on get the current time.
+ALTER TABLE users ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT '1970-01-01';
+ALTER TABLE users ALTER COLUMN created_at SET DEFAULT CURRENT_TIMESTAMP;
+
+ALTER TABLE crates ADD COLUMN publish_hold BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE held_index_entries (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  entry JSONB NOT NULL,
+  reason VARCHAR NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
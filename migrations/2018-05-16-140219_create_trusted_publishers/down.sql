@@ -0,0 +1,2 @@
+DROP TABLE trusted_publishing_tokens;
+DROP TABLE trusted_publishers;
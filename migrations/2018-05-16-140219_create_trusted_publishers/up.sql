@@ -0,0 +1,20 @@
+CREATE TABLE trusted_publishers (
+  id SERIAL PRIMARY KEY,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  repository_owner VARCHAR NOT NULL,
+  repository_name VARCHAR NOT NULL,
+  workflow_filename VARCHAR NOT NULL,
+  created_by INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX trusted_publishers_crate_id ON trusted_publishers (crate_id);
+
+CREATE TABLE trusted_publishing_tokens (
+  id SERIAL PRIMARY KEY,
+  repository_owner VARCHAR NOT NULL,
+  repository_name VARCHAR NOT NULL,
+  workflow_filename VARCHAR NOT NULL,
+  token VARCHAR NOT NULL UNIQUE DEFAULT random_string(32),
+  expires_at TIMESTAMP NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
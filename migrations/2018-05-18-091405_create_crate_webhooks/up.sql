@@ -0,0 +1,20 @@
+CREATE TABLE crate_webhooks (
+  id SERIAL PRIMARY KEY,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  url VARCHAR NOT NULL,
+  secret VARCHAR NOT NULL DEFAULT random_string(32),
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX crate_webhooks_crate_id ON crate_webhooks (crate_id);
+
+CREATE TABLE webhook_deliveries (
+  id SERIAL PRIMARY KEY,
+  webhook_id INTEGER NOT NULL REFERENCES crate_webhooks (id) ON DELETE CASCADE,
+  event VARCHAR NOT NULL,
+  payload JSONB NOT NULL,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  last_error VARCHAR,
+  delivered_at TIMESTAMP,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX webhook_deliveries_pending ON webhook_deliveries (id) WHERE delivered_at IS NULL;
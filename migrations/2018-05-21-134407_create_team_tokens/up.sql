@@ -0,0 +1,10 @@
+CREATE TABLE team_tokens (
+  id SERIAL PRIMARY KEY,
+  team_id INTEGER NOT NULL REFERENCES teams (id) ON DELETE CASCADE,
+  created_by INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  token VARCHAR NOT NULL UNIQUE DEFAULT random_string(32),
+  name VARCHAR NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  last_used_at TIMESTAMP
+);
+CREATE INDEX team_tokens_team_id ON team_tokens (team_id);
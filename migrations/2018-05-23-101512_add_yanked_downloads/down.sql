@@ -0,0 +1 @@
+ALTER TABLE version_downloads DROP COLUMN yanked_downloads;
@@ -0,0 +1 @@
+ALTER TABLE version_downloads ADD COLUMN yanked_downloads INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+DROP FUNCTION resolve_crate_name(text);
+DROP TABLE crate_renames;
@@ -0,0 +1,25 @@
+CREATE TABLE crate_renames (
+    id SERIAL PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+    old_name VARCHAR NOT NULL,
+    new_name VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    index_rewritten BOOLEAN NOT NULL DEFAULT FALSE,
+    -- The last version published before the rename, whose files are stored
+    -- under the former name along with the versions before it. NULL if the
+    -- crate had no versions yet.
+    last_version_id INTEGER REFERENCES versions (id) ON DELETE SET NULL
+);
+CREATE INDEX index_crate_renames_old_name ON crate_renames (canon_crate_name(old_name));
+CREATE INDEX index_crate_renames_crate_id ON crate_renames (crate_id);
+
+-- Former names can't be taken by other crates, so a name resolves to the
+-- current name of the crate it used to belong to, if any.
+CREATE FUNCTION resolve_crate_name(text) RETURNS text AS $$
+    SELECT COALESCE((
+        SELECT crates.name FROM crate_renames
+        INNER JOIN crates ON crates.id = crate_renames.crate_id
+        WHERE canon_crate_name(crate_renames.old_name) = canon_crate_name($1)
+        LIMIT 1
+    ), $1)
+$$ LANGUAGE SQL STABLE;
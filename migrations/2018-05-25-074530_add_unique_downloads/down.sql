@@ -0,0 +1 @@
+ALTER TABLE version_downloads DROP COLUMN unique_downloads;
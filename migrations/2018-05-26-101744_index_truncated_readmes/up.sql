@@ -0,0 +1,28 @@
+-- READMEs are indexed with the lowest weight, and truncated so that the
+-- longest ones don't exceed the size limit of a tsvector.
+CREATE OR REPLACE FUNCTION trigger_crates_name_search() RETURNS trigger AS $$
+DECLARE kws TEXT;
+begin
+  SELECT array_to_string(array_agg(keyword), ',') INTO kws
+    FROM keywords INNER JOIN crates_keywords
+    ON keywords.id = crates_keywords.keyword_id
+    WHERE crates_keywords.crate_id = new.id;
+  new.textsearchable_index_col :=
+     setweight(to_tsvector('pg_catalog.english',
+                           coalesce(new.name, '')), 'A') ||
+     setweight(to_tsvector('pg_catalog.english',
+                           coalesce(kws, '')), 'B') ||
+     setweight(to_tsvector('pg_catalog.english',
+                           coalesce(new.description, '')), 'C') ||
+     setweight(to_tsvector('pg_catalog.english',
+                           coalesce(left(new.readme, 65536), '')), 'D');
+  return new;
+end
+$$ LANGUAGE plpgsql;
+
+-- Publishing a version with a new README reindexes the crate
+DROP TRIGGER trigger_crates_tsvector_update ON crates;
+
+CREATE TRIGGER trigger_crates_tsvector_update BEFORE INSERT OR UPDATE OF updated_at, readme
+ON crates
+FOR EACH ROW EXECUTE PROCEDURE trigger_crates_name_search();
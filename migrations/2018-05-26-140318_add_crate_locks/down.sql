@@ -0,0 +1,2 @@
+ALTER TABLE crates DROP COLUMN lock_reason;
+ALTER TABLE crates DROP COLUMN locked;
@@ -0,0 +1,2 @@
+ALTER TABLE crates ADD COLUMN locked BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE crates ADD COLUMN lock_reason TEXT;
@@ -0,0 +1,3 @@
+-- The checksums of the versions published before this migration are unknown
+ALTER TABLE versions ADD COLUMN checksum VARCHAR;
+ALTER TABLE versions ADD COLUMN links VARCHAR;
@@ -0,0 +1,2 @@
+DROP TABLE crate_list_items;
+DROP TABLE crate_lists;
@@ -0,0 +1,18 @@
+CREATE TABLE crate_lists (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  name VARCHAR NOT NULL,
+  description VARCHAR,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE UNIQUE INDEX crate_lists_user_id_name ON crate_lists (user_id, lower(name));
+SELECT diesel_manage_updated_at('crate_lists');
+
+CREATE TABLE crate_list_items (
+  list_id INTEGER NOT NULL REFERENCES crate_lists (id) ON DELETE CASCADE,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (list_id, crate_id)
+);
+CREATE INDEX crate_list_items_crate_id ON crate_list_items (crate_id);
@@ -0,0 +1 @@
+ALTER TABLE api_tokens ADD COLUMN allowed_ips VARCHAR[] NOT NULL DEFAULT '{}';
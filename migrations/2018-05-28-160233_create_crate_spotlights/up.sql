@@ -0,0 +1,10 @@
+CREATE TABLE crate_spotlights (
+  id SERIAL PRIMARY KEY,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  featured_on DATE NOT NULL UNIQUE,
+  rationale VARCHAR NOT NULL,
+  -- The administrator who chose the crate, NULL when picked automatically
+  created_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX crate_spotlights_crate_id ON crate_spotlights (crate_id);
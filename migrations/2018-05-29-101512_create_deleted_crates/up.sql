@@ -0,0 +1,15 @@
+CREATE TABLE deleted_crates (
+  id SERIAL PRIMARY KEY,
+  -- The id the crate had, kept so that it can be restored under it
+  crate_id INTEGER NOT NULL,
+  name VARCHAR NOT NULL,
+  reason VARCHAR,
+  deleted_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+  -- The rows of the crate, its versions, owners, dependencies, keywords and
+  -- categories at the time it was deleted
+  snapshot JSONB NOT NULL,
+  -- The lines of the crate's file in the git index
+  index_entries TEXT,
+  deleted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX deleted_crates_name ON deleted_crates (canon_crate_name(name));
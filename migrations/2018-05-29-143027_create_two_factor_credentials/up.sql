@@ -0,0 +1,17 @@
+CREATE TABLE two_factor_credentials (
+  user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+  -- The base32 encoded TOTP secret shared with the authenticator app
+  secret VARCHAR NOT NULL,
+  -- NULL until the user confirms the enrollment with a first code
+  enabled_at TIMESTAMP,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE two_factor_recovery_codes (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  -- The hex encoded SHA-256 of the code
+  code_hash VARCHAR NOT NULL,
+  used_at TIMESTAMP
+);
+CREATE INDEX two_factor_recovery_codes_user_id ON two_factor_recovery_codes (user_id);
@@ -0,0 +1,2 @@
+ALTER TABLE versions DROP COLUMN binaries;
+ALTER TABLE versions DROP COLUMN edition;
@@ -0,0 +1,5 @@
+-- The Rust edition the version is compiled with, unknown for old versions
+ALTER TABLE versions ADD COLUMN edition VARCHAR;
+-- The binaries of the version and the features they require, as
+-- `[{"name": ..., "required_features": [...]}]`
+ALTER TABLE versions ADD COLUMN binaries JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,29 @@
+ALTER TABLE crates ADD COLUMN quality_score DOUBLE PRECISION;
+
+-- The score is recomputed every night, that mustn't touch the crates like the
+-- download counts don't
+CREATE FUNCTION set_crates_updated_at() RETURNS trigger AS $$
+DECLARE
+    new_downloads integer;
+    new_quality_score double precision;
+BEGIN
+    new_downloads := NEW.downloads;
+    new_quality_score := NEW.quality_score;
+    OLD.downloads := NEW.downloads;
+    OLD.quality_score := NEW.quality_score;
+    IF (
+        NEW IS DISTINCT FROM OLD AND
+        NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+    ) THEN
+        NEW.updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    NEW.downloads := new_downloads;
+    NEW.quality_score := new_quality_score;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER trigger_crates_set_updated_at ON crates;
+CREATE TRIGGER trigger_crates_set_updated_at BEFORE UPDATE
+ON crates
+FOR EACH ROW EXECUTE PROCEDURE set_crates_updated_at();
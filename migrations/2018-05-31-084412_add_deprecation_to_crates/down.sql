@@ -0,0 +1,4 @@
+ALTER TABLE crates
+  DROP COLUMN deprecated,
+  DROP COLUMN deprecation_message,
+  DROP COLUMN alternative;
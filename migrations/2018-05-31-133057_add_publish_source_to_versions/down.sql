@@ -0,0 +1,4 @@
+ALTER TABLE versions
+  DROP COLUMN published_by,
+  DROP COLUMN cargo_version,
+  DROP COLUMN ci_provider;
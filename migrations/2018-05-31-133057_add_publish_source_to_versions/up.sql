@@ -0,0 +1,5 @@
+-- Where a version was published from, unknown for old versions
+ALTER TABLE versions
+  ADD COLUMN published_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+  ADD COLUMN cargo_version VARCHAR,
+  ADD COLUMN ci_provider VARCHAR;
@@ -0,0 +1,8 @@
+-- Keywords crates are published with that stand for another keyword, e.g.
+-- `wasm` for `webassembly`
+CREATE TABLE keyword_aliases (
+  alias VARCHAR NOT NULL PRIMARY KEY,
+  keyword_id INTEGER NOT NULL REFERENCES keywords (id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX ON keyword_aliases (keyword_id);
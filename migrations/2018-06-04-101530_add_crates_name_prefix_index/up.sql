@@ -0,0 +1,3 @@
+-- Lets `LIKE 'prefix%'` on the canonical names use an index whatever the
+-- collation of the database
+CREATE INDEX index_crates_name_prefix ON crates (canon_crate_name(name) text_pattern_ops);
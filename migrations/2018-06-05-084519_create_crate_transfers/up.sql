@@ -0,0 +1,10 @@
+CREATE TABLE crate_transfers (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates ON DELETE CASCADE,
+    claimant_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    reason VARCHAR NOT NULL,
+    transfer_at TIMESTAMP NOT NULL,
+    created_by INTEGER REFERENCES users ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crate_transfers_transfer_at ON crate_transfers (transfer_at);
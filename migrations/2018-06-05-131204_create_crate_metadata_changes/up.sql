@@ -0,0 +1,11 @@
+CREATE TABLE crate_metadata_changes (
+    id SERIAL PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates ON DELETE CASCADE,
+    version_id INTEGER REFERENCES versions ON DELETE SET NULL,
+    field VARCHAR NOT NULL,
+    added VARCHAR[] NOT NULL DEFAULT '{}',
+    removed VARCHAR[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crate_metadata_changes_crate_id ON crate_metadata_changes (crate_id, created_at);
@@ -0,0 +1 @@
+ALTER TABLE crates DROP COLUMN logo_path;
@@ -0,0 +1 @@
+ALTER TABLE crates ADD COLUMN logo_path VARCHAR;
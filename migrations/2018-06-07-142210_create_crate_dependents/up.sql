@@ -0,0 +1,8 @@
+CREATE TABLE crate_dependents (
+    crate_id INTEGER NOT NULL REFERENCES crates ON DELETE CASCADE,
+    dependent_id INTEGER NOT NULL REFERENCES crates ON DELETE CASCADE,
+    first_seen_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (crate_id, dependent_id)
+);
+
+CREATE INDEX crate_dependents_first_seen_at ON crate_dependents (crate_id, first_seen_at);
@@ -0,0 +1,6 @@
+CREATE TABLE version_sources (
+    version_id INTEGER PRIMARY KEY REFERENCES versions ON DELETE CASCADE,
+    readme TEXT,
+    readme_file VARCHAR,
+    manifest TEXT
+);
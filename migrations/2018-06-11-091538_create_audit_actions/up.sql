@@ -0,0 +1,12 @@
+CREATE TABLE audit_actions (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+    crate_id INTEGER REFERENCES crates ON DELETE SET NULL,
+    crate_name VARCHAR NOT NULL,
+    version VARCHAR,
+    action VARCHAR NOT NULL,
+    detail VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX audit_actions_user_id ON audit_actions (user_id, id);
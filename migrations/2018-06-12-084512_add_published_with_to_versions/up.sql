@@ -0,0 +1,2 @@
+-- How the publish of a version was authenticated, unknown for old versions
+ALTER TABLE versions ADD COLUMN published_with VARCHAR;
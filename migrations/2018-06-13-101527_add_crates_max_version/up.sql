@@ -0,0 +1,5 @@
+-- The highest versions of each crate that are not yanked, filled in by the
+-- `backfill_crate_max_versions` background migration for existing crates
+ALTER TABLE crates
+  ADD COLUMN max_version VARCHAR,
+  ADD COLUMN max_stable_version VARCHAR;
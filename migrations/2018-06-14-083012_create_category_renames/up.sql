@@ -0,0 +1,8 @@
+-- The slugs categories had before they were renamed in `categories.toml`, so
+-- that links to them and searches by them keep working
+CREATE TABLE category_renames (
+  old_slug VARCHAR NOT NULL PRIMARY KEY,
+  category_id INTEGER NOT NULL REFERENCES categories (id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX ON category_renames (category_id);
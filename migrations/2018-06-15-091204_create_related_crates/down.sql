@@ -0,0 +1 @@
+DROP TABLE related_crates;
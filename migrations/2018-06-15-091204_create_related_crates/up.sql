@@ -0,0 +1,8 @@
+-- The crates most related to each crate, recomputed by the
+-- `update-related-crates` job
+CREATE TABLE related_crates (
+    crate_id INTEGER NOT NULL REFERENCES crates ON DELETE CASCADE,
+    related_id INTEGER NOT NULL REFERENCES crates ON DELETE CASCADE,
+    score INTEGER NOT NULL,
+    PRIMARY KEY (crate_id, related_id)
+);
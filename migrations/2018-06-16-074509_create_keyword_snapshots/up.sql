@@ -0,0 +1,8 @@
+-- The number of crates of each keyword every day, recorded by the
+-- `snapshot-keywords` job to tell which keywords are trending
+CREATE TABLE keyword_snapshots (
+    keyword_id INTEGER NOT NULL REFERENCES keywords ON DELETE CASCADE,
+    date DATE NOT NULL DEFAULT CURRENT_DATE,
+    crates_cnt INTEGER NOT NULL,
+    PRIMARY KEY (keyword_id, date)
+);
@@ -0,0 +1,8 @@
+-- The index entries of published versions, written in the same transaction
+-- as the version so that they reach the index even if the server stops
+-- before writing them.
+CREATE TABLE index_outbox (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  entry JSONB NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
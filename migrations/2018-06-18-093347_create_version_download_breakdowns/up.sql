@@ -0,0 +1,10 @@
+-- The daily downloads of versions per cargo version and country, only
+-- counted when enabled by `DOWNLOAD_BREAKDOWN`.
+CREATE TABLE version_download_breakdowns (
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  date DATE NOT NULL DEFAULT CURRENT_DATE,
+  cargo_version VARCHAR NOT NULL,
+  country VARCHAR NOT NULL,
+  downloads INTEGER NOT NULL DEFAULT 1,
+  PRIMARY KEY (version_id, date, cargo_version, country)
+);
@@ -0,0 +1,7 @@
+-- The sitemaps of the crates and categories, regenerated daily by the
+-- `update-sitemaps` job.
+CREATE TABLE sitemaps (
+  name VARCHAR PRIMARY KEY,
+  body TEXT NOT NULL,
+  generated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
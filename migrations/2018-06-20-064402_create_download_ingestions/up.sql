@@ -0,0 +1,7 @@
+-- The batches of downloads served by the CDN that were added to
+-- `version_downloads`, so that a batch sent again isn't counted twice.
+CREATE TABLE download_ingestions (
+  batch_id VARCHAR PRIMARY KEY,
+  records INTEGER NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
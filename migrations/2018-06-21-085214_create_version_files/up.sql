@@ -0,0 +1,8 @@
+-- The files of the crate files of versions, listed when they are published.
+CREATE TABLE version_files (
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  path VARCHAR NOT NULL,
+  size BIGINT NOT NULL,
+  sha256 VARCHAR NOT NULL,
+  PRIMARY KEY (version_id, path)
+);
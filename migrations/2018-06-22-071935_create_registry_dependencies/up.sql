@@ -0,0 +1,15 @@
+-- The dependencies of versions on crates of other registries, which aren't in
+-- the `crates` table.
+CREATE TABLE registry_dependencies (
+  id SERIAL PRIMARY KEY,
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  registry VARCHAR NOT NULL,
+  name VARCHAR NOT NULL,
+  req VARCHAR NOT NULL,
+  optional BOOLEAN NOT NULL,
+  default_features BOOLEAN NOT NULL,
+  features TEXT[] NOT NULL,
+  target VARCHAR,
+  kind INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX registry_dependencies_version_id ON registry_dependencies (version_id);
@@ -0,0 +1,10 @@
+-- The crates administrators picked to feature at the top of a category.
+CREATE TABLE category_featured_crates (
+  category_id INTEGER NOT NULL REFERENCES categories (id) ON DELETE CASCADE,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  created_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (category_id, crate_id)
+);
+CREATE INDEX category_featured_crates_crate_id ON category_featured_crates (crate_id);
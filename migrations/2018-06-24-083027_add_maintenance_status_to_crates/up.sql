@@ -0,0 +1,5 @@
+ALTER TABLE crates ADD COLUMN maintenance_status VARCHAR CHECK (maintenance_status IN (
+  'actively-developed',
+  'passively-maintained',
+  'looking-for-maintainer'
+));
@@ -0,0 +1,6 @@
+-- The detached signatures of crate files sent along with them when publishing.
+CREATE TABLE version_signatures (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  signature TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
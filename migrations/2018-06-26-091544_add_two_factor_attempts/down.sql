@@ -0,0 +1,3 @@
+ALTER TABLE two_factor_credentials DROP COLUMN locked_until;
+ALTER TABLE two_factor_credentials DROP COLUMN failed_attempts;
+ALTER TABLE two_factor_credentials DROP COLUMN last_used_step;
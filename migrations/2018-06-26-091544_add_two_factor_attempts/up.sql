@@ -0,0 +1,6 @@
+-- The step of the last code accepted, so that codes can't be used twice
+ALTER TABLE two_factor_credentials ADD COLUMN last_used_step BIGINT;
+-- The invalid codes sent in a row, the second factor is locked for a while
+-- once there are too many of them
+ALTER TABLE two_factor_credentials ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE two_factor_credentials ADD COLUMN locked_until TIMESTAMP;
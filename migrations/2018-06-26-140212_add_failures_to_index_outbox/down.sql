@@ -0,0 +1,2 @@
+ALTER TABLE index_outbox DROP COLUMN last_error;
+ALTER TABLE index_outbox DROP COLUMN attempts;
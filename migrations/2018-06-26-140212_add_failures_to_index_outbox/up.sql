@@ -0,0 +1,4 @@
+-- The entries that couldn't be written are skipped, so that they don't hold
+-- up the entries recorded after them, and retried by the outbox task
+ALTER TABLE index_outbox ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE index_outbox ADD COLUMN last_error TEXT;
@@ -0,0 +1,2 @@
+UPDATE users SET gh_id = -1 WHERE deleted_at IS NOT NULL;
+ALTER TABLE users DROP COLUMN deleted_at;
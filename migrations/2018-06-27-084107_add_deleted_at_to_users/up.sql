@@ -0,0 +1,4 @@
+-- When the account was deleted, the sessions of deleted users don't
+-- authenticate anymore. The accounts deleted before are marked by the
+-- `mark_deleted_users` background migration.
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMP;
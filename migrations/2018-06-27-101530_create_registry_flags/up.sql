@@ -0,0 +1,8 @@
+-- The switches administrators toggle at runtime, like the read-only mode,
+-- kept here so that every server sees them.
+CREATE TABLE registry_flags (
+  name VARCHAR PRIMARY KEY,
+  enabled BOOLEAN NOT NULL,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+SELECT diesel_manage_updated_at('registry_flags');
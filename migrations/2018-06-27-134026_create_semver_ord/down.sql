@@ -0,0 +1 @@
+DROP FUNCTION semver_ord(VARCHAR);
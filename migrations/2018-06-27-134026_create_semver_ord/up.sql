@@ -0,0 +1,24 @@
+-- Returns an array of the parts of a version number that sorts them by semver
+-- precedence when compared with the "C" collation: the major, minor and patch
+-- numbers padded with zeroes, `1` for releases or `0` for pre-releases, then
+-- the identifiers of the pre-release, numeric ones padded and sorted before
+-- alphanumeric ones. Build metadata is ignored.
+CREATE FUNCTION semver_ord(num VARCHAR) RETURNS TEXT[] AS $$
+    SELECT ARRAY[
+        lpad(split_part(core, '.', 1), 20, '0'),
+        lpad(split_part(core, '.', 2), 20, '0'),
+        lpad(split_part(core, '.', 3), 20, '0'),
+        CASE WHEN pre IS NULL THEN '1' ELSE '0' END
+    ] || COALESCE((
+        SELECT array_agg(
+            CASE WHEN id ~ '^[0-9]+$' THEN '0' || lpad(id, 20, '0') ELSE '1' || id END
+            ORDER BY position
+        )
+        FROM unnest(string_to_array(pre, '.')) WITH ORDINALITY AS ids(id, position)
+    ), '{}')
+    FROM (
+        SELECT split_part(version, '-', 1) AS core,
+            substring(version from '^[^-]*-(.*)$') AS pre
+        FROM (SELECT split_part(num, '+', 1) AS version) stripped
+    ) parts
+$$ LANGUAGE SQL IMMUTABLE;
@@ -0,0 +1 @@
+DROP TABLE stale_crates;
@@ -0,0 +1,7 @@
+-- The crates with no release and no action of their owners for a long time,
+-- recomputed daily by `Crate::update_stale`
+CREATE TABLE stale_crates (
+  crate_id INTEGER NOT NULL PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+  -- The last release or action of the owners of the crate
+  last_activity_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE metadata DROP COLUMN dependents_recorded_up_to;
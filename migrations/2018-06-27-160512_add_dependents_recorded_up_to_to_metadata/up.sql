@@ -0,0 +1,4 @@
+-- The id of the last version whose dependencies were recorded in
+-- `crate_dependents`, so that `record-new-dependents` only looks at the
+-- versions published since
+ALTER TABLE metadata ADD COLUMN dependents_recorded_up_to INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,1317 @@
+//! A description of the API in the OpenAPI 3 format, served at
+//! `/api/openapi.json` so that client generators and the frontend can rely on it.
+//!
+//! Every route mounted under `/api/v1` is listed in `ROUTES` along with the
+//! shape of its response, and the encodable structs describe their fields
+//! through `ApiSchema`. The tests of the router check that every documented
+//! route is actually routed. The same routes are served under `/api/v2` with
+//! the responses encoded by `views::v2`, which this document doesn't describe.
+
+use serde_json::Value;
+
+use models::krate::{LetterBucket, RequirementDependents, VersionLineDownloads};
+use models::{Binary, DownloadBreakdown};
+use views::*;
+
+/// The type of a field, as described in the document.
+#[derive(Clone, Copy, Debug)]
+pub enum Type {
+    Str,
+    Int,
+    Num,
+    Bool,
+    DateTime,
+    /// An object with arbitrary keys.
+    Map,
+    /// One of the structs implementing `ApiSchema`, by name.
+    Ref(&'static str),
+    Array(&'static Type),
+    Nullable(&'static Type),
+}
+
+impl Type {
+    fn to_json(&self) -> Value {
+        match *self {
+            Type::Str => json!({ "type": "string" }),
+            Type::Int => json!({ "type": "integer" }),
+            Type::Num => json!({ "type": "number" }),
+            Type::Bool => json!({ "type": "boolean" }),
+            Type::DateTime => json!({ "type": "string", "format": "date-time" }),
+            Type::Map => json!({ "type": "object" }),
+            Type::Ref(name) => json!({ "$ref": format!("#/components/schemas/{}", name) }),
+            Type::Array(items) => json!({ "type": "array", "items": items.to_json() }),
+            Type::Nullable(ty) => {
+                let mut json = ty.to_json();
+                if let Type::Ref(_) = *ty {
+                    json = json!({ "allOf": [json] });
+                }
+                json["nullable"] = json!(true);
+                json
+            }
+        }
+    }
+
+    /// Returns the names of the schemas this type refers to.
+    pub fn refs(&self) -> Vec<&'static str> {
+        match *self {
+            Type::Ref(name) => vec![name],
+            Type::Array(ty) | Type::Nullable(ty) => ty.refs(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The fields of an object, by their name in the JSON payload.
+pub type Fields = &'static [(&'static str, Type)];
+
+fn object(fields: Fields) -> Value {
+    let properties = fields
+        .iter()
+        .map(|&(name, ty)| (name.to_string(), ty.to_json()))
+        .collect::<::serde_json::Map<_, _>>();
+    json!({ "type": "object", "properties": properties })
+}
+
+/// Implemented by the structs sent in API responses to describe their fields.
+pub trait ApiSchema {
+    const NAME: &'static str;
+    const FIELDS: Fields;
+}
+
+macro_rules! api_schema {
+    ($ty:ident { $($field:expr => $kind:expr),* $(,)* }) => {
+        impl ApiSchema for $ty {
+            const NAME: &'static str = stringify!($ty);
+            const FIELDS: Fields = &[$(($field, $kind)),*];
+        }
+    };
+}
+
+use self::Type::*;
+
+api_schema!(Binary {
+    "name" => Str,
+    "required_features" => Array(&Str),
+});
+
+api_schema!(EncodableAnnouncement {
+    "id" => Int,
+    "message" => Str,
+    "starts_at" => DateTime,
+    "ends_at" => Nullable(&DateTime),
+});
+
+api_schema!(EncodableAuditAction {
+    "action" => Str,
+    "crate" => Str,
+    "version" => Nullable(&Str),
+    "detail" => Nullable(&Str),
+    "created_at" => DateTime,
+});
+
+api_schema!(EncodableBadge {
+    "badge_type" => Str,
+    "attributes" => Map,
+});
+
+api_schema!(EncodableCategory {
+    "id" => Str,
+    "category" => Str,
+    "slug" => Str,
+    "description" => Str,
+    "created_at" => DateTime,
+    "crates_cnt" => Int,
+});
+
+api_schema!(EncodableCategoryWithSubcategories {
+    "id" => Str,
+    "category" => Str,
+    "slug" => Str,
+    "description" => Str,
+    "created_at" => DateTime,
+    "crates_cnt" => Int,
+    "subcategories" => Array(&Ref("EncodableCategory")),
+});
+
+api_schema!(EncodableCrate {
+    "id" => Str,
+    "name" => Str,
+    "updated_at" => DateTime,
+    "versions" => Nullable(&Array(&Int)),
+    "keywords" => Nullable(&Array(&Str)),
+    "categories" => Nullable(&Array(&Str)),
+    "badges" => Nullable(&Array(&Ref("EncodableBadge"))),
+    "created_at" => DateTime,
+    "downloads" => Int,
+    "recent_downloads" => Nullable(&Int),
+    "dependency_freshness" => Nullable(&Num),
+    "quality_score" => Nullable(&Num),
+    "max_version" => Str,
+    "default_version" => Str,
+    "all_versions_yanked" => Bool,
+    "description" => Nullable(&Str),
+    "homepage" => Nullable(&Str),
+    "documentation" => Nullable(&Str),
+    "repository" => Nullable(&Str),
+    "license" => Nullable(&Str),
+    "license_expression" => Nullable(&Map),
+    "deprecated" => Bool,
+    "deprecation_message" => Nullable(&Str),
+    "alternative" => Nullable(&Str),
+    "maintenance_status" => Nullable(&Str),
+    "logo_url" => Nullable(&Str),
+    "links" => Map,
+    "exact_match" => Bool,
+});
+
+api_schema!(EncodableCrateDownload {
+    "date" => Str,
+    "downloads" => Int,
+});
+
+api_schema!(EncodableCrateList {
+    "id" => Int,
+    "name" => Str,
+    "description" => Nullable(&Str),
+    "user" => Str,
+    "crates" => Array(&Str),
+    "created_at" => DateTime,
+    "updated_at" => DateTime,
+});
+
+api_schema!(EncodableCrateMetadataChange {
+    "version" => Nullable(&Str),
+    "field" => Str,
+    "added" => Array(&Str),
+    "removed" => Array(&Str),
+    "created_at" => DateTime,
+});
+
+api_schema!(EncodableCrateOwnerInvitation {
+    "invited_by_username" => Str,
+    "crate_name" => Str,
+    "crate_id" => Int,
+    "created_at" => DateTime,
+});
+
+api_schema!(EncodableCrateSpotlight {
+    "crate" => Ref("EncodableCrate"),
+    "featured_on" => Str,
+    "rationale" => Str,
+});
+
+api_schema!(EncodableCrateStatus {
+    "contested" => Bool,
+    "reason" => Nullable(&Str),
+    "transfer_at" => Nullable(&DateTime),
+});
+
+api_schema!(EncodableDeletedCrate {
+    "id" => Int,
+    "name" => Str,
+    "reason" => Nullable(&Str),
+    "deleted_by" => Nullable(&Str),
+    "deleted_at" => DateTime,
+    "versions" => Array(&Str),
+    "snapshot" => Map,
+});
+
+api_schema!(EncodableDependency {
+    "id" => Int,
+    "version_id" => Int,
+    "crate_id" => Str,
+    "req" => Str,
+    "optional" => Bool,
+    "default_features" => Bool,
+    "features" => Array(&Str),
+    "target" => Nullable(&Str),
+    "kind" => Str,
+    "downloads" => Int,
+});
+
+api_schema!(EncodableKeyword {
+    "id" => Str,
+    "keyword" => Str,
+    "created_at" => DateTime,
+    "crates_cnt" => Int,
+});
+
+api_schema!(EncodableTrendingKeyword {
+    "keyword" => Ref("EncodableKeyword"),
+    "previous_crates_cnt" => Int,
+});
+
+api_schema!(EncodableKeywordAlias {
+    "alias" => Str,
+    "keyword" => Str,
+    "created_at" => DateTime,
+});
+
+api_schema!(EncodableNewDependent {
+    "name" => Str,
+    "description" => Nullable(&Str),
+    "first_seen_at" => DateTime,
+});
+
+api_schema!(EncodableOwner {
+    "id" => Int,
+    "login" => Str,
+    "kind" => Str,
+    "url" => Nullable(&Str),
+    "name" => Nullable(&Str),
+    "avatar" => Nullable(&Str),
+});
+
+api_schema!(EncodableCrateOwner {
+    "id" => Int,
+    "login" => Str,
+    "kind" => Str,
+    "url" => Nullable(&Str),
+    "name" => Nullable(&Str),
+    "avatar" => Nullable(&Str),
+    "invited_by" => Nullable(&Str),
+    "created_at" => DateTime,
+    "pending" => Bool,
+});
+
+api_schema!(EncodableOwnerChange {
+    "login" => Str,
+    "status" => Str,
+    "msg" => Str,
+});
+
+api_schema!(EncodablePrivateUser {
+    "id" => Int,
+    "login" => Str,
+    "email" => Nullable(&Str),
+    "email_verified" => Bool,
+    "email_verification_sent" => Bool,
+    "confirm_new_crates" => Bool,
+    "name" => Nullable(&Str),
+    "avatar" => Nullable(&Str),
+    "url" => Nullable(&Str),
+});
+
+api_schema!(EncodablePublicUser {
+    "id" => Int,
+    "login" => Str,
+    "name" => Nullable(&Str),
+    "avatar" => Nullable(&Str),
+    "url" => Nullable(&Str),
+});
+
+api_schema!(EncodableTeam {
+    "id" => Int,
+    "login" => Str,
+    "kind" => Str,
+    "org" => Nullable(&Str),
+    "name" => Nullable(&Str),
+    "avatar" => Nullable(&Str),
+    "url" => Nullable(&Str),
+});
+
+api_schema!(EncodableVersion {
+    "id" => Int,
+    "crate" => Str,
+    "num" => Str,
+    "dl_path" => Str,
+    "readme_path" => Str,
+    "updated_at" => DateTime,
+    "created_at" => DateTime,
+    "downloads" => Int,
+    "features" => Map,
+    "yanked" => Bool,
+    "license" => Nullable(&Str),
+    "edition" => Nullable(&Str),
+    "binaries" => Array(&Ref("Binary")),
+    "published_by" => Nullable(&Str),
+    "published_with" => Nullable(&Str),
+    "links" => Map,
+});
+
+api_schema!(EncodableVersionDownload {
+    "id" => Int,
+    "version" => Int,
+    "downloads" => Int,
+    "yanked_downloads" => Int,
+    "unique_downloads" => Int,
+    "date" => Str,
+});
+
+api_schema!(EncodableVersionPublish {
+    "num" => Str,
+    "published_at" => DateTime,
+    "published_by" => Nullable(&Str),
+    "published_with" => Nullable(&Str),
+    "cargo_version" => Nullable(&Str),
+    "ci_provider" => Nullable(&Str),
+    "yanked" => Bool,
+});
+
+api_schema!(EncodableVersionFile {
+    "path" => Str,
+    "size" => Int,
+    "sha256" => Str,
+});
+
+api_schema!(EncodableFileChange {
+    "path" => Str,
+    "from_size" => Int,
+    "to_size" => Int,
+    "from_sha256" => Str,
+    "to_sha256" => Str,
+});
+
+api_schema!(EncodableVersionScanResult {
+    "scanner" => Str,
+    "blocked" => Bool,
+    "detail" => Nullable(&Str),
+    "scanned_at" => DateTime,
+});
+
+api_schema!(DownloadBreakdown {
+    "version" => Str,
+    "cargo_version" => Str,
+    "country" => Str,
+    "downloads" => Int,
+});
+
+api_schema!(LetterBucket {
+    "letter" => Str,
+    "crates" => Int,
+});
+
+api_schema!(RequirementDependents {
+    "range" => Str,
+    "dependents" => Int,
+});
+
+api_schema!(VersionLineDownloads {
+    "line" => Str,
+    "downloads" => Int,
+    "recent_downloads" => Int,
+});
+
+/// Returns the name and fields of every documented struct.
+pub fn schemas() -> Vec<(&'static str, Fields)> {
+    macro_rules! schemas {
+        ($($ty:ident),*) => { vec![$(($ty::NAME, $ty::FIELDS)),*] };
+    }
+    schemas!(
+        Binary,
+        DownloadBreakdown,
+        EncodableAnnouncement,
+        EncodableAuditAction,
+        EncodableBadge,
+        EncodableCategory,
+        EncodableCategoryWithSubcategories,
+        EncodableCrate,
+        EncodableCrateDownload,
+        EncodableCrateList,
+        EncodableCrateMetadataChange,
+        EncodableCrateOwner,
+        EncodableCrateOwnerInvitation,
+        EncodableCrateSpotlight,
+        EncodableCrateStatus,
+        EncodableDeletedCrate,
+        EncodableDependency,
+        EncodableFileChange,
+        EncodableKeyword,
+        EncodableKeywordAlias,
+        EncodableNewDependent,
+        EncodableOwner,
+        EncodableOwnerChange,
+        EncodablePrivateUser,
+        EncodablePublicUser,
+        EncodableTeam,
+        EncodableTrendingKeyword,
+        EncodableVersion,
+        EncodableVersionDownload,
+        EncodableVersionFile,
+        EncodableVersionPublish,
+        EncodableVersionScanResult,
+        LetterBucket,
+        RequirementDependents,
+        VersionLineDownloads
+    )
+}
+
+/// A route mounted under `/api/v1`.
+#[derive(Clone, Copy, Debug)]
+pub struct Route {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub summary: &'static str,
+    /// The fields of the JSON object sent in response.
+    pub response: Fields,
+}
+
+const OK: Fields = &[("ok", Bool)];
+const TOTAL: Type = Ref("Meta");
+
+macro_rules! route {
+    ($method:expr, $path:expr, $summary:expr) => {
+        route!($method, $path, $summary, OK)
+    };
+    ($method:expr, $path:expr, $summary:expr, $response:expr) => {
+        Route {
+            method: $method,
+            path: $path,
+            summary: $summary,
+            response: $response,
+        }
+    };
+}
+
+pub const ROUTES: &[Route] = &[
+    route!(
+        "get",
+        "/crates",
+        "Searches and lists crates",
+        &[("crates", Array(&Ref("EncodableCrate"))), ("meta", Ref("CrateListMeta"))]
+    ),
+    route!(
+        "get",
+        "/crates/suggest",
+        "Suggests the names of the crates starting with a prefix",
+        &[("crates", Array(&Str))]
+    ),
+    route!("put", "/crates/new", "Publishes a new crate or a new version of a crate", &[
+        ("crate", Ref("EncodableCrate")),
+        ("warnings", Map),
+    ]),
+    route!(
+        "get",
+        "/crates/:crate_id/owners",
+        "Lists the owners of a crate and the pending invitations",
+        &[
+            ("users", Array(&Ref("EncodableCrateOwner"))),
+            ("invitations", Array(&Ref("EncodableCrateOwner"))),
+            ("meta", TOTAL),
+        ]
+    ),
+    route!(
+        "put",
+        "/crates/:crate_id/owners",
+        "Invites owners to a crate",
+        &[("ok", Bool), ("msg", Str), ("results", Array(&Ref("EncodableOwnerChange")))]
+    ),
+    route!(
+        "delete",
+        "/crates/:crate_id/owners",
+        "Removes owners from a crate",
+        &[("ok", Bool), ("msg", Str), ("results", Array(&Ref("EncodableOwnerChange")))]
+    ),
+    route!("delete", "/crates/:crate_id/:version/yank", "Yanks a version"),
+    route!("put", "/crates/:crate_id/:version/unyank", "Unyanks a version"),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/download",
+        "Redirects to the archive of a version",
+        &[("url", Str)]
+    ),
+    route!(
+        "head",
+        "/crates/:crate_id/:version/download",
+        "Checks the archive of a version without counting a download",
+        &[]
+    ),
+    route!(
+        "get",
+        "/versions",
+        "Lists versions by id",
+        &[("versions", Array(&Ref("EncodableVersion")))]
+    ),
+    route!(
+        "get",
+        "/versions/:version_id",
+        "Shows a version by id",
+        &[("version", Ref("EncodableVersion"))]
+    ),
+    route!("get", "/crates/:crate_id", "Shows a crate", &[
+        ("crate", Ref("EncodableCrate")),
+        ("versions", Array(&Ref("EncodableVersion"))),
+        ("keywords", Array(&Ref("EncodableKeyword"))),
+        ("categories", Array(&Ref("EncodableCategory"))),
+        ("moved_to", Nullable(&Str)),
+        ("canonical_name", Str),
+        ("status", Ref("EncodableCrateStatus")),
+        ("stale", Bool),
+    ]),
+    route!(
+        "get",
+        "/crates/:crate_id/:version",
+        "Shows a version of a crate",
+        &[("version", Ref("EncodableVersion"))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/readme",
+        "Redirects to the rendered README of a version",
+        &[("url", Str)]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/readme_raw",
+        "The README of a version as published, as text"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/readme_rendered",
+        "The README of a version rendered to HTML"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/manifest",
+        "The `Cargo.toml` found in the crate file of a version, as text"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/signature",
+        "The detached signature of the crate file of a version, as text"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/files",
+        "Lists the files of the crate file of a version, with their size and checksum",
+        &[("files", Array(&Ref("EncodableVersionFile")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/dependencies",
+        "Lists the dependencies of a version",
+        &[("dependencies", Array(&Ref("EncodableDependency")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/downloads",
+        "Lists the daily downloads of a version",
+        &[("version_downloads", Array(&Ref("EncodableVersionDownload")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/authors",
+        "Lists the authors of a version",
+        &[("users", Array(&Ref("EncodablePublicUser"))), ("meta", Map)]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/:version/scan_results",
+        "Lists the scanner verdicts of a version, for administrators",
+        &[("scan_results", Array(&Ref("EncodableVersionScanResult")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/diff",
+        "Lists the files added, removed and changed between two versions of a crate",
+        &[
+            ("from", Str),
+            ("to", Str),
+            ("added", Array(&Ref("EncodableVersionFile"))),
+            ("removed", Array(&Ref("EncodableVersionFile"))),
+            ("changed", Array(&Ref("EncodableFileChange"))),
+        ]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/downloads",
+        "Lists the daily downloads of a crate",
+        &[("version_downloads", Array(&Ref("EncodableVersionDownload"))), ("meta", Map)]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/downloads.csv",
+        "Exports the daily downloads of a crate as CSV",
+        &[]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/downloads/total",
+        "Shows the daily downloads of a crate over the last 90 days, and its total downloads",
+        &[("crate_downloads", Array(&Ref("EncodableCrateDownload"))), ("meta", TOTAL)]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/downloads/by_semver",
+        "Sums the downloads of a crate per major or major.minor version line",
+        &[("lines", Array(&Ref("VersionLineDownloads")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/downloads/breakdown",
+        "Sums the recent downloads of each version of a crate per cargo version and country",
+        &[("breakdown", Array(&Ref("DownloadBreakdown")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/versions",
+        "Lists the versions of a crate",
+        &[("versions", Array(&Ref("EncodableVersion"))), ("meta", TOTAL)]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/versions/match",
+        "Resolves a semver requirement against the versions of a crate",
+        &[
+            ("best_match", Nullable(&Ref("EncodableVersion"))),
+            ("matches", Array(&Ref("EncodableVersion"))),
+        ]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/new_dependents",
+        "Lists the crates that started depending on a crate in the last 30 days",
+        &[("dependents", Array(&Ref("EncodableNewDependent")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/new_dependents.rss",
+        "The crates that started depending on a crate in the last 30 days, as an RSS feed"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/related",
+        "Lists the crates most similar to a crate",
+        &[("crates", Array(&Ref("EncodableCrate")))]
+    ),
+    route!("put", "/crates/:crate_id/follow", "Follows a crate"),
+    route!("delete", "/crates/:crate_id/follow", "Unfollows a crate"),
+    route!(
+        "get",
+        "/crates/:crate_id/following",
+        "Tells whether the crate is followed",
+        &[("following", Bool)]
+    ),
+    route!(
+        "put",
+        "/crates/:crate_id/keywords",
+        "Replaces the keywords of a crate",
+        &[("keywords", Array(&Ref("EncodableKeyword")))]
+    ),
+    route!(
+        "put",
+        "/crates/:crate_id/deprecate",
+        "Deprecates a crate, with a `message` and an optional `alternative` crate"
+    ),
+    route!("delete", "/crates/:crate_id/deprecate", "Undeprecates a crate"),
+    route!(
+        "put",
+        "/crates/:crate_id/maintenance",
+        "Sets the maintenance `status` of a crate, or clears it when null"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/logo",
+        "Redirects to the logo of a crate"
+    ),
+    route!(
+        "put",
+        "/crates/:crate_id/logo",
+        "Uploads the logo of a crate, a PNG image of at most 64KiB"
+    ),
+    route!("delete", "/crates/:crate_id/logo", "Removes the logo of a crate"),
+    route!(
+        "get",
+        "/crates/:crate_id/trusted_publishers",
+        "Lists the workflows allowed to publish a crate",
+        &[("trusted_publishers", Array(&Map))]
+    ),
+    route!(
+        "put",
+        "/crates/:crate_id/trusted_publishers",
+        "Allows a GitHub Actions workflow to publish a crate",
+        &[("trusted_publisher", Map)]
+    ),
+    route!(
+        "delete",
+        "/crates/:crate_id/trusted_publishers/:id",
+        "Removes a trusted publisher of a crate"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/audit",
+        "Lists who published the versions of a crate and from where, to its owners",
+        &[("versions", Array(&Ref("EncodableVersionPublish")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/metadata_changes",
+        "Lists the changes of the keywords, categories and metadata of a crate, to its owners",
+        &[("changes", Array(&Ref("EncodableCrateMetadataChange")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/webhooks",
+        "Lists the webhooks notified of a crate's publishes and yanks",
+        &[("webhooks", Array(&Map))]
+    ),
+    route!(
+        "put",
+        "/crates/:crate_id/webhooks",
+        "Registers a webhook, returning the secret its payloads are signed with",
+        &[("webhook", Map), ("secret", Str)]
+    ),
+    route!(
+        "delete",
+        "/crates/:crate_id/webhooks/:id",
+        "Removes a webhook of a crate"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/lists",
+        "Lists the crate lists a crate was added to",
+        &[("lists", Array(&Ref("EncodableCrateList"))), ("meta", Map)]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/owner_team",
+        "Lists the teams owning a crate",
+        &[("teams", Array(&Ref("EncodableOwner")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/owner_user",
+        "Lists the users owning a crate",
+        &[("users", Array(&Ref("EncodableOwner")))]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/index",
+        "Renders the git index entries of a crate from the database"
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/reverse_dependencies",
+        "Lists the reverse dependencies of a crate",
+        &[
+            ("dependencies", Array(&Ref("EncodableDependency"))),
+            ("versions", Array(&Ref("EncodableVersion"))),
+            ("meta", TOTAL),
+        ]
+    ),
+    route!(
+        "get",
+        "/crates/:crate_id/dependents/version_requirements",
+        "Counts the dependents of a crate per range of versions they require",
+        &[("requirements", Array(&Ref("RequirementDependents")))]
+    ),
+    route!(
+        "get",
+        "/keywords",
+        "Lists keywords",
+        &[("keywords", Array(&Ref("EncodableKeyword"))), ("meta", TOTAL)]
+    ),
+    route!(
+        "get",
+        "/keywords/trending",
+        "Lists the keywords whose number of crates grew the most in the last 30 days",
+        &[("keywords", Array(&Ref("EncodableTrendingKeyword")))]
+    ),
+    route!(
+        "get",
+        "/keywords/:keyword_id",
+        "Shows a keyword",
+        &[
+            ("keyword", Ref("EncodableKeyword")),
+            ("recently_updated", Array(&Ref("EncodableCrate"))),
+        ]
+    ),
+    route!(
+        "get",
+        "/categories",
+        "Lists the top-level categories",
+        &[("categories", Array(&Ref("EncodableCategory"))), ("meta", TOTAL)]
+    ),
+    route!(
+        "get",
+        "/categories/:category_id",
+        "Shows a category",
+        &[
+            ("category", Ref("EncodableCategoryWithSubcategories")),
+            ("featured", Array(&Ref("EncodableCrate"))),
+            ("recently_updated", Array(&Ref("EncodableCrate"))),
+            ("meta", Ref("CategoryMeta")),
+        ]
+    ),
+    route!("get", "/categories/:category_id/crates", "Lists the crates of a category", &[
+        ("category", Ref("EncodableCategory")),
+        ("crates", Array(&Ref("EncodableCrate"))),
+        ("meta", Ref("CategoryMeta")),
+    ]),
+    route!(
+        "get",
+        "/category_slugs",
+        "Lists the slugs of every category",
+        &[("category_slugs", Array(&Map))]
+    ),
+    route!(
+        "get",
+        "/users/:user_id",
+        "Shows a user",
+        &[("user", Ref("EncodablePublicUser"))]
+    ),
+    route!("put", "/users/:user_id", "Updates the email of the current user"),
+    route!(
+        "get",
+        "/users/:user_id/stats",
+        "Shows the download statistics of a user",
+        &[("total_downloads", Int)]
+    ),
+    route!(
+        "get",
+        "/users/:user_id/lists",
+        "Lists the crate lists curated by a user",
+        &[("lists", Array(&Ref("EncodableCrateList"))), ("meta", Map)]
+    ),
+    route!(
+        "get",
+        "/teams/:team_id",
+        "Shows a team",
+        &[("team", Ref("EncodableTeam"))]
+    ),
+    route!(
+        "get",
+        "/teams/:team_id/crates",
+        "Lists the crates owned by a team",
+        &[("crates", Array(&Ref("EncodableCrate"))), ("meta", TOTAL)]
+    ),
+    route!(
+        "get",
+        "/teams/:team_id/tokens",
+        "Lists the API tokens of a team",
+        &[("team_tokens", Array(&Map))]
+    ),
+    route!(
+        "put",
+        "/teams/:team_id/tokens",
+        "Creates an API token publishing the crates of a team",
+        &[("team_token", Map)]
+    ),
+    route!(
+        "delete",
+        "/teams/:team_id/tokens/:id",
+        "Revokes an API token of a team"
+    ),
+    route!("get", "/me", "Shows the current user and their account", &[
+        ("user", Ref("EncodablePrivateUser")),
+        ("owned_crates", Int),
+        ("pending_invitations", Int),
+        ("api_tokens", Int),
+    ]),
+    route!(
+        "delete",
+        "/me",
+        "Deletes the account of the current user, orphaning their crates"
+    ),
+    route!(
+        "get",
+        "/me/updates",
+        "Lists the latest versions of the crates followed by the current user",
+        &[("versions", Array(&Ref("EncodableVersion"))), ("meta", Map)]
+    ),
+    route!(
+        "get",
+        "/me/activity",
+        "Lists the publishes, yanks and owner changes made by the current user",
+        &[("activity", Array(&Ref("EncodableAuditAction"))), ("meta", TOTAL)]
+    ),
+    route!(
+        "get",
+        "/me/crates",
+        "Lists the crates of the current user with their recent downloads",
+        &[
+            ("crates", Array(&Map)),
+            (
+                "pending_invitations",
+                Array(&Ref("EncodableCrateOwnerInvitation"))
+            ),
+        ]
+    ),
+    route!("put", "/me/email", "Updates the email of the current user"),
+    route!("put", "/me/email/resend", "Resends the email verification link"),
+    route!("put", "/me/settings", "Updates the settings of the current user"),
+    route!(
+        "post",
+        "/me/2fa",
+        "Starts enrolling the current user in two-factor authentication",
+        &[("secret", Str), ("otpauth_url", Str)]
+    ),
+    route!(
+        "put",
+        "/me/2fa",
+        "Enables two-factor authentication with a first code",
+        &[("recovery_codes", Array(&Str))]
+    ),
+    route!("delete", "/me/2fa", "Disables two-factor authentication"),
+    route!(
+        "put",
+        "/me/2fa/verify",
+        "Verifies the session of the current user with a two-factor code"
+    ),
+    route!(
+        "get",
+        "/me/tokens",
+        "Lists the API tokens of the current user",
+        &[("api_tokens", Array(&Map))]
+    ),
+    route!(
+        "put",
+        "/me/tokens",
+        "Creates an API token",
+        &[("api_token", Map)]
+    ),
+    route!(
+        "put",
+        "/me/tokens/:id",
+        "Restricts the IP addresses an API token can be used from",
+        &[("api_token", Map)]
+    ),
+    route!("delete", "/me/tokens/:id", "Revokes an API token", &[]),
+    route!(
+        "put",
+        "/me/lists",
+        "Creates a crate list curated by the current user",
+        &[("list", Ref("EncodableCrateList"))]
+    ),
+    route!(
+        "get",
+        "/lists/:list_id",
+        "Shows a crate list",
+        &[("list", Ref("EncodableCrateList"))]
+    ),
+    route!(
+        "put",
+        "/lists/:list_id",
+        "Renames a crate list or updates its description",
+        &[("list", Ref("EncodableCrateList"))]
+    ),
+    route!("delete", "/lists/:list_id", "Deletes a crate list"),
+    route!(
+        "put",
+        "/lists/:list_id/crates/:crate_id",
+        "Adds a crate to a crate list"
+    ),
+    route!(
+        "delete",
+        "/lists/:list_id/crates/:crate_id",
+        "Removes a crate from a crate list"
+    ),
+    route!(
+        "get",
+        "/me/crate_owner_invitations",
+        "Lists the pending ownership invitations of the current user",
+        &[("crate_owner_invitations", Array(&Ref("EncodableCrateOwnerInvitation")))]
+    ),
+    route!(
+        "put",
+        "/me/crate_owner_invitations/:crate_id",
+        "Accepts or declines an ownership invitation",
+        &[("crate_owner_invitation", Map)]
+    ),
+    route!("get", "/summary", "Shows the summary of the front page", &[
+        ("num_downloads", Int),
+        ("num_crates", Int),
+        ("new_crates", Array(&Ref("EncodableCrate"))),
+        ("most_downloaded", Array(&Ref("EncodableCrate"))),
+        ("most_recently_downloaded", Array(&Ref("EncodableCrate"))),
+        ("just_updated", Array(&Ref("EncodableCrate"))),
+        ("popular_keywords", Array(&Ref("EncodableKeyword"))),
+        ("popular_categories", Array(&Ref("EncodableCategory"))),
+        ("spotlight", Nullable(&Ref("EncodableCrateSpotlight"))),
+    ]),
+    route!(
+        "get",
+        "/spotlight",
+        "Shows the crate featured today along with why it was chosen",
+        &[("spotlight", Nullable(&Ref("EncodableCrateSpotlight")))]
+    ),
+    route!(
+        "get",
+        "/graphql",
+        "Runs a GraphQL query sent in the URL",
+        &[("data", Map), ("errors", Array(&Map))]
+    ),
+    route!(
+        "post",
+        "/graphql",
+        "Runs a GraphQL query",
+        &[("data", Map), ("errors", Array(&Map))]
+    ),
+    route!("put", "/confirm/:email_token", "Confirms an email address"),
+    route!(
+        "put",
+        "/confirm_publish/:token",
+        "Confirms the publication of a new crate name"
+    ),
+    route!(
+        "get",
+        "/confirm_publish/:token",
+        "Confirms the publication of a new crate name from the emailed link"
+    ),
+    route!(
+        "put",
+        "/admin/crates/:crate_id/approve",
+        "Approves a crate held for review",
+        &[("ok", Bool), ("released", Int)]
+    ),
+    route!(
+        "put",
+        "/admin/crates/:crate_id/rename",
+        "Renames a crate, keeping its former name resolving to it",
+        &[("ok", Bool), ("name", Str)]
+    ),
+    route!(
+        "put",
+        "/admin/crates/:crate_id/lock",
+        "Locks a crate, rejecting publishes of new versions"
+    ),
+    route!(
+        "delete",
+        "/admin/crates/:crate_id/lock",
+        "Unlocks a locked crate"
+    ),
+    route!(
+        "put",
+        "/admin/crates/:crate_id/transfer",
+        "Marks the name of a crate as contested and schedules its transfer",
+        &[("status", Ref("EncodableCrateStatus"))]
+    ),
+    route!(
+        "delete",
+        "/admin/crates/:crate_id/transfer",
+        "Cancels the scheduled transfer of a contested crate"
+    ),
+    route!(
+        "post",
+        "/admin/crates/:crate_id/sync_index",
+        "Rewrites the index entries of a crate from the database",
+        &[("ok", Bool), ("changed", Bool), ("versions", Int)]
+    ),
+    route!(
+        "put",
+        "/admin/crates/:crate_id/spotlight",
+        "Features a crate on the front page on a given day",
+        &[("spotlight", Ref("EncodableCrateSpotlight"))]
+    ),
+    route!(
+        "put",
+        "/admin/categories/:category_id/featured/:crate_id",
+        "Features a crate at the top of a category",
+        &[("featured", Array(&Ref("EncodableCrate")))]
+    ),
+    route!(
+        "delete",
+        "/admin/categories/:category_id/featured/:crate_id",
+        "Stops featuring a crate at the top of a category",
+        &[("featured", Array(&Ref("EncodableCrate")))]
+    ),
+    route!(
+        "delete",
+        "/admin/crates/:crate_id",
+        "Deletes a crate, archiving it and reserving its name",
+        &[("ok", Bool), ("id", Int)]
+    ),
+    route!(
+        "get",
+        "/admin/deleted_crates",
+        "Lists the deleted crates along with the rows archived with them",
+        &[("deleted_crates", Array(&Ref("EncodableDeletedCrate"))), ("meta", TOTAL)]
+    ),
+    route!(
+        "put",
+        "/admin/deleted_crates/:id/restore",
+        "Restores a deleted crate",
+        &[("ok", Bool), ("name", Str)]
+    ),
+    route!(
+        "get",
+        "/admin/keyword_aliases",
+        "Lists the keyword aliases, e.g. `wasm` for `webassembly`",
+        &[("keyword_aliases", Array(&Ref("EncodableKeywordAlias")))]
+    ),
+    route!(
+        "put",
+        "/admin/keyword_aliases",
+        "Makes an `alias` stand for a `keyword`, moving the crates that have the alias",
+        &[("keyword_alias", Ref("EncodableKeywordAlias"))]
+    ),
+    route!(
+        "delete",
+        "/admin/keyword_aliases/:alias",
+        "Removes a keyword alias"
+    ),
+    route!(
+        "post",
+        "/admin/jobs/recount",
+        "Recomputes the crate counts of keywords and categories",
+        &[("ok", Bool), ("corrected", Map)]
+    ),
+    route!(
+        "post",
+        "/admin/downloads/ingest",
+        "Adds a batch of downloads served by the CDN to the downloads of versions",
+        &[("ok", Bool), ("ingested", Bool), ("unknown", Array(&Str))]
+    ),
+    route!(
+        "get",
+        "/admin/read_only",
+        "Shows whether the registry is in read-only mode",
+        &[("read_only", Bool)]
+    ),
+    route!(
+        "put",
+        "/admin/read_only",
+        "Refuses publishes, yanks, owner changes and follows until turned off",
+        &[("read_only", Bool)]
+    ),
+    route!(
+        "delete",
+        "/admin/read_only",
+        "Turns the read-only mode off",
+        &[("read_only", Bool)]
+    ),
+    route!(
+        "put",
+        "/trusted_publishing/tokens",
+        "Exchanges a GitHub Actions OIDC token for a short-lived publish token",
+        &[("token", Str), ("expires_at", DateTime)]
+    ),
+    route!(
+        "put",
+        "/users/:user_id/resend",
+        "Resends the email verification link"
+    ),
+    route!(
+        "get",
+        "/site_metadata",
+        "Shows the deployed version of the registry",
+        &[("deployed_sha", Str)]
+    ),
+    route!(
+        "get",
+        "/announcements",
+        "Lists the active announcements",
+        &[("announcements", Array(&Ref("EncodableAnnouncement")))]
+    ),
+    route!(
+        "get",
+        "/updates/stream",
+        "Streams the publishes, yanks and owner changes of crates as server-sent events"
+    ),
+];
+
+/// Converts a route path such as `/crates/:crate_id` to its OpenAPI form,
+/// `/crates/{crate_id}`, returning the names of its parameters as well.
+fn path_template(path: &str) -> (String, Vec<&str>) {
+    let mut params = Vec::new();
+    let template = path.split('/')
+        .map(|segment| {
+            if segment.starts_with(':') {
+                params.push(&segment[1..]);
+                format!("{{{}}}", &segment[1..])
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    (template, params)
+}
+
+/// Builds the OpenAPI document describing the API.
+pub fn document() -> Value {
+    let mut paths = json!({});
+    for route in ROUTES {
+        let (template, params) = path_template(route.path);
+        let parameters = params
+            .iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect::<Vec<_>>();
+        paths[&template][route.method] = json!({
+            "summary": route.summary,
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "OK",
+                    "content": { "application/json": { "schema": object(route.response) } },
+                },
+            },
+        });
+    }
+
+    let mut components = json!({
+        "Meta": { "type": "object", "properties": { "total": { "type": "integer" } } },
+        // Only listings by letter send the `letters` buckets, and searches by
+        // category the `canonical_category`
+        "CrateListMeta": object(&[
+            ("total", Int),
+            ("letters", Array(&Ref("LetterBucket"))),
+            ("canonical_category", Str),
+        ]),
+        // Only the crates of a category send the `total`
+        "CategoryMeta": object(&[("total", Int), ("canonical_slug", Str)]),
+    });
+    for (name, fields) in schemas() {
+        components[name] = object(fields);
+    }
+
+    json!({
+        "openapi": "3.0.0",
+        "info": { "title": "crates.io", "version": "1" },
+        "servers": [{ "url": "/api/v1" }],
+        "paths": paths,
+        "components": { "schemas": components },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{document, path_template, schemas, ROUTES};
+
+    #[test]
+    fn path_templates() {
+        assert_eq!(
+            path_template("/crates/:crate_id/:version/download"),
+            (
+                "/crates/{crate_id}/{version}/download".to_string(),
+                vec!["crate_id", "version"]
+            )
+        );
+        assert_eq!(path_template("/summary"), ("/summary".to_string(), vec![]));
+    }
+
+    #[test]
+    fn every_referenced_schema_is_documented() {
+        let mut names = schemas().into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+        names.push("Meta");
+        names.push("CrateListMeta");
+        names.push("CategoryMeta");
+
+        let fields = ROUTES
+            .iter()
+            .flat_map(|r| r.response.iter())
+            .chain(schemas().into_iter().flat_map(|(_, f)| f.iter()));
+        for &(_, ty) in fields {
+            for name in ty.refs() {
+                assert!(names.contains(&name), "`{}` is not documented", name);
+            }
+        }
+    }
+
+    #[test]
+    fn document_lists_every_route() {
+        let doc = document();
+        assert_eq!(doc["openapi"], "3.0.0");
+        assert!(doc["paths"]["/crates/{crate_id}"]["get"].is_object());
+        assert!(doc["paths"]["/crates/{crate_id}/owners"]["put"].is_object());
+        assert!(doc["paths"]["/crates/{crate_id}/owners"]["delete"].is_object());
+        assert!(doc["components"]["schemas"]["EncodableCrate"].is_object());
+    }
+}
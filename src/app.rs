@@ -3,15 +3,22 @@
 use std::env;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use curl::easy::Easy;
+use diesel::prelude::*;
 use diesel::r2d2;
 use git2;
 use oauth2;
 use scheduled_thread_pool::ScheduledThreadPool;
+use serde_json;
 
-use {db, Config};
+use download_dedup::DownloadFilter;
+use download_routing::DownloadRoutingMetrics;
+use metrics::Metrics;
+use models::registry_flag::{RegistryFlag, READ_ONLY};
+use util::CargoResult;
+use {db, updates, Config};
 
 /// The `App` struct holds the main components of the application like
 /// the database connection pool and configurations
@@ -35,6 +42,21 @@ pub struct App {
 
     /// The server configuration
     pub config: Config,
+
+    /// The last computed `/summary` payload, along with when it was computed
+    pub summary_cache: Mutex<Option<(Instant, serde_json::Value)>>,
+
+    /// How many download redirects were sent to each storage backend
+    pub download_routing_metrics: DownloadRoutingMetrics,
+
+    /// The request, publish and download counters exposed at `/metrics`
+    pub metrics: Metrics,
+
+    /// The downloads made from each IP address today, to count unique downloads
+    pub download_filter: DownloadFilter,
+
+    /// The clients of `GET /updates/stream` connected to this server
+    pub update_subscribers: updates::Subscribers,
 }
 
 impl App {
@@ -96,9 +118,27 @@ impl App {
             git_repo: Mutex::new(repo),
             git_repo_checkout: config.git_repo_checkout.clone(),
             config: config.clone(),
+            summary_cache: Mutex::new(None),
+            download_routing_metrics: DownloadRoutingMetrics::default(),
+            metrics: Metrics::default(),
+            download_filter: DownloadFilter::default(),
+            update_subscribers: updates::Subscribers::new(config.max_update_subscribers),
         }
     }
 
+    /// Returns whether publishes, yanks, owner changes and follows are
+    /// refused, while operators migrate the database. The administrators
+    /// toggle it at runtime for every server, unless `Config::read_only` keeps
+    /// this one read-only.
+    pub fn read_only(&self, conn: &PgConnection) -> CargoResult<bool> {
+        Ok(self.config.read_only || RegistryFlag::is_enabled(conn, READ_ONLY)?)
+    }
+
+    /// Drops the cached `/summary` payload so that the next request recomputes it.
+    pub fn invalidate_summary_cache(&self) {
+        *self.summary_cache.lock().unwrap() = None;
+    }
+
     /// Returns a handle for making HTTP requests to upload crate files.
     ///
     /// The handle will go through a proxy if the uploader being used has specified one, which
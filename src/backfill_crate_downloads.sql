@@ -0,0 +1,10 @@
+-- Recomputes the daily downloads of the crates with an id in ($1, $2]. Only
+-- the counted downloads are summed, `update-downloads` adding the others to
+-- `crate_downloads` as it counts them.
+INSERT INTO crate_downloads (crate_id, date, downloads)
+SELECT versions.crate_id, version_downloads.date, SUM(version_downloads.counted)
+FROM version_downloads
+INNER JOIN versions ON versions.id = version_downloads.version_id
+WHERE versions.crate_id > $1 AND versions.crate_id <= $2
+GROUP BY versions.crate_id, version_downloads.date
+ON CONFLICT (crate_id, date) DO UPDATE SET downloads = EXCLUDED.downloads
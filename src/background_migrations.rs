@@ -0,0 +1,240 @@
+//! A framework for long-running data migrations that run while the registry is
+//! online.
+//!
+//! A background migration processes the rows of a table in chunks ordered by
+//! id. Each chunk runs in its own transaction that also records the last
+//! processed id in the `background_migrations` table, so an interrupted
+//! migration resumes where it stopped instead of starting over.
+
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use models::Crate;
+use schema::{background_migrations, crates, users};
+use util::{CargoError, CargoResult};
+
+/// A data migration that can be run in chunks.
+pub trait BackgroundMigration {
+    /// The unique name the progress of the migration is recorded under.
+    fn name(&self) -> &'static str;
+
+    /// The number of rows the migration has to process, used to report progress.
+    fn total(&self, conn: &PgConnection) -> CargoResult<i64>;
+
+    /// Processes at most `limit` rows whose id is greater than `after`, in
+    /// ascending id order, and returns the ids of the processed rows. Returning
+    /// no ids marks the migration as finished.
+    fn run_batch(&self, conn: &PgConnection, after: i32, limit: i64) -> CargoResult<Vec<i32>>;
+}
+
+/// The recorded progress of a background migration.
+#[derive(Debug, Clone, Queryable)]
+pub struct Progress {
+    pub name: String,
+    pub last_id: i32,
+    pub processed: i64,
+    pub total: i64,
+    pub started_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+impl Progress {
+    pub fn all(conn: &PgConnection) -> CargoResult<Vec<Progress>> {
+        Ok(background_migrations::table
+            .order(background_migrations::started_at)
+            .load(conn)?)
+    }
+}
+
+/// Returns every background migration that can be run.
+pub fn all() -> Vec<Box<BackgroundMigration>> {
+    vec![
+        Box::new(RebuildCrateSearchIndex),
+        Box::new(BackfillCrateDownloads),
+        Box::new(BackfillCrateMaxVersions),
+        Box::new(MarkDeletedUsers),
+    ]
+}
+
+/// Runs the given migration to completion, `batch_size` rows at a time,
+/// resuming from its recorded progress if it was started before.
+pub fn run(
+    conn: &PgConnection,
+    migration: &BackgroundMigration,
+    batch_size: i64,
+) -> CargoResult<Progress> {
+    use self::background_migrations::dsl::*;
+
+    let total_rows = migration.total(conn)?;
+    diesel::insert_into(background_migrations)
+        .values((name.eq(migration.name()), total.eq(total_rows)))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    loop {
+        let progress = conn.transaction::<_, Box<CargoError>, _>(|| {
+            let target = background_migrations.find(migration.name());
+            let progress = target.first::<Progress>(conn)?;
+            if progress.finished_at.is_some() {
+                return Ok(progress);
+            }
+
+            let ids = migration.run_batch(conn, progress.last_id, batch_size)?;
+            let progress = match ids.last() {
+                Some(&id) => diesel::update(target)
+                    .set((
+                        last_id.eq(id),
+                        processed.eq(processed + ids.len() as i64),
+                    ))
+                    .get_result(conn)?,
+                None => diesel::update(target)
+                    .set(finished_at.eq(now.nullable()))
+                    .get_result(conn)?,
+            };
+            Ok(progress)
+        })?;
+
+        if progress.finished_at.is_some() {
+            return Ok(progress);
+        }
+        println!(
+            "{}: processed {} of {} rows",
+            progress.name, progress.processed, progress.total
+        );
+    }
+}
+
+/// Recomputes the full text search index of every crate, e.g. after the
+/// trigger maintaining it changed.
+#[derive(Debug, Clone, Copy)]
+pub struct RebuildCrateSearchIndex;
+
+impl BackgroundMigration for RebuildCrateSearchIndex {
+    fn name(&self) -> &'static str {
+        "rebuild_crate_search_index"
+    }
+
+    fn total(&self, conn: &PgConnection) -> CargoResult<i64> {
+        Ok(crates::table.count().get_result(conn)?)
+    }
+
+    fn run_batch(&self, conn: &PgConnection, after: i32, limit: i64) -> CargoResult<Vec<i32>> {
+        let ids = crates::table
+            .select(crates::id)
+            .filter(crates::id.gt(after))
+            .order(crates::id)
+            .limit(limit)
+            .load::<i32>(conn)?;
+
+        // The search index is updated by a trigger on the updates of
+        // `updated_at`, which fires even though its value doesn't change
+        if let Some(&last) = ids.last() {
+            diesel::update(crates::table.filter(crates::id.gt(after).and(crates::id.le(last))))
+                .set(crates::updated_at.eq(crates::updated_at))
+                .execute(conn)?;
+        }
+        Ok(ids)
+    }
+}
+
+/// Recomputes the daily download totals of every crate from the counted
+/// downloads of its versions, for the days before `update-downloads` started
+/// maintaining them.
+#[derive(Debug, Clone, Copy)]
+pub struct BackfillCrateDownloads;
+
+impl BackgroundMigration for BackfillCrateDownloads {
+    fn name(&self) -> &'static str {
+        "backfill_crate_downloads"
+    }
+
+    fn total(&self, conn: &PgConnection) -> CargoResult<i64> {
+        Ok(crates::table.count().get_result(conn)?)
+    }
+
+    fn run_batch(&self, conn: &PgConnection, after: i32, limit: i64) -> CargoResult<Vec<i32>> {
+        use diesel::sql_types::Integer;
+
+        let ids = crates::table
+            .select(crates::id)
+            .filter(crates::id.gt(after))
+            .order(crates::id)
+            .limit(limit)
+            .load::<i32>(conn)?;
+
+        if let Some(&last) = ids.last() {
+            diesel::sql_query(include_str!("backfill_crate_downloads.sql"))
+                .bind::<Integer, _>(after)
+                .bind::<Integer, _>(last)
+                .execute(conn)?;
+        }
+        Ok(ids)
+    }
+}
+
+/// Fills in the cached max versions of the crates published before they were
+/// maintained, see `Crate::update_max_versions`.
+#[derive(Debug, Clone, Copy)]
+pub struct BackfillCrateMaxVersions;
+
+impl BackgroundMigration for BackfillCrateMaxVersions {
+    fn name(&self) -> &'static str {
+        "backfill_crate_max_versions"
+    }
+
+    fn total(&self, conn: &PgConnection) -> CargoResult<i64> {
+        Ok(crates::table.count().get_result(conn)?)
+    }
+
+    fn run_batch(&self, conn: &PgConnection, after: i32, limit: i64) -> CargoResult<Vec<i32>> {
+        let krates = Crate::all()
+            .filter(crates::id.gt(after))
+            .order(crates::id)
+            .limit(limit)
+            .load::<Crate>(conn)?;
+        for krate in &krates {
+            krate.update_max_versions(conn)?;
+        }
+        Ok(krates.iter().map(|krate| krate.id).collect())
+    }
+}
+
+/// Marks the accounts deleted before `users.deleted_at` was added as deleted,
+/// like `User::delete` does now. They were only told apart by their GitHub id
+/// of -1 and their `deleted-user-<id>` login.
+#[derive(Debug, Clone, Copy)]
+pub struct MarkDeletedUsers;
+
+impl BackgroundMigration for MarkDeletedUsers {
+    fn name(&self) -> &'static str {
+        "mark_deleted_users"
+    }
+
+    fn total(&self, conn: &PgConnection) -> CargoResult<i64> {
+        Ok(users::table.count().get_result(conn)?)
+    }
+
+    fn run_batch(&self, conn: &PgConnection, after: i32, limit: i64) -> CargoResult<Vec<i32>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::Bool;
+
+        let ids = users::table
+            .select(users::id)
+            .filter(users::id.gt(after))
+            .order(users::id)
+            .limit(limit)
+            .load::<i32>(conn)?;
+
+        if let Some(&last) = ids.last() {
+            diesel::update(users::table.filter(users::id.gt(after).and(users::id.le(last))))
+                .filter(users::gh_id.eq(-1))
+                .filter(sql::<Bool>("gh_login = 'deleted-user-' || id"))
+                .set((users::deleted_at.eq(now.nullable()), users::gh_id.eq(0)))
+                .execute(conn)?;
+        }
+        Ok(ids)
+    }
+}
@@ -0,0 +1,42 @@
+// Broadcasts an announcement to the users of the registry, through the
+// `/announcements` endpoint and the warnings returned when publishing.
+//
+// Usage:
+//      cargo run --bin announce message [ends-at]
+//
+// where `ends-at` is formatted as `%Y-%m-%d %H:%M:%S` (UTC).
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+extern crate chrono;
+extern crate diesel;
+
+use chrono::NaiveDateTime;
+use std::env;
+
+use cargo_registry::models::NewAnnouncement;
+
+fn main() {
+    let conn = cargo_registry::db::connect_now().unwrap();
+
+    let message = match env::args().nth(1) {
+        None => {
+            println!("needs a message argument");
+            return;
+        }
+        Some(s) => s,
+    };
+    let ends_at = env::args().nth(2).map(|s| {
+        NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S")
+            .expect("Could not parse the ends-at argument as a time")
+    });
+
+    let announcement = NewAnnouncement {
+        message: &message,
+        starts_at: None,
+        ends_at,
+    }.create(&conn)
+        .unwrap();
+    println!("created announcement {}", announcement.id);
+}
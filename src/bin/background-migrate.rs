@@ -0,0 +1,65 @@
+// Runs a background data migration in chunks, resuming from where it stopped
+// if it was interrupted, or lists the progress of the known migrations.
+//
+// Usage:
+//      cargo run --bin background-migrate list
+//      cargo run --bin background-migrate run <name> [batch size]
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+
+use std::env;
+use std::process::exit;
+
+use cargo_registry::background_migrations::{self, Progress};
+
+const DEFAULT_BATCH_SIZE: i64 = 1000;
+
+fn main() {
+    let conn = cargo_registry::db::connect_now().unwrap();
+    match env::args().nth(1).as_ref().map(|s| &s[..]) {
+        Some("list") => {
+            let progress = Progress::all(&conn).unwrap();
+            for migration in background_migrations::all() {
+                match progress.iter().find(|p| p.name == migration.name()) {
+                    Some(p) if p.finished_at.is_some() => {
+                        println!("{}: finished, {} rows", p.name, p.processed)
+                    }
+                    Some(p) => println!("{}: {} of {} rows", p.name, p.processed, p.total),
+                    None => println!("{}: not started", migration.name()),
+                }
+            }
+        }
+        Some("run") => {
+            let name = match env::args().nth(2) {
+                Some(name) => name,
+                None => {
+                    println!("needs the name of the migration to run");
+                    exit(1);
+                }
+            };
+            let batch_size = env::args()
+                .nth(3)
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(DEFAULT_BATCH_SIZE);
+            let migration = match background_migrations::all()
+                .into_iter()
+                .find(|m| m.name() == name)
+            {
+                Some(migration) => migration,
+                None => {
+                    println!("unknown migration `{}`", name);
+                    exit(1);
+                }
+            };
+            let progress = background_migrations::run(&conn, &*migration, batch_size).unwrap();
+            println!("{}: finished, {} rows", progress.name, progress.processed);
+        }
+        _ => {
+            println!("Usage: background-migrate list");
+            println!("       background-migrate run <name> [batch size]");
+            exit(1);
+        }
+    }
+}
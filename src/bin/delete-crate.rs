@@ -1,6 +1,8 @@
 // Purge all references to a crate from the database.
 //
-// Please be super sure you want to do this before running this.
+// Please be super sure you want to do this before running this. The crate is
+// archived in `deleted_crates` and its name is reserved, but its entries in
+// the index are left alone.
 //
 // Usage:
 //      cargo run --bin delete-crate crate-name
@@ -15,8 +17,7 @@ use std::env;
 use std::io;
 use std::io::prelude::*;
 
-use cargo_registry::models::Crate;
-use cargo_registry::schema::crates;
+use cargo_registry::models::{Crate, DeletedCrate};
 
 fn main() {
     let conn = cargo_registry::db::connect_now().unwrap();
@@ -48,10 +49,8 @@ fn delete(conn: &PgConnection) {
     }
 
     println!("deleting the crate");
-    let n = diesel::delete(crates::table.find(krate.id))
-        .execute(conn)
-        .unwrap();
-    println!("  {} deleted", n);
+    let deleted = DeletedCrate::archive(conn, &krate, None, None).unwrap();
+    println!("  archived as deleted crate {}", deleted.id);
 
     print!("commit? [y/N]: ");
     io::stdout().flush().unwrap();
@@ -62,6 +62,7 @@ fn delete(conn: &PgConnection) {
     diesel::delete(versions::table.find(&v.id))
         .execute(conn)
         .unwrap();
+    krate.update_max_versions(conn).unwrap();
 
     print!("commit? [y/N]: ");
     io::stdout().flush().unwrap();
@@ -0,0 +1,18 @@
+// Records the crates that started depending on another in the versions
+// published since the last run, listed by `GET /crates/:crate_id/new_dependents`.
+// Meant to be run every hour.
+//
+// Usage:
+//      cargo run --bin record-new-dependents
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+
+use cargo_registry::models::CrateDependent;
+
+fn main() {
+    let conn = cargo_registry::db::connect_now().unwrap();
+    let recorded = CrateDependent::record_new(&conn).unwrap();
+    println!("recorded {} new dependents", recorded);
+}
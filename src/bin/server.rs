@@ -1,6 +1,7 @@
 #![deny(warnings)]
 
 extern crate cargo_registry;
+extern crate chrono;
 extern crate civet;
 extern crate env_logger;
 extern crate git2;
@@ -11,6 +12,7 @@ use std::env;
 use std::fs::{self, File};
 use std::sync::mpsc::channel;
 use std::sync::Arc;
+use std::time::Duration;
 
 fn main() {
     // Initialize logging
@@ -44,8 +46,53 @@ fn main() {
     cfg.set_str("user.name", "bors").unwrap();
     cfg.set_str("user.email", "bors@rust-lang.org").unwrap();
 
-    let app = cargo_registry::App::new(&config);
-    let app = cargo_registry::build_handler(Arc::new(app));
+    let app = Arc::new(cargo_registry::App::new(&config));
+
+    // If `INDEX_RETENTION_DAYS` is set, squash the history of the index older than
+    // that many days once a day so that the repository doesn't grow unboundedly.
+    if let Ok(days) = env::var("INDEX_RETENTION_DAYS") {
+        let days = days.parse().expect("couldn't parse INDEX_RETENTION_DAYS");
+        cargo_registry::git::spawn_squash_task(
+            Arc::clone(&app),
+            chrono::Duration::days(days),
+            Duration::from_secs(24 * 60 * 60),
+        );
+    }
+
+    // Send the events queued for the webhooks of crates.
+    cargo_registry::webhooks::spawn_delivery_task(Arc::clone(&app), Duration::from_secs(30));
+
+    // Move the index entries of the crates renamed by administrators.
+    cargo_registry::git::spawn_rename_task(Arc::clone(&app), Duration::from_secs(60));
+
+    // Write the index entries of the publishes that stopped before writing them.
+    cargo_registry::git::spawn_outbox_task(Arc::clone(&app), Duration::from_secs(60));
+
+    // Forward the changes made to crates to the clients of `/updates/stream`.
+    cargo_registry::updates::spawn_listener(Arc::clone(&app), Duration::from_millis(500));
+
+    // Correct the crate counts of keywords and categories once a day.
+    cargo_registry::recount::spawn_recount_task(
+        Arc::clone(&app),
+        Duration::from_secs(24 * 60 * 60),
+    );
+
+    // Flag the crates without a release or an action of their owners once a day.
+    cargo_registry::stale::spawn_stale_task(Arc::clone(&app), Duration::from_secs(24 * 60 * 60));
+
+    // Recompute the quality scores boosting the relevance of crates every night.
+    cargo_registry::quality::spawn_quality_task(
+        Arc::clone(&app),
+        Duration::from_secs(24 * 60 * 60),
+    );
+
+    // Recompute the crates related to each crate once a day.
+    cargo_registry::related::spawn_related_task(
+        Arc::clone(&app),
+        Duration::from_secs(24 * 60 * 60),
+    );
+
+    let app = cargo_registry::build_handler(app);
 
     // On every server restart, ensure the categories available in the database match
     // the information in *src/categories.toml*.
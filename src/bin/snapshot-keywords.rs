@@ -0,0 +1,17 @@
+// Records the number of crates of every keyword, to compute the keywords
+// listed by `GET /keywords/trending`. Meant to be run every day.
+//
+// Usage:
+//      cargo run --bin snapshot-keywords
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+
+use cargo_registry::models::Keyword;
+
+fn main() {
+    let conn = cargo_registry::db::connect_now().unwrap();
+    let recorded = Keyword::snapshot_counts(&conn).unwrap();
+    println!("recorded the crate counts of {} keywords", recorded);
+}
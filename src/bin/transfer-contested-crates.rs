@@ -0,0 +1,17 @@
+// Transfers the contested crates whose grace window is over to the users
+// claiming them, see `CrateTransfer`. Meant to be run every hour.
+//
+// Usage:
+//      cargo run --bin transfer-contested-crates
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+
+use cargo_registry::models::CrateTransfer;
+
+fn main() {
+    let conn = cargo_registry::db::connect_now().unwrap();
+    let transferred = CrateTransfer::perform_due(&conn).unwrap();
+    println!("transferred {} contested crates", transferred);
+}
@@ -136,7 +136,7 @@ mod test {
         let krate = NewCrate {
             name: "foo",
             ..Default::default()
-        }.create_or_update(&conn, None, user_id)
+        }.create_or_update(&conn, None, user_id, &Default::default())
             .unwrap();
         let version = NewVersion::new(
             krate.id,
@@ -0,0 +1,66 @@
+// Recomputes the dependency freshness score of every crate, that is the share
+// of the dependencies of its latest version whose requirement accepts the
+// latest release of the dependency.
+//
+// Usage:
+//      cargo run --bin update-freshness [daemon <seconds>]
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+extern crate diesel;
+
+use diesel::prelude::*;
+use std::env;
+use std::time::Duration;
+
+use cargo_registry::models::Crate;
+use cargo_registry::schema::*;
+use cargo_registry::util::CargoResult;
+
+fn main() {
+    let daemon = env::args().nth(1).as_ref().map(|s| &s[..]) == Some("daemon");
+    let sleep = env::args().nth(2).map(|s| s.parse().unwrap());
+    loop {
+        let conn = cargo_registry::db::connect_now().unwrap();
+        update(&conn).unwrap();
+        drop(conn);
+        if daemon {
+            std::thread::sleep(Duration::new(sleep.unwrap(), 0));
+        } else {
+            break;
+        }
+    }
+}
+
+fn update(conn: &PgConnection) -> CargoResult<()> {
+    use diesel::dsl::now;
+    use diesel::{delete, insert_into};
+
+    let krates = Crate::all().load::<Crate>(conn)?;
+    println!("updating the freshness of {} crates", krates.len());
+
+    for krate in krates {
+        match krate.dependency_freshness(conn)? {
+            Some(freshness) => {
+                insert_into(crate_freshness::table)
+                    .values((
+                        crate_freshness::crate_id.eq(krate.id),
+                        crate_freshness::freshness.eq(freshness),
+                    ))
+                    .on_conflict(crate_freshness::crate_id)
+                    .do_update()
+                    .set((
+                        crate_freshness::freshness.eq(freshness),
+                        crate_freshness::computed_at.eq(now),
+                    ))
+                    .execute(conn)?;
+            }
+            None => {
+                delete(crate_freshness::table.find(krate.id)).execute(conn)?;
+            }
+        }
+    }
+
+    Ok(())
+}
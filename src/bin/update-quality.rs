@@ -0,0 +1,38 @@
+// Recomputes the quality score of every crate, see `Crate::quality_score`.
+// The server does it every night, this is for doing it on demand.
+//
+// Usage:
+//      cargo run --bin update-quality [daemon <seconds>]
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+extern crate diesel;
+
+use diesel::prelude::*;
+use std::env;
+use std::time::Duration;
+
+use cargo_registry::models::Crate;
+use cargo_registry::util::CargoResult;
+
+fn main() {
+    let daemon = env::args().nth(1).as_ref().map(|s| &s[..]) == Some("daemon");
+    let sleep = env::args().nth(2).map(|s| s.parse().unwrap());
+    loop {
+        let conn = cargo_registry::db::connect_now().unwrap();
+        update(&conn).unwrap();
+        drop(conn);
+        if daemon {
+            std::thread::sleep(Duration::new(sleep.unwrap(), 0));
+        } else {
+            break;
+        }
+    }
+}
+
+fn update(conn: &PgConnection) -> CargoResult<()> {
+    let scored = Crate::update_quality_scores(conn)?;
+    println!("updated the quality score of {} crates", scored);
+    Ok(())
+}
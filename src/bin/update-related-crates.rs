@@ -0,0 +1,18 @@
+// Recomputes the crates related to each crate, listed by
+// `GET /crates/:crate_id/related`. The server does it every day, this is for
+// doing it on demand.
+//
+// Usage:
+//      cargo run --bin update-related-crates
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+
+use cargo_registry::models::RelatedCrate;
+
+fn main() {
+    let conn = cargo_registry::db::connect_now().unwrap();
+    let recorded = RelatedCrate::recompute(&conn).unwrap();
+    println!("recorded {} related crates", recorded);
+}
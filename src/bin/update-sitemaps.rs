@@ -0,0 +1,17 @@
+// Regenerates the sitemaps of the crates and categories served at
+// `/sitemap.xml`. Meant to be run every day.
+//
+// Usage:
+//      cargo run --bin update-sitemaps
+
+#![deny(warnings)]
+
+extern crate cargo_registry;
+
+use cargo_registry::models::Sitemap;
+
+fn main() {
+    let conn = cargo_registry::db::connect_now().unwrap();
+    let generated = Sitemap::regenerate(&conn).unwrap();
+    println!("generated {} sitemaps", generated);
+}
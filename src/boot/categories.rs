@@ -13,6 +13,7 @@ struct Category {
     slug: String,
     name: String,
     description: String,
+    renamed_from: Vec<String>,
 }
 
 impl Category {
@@ -20,6 +21,7 @@ impl Category {
         slug: &str,
         name: &str,
         description: &str,
+        renamed_from: Vec<String>,
         parent: Option<&Category>,
     ) -> Category {
         match parent {
@@ -27,11 +29,13 @@ impl Category {
                 slug: format!("{}::{}", parent.slug, slug),
                 name: format!("{}::{}", parent.name, name),
                 description: description.into(),
+                renamed_from,
             },
             None => Category {
                 slug: slug.into(),
                 name: name.into(),
                 description: description.into(),
+                renamed_from,
             },
         }
     }
@@ -50,6 +54,23 @@ fn optional_string_from_toml<'a>(toml: &'a toml::value::Table, key: &str) -> &'a
     toml.get(key).and_then(toml::Value::as_str).unwrap_or("")
 }
 
+/// The full slugs a category had before, from its `renamed_from` list.
+fn renamed_from_toml(toml: &toml::value::Table) -> CargoResult<Vec<String>> {
+    let slugs = match toml.get("renamed_from") {
+        Some(slugs) => slugs,
+        None => return Ok(Vec::new()),
+    };
+    slugs
+        .as_array()
+        .and_then(|slugs| {
+            slugs
+                .iter()
+                .map(|slug| slug.as_str().map(str::to_lowercase))
+                .collect()
+        })
+        .chain_error(|| internal("Expected category TOML attribute 'renamed_from' to be a list"))
+}
+
 fn categories_from_toml(
     categories: &toml::value::Table,
     parent: Option<&Category>,
@@ -65,6 +86,7 @@ fn categories_from_toml(
             slug,
             required_string_from_toml(details, "name")?,
             optional_string_from_toml(details, "description"),
+            renamed_from_toml(details)?,
             parent,
         );
 
@@ -98,19 +120,26 @@ pub fn sync_with_connection(toml_str: &str, conn: &PgConnection) -> CargoResult<
     let toml: toml::value::Table =
         toml::from_str(toml_str).expect("Could not parse categories toml");
 
-    let to_insert = categories_from_toml(&toml, None)
-        .expect("Could not convert categories from TOML")
-        .into_iter()
+    let new_categories =
+        categories_from_toml(&toml, None).expect("Could not convert categories from TOML");
+    let to_insert = new_categories
+        .iter()
         .map(|c| {
             (
                 slug.eq(c.slug.to_lowercase()),
-                category.eq(c.name),
-                description.eq(c.description),
+                category.eq(&c.name),
+                description.eq(&c.description),
             )
         })
         .collect::<Vec<_>>();
 
     conn.transaction(|| {
+        for c in &new_categories {
+            for old_slug in &c.renamed_from {
+                rename(conn, old_slug, &c.slug.to_lowercase())?;
+            }
+        }
+
         let slugs = diesel::insert_into(categories)
             .values(&to_insert)
             .on_conflict(slug)
@@ -128,3 +157,59 @@ pub fn sync_with_connection(toml_str: &str, conn: &PgConnection) -> CargoResult<
         Ok(())
     })
 }
+
+/// Renames the category `old_slug` and its subcategories to `new_slug`,
+/// keeping their crates, and records the old slug so that it resolves to the
+/// category. The crates of `old_slug` are moved over if both exist.
+fn rename(conn: &PgConnection, old_slug: &str, new_slug: &str) -> QueryResult<()> {
+    use diesel::sql_types::{Integer, Text};
+    use schema::{categories, category_renames};
+
+    let id_of = |slug: &str| {
+        categories::table
+            .filter(categories::slug.eq(slug))
+            .select(categories::id)
+            .first::<i32>(conn)
+            .optional()
+    };
+    let category_id = match (id_of(old_slug)?, id_of(new_slug)?) {
+        (Some(_), None) => {
+            diesel::sql_query(
+                "UPDATE categories SET slug = $1 || substr(slug, $2) \
+                 WHERE slug = $3 OR slug LIKE $4",
+            ).bind::<Text, _>(new_slug)
+                .bind::<Integer, _>(old_slug.len() as i32 + 1)
+                .bind::<Text, _>(old_slug)
+                .bind::<Text, _>(format!("{}::%", old_slug))
+                .execute(conn)?;
+            id_of(new_slug)?.expect("the category was just renamed")
+        }
+        (Some(old_id), Some(new_id)) => {
+            diesel::sql_query(
+                "INSERT INTO crates_categories (crate_id, category_id) \
+                 SELECT crate_id, $1 FROM crates_categories WHERE category_id = $2 \
+                 ON CONFLICT DO NOTHING",
+            ).bind::<Integer, _>(new_id)
+                .bind::<Integer, _>(old_id)
+                .execute(conn)?;
+            let renames = category_renames::table.filter(category_renames::category_id.eq(old_id));
+            diesel::update(renames)
+                .set(category_renames::category_id.eq(new_id))
+                .execute(conn)?;
+            new_id
+        }
+        (None, Some(new_id)) => new_id,
+        (None, None) => return Ok(()),
+    };
+
+    diesel::insert_into(category_renames::table)
+        .values((
+            category_renames::old_slug.eq(old_slug),
+            category_renames::category_id.eq(category_id),
+        ))
+        .on_conflict(category_renames::old_slug)
+        .do_update()
+        .set(category_renames::category_id.eq(category_id))
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Invalidation of the API responses cached by a CDN in front of the registry.
+//!
+//! Responses are cached according to their `CachePolicy`. When a crate is
+//! published or yanked, the paths of its cached responses are POSTed as
+//! `{"paths": [...]}` to `Config::cdn_purge_url`, from a separate thread so a
+//! slow CDN doesn't hold up the request.
+
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+use curl::easy::List;
+
+use util::{internal, CargoResult};
+use App;
+
+/// The paths of the cached responses that change with the given version. The
+/// download path is only cached for HEAD requests, which aren't counted.
+pub fn crate_paths(crate_name: &str, version: &str) -> Vec<String> {
+    vec![
+        format!("/api/v1/crates/{}", crate_name),
+        format!("/api/v1/crates/{}/{}/download", crate_name, version),
+    ]
+}
+
+/// Purges `paths` from the CDN in the background, if one is configured.
+pub fn purge(app: &Arc<App>, paths: Vec<String>) {
+    if app.config.cdn_purge_url.is_none() {
+        return;
+    }
+    let app = Arc::clone(app);
+    thread::spawn(move || {
+        if let Err(e) = send(&app, &paths) {
+            error!("failed to purge {:?} from the CDN: {}", paths, e);
+        }
+    });
+}
+
+fn send(app: &App, paths: &[String]) -> CargoResult<()> {
+    let url = match app.config.cdn_purge_url {
+        Some(ref url) => url,
+        None => return Ok(()),
+    };
+    let body = json!({ "paths": paths }).to_string();
+    let mut headers = List::new();
+    headers.append("Content-Type: application/json")?;
+
+    let mut handle = app.handle();
+    handle.url(url)?;
+    handle.post(true)?;
+    handle.post_fields_copy(body.as_bytes())?;
+    handle.http_headers(headers)?;
+    handle.timeout(time::Duration::from_secs(10))?;
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|buf| Ok(buf.len()))?;
+        transfer.perform()?;
+    }
+    match handle.response_code()? {
+        200...299 => Ok(()),
+        code => Err(internal(&format_args!("the CDN responded with {}", code))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crate_paths_cover_show_and_download() {
+        assert_eq!(
+            crate_paths("foo", "1.0.0"),
+            vec!["/api/v1/crates/foo", "/api/v1/crates/foo/1.0.0/download"]
+        );
+    }
+}
@@ -3,6 +3,13 @@ use s3;
 use std::env;
 use std::path::PathBuf;
 
+use dependency_policy::DependencyPolicy;
+use download_routing::DownloadRouting;
+use git::SigningKey;
+use models::krate::MAX_NAME_LENGTH;
+use name_policy::NamePolicy;
+use regex::Regex;
+use scanning::{ScanPolicy, Scanner};
 use {env, Env, Replica, Uploader};
 
 #[derive(Clone, Debug)]
@@ -18,6 +25,31 @@ pub struct Config {
     pub max_unpack_size: u64,
     pub mirror: Replica,
     pub api_protocol: String,
+    pub rate_limit_requests: u64,
+    pub rate_limit_window: u64,
+    pub scanners: Vec<Scanner>,
+    pub scan_policy: ScanPolicy,
+    pub admin_github_ids: Vec<i32>,
+    pub download_routing: Option<DownloadRouting>,
+    pub require_verified_email: bool,
+    pub publish_hold_days: Option<i64>,
+    pub metrics_token: Option<String>,
+    pub warn_yanked_downloads: bool,
+    pub unique_downloads: bool,
+    pub download_breakdown: bool,
+    pub download_country_header: Option<String>,
+    pub orphan_team: Option<String>,
+    pub cdn_purge_url: Option<String>,
+    pub require_two_factor: bool,
+    pub dependency_policy: DependencyPolicy,
+    pub name_policy: NamePolicy,
+    pub read_only: bool,
+    /// How many clients can follow `GET /updates/stream` at once, each of
+    /// them holding a server thread.
+    pub max_update_subscribers: usize,
+    /// The private key the commits of the git index are signed with, see
+    /// `git::verify_commit`.
+    pub index_signing_key: Option<SigningKey>,
 }
 
 impl Default for Config {
@@ -25,8 +57,12 @@ impl Default for Config {
     ///
     /// Sets the following default values:
     ///
-    /// - `Config::max_upload_size`: 10MiB
+    /// - `Config::max_upload_size`: 10MiB, unless `MAX_UPLOAD_SIZE` is set
     /// - `Config::api_protocol`: `https`
+    /// - `Config::rate_limit_requests`: 1000
+    /// - `Config::rate_limit_window`: 60 seconds
+    /// - `Config::scan_policy`: `ScanPolicy::AnyBlock`
+    /// - `Config::max_update_subscribers`: 10, unless `MAX_UPDATE_SUBSCRIBERS` is set
     ///
     /// Pulls values from the following environment variables:
     ///
@@ -38,10 +74,55 @@ impl Default for Config {
     /// - `S3_REGION`: The region in which the bucket was created. Optional if US standard.
     /// - `S3_ACCESS_KEY`: The access key to interact with S3. Optional if running a mirror.
     /// - `S3_SECRET_KEY`: The secret key to interact with S3. Optional if running a mirror.
+    /// - `S3_SIGNED_URLS_TTL`: Redirect authenticated downloads to presigned URLs valid for this
+    /// many seconds, instead of public links, for private registries.
+    /// - `S3_REPLICAS`: The comma separated buckets crate files are copied to, as `bucket` or
+    /// `bucket@region`, using the same keys as `S3_BUCKET`.
+    /// - `S3_NEAREST_BUCKET`: The bucket crate files are preferably served from, while it's
+    /// healthy. Defaults to `S3_BUCKET`.
     /// - `SESSION_KEY`: The key used to sign and encrypt session cookies.
     /// - `GH_CLIENT_ID`: The client ID of the associated GitHub application.
     /// - `GH_CLIENT_SECRET`: The client secret of the associated GitHub application.
     /// - `DATABASE_URL`: The URL of the postgres database to use.
+    /// - `MAX_UPLOAD_SIZE`: The default maximum size of an uploaded crate file, in bytes.
+    /// - `RATE_LIMIT_REQUESTS`: How many requests an authenticated user can make per window.
+    /// - `RATE_LIMIT_WINDOW`: The length of a rate limiting window, in seconds.
+    /// - `CRATE_SCANNERS`: The scanners uploaded crates are sent to, as `name=url,name=url`.
+    /// - `CRATE_SCAN_POLICY`: How scanner verdicts are combined, `any-block` or `quorum:N`.
+    /// - `ADMIN_GITHUB_IDS`: The comma separated GitHub ids of the registry administrators.
+    /// - `DOWNLOAD_SECONDARY_HOST`: The host of a second storage backend to route downloads to.
+    /// - `DOWNLOAD_SECONDARY_WEIGHT`: The percentage of downloads routed to the second backend.
+    /// - `DOWNLOAD_BACKEND_OVERRIDES`: Crates pinned to a backend, as `name=primary,name=secondary`.
+    /// - `REQUIRE_VERIFIED_EMAIL`: Only allow users with a verified email address to publish.
+    /// - `PUBLISH_HOLD_DAYS`: Hold the crates of accounts younger than this many days for review.
+    /// - `METRICS_AUTHORIZATION_TOKEN`: The token `/metrics` is served to, disabled if unset.
+    /// - `WARN_YANKED_DOWNLOADS`: Flag downloads of yanked versions and count them separately.
+    /// - `COUNT_UNIQUE_DOWNLOADS`: Also count the downloads of each version per IP address and day.
+    /// - `DOWNLOAD_BREAKDOWN`: Also count the downloads of each version per cargo version and
+    /// country.
+    /// - `DOWNLOAD_COUNTRY_HEADER`: The header the CDN sends the country of a download in, like
+    /// `CF-IPCountry`.
+    /// - `ORPHAN_TEAM`: The team the crates of deleted accounts are transferred to, as
+    /// `github:org:team`. Accounts owning crates alone can't be deleted if unset.
+    /// - `CDN_PURGE_URL`: Where the paths cached by the CDN are purged when a crate changes.
+    /// - `REQUIRE_TWO_FACTOR`: Only allow users with two-factor authentication enabled to publish
+    /// crates and change their owners.
+    /// - `BAN_WILDCARD_DEPENDENCIES`: Reject dependency requirements with a wildcard, like `1.*`.
+    /// - `ALLOWED_DEPENDENCIES`: The comma separated names of the only crates that can be depended
+    /// on.
+    /// - `REJECT_YANKED_DEPENDENCIES`: Reject dependency requirements only matched by yanked
+    /// versions, instead of warning about them.
+    /// - `ALLOWED_REGISTRIES`: The comma separated index URLs of the other registries whose crates
+    /// can be depended on.
+    /// - `MAX_CRATE_NAME_LENGTH`: The longest name new crates can have, 64 at most.
+    /// - `FORBID_CONFUSABLE_CRATE_NAMES`: Reject the names of new crates that only differ from an
+    /// existing crate by their `-` and `_`.
+    /// - `CRATE_NAME_DENYLIST`: A regular expression the names of new crates can't match.
+    /// - `READ_ONLY`: Keep this server in read-only mode, refusing publishes, yanks, owner changes
+    /// and follows whatever the administrators set.
+    /// - `MAX_UPDATE_SUBSCRIBERS`: How many clients can follow the stream of updates at once.
+    /// - `INDEX_SIGNING_KEY`: The PEM encoded private key the commits of the git index are signed
+    /// with, in a `Registry-Signature` trailer of their message.
     fn default() -> Config {
         let checkout = PathBuf::from(env("GIT_REPO_CHECKOUT"));
         let api_protocol = String::from("https");
@@ -56,6 +137,9 @@ impl Default for Config {
         } else {
             Env::Development
         };
+        let signed_urls_ttl = env::var("S3_SIGNED_URLS_TTL")
+            .ok()
+            .map(|s| s.parse().expect("couldn't parse S3_SIGNED_URLS_TTL"));
         let uploader = match (cargo_env, mirror) {
             (Env::Production, Replica::Primary) => {
                 // `env` panics if these vars are not set, and in production for a primary instance,
@@ -71,6 +155,7 @@ impl Default for Config {
                     ),
                     cdn: env::var("S3_CDN").ok(),
                     proxy: None,
+                    signed_urls_ttl,
                 }
             }
             (Env::Production, Replica::ReadOnlyMirror) => {
@@ -92,6 +177,7 @@ impl Default for Config {
                     ),
                     cdn: env::var("S3_CDN").ok(),
                     proxy: None,
+                    signed_urls_ttl,
                 }
             }
             // In Development mode, either running as a primary instance or a read-only mirror
@@ -112,6 +198,7 @@ impl Default for Config {
                         ),
                         cdn: env::var("S3_CDN").ok(),
                         proxy: None,
+                        signed_urls_ttl,
                     }
                 } else {
                     // If we don't set the `S3_BUCKET` variable, we'll use a development-only
@@ -124,6 +211,40 @@ impl Default for Config {
                 }
             }
         };
+        let uploader = match env::var("S3_REPLICAS") {
+            Ok(replicas) => {
+                let buckets = replicas
+                    .split(',')
+                    .map(|replica| {
+                        let mut parts = replica.trim().splitn(2, '@');
+                        let name = parts.next().unwrap().to_string();
+                        (name, parts.next().map(String::from))
+                    })
+                    .collect::<Vec<_>>();
+                let nearest = env::var("S3_NEAREST_BUCKET").ok();
+                let nearest = buckets
+                    .iter()
+                    .position(|&(ref name, _)| Some(name) == nearest.as_ref())
+                    .map_or(0, |i| i + 1);
+                let replicas = buckets
+                    .into_iter()
+                    .map(|(name, region)| Uploader::S3 {
+                        bucket: s3::Bucket::new(
+                            name,
+                            region,
+                            env::var("S3_ACCESS_KEY").unwrap_or_default(),
+                            env::var("S3_SECRET_KEY").unwrap_or_default(),
+                            &api_protocol,
+                        ),
+                        cdn: None,
+                        proxy: None,
+                        signed_urls_ttl,
+                    })
+                    .collect();
+                Uploader::replicated(uploader, replicas, nearest)
+            }
+            Err(_) => uploader,
+        };
         Config {
             uploader,
             session_key: env("SESSION_KEY"),
@@ -132,10 +253,81 @@ impl Default for Config {
             gh_client_secret: env("GH_CLIENT_SECRET"),
             db_url: env("DATABASE_URL"),
             env: cargo_env,
-            max_upload_size: 10 * 1024 * 1024, // 10 MB default file upload size limit
+            // 10 MB default file upload size limit
+            max_upload_size: env::var("MAX_UPLOAD_SIZE")
+                .map(|s| s.parse().expect("couldn't parse MAX_UPLOAD_SIZE"))
+                .unwrap_or(10 * 1024 * 1024),
             max_unpack_size: 512 * 1024 * 1024, // 512 MB max when decompressed
             mirror,
             api_protocol,
+            rate_limit_requests: env::var("RATE_LIMIT_REQUESTS")
+                .map(|s| s.parse().expect("couldn't parse RATE_LIMIT_REQUESTS"))
+                .unwrap_or(1000),
+            rate_limit_window: env::var("RATE_LIMIT_WINDOW")
+                .map(|s| s.parse().expect("couldn't parse RATE_LIMIT_WINDOW"))
+                .unwrap_or(60),
+            scanners: env::var("CRATE_SCANNERS")
+                .map(|s| Scanner::parse_list(&s))
+                .unwrap_or_default(),
+            scan_policy: env::var("CRATE_SCAN_POLICY")
+                .map(|s| ScanPolicy::parse(&s).expect("couldn't parse CRATE_SCAN_POLICY"))
+                .unwrap_or(ScanPolicy::AnyBlock),
+            admin_github_ids: env::var("ADMIN_GITHUB_IDS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|id| id.trim().parse().expect("couldn't parse ADMIN_GITHUB_IDS"))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            download_routing: env::var("DOWNLOAD_SECONDARY_HOST")
+                .ok()
+                .map(|secondary_host| DownloadRouting {
+                    secondary_host,
+                    secondary_weight: env::var("DOWNLOAD_SECONDARY_WEIGHT")
+                        .map(|s| s.parse().expect("couldn't parse DOWNLOAD_SECONDARY_WEIGHT"))
+                        .unwrap_or(0),
+                    overrides: env::var("DOWNLOAD_BACKEND_OVERRIDES")
+                        .map(|s| DownloadRouting::parse_overrides(&s))
+                        .unwrap_or_default(),
+                }),
+            require_verified_email: env::var("REQUIRE_VERIFIED_EMAIL").is_ok(),
+            publish_hold_days: env::var("PUBLISH_HOLD_DAYS")
+                .ok()
+                .map(|s| s.parse().expect("couldn't parse PUBLISH_HOLD_DAYS")),
+            metrics_token: env::var("METRICS_AUTHORIZATION_TOKEN").ok(),
+            warn_yanked_downloads: env::var("WARN_YANKED_DOWNLOADS").is_ok(),
+            unique_downloads: env::var("COUNT_UNIQUE_DOWNLOADS").is_ok(),
+            download_breakdown: env::var("DOWNLOAD_BREAKDOWN").is_ok(),
+            download_country_header: env::var("DOWNLOAD_COUNTRY_HEADER").ok(),
+            orphan_team: env::var("ORPHAN_TEAM").ok(),
+            cdn_purge_url: env::var("CDN_PURGE_URL").ok(),
+            require_two_factor: env::var("REQUIRE_TWO_FACTOR").is_ok(),
+            dependency_policy: DependencyPolicy {
+                ban_wildcards: env::var("BAN_WILDCARD_DEPENDENCIES").is_ok(),
+                allowed_crates: env::var("ALLOWED_DEPENDENCIES")
+                    .ok()
+                    .map(|s| DependencyPolicy::parse_allowlist(&s)),
+                reject_yanked: env::var("REJECT_YANKED_DEPENDENCIES").is_ok(),
+                allowed_registries: env::var("ALLOWED_REGISTRIES")
+                    .map(|s| DependencyPolicy::parse_allowlist(&s))
+                    .unwrap_or_default(),
+            },
+            name_policy: NamePolicy {
+                max_length: env::var("MAX_CRATE_NAME_LENGTH")
+                    .map(|s| s.parse().expect("couldn't parse MAX_CRATE_NAME_LENGTH"))
+                    .unwrap_or(MAX_NAME_LENGTH),
+                forbid_confusable: env::var("FORBID_CONFUSABLE_CRATE_NAMES").is_ok(),
+                denylist: env::var("CRATE_NAME_DENYLIST")
+                    .ok()
+                    .map(|s| Regex::new(&s).expect("couldn't parse CRATE_NAME_DENYLIST")),
+            },
+            read_only: env::var("READ_ONLY").is_ok(),
+            max_update_subscribers: env::var("MAX_UPDATE_SUBSCRIBERS")
+                .map(|s| s.parse().expect("couldn't parse MAX_UPDATE_SUBSCRIBERS"))
+                .unwrap_or(10),
+            index_signing_key: env::var("INDEX_SIGNING_KEY").ok().map(|pem| {
+                SigningKey::from_pem(&pem).expect("couldn't parse INDEX_SIGNING_KEY")
+            }),
         }
     }
 }
@@ -0,0 +1,552 @@
+//! Endpoints only available to the administrators of the registry.
+
+use super::prelude::*;
+
+use chrono::NaiveDate;
+use diesel;
+use serde_json;
+
+use controllers::helpers::{encode_crates, Paginate};
+use git;
+use models::category::MAX_FEATURED_CRATES;
+use models::registry_flag::{RegistryFlag, READ_ONLY};
+use models::{Category, Crate, CrateRename, CrateSpotlight, CrateTransfer, DeletedCrate,
+             DownloadIngestion, HeldIndexEntry, IngestedDownloads, KeywordAlias, User};
+use recount::{self, Recount};
+use schema::{crates, deleted_crates, users};
+use views::{EncodableCrate, EncodableCrateSpotlight, EncodableCrateStatus, EncodableDeletedCrate,
+            EncodableKeywordAlias};
+
+/// Handles the `PUT /admin/crates/:crate_id/approve` route.
+///
+/// Approves a crate held for review, writing its held versions to the index.
+pub fn approve_crate(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    if !krate.publish_hold {
+        return Err(human(&format_args!(
+            "crate `{}` is not held for review",
+            krate.name
+        )));
+    }
+    let released = HeldIndexEntry::release(&conn, req.app(), &krate)?;
+    req.app().invalidate_summary_cache();
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        released: usize,
+    }
+    Ok(req.json(&R { ok: true, released }))
+}
+
+/// Handles the `PUT /admin/crates/:crate_id/rename` route.
+///
+/// Renames a crate, e.g. after a trademark dispute. The former name keeps
+/// resolving to the crate, and the index entries of the crate are moved to
+/// the new name in the background.
+pub fn rename_crate(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        name: String,
+    }
+    let request: Request =
+        serde_json::from_str(&body).map_err(|e| human(&format_args!("invalid rename: {}", e)))?;
+    let krate = CrateRename::rename(&conn, &krate, &request.name)?;
+    req.app().invalidate_summary_cache();
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        name: String,
+    }
+    Ok(req.json(&R {
+        ok: true,
+        name: krate.name,
+    }))
+}
+
+/// Handles the `PUT /admin/crates/:crate_id/lock` route.
+///
+/// Locks a crate while a dispute or a malware cleanup is handled. Publishes of
+/// new versions of a locked crate are rejected with the given reason.
+pub fn lock_crate(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        reason: Option<String>,
+    }
+    let request: Request =
+        serde_json::from_str(&body).map_err(|e| human(&format_args!("invalid lock: {}", e)))?;
+    diesel::update(&krate)
+        .set((
+            crates::locked.eq(true),
+            crates::lock_reason.eq(request.reason),
+        ))
+        .execute(&*conn)?;
+    ok_true()
+}
+
+/// Handles the `DELETE /admin/crates/:crate_id/lock` route.
+pub fn unlock_crate(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    diesel::update(&krate)
+        .set((
+            crates::locked.eq(false),
+            crates::lock_reason.eq(None::<String>),
+        ))
+        .execute(&*conn)?;
+    ok_true()
+}
+
+/// Handles the `PUT /admin/crates/:crate_id/transfer` route.
+///
+/// Marks the name of a crate as contested and schedules its transfer to the
+/// user claiming it once the owners had `days` days to respond. The owners
+/// are notified, and the status is shown on the crate. The transfer is done
+/// by the `transfer-contested-crates` job.
+pub fn schedule_transfer(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    let admin = req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        claimant: String,
+        reason: String,
+        days: i64,
+    }
+    let request: Request = serde_json::from_str(&body)
+        .map_err(|e| human(&format_args!("invalid transfer: {}", e)))?;
+    let claimant = users::table
+        .filter(users::gh_login.eq(&request.claimant))
+        .first::<User>(&*conn)
+        .optional()?
+        .ok_or_else(|| human(&format_args!("no user named `{}`", request.claimant)))?;
+    let transfer = CrateTransfer::schedule(
+        &conn,
+        &krate,
+        &claimant,
+        &request.reason,
+        request.days,
+        admin,
+    )?;
+
+    #[derive(Serialize)]
+    struct R {
+        status: EncodableCrateStatus,
+    }
+    Ok(req.json(&R {
+        status: CrateTransfer::encodable_status(Some(transfer)),
+    }))
+}
+
+/// Handles the `DELETE /admin/crates/:crate_id/transfer` route.
+pub fn cancel_transfer(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let transfer = CrateTransfer::find(&conn, &krate)?
+        .ok_or_else(|| human(&format_args!("crate `{}` isn't contested", krate.name)))?;
+    transfer.cancel(&conn)?;
+    ok_true()
+}
+
+/// Handles the `DELETE /admin/crates/:crate_id` route.
+///
+/// Deletes a crate, e.g. because it's malware. The crate is archived with its
+/// versions and owners so that it can be inspected or restored later, and its
+/// name is reserved.
+pub fn delete_crate(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    let admin = req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        reason: Option<String>,
+    }
+    let request: Request =
+        serde_json::from_str(&body).map_err(|e| human(&format_args!("invalid deletion: {}", e)))?;
+    let deleted = DeletedCrate::delete(
+        req.app(),
+        &conn,
+        &krate,
+        admin,
+        request.reason.as_ref().map(|s| &**s),
+    )?;
+    req.app().invalidate_summary_cache();
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        id: i32,
+    }
+    Ok(req.json(&R {
+        ok: true,
+        id: deleted.id,
+    }))
+}
+
+/// Handles the `GET /admin/deleted_crates` route.
+///
+/// Lists the deleted crates, the most recently deleted first.
+pub fn deleted_crates(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let conn = req.db_conn()?;
+    let (offset, limit) = req.pagination(10, 100)?;
+
+    let data = deleted_crates::table
+        .left_join(users::table)
+        .select((deleted_crates::all_columns, users::gh_login.nullable()))
+        .order(deleted_crates::deleted_at.desc())
+        .paginate(limit, offset)
+        .load::<((DeletedCrate, Option<String>), i64)>(&*conn)?;
+    let total = data.get(0).map(|&(_, t)| t).unwrap_or(0);
+    let deleted_crates = data.into_iter()
+        .map(|((deleted, login), _)| deleted.encodable(login))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        deleted_crates: Vec<EncodableDeletedCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+    Ok(req.json(&R {
+        deleted_crates,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `PUT /admin/deleted_crates/:id/restore` route.
+///
+/// Restores a deleted crate, e.g. after it was deleted by mistake.
+pub fn restore_crate(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let id = req.params()["id"]
+        .parse::<i32>()
+        .map_err(|_| human("invalid deleted crate id"))?;
+    let conn = req.db_conn()?;
+    let deleted = deleted_crates::table.find(id).first::<DeletedCrate>(&*conn)?;
+    let krate = deleted.restore(req.app(), &conn)?;
+    req.app().invalidate_summary_cache();
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        name: String,
+    }
+    Ok(req.json(&R {
+        ok: true,
+        name: krate.name,
+    }))
+}
+
+/// Handles the `PUT /admin/crates/:crate_id/spotlight` route.
+///
+/// Features a crate on the front page on the given day, in place of the crate
+/// that would have been picked automatically.
+pub fn feature_crate(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    let admin = req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        date: NaiveDate,
+        rationale: String,
+    }
+    let request: Request = serde_json::from_str(&body)
+        .map_err(|e| human(&format_args!("invalid spotlight: {}", e)))?;
+    if request.rationale.trim().is_empty() {
+        return Err(human("the rationale of a spotlight cannot be empty"));
+    }
+    let spotlight =
+        CrateSpotlight::feature(&conn, &krate, request.date, &request.rationale, admin)?;
+    req.app().invalidate_summary_cache();
+
+    #[derive(Serialize)]
+    struct R {
+        spotlight: EncodableCrateSpotlight,
+    }
+    let krate = encode_crates(&conn, vec![krate])?.remove(0);
+    Ok(req.json(&R {
+        spotlight: spotlight.encodable(krate),
+    }))
+}
+
+/// Handles the `PUT /admin/categories/:category_id/featured/:crate_id` route.
+///
+/// Features a crate of the category or of one of its subcategories at the top
+/// of the category, after the crates already featured.
+pub fn feature_category_crate(req: &mut Request) -> CargoResult<Response> {
+    let admin = req.admin()?;
+    let conn = req.db_conn()?;
+    let category = Category::find_by_slug(&conn, &req.params()["category_id"])?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    if !category.contains_crate(&conn, &krate)? {
+        return Err(human(&format_args!(
+            "crate `{}` is not in category `{}`",
+            krate.name, category.slug
+        )));
+    }
+    let featured = category.featured_crates(&conn)?;
+    if featured.len() >= MAX_FEATURED_CRATES && !featured.iter().any(|c| c.id == krate.id) {
+        return Err(human(&format_args!(
+            "category `{}` already features {} crates, the most it can",
+            category.slug, MAX_FEATURED_CRATES
+        )));
+    }
+    category.feature_crate(&conn, &krate, admin)?;
+
+    #[derive(Serialize)]
+    struct R {
+        featured: Vec<EncodableCrate>,
+    }
+    let featured = category.featured_crates(&conn)?;
+    Ok(req.json(&R {
+        featured: encode_crates(&conn, featured)?,
+    }))
+}
+
+/// Handles the `DELETE /admin/categories/:category_id/featured/:crate_id` route.
+pub fn unfeature_category_crate(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let conn = req.db_conn()?;
+    let category = Category::find_by_slug(&conn, &req.params()["category_id"])?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    if !category.unfeature_crate(&conn, &krate)? {
+        return Err(human(&format_args!(
+            "crate `{}` is not featured in category `{}`",
+            krate.name, category.slug
+        )));
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        featured: Vec<EncodableCrate>,
+    }
+    let featured = category.featured_crates(&conn)?;
+    Ok(req.json(&R {
+        featured: encode_crates(&conn, featured)?,
+    }))
+}
+
+/// Handles the `POST /admin/jobs/recount` route.
+///
+/// Recomputes the crate counts of keywords and categories right away rather
+/// than waiting for the daily job.
+pub fn recount_crates(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let conn = req.db_conn()?;
+    let corrected = recount::recount(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        corrected: Recount,
+    }
+    Ok(req.json(&R {
+        ok: true,
+        corrected,
+    }))
+}
+
+/// The most records a batch of downloads can have.
+const MAX_INGESTED_RECORDS: usize = 10_000;
+
+/// Handles the `POST /admin/downloads/ingest` route.
+///
+/// Adds the downloads served by the CDN, which the registry never sees, to
+/// the daily downloads of the versions. A batch is only ingested once, so the
+/// log processor can send it again after a failure.
+pub fn ingest_downloads(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    req.admin()?;
+    let conn = req.db_conn()?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        batch_id: String,
+        downloads: Vec<IngestedDownloads>,
+    }
+    let request: Request = serde_json::from_str(&body)
+        .map_err(|e| human(&format_args!("invalid downloads: {}", e)))?;
+    if request.downloads.len() > MAX_INGESTED_RECORDS {
+        return Err(human(&format_args!(
+            "a batch can't have more than {} records",
+            MAX_INGESTED_RECORDS
+        )));
+    }
+    let ingested = DownloadIngestion::ingest(&conn, &request.batch_id, &request.downloads)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        /// Whether the batch was ingested, rather than skipped because it
+        /// already was.
+        ingested: bool,
+        /// The records of versions that don't exist, as `name#version`.
+        unknown: Vec<String>,
+    }
+    Ok(req.json(&R {
+        ok: true,
+        ingested: ingested.is_some(),
+        unknown: ingested.unwrap_or_default(),
+    }))
+}
+
+/// Handles the `GET /admin/read_only` route.
+pub fn read_only(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    read_only_response(req)
+}
+
+/// Handles the `PUT /admin/read_only` route.
+///
+/// Refuses publishes, yanks, owner changes and follows with a `503` on every
+/// server until the read-only mode is turned off, while the database is
+/// migrated. Reads keep working.
+pub fn enable_read_only(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    RegistryFlag::set(&*req.db_conn()?, READ_ONLY, true)?;
+    read_only_response(req)
+}
+
+/// Handles the `DELETE /admin/read_only` route.
+pub fn disable_read_only(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    RegistryFlag::set(&*req.db_conn()?, READ_ONLY, false)?;
+    read_only_response(req)
+}
+
+fn read_only_response(req: &Request) -> CargoResult<Response> {
+    #[derive(Serialize)]
+    struct R {
+        read_only: bool,
+    }
+    Ok(req.json(&R {
+        read_only: req.app().read_only(&*req.db_conn()?)?,
+    }))
+}
+
+/// Handles the `POST /admin/crates/:crate_id/sync_index` route.
+///
+/// Rewrites the index entries of a crate from its versions in the database,
+/// when they drifted apart.
+pub fn sync_index(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let entries = krate.index_entries(&conn)?;
+    let versions = entries.len();
+    let changed = git::sync_crate(req.app(), &krate.name, entries)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        changed: bool,
+        versions: usize,
+    }
+    Ok(req.json(&R {
+        ok: true,
+        changed,
+        versions,
+    }))
+}
+
+/// Handles the `GET /admin/keyword_aliases` route.
+pub fn keyword_aliases(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let conn = req.db_conn()?;
+    let keyword_aliases = KeywordAlias::all(&conn)?
+        .into_iter()
+        .map(|(alias, keyword)| alias.encodable(keyword))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        keyword_aliases: Vec<EncodableKeywordAlias>,
+    }
+    Ok(req.json(&R { keyword_aliases }))
+}
+
+/// Handles the `PUT /admin/keyword_aliases` route.
+///
+/// Makes a keyword stand for another one, e.g. `wasm` for `webassembly`. The
+/// crates that have the alias get the keyword instead, as do the crates
+/// published with it from then on.
+pub fn new_keyword_alias(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    req.admin()?;
+    let conn = req.db_conn()?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        alias: String,
+        keyword: String,
+    }
+    let request: Request = serde_json::from_str(&body)
+        .map_err(|e| human(&format_args!("invalid keyword alias: {}", e)))?;
+    let alias = KeywordAlias::create(&conn, &request.alias, &request.keyword)?;
+
+    #[derive(Serialize)]
+    struct R {
+        keyword_alias: EncodableKeywordAlias,
+    }
+    Ok(req.json(&R {
+        keyword_alias: alias.encodable(request.keyword.to_lowercase()),
+    }))
+}
+
+/// Handles the `DELETE /admin/keyword_aliases/:alias` route.
+pub fn delete_keyword_alias(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let conn = req.db_conn()?;
+    if !KeywordAlias::delete(&conn, &req.params()["alias"])? {
+        return Err(human("keyword alias not found"));
+    }
+    ok_true()
+}
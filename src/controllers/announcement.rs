@@ -0,0 +1,19 @@
+use super::prelude::*;
+
+use models::Announcement;
+use views::EncodableAnnouncement;
+
+/// Handles the `GET /announcements` route.
+pub fn list(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let announcements = Announcement::active(&conn)?
+        .into_iter()
+        .map(Announcement::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        announcements: Vec<EncodableAnnouncement>,
+    }
+    Ok(req.json(&R { announcements }))
+}
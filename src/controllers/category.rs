@@ -1,8 +1,10 @@
 use super::prelude::*;
 
-use models::Category;
-use schema::categories;
-use views::{EncodableCategory, EncodableCategoryWithSubcategories};
+use controllers::helpers::{encode_crates, Paginate};
+use models::krate::ALL_COLUMNS;
+use models::{Category, Crate, CrateBadge};
+use schema::{badges, categories, crates, crates_categories, recent_crate_downloads};
+use views::{EncodableCategory, EncodableCategoryWithSubcategories, EncodableCrate};
 
 /// Handles the `GET /categories` route.
 pub fn index(req: &mut Request) -> CargoResult<Response> {
@@ -34,17 +36,42 @@ pub fn index(req: &mut Request) -> CargoResult<Response> {
 }
 
 /// Handles the `GET /categories/:category_id` route.
+///
+/// The slugs categories had before being renamed resolve to them, the
+/// current slug is given as `meta.canonical_slug`.
 pub fn show(req: &mut Request) -> CargoResult<Response> {
     let slug = &req.params()["category_id"];
     let conn = req.db_conn()?;
-    let cat = categories::table
-        .filter(categories::slug.eq(::lower(slug)))
-        .first::<Category>(&*conn)?;
+    let cat = Category::find_by_slug(&conn, slug)?;
     let subcats = cat.subcategories(&conn)?
         .into_iter()
         .map(Category::encodable)
         .collect();
 
+    // The crates of the category and its subcategories updated since they
+    // were first published, most recent first.
+    let recently_updated = crates::table
+        .filter(
+            crates::id.eq_any(
+                crates_categories::table
+                    .select(crates_categories::crate_id)
+                    .inner_join(categories::table)
+                    .filter(
+                        categories::slug
+                            .eq(&cat.slug)
+                            .or(categories::slug.like(format!("{}::%", cat.slug))),
+                    ),
+            ),
+        )
+        .filter(crates::updated_at.ne(crates::created_at))
+        .order(crates::updated_at.desc())
+        .select(ALL_COLUMNS)
+        .limit(10)
+        .load(&*conn)?;
+
+    let featured = cat.featured_crates(&conn)?;
+
+    let canonical_slug = cat.slug.clone();
     let cat = cat.encodable();
     let cat_with_subcats = EncodableCategoryWithSubcategories {
         id: cat.id,
@@ -59,9 +86,117 @@ pub fn show(req: &mut Request) -> CargoResult<Response> {
     #[derive(Serialize)]
     struct R {
         category: EncodableCategoryWithSubcategories,
+        featured: Vec<EncodableCrate>,
+        recently_updated: Vec<EncodableCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        canonical_slug: String,
     }
     Ok(req.json(&R {
         category: cat_with_subcats,
+        featured: encode_crates(&conn, featured)?,
+        recently_updated: encode_crates(&conn, recently_updated)?,
+        meta: Meta { canonical_slug },
+    }))
+}
+
+/// Handles the `GET /categories/:category_id/crates` route.
+///
+/// Lists the crates of the category and its subcategories, sorted like the
+/// crates of the main index.
+pub fn crates(req: &mut Request) -> CargoResult<Response> {
+    let slug = &req.params()["category_id"];
+    let conn = req.db_conn()?;
+    let (offset, limit) = req.pagination(10, 100)?;
+    let query = req.query();
+    let sort = query
+        .get("sort")
+        .map(|s| &**s)
+        .unwrap_or("recent-downloads");
+
+    let cat = Category::find_by_slug(&conn, slug)?;
+
+    let mut query = crates::table
+        .left_join(recent_crate_downloads::table)
+        .select((ALL_COLUMNS, recent_crate_downloads::downloads.nullable()))
+        .filter(
+            crates::id.eq_any(
+                crates_categories::table
+                    .select(crates_categories::crate_id)
+                    .inner_join(categories::table)
+                    .filter(
+                        categories::slug
+                            .eq(&cat.slug)
+                            .or(categories::slug.like(format!("{}::%", cat.slug))),
+                    ),
+            ),
+        )
+        .into_boxed();
+
+    query = match sort {
+        "alpha" => query.order(crates::name.asc()),
+        "downloads" => query.order(crates::downloads.desc()),
+        "recently-updated" => query.order(crates::updated_at.desc()),
+        _ => query.order(recent_crate_downloads::downloads.desc().nulls_last()),
+    };
+
+    let data = query
+        .paginate(limit, offset)
+        .load::<((Crate, Option<i64>), i64)>(&*conn)?;
+    let total = data.first().map(|&(_, t)| t).unwrap_or(0);
+    let recent_downloads = data.iter()
+        .map(|&((_, s), _)| s.unwrap_or(0))
+        .collect::<Vec<_>>();
+    let crates = data.into_iter().map(|((c, _), _)| c).collect::<Vec<_>>();
+
+    let versions = Crate::max_versions(&conn, &crates)?.into_iter();
+
+    let badges = CrateBadge::belonging_to(&crates)
+        .select((badges::crate_id, badges::all_columns))
+        .load::<CrateBadge>(&conn)?
+        .grouped_by(&crates)
+        .into_iter()
+        .map(|badges| badges.into_iter().map(|cb| cb.badge).collect());
+
+    let crates = versions
+        .zip(crates)
+        .zip(recent_downloads)
+        .zip(badges)
+        .map(
+            |((((max_version, default_version), krate), recent_downloads), badges)| {
+                krate.minimal_encodable(
+                    max_version.as_ref(),
+                    &default_version,
+                    Some(badges),
+                    false,
+                    Some(recent_downloads),
+                )
+            },
+        )
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        category: EncodableCategory,
+        crates: Vec<EncodableCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+        canonical_slug: String,
+    }
+
+    let canonical_slug = cat.slug.clone();
+    Ok(req.json(&R {
+        category: cat.encodable(),
+        crates,
+        meta: Meta {
+            total,
+            canonical_slug,
+        },
     }))
 }
 
@@ -0,0 +1,181 @@
+//! Named public lists of crates curated by users, such as `awesome-async`.
+
+use serde_json;
+
+use super::prelude::*;
+
+use models::{Crate, CrateList, User};
+use schema::{crate_list_items, crate_lists, users};
+use views::EncodableCrateList;
+
+#[derive(Deserialize)]
+struct ListFields {
+    name: String,
+    description: Option<String>,
+}
+
+fn list_fields(req: &mut Request) -> CargoResult<ListFields> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    serde_json::from_str(&body).map_err(|_| human("invalid json request"))
+}
+
+/// Loads the list named by the `list_id` parameter of the request.
+fn find_list(req: &Request) -> CargoResult<CrateList> {
+    let id = req.params()["list_id"]
+        .parse::<i32>()
+        .map_err(|_| human("invalid list id"))?;
+    let conn = req.db_conn()?;
+    Ok(crate_lists::table.find(id).first(&*conn)?)
+}
+
+/// Loads the list named by the `list_id` parameter of the request, checking
+/// that it belongs to the current user.
+fn owned_list(req: &Request) -> CargoResult<CrateList> {
+    let user = req.user()?;
+    let list = find_list(req)?;
+    if list.user_id != user.id {
+        return Err(human("only the creator of a list can modify it"));
+    }
+    Ok(list)
+}
+
+fn list_response(req: &Request, list: CrateList) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let list = CrateList::encode_all(&conn, vec![list])?.pop().unwrap();
+
+    #[derive(Serialize)]
+    struct R {
+        list: EncodableCrateList,
+    }
+    Ok(req.json(&R { list }))
+}
+
+fn lists_response(req: &Request, lists: Vec<CrateList>, total: i64) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let lists = CrateList::encode_all(&conn, lists)?;
+
+    #[derive(Serialize)]
+    struct R {
+        lists: Vec<EncodableCrateList>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+    Ok(req.json(&R {
+        lists,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `PUT /me/lists` route.
+pub fn new(req: &mut Request) -> CargoResult<Response> {
+    let fields = list_fields(req)?;
+    let list = {
+        let user = req.user()?;
+        let conn = req.db_conn()?;
+        CrateList::create(
+            &conn,
+            user,
+            &fields.name,
+            fields.description.as_ref().map(|s| &**s),
+        )?
+    };
+    list_response(req, list)
+}
+
+/// Handles the `GET /users/:user_id/lists` route.
+pub fn list_for_user(req: &mut Request) -> CargoResult<Response> {
+    let (offset, limit) = req.pagination(10, 100)?;
+    let (lists, total) = {
+        let name = req.params()["user_id"].to_lowercase();
+        let conn = req.db_conn()?;
+        let user = users::table
+            .filter(::lower(users::gh_login).eq(&name))
+            .order(users::id.desc())
+            .first::<User>(&*conn)?;
+        let total = CrateList::belonging_to(&user).count().get_result(&*conn)?;
+        let lists = CrateList::belonging_to(&user)
+            .order(crate_lists::name)
+            .offset(offset)
+            .limit(limit)
+            .load::<CrateList>(&*conn)?;
+        (lists, total)
+    };
+    lists_response(req, lists, total)
+}
+
+/// Handles the `GET /crates/:crate_id/lists` route.
+pub fn list_for_crate(req: &mut Request) -> CargoResult<Response> {
+    let (offset, limit) = req.pagination(10, 100)?;
+    let (lists, total) = {
+        let conn = req.db_conn()?;
+        let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+        let total = crate_lists::table
+            .inner_join(crate_list_items::table)
+            .filter(crate_list_items::crate_id.eq(krate.id))
+            .count()
+            .get_result(&*conn)?;
+        let lists = crate_lists::table
+            .inner_join(crate_list_items::table)
+            .filter(crate_list_items::crate_id.eq(krate.id))
+            .select(crate_lists::all_columns)
+            .order(crate_lists::id)
+            .offset(offset)
+            .limit(limit)
+            .load::<CrateList>(&*conn)?;
+        (lists, total)
+    };
+    lists_response(req, lists, total)
+}
+
+/// Handles the `GET /lists/:list_id` route.
+pub fn show(req: &mut Request) -> CargoResult<Response> {
+    let list = find_list(req)?;
+    list_response(req, list)
+}
+
+/// Handles the `PUT /lists/:list_id` route.
+pub fn update(req: &mut Request) -> CargoResult<Response> {
+    let fields = list_fields(req)?;
+    let list = owned_list(req)?;
+    let list = {
+        let conn = req.db_conn()?;
+        list.update(
+            &conn,
+            &fields.name,
+            fields.description.as_ref().map(|s| &**s),
+        )?
+    };
+    list_response(req, list)
+}
+
+/// Handles the `DELETE /lists/:list_id` route.
+pub fn delete(req: &mut Request) -> CargoResult<Response> {
+    use diesel;
+
+    let list = owned_list(req)?;
+    let conn = req.db_conn()?;
+    diesel::delete(&list).execute(&*conn)?;
+    ok_true()
+}
+
+/// Handles the `PUT /lists/:list_id/crates/:crate_id` route.
+pub fn add_crate(req: &mut Request) -> CargoResult<Response> {
+    let list = owned_list(req)?;
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    list.add_crate(&conn, &krate)?;
+    ok_true()
+}
+
+/// Handles the `DELETE /lists/:list_id/crates/:crate_id` route.
+pub fn remove_crate(req: &mut Request) -> CargoResult<Response> {
+    let list = owned_list(req)?;
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    list.remove_crate(&conn, &krate)?;
+    ok_true()
+}
@@ -0,0 +1,53 @@
+//! Serves the GraphQL API, see the `graphql` module for its schema.
+
+use juniper::http::GraphQLRequest;
+use serde_json::{self, Value};
+
+use super::prelude::*;
+
+use graphql::{self, Context};
+
+/// Handles the `POST /graphql` route.
+pub fn query(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: Value = serde_json::from_str(&body)
+        .map_err(|e| human(&format_args!("invalid GraphQL request: {}", e)))?;
+    let query = request["query"].as_str().unwrap_or_default().to_string();
+    let request: GraphQLRequest = serde_json::from_value(request)
+        .map_err(|e| human(&format_args!("invalid GraphQL request: {}", e)))?;
+    execute(req, &query, &request)
+}
+
+/// Handles the `GET /graphql` route.
+///
+/// The query is sent in the `query` parameter, along with the optional
+/// `operationName` and `variables` parameters, `variables` being encoded as
+/// JSON.
+pub fn query_from_url(req: &mut Request) -> CargoResult<Response> {
+    let params = req.query();
+    let query = params
+        .get("query")
+        .ok_or_else(|| human("missing parameter: query"))?;
+    let variables = match params.get("variables") {
+        Some(variables) => Some(serde_json::from_str(variables).map_err(|e| {
+            human(&format_args!("invalid GraphQL variables: {}", e))
+        })?),
+        None => None,
+    };
+    let request = GraphQLRequest::new(
+        query.clone(),
+        params.get("operationName").cloned(),
+        variables,
+    );
+    execute(req, query, &request)
+}
+
+fn execute(req: &Request, query: &str, request: &GraphQLRequest) -> CargoResult<Response> {
+    if let Err(message) = graphql::check_depth(query) {
+        return Ok(req.json(&json!({ "errors": [{ "message": message }] })));
+    }
+    let context = Context::new(req.db_conn()?);
+    let response = request.execute(&graphql::schema(), &context);
+    Ok(req.json(&response))
+}
@@ -0,0 +1,90 @@
+//! Endpoints for load balancers, telling whether the server is up and whether
+//! it can serve requests.
+
+use std::collections::BTreeMap;
+use std::sync::TryLockError;
+
+use diesel;
+
+use super::prelude::*;
+
+use util::internal;
+
+/// Handles the `GET /healthz` route, which succeeds as long as the process
+/// handles requests.
+pub fn healthz(req: &mut Request) -> CargoResult<Response> {
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+    }
+    Ok(req.json(&R { ok: true }))
+}
+
+/// The result of one of the checks of `GET /readyz`. The route is public, so
+/// the error of a failed check is logged rather than returned.
+#[derive(Serialize, Debug)]
+struct Check {
+    ok: bool,
+}
+
+impl Check {
+    fn new(name: &str, result: CargoResult<()>) -> Check {
+        if let Err(ref e) = result {
+            error!("the {} readiness check failed: {}", name, e);
+        }
+        Check { ok: result.is_ok() }
+    }
+}
+
+/// Handles the `GET /readyz` route.
+///
+/// Checks that a database connection can be used, that the git index can be
+/// read and that the uploader can be reached. Responds with a `503` when any
+/// of them fails, along with whether each check succeeded.
+pub fn readyz(req: &mut Request) -> CargoResult<Response> {
+    let mut checks = BTreeMap::new();
+    checks.insert("database", Check::new("database", check_database(req)));
+    checks.insert("git_index", Check::new("git index", check_git_index(req)));
+    let uploader = req.app().config.uploader.check_reachable();
+    checks.insert("uploader", Check::new("uploader", uploader));
+    let ready = checks.values().all(|check| check.ok);
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        checks: BTreeMap<&'static str, Check>,
+    }
+    let mut response = req.json(&R { ok: ready, checks });
+    if !ready {
+        response.status = (503, "Service Unavailable");
+    }
+    Ok(response)
+}
+
+fn check_database(req: &mut Request) -> CargoResult<()> {
+    let conn = req.db_conn()?;
+    diesel::sql_query("SELECT 1").execute(&*conn)?;
+    Ok(())
+}
+
+/// The index is ready while it's locked, e.g. to write an entry, so the check
+/// never waits for the lock.
+fn check_git_index(req: &mut Request) -> CargoResult<()> {
+    let app = req.app();
+    match app.git_repo.try_lock() {
+        Ok(repo) => {
+            repo.head()?;
+        }
+        Err(TryLockError::WouldBlock) => {}
+        Err(TryLockError::Poisoned(_)) => {
+            return Err(internal("the lock of the git index is poisoned"));
+        }
+    }
+    if !app.git_repo_checkout.is_dir() {
+        return Err(human(&format_args!(
+            "the checkout of the git index at `{}` is missing",
+            app.git_repo_checkout.display()
+        )));
+    }
+    Ok(())
+}
@@ -1,5 +1,16 @@
-use conduit::Response;
-use util::{json_response, CargoResult};
+use std::collections::HashMap;
+
+use conduit::{Request, Response};
+use conduit_router::RequestParams;
+use diesel::prelude::*;
+
+use db::RequestTransaction;
+use middleware::app::RequestApp;
+use middleware::current_user::RequestUser;
+use models::{Crate, Rights, Version};
+use schema::users;
+use util::{human, json_response, CargoResult};
+use views::{EncodableCrate, EncodableVersion};
 
 pub mod pagination;
 
@@ -13,3 +24,85 @@ pub fn ok_true() -> CargoResult<Response> {
 
     Ok(json_response(&R { ok: true }))
 }
+
+/// Encodes crates for the lists of the front page, with their versions but
+/// without badges or download counts.
+pub fn encode_crates(conn: &PgConnection, krates: Vec<Crate>) -> CargoResult<Vec<EncodableCrate>> {
+    Ok(Crate::max_versions(conn, &krates)?
+        .into_iter()
+        .zip(krates)
+        .map(|((max_version, default_version), krate)| {
+            krate.minimal_encodable(max_version.as_ref(), &default_version, None, false, None)
+        })
+        .collect())
+}
+
+/// Loads the crate named by the `crate_id` parameter of the request, checking
+/// that the current user is one of its owners. `action` describes what the
+/// owners are allowed to do in the error sent to other users.
+pub fn owned_crate(req: &Request, action: &str) -> CargoResult<Crate> {
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    let owners = krate.owners(&conn)?;
+    if user.rights(req.app(), &owners)? < Rights::Full {
+        return Err(human(&format_args!(
+            "only owners have permission to {}",
+            action
+        )));
+    }
+    Ok(krate)
+}
+
+/// Encodes the versions of a crate, along with who published them and how
+/// when the current user is one of its owners or an administrator.
+pub fn encode_versions(
+    req: &Request,
+    conn: &PgConnection,
+    krate: &Crate,
+    versions: Vec<Version>,
+) -> CargoResult<Vec<EncodableVersion>> {
+    if !sees_publishers(req, conn, krate)? {
+        return Ok(versions
+            .into_iter()
+            .map(|v| v.encodable(&krate.name))
+            .collect());
+    }
+
+    let ids = versions
+        .iter()
+        .filter_map(|v| v.published_by)
+        .collect::<Vec<_>>();
+    let logins = users::table
+        .filter(users::id.eq_any(ids))
+        .select((users::id, users::gh_login))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    Ok(versions
+        .into_iter()
+        .map(|v| {
+            let published_by = v.published_by.and_then(|id| logins.get(&id).cloned());
+            let published_with = v.published_with.clone();
+            EncodableVersion {
+                published_by,
+                published_with,
+                ..v.encodable(&krate.name)
+            }
+        })
+        .collect())
+}
+
+/// Whether the current user can see who published the versions of the crate:
+/// its owners and the administrators can.
+fn sees_publishers(req: &Request, conn: &PgConnection, krate: &Crate) -> CargoResult<bool> {
+    let user = match req.user() {
+        Ok(user) => user,
+        Err(_) => return Ok(false),
+    };
+    if req.app().config.admin_github_ids.contains(&user.gh_id) {
+        return Ok(true);
+    }
+    let owners = krate.owners(conn)?;
+    Ok(user.rights(req.app(), &owners)? == Rights::Full)
+}
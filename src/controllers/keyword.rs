@@ -1,8 +1,12 @@
 use super::prelude::*;
 
-use controllers::helpers::Paginate;
-use models::Keyword;
-use views::EncodableKeyword;
+use serde_json;
+
+use controllers::helpers::{encode_crates, Paginate};
+use models::krate::ALL_COLUMNS;
+use models::{Crate, CrateKeyword, Keyword, Rights};
+use views::krate_publish::KeywordList;
+use views::{EncodableCrate, EncodableKeyword, EncodableTrendingKeyword};
 
 /// Handles the `GET /keywords` route.
 pub fn index(req: &mut Request) -> CargoResult<Response> {
@@ -45,18 +49,106 @@ pub fn index(req: &mut Request) -> CargoResult<Response> {
     }))
 }
 
+/// Handles the `GET /keywords/trending` route.
+///
+/// Lists the keywords whose number of crates grew the most relative to their
+/// number of crates 30 days ago, from the daily snapshots of the
+/// `snapshot-keywords` job.
+pub fn trending(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let (offset, limit) = req.pagination(10, 100)?;
+    let keywords = Keyword::trending(&conn, limit, offset)?
+        .into_iter()
+        .map(|(keyword, previous_crates_cnt)| EncodableTrendingKeyword {
+            keyword: keyword.encodable(),
+            previous_crates_cnt,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        keywords: Vec<EncodableTrendingKeyword>,
+    }
+    Ok(req.json(&R { keywords }))
+}
+
 /// Handles the `GET /keywords/:keyword_id` route.
 pub fn show(req: &mut Request) -> CargoResult<Response> {
+    use schema::{crates, crates_keywords};
+
     let name = &req.params()["keyword_id"];
     let conn = req.db_conn()?;
 
     let kw = Keyword::find_by_keyword(&conn, name)?;
 
+    // The crates with the keyword updated since they were first published,
+    // most recent first.
+    let recently_updated = crates::table
+        .filter(
+            crates::id.eq_any(
+                crates_keywords::table
+                    .select(crates_keywords::crate_id)
+                    .filter(crates_keywords::keyword_id.eq(kw.id)),
+            ),
+        )
+        .filter(crates::updated_at.ne(crates::created_at))
+        .order(crates::updated_at.desc())
+        .select(ALL_COLUMNS)
+        .limit(10)
+        .load(&*conn)?;
+
     #[derive(Serialize)]
     struct R {
         keyword: EncodableKeyword,
+        recently_updated: Vec<EncodableCrate>,
     }
     Ok(req.json(&R {
         keyword: kw.encodable(),
+        recently_updated: encode_crates(&conn, recently_updated)?,
     }))
 }
+
+/// Handles the `PUT /crates/:crate_id/keywords` route.
+///
+/// Replaces the keywords of a crate without publishing a new version. The
+/// keywords are validated the same way as when publishing.
+pub fn update_crate(req: &mut Request) -> CargoResult<Response> {
+    use schema::keywords;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    let owners = krate.owners(&conn)?;
+    if user.rights(req.app(), &owners)? < Rights::Publish {
+        return Err(human("only owners have permission to modify keywords"));
+    }
+
+    #[derive(Deserialize)]
+    struct Request {
+        keywords: KeywordList,
+    }
+    let request: Request = serde_json::from_str(&body)
+        .map_err(|e| human(&format_args!("invalid keywords: {}", e)))?;
+    let new_keywords = request.keywords.0.iter().map(|kw| &***kw).collect::<Vec<_>>();
+
+    // The full text search index of the crate is updated by the triggers on
+    // `crates_keywords`
+    Keyword::update_crate(&conn, &krate, &new_keywords)?;
+
+    let kws = CrateKeyword::belonging_to(&krate)
+        .inner_join(keywords::table)
+        .select(keywords::all_columns)
+        .load::<Keyword>(&*conn)?
+        .into_iter()
+        .map(Keyword::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        keywords: Vec<EncodableKeyword>,
+    }
+    Ok(req.json(&R { keywords: kws }))
+}
@@ -0,0 +1,64 @@
+//! Endpoints for the owners of a crate to review where its versions were
+//! published from and how they changed its metadata
+
+use controllers::helpers::owned_crate;
+use controllers::prelude::*;
+use models::{CrateMetadataChange, Version};
+use schema::*;
+use views::{EncodableCrateMetadataChange, EncodableVersionPublish};
+
+/// Handles the `GET /crates/:crate_id/audit` route.
+///
+/// Lists the versions of the crate, the latest first, with the user who
+/// published each of them and the cargo version and CI service they were
+/// published with.
+pub fn audit(req: &mut Request) -> CargoResult<Response> {
+    let krate = owned_crate(req, "audit the publishes of the crate")?;
+    let conn = req.db_conn()?;
+    let versions = Version::belonging_to(&krate)
+        .left_join(users::table)
+        .select((versions::all_columns, users::gh_login.nullable()))
+        .order(versions::created_at.desc())
+        .load::<(Version, Option<String>)>(&*conn)?
+        .into_iter()
+        .map(|(version, published_by)| EncodableVersionPublish {
+            num: version.num.to_string(),
+            published_at: version.created_at,
+            published_by,
+            published_with: version.published_with,
+            cargo_version: version.cargo_version,
+            ci_provider: version.ci_provider,
+            yanked: version.yanked,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        versions: Vec<EncodableVersionPublish>,
+    }
+    Ok(req.json(&R { versions }))
+}
+
+/// Handles the `GET /crates/:crate_id/metadata_changes` route.
+///
+/// Lists the changes of the keywords, categories and other metadata of the
+/// crate made by its publishes, the latest first, which helps explaining
+/// changes in how the crate ranks in searches.
+pub fn metadata_changes(req: &mut Request) -> CargoResult<Response> {
+    let krate = owned_crate(req, "review the metadata changes of the crate")?;
+    let conn = req.db_conn()?;
+    let changes = CrateMetadataChange::belonging_to(&krate)
+        .left_join(versions::table)
+        .select((crate_metadata_changes::all_columns, versions::num.nullable()))
+        .order(crate_metadata_changes::id.desc())
+        .load::<(CrateMetadataChange, Option<String>)>(&*conn)?
+        .into_iter()
+        .map(|(change, version)| change.encodable(version))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        changes: Vec<EncodableCrateMetadataChange>,
+    }
+    Ok(req.json(&R { changes }))
+}
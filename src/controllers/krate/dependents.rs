@@ -0,0 +1,87 @@
+//! Endpoints listing the crates that recently started depending on a crate,
+//! as JSON and as an RSS feed
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use chrono::{DateTime, Utc};
+
+use controllers::prelude::*;
+use models::{Crate, CrateDependent};
+use views::EncodableNewDependent;
+
+/// Handles the `GET /crates/:crate_id/new_dependents` route.
+///
+/// Lists the crates whose first version depending on the crate was published
+/// in the last 30 days, the most recent first.
+pub fn new_dependents(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    let dependents = CrateDependent::recent(&conn, &krate)?
+        .into_iter()
+        .map(|(first_seen_at, dependent)| EncodableNewDependent {
+            name: dependent.name,
+            description: dependent.description,
+            first_seen_at,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        dependents: Vec<EncodableNewDependent>,
+    }
+    Ok(req.json(&R { dependents }))
+}
+
+/// Handles the `GET /crates/:crate_id/new_dependents.rss` route, the same list
+/// as an RSS feed.
+pub fn new_dependents_rss(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    let dependents = CrateDependent::recent(&conn, &krate)?;
+
+    let mut rss = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <rss version=\"2.0\"><channel>\
+         <title>New dependents of {name}</title>\
+         <link>https://crates.io/crates/{name}/reverse_dependencies</link>\
+         <description>Crates that started depending on {name} in the last 30 days\
+         </description>",
+        name = escape(&krate.name),
+    );
+    for (first_seen_at, dependent) in dependents {
+        let published = DateTime::<Utc>::from_utc(first_seen_at, Utc);
+        rss.push_str(&format!(
+            "<item><title>{dependent} depends on {name}</title>\
+             <link>https://crates.io/crates/{dependent}</link>\
+             <guid isPermaLink=\"false\">{name}/{dependent}</guid>\
+             <pubDate>{date}</pubDate>\
+             <description>{description}</description></item>",
+            dependent = escape(&dependent.name),
+            name = escape(&krate.name),
+            date = published.to_rfc2822(),
+            description = escape(dependent.description.as_ref().map_or("", |s| &**s)),
+        ));
+    }
+    rss.push_str("</channel></rss>\n");
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["application/rss+xml; charset=utf-8".to_string()],
+    );
+    headers.insert("Content-Length".to_string(), vec![rss.len().to_string()]);
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(rss.into_bytes())),
+    })
+}
+
+/// Escapes the text of an XML element.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
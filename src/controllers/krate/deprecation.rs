@@ -0,0 +1,66 @@
+//! Endpoints for deprecating a crate as a whole, and telling how it's
+//! maintained
+
+use serde_json;
+
+use controllers::helpers::owned_crate;
+use controllers::prelude::*;
+
+const DEPRECATE: &str = "deprecate the crate";
+const MAINTAIN: &str = "set how the crate is maintained";
+
+/// Handles the `PUT /crates/:crate_id/deprecate` route.
+///
+/// Marks the crate as no longer maintained, with a message and the name of
+/// the crate to use instead. Its versions stay available, the deprecation is
+/// shown with the crate and in search results.
+pub fn deprecate(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        message: String,
+        alternative: Option<String>,
+    }
+    let request: Request = serde_json::from_str(&body)
+        .map_err(|e| human(&format_args!("invalid deprecation: {}", e)))?;
+
+    let krate = owned_crate(req, DEPRECATE)?;
+    let conn = req.db_conn()?;
+    krate.deprecate(
+        &conn,
+        &request.message,
+        request.alternative.as_ref().map(|s| &**s),
+    )?;
+    ok_true()
+}
+
+/// Handles the `DELETE /crates/:crate_id/deprecate` route.
+pub fn undeprecate(req: &mut Request) -> CargoResult<Response> {
+    let krate = owned_crate(req, DEPRECATE)?;
+    let conn = req.db_conn()?;
+    krate.undeprecate(&conn)?;
+    ok_true()
+}
+
+/// Handles the `PUT /crates/:crate_id/maintenance` route.
+///
+/// Sets the maintenance status of the crate, e.g. `looking-for-maintainer`
+/// when its owners want to hand it over, or clears it when `status` is null.
+pub fn set_maintenance_status(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    #[derive(Deserialize)]
+    struct Request {
+        status: Option<String>,
+    }
+    let request: Request = serde_json::from_str(&body)
+        .map_err(|e| human(&format_args!("invalid maintenance status: {}", e)))?;
+
+    let krate = owned_crate(req, MAINTAIN)?;
+    let conn = req.db_conn()?;
+    krate.set_maintenance_status(&conn, request.status.as_ref().map(|s| &**s))?;
+    ok_true()
+}
@@ -4,21 +4,33 @@
 //! download counts are located in `krate::downloads`.
 
 use std::cmp;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::sync::Arc;
 
+use chrono::{Duration, NaiveDate, Utc};
+
+use app::App;
 use controllers::prelude::*;
 
-use models::{Crate, Version, VersionDownload};
-use schema::version_downloads;
-use views::EncodableVersionDownload;
+use models::{Crate, CrateDownload, DownloadBreakdown, Version, VersionDownload};
+use schema::{crate_downloads, version_downloads, versions};
+use views::{EncodableCrateDownload, EncodableVersionDownload};
 
-use models::krate::to_char;
+use models::krate::{to_char, VersionLineDownloads};
 
 /// Handles the `GET /crates/:crate_id/downloads` route.
+///
+/// Returns the downloads of the last 90 days, or only those since the date
+/// given by the optional `since` parameter so that clients polling it don't
+/// fetch the same days again.
 pub fn downloads(req: &mut Request) -> CargoResult<Response> {
     use diesel::dsl::*;
     use diesel::sql_types::BigInt;
 
     let crate_name = &req.params()["crate_id"];
+    let start = Utc::today().naive_utc() - Duration::days(89);
+    let start = since(req)?.map_or(start, |since| cmp::max(start, since));
     let conn = req.db_conn()?;
     let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
 
@@ -27,7 +39,7 @@ pub fn downloads(req: &mut Request) -> CargoResult<Response> {
     let (latest_five, rest) = versions.split_at(cmp::min(5, versions.len()));
 
     let downloads = VersionDownload::belonging_to(latest_five)
-        .filter(version_downloads::date.gt(date(now - 90.days())))
+        .filter(version_downloads::date.ge(start))
         .order(version_downloads::date.asc())
         .load(&*conn)?
         .into_iter()
@@ -40,7 +52,7 @@ pub fn downloads(req: &mut Request) -> CargoResult<Response> {
             to_char(version_downloads::date, "YYYY-MM-DD"),
             sum_downloads,
         ))
-        .filter(version_downloads::date.gt(date(now - 90.days())))
+        .filter(version_downloads::date.ge(start))
         .group_by(version_downloads::date)
         .order(version_downloads::date.asc())
         .load::<ExtraDownload>(&*conn)?;
@@ -67,3 +79,205 @@ pub fn downloads(req: &mut Request) -> CargoResult<Response> {
         meta,
     }))
 }
+
+/// Handles the `GET /crates/:crate_id/downloads/total` route.
+///
+/// Returns the daily downloads of the crate as a whole over the last 90 days,
+/// so that clients don't have to sum the downloads of every version.
+pub fn downloads_total(req: &mut Request) -> CargoResult<Response> {
+    use diesel::dsl::*;
+
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+
+    let downloads = CrateDownload::belonging_to(&krate)
+        .filter(crate_downloads::date.gt(date(now - 90.days())))
+        .order(crate_downloads::date.asc())
+        .load(&*conn)?
+        .into_iter()
+        .map(CrateDownload::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crate_downloads: Vec<EncodableCrateDownload>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i32,
+    }
+    Ok(req.json(&R {
+        crate_downloads: downloads,
+        meta: Meta {
+            total: krate.downloads,
+        },
+    }))
+}
+
+/// Handles the `GET /crates/:crate_id/downloads/by_semver` route.
+///
+/// Sums the downloads of the crate per major version line, or per major.minor
+/// line with `?group=minor`, to tell how much each release line is used.
+pub fn downloads_by_semver(req: &mut Request) -> CargoResult<Response> {
+    let by_minor = match req.query().get("group").map(|s| &**s) {
+        None | Some("major") => false,
+        Some("minor") => true,
+        Some(group) => {
+            return Err(human(&format_args!(
+                "`{}` is not a valid group, expected `major` or `minor`",
+                group
+            )))
+        }
+    };
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let lines = krate.downloads_by_semver(&conn, by_minor)?;
+
+    #[derive(Serialize)]
+    struct R {
+        lines: Vec<VersionLineDownloads>,
+    }
+    Ok(req.json(&R { lines }))
+}
+
+/// Handles the `GET /crates/:crate_id/downloads/breakdown` route.
+///
+/// Sums the downloads of the last 90 days of each version per cargo version
+/// and country, to tell which cargo versions still download old releases. They
+/// are only counted when the registry enables `Config::download_breakdown`.
+/// The buckets with few downloads are merged, see `DownloadBreakdown`.
+pub fn downloads_breakdown(req: &mut Request) -> CargoResult<Response> {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let breakdown = DownloadBreakdown::for_crate(&conn, &krate)?;
+
+    #[derive(Serialize)]
+    struct R {
+        breakdown: Vec<DownloadBreakdown>,
+    }
+    Ok(req.json(&R { breakdown }))
+}
+
+/// Parses the optional `since` parameter, the first day of downloads returned.
+fn since(req: &Request) -> CargoResult<Option<NaiveDate>> {
+    match req.query().get("since") {
+        Some(since) => NaiveDate::parse_from_str(since, "%F")
+            .map(Some)
+            .map_err(|_| human("invalid `since` date, expected YYYY-MM-DD")),
+        None => Ok(None),
+    }
+}
+
+/// How many rows of `version_downloads` are loaded at once when exporting them.
+const CSV_BATCH_SIZE: i64 = 1000;
+
+/// Handles the `GET /crates/:crate_id/downloads.csv` route.
+///
+/// Exports the daily downloads of every version of the crate as CSV, starting
+/// at the date given by the optional `since` parameter. The rows are loaded
+/// in batches while the response is written, so that the whole export is
+/// never kept in memory.
+pub fn downloads_csv(req: &mut Request) -> CargoResult<Response> {
+    let crate_name = &req.params()["crate_id"];
+    let since = since(req)?;
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["text/csv; charset=utf-8".to_string()],
+    );
+    headers.insert(
+        "Content-Disposition".to_string(),
+        vec![format!(
+            "attachment; filename=\"{}-downloads.csv\"",
+            krate.name
+        )],
+    );
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(DownloadsCsv {
+            app: Arc::clone(req.app()),
+            crate_id: krate.id,
+            since,
+            last: None,
+            buf: b"date,version,downloads\n".to_vec(),
+            pos: 0,
+            done: false,
+        }),
+    })
+}
+
+/// The body of the CSV export of a crate's downloads, loading the next batch
+/// of rows whenever the previous one was read.
+struct DownloadsCsv {
+    app: Arc<App>,
+    crate_id: i32,
+    since: Option<NaiveDate>,
+    /// The date and id of the last row that was loaded.
+    last: Option<(NaiveDate, i32)>,
+    buf: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl DownloadsCsv {
+    fn next_batch(&mut self) -> CargoResult<()> {
+        let conn = self.app.diesel_database.get()?;
+        let mut query = version_downloads::table
+            .inner_join(versions::table)
+            .select((
+                version_downloads::id,
+                version_downloads::date,
+                versions::num,
+                version_downloads::downloads,
+            ))
+            .filter(versions::crate_id.eq(self.crate_id))
+            .order((version_downloads::date, version_downloads::id))
+            .limit(CSV_BATCH_SIZE)
+            .into_boxed();
+        if let Some(since) = self.since {
+            query = query.filter(version_downloads::date.ge(since));
+        }
+        if let Some((date, id)) = self.last {
+            query = query.filter(
+                version_downloads::date
+                    .gt(date)
+                    .or(version_downloads::date.eq(date).and(version_downloads::id.gt(id))),
+            );
+        }
+        let rows = query.load::<(i32, NaiveDate, String, i32)>(&*conn)?;
+
+        self.done = (rows.len() as i64) < CSV_BATCH_SIZE;
+        self.buf.clear();
+        self.pos = 0;
+        for &(id, date, ref num, downloads) in &rows {
+            self.buf
+                .extend(format!("{},{},{}\n", date.format("%F"), num, downloads).bytes());
+            self.last = Some((date, id));
+        }
+        Ok(())
+    }
+}
+
+impl Read for DownloadsCsv {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.next_batch()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        }
+        let n = cmp::min(out.len(), self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,104 @@
+//! Endpoints for the logo the owners of a crate can show with it
+
+use std::io::Read;
+use std::sync::Arc;
+
+use controllers::helpers::owned_crate;
+use controllers::prelude::*;
+use models::Crate;
+
+const CHANGE_LOGO: &str = "change the logo of the crate";
+
+/// The largest logo accepted, in bytes.
+const MAX_LOGO_SIZE: u64 = 64 * 1024;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Handles the `GET /crates/:crate_id/logo` route.
+pub fn show(req: &mut Request) -> CargoResult<Response> {
+    let krate = {
+        let conn = req.db_conn()?;
+        Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?
+    };
+    let redirect_url = krate
+        .logo_path
+        .and_then(|path| req.app().config.uploader.logo_location(&path))
+        .ok_or_else(|| human("crate logo not found"))?;
+
+    if req.wants_json() {
+        #[derive(Serialize)]
+        struct R {
+            url: String,
+        }
+        Ok(req.json(&R { url: redirect_url }))
+    } else {
+        Ok(req.redirect(redirect_url))
+    }
+}
+
+/// Handles the `PUT /crates/:crate_id/logo` route.
+///
+/// The body is the logo itself, a PNG image of at most 64KiB given with its
+/// `Content-Type`. SVG logos aren't accepted: they can run scripts in many
+/// ways, and the `Local` uploader serves them from the origin of the registry.
+pub fn upload(req: &mut Request) -> CargoResult<Response> {
+    let content_type = req.headers()
+        .find("Content-Type")
+        .and_then(|values| values.first().map(|value| value.to_string()))
+        .unwrap_or_default();
+    let mut logo = Vec::new();
+    req.body().take(MAX_LOGO_SIZE + 1).read_to_end(&mut logo)?;
+    let extension = validate(&content_type, &logo)?;
+
+    let krate = owned_crate(req, CHANGE_LOGO)?;
+    let app = Arc::clone(req.app());
+    let path = app.config
+        .uploader
+        .upload_logo(&app, &krate, &logo, &content_type, extension)?;
+    let conn = req.db_conn()?;
+    krate.set_logo_path(&conn, path.as_ref().map(|s| &**s))?;
+    ok_true()
+}
+
+/// Handles the `DELETE /crates/:crate_id/logo` route.
+///
+/// The crate is no longer shown with a logo. The uploaded file is kept, like
+/// the files of yanked versions.
+pub fn delete(req: &mut Request) -> CargoResult<Response> {
+    let krate = owned_crate(req, CHANGE_LOGO)?;
+    let conn = req.db_conn()?;
+    krate.set_logo_path(&conn, None)?;
+    ok_true()
+}
+
+/// Checks that the logo is a small PNG image, returning the extension to store it with.
+fn validate(content_type: &str, logo: &[u8]) -> CargoResult<&'static str> {
+    if logo.len() as u64 > MAX_LOGO_SIZE {
+        return Err(human(&format_args!(
+            "the logo is too large, the maximum size is {} bytes",
+            MAX_LOGO_SIZE
+        )));
+    }
+    if content_type != "image/png" {
+        return Err(human("the logo must be a PNG image, given as `image/png`"));
+    }
+    if !logo.starts_with(PNG_SIGNATURE) {
+        return Err(human("the logo isn't a valid PNG image"));
+    }
+    Ok("png")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+
+    #[test]
+    fn validates_logos() {
+        assert_eq!(validate("image/png", b"\x89PNG\r\n\x1a\nrest").unwrap(), "png");
+        assert!(validate("image/png", b"GIF89a").is_err());
+        assert!(validate("image/svg+xml", b"<svg></svg>").is_err());
+        assert!(validate("image/svg+xml", b"<svg onload=\"alert(1)\"></svg>").is_err());
+        assert!(validate("image/gif", b"GIF89a").is_err());
+        assert!(validate("image/png", &vec![0; 64 * 1024 + 1]).is_err());
+    }
+}
@@ -4,38 +4,51 @@
 //! index or cached metadata which was extracted (client side) from the
 //! `Cargo.toml` file.
 
+use diesel::dsl::sql;
+use diesel::sql_types::{Array, Text};
+use semver;
+use serde_json;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use controllers::helpers::{encode_crates, encode_versions, Paginate};
 use controllers::prelude::*;
-use models::{Category, Crate, CrateCategory, CrateDownload, CrateKeyword, Keyword, Version};
+use controllers::spotlight::todays_spotlight;
+use middleware::CachePolicy;
+use models::{Category, Crate, CrateCategory, CrateDownload, CrateKeyword, CrateTransfer,
+             DependencyKind, Keyword, Version};
 use schema::*;
-use views::{EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword,
-            EncodableVersion};
+use views::{EncodableCategory, EncodableCrate, EncodableCrateSpotlight, EncodableCrateStatus,
+            EncodableDependency, EncodableKeyword, EncodableVersion};
+
+use models::krate::{RequirementDependents, ALL_COLUMNS};
 
-use models::krate::ALL_COLUMNS;
+/// How long the `/summary` payload is served from the cache, in seconds.
+const SUMMARY_CACHE_TTL: u64 = 60;
 
 /// Handles the `GET /summary` route.
+///
+/// The payload is cached in memory for `SUMMARY_CACHE_TTL` seconds, or until a
+/// new version is published.
 pub fn summary(req: &mut Request) -> CargoResult<Response> {
     use schema::crates::dsl::*;
 
+    {
+        let cache = req.app().summary_cache.lock().unwrap();
+        if let Some((computed_at, ref summary)) = *cache {
+            if computed_at.elapsed() < Duration::from_secs(SUMMARY_CACHE_TTL) {
+                return Ok(req.json(summary));
+            }
+        }
+    }
+
     let conn = req.db_conn()?;
     let num_crates = crates.count().get_result(&*conn)?;
     let num_downloads = metadata::table
         .select(metadata::total_downloads)
         .get_result(&*conn)?;
 
-    let encode_crates = |krates: Vec<Crate>| -> CargoResult<Vec<_>> {
-        Version::belonging_to(&krates)
-            .filter(versions::yanked.eq(false))
-            .load::<Version>(&*conn)?
-            .grouped_by(&krates)
-            .into_iter()
-            .map(|versions| Version::max(versions.into_iter().map(|v| v.num)))
-            .zip(krates)
-            .map(|(max_version, krate)| {
-                Ok(krate.minimal_encodable(&max_version, None, false, None))
-            })
-            .collect()
-    };
-
     let new_crates = crates
         .order(created_at.desc())
         .select(ALL_COLUMNS)
@@ -83,73 +96,198 @@ pub fn summary(req: &mut Request) -> CargoResult<Response> {
         just_updated: Vec<EncodableCrate>,
         popular_keywords: Vec<EncodableKeyword>,
         popular_categories: Vec<EncodableCategory>,
+        spotlight: Option<EncodableCrateSpotlight>,
     }
-    Ok(req.json(&R {
+    let summary = serde_json::to_value(&R {
         num_downloads,
         num_crates,
-        new_crates: encode_crates(new_crates)?,
-        most_downloaded: encode_crates(most_downloaded)?,
-        most_recently_downloaded: encode_crates(most_recently_downloaded)?,
-        just_updated: encode_crates(just_updated)?,
+        new_crates: encode_crates(&conn, new_crates)?,
+        most_downloaded: encode_crates(&conn, most_downloaded)?,
+        most_recently_downloaded: encode_crates(&conn, most_recently_downloaded)?,
+        just_updated: encode_crates(&conn, just_updated)?,
         popular_keywords,
         popular_categories,
-    }))
+        spotlight: todays_spotlight(&conn)?,
+    })?;
+    *req.app().summary_cache.lock().unwrap() = Some((Instant::now(), summary.clone()));
+    Ok(req.json(&summary))
+}
+
+/// The optional parts of the `GET /crates/:crate_id` payload, selected with
+/// the `include` query parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ShowIncludes {
+    versions: bool,
+    keywords: bool,
+    categories: bool,
+    badges: bool,
+    downloads: bool,
+}
+
+impl ShowIncludes {
+    const ALL: ShowIncludes = ShowIncludes {
+        versions: true,
+        keywords: true,
+        categories: true,
+        badges: true,
+        downloads: true,
+    };
+
+    /// Parses a comma separated list of parts, `full` standing for all of them.
+    fn parse(s: &str) -> CargoResult<ShowIncludes> {
+        let mut includes = ShowIncludes {
+            versions: false,
+            keywords: false,
+            categories: false,
+            badges: false,
+            downloads: false,
+        };
+        for part in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match part {
+                "versions" => includes.versions = true,
+                "keywords" => includes.keywords = true,
+                "categories" => includes.categories = true,
+                "badges" => includes.badges = true,
+                "downloads" => includes.downloads = true,
+                "full" => return Ok(ShowIncludes::ALL),
+                _ => {
+                    return Err(human(&format_args!(
+                        "invalid include `{}`, expected one of versions, keywords, \
+                         categories, badges, downloads or full",
+                        part
+                    )))
+                }
+            }
+        }
+        Ok(includes)
+    }
 }
 
 /// Handles the `GET /crates/:crate_id` route.
+///
+/// The `include` query parameter lists the parts of the payload to load, all
+/// of them are loaded when it's missing.
 pub fn show(req: &mut Request) -> CargoResult<Response> {
     use diesel::dsl::*;
 
+    req.mut_extensions().insert(CachePolicy::Short);
+
+    let includes = match req.query().get("include") {
+        Some(include) => ShowIncludes::parse(include)?,
+        None => ShowIncludes::ALL,
+    };
     let name = &req.params()["crate_id"];
     let conn = req.db_conn()?;
     let krate = Crate::by_name(name).first::<Crate>(&*conn)?;
 
-    let mut versions = Version::belonging_to(&krate).load::<Version>(&*conn)?;
-    versions.sort_by(|a, b| b.num.cmp(&a.num));
-    let ids = versions.iter().map(|v| v.id).collect();
+    let versions = if includes.versions {
+        let mut versions = Version::belonging_to(&krate).load::<Version>(&*conn)?;
+        versions.sort_by(|a, b| b.num.cmp(&a.num));
+        Some(versions)
+    } else {
+        None
+    };
+    let ids = versions
+        .as_ref()
+        .map(|versions| versions.iter().map(|v| v.id).collect());
+    let versions = match versions {
+        Some(versions) => Some(encode_versions(req, &conn, &krate, versions)?),
+        None => None,
+    };
 
-    let kws = CrateKeyword::belonging_to(&krate)
-        .inner_join(keywords::table)
-        .select(keywords::all_columns)
-        .load(&*conn)?;
-    let cats = CrateCategory::belonging_to(&krate)
-        .inner_join(categories::table)
-        .select(categories::all_columns)
-        .load(&*conn)?;
-    let recent_downloads = CrateDownload::belonging_to(&krate)
-        .filter(crate_downloads::date.gt(date(now - 90.days())))
-        .select(sum(crate_downloads::downloads))
-        .get_result(&*conn)?;
+    let kws = if includes.keywords {
+        Some(
+            CrateKeyword::belonging_to(&krate)
+                .inner_join(keywords::table)
+                .select(keywords::all_columns)
+                .load::<Keyword>(&*conn)?,
+        )
+    } else {
+        None
+    };
+    let cats = if includes.categories {
+        Some(
+            CrateCategory::belonging_to(&krate)
+                .inner_join(categories::table)
+                .select(categories::all_columns)
+                .load::<Category>(&*conn)?,
+        )
+    } else {
+        None
+    };
+    let recent_downloads = if includes.downloads {
+        CrateDownload::belonging_to(&krate)
+            .filter(crate_downloads::date.gt(date(now - 90.days())))
+            .select(sum(crate_downloads::downloads))
+            .get_result(&*conn)?
+    } else {
+        None
+    };
 
-    let badges = badges::table
-        .filter(badges::crate_id.eq(krate.id))
-        .load(&*conn)?;
-    let max_version = krate.max_version(&conn)?;
+    let badges = if includes.badges {
+        Some(
+            badges::table
+                .filter(badges::crate_id.eq(krate.id))
+                .load(&*conn)?,
+        )
+    } else {
+        None
+    };
+    let (max_version, default_version) = match krate.cached_versions() {
+        Some((max_version, default_version)) => (Some(max_version), default_version),
+        None => (krate.max_version(&conn)?, krate.default_version(&conn)?),
+    };
+    let dependency_freshness = crate_freshness::table
+        .find(krate.id)
+        .select(crate_freshness::freshness)
+        .first(&*conn)
+        .optional()?;
+    let transfer = CrateTransfer::find(&conn, &krate)?;
+    let stale = krate.is_stale(&conn)?;
 
     #[derive(Serialize)]
     struct R {
         #[serde(rename = "crate")]
         krate: EncodableCrate,
-        versions: Vec<EncodableVersion>,
-        keywords: Vec<EncodableKeyword>,
-        categories: Vec<EncodableCategory>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        versions: Option<Vec<EncodableVersion>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        keywords: Option<Vec<EncodableKeyword>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        categories: Option<Vec<EncodableCategory>>,
+        /// The current name of the crate, when it was requested by a former name.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        moved_to: Option<String>,
+        /// The name of the crate as published, which may differ from the
+        /// requested name in case or in `-` and `_`.
+        canonical_name: String,
+        /// Whether the name of the crate is contested.
+        status: EncodableCrateStatus,
+        /// Whether the crate had no release and no action of its owners for
+        /// a long time, see `Crate::update_stale`.
+        stale: bool,
     }
+    // `crate.exact_match` tells clients whether the requested name is a variant
+    let exact_match = *name == krate.name;
     Ok(req.json(&R {
         krate: krate.clone().encodable(
-            &max_version,
-            Some(ids),
-            Some(&kws),
-            Some(&cats),
-            Some(badges),
-            false,
+            max_version.as_ref(),
+            &default_version,
+            ids,
+            kws.as_ref().map(|kws| &kws[..]),
+            cats.as_ref().map(|cats| &cats[..]),
+            badges,
+            exact_match,
             recent_downloads,
+            dependency_freshness,
         ),
-        versions: versions
-            .into_iter()
-            .map(|v| v.encodable(&krate.name))
-            .collect(),
-        keywords: kws.into_iter().map(|k| k.encodable()).collect(),
-        categories: cats.into_iter().map(|k| k.encodable()).collect(),
+        versions,
+        keywords: kws.map(|kws| kws.into_iter().map(|k| k.encodable()).collect()),
+        categories: cats.map(|cats| cats.into_iter().map(|k| k.encodable()).collect()),
+        moved_to: krate.moved_to(name).map(String::from),
+        canonical_name: krate.name.clone(),
+        status: CrateTransfer::encodable_status(transfer),
+        stale,
     }))
 }
 
@@ -176,27 +314,147 @@ pub fn readme(req: &mut Request) -> CargoResult<Response> {
 }
 
 /// Handles the `GET /crates/:crate_id/versions` route.
+///
+/// Versions are sorted newest first, by semver precedence or with
+/// `?sort=date` by publication date. `?include_yanked=false` leaves the
+/// yanked versions out. All the versions are returned unless `page` or
+/// `per_page` is given.
 // FIXME: Not sure why this is necessary since /crates/:crate_id returns
 // this information already, but ember is definitely requesting it
 pub fn versions(req: &mut Request) -> CargoResult<Response> {
     let crate_name = &req.params()["crate_id"];
+    let params = req.query();
+    let include_yanked = match params.get("include_yanked").map(|s| &**s) {
+        None | Some("true") => true,
+        Some("false") => false,
+        Some(_) => return Err(human("`include_yanked` must be `true` or `false`")),
+    };
+
     let conn = req.db_conn()?;
     let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
-    let mut versions = Version::belonging_to(&krate).load::<Version>(&*conn)?;
-    versions.sort_by(|a, b| b.num.cmp(&a.num));
-    let versions = versions
-        .into_iter()
-        .map(|v| v.encodable(crate_name))
-        .collect();
+    let mut query = Version::belonging_to(&krate).into_boxed();
+    if !include_yanked {
+        query = query.filter(versions::yanked.eq(false));
+    }
+    // `semver_ord` is the semver precedence of the number, compared byte by
+    // byte
+    query = match params.get("sort").map(|s| &**s) {
+        None | Some("semver") => query.order((
+            sql::<Array<Text>>("semver_ord(num) COLLATE \"C\"").desc(),
+            versions::id.desc(),
+        )),
+        Some("date") => query.order((versions::created_at.desc(), versions::id.desc())),
+        Some(sort) => {
+            return Err(human(&format_args!(
+                "unknown sort `{}`, expected `semver` or `date`",
+                sort
+            )))
+        }
+    };
+
+    let (versions, total) = if params.contains_key("page") || params.contains_key("per_page") {
+        let (offset, limit) = req.pagination(100, 100)?;
+        let data = query
+            .paginate(limit, offset)
+            .load::<(Version, i64)>(&*conn)?;
+        let total = data.get(0).map_or(0, |&(_, total)| total);
+        (data.into_iter().map(|(version, _)| version).collect(), total)
+    } else {
+        let versions = query.load::<Version>(&*conn)?;
+        let total = versions.len() as i64;
+        (versions, total)
+    };
+    let versions = encode_versions(req, &conn, &krate, versions)?;
 
     #[derive(Serialize)]
     struct R {
         versions: Vec<EncodableVersion>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+    Ok(req.json(&R {
+        versions,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `GET /crates/:crate_id/versions/match` route.
+///
+/// Resolves the semver requirement given as `req` against the versions of the
+/// crate that aren't yanked, the way Cargo does without a lockfile: the best
+/// match is the greatest matching version, and pre-releases only match
+/// requirements that mention a pre-release of the same version.
+pub fn versions_match(req: &mut Request) -> CargoResult<Response> {
+    let crate_name = &req.params()["crate_id"];
+    let requirement = req.query()
+        .get("req")
+        .ok_or_else(|| human("missing the `req` parameter"))
+        .and_then(|s| {
+            semver::VersionReq::parse(s).map_err(|_| {
+                human(&format_args!(
+                    "`{}` is not a valid version requirement",
+                    s
+                ))
+            })
+        })?;
+
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let mut versions = Version::belonging_to(&krate)
+        .filter(versions::yanked.eq(false))
+        .load::<Version>(&*conn)?
+        .into_iter()
+        .filter(|v| requirement.matches(&v.num))
+        .collect::<Vec<_>>();
+    versions.sort_by(|a, b| b.num.cmp(&a.num));
+
+    let matches = encode_versions(req, &conn, &krate, versions)?;
+    let best_match = matches.first().cloned();
+
+    #[derive(Serialize)]
+    struct R {
+        best_match: Option<EncodableVersion>,
+        matches: Vec<EncodableVersion>,
+    }
+    Ok(req.json(&R {
+        best_match,
+        matches,
+    }))
+}
+
+/// Handles the `GET /crates/:crate_id/index` route.
+///
+/// Renders the JSON lines the registry would write for the crate to the git
+/// index, computed from the database, to track down index mismatches.
+pub fn index(req: &mut Request) -> CargoResult<Response> {
+    let name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(name).first::<Crate>(&*conn)?;
+
+    let mut body = String::new();
+    for entry in krate.index_entries(&conn)? {
+        body.push_str(&serde_json::to_string(&entry)?);
+        body.push('\n');
     }
-    Ok(req.json(&R { versions }))
+
+    let mut headers = HashMap::new();
+    headers.insert("Content-Type".to_string(), vec!["text/plain; charset=utf-8".to_string()]);
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(body.into_bytes())),
+    })
 }
 
 /// Handles the `GET /crates/:crate_id/reverse_dependencies` route.
+///
+/// The optional `req` query parameter restricts the results to the dependents
+/// whose version requirement intersects the given range, e.g. `?req=^1.0`.
+/// The `kind` (`normal`, `build` or `dev`) and `target` query parameters only
+/// keep the dependencies of that kind or for that target.
 pub fn reverse_dependencies(req: &mut Request) -> CargoResult<Response> {
     use diesel::dsl::any;
 
@@ -204,7 +462,39 @@ pub fn reverse_dependencies(req: &mut Request) -> CargoResult<Response> {
     let conn = req.db_conn()?;
     let krate = Crate::by_name(name).first::<Crate>(&*conn)?;
     let (offset, limit) = req.pagination(10, 100)?;
-    let (rev_deps, total) = krate.reverse_dependencies(&*conn, offset, limit)?;
+    let query = req.query();
+    let kind = match query.get("kind").map(|s| &**s) {
+        None => None,
+        Some("normal") => Some(DependencyKind::Normal),
+        Some("build") => Some(DependencyKind::Build),
+        Some("dev") => Some(DependencyKind::Dev),
+        Some(kind) => {
+            return Err(human(&format_args!(
+                "`{}` is not a valid dependency kind",
+                kind
+            )))
+        }
+    };
+    let target = query.get("target").map(|s| &**s);
+    let (rev_deps, total) = match query.get("req") {
+        Some(version_req) => {
+            let version_req = semver::VersionReq::parse(version_req).map_err(|_| {
+                human(&format_args!(
+                    "`{}` is not a valid version requirement",
+                    version_req
+                ))
+            })?;
+            krate.reverse_dependencies_matching(
+                &*conn,
+                &version_req,
+                kind,
+                target,
+                offset,
+                limit,
+            )?
+        }
+        None => krate.reverse_dependencies(&*conn, kind, target, offset, limit)?,
+    };
     let rev_deps: Vec<_> = rev_deps
         .into_iter()
         .map(|dep| dep.encodable(&krate.name))
@@ -237,3 +527,20 @@ pub fn reverse_dependencies(req: &mut Request) -> CargoResult<Response> {
         meta: Meta { total },
     }))
 }
+
+/// Handles the `GET /crates/:crate_id/dependents/version_requirements` route.
+///
+/// Shows how many dependents require each range of versions of the crate, to
+/// help maintainers gauge how far an upgrade has been adopted.
+pub fn dependents_by_requirement(req: &mut Request) -> CargoResult<Response> {
+    let name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(name).first::<Crate>(&*conn)?;
+    let requirements = krate.dependents_by_requirement(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        requirements: Vec<RequirementDependents>,
+    }
+    Ok(req.json(&R { requirements }))
+}
@@ -1,6 +1,11 @@
+pub mod audit;
+pub mod dependents;
+pub mod deprecation;
 pub mod downloads;
 pub mod follow;
+pub mod logo;
 pub mod metadata;
 pub mod owners;
 pub mod publish;
+pub mod related;
 pub mod search;
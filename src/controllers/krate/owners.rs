@@ -1,27 +1,138 @@
 //! All routes related to managing owners of a crate
 
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
 use serde_json;
 
 use controllers::prelude::*;
-use models::{Crate, Owner, Rights, Team, User};
-use views::EncodableOwner;
+use models::{AuditAction, Crate, CrateOwner, CrateOwnerInvitation, Owner, OwnerKind, Rights, Team,
+             User};
+use schema::{crate_owner_invitations, crate_owners, users};
+use views::{EncodableCrateOwner, EncodableOwner, EncodableOwnerChange};
 
 /// Handles the `GET /crates/:crate_id/owners` route.
+///
+/// Lists the owners of the crate with when and by whom they were added,
+/// users then teams ordered by their login. The list is only paginated when
+/// `page` or `per_page` is given, so that `cargo owner --list` keeps getting
+/// all of them.
+///
+/// The users invited to become owners are listed separately in
+/// `invitations`, since `cargo owner --list` prints every entry of `users` as
+/// an owner.
 pub fn owners(req: &mut Request) -> CargoResult<Response> {
     let crate_name = &req.params()["crate_id"];
     let conn = req.db_conn()?;
     let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
-    let owners = krate
-        .owners(&conn)?
+
+    let added = CrateOwner::belonging_to(&krate)
+        .filter(crate_owners::deleted.eq(false))
+        .left_join(users::table.on(crate_owners::created_by.eq(users::id.nullable())))
+        .select((
+            crate_owners::owner_id,
+            crate_owners::owner_kind,
+            crate_owners::created_at,
+            crate_owners::created_by,
+            users::gh_login.nullable(),
+        ))
+        .load::<(i32, i32, NaiveDateTime, Option<i32>, Option<String>)>(&*conn)?
         .into_iter()
-        .map(Owner::encodable)
-        .collect();
+        .map(|(id, kind, created_at, created_by, login)| {
+            // The first owner of a crate is recorded as having added themselves
+            let invited_by = if created_by == Some(id) && kind == OwnerKind::User as i32 {
+                None
+            } else {
+                login
+            };
+            ((id, kind), (created_at, invited_by))
+        })
+        .collect::<HashMap<_, _>>();
+    let mut owners = Vec::new();
+    for owner in krate.owners(&conn)? {
+        if let Some(&(created_at, ref invited_by)) = added.get(&(owner.id(), owner.kind())) {
+            owners.push(crate_owner(owner, created_at, invited_by.clone(), false));
+        }
+    }
+
+    let invitations = crate_owner_invitations::table
+        .filter(crate_owner_invitations::crate_id.eq(krate.id))
+        .order(crate_owner_invitations::created_at)
+        .load::<CrateOwnerInvitation>(&*conn)?;
+    let user_ids = invitations
+        .iter()
+        .flat_map(|i| vec![i.invited_user_id, i.invited_by_user_id])
+        .collect::<Vec<_>>();
+    let invited_users = users::table
+        .filter(users::id.eq_any(user_ids))
+        .load::<User>(&*conn)?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect::<HashMap<_, _>>();
+    let mut pending = Vec::new();
+    for invitation in invitations {
+        if let Some(user) = invited_users.get(&invitation.invited_user_id) {
+            let invited_by = invited_users
+                .get(&invitation.invited_by_user_id)
+                .map(|user| user.gh_login.clone());
+            let owner = Owner::User(user.clone());
+            pending.push(crate_owner(owner, invitation.created_at, invited_by, true));
+        }
+    }
+
+    let total = owners.len() as i64;
+    let query = req.query();
+    if query.contains_key("page") || query.contains_key("per_page") {
+        let (offset, limit) = req.pagination(100, 100)?;
+        owners = owners
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+    }
 
     #[derive(Serialize)]
     struct R {
-        users: Vec<EncodableOwner>,
+        users: Vec<EncodableCrateOwner>,
+        invitations: Vec<EncodableCrateOwner>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+    Ok(req.json(&R {
+        users: owners,
+        invitations: pending,
+        meta: Meta { total },
+    }))
+}
+
+fn crate_owner(
+    owner: Owner,
+    created_at: NaiveDateTime,
+    invited_by: Option<String>,
+    pending: bool,
+) -> EncodableCrateOwner {
+    let EncodableOwner {
+        id,
+        login,
+        kind,
+        url,
+        name,
+        avatar,
+    } = owner.encodable();
+    EncodableCrateOwner {
+        id,
+        login,
+        kind,
+        url,
+        name,
+        avatar,
+        invited_by,
+        created_at,
+        pending,
     }
-    Ok(req.json(&R { users: owners }))
 }
 
 /// Handles the `GET /crates/:crate_id/owner_team` route.
@@ -73,6 +184,7 @@ fn modify_owners(req: &mut Request, add: bool) -> CargoResult<Response> {
     req.body().read_to_string(&mut body)?;
 
     let user = req.user()?;
+    req.require_two_factor(user)?;
     let conn = req.db_conn()?;
     let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
     let owners = krate.owners(&conn)?;
@@ -102,35 +214,69 @@ fn modify_owners(req: &mut Request, add: bool) -> CargoResult<Response> {
         .or(request.users)
         .ok_or_else(|| human("invalid json request"))?;
 
-    let mut msgs = Vec::new();
-
-    for login in &logins {
-        if add {
-            let login_test = |owner: &Owner| owner.login().to_lowercase() == *login.to_lowercase();
-            if owners.iter().any(login_test) {
-                return Err(human(&format_args!("`{}` is already an owner", login)));
-            }
-            let msg = krate.owner_add(req.app(), &conn, user, login)?;
-            msgs.push(msg);
-        } else {
-            // Removing the team that gives you rights is prevented because
-            // team members only have Rights::Publish
-            if owners.len() == 1 {
-                return Err(human("cannot remove the sole owner of a crate"));
+    // Each login is changed on its own, so that a bad login only rolls back
+    // its own change and the others are still made
+    let mut results = Vec::new();
+    for login in logins {
+        let change = conn.transaction(|| {
+            let owners = krate.owners(&conn)?;
+            if add {
+                let login_test =
+                    |owner: &Owner| owner.login().to_lowercase() == *login.to_lowercase();
+                if owners.iter().any(login_test) {
+                    return Err(human(&format_args!("`{}` is already an owner", login)));
+                }
+                let msg = krate.owner_add(req.app(), &conn, user, &login)?;
+                AuditAction::record(&conn, user, &krate, None, "owner_add", Some(&login))?;
+                Ok(msg)
+            } else {
+                // Removing the team that gives you rights is prevented because
+                // team members only have Rights::Publish
+                if owners.len() == 1 {
+                    return Err(human("cannot remove the sole owner of a crate"));
+                }
+                krate.owner_remove(req.app(), &conn, user, &login)?;
+                AuditAction::record(&conn, user, &krate, None, "owner_remove", Some(&login))?;
+                Ok(format!(
+                    "{} has been removed as an owner of crate {}",
+                    login, krate.name
+                ))
             }
-            krate.owner_remove(req.app(), &conn, user, login)?;
-        }
+        });
+        let (status, msg) = match change {
+            Ok(msg) => (if add { "added" } else { "removed" }, msg),
+            Err(ref e) if e.human() => ("error", e.description().to_string()),
+            Err(e) => return Err(e),
+        };
+        results.push(EncodableOwnerChange {
+            login,
+            status: status.to_string(),
+            msg,
+        });
+    }
+
+    // Requests where nothing could be changed fail as a whole, as they did
+    // before several logins were handled independently
+    if !results.is_empty() && results.iter().all(|result| result.status == "error") {
+        return Err(human(&results[0].msg));
     }
 
-    let comma_sep_msg = msgs.join(",");
+    let comma_sep_msg = results
+        .iter()
+        .filter(|result| add && result.status != "error")
+        .map(|result| &*result.msg)
+        .collect::<Vec<_>>()
+        .join(",");
 
     #[derive(Serialize)]
     struct R {
         ok: bool,
         msg: String,
+        results: Vec<EncodableOwnerChange>,
     }
     Ok(req.json(&R {
         ok: true,
         msg: comma_sep_msg,
+        results,
     }))
 }
@@ -2,19 +2,31 @@
 
 use std::cmp;
 use std::collections::HashMap;
+use std::io::{Cursor, Read};
 use std::sync::Arc;
 
+use flate2::read::{GzDecoder, ZlibDecoder};
 use hex::ToHex;
 use serde_json;
 
+use cdn;
 use git;
+use license;
 use render;
-use util::{internal, ChainError};
-use util::{read_fill, read_le_u32};
+use util::errors::Unauthorized;
+use util::ChainError;
+use util::{read_fill, read_le_u32, LimitErrorReader};
 
 use controllers::prelude::*;
+use middleware::current_user::AuthenticationSource;
 use models::dependency;
-use models::{Badge, Category, Keyword, NewCrate, NewVersion, Rights, User};
+use models::{Announcement, ApiToken, AuditAction, Badge, Category, Crate,
+             CrateMetadataChange, CrateWebhook, HeldIndexEntry, IndexOutboxEntry, Keyword,
+             MetadataSnapshot, NewCrate, NewVersion, PendingPublish, PublishSource, Rights,
+             TeamToken, TrustedPublishingToken, User, VersionFile, VersionScanResult,
+             VersionSignature, VersionSource};
+use models::version_signature::MAX_SIGNATURE_SIZE;
+use schema::users;
 use views::{EncodableCrate, EncodableCrateUpload};
 
 /// Handles the `PUT /crates/new` route.
@@ -26,7 +38,8 @@ use views::{EncodableCrate, EncodableCrateUpload};
 /// --status` command, via crates.io's front end, or email.
 pub fn publish(req: &mut Request) -> CargoResult<Response> {
     let app = Arc::clone(req.app());
-    let (new_crate, user) = parse_new_headers(req)?;
+    let (new_crate, user, team_token) = parse_new_headers(req)?;
+    let source = publish_source(req, team_token.as_ref())?;
 
     let name = &*new_crate.name;
     let vers = &*new_crate.vers;
@@ -51,10 +64,63 @@ pub fn publish(req: &mut Request) -> CargoResult<Response> {
     let categories = new_crate.categories.as_ref().map(|s| &s[..]).unwrap_or(&[]);
     let categories: Vec<_> = categories.iter().map(|k| &***k).collect();
 
+    // Licenses are stored normalized, e.g. `MIT/Apache-2.0` as `MIT OR Apache-2.0`
+    let license = match new_crate.license {
+        Some(ref license) => Some(license::normalize(license)?),
+        None => None,
+    };
+
     let conn = req.db_conn()?;
+    if app.config.require_verified_email && user.verified_email(&conn)?.is_none() {
+        return Err(human(
+            "A verified email address is required to publish crates. \
+             Visit https://crates.io/me to set and verify your email address.",
+        ));
+    }
+    req.require_two_factor(&user)?;
+
+    // Users can choose to confirm the publication of new crate names by email
+    let is_new_crate = Crate::by_name(name)
+        .first::<Crate>(&*conn)
+        .optional()?
+        .is_none();
+    let confirmation_required = user.confirm_new_crates && is_new_crate;
+    if confirmation_required && !PendingPublish::is_confirmed(&conn, &user, name)? {
+        let email = user.verified_email(&conn)?.ok_or_else(|| {
+            human(
+                "publishing new crates must be confirmed by email, \
+                 but you don't have a verified email address",
+            )
+        })?;
+        let pending = PendingPublish::create(&conn, &user, name)?;
+        ::email::send_publish_confirmation_email(&email, name, &pending.token)?;
+        return Err(human(&format_args!(
+            "publishing the new crate `{}` must be confirmed, please follow \
+             the link sent to your email address and publish again",
+            name
+        )));
+    }
+
     // Create a transaction on the database, if there are no errors,
     // commit the transactions to record a new or updated crate.
-    conn.transaction(|| {
+    let (version_id, response) = conn.transaction(|| {
+        // Publishes of the same crate wait for each other from here on, until
+        // the version and its index outbox entry are recorded. The entry is
+        // written to the index once the transaction commits.
+        Crate::lock_name(&conn, name)?;
+
+        // The confirmation is only used up if the publish goes through
+        if confirmation_required && !PendingPublish::take_confirmed(&conn, &user, name)? {
+            return Err(human(&format_args!(
+                "the confirmation of the publication of `{}` expired, please publish again",
+                name
+            )));
+        }
+
+        // The metadata changed by this publish is recorded for the owners
+        let existing = Crate::by_name(name).first::<Crate>(&*conn).optional()?;
+        let metadata_before = MetadataSnapshot::take(&conn, existing.as_ref())?;
+
         // Persist the new crate, if it doesn't already exist
         let persist = NewCrate {
             name,
@@ -64,15 +130,25 @@ pub fn publish(req: &mut Request) -> CargoResult<Response> {
             readme: new_crate.readme.as_ref().map(|s| &**s),
             readme_file: new_crate.readme_file.as_ref().map(|s| &**s),
             repository: repo,
-            license: new_crate.license.as_ref().map(|s| &**s),
+            license: license.as_ref().map(|s| &**s),
             max_upload_size: None,
         };
 
         let license_file = new_crate.license_file.as_ref().map(|s| &**s);
-        let krate = persist.create_or_update(&conn, license_file, user.id)?;
+        let krate = persist.create_or_update(
+            &conn,
+            license_file,
+            user.id,
+            &app.config.name_policy,
+        )?;
 
+        // Team tokens can only publish the crates owned by their team
         let owners = krate.owners(&conn)?;
-        if user.rights(req.app(), &owners)? < Rights::Publish {
+        let rights = match team_token {
+            Some(ref team_token) => team_token.rights(&owners),
+            None => user.rights(req.app(), &owners)?,
+        };
+        if rights < Rights::Publish {
             return Err(human(
                 "this crate exists but you don't seem to be an owner. \
                  If you believe this is a mistake, perhaps you need \
@@ -81,6 +157,14 @@ pub fn publish(req: &mut Request) -> CargoResult<Response> {
             ));
         }
 
+        if krate.locked {
+            return Err(human(&format_args!(
+                "crate `{}` is locked and new versions can't be published: {}",
+                krate.name,
+                krate.lock_reason.as_ref().map(|s| &**s).unwrap_or("no reason given")
+            )));
+        }
+
         if &krate.name != name {
             return Err(human(&format_args!(
                 "crate was previously named `{}`",
@@ -98,15 +182,30 @@ pub fn publish(req: &mut Request) -> CargoResult<Response> {
             return Err(human(&format_args!("max upload size is: {}", max)));
         }
 
-        // This is only redundant for now. Eventually the duplication will be removed.
-        let license = new_crate.license.clone();
-
         // Persist the new version of this crate
-        let version = NewVersion::new(krate.id, vers, &features, license, license_file)?
+        let version = NewVersion::new(krate.id, vers, &features, license.clone(), license_file)?
             .save(&conn, &new_crate.authors)?;
 
         // Link this new version to all dependencies
-        let git_deps = dependency::add_dependencies(&conn, &new_crate.deps, version.id)?;
+        let (git_deps, dependency_warnings) = dependency::add_dependencies(
+            &conn,
+            &new_crate.deps,
+            version.id,
+            &app.config.dependency_policy,
+        )?;
+
+        // Binaries can require the features of the optional dependencies
+        let edition = new_crate.edition.as_ref().map(|s| &**s);
+        version.record_build_metadata(&conn, edition, &new_crate.binaries)?;
+        version.record_publish_source(&conn, user.id, &source)?;
+        AuditAction::record(
+            &conn,
+            &user,
+            &krate,
+            Some(vers),
+            "publish",
+            source.published_with.as_ref().map(|s| &**s),
+        )?;
 
         // Update all keywords for this crate
         Keyword::update_crate(&conn, &krate, &keywords)?;
@@ -118,7 +217,18 @@ pub fn publish(req: &mut Request) -> CargoResult<Response> {
         // Update all badges for this crate, collecting any invalid badges in
         // order to be able to warn about them
         let ignored_invalid_badges = Badge::update_crate(&conn, &krate, new_crate.badges.as_ref())?;
+        krate.update_max_versions(&conn)?;
         let max_version = krate.max_version(&conn)?;
+        let default_version = krate.default_version(&conn)?;
+
+        // Keep the crate's license in sync with its latest version, publishing
+        // an older version shouldn't overwrite it.
+        let krate = match max_version {
+            Some(ref max_version) => krate.update_license(&conn, max_version)?,
+            None => krate,
+        };
+        let metadata_after = MetadataSnapshot::take(&conn, Some(&krate))?;
+        CrateMetadataChange::record(&conn, &version, &metadata_before, &metadata_after)?;
 
         // Render the README for this crate
         let readme = match new_crate.readme.as_ref() {
@@ -134,15 +244,28 @@ pub fn publish(req: &mut Request) -> CargoResult<Response> {
         // If the git commands fail below, we shouldn't keep the crate on the
         // server.
         let max_unpack = cmp::max(app.config.max_unpack_size, max);
-        let (cksum, mut crate_bomb, mut readme_bomb) = app.config
+        let (cksum, mut crate_bomb, mut readme_bomb, scan_results, tarball) = app.config
             .uploader
             .upload_crate(req, &krate, readme, max, max_unpack, vers)?;
         version.record_readme_rendering(&conn)?;
+        VersionSource {
+            version_id: version.id,
+            readme: new_crate.readme.clone(),
+            readme_file: new_crate.readme_file.clone(),
+            manifest: tarball.manifest,
+        }.save(&conn)?;
+        VersionFile::save_all(&conn, version.id, &tarball.files)?;
+        if let Some(ref signature) = new_crate.signature {
+            VersionSignature::save(&conn, &version, signature)?;
+        }
+        VersionScanResult::save_all(&conn, version.id, &scan_results)?;
 
         let mut hex_cksum = String::new();
         cksum.write_hex(&mut hex_cksum)?;
+        version.record_index_fields(&conn, &hex_cksum, links.as_ref().map(|s| &**s))?;
 
-        // Register this crate in our local git repo.
+        // Register this crate in our local git repo once the transaction is
+        // committed, unless it has to be reviewed by an administrator first.
         let git_crate = git::Crate {
             name: name.to_string(),
             vers: vers.to_string(),
@@ -151,26 +274,44 @@ pub fn publish(req: &mut Request) -> CargoResult<Response> {
             deps: git_deps,
             yanked: Some(false),
             links,
+            edition: new_crate.edition.clone(),
         };
-        git::add_crate(&**req.app(), &git_crate).chain_error(|| {
-            internal(&format_args!(
-                "could not add crate `{}` to the git repo",
-                name
-            ))
-        })?;
+        let hold_reason = HeldIndexEntry::hold_reason(&app, &krate, &user, &scan_results);
+        if let Some(ref reason) = hold_reason {
+            HeldIndexEntry::create(&conn, &krate, &version, &git_crate, reason)?;
+        } else {
+            IndexOutboxEntry::create(&conn, &version, &git_crate)?;
+            CrateWebhook::enqueue(&conn, &krate, "publish", &version.num)?;
+        }
 
         // Now that we've come this far, we're committed!
         crate_bomb.path = None;
         readme_bomb.path = None;
 
+        // Relay the active announcements so that cargo displays them
+        let mut other = Announcement::active(&conn)?
+            .into_iter()
+            .map(|a| a.message)
+            .collect::<Vec<_>>();
+        other.extend(dependency_warnings);
+        if let Some(reason) = hold_reason {
+            other.push(format!(
+                "this version is held for review because {}, it will be \
+                 available once approved by the crates.io team",
+                reason
+            ));
+        }
+
         #[derive(Serialize)]
         struct Warnings<'a> {
             invalid_categories: Vec<&'a str>,
             invalid_badges: Vec<&'a str>,
+            other: Vec<String>,
         }
         let warnings = Warnings {
             invalid_categories: ignored_invalid_categories,
             invalid_badges: ignored_invalid_badges,
+            other,
         };
 
         #[derive(Serialize)]
@@ -179,19 +320,42 @@ pub fn publish(req: &mut Request) -> CargoResult<Response> {
             krate: EncodableCrate,
             warnings: Warnings<'a>,
         }
-        Ok(req.json(&R {
-            krate: krate.minimal_encodable(&max_version, None, false, None),
+        let response = req.json(&R {
+            krate: krate.minimal_encodable(
+                max_version.as_ref(),
+                &default_version,
+                None,
+                false,
+                None,
+            ),
             warnings,
-        }))
-    })
+        });
+        Ok((version.id, response))
+    })?;
+
+    // Only once committed, or the summary and the CDN could be filled again
+    // without the new version
+    app.invalidate_summary_cache();
+    app.metrics.record_publish();
+    cdn::purge(&app, cdn::crate_paths(name, vers));
+
+    // The version is published even if its entry can't be written to the index
+    // right now, the outbox task writing it later along with the entries of
+    // the previous publishes that failed.
+    if let Err(e) = IndexOutboxEntry::write_entry(&conn, &app, version_id) {
+        error!("failed to write the index entry of `{}#{}`: {}", name, vers, e);
+    }
+    Ok(response)
 }
 
 /// Used by the `krate::new` function.
 ///
 /// This function parses the JSON headers to interpret the data and validates
-/// the data during and after the parsing. Returns crate metadata and user
-/// information.
-fn parse_new_headers(req: &mut Request) -> CargoResult<(EncodableCrateUpload, User)> {
+/// the data during and after the parsing. Returns crate metadata, user
+/// information and the team token the publish is authenticated with, if any.
+fn parse_new_headers(
+    req: &mut Request,
+) -> CargoResult<(EncodableCrateUpload, User, Option<TeamToken>)> {
     // Read the json upload request
     let amt = u64::from(read_le_u32(req.body())?);
     let max = req.app().config.max_upload_size;
@@ -200,6 +364,10 @@ fn parse_new_headers(req: &mut Request) -> CargoResult<(EncodableCrateUpload, Us
     }
     let mut json = vec![0; amt as usize];
     read_fill(req.body(), &mut json)?;
+    let encoding = req.headers()
+        .find("Content-Encoding")
+        .and_then(|headers| headers.first().map(|s| s.trim().to_lowercase()));
+    let json = decode_metadata(encoding.as_ref().map(|s| &**s), json, max)?;
     let json = String::from_utf8(json).map_err(|_| human("json body was not valid utf-8"))?;
     let new: EncodableCrateUpload = serde_json::from_str(&json)
         .map_err(|e| human(&format_args!("invalid upload request: {}", e)))?;
@@ -227,7 +395,114 @@ fn parse_new_headers(req: &mut Request) -> CargoResult<(EncodableCrateUpload, Us
             missing.join(", ")
         )));
     }
+    if let Some(ref signature) = new.signature {
+        if signature.trim().is_empty() || signature.len() > MAX_SIGNATURE_SIZE {
+            return Err(human(&format_args!(
+                "the signature of the crate file must be between 1 and {} bytes",
+                MAX_SIGNATURE_SIZE
+            )));
+        }
+    }
+
+    let (user, team_token) = match req.user() {
+        Ok(user) => (user.clone(), None),
+        Err(_) => match team_token(&*req)? {
+            Some((user, token)) => (user, Some(token)),
+            None => (trusted_publisher_user(&*req, &new.name)?, None),
+        },
+    };
+    Ok((new, user, team_token))
+}
+
+/// The longest `X-Cargo-Version` or `X-CI-Provider` header kept.
+const MAX_SOURCE_HEADER_LENGTH: usize = 128;
+
+/// Reads where the publish is made from out of its optional `X-Cargo-Version`
+/// and `X-CI-Provider` headers, along with how it's authenticated.
+fn publish_source(req: &Request, team_token: Option<&TeamToken>) -> CargoResult<PublishSource> {
+    let header = |name: &str| {
+        let value = match req.headers().find(name) {
+            Some(values) => values.first().map_or("", |s| s.trim()),
+            None => "",
+        };
+        if value.is_empty() {
+            None
+        } else {
+            Some(value.chars().take(MAX_SOURCE_HEADER_LENGTH).collect::<String>())
+        }
+    };
+    let published_with = match (team_token, req.authentication_source()) {
+        (Some(team_token), _) => format!("team_token:{}", team_token.name),
+        (None, Ok(AuthenticationSource::SessionCookie)) => "session".to_string(),
+        (None, Ok(AuthenticationSource::ApiToken)) => {
+            let conn = req.db_conn()?;
+            let header = req.headers().find("Authorization").unwrap_or_default();
+            let token = ApiToken::find_by_token(&conn, header.first().map_or("", |s| *s))?;
+            format!("api_token:{}", token.name)
+        }
+        (None, Err(_)) => "trusted_publishing".to_string(),
+    };
+    Ok(PublishSource {
+        cargo_version: header("X-Cargo-Version"),
+        ci_provider: header("X-CI-Provider").map(|s| s.to_lowercase()),
+        published_with: Some(published_with),
+    })
+}
 
-    let user = req.user()?;
-    Ok((new, user.clone()))
+/// Decompresses the JSON metadata of a publish sent with a `Content-Encoding`
+/// of `gzip` or `deflate`. The crate file that follows it is left alone as
+/// it's compressed already.
+///
+/// The decompressed metadata is capped at `max` bytes so that a small upload
+/// can't expand into gigabytes of JSON.
+fn decode_metadata(encoding: Option<&str>, json: Vec<u8>, max: u64) -> CargoResult<Vec<u8>> {
+    let decoder: Box<Read> = match encoding {
+        None | Some("identity") => return Ok(json),
+        Some("gzip") => Box::new(GzDecoder::new(Cursor::new(json))?),
+        Some("deflate") => Box::new(ZlibDecoder::new(Cursor::new(json))),
+        Some(encoding) => {
+            return Err(human(&format_args!(
+                "unsupported content encoding `{}`, expected `gzip` or `deflate`",
+                encoding
+            )))
+        }
+    };
+    let mut decoded = Vec::new();
+    LimitErrorReader::new(decoder, max)
+        .read_to_end(&mut decoded)
+        .map_err(|_| {
+            human(&format_args!(
+                "metadata could not be decompressed or is larger than {} bytes",
+                max
+            ))
+        })?;
+    Ok(decoded)
+}
+
+/// Finds the team token the request is authenticated with, along with the
+/// team admin who created it and on whose behalf the publish is made.
+fn team_token(req: &Request) -> CargoResult<Option<(User, TeamToken)>> {
+    let token = match req.headers().find("Authorization") {
+        Some(headers) => headers[0].to_string(),
+        None => return Ok(None),
+    };
+    let conn = req.db_conn()?;
+    let team_token = match TeamToken::find_by_token(&conn, &token)? {
+        Some(team_token) => team_token,
+        None => return Ok(None),
+    };
+    let user = users::table.find(team_token.created_by).first::<User>(&*conn)?;
+    Ok(Some((user, team_token)))
+}
+
+/// Finds the user a publish authenticated with a trusted publishing token is
+/// made on behalf of: the owner who registered the workflow the token was
+/// issued to as a trusted publisher of the crate.
+fn trusted_publisher_user(req: &Request, crate_name: &str) -> CargoResult<User> {
+    let token = req.headers()
+        .find("Authorization")
+        .and_then(|headers| headers.first().map(|s| s.to_string()))
+        .chain_error(|| Unauthorized)?;
+    let conn = req.db_conn()?;
+    TrustedPublishingToken::publisher_user(&conn, &token, crate_name)?.chain_error(|| Unauthorized)
 }
@@ -0,0 +1,25 @@
+//! Endpoint recommending the crates similar to a crate
+
+use controllers::helpers::encode_crates;
+use controllers::prelude::*;
+use models::{Crate, RelatedCrate};
+use views::EncodableCrate;
+
+/// Handles the `GET /crates/:crate_id/related` route.
+///
+/// Lists the crates sharing the most keywords, categories and dependents with
+/// the crate, the most related first. They are recomputed daily by the
+/// `update-related-crates` job, so new crates aren't listed right away.
+pub fn related(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    let related = RelatedCrate::related(&conn, &krate)?;
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableCrate>,
+    }
+    Ok(req.json(&R {
+        crates: encode_crates(&conn, related)?,
+    }))
+}
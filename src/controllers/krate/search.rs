@@ -4,11 +4,14 @@ use diesel_full_text_search::*;
 
 use controllers::helpers::Paginate;
 use controllers::prelude::*;
-use models::{Crate, CrateBadge, OwnerKind, Version};
+use models::{Category, Crate, CrateBadge, KeywordAlias, OwnerKind};
 use schema::*;
 use views::EncodableCrate;
 
-use models::krate::{canon_crate_name, ALL_COLUMNS};
+use models::krate::{canon_crate_name, left, LetterBucket, ALL_COLUMNS};
+
+/// How many crate names `GET /crates/suggest` returns.
+const SUGGESTIONS: i64 = 10;
 
 /// Handles the `GET /crates` route.
 /// Returns a list of crates. Called in a variety of scenarios in the
@@ -16,6 +19,8 @@ use models::krate::{canon_crate_name, ALL_COLUMNS};
 /// - Alphabetical listing of crates
 /// - List of crates under a specific owner
 /// - Listing a user's followed crates
+/// - Listing the crates with a maintenance status, like the ones looking for
+///   maintainers, the deprecated ones or the stale ones
 ///
 /// Notes:
 /// The different use cases this function covers is handled through passing
@@ -32,7 +37,8 @@ use models::krate::{canon_crate_name, ALL_COLUMNS};
 /// function out to cover the different use cases, and create unit tests
 /// for them.
 pub fn search(req: &mut Request) -> CargoResult<Response> {
-    use diesel::sql_types::Bool;
+    use diesel::dsl::sql;
+    use diesel::sql_types::{Bool, Float};
 
     let conn = req.db_conn()?;
     let (offset, limit) = req.pagination(10, 100)?;
@@ -68,13 +74,20 @@ pub fn search(req: &mut Request) -> CargoResult<Response> {
             query = query.order(Crate::with_name(q_string).desc());
 
             if sort == "relevance" {
+                // Better maintained crates rank up to twice as high
+                let boost = sql::<Float>("(1 + COALESCE(crates.quality_score, 0))::real");
                 let rank = ts_rank_cd(crates::textsearchable_index_col, q);
-                query = query.then_order_by(rank.desc())
+                query = query.then_order_by((boost * rank).desc())
             }
         }
     }
 
+    // Categories can be searched by the slugs they had before being renamed
+    let mut canonical_category = None;
     if let Some(cat) = params.get("category") {
+        let cat = Category::find_by_slug(&conn, cat)
+            .optional()?
+            .map_or_else(|| cat.clone(), |cat| cat.slug);
         query = query.filter(
             crates::id.eq_any(
                 crates_categories::table
@@ -82,14 +95,62 @@ pub fn search(req: &mut Request) -> CargoResult<Response> {
                     .inner_join(categories::table)
                     .filter(
                         categories::slug
-                            .eq(cat)
+                            .eq(cat.clone())
                             .or(categories::slug.like(format!("{}::%", cat))),
                     ),
             ),
         );
+        canonical_category = Some(cat);
+    }
+
+    // e.g. `?maintenance=looking-for-maintainer`, `deprecated` and `stale`
+    // not being statuses set by the owners
+    if let Some(status) = params.get("maintenance") {
+        query = match &**status {
+            "deprecated" => query.filter(crates::deprecated.eq(true)),
+            "stale" => {
+                let stale = stale_crates::table.select(stale_crates::crate_id);
+                query.filter(crates::id.eq_any(stale))
+            }
+            _ => query.filter(crates::maintenance_status.eq(status.clone())),
+        };
+    }
+
+    // Lists of tags the crates must all have, e.g. `?all_keywords=async,http`
+    let tags = |name: &str| -> Vec<String> {
+        let mut tags = params
+            .get(name)
+            .map(|list| {
+                list.split(',')
+                    .map(|tag| tag.trim().to_lowercase())
+                    .filter(|tag| !tag.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        tags.sort();
+        tags.dedup();
+        tags
+    };
+    let all_categories = tags("categories");
+    if !all_categories.is_empty() {
+        let ids = Crate::ids_in_all_categories(&conn, &all_categories)?;
+        query = query.filter(crates::id.eq_any(ids));
+    }
+    let mut all_keywords = Vec::new();
+    for kw in tags("all_keywords") {
+        let kw = KeywordAlias::keyword_of(&conn, &kw)?.map_or(kw, |kw| kw.to_lowercase());
+        all_keywords.push(kw);
+    }
+    if !all_keywords.is_empty() {
+        all_keywords.sort();
+        all_keywords.dedup();
+        let ids = Crate::ids_with_all_keywords(&conn, &all_keywords)?;
+        query = query.filter(crates::id.eq_any(ids));
     }
 
     if let Some(kw) = params.get("keyword") {
+        // Aliases list the crates with the keyword they stand for
+        let kw = KeywordAlias::keyword_of(&conn, kw)?.unwrap_or_else(|| kw.clone());
         query = query.filter(
             crates::id.eq_any(
                 crates_keywords::table
@@ -99,16 +160,18 @@ pub fn search(req: &mut Request) -> CargoResult<Response> {
             ),
         );
     } else if let Some(letter) = params.get("letter") {
-        let pattern = format!(
-            "{}%",
-            letter
-                .chars()
-                .next()
-                .unwrap()
-                .to_lowercase()
-                .collect::<String>()
-        );
-        query = query.filter(canon_crate_name(crates::name).like(pattern));
+        // Crates are listed by the prefix of their canonical name, so that
+        // `foo-bar` and `Foo_Bar` are found under the same prefix
+        let prefix = letter.trim().to_lowercase().replace('-', "_");
+        if letter == "0-9" {
+            let digits = (0..10).map(|d| d.to_string()).collect::<Vec<_>>();
+            query = query.filter(left(canon_crate_name(crates::name), 1).eq_any(digits));
+        } else if prefix.is_empty() {
+            return Err(human("the `letter` parameter cannot be empty"));
+        } else {
+            let len = prefix.chars().count() as i32;
+            query = query.filter(left(canon_crate_name(crates::name), len).eq(prefix));
+        }
     } else if let Some(user_id) = params.get("user_id").and_then(|s| s.parse::<i32>().ok()) {
         query = query.filter(
             crates::id.eq_any(
@@ -143,6 +206,10 @@ pub fn search(req: &mut Request) -> CargoResult<Response> {
         query = query.then_order_by(crates::downloads.desc())
     } else if sort == "recent-downloads" {
         query = query.then_order_by(recent_crate_downloads::downloads.desc().nulls_last())
+    } else if sort == "recently-updated" {
+        query = query.then_order_by(crates::updated_at.desc())
+    } else if sort == "relevance" {
+        query = query.then_order_by(crates::quality_score.desc().nulls_last())
     } else {
         query = query.then_order_by(crates::name.asc())
     }
@@ -159,11 +226,7 @@ pub fn search(req: &mut Request) -> CargoResult<Response> {
         .collect::<Vec<_>>();
     let crates = data.into_iter().map(|((c, _, _), _)| c).collect::<Vec<_>>();
 
-    let versions = Version::belonging_to(&crates)
-        .load::<Version>(&*conn)?
-        .grouped_by(&crates)
-        .into_iter()
-        .map(|versions| Version::max(versions.into_iter().map(|v| v.num)));
+    let versions = Crate::max_versions(&conn, &crates)?.into_iter();
 
     let badges = CrateBadge::belonging_to(&crates)
         .select((badges::crate_id, badges::all_columns))
@@ -177,16 +240,16 @@ pub fn search(req: &mut Request) -> CargoResult<Response> {
         .zip(perfect_matches)
         .zip(recent_downloads)
         .zip(badges)
-        .map(
-            |((((max_version, krate), perfect_match), recent_downloads), badges)| {
-                krate.minimal_encodable(
-                    &max_version,
-                    Some(badges),
-                    perfect_match,
-                    Some(recent_downloads),
-                )
-            },
-        )
+        .map(|((((versions, krate), perfect_match), recent_downloads), badges)| {
+            let (max_version, default_version) = versions;
+            krate.minimal_encodable(
+                max_version.as_ref(),
+                &default_version,
+                Some(badges),
+                perfect_match,
+                Some(recent_downloads),
+            )
+        })
         .collect();
 
     #[derive(Serialize)]
@@ -197,10 +260,68 @@ pub fn search(req: &mut Request) -> CargoResult<Response> {
     #[derive(Serialize)]
     struct Meta {
         total: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        letters: Option<Vec<LetterBucket>>,
+        /// The current slug of the `category` searched by
+        #[serde(skip_serializing_if = "Option::is_none")]
+        canonical_category: Option<String>,
     }
 
+    // Listings by letter also show how many crates are under each letter
+    let letters = if params.contains_key("letter") {
+        Some(Crate::letter_buckets(&conn)?)
+    } else {
+        None
+    };
+
     Ok(req.json(&R {
         crates,
-        meta: Meta { total },
+        meta: Meta {
+            total,
+            letters,
+            canonical_category,
+        },
     }))
 }
+
+/// Handles the `GET /crates/suggest` route.
+///
+/// Returns the names of the crates starting with the `q` parameter, for
+/// typeahead. An exact match comes first, then the most downloaded crates.
+pub fn suggest(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    // The same canonical form as `canon_crate_name`, so that the prefix index
+    // on it is used
+    let prefix = req.query()
+        .get("q")
+        .map(|q| q.trim().to_lowercase().replace('-', "_"))
+        .unwrap_or_default();
+
+    let names = if prefix.is_empty() {
+        Vec::new()
+    } else {
+        let pattern = format!(
+            "{}%",
+            prefix
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        crates::table
+            .select(crates::name)
+            .filter(canon_crate_name(crates::name).like(pattern))
+            .order((
+                canon_crate_name(crates::name).eq(&prefix).desc(),
+                crates::downloads.desc(),
+                crates::name.asc(),
+            ))
+            .limit(SUGGESTIONS)
+            .load::<String>(&*conn)?
+    };
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<String>,
+    }
+    Ok(req.json(&R { crates: names }))
+}
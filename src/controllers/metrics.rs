@@ -0,0 +1,41 @@
+//! Serves the metrics of the server to a Prometheus scraper.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use openssl::memcmp;
+
+use super::prelude::*;
+
+use util::errors::{NotFound, Unauthorized};
+use util::request_header;
+
+/// Handles the `GET /metrics` route.
+///
+/// The route only exists when `Config::metrics_token` is set, and the token
+/// has to be sent as `Authorization: Bearer <token>`.
+pub fn prometheus(req: &mut Request) -> CargoResult<Response> {
+    let token = match req.app().config.metrics_token {
+        Some(ref token) => token,
+        None => return Err(Box::new(NotFound)),
+    };
+    // Compared in constant time so that the token can't be guessed from how
+    // long the comparison takes
+    let expected = format!("Bearer {}", token);
+    let sent = request_header(req, "Authorization");
+    if sent.len() != expected.len() || !memcmp::eq(sent.as_bytes(), expected.as_bytes()) {
+        return Err(Box::new(Unauthorized));
+    }
+
+    let body = req.app().metrics.render(req.app());
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["text/plain; version=0.0.4".to_string()],
+    );
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(body.into_bytes())),
+    })
+}
@@ -80,12 +80,22 @@ mod prelude {
 
 pub mod helpers;
 
+pub mod admin;
+pub mod announcement;
 pub mod category;
+pub mod crate_list;
 pub mod crate_owner_invitation;
+pub mod graphql;
+pub mod health;
 pub mod keyword;
 pub mod krate;
+pub mod metrics;
 pub mod site_metadata;
+pub mod spotlight;
 pub mod team;
 pub mod token;
+pub mod trusted_publishing;
+pub mod updates;
 pub mod user;
 pub mod version;
+pub mod webhooks;
@@ -1,5 +1,11 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
 use super::prelude::*;
 
+use middleware::CachePolicy;
+use models::sitemap::{Sitemap, SITEMAP_INDEX};
+
 /// Returns the JSON representation of the current deployed commit sha.
 ///
 /// The sha is contained within the `HEROKU_SLUG_COMMIT` environment variable.
@@ -14,3 +20,67 @@ pub fn show_deployed_sha(req: &mut Request) -> CargoResult<Response> {
     }
     Ok(req.json(&R { deployed_sha }))
 }
+
+/// Handles the `GET /api/openapi.json` route.
+///
+/// Returns the OpenAPI description of the routes mounted under `/api/v1`.
+pub fn openapi(req: &mut Request) -> CargoResult<Response> {
+    Ok(req.json(&::api_doc::document()))
+}
+
+/// Handles the `GET /sitemap.xml` route, the index of the sitemaps of the
+/// crates and categories.
+pub fn sitemap_index(req: &mut Request) -> CargoResult<Response> {
+    sitemap_response(req, SITEMAP_INDEX)
+}
+
+/// Handles the `GET /sitemaps/:name` route.
+pub fn sitemap(req: &mut Request) -> CargoResult<Response> {
+    let name = req.params()["name"].to_string();
+    sitemap_response(req, &name)
+}
+
+/// Handles the `GET /index_signing_key.pem` route, the public key the commits
+/// of the git index can be verified with.
+pub fn index_signing_key(req: &mut Request) -> CargoResult<Response> {
+    let key = req.app()
+        .config
+        .index_signing_key
+        .as_ref()
+        .map(|key| key.public_key().to_string())
+        .ok_or_else(|| human("the commits of the index aren't signed"))?;
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["application/x-pem-file".to_string()],
+    );
+    headers.insert("Content-Length".to_string(), vec![key.len().to_string()]);
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(key.into_bytes())),
+    })
+}
+
+fn sitemap_response(req: &mut Request, name: &str) -> CargoResult<Response> {
+    // The sitemaps are only regenerated daily
+    req.mut_extensions().insert(CachePolicy::Long);
+    let conn = req.db_conn()?;
+    let sitemap = Sitemap::find(&conn, name)?;
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["application/xml; charset=utf-8".to_string()],
+    );
+    headers.insert(
+        "Content-Length".to_string(),
+        vec![sitemap.body.len().to_string()],
+    );
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(sitemap.body.into_bytes())),
+    })
+}
@@ -0,0 +1,31 @@
+use super::prelude::*;
+
+use chrono::Utc;
+
+use controllers::helpers::encode_crates;
+use models::CrateSpotlight;
+use views::EncodableCrateSpotlight;
+
+/// Returns the crate featured today, picking one if no administrator did.
+pub fn todays_spotlight(conn: &PgConnection) -> CargoResult<Option<EncodableCrateSpotlight>> {
+    let spotlight = CrateSpotlight::of_the_day(conn, Utc::today().naive_utc())?;
+    Ok(match spotlight {
+        Some((spotlight, krate)) => {
+            let krate = encode_crates(conn, vec![krate])?.remove(0);
+            Some(spotlight.encodable(krate))
+        }
+        None => None,
+    })
+}
+
+/// Handles the `GET /spotlight` route.
+pub fn show(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let spotlight = todays_spotlight(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        spotlight: Option<EncodableCrateSpotlight>,
+    }
+    Ok(req.json(&R { spotlight }))
+}
@@ -1,8 +1,16 @@
 use controllers::prelude::*;
 
-use models::Team;
-use schema::teams;
-use views::EncodableTeam;
+use diesel;
+use serde_json;
+
+use controllers::helpers::{encode_crates, Paginate};
+use middleware::current_user::AuthenticationSource;
+use util::bad_request;
+
+use models::krate::ALL_COLUMNS;
+use models::{Crate, OwnerKind, Team, TeamToken};
+use schema::{crate_owners, crates, team_tokens, teams};
+use views::{EncodableApiTokenWithToken, EncodableCrate, EncodableTeam};
 
 /// Handles the `GET /teams/:team_id` route.
 pub fn show_team(req: &mut Request) -> CargoResult<Response> {
@@ -20,3 +28,120 @@ pub fn show_team(req: &mut Request) -> CargoResult<Response> {
         team: team.encodable(),
     }))
 }
+
+/// Handles the `GET /teams/:team_id/crates` route.
+///
+/// Lists the crates owned by a team, most downloaded first.
+pub fn crates(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let (offset, limit) = req.pagination(10, 100)?;
+    let team = teams::table
+        .filter(teams::login.eq(&req.params()["team_id"]))
+        .first::<Team>(&*conn)?;
+
+    let data = crates::table
+        .select(ALL_COLUMNS)
+        .filter(
+            crates::id.eq_any(
+                crate_owners::table
+                    .select(crate_owners::crate_id)
+                    .filter(crate_owners::owner_id.eq(team.id))
+                    .filter(crate_owners::deleted.eq(false))
+                    .filter(crate_owners::owner_kind.eq(OwnerKind::Team as i32)),
+            ),
+        )
+        .order((crates::downloads.desc(), crates::name.asc()))
+        .paginate(limit, offset)
+        .load::<(Crate, i64)>(&*conn)?;
+    let total = data.first().map(|&(_, t)| t).unwrap_or(0);
+    let krates = data.into_iter().map(|(c, _)| c).collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+    Ok(req.json(&R {
+        crates: encode_crates(&conn, krates)?,
+        meta: Meta { total },
+    }))
+}
+
+/// Returns the team of the request if the current user is one of its admins.
+fn administered_team(req: &Request) -> CargoResult<Team> {
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+    let team = teams::table
+        .filter(teams::login.eq(&req.params()["team_id"]))
+        .first::<Team>(&*conn)?;
+    if !team.is_maintainer(req.app(), user)? {
+        return Err(human("only the maintainers of a team can manage its tokens"));
+    }
+    Ok(team)
+}
+
+/// Handles the `GET /teams/:team_id/tokens` route.
+pub fn tokens(req: &mut Request) -> CargoResult<Response> {
+    let team = administered_team(req)?;
+    let tokens = TeamToken::belonging_to(&team)
+        .order(team_tokens::created_at.desc())
+        .load(&*req.db_conn()?)?;
+
+    #[derive(Serialize)]
+    struct R {
+        team_tokens: Vec<TeamToken>,
+    }
+    Ok(req.json(&R { team_tokens: tokens }))
+}
+
+/// Handles the `PUT /teams/:team_id/tokens` route.
+pub fn new_token(req: &mut Request) -> CargoResult<Response> {
+    #[derive(Deserialize)]
+    struct NewTeamToken {
+        name: String,
+    }
+    #[derive(Deserialize)]
+    struct NewTeamTokenRequest {
+        team_token: NewTeamToken,
+    }
+
+    if req.authentication_source()? != AuthenticationSource::SessionCookie {
+        return Err(bad_request(
+            "cannot use an API token to create a new API token",
+        ));
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let new: NewTeamTokenRequest = serde_json::from_str(&body)
+        .map_err(|e| bad_request(&format!("invalid new token request: {:?}", e)))?;
+    if new.team_token.name.is_empty() {
+        return Err(bad_request("name must have a value"));
+    }
+
+    let team = administered_team(req)?;
+    let conn = req.db_conn()?;
+    let team_token = TeamToken::insert(&conn, &team, req.user()?, &new.team_token.name)?;
+
+    #[derive(Serialize)]
+    struct R {
+        team_token: EncodableApiTokenWithToken,
+    }
+    Ok(req.json(&R {
+        team_token: team_token.encodable_with_token(),
+    }))
+}
+
+/// Handles the `DELETE /teams/:team_id/tokens/:id` route.
+pub fn revoke_token(req: &mut Request) -> CargoResult<Response> {
+    let id = req.params()["id"]
+        .parse::<i32>()
+        .map_err(|e| bad_request(&format!("invalid token id: {:?}", e)))?;
+    let team = administered_team(req)?;
+    diesel::delete(TeamToken::belonging_to(&team).find(id)).execute(&*req.db_conn()?)?;
+    ok_true()
+}
@@ -27,6 +27,8 @@ pub fn new(req: &mut Request) -> CargoResult<Response> {
     #[derive(Deserialize, Serialize)]
     struct NewApiToken {
         name: String,
+        #[serde(default)]
+        allowed_ips: Vec<String>,
     }
 
     /// The incoming serialization format for the `ApiToken` model.
@@ -40,6 +42,8 @@ pub fn new(req: &mut Request) -> CargoResult<Response> {
             "cannot use an API token to create a new API token",
         ));
     }
+    // Tokens skip the verification of the session, so creating one takes it
+    req.require_two_factor(req.user()?)?;
 
     let max_size = 2000;
     let length = req.content_length()
@@ -62,6 +66,8 @@ pub fn new(req: &mut Request) -> CargoResult<Response> {
         return Err(bad_request("name must have a value"));
     }
 
+    let allowed_ips = ApiToken::parse_allowed_ips(&new.api_token.allowed_ips)?;
+
     let user = req.user()?;
 
     let max_token_per_user = 500;
@@ -75,7 +81,15 @@ pub fn new(req: &mut Request) -> CargoResult<Response> {
         )));
     }
 
-    let api_token = ApiToken::insert(&*req.db_conn()?, user.id, name)?;
+    let api_token = {
+        let conn = req.db_conn()?;
+        let api_token = ApiToken::insert(&conn, user.id, name)?;
+        if allowed_ips.is_empty() {
+            api_token
+        } else {
+            api_token.set_allowed_ips(&conn, &allowed_ips)?
+        }
+    };
 
     #[derive(Serialize)]
     struct R {
@@ -86,6 +100,50 @@ pub fn new(req: &mut Request) -> CargoResult<Response> {
     }))
 }
 
+/// Handles the `PUT /me/tokens/:id` route.
+///
+/// Replaces the address ranges the token can be used from, in CIDR notation.
+/// An empty list lets the token be used from anywhere.
+pub fn update(req: &mut Request) -> CargoResult<Response> {
+    #[derive(Deserialize)]
+    struct ApiTokenUpdate {
+        allowed_ips: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct ApiTokenUpdateRequest {
+        api_token: ApiTokenUpdate,
+    }
+
+    if req.authentication_source()? != AuthenticationSource::SessionCookie {
+        return Err(bad_request(
+            "cannot use an API token to change the addresses of an API token",
+        ));
+    }
+
+    let id = req.params()["id"]
+        .parse::<i32>()
+        .map_err(|e| bad_request(&format!("invalid token id: {:?}", e)))?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let update: ApiTokenUpdateRequest = json::from_str(&body)
+        .map_err(|e| bad_request(&format!("invalid token update: {:?}", e)))?;
+    let allowed_ips = ApiToken::parse_allowed_ips(&update.api_token.allowed_ips)?;
+
+    let conn = req.db_conn()?;
+    let api_token = ApiToken::belonging_to(req.user()?)
+        .find(id)
+        .first::<ApiToken>(&*conn)?
+        .set_allowed_ips(&conn, &allowed_ips)?;
+
+    #[derive(Serialize)]
+    struct R {
+        api_token: ApiToken,
+    }
+    Ok(req.json(&R { api_token }))
+}
+
 /// Handles the `DELETE /me/tokens/:id` route.
 pub fn revoke(req: &mut Request) -> CargoResult<Response> {
     let id = req.params()["id"]
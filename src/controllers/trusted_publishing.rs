@@ -0,0 +1,131 @@
+//! Management of the trusted publishers of a crate, and exchange of the OIDC
+//! tokens of their workflows for short-lived publish tokens.
+
+use serde_json;
+
+use super::prelude::*;
+
+use chrono::NaiveDateTime;
+
+use controllers::helpers::owned_crate;
+use models::{TrustedPublisher, TrustedPublishingToken};
+use schema::trusted_publishers;
+use trusted_publishing;
+
+const MANAGE: &str = "manage trusted publishers";
+
+/// Handles the `GET /crates/:crate_id/trusted_publishers` route.
+pub fn list(req: &mut Request) -> CargoResult<Response> {
+    let krate = owned_crate(req, MANAGE)?;
+    let conn = req.db_conn()?;
+    let trusted_publishers = TrustedPublisher::belonging_to(&krate)
+        .order(trusted_publishers::id)
+        .load(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        trusted_publishers: Vec<TrustedPublisher>,
+    }
+    Ok(req.json(&R { trusted_publishers }))
+}
+
+/// Handles the `PUT /crates/:crate_id/trusted_publishers` route.
+pub fn create(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    #[derive(Deserialize)]
+    struct NewTrustedPublisher {
+        repository_owner: String,
+        repository_name: String,
+        workflow_filename: String,
+    }
+    let new: NewTrustedPublisher =
+        serde_json::from_str(&body).map_err(|_| human("invalid json request"))?;
+    if new.repository_owner.is_empty() || new.repository_name.is_empty()
+        || new.workflow_filename.is_empty()
+    {
+        return Err(human(
+            "the repository owner, repository name and workflow filename are required",
+        ));
+    }
+
+    let krate = owned_crate(req, MANAGE)?;
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+    let trusted_publisher = TrustedPublisher::create(
+        &conn,
+        &krate,
+        user,
+        &new.repository_owner,
+        &new.repository_name,
+        &new.workflow_filename,
+    )?;
+
+    #[derive(Serialize)]
+    struct R {
+        trusted_publisher: TrustedPublisher,
+    }
+    Ok(req.json(&R { trusted_publisher }))
+}
+
+/// Handles the `DELETE /crates/:crate_id/trusted_publishers/:id` route.
+pub fn delete(req: &mut Request) -> CargoResult<Response> {
+    use diesel;
+
+    let krate = owned_crate(req, MANAGE)?;
+    let id = req.params()["id"]
+        .parse::<i32>()
+        .map_err(|_| human("invalid trusted publisher id"))?;
+    let conn = req.db_conn()?;
+    diesel::delete(TrustedPublisher::belonging_to(&krate).filter(trusted_publishers::id.eq(id)))
+        .execute(&*conn)?;
+    ok_true()
+}
+
+/// Handles the `PUT /trusted_publishing/tokens` route.
+///
+/// Exchanges the OIDC token of a GitHub Actions workflow, sent as
+/// `{"jwt": "..."}`, for a publish token valid for
+/// `trusted_publishing::TOKEN_LIFETIME` minutes. The publish token can only
+/// publish the crates the workflow is a trusted publisher of.
+pub fn exchange(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    #[derive(Deserialize)]
+    struct ExchangeRequest {
+        jwt: String,
+    }
+    let request: ExchangeRequest =
+        serde_json::from_str(&body).map_err(|_| human("invalid json request"))?;
+
+    let claims = trusted_publishing::verify(req.app(), &request.jwt)?;
+    let workflow_filename = claims
+        .workflow_filename()
+        .ok_or_else(|| human("the OIDC token doesn't identify a workflow of its repository"))?;
+    let conn = req.db_conn()?;
+    if !TrustedPublisher::any_matching(
+        &conn,
+        &claims.repository_owner,
+        claims.repository_name(),
+        workflow_filename,
+    )? {
+        return Err(human(&format_args!(
+            "no crate has `{}` with the workflow `{}` as trusted publisher",
+            claims.repository, workflow_filename
+        )));
+    }
+    let token = TrustedPublishingToken::create(&conn, &claims, workflow_filename)?;
+
+    #[derive(Serialize)]
+    struct R {
+        token: String,
+        #[serde(with = "::util::rfc3339")]
+        expires_at: NaiveDateTime,
+    }
+    Ok(req.json(&R {
+        token: token.token,
+        expires_at: token.expires_at,
+    }))
+}
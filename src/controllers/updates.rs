@@ -0,0 +1,27 @@
+//! Endpoint streaming the changes made to crates, see `updates`.
+
+use std::collections::HashMap;
+
+use super::prelude::*;
+
+/// Handles the `GET /updates/stream` route.
+///
+/// Sends the publishes, yanks and owner changes of crates as server-sent
+/// events as they happen, named after their action with the crate and the
+/// version as data. The stream stays open until the client disconnects.
+/// Responds with a `503` when too many clients are already connected.
+pub fn stream(req: &mut Request) -> CargoResult<Response> {
+    let updates = req.app().update_subscribers.subscribe()?;
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["text/event-stream".to_string()],
+    );
+    headers.insert("Cache-Control".to_string(), vec!["no-cache".to_string()]);
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(updates),
+    })
+}
@@ -1,14 +1,21 @@
 use controllers::prelude::*;
 
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use conduit_cookie::RequestSession;
 use serde_json;
 
 use controllers::helpers::Paginate;
 use email;
 use util::bad_request;
 
-use models::{Email, Follow, NewEmail, User, Version};
-use schema::{crates, emails, follows, users, versions};
-use views::{EncodablePrivateUser, EncodableVersion};
+use models::{AuditAction, CrateOwnerInvitation, Email, Follow, NewEmail, OwnerKind,
+             PendingPublish, Team, User, Version};
+use schema::{api_tokens, audit_actions, crate_downloads, crate_owner_invitations, crate_owners,
+             crates, emails, follows, users, versions};
+use views::{EncodableAuditAction, EncodableCrateOwnerInvitation, EncodablePrivateUser,
+            EncodableVersion};
 
 /// Handles the `GET /me` route.
 pub fn me(req: &mut Request) -> CargoResult<Response> {
@@ -41,15 +48,50 @@ pub fn me(req: &mut Request) -> CargoResult<Response> {
     let verification_sent = verified || verification_sent;
     let user = User { email, ..user };
 
+    let owned_crates = crate_owners::table
+        .filter(crate_owners::owner_id.eq(id))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+        .filter(crate_owners::deleted.eq(false))
+        .count()
+        .get_result(&*conn)?;
+    let pending_invitations = crate_owner_invitations::table
+        .filter(crate_owner_invitations::invited_user_id.eq(id))
+        .count()
+        .get_result(&*conn)?;
+    let api_tokens = api_tokens::table
+        .filter(api_tokens::user_id.eq(id))
+        .count()
+        .get_result(&*conn)?;
+
     #[derive(Serialize)]
     struct R {
         user: EncodablePrivateUser,
+        owned_crates: i64,
+        pending_invitations: i64,
+        api_tokens: i64,
     }
     Ok(req.json(&R {
         user: user.encodable_private(verified, verification_sent),
+        owned_crates,
+        pending_invitations,
+        api_tokens,
     }))
 }
 
+/// Handles the `DELETE /me` route.
+///
+/// Deletes the account of the current user, see `User::delete_account`.
+pub fn delete(req: &mut Request) -> CargoResult<Response> {
+    {
+        let user = req.user()?;
+        let conn = req.db_conn()?;
+        let orphan_team = req.app().config.orphan_team.as_ref().map(|s| &**s);
+        user.delete_account(&conn, orphan_team)?;
+    }
+    req.session().remove(&"user_id".to_string());
+    ok_true()
+}
+
 /// Handles the `GET /me/updates` route.
 pub fn updates(req: &mut Request) -> CargoResult<Response> {
     use diesel::dsl::any;
@@ -90,12 +132,148 @@ pub fn updates(req: &mut Request) -> CargoResult<Response> {
     }))
 }
 
+/// Handles the `GET /me/activity` route.
+///
+/// Lists what the user did to crates, the latest first: the versions they
+/// published, yanked or unyanked and the owners they added or removed.
+pub fn activity(req: &mut Request) -> CargoResult<Response> {
+    let user = req.user()?;
+    let (offset, limit) = req.pagination(20, 100)?;
+    let conn = req.db_conn()?;
+
+    let data = AuditAction::belonging_to(user)
+        .order(audit_actions::id.desc())
+        .paginate(limit, offset)
+        .load::<(AuditAction, i64)>(&*conn)?;
+    let total = data.first().map(|&(_, t)| t).unwrap_or(0);
+    let activity = data.into_iter()
+        .map(|(action, _)| action.encodable())
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        activity: Vec<EncodableAuditAction>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+    Ok(req.json(&R {
+        activity,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `GET /me/crates` route.
+///
+/// Lists the crates the user owns, directly or through one of their teams,
+/// along with their downloads over the last two weeks and the invitations
+/// to own crates the user hasn't answered yet.
+pub fn crates(req: &mut Request) -> CargoResult<Response> {
+    use chrono::{Duration, NaiveDate, Utc};
+    use diesel::dsl::any;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct OwnedCrate {
+        name: String,
+        /// `owner` if the user owns the crate, `team` if they can only
+        /// publish it as a member of the owning teams.
+        role: &'static str,
+        teams: Vec<String>,
+        downloads: i32,
+        downloads_last_week: i64,
+        downloads_previous_week: i64,
+    }
+
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+    let teams = Team::owning_crates_with_member(req.app(), &conn, user)?;
+    let team_ids = teams.iter().map(|team| team.id).collect::<Vec<_>>();
+
+    let owners = crate_owners::table
+        .inner_join(crates::table)
+        .filter(crate_owners::deleted.eq(false))
+        .filter(
+            crate_owners::owner_kind
+                .eq(OwnerKind::User as i32)
+                .and(crate_owners::owner_id.eq(user.id))
+                .or(crate_owners::owner_kind
+                    .eq(OwnerKind::Team as i32)
+                    .and(crate_owners::owner_id.eq(any(&team_ids)))),
+        )
+        .select((
+            crates::id,
+            crates::name,
+            crates::downloads,
+            crate_owners::owner_kind,
+            crate_owners::owner_id,
+        ))
+        .load::<(i32, String, i32, i32, i32)>(&*conn)?;
+
+    let mut owned = BTreeMap::new();
+    for (crate_id, name, downloads, owner_kind, owner_id) in owners {
+        let krate = owned.entry(crate_id).or_insert_with(|| OwnedCrate {
+            name,
+            role: "team",
+            teams: Vec::new(),
+            downloads,
+            downloads_last_week: 0,
+            downloads_previous_week: 0,
+        });
+        if owner_kind == OwnerKind::User as i32 {
+            krate.role = "owner";
+        } else if let Some(team) = teams.iter().find(|team| team.id == owner_id) {
+            krate.teams.push(team.login.clone());
+        }
+    }
+
+    let today = Utc::today().naive_utc();
+    let last_week = today - Duration::days(6);
+    let crate_ids = owned.keys().cloned().collect::<Vec<_>>();
+    let downloads = crate_downloads::table
+        .filter(crate_downloads::crate_id.eq(any(&crate_ids)))
+        .filter(crate_downloads::date.ge(last_week - Duration::days(7)))
+        .select((
+            crate_downloads::crate_id,
+            crate_downloads::date,
+            crate_downloads::downloads,
+        ))
+        .load::<(i32, NaiveDate, i32)>(&*conn)?;
+    for (crate_id, date, count) in downloads {
+        if let Some(krate) = owned.get_mut(&crate_id) {
+            if date >= last_week {
+                krate.downloads_last_week += i64::from(count);
+            } else {
+                krate.downloads_previous_week += i64::from(count);
+            }
+        }
+    }
+
+    let mut crates = owned.into_iter().map(|(_, krate)| krate).collect::<Vec<_>>();
+    crates.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let pending_invitations = crate_owner_invitations::table
+        .filter(crate_owner_invitations::invited_user_id.eq(user.id))
+        .load::<CrateOwnerInvitation>(&*conn)?
+        .into_iter()
+        .map(|invitation| invitation.encodable(&conn))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<OwnedCrate>,
+        pending_invitations: Vec<EncodableCrateOwnerInvitation>,
+    }
+    Ok(req.json(&R {
+        crates,
+        pending_invitations,
+    }))
+}
+
 /// Handles the `PUT /user/:user_id` route.
 pub fn update_user(req: &mut Request) -> CargoResult<Response> {
-    use self::emails::user_id;
-    use self::users::dsl::{email, gh_login, users};
-    use diesel::{insert_into, update};
-
     let mut body = String::new();
     req.body().read_to_string(&mut body)?;
     let user = req.user()?;
@@ -124,7 +302,44 @@ pub fn update_user(req: &mut Request) -> CargoResult<Response> {
         return Err(human("empty email rejected"));
     }
 
-    let user_email = user_update.user.email.unwrap();
+    set_email(&conn, user, &user_update.user.email.unwrap())?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+    }
+    Ok(req.json(&R { ok: true }))
+}
+
+/// Handles the `PUT /me/email` route.
+pub fn update_email(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+
+    #[derive(Deserialize)]
+    struct EmailUpdate {
+        email: String,
+    }
+
+    let update: EmailUpdate =
+        serde_json::from_str(&body).map_err(|_| human("invalid json request"))?;
+    set_email(&conn, user, &update.email)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+    }
+    Ok(req.json(&R { ok: true }))
+}
+
+/// Sets the email address of the user, and sends them a new token to verify it.
+fn set_email(conn: &PgConnection, user: &User, user_email: &str) -> CargoResult<()> {
+    use self::emails::user_id;
+    use self::users::dsl::{email, gh_login, users};
+    use diesel::{insert_into, update};
+
     let user_email = user_email.trim();
 
     if user_email == "" {
@@ -134,7 +349,7 @@ pub fn update_user(req: &mut Request) -> CargoResult<Response> {
     conn.transaction(|| {
         update(users.filter(gh_login.eq(&user.gh_login)))
             .set(email.eq(user_email))
-            .execute(&*conn)?;
+            .execute(conn)?;
 
         let new_email = NewEmail {
             user_id: user.id,
@@ -147,18 +362,12 @@ pub fn update_user(req: &mut Request) -> CargoResult<Response> {
             .do_update()
             .set(&new_email)
             .returning(emails::token)
-            .get_result::<String>(&*conn)
+            .get_result::<String>(conn)
             .map_err(|_| human("Error in creating token"))?;
 
         ::email::send_user_confirm_email(user_email, &user.gh_login, &token)
             .map_err(|_| bad_request("Email could not be sent"))
-    })?;
-
-    #[derive(Serialize)]
-    struct R {
-        ok: bool,
-    }
-    Ok(req.json(&R { ok: true }))
+    })
 }
 
 /// Handles the `PUT /confirm/:email_token` route
@@ -185,9 +394,6 @@ pub fn confirm_user_email(req: &mut Request) -> CargoResult<Response> {
 
 /// Handles `PUT /user/:user_id/resend` route
 pub fn regenerate_token_and_send(req: &mut Request) -> CargoResult<Response> {
-    use diesel::dsl::sql;
-    use diesel::update;
-
     let user = req.user()?;
     let name = &req.params()["user_id"].parse::<i32>().ok().unwrap();
     let conn = req.db_conn()?;
@@ -197,15 +403,63 @@ pub fn regenerate_token_and_send(req: &mut Request) -> CargoResult<Response> {
         return Err(human("current user does not match requested user"));
     }
 
+    resend_token(&conn, user)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+    }
+    Ok(req.json(&R { ok: true }))
+}
+
+/// Handles the `PUT /me/email/resend` route.
+pub fn resend_email(req: &mut Request) -> CargoResult<Response> {
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+    resend_token(&conn, user)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+    }
+    Ok(req.json(&R { ok: true }))
+}
+
+/// Generates a new verification token for the email of the user and sends it.
+fn resend_token(conn: &PgConnection, user: &User) -> CargoResult<()> {
+    use diesel::dsl::sql;
+    use diesel::update;
+
     conn.transaction(|| {
         let email = update(Email::belonging_to(user))
             .set(emails::token.eq(sql("DEFAULT")))
-            .get_result::<Email>(&*conn)
+            .get_result::<Email>(conn)
             .map_err(|_| bad_request("Email could not be found"))?;
 
         email::send_user_confirm_email(&email.email, &user.gh_login, &email.token)
             .map_err(|_| bad_request("Error in sending email"))
-    })?;
+    })
+}
+
+/// Handles the `PUT /me/settings` route.
+pub fn update_settings(req: &mut Request) -> CargoResult<Response> {
+    use diesel::update;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+
+    #[derive(Deserialize)]
+    struct Settings {
+        confirm_new_crates: bool,
+    }
+
+    let settings: Settings =
+        serde_json::from_str(&body).map_err(|_| human("invalid json request"))?;
+    update(user)
+        .set(users::confirm_new_crates.eq(settings.confirm_new_crates))
+        .execute(&*conn)?;
 
     #[derive(Serialize)]
     struct R {
@@ -213,3 +467,53 @@ pub fn regenerate_token_and_send(req: &mut Request) -> CargoResult<Response> {
     }
     Ok(req.json(&R { ok: true }))
 }
+
+/// Handles the `PUT /confirm_publish/:token` route.
+pub fn confirm_publish(req: &mut Request) -> CargoResult<Response> {
+    let conn = req.db_conn()?;
+    let token = &req.params()["token"];
+
+    if !PendingPublish::confirm(&conn, token)? {
+        return Err(bad_request("Publication belonging to token not found or expired."));
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+    }
+    Ok(req.json(&R { ok: true }))
+}
+
+/// Handles the `GET /confirm_publish/:token` route, the link sent by
+/// `email::send_publish_confirmation_email`.
+///
+/// Confirms the publication like `PUT /confirm_publish/:token` and tells the
+/// user to publish again, as a page since it's opened from an email.
+pub fn confirm_publish_link(req: &mut Request) -> CargoResult<Response> {
+    let confirmed = {
+        let conn = req.db_conn()?;
+        PendingPublish::confirm(&conn, &req.params()["token"])?
+    };
+    let message = if confirmed {
+        "The publication is confirmed, you can now publish the crate again."
+    } else {
+        "This link expired or was already used, please publish the crate again \
+         to receive a new one."
+    };
+    let body = format!(
+        "<!DOCTYPE html>\n<html><head><title>crates.io</title></head>\
+         <body><p>{}</p></body></html>\n",
+        message
+    );
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["text/html; charset=utf-8".to_string()],
+    );
+    Ok(Response {
+        status: if confirmed { (200, "OK") } else { (404, "Not Found") },
+        headers,
+        body: Box::new(Cursor::new(body.into_bytes())),
+    })
+}
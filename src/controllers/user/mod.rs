@@ -1,3 +1,4 @@
 pub mod me;
 pub mod other;
 pub mod session;
+pub mod two_factor;
@@ -119,5 +119,6 @@ pub fn github_access_token(req: &mut Request) -> CargoResult<Response> {
 /// Handles the `GET /logout` route.
 pub fn logout(req: &mut Request) -> CargoResult<Response> {
     req.session().remove(&"user_id".to_string());
+    req.session().remove(&"two_factor_verified".to_string());
     Ok(req.json(&true))
 }
@@ -0,0 +1,131 @@
+//! Enrollment in TOTP two-factor authentication, and verification of the
+//! sessions of enrolled users.
+
+use controllers::prelude::*;
+
+use conduit_cookie::RequestSession;
+use serde_json;
+
+use models::TwoFactorCredential;
+use totp;
+use util::bad_request;
+
+/// The body of the routes taking a code from the authenticator app, or one
+/// of the recovery codes instead.
+#[derive(Deserialize)]
+struct CodeRequest {
+    code: Option<String>,
+    recovery_code: Option<String>,
+}
+
+fn read_code_request(req: &mut Request) -> CargoResult<CodeRequest> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))
+}
+
+/// Checks the code or recovery code sent against an enabled credential.
+fn check_code(
+    conn: &PgConnection,
+    credential: &TwoFactorCredential,
+    request: &CodeRequest,
+) -> CargoResult<()> {
+    let valid = match (&request.code, &request.recovery_code) {
+        (&Some(ref code), _) => credential.verify(conn, code)?,
+        (&None, &Some(ref code)) => credential.use_recovery_code(conn, code)?,
+        (&None, &None) => return Err(bad_request("a code or a recovery code is required")),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(human("invalid two-factor authentication code"))
+    }
+}
+
+fn enabled_credential(conn: &PgConnection, user_id: i32) -> CargoResult<TwoFactorCredential> {
+    match TwoFactorCredential::find(conn, user_id)? {
+        Some(ref credential) if credential.is_enabled() => Ok(credential.clone()),
+        _ => Err(human("two-factor authentication is not enabled")),
+    }
+}
+
+/// Handles the `POST /me/2fa` route.
+///
+/// Starts enrolling the current user, returning the secret to add to their
+/// authenticator app. The enrollment is confirmed with `PUT /me/2fa`.
+pub fn begin(req: &mut Request) -> CargoResult<Response> {
+    let user = req.user()?;
+    let conn = req.db_conn()?;
+    let credential = TwoFactorCredential::begin(&conn, user.id)?;
+
+    #[derive(Serialize)]
+    struct R {
+        secret: String,
+        otpauth_url: String,
+    }
+    Ok(req.json(&R {
+        otpauth_url: totp::otpauth_url(&credential.secret, &user.gh_login),
+        secret: credential.secret,
+    }))
+}
+
+/// Handles the `PUT /me/2fa` route.
+///
+/// Enables two-factor authentication once the user sends a first code,
+/// returning the recovery codes. They are only ever shown this once.
+pub fn enable(req: &mut Request) -> CargoResult<Response> {
+    let request = read_code_request(req)?;
+    let code = request
+        .code
+        .ok_or_else(|| bad_request("a code is required"))?;
+    let user_id = req.user()?.id;
+
+    let recovery_codes = {
+        let conn = req.db_conn()?;
+        let credential = TwoFactorCredential::find(&conn, user_id)?
+            .ok_or_else(|| human("two-factor authentication enrollment was not started"))?;
+        credential.enable(&conn, &code)?
+    };
+
+    // The code was just checked, no need to ask for another one
+    req.session()
+        .insert("two_factor_verified".to_string(), user_id.to_string());
+
+    #[derive(Serialize)]
+    struct R {
+        recovery_codes: Vec<String>,
+    }
+    Ok(req.json(&R { recovery_codes }))
+}
+
+/// Handles the `DELETE /me/2fa` route.
+pub fn disable(req: &mut Request) -> CargoResult<Response> {
+    let request = read_code_request(req)?;
+    let user_id = req.user()?.id;
+    {
+        let conn = req.db_conn()?;
+        let credential = enabled_credential(&conn, user_id)?;
+        check_code(&conn, &credential, &request)?;
+        credential.disable(&conn)?;
+    }
+    req.session().remove(&"two_factor_verified".to_string());
+    ok_true()
+}
+
+/// Handles the `PUT /me/2fa/verify` route.
+///
+/// Verifies the session of the current user with a code, which publishing
+/// and changing owners require when the registry enforces two-factor
+/// authentication.
+pub fn verify(req: &mut Request) -> CargoResult<Response> {
+    let request = read_code_request(req)?;
+    let user_id = req.user()?.id;
+    {
+        let conn = req.db_conn()?;
+        let credential = enabled_credential(&conn, user_id)?;
+        check_code(&conn, &credential, &request)?;
+    }
+    req.session()
+        .insert("two_factor_verified".to_string(), user_id.to_string());
+    ok_true()
+}
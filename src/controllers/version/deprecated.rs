@@ -5,6 +5,7 @@
 //! period of time to ensure there are no external users of an endpoint before
 //! it is removed.
 
+use controllers::helpers::encode_versions;
 use controllers::prelude::*;
 
 use url;
@@ -65,11 +66,12 @@ pub fn show(req: &mut Request) -> CargoResult<Response> {
         }
     };
 
+    let conn = req.db_conn()?;
+    let version = encode_versions(req, &conn, &krate, vec![version])?.remove(0);
+
     #[derive(Serialize)]
     struct R {
         version: EncodableVersion,
     }
-    Ok(req.json(&R {
-        version: version.encodable(&krate.name),
-    }))
+    Ok(req.json(&R { version }))
 }
@@ -6,59 +6,166 @@ use controllers::prelude::*;
 
 use chrono::{Duration, NaiveDate, Utc};
 
-use Replica;
+use download_routing::Backend;
+use middleware::{CachePolicy, HeadRequest};
+use util::client_ip;
+use util::errors::NotFound;
+use {Replica, Uploader};
 
-use models::{Crate, VersionDownload};
+use models::{Crate, CrateRename, DownloadBreakdown, VersionDownload};
 use schema::*;
 use views::EncodableVersionDownload;
 
 use super::version_and_crate;
 
+/// Sent in the `X-Cargo-Yanked` header of the downloads of yanked versions.
+const YANKED_REASON: &str = "this version has been yanked by the owners of the crate";
+
 /// Handles the `GET /crates/:crate_id/:version/download` route.
 /// This returns a URL to the location where the crate is stored.
+///
+/// HEAD requests, which mirrors send to check their copies, get the same
+/// response but aren't counted as downloads. Only their responses can be
+/// cached: a GET served by a cache wouldn't be counted, and would keep going
+/// to the backend chosen by `Config::download_routing` when it was cached.
 pub fn download(req: &mut Request) -> CargoResult<Response> {
+    let head = req.extensions().find::<HeadRequest>().is_some();
+    if head {
+        req.mut_extensions().insert(CachePolicy::Redirect);
+    }
+
     let crate_name = &req.params()["crate_id"];
     let version = &req.params()["version"];
 
+    // Private registries only hand out signed URLs to their users.
+    if req.app().config.uploader.signs_downloads() {
+        req.user()?;
+    }
+
+    // Crates held for review can't be downloaded until they are approved, not
+    // even from mirrors, which ignore the failures to count downloads below.
+    if is_held(req, crate_name)? {
+        return Err(Box::new(NotFound));
+    }
+
     // If we are a mirror, ignore failure to update download counts.
     // API-only mirrors won't have any crates in their database, and
     // incrementing the download count will look up the crate in the
     // database. Mirrors just want to pass along a redirect URL.
-    if req.app().config.mirror == Replica::ReadOnlyMirror {
-        let _ = increment_download_counts(req, crate_name, version);
+    let (yanked, stored_name) = if req.app().config.mirror == Replica::ReadOnlyMirror {
+        increment_download_counts(req, crate_name, version, head).unwrap_or((false, None))
     } else {
-        increment_download_counts(req, crate_name, version)?;
+        increment_download_counts(req, crate_name, version, head)?
+    };
+    // The files are stored under the name the crate was published with, not
+    // the variant of it that was requested.
+    let file_name = stored_name.as_ref().map_or(&**crate_name, String::as_str);
+    if file_name != crate_name {
+        info!(
+            "download of `{}` {} requested as `{}`",
+            file_name, version, crate_name
+        );
+    }
+    if !head {
+        req.app().metrics.record_download();
     }
 
-    let redirect_url = req.app()
+    let mut redirect_url = req.app()
         .config
         .uploader
-        .crate_location(crate_name, version)
+        .crate_location(file_name, version)
         .ok_or_else(|| human("crate files not found"))?;
 
-    if req.wants_json() {
+    // When migrating between storage backends, send a share of the downloads
+    // to the new backend.
+    if let Some(ref routing) = req.app().config.download_routing {
+        let backend = routing.route(crate_name);
+        if backend == Backend::Secondary {
+            redirect_url = routing.secondary_location(&Uploader::crate_path(file_name, version));
+        }
+        req.app().download_routing_metrics.record(backend);
+    }
+
+    let mut response = if req.wants_json() {
         #[derive(Serialize)]
         struct R {
             url: String,
         }
-        Ok(req.json(&R { url: redirect_url }))
+        req.json(&R { url: redirect_url })
     } else {
-        Ok(req.redirect(redirect_url))
+        req.redirect(redirect_url)
+    };
+    if yanked {
+        response.headers.insert(
+            "X-Cargo-Yanked".to_string(),
+            vec![YANKED_REASON.to_string()],
+        );
     }
+    Ok(response)
+}
+
+/// Returns whether the crate is held for review, see `HeldIndexEntry`. Crates
+/// that aren't found, e.g. by mirrors, aren't held.
+fn is_held(req: &Request, crate_name: &str) -> CargoResult<bool> {
+    let conn = req.db_conn()?;
+    let held = Crate::by_name(crate_name)
+        .select(crates::publish_hold)
+        .first::<bool>(&*conn)
+        .optional()?;
+    Ok(held.unwrap_or(false))
 }
 
-fn increment_download_counts(req: &Request, crate_name: &str, version: &str) -> CargoResult<()> {
+/// Counts a download of the version unless `head` is set, returning whether
+/// it was flagged as the download of a yanked version under
+/// `Config::warn_yanked_downloads`, and the name its files are stored under.
+/// That is the name of the crate as published, or its former name if the
+/// version predates a rename.
+fn increment_download_counts(
+    req: &Request,
+    crate_name: &str,
+    version: &str,
+    head: bool,
+) -> CargoResult<(bool, Option<String>)> {
     use self::versions::dsl::*;
 
     let conn = req.db_conn()?;
-    let version_id = versions
-        .select(id)
-        .filter(crate_id.eq_any(Crate::by_name(crate_name).select(crates::id)))
+    let crate_ids = Crate::by_name(crate_name).select(crates::id);
+    let (version_id, version_crate_id, is_yanked, name) = versions
+        .inner_join(crates::table)
+        .select((id, crate_id, yanked, crates::name))
+        .filter(crate_id.eq_any(crate_ids))
         .filter(num.eq(version))
-        .first(&*conn)?;
+        .first::<(i32, i32, bool, String)>(&*conn)?;
 
-    VersionDownload::create_or_increment(version_id, &conn)?;
-    Ok(())
+    let flagged = is_yanked && req.app().config.warn_yanked_downloads;
+    if !head {
+        let unique = req.app().config.unique_downloads
+            && req.app()
+                .download_filter
+                .insert(&client_ip(req), version_id, Utc::today().naive_utc());
+        VersionDownload::create_or_increment(version_id, flagged, unique, &conn)?;
+        if req.app().config.download_breakdown {
+            let header = |name: &str| {
+                req.headers()
+                    .find(name)
+                    .and_then(|values| values.first().map(|s| s.to_string()))
+            };
+            let user_agent = header("User-Agent");
+            let country = req.app()
+                .config
+                .download_country_header
+                .as_ref()
+                .and_then(|name| header(name));
+            DownloadBreakdown::increment(
+                &conn,
+                version_id,
+                user_agent.as_ref().map(|s| &**s),
+                country.as_ref().map(|s| &**s),
+            )?;
+        }
+    }
+    let published_name = CrateRename::published_name(&conn, version_crate_id, version_id)?;
+    Ok((flagged, Some(published_name.unwrap_or(name))))
 }
 
 /// Handles the `GET /crates/:crate_id/:version/downloads` route.
@@ -0,0 +1,88 @@
+//! Endpoints listing the files of the crate files of versions and comparing
+//! them between versions, for reviewing what changed in a release.
+
+use controllers::prelude::*;
+use models::{Crate, Version, VersionFile};
+use schema::versions;
+use views::{EncodableFileChange, EncodableVersionFile};
+
+use super::version_and_crate;
+
+/// Handles the `GET /crates/:crate_id/:version/files` route.
+pub fn files(req: &mut Request) -> CargoResult<Response> {
+    let (version, krate) = version_and_crate(req)?;
+    let conn = req.db_conn()?;
+    let files = version_files(&conn, &krate, &version)?
+        .into_iter()
+        .map(VersionFile::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        files: Vec<EncodableVersionFile>,
+    }
+    Ok(req.json(&R { files }))
+}
+
+/// Handles the `GET /crates/:crate_id/diff` route.
+///
+/// Lists the files added, removed and changed between the versions given by
+/// the `from` and `to` parameters.
+pub fn diff(req: &mut Request) -> CargoResult<Response> {
+    let query = req.query();
+    let param = |name: &str| {
+        query
+            .get(name)
+            .cloned()
+            .ok_or_else(|| human(&format_args!("missing the `{}` version", name)))
+    };
+    let (from, to) = (param("from")?, param("to")?);
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(&req.params()["crate_id"]).first::<Crate>(&*conn)?;
+    let find_version = |num: &str| {
+        Version::belonging_to(&krate)
+            .filter(versions::num.eq(num))
+            .first::<Version>(&*conn)
+            .map_err(|_| {
+                human(&format_args!(
+                    "crate `{}` does not have a version `{}`",
+                    krate.name, num
+                ))
+            })
+    };
+    let from_files = version_files(&conn, &krate, &find_version(&from)?)?;
+    let to_files = version_files(&conn, &krate, &find_version(&to)?)?;
+    let diff = VersionFile::diff(from_files, to_files);
+
+    #[derive(Serialize)]
+    struct R {
+        from: String,
+        to: String,
+        added: Vec<EncodableVersionFile>,
+        removed: Vec<EncodableVersionFile>,
+        changed: Vec<EncodableFileChange>,
+    }
+    Ok(req.json(&R {
+        from,
+        to,
+        added: diff.added.into_iter().map(VersionFile::encodable).collect(),
+        removed: diff.removed.into_iter().map(VersionFile::encodable).collect(),
+        changed: diff.changed
+            .into_iter()
+            .map(|(from, to)| VersionFile::encodable_change(from, to))
+            .collect(),
+    }))
+}
+
+fn version_files(
+    conn: &PgConnection,
+    krate: &Crate,
+    version: &Version,
+) -> CargoResult<Vec<VersionFile>> {
+    VersionFile::for_version(conn, version)?.ok_or_else(|| {
+        human(&format_args!(
+            "the files of version `{}` of crate `{}` weren't listed",
+            version.num, krate.name
+        ))
+    })
+}
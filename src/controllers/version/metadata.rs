@@ -6,8 +6,9 @@
 
 use controllers::prelude::*;
 
+use models::VersionScanResult;
 use schema::*;
-use views::{EncodableDependency, EncodablePublicUser};
+use views::{EncodableDependency, EncodablePublicUser, EncodableVersionScanResult};
 
 use super::version_and_crate;
 
@@ -60,3 +61,24 @@ pub fn authors(req: &mut Request) -> CargoResult<Response> {
         meta: Meta { names },
     }))
 }
+
+/// Handles the `GET /crates/:crate_id/:version/scan_results` route.
+///
+/// Only available to administrators, to help triaging incidents.
+pub fn scan_results(req: &mut Request) -> CargoResult<Response> {
+    req.admin()?;
+    let (version, _) = version_and_crate(req)?;
+    let conn = req.db_conn()?;
+    let scan_results = VersionScanResult::belonging_to(&version)
+        .order(version_scan_results::scanned_at.desc())
+        .load::<VersionScanResult>(&*conn)?
+        .into_iter()
+        .map(VersionScanResult::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        scan_results: Vec<EncodableVersionScanResult>,
+    }
+    Ok(req.json(&R { scan_results }))
+}
@@ -1,6 +1,8 @@
 pub mod deprecated;
 pub mod downloads;
+pub mod files;
 pub mod metadata;
+pub mod sources;
 pub mod yank;
 
 use super::prelude::*;
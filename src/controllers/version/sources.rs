@@ -0,0 +1,85 @@
+//! Endpoints serving the readme and the `Cargo.toml` of a version as they
+//! were published, without unpacking its crate file, and the signature its
+//! crate file was published with.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use controllers::prelude::*;
+use models::{VersionSignature, VersionSource};
+use render;
+
+use super::version_and_crate;
+
+/// Handles the `GET /crates/:crate_id/:version/readme_raw` route.
+pub fn readme_raw(req: &mut Request) -> CargoResult<Response> {
+    let (source, _) = version_source(req)?;
+    let readme = source.readme.ok_or_else(|| human("version readme not found"))?;
+    Ok(text_response(readme, "text/plain; charset=utf-8"))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/readme_rendered` route.
+///
+/// Renders the readme to HTML like it was when the version was published,
+/// relative links being resolved against the repository of the crate.
+pub fn readme_rendered(req: &mut Request) -> CargoResult<Response> {
+    let (source, repository) = version_source(req)?;
+    let readme = source
+        .readme
+        .as_ref()
+        .ok_or_else(|| human("version readme not found"))?;
+    let html = render::readme_to_html(
+        readme,
+        source.readme_file.as_ref().map_or("README.md", |s| &**s),
+        repository.as_ref().map(|s| &**s),
+    )?;
+    Ok(text_response(html, "text/html; charset=utf-8"))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/manifest` route, the
+/// `Cargo.toml` found in the crate file.
+pub fn manifest(req: &mut Request) -> CargoResult<Response> {
+    let (source, _) = version_source(req)?;
+    let manifest = source
+        .manifest
+        .ok_or_else(|| human("version manifest not found"))?;
+    Ok(text_response(manifest, "text/plain; charset=utf-8"))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/signature` route, the detached
+/// signature of the crate file sent by its publisher.
+pub fn signature(req: &mut Request) -> CargoResult<Response> {
+    let (version, krate) = version_and_crate(req)?;
+    let conn = req.db_conn()?;
+    let signature = VersionSignature::find(&conn, &version)?.ok_or_else(|| {
+        human(&format_args!(
+            "version `{}` of crate `{}` wasn't published with a signature",
+            version.num, krate.name
+        ))
+    })?;
+    Ok(text_response(signature.signature, "text/plain; charset=utf-8"))
+}
+
+/// Returns the sources of the version, and the repository of its crate.
+fn version_source(req: &mut Request) -> CargoResult<(VersionSource, Option<String>)> {
+    let (version, krate) = version_and_crate(req)?;
+    let conn = req.db_conn()?;
+    let source = VersionSource::find(&conn, &version)?.ok_or_else(|| {
+        human(&format_args!(
+            "the sources of version `{}` of crate `{}` weren't kept",
+            version.num, krate.name
+        ))
+    })?;
+    Ok((source, krate.repository))
+}
+
+fn text_response(body: String, content_type: &str) -> Response {
+    let mut headers = HashMap::new();
+    headers.insert("Content-Type".to_string(), vec![content_type.to_string()]);
+    headers.insert("Content-Length".to_string(), vec![body.len().to_string()]);
+    Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(body.into_bytes())),
+    }
+}
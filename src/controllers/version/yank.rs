@@ -4,10 +4,11 @@ use controllers::prelude::*;
 
 use diesel;
 
+use cdn;
 use git;
 use util::errors::CargoError;
 
-use models::Rights;
+use models::{AuditAction, CrateWebhook, Rights};
 use schema::*;
 
 use super::version_and_crate;
@@ -45,9 +46,14 @@ fn modify_yank(req: &mut Request, yanked: bool) -> CargoResult<Response> {
             diesel::update(&version)
                 .set(versions::yanked.eq(yanked))
                 .execute(&*conn)?;
+            krate.update_max_versions(&conn)?;
             git::yank(&**req.app(), &krate.name, &version.num, yanked)?;
+            let event = if yanked { "yank" } else { "unyank" };
+            CrateWebhook::enqueue(&conn, &krate, event, &version.num)?;
+            AuditAction::record(&conn, user, &krate, Some(&version.num), event, None)?;
             Ok(())
         })?;
+        cdn::purge(req.app(), cdn::crate_paths(&krate.name, &version.num));
     }
 
     #[derive(Serialize)]
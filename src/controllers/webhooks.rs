@@ -0,0 +1,72 @@
+//! Management of the webhooks notified of the publications and yanks of a
+//! crate's versions.
+
+use serde_json;
+
+use super::prelude::*;
+
+use controllers::helpers::owned_crate;
+use models::CrateWebhook;
+use schema::crate_webhooks;
+use webhooks;
+
+const MANAGE: &str = "manage webhooks";
+
+/// Handles the `GET /crates/:crate_id/webhooks` route.
+pub fn list(req: &mut Request) -> CargoResult<Response> {
+    let krate = owned_crate(req, MANAGE)?;
+    let conn = req.db_conn()?;
+    let webhooks = CrateWebhook::belonging_to(&krate)
+        .order(crate_webhooks::id)
+        .load(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        webhooks: Vec<CrateWebhook>,
+    }
+    Ok(req.json(&R { webhooks }))
+}
+
+/// Handles the `PUT /crates/:crate_id/webhooks` route.
+///
+/// The secret the payloads are signed with is only returned here.
+pub fn create(req: &mut Request) -> CargoResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+
+    #[derive(Deserialize)]
+    struct NewWebhook {
+        url: String,
+    }
+    let new: NewWebhook =
+        serde_json::from_str(&body).map_err(|_| human("invalid json request"))?;
+    webhooks::check_url(&new.url)?;
+
+    let krate = owned_crate(req, MANAGE)?;
+    let conn = req.db_conn()?;
+    let webhook = CrateWebhook::create(&conn, &krate, &new.url)?;
+
+    #[derive(Serialize)]
+    struct R {
+        webhook: CrateWebhook,
+        secret: String,
+    }
+    Ok(req.json(&R {
+        secret: webhook.secret.clone(),
+        webhook,
+    }))
+}
+
+/// Handles the `DELETE /crates/:crate_id/webhooks/:id` route.
+pub fn delete(req: &mut Request) -> CargoResult<Response> {
+    use diesel;
+
+    let krate = owned_crate(req, MANAGE)?;
+    let id = req.params()["id"]
+        .parse::<i32>()
+        .map_err(|_| human("invalid webhook id"))?;
+    let conn = req.db_conn()?;
+    diesel::delete(CrateWebhook::belonging_to(&krate).filter(crate_webhooks::id.eq(id)))
+        .execute(&*conn)?;
+    ok_true()
+}
@@ -9,7 +9,7 @@ use middleware::app::RequestApp;
 use util::CargoResult;
 
 pub type DieselPool = r2d2::Pool<ConnectionManager<PgConnection>>;
-type DieselPooledConn = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
+pub type DieselPooledConn = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
 
 pub fn connect_now() -> ConnectionResult<PgConnection> {
     use diesel::Connection;
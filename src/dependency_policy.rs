@@ -0,0 +1,192 @@
+//! The dependencies crates can be published with, for internal registries.
+//!
+//! crates.io only rejects the `*` requirement. Internal registries can also
+//! reject requirements with a wildcard in any of their parts, like `1.*`, and
+//! only allow depending on an allowlist of crates. Requirements only matched
+//! by yanked versions are warned about, or rejected.
+//!
+//! Dependencies on crates of other registries are rejected, unless the URL
+//! of the registry is on the allowlist of registries.
+
+use semver;
+
+use util::{human, CargoResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct DependencyPolicy {
+    /// Reject the requirements with a wildcard in them, not only `*`.
+    pub ban_wildcards: bool,
+    /// The only crates that can be depended on, any crate if unset.
+    pub allowed_crates: Option<Vec<String>>,
+    /// Reject the requirements no version that isn't yanked matches, instead
+    /// of warning about them.
+    pub reject_yanked: bool,
+    /// The URLs of the registries whose crates can be depended on.
+    pub allowed_registries: Vec<String>,
+}
+
+impl DependencyPolicy {
+    /// Parses an allowlist of crate names formatted as `name,other-name`.
+    pub fn parse_allowlist(s: &str) -> Vec<String> {
+        s.split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    /// Checks a dependency on the crate named `name`, returning why it isn't
+    /// allowed.
+    pub fn check(&self, name: &str, req: &semver::VersionReq) -> CargoResult<()> {
+        self.check_wildcards(name, req)?;
+        if let Some(ref allowed) = self.allowed_crates {
+            if !allowed.iter().any(|a| canonical(a) == canonical(name)) {
+                return Err(human(&format_args!(
+                    "crates published to this registry can't depend on `{}`, \
+                     which isn't on the allowlist of dependencies",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a dependency on the crate named `name` of the registry at the
+    /// URL `registry`, returning why it isn't allowed.
+    pub fn check_registry(
+        &self,
+        name: &str,
+        req: &semver::VersionReq,
+        registry: &str,
+    ) -> CargoResult<()> {
+        self.check_wildcards(name, req)?;
+        let url = registry.trim_right_matches('/');
+        if !self.allowed_registries
+            .iter()
+            .any(|a| a.trim_right_matches('/') == url)
+        {
+            return Err(human(&format_args!(
+                "crates published to this registry can't depend on `{}` from `{}`, \
+                 which isn't on the allowlist of registries",
+                name, registry
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_wildcards(&self, name: &str, req: &semver::VersionReq) -> CargoResult<()> {
+        if self.ban_wildcards && req.to_string().contains('*') {
+            return Err(human(&format_args!(
+                "the requirement `{}` on `{}` has a wildcard, which this \
+                 registry doesn't allow. Use a requirement like `^1.2` instead",
+                req, name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that a version that isn't yanked matches the requirement on the
+    /// crate named `name`, given the versions of the crate along with whether
+    /// they are yanked. Returns a warning if none does, unless they are
+    /// rejected.
+    pub fn check_versions(
+        &self,
+        name: &str,
+        req: &semver::VersionReq,
+        versions: &[(semver::Version, bool)],
+    ) -> CargoResult<Option<String>> {
+        let matching = versions
+            .iter()
+            .filter(|&&(ref v, _)| req.matches(v))
+            .collect::<Vec<_>>();
+        if matching.iter().any(|&&(_, yanked)| !yanked) {
+            return Ok(None);
+        }
+        let problem = if matching.is_empty() {
+            format!("no version of `{}` matches the requirement `{}`", name, req)
+        } else {
+            format!(
+                "every version of `{}` matching the requirement `{}` is yanked",
+                name, req
+            )
+        };
+        if self.reject_yanked {
+            Err(human(&format_args!(
+                "{}, so this crate couldn't be built",
+                problem
+            )))
+        } else {
+            Ok(Some(format!("{}, so this crate may not build", problem)))
+        }
+    }
+}
+
+/// Crate names are compared like `canon_crate_name` does in the database.
+fn canonical(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DependencyPolicy;
+    use semver::VersionReq;
+
+    fn req(s: &str) -> VersionReq {
+        VersionReq::parse(s).unwrap()
+    }
+
+    #[test]
+    fn wildcards_can_be_banned() {
+        let mut policy = DependencyPolicy::default();
+        assert!(policy.check("foo", &req("1.*")).is_ok());
+
+        policy.ban_wildcards = true;
+        assert!(policy.check("foo", &req("1.*")).is_err());
+        assert!(policy.check("foo", &req("1.2.x")).is_err());
+        assert!(policy.check("foo", &req("^1.2")).is_ok());
+        assert!(policy.check("foo", &req(">= 1.0, < 2.0")).is_ok());
+    }
+
+    #[test]
+    fn dependencies_can_be_allowlisted() {
+        let policy = DependencyPolicy {
+            allowed_crates: Some(DependencyPolicy::parse_allowlist("serde, foo-bar,")),
+            ..DependencyPolicy::default()
+        };
+        assert!(policy.check("serde", &req("1")).is_ok());
+        assert!(policy.check("Foo_Bar", &req("1")).is_ok());
+        assert!(policy.check("rand", &req("1")).is_err());
+    }
+
+    #[test]
+    fn registries_can_be_allowlisted() {
+        let mut policy = DependencyPolicy::default();
+        let registry = "https://example.com/index";
+        assert!(policy.check_registry("foo", &req("1"), registry).is_err());
+
+        policy.allowed_registries = DependencyPolicy::parse_allowlist("https://example.com/index/");
+        assert!(policy.check_registry("foo", &req("1"), registry).is_ok());
+        assert!(policy.check_registry("foo", &req("1"), "https://other.com/index").is_err());
+
+        policy.ban_wildcards = true;
+        assert!(policy.check_registry("foo", &req("1.*"), registry).is_err());
+    }
+
+    #[test]
+    fn yanked_only_requirements_are_warned_about() {
+        use semver::Version;
+
+        let versions = vec![
+            (Version::parse("1.0.0").unwrap(), false),
+            (Version::parse("2.0.0").unwrap(), true),
+        ];
+        let mut policy = DependencyPolicy::default();
+        assert_eq!(policy.check_versions("foo", &req("^1"), &versions).unwrap(), None);
+        assert!(policy.check_versions("foo", &req("^2"), &versions).unwrap().is_some());
+        assert!(policy.check_versions("foo", &req("^3"), &versions).unwrap().is_some());
+
+        policy.reject_yanked = true;
+        assert!(policy.check_versions("foo", &req("^1"), &versions).is_ok());
+        assert!(policy.check_versions("foo", &req("^2"), &versions).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Deduplication of the downloads made from the same IP address.
+//!
+//! CI systems re-download the same versions over and over, which inflates the
+//! download counts. When `Config::unique_downloads` is set, the first download
+//! of a version by an IP address on a given day is also counted in
+//! `version_downloads.unique_downloads`. The (IP, version, day) triples seen
+//! so far are kept in a bloom filter that is cleared every day, so a few
+//! unique downloads may be missed but memory use stays constant.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use chrono::NaiveDate;
+
+/// The size of the filter, in bits (2MiB).
+const FILTER_BITS: usize = 1 << 24;
+/// The number of bits set in the filter for each download.
+const HASHES: u64 = 4;
+
+#[derive(Debug, Default)]
+pub struct DownloadFilter {
+    /// The day the filter is for, and the bits of the filter.
+    state: Mutex<Option<(NaiveDate, Vec<u64>)>>,
+}
+
+impl DownloadFilter {
+    /// Records a download of the version from `ip` on `date`, returning
+    /// whether it's the first one of the day.
+    pub fn insert(&self, ip: &str, version_id: i32, date: NaiveDate) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let is_today = match *state {
+            Some((day, _)) => day == date,
+            None => false,
+        };
+        if !is_today {
+            *state = Some((date, vec![0; FILTER_BITS / 64]));
+        }
+        let bits = &mut state.as_mut().unwrap().1;
+
+        let mut seen = true;
+        for seed in 0..HASHES {
+            let mut hasher = DefaultHasher::new();
+            (seed, ip, version_id).hash(&mut hasher);
+            let bit = hasher.finish() as usize % FILTER_BITS;
+            let (word, mask) = (bit / 64, 1 << (bit % 64));
+            if bits[word] & mask == 0 {
+                seen = false;
+                bits[word] |= mask;
+            }
+        }
+        !seen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DownloadFilter;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn dedups_downloads_per_ip_version_and_day() {
+        let filter = DownloadFilter::default();
+        let today = NaiveDate::from_ymd(2018, 5, 25);
+        assert!(filter.insert("10.0.0.1", 1, today));
+        assert!(!filter.insert("10.0.0.1", 1, today));
+        assert!(filter.insert("10.0.0.2", 1, today));
+        assert!(filter.insert("10.0.0.1", 2, today));
+
+        let tomorrow = today.succ();
+        assert!(filter.insert("10.0.0.1", 1, tomorrow));
+        assert!(!filter.insert("10.0.0.1", 1, tomorrow));
+    }
+}
@@ -0,0 +1,151 @@
+//! Weighted routing of crate downloads between two storage backends.
+//!
+//! While moving crate files from one storage backend to another (e.g. from the
+//! original bucket to a new CDN), a share of the download redirects can be sent
+//! to the secondary backend to validate it with real traffic before switching
+//! over completely. Some crates can be pinned to either backend, and the number
+//! of redirects sent to each backend is counted.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::{thread_rng, Rng};
+
+/// One of the two storage backends downloads can be routed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// The backend of the configured `Uploader`.
+    Primary,
+    /// The backend being migrated to.
+    Secondary,
+}
+
+impl Backend {
+    pub fn parse(s: &str) -> Option<Backend> {
+        match s {
+            "primary" => Some(Backend::Primary),
+            "secondary" => Some(Backend::Secondary),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Backend::Primary => "primary",
+            Backend::Secondary => "secondary",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DownloadRouting {
+    /// The host crate files are served from by the secondary backend.
+    pub secondary_host: String,
+    /// The percentage of downloads routed to the secondary backend.
+    pub secondary_weight: u32,
+    /// Crates that are always served from the given backend.
+    pub overrides: HashMap<String, Backend>,
+}
+
+impl DownloadRouting {
+    /// Parses a list of overrides formatted as `name=primary,other-name=secondary`.
+    pub fn parse_overrides(s: &str) -> HashMap<String, Backend> {
+        s.split(',')
+            .filter_map(|o| {
+                let mut parts = o.splitn(2, '=');
+                match (parts.next(), parts.next().and_then(|b| Backend::parse(b.trim()))) {
+                    (Some(name), Some(backend)) => Some((name.trim().to_string(), backend)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Picks the backend a download of the given crate is redirected to.
+    pub fn route(&self, crate_name: &str) -> Backend {
+        self.route_with_roll(crate_name, thread_rng().gen_range(0, 100))
+    }
+
+    /// Same as `route`, with `roll` being a number between 0 and 99 standing in
+    /// for the random draw.
+    fn route_with_roll(&self, crate_name: &str, roll: u32) -> Backend {
+        if let Some(&backend) = self.overrides.get(crate_name) {
+            return backend;
+        }
+        if roll < self.secondary_weight {
+            Backend::Secondary
+        } else {
+            Backend::Primary
+        }
+    }
+
+    /// Returns the URL of a crate's version archive on the secondary backend.
+    pub fn secondary_location(&self, path: &str) -> String {
+        format!("https://{}/{}", self.secondary_host, path)
+    }
+}
+
+/// How many download redirects were sent to each backend since the server
+/// started.
+#[derive(Debug, Default)]
+pub struct DownloadRoutingMetrics {
+    primary: AtomicUsize,
+    secondary: AtomicUsize,
+}
+
+impl DownloadRoutingMetrics {
+    pub fn record(&self, backend: Backend) {
+        let counter = match backend {
+            Backend::Primary => &self.primary,
+            Backend::Secondary => &self.secondary,
+        };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn count(&self, backend: Backend) -> usize {
+        match backend {
+            Backend::Primary => self.primary.load(Ordering::SeqCst),
+            Backend::Secondary => self.secondary.load(Ordering::SeqCst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, DownloadRouting};
+
+    fn config(weight: u32, overrides: &str) -> DownloadRouting {
+        DownloadRouting {
+            secondary_host: "cdn.example.com".into(),
+            secondary_weight: weight,
+            overrides: DownloadRouting::parse_overrides(overrides),
+        }
+    }
+
+    #[test]
+    fn parse_overrides() {
+        let overrides = DownloadRouting::parse_overrides("foo=secondary, bar=primary,baz=other");
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides["foo"], Backend::Secondary);
+        assert_eq!(overrides["bar"], Backend::Primary);
+    }
+
+    #[test]
+    fn routes_by_weight() {
+        let routing = config(25, "");
+        assert_eq!(routing.route_with_roll("foo", 0), Backend::Secondary);
+        assert_eq!(routing.route_with_roll("foo", 24), Backend::Secondary);
+        assert_eq!(routing.route_with_roll("foo", 25), Backend::Primary);
+        assert_eq!(routing.route_with_roll("foo", 99), Backend::Primary);
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let routing = config(100, "foo=primary");
+        assert_eq!(routing.route_with_roll("foo", 0), Backend::Primary);
+        assert_eq!(routing.route_with_roll("bar", 0), Backend::Secondary);
+
+        let routing = config(0, "foo=secondary");
+        assert_eq!(routing.route_with_roll("foo", 99), Backend::Secondary);
+    }
+}
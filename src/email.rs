@@ -1,6 +1,7 @@
 use std::env;
 use std::path::Path;
 
+use chrono::NaiveDateTime;
 use dotenv::dotenv;
 use lettre::email::{Email, EmailBuilder};
 use lettre::transport::file::FileEmailTransport;
@@ -71,6 +72,43 @@ https://crates.io/confirm/{}",
     send_email(email, subject, &body)
 }
 
+pub fn send_publish_confirmation_email(
+    email: &str,
+    crate_name: &str,
+    token: &str,
+) -> CargoResult<()> {
+    let subject = format!("Please confirm the publication of {}", crate_name);
+    let body = format!(
+        "Someone tried to publish a new crate named {} with your account.
+If that was you, please click the link below within 15 minutes and publish
+the crate again. Otherwise, you can ignore this email.\n
+https://crates.io/api/v1/confirm_publish/{}",
+        crate_name, token
+    );
+
+    send_email(email, &subject, &body)
+}
+
+pub fn send_crate_contested_email(
+    email: &str,
+    crate_name: &str,
+    reason: &str,
+    transfer_at: NaiveDateTime,
+) -> CargoResult<()> {
+    let subject = format!("The name of your crate {} is contested", crate_name);
+    let body = format!(
+        "The name of your crate {} was claimed by another user and is contested:\n
+{}\n
+Unless the dispute is resolved, the crate will be transferred to them on {} UTC.
+Please reply to this email to respond to the claim.",
+        crate_name,
+        reason,
+        transfer_at.format("%Y-%m-%d %H:%M")
+    );
+
+    send_email(email, &subject, &body)
+}
+
 fn send_email(recipient: &str, subject: &str, body: &str) -> CargoResult<()> {
     let mailgun_config = init_config_vars();
     let email = build_email(recipient, subject, body, &mailgun_config)?;
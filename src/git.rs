@@ -1,17 +1,26 @@
 use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs::{self, File};
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time;
 
+use chrono::{Duration, NaiveDateTime, Utc};
 use git2;
+use hex;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::{Signer, Verifier};
 use semver;
 use serde_json;
 
 use app::App;
 use util::{internal, CargoResult};
 
-use models::DependencyKind;
+use models::{CrateRename, DependencyKind, IndexOutboxEntry};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Crate {
@@ -23,6 +32,8 @@ pub struct Crate {
     pub yanked: Option<bool>,
     #[serde(default)]
     pub links: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edition: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -34,6 +45,10 @@ pub struct Dependency {
     pub default_features: bool,
     pub target: Option<String>,
     pub kind: Option<DependencyKind>,
+    /// The URL of the index of the registry of the crate, if it's not this
+    /// registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
 }
 
 fn index_file(base: &Path, name: &str) -> PathBuf {
@@ -48,13 +63,23 @@ fn index_file(base: &Path, name: &str) -> PathBuf {
     }
 }
 
+/// Adds the entry of a version to the index. Nothing is committed if the index
+/// already has the version, so that an entry written again after a crash or by
+/// another server isn't duplicated.
 pub fn add_crate(app: &App, krate: &Crate) -> CargoResult<()> {
+    let _job = app.metrics.git_job();
     let repo = app.git_repo.lock().unwrap();
     let repo = &*repo;
     let repo_path = repo.workdir().unwrap();
     let dst = index_file(repo_path, &krate.name);
 
-    commit_and_push(repo, || {
+    commit_and_push(app, repo, || {
+        // Checked on every attempt, since the index is fetched between them
+        let msg = format!("Updating crate `{}#{}`", krate.name, krate.vers);
+        if has_version(&dst, &krate.vers)? {
+            return Ok((msg, Vec::new()));
+        }
+
         // Add the crate to its relevant file
         fs::create_dir_all(dst.parent().unwrap())?;
         let mut prev = String::new();
@@ -67,23 +92,34 @@ pub fn add_crate(app: &App, krate: &Crate) -> CargoResult<()> {
         f.write_all(new.as_bytes())?;
         f.write_all(b"\n")?;
 
-        Ok((
-            format!("Updating crate `{}#{}`", krate.name, krate.vers),
-            dst.clone(),
-        ))
+        Ok((msg, vec![dst.clone()]))
     })
 }
 
+/// Returns whether the index file has an entry for the version.
+fn has_version(dst: &Path, vers: &str) -> CargoResult<bool> {
+    if fs::metadata(dst).is_err() {
+        return Ok(false);
+    }
+    let mut contents = String::new();
+    File::open(dst).and_then(|mut f| f.read_to_string(&mut contents))?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Crate>(line).ok())
+        .any(|git_crate| git_crate.vers == vers))
+}
+
 /// Yanks or unyanks a crate version. This requires finding the index
 /// file, deserlialise the crate from JSON, change the yank boolean to
 /// `true` or `false`, write all the lines back out, and commit and
 /// push the changes.
 pub fn yank(app: &App, krate: &str, version: &semver::Version, yanked: bool) -> CargoResult<()> {
+    let _job = app.metrics.git_job();
     let repo = app.git_repo.lock().unwrap();
     let repo_path = repo.workdir().unwrap();
     let dst = index_file(repo_path, krate);
 
-    commit_and_push(&repo, || {
+    commit_and_push(app, &repo, || {
         let mut prev = String::new();
         File::open(&dst).and_then(|mut f| f.read_to_string(&mut prev))?;
         let new = prev.lines()
@@ -109,11 +145,166 @@ pub fn yank(app: &App, krate: &str, version: &semver::Version, yanked: bool) ->
                 krate,
                 version
             ),
-            dst.clone(),
+            vec![dst.clone()],
         ))
     })
 }
 
+/// Copies the index entries of a renamed crate to the file of its new name,
+/// renaming them along the way. The file of the old name is kept, so that the
+/// crates depending on the crate under that name keep resolving. Nothing is
+/// committed if the crate has no entries under its old name.
+pub fn rename_crate(app: &App, old_name: &str, new_name: &str) -> CargoResult<()> {
+    let _job = app.metrics.git_job();
+    let repo = app.git_repo.lock().unwrap();
+    let repo_path = repo.workdir().unwrap();
+    let src = index_file(repo_path, old_name);
+    let dst = index_file(repo_path, new_name);
+    if fs::metadata(&src).is_err() {
+        return Ok(());
+    }
+
+    commit_and_push(app, &repo, || {
+        let decode = |line: &str| {
+            serde_json::from_str::<Crate>(line)
+                .map_err(|_| internal(&format_args!("couldn't decode: `{}`", line)))
+        };
+
+        // The crate can be renamed back to a former name, whose file is kept:
+        // its entries are updated and the versions published since appended.
+        let mut entries = Vec::new();
+        if fs::metadata(&dst).is_ok() {
+            let mut existing = String::new();
+            File::open(&dst).and_then(|mut f| f.read_to_string(&mut existing))?;
+            for line in existing.lines() {
+                entries.push(decode(line)?);
+            }
+        }
+        let mut prev = String::new();
+        File::open(&src).and_then(|mut f| f.read_to_string(&mut prev))?;
+        for line in prev.lines() {
+            let mut git_crate = decode(line)?;
+            git_crate.name = new_name.to_string();
+            match entries.iter().position(|entry| entry.vers == git_crate.vers) {
+                Some(i) => entries[i] = git_crate,
+                None => entries.push(git_crate),
+            }
+        }
+
+        let new = entries
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::create_dir_all(dst.parent().unwrap())?;
+        let mut f = File::create(&dst)?;
+        f.write_all(new.as_bytes())?;
+        f.write_all(b"\n")?;
+
+        Ok((
+            format!("Renaming crate `{}` to `{}`", old_name, new_name),
+            vec![dst.clone()],
+        ))
+    })
+}
+
+/// Removes the index file of a deleted crate, returning its lines so that they
+/// can be written back if the crate is restored.
+pub fn delete_crate(app: &App, name: &str) -> CargoResult<Option<String>> {
+    let _job = app.metrics.git_job();
+    let repo = app.git_repo.lock().unwrap();
+    let repo_path = repo.workdir().unwrap();
+    let dst = index_file(repo_path, name);
+    if fs::metadata(&dst).is_err() {
+        return Ok(None);
+    }
+
+    let mut prev = String::new();
+    commit_and_push(app, &repo, || {
+        prev.clear();
+        File::open(&dst).and_then(|mut f| f.read_to_string(&mut prev))?;
+        fs::remove_file(&dst)?;
+
+        Ok((format!("Deleting crate `{}`", name), vec![dst.clone()]))
+    })?;
+    Ok(Some(prev))
+}
+
+/// Writes back the index file of a restored crate.
+pub fn restore_crate(app: &App, name: &str, entries: &str) -> CargoResult<()> {
+    let _job = app.metrics.git_job();
+    let repo = app.git_repo.lock().unwrap();
+    let repo_path = repo.workdir().unwrap();
+    let dst = index_file(repo_path, name);
+
+    commit_and_push(app, &repo, || {
+        fs::create_dir_all(dst.parent().unwrap())?;
+        let mut f = File::create(&dst)?;
+        f.write_all(entries.as_bytes())?;
+
+        Ok((format!("Restoring crate `{}`", name), vec![dst.clone()]))
+    })
+}
+
+/// Overwrites the index file of a crate with its entries regenerated from the
+/// database, e.g. after a failed publish left the index out of date. The
+/// checksums missing from the entries are kept from the current file. Returns
+/// whether the file changed, nothing is committed otherwise.
+pub fn sync_crate(app: &App, name: &str, mut entries: Vec<Crate>) -> CargoResult<bool> {
+    let _job = app.metrics.git_job();
+    let repo = app.git_repo.lock().unwrap();
+    let repo_path = repo.workdir().unwrap();
+    let dst = index_file(repo_path, name);
+
+    let read = |dst: &Path| -> CargoResult<String> {
+        let mut prev = String::new();
+        if fs::metadata(dst).is_ok() {
+            File::open(dst).and_then(|mut f| f.read_to_string(&mut prev))?;
+        }
+        Ok(prev)
+    };
+    let prev = read(&dst)?;
+    if synced_lines(&prev, &mut entries) == prev {
+        return Ok(false);
+    }
+
+    commit_and_push(app, &repo, || {
+        let new = synced_lines(&read(&dst)?, &mut entries);
+        if new.is_empty() {
+            if fs::metadata(&dst).is_ok() {
+                fs::remove_file(&dst)?;
+            }
+        } else {
+            fs::create_dir_all(dst.parent().unwrap())?;
+            let mut f = File::create(&dst)?;
+            f.write_all(new.as_bytes())?;
+        }
+
+        Ok((format!("Synchronizing crate `{}`", name), vec![dst.clone()]))
+    })?;
+    Ok(true)
+}
+
+/// Renders the lines of the index file of a crate, filling in the unknown
+/// checksums from its previous lines.
+fn synced_lines(prev: &str, entries: &mut [Crate]) -> String {
+    let checksums = prev.lines()
+        .filter_map(|line| serde_json::from_str::<Crate>(line).ok())
+        .map(|krate| (krate.vers, krate.cksum))
+        .collect::<HashMap<_, _>>();
+    let mut new = String::new();
+    for entry in entries {
+        if entry.cksum.is_empty() {
+            if let Some(cksum) = checksums.get(&entry.vers) {
+                entry.cksum = cksum.clone();
+            }
+        }
+        new.push_str(&serde_json::to_string(entry).unwrap());
+        new.push('\n');
+    }
+    new
+}
+
 /// Commits and pushes to the crates.io index.
 ///
 /// There are currently 2 instances of the crates.io backend running
@@ -130,9 +321,9 @@ pub fn yank(app: &App, krate: &str, version: &semver::Version, yanked: bool) ->
 /// Spawning a separate thread for this function means that the request
 /// can return without waiting for completion, and other methods of
 /// notifying upon completion or error can be used.
-fn commit_and_push<F>(repo: &git2::Repository, mut f: F) -> CargoResult<()>
+fn commit_and_push<F>(app: &App, repo: &git2::Repository, mut f: F) -> CargoResult<()>
 where
-    F: FnMut() -> CargoResult<(String, PathBuf)>,
+    F: FnMut() -> CargoResult<(String, Vec<PathBuf>)>,
 {
     let repo_path = repo.workdir().unwrap();
 
@@ -141,23 +332,33 @@ where
     // race to commit the changes. For now we just cap out the maximum number of
     // retries at a fixed number.
     for _ in 0..20 {
-        let (msg, dst) = f()?;
+        let (msg, paths) = f()?;
 
-        // git add $file
+        // git add $file, or git rm $file if it was removed
         let mut index = repo.index()?;
-        let mut repo_path = repo_path.iter();
-        let dst = dst.iter()
-            .skip_while(|s| Some(*s) == repo_path.next())
-            .collect::<PathBuf>();
-        index.add_path(&dst)?;
+        for path in paths {
+            let mut repo_path = repo_path.iter();
+            let dst = path.iter()
+                .skip_while(|s| Some(*s) == repo_path.next())
+                .collect::<PathBuf>();
+            if path.exists() {
+                index.add_path(&dst)?;
+            } else {
+                index.remove_path(&dst)?;
+            }
+        }
         index.write()?;
         let tree_id = index.write_tree()?;
         let tree = repo.find_tree(tree_id)?;
 
-        // git commit -m "..."
+        // git commit -m "...", unless there is nothing to commit
         let head = repo.head()?;
         let parent = repo.find_commit(head.target().unwrap())?;
+        if tree_id == parent.tree_id() {
+            return Ok(());
+        }
         let sig = repo.signature()?;
+        let msg = signed_message(app, tree_id, &[parent.id()], &msg)?;
         repo.commit(Some("HEAD"), &sig, &sig, &msg, &tree, &[&parent])?;
 
         // git push
@@ -181,23 +382,287 @@ where
             Err(e) => info!("failure to push: {}", e),
         }
 
-        let mut callbacks = git2::RemoteCallbacks::new();
-        callbacks.credentials(credentials);
-        origin.update_tips(
-            Some(&mut callbacks),
-            true,
-            git2::AutotagOption::Unspecified,
+        // Ok, we need to update, so fetch and reset --hard
+        fetch_and_reset(repo)?;
+    }
+
+    Err(internal("Too many rebase failures"))
+}
+
+/// The trailer of the commit messages signed by the registry, followed by the
+/// signature in hex.
+pub const SIGNATURE_TRAILER: &str = "Registry-Signature: ";
+
+/// Returns what the signature of a commit covers: its tree, its parents and
+/// its message without the signature, laid out like git does.
+pub fn signed_payload(tree: git2::Oid, parents: &[git2::Oid], msg: &str) -> String {
+    let mut payload = format!("tree {}\n", tree);
+    for parent in parents {
+        payload.push_str(&format!("parent {}\n", parent));
+    }
+    payload.push('\n');
+    payload.push_str(msg);
+    payload
+}
+
+/// The private key the commits of the index are signed with, parsed from PEM
+/// when the server starts.
+#[derive(Clone)]
+pub struct SigningKey {
+    key: Arc<PKey>,
+    /// The public key, PEM encoded.
+    public_key: String,
+}
+
+impl SigningKey {
+    pub fn from_pem(pem: &str) -> CargoResult<SigningKey> {
+        let key = PKey::private_key_from_pem(pem.as_bytes())?;
+        let public_key = String::from_utf8(key.public_key_to_pem()?)?;
+        Ok(SigningKey {
+            key: Arc::new(key),
+            public_key,
+        })
+    }
+
+    /// Returns the public key the commits can be verified with, PEM encoded.
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// Appends the signature of a commit to its message.
+    pub fn sign_message(
+        &self,
+        tree: git2::Oid,
+        parents: &[git2::Oid],
+        msg: &str,
+    ) -> CargoResult<String> {
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(signed_payload(tree, parents, msg).as_bytes())?;
+        Ok(format!(
+            "{}\n\n{}{}",
+            msg,
+            SIGNATURE_TRAILER,
+            hex::encode(signer.sign_to_vec()?)
+        ))
+    }
+}
+
+// The private key is never printed.
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("public_key", &self.public_key)
+            .finish()
+    }
+}
+
+/// Appends the signature of the commit with `Config::index_signing_key` to
+/// its message, unless no key is configured.
+fn signed_message(
+    app: &App,
+    tree: git2::Oid,
+    parents: &[git2::Oid],
+    msg: &str,
+) -> CargoResult<String> {
+    match app.config.index_signing_key {
+        Some(ref key) => key.sign_message(tree, parents, msg),
+        None => Ok(msg.to_string()),
+    }
+}
+
+/// Returns the message of a commit without its signature.
+fn unsigned_message(message: &str) -> &str {
+    match message.rfind(&format!("\n\n{}", SIGNATURE_TRAILER)) {
+        Some(i) => &message[..i],
+        None => message,
+    }
+}
+
+/// Checks the signature of a commit of the index against the public key of
+/// the registry, PEM encoded. Unsigned commits aren't valid.
+pub fn verify_commit(public_key: &str, commit: &git2::Commit) -> CargoResult<bool> {
+    let message = commit.message().unwrap_or_default();
+    let msg = unsigned_message(message);
+    if msg.len() == message.len() {
+        return Ok(false);
+    }
+    let signature = match hex::decode(message[msg.len() + 2 + SIGNATURE_TRAILER.len()..].trim()) {
+        Ok(signature) => signature,
+        Err(_) => return Ok(false),
+    };
+    let key = PKey::public_key_from_pem(public_key.as_bytes())?;
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &key)?;
+    let parents = commit.parent_ids().collect::<Vec<_>>();
+    verifier.update(signed_payload(commit.tree_id(), &parents, msg).as_bytes())?;
+    Ok(verifier.verify(&signature)?)
+}
+
+fn fetch_and_reset(repo: &git2::Repository) -> CargoResult<()> {
+    let mut origin = repo.find_remote("origin")?;
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(credentials);
+    origin.update_tips(
+        Some(&mut callbacks),
+        true,
+        git2::AutotagOption::Unspecified,
+        None,
+    )?;
+
+    origin.fetch(&["refs/heads/*:refs/heads/*"], None, None)?;
+    let head = repo.head()?.target().unwrap();
+    let obj = repo.find_object(head, None)?;
+    repo.reset(&obj, git2::ResetType::Hard, None)?;
+    Ok(())
+}
+
+/// Squashes all the commits of the index made before `before` into a single
+/// root commit, replays the newer commits on top of it and force pushes the
+/// result. Returns whether the history was rewritten.
+///
+/// When the commits are signed, the signatures of the squashed commit and of
+/// the retained ones are checked before they are signed again, from the first
+/// one signed on: the squash is aborted if any of them wasn't signed by the
+/// registry.
+///
+/// This holds the lock on the index repository for its whole duration, so no
+/// crate can be added or yanked by this instance in the meantime. Another
+/// instance pushing between our fetch and our force push would have its
+/// commit discarded though, so this should be run while traffic is low.
+pub fn squash_history(app: &App, before: NaiveDateTime) -> CargoResult<bool> {
+    let _job = app.metrics.git_job();
+    let repo = app.git_repo.lock().unwrap();
+    let repo = &*repo;
+
+    // Make sure we're rewriting the latest state of the index
+    fetch_and_reset(repo)?;
+
+    // Walk the history from the newest commit until we find the first one
+    // that is old enough to be squashed
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    walk.set_sorting(git2::SORT_TOPOLOGICAL);
+    let mut retained = Vec::new();
+    let mut base = None;
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.time().seconds() < before.timestamp() {
+            base = Some(commit);
+            break;
+        }
+        retained.push(commit);
+    }
+    let base = match base {
+        Some(ref commit) if commit.parent_count() > 0 => commit,
+        _ => return Ok(false),
+    };
+
+    // Don't sign commits pushed by someone else
+    if let Some(ref key) = app.config.index_signing_key {
+        let mut signing = false;
+        for commit in Some(base).into_iter().chain(retained.iter().rev()) {
+            if verify_commit(key.public_key(), commit)? {
+                signing = true;
+            } else if signing {
+                return Err(internal(&format_args!(
+                    "commit {} of the index isn't signed by the registry, not squashing",
+                    commit.id()
+                )));
+            }
+        }
+    }
+
+    let sig = repo.signature()?;
+    let msg = format!("Collapse index history up to {}", before);
+    let signed = signed_message(app, base.tree_id(), &[], &msg)?;
+    let root = repo.commit(None, &sig, &sig, &signed, &base.tree()?, &[])?;
+    let mut head = repo.find_commit(root)?;
+    for commit in retained.iter().rev() {
+        // The signatures cover the parents, which changed
+        let msg = unsigned_message(commit.message().unwrap_or_default());
+        let msg = signed_message(app, commit.tree_id(), &[head.id()], msg)?;
+        let id = repo.commit(
             None,
+            &commit.author(),
+            &commit.committer(),
+            &msg,
+            &commit.tree()?,
+            &[&head],
         )?;
+        head = repo.find_commit(id)?;
+    }
 
-        // Ok, we need to update, so fetch and reset --hard
-        origin.fetch(&["refs/heads/*:refs/heads/*"], None, None)?;
-        let head = repo.head()?.target().unwrap();
-        let obj = repo.find_object(head, None)?;
-        repo.reset(&obj, git2::ResetType::Hard, None)?;
+    repo.reference("refs/heads/master", head.id(), true, &msg)?;
+    repo.reset(head.as_object(), git2::ResetType::Hard, None)?;
+
+    // git push --force
+    let mut ref_status = None;
+    let mut origin = repo.find_remote("origin")?;
+    {
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.credentials(credentials);
+        callbacks.push_update_reference(|_, status| {
+            ref_status = status.map(|s| s.to_string());
+            Ok(())
+        });
+        let mut opts = git2::PushOptions::new();
+        opts.remote_callbacks(callbacks);
+        origin.push(&["+refs/heads/master"], Some(&mut opts))?;
+    }
+    match ref_status {
+        None => Ok(true),
+        Some(status) => Err(internal(&format_args!(
+            "failed to force push the squashed index: {}",
+            status
+        ))),
     }
+}
 
-    Err(internal("Too many rebase failures"))
+/// Spawns a thread squashing the index history older than `retention` every
+/// `interval`.
+pub fn spawn_squash_task(
+    app: Arc<App>,
+    retention: Duration,
+    interval: time::Duration,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let before = Utc::now().naive_utc() - retention;
+        match squash_history(&app, before) {
+            Ok(true) => info!("squashed the index history up to {}", before),
+            Ok(false) => info!("no index history to squash up to {}", before),
+            Err(e) => error!("failed to squash the index history: {}", e),
+        }
+    })
+}
+
+/// Spawns a thread moving the index entries of renamed crates to their new
+/// names every `interval`.
+pub fn spawn_rename_task(app: Arc<App>, interval: time::Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        match CrateRename::rewrite_pending_index_entries(&app) {
+            Ok(0) => {}
+            Ok(n) => info!("moved the index entries of {} renamed crates", n),
+            Err(e) => error!("failed to move the index entries of renamed crates: {}", e),
+        }
+    })
+}
+
+/// Spawns a thread writing the index entries left in the outbox to the index,
+/// every `interval`.
+pub fn spawn_outbox_task(app: Arc<App>, interval: time::Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let written = app.diesel_database
+            .get()
+            .map_err(Into::into)
+            .and_then(|conn| IndexOutboxEntry::write_pending(&conn, &app));
+        match written {
+            Ok(0) => {}
+            Ok(n) => info!("wrote {} index entries left in the outbox", n),
+            Err(e) => error!("failed to write the index entries of the outbox: {}", e),
+        }
+    })
 }
 
 pub fn credentials(
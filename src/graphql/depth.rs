@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+/// A token of a GraphQL document, as far as its nesting is concerned.
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Spread,
+    At,
+    Name(String),
+}
+
+/// Splits a document into tokens, skipping comments, strings, and the
+/// arguments between parentheses, whose braces are input objects instead of
+/// selections.
+fn tokens(document: &str) -> Vec<Token> {
+    let chars = document.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut parens = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '"' if chars[i..].starts_with(&['"', '"', '"']) => {
+                i += 3;
+                while i < chars.len() && !chars[i..].starts_with(&['"', '"', '"']) {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i += 2;
+            }
+            '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' && chars[i] != '\n' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+            }
+            '(' => parens += 1,
+            ')' => parens -= 1,
+            _ if parens > 0 => {}
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '@' => tokens.push(Token::At),
+            '.' if chars[i..].starts_with(&['.', '.', '.']) => {
+                tokens.push(Token::Spread);
+                i += 2;
+            }
+            c if c == '_' || c.is_ascii_alphanumeric() => {
+                let start = i;
+                let is_name = |c: char| c == '_' || c.is_ascii_alphanumeric();
+                while i + 1 < chars.len() && is_name(chars[i + 1]) {
+                    i += 1;
+                }
+                tokens.push(Token::Name(chars[start..i + 1].iter().collect()));
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// The nesting of an operation or a fragment, before its fragment spreads are
+/// expanded.
+#[derive(Debug, Default)]
+struct Definition {
+    depth: usize,
+    /// The fragments spread in the definition, with the depth they are spread
+    /// at.
+    spreads: Vec<(usize, String)>,
+}
+
+/// Returns the depth of the deepest operation of a document: the number of
+/// nested selection sets, once the fragments are expanded.
+///
+/// The document isn't validated: a syntax error or an unknown fragment is left
+/// for the executor to report.
+pub fn depth(document: &str) -> usize {
+    let tokens = tokens(document);
+    let mut operations = Vec::new();
+    let mut fragments = HashMap::new();
+
+    let mut current = Definition::default();
+    let mut fragment_name = None;
+    // Whether each open selection set adds a level, which inline fragments
+    // don't.
+    let mut open = Vec::new();
+    let mut level = 0;
+    let mut inline = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            Token::Name(ref name) if open.is_empty() && name == "fragment" => {
+                if let Some(&Token::Name(ref name)) = tokens.get(i + 1) {
+                    fragment_name = Some(name.clone());
+                    i += 1;
+                }
+            }
+            Token::Open => {
+                open.push(!inline);
+                if !inline {
+                    level += 1;
+                    current.depth = current.depth.max(level);
+                }
+                inline = false;
+            }
+            Token::Close => {
+                if let Some(counted) = open.pop() {
+                    if counted {
+                        level -= 1;
+                    }
+                    if open.is_empty() {
+                        let definition = mem::replace(&mut current, Definition::default());
+                        match fragment_name.take() {
+                            Some(name) => {
+                                fragments.insert(name, definition);
+                            }
+                            None => operations.push(definition),
+                        }
+                    }
+                }
+            }
+            Token::Spread => match tokens.get(i + 1) {
+                Some(&Token::Name(ref name)) if name != "on" => {
+                    current.spreads.push((level, name.clone()));
+                    i += 1;
+                }
+                _ => inline = true,
+            },
+            Token::At => i += 1,
+            Token::Name(_) => {}
+        }
+        i += 1;
+    }
+
+    let mut depths = HashMap::new();
+    operations
+        .iter()
+        .map(|operation| expanded_depth(operation, &fragments, &mut depths, &mut HashSet::new()))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns the depth of a definition once the fragments it spreads are
+/// expanded, memoizing the depth of each fragment.
+fn expanded_depth<'a>(
+    definition: &'a Definition,
+    fragments: &'a HashMap<String, Definition>,
+    depths: &mut HashMap<&'a str, usize>,
+    visiting: &mut HashSet<&'a str>,
+) -> usize {
+    let mut depth = definition.depth;
+    for &(level, ref name) in &definition.spreads {
+        let memoized = depths.get(&**name).cloned();
+        let fragment_depth = if let Some(fragment_depth) = memoized {
+            fragment_depth
+        } else if let Some(fragment) = fragments.get(name) {
+            // Fragment cycles are rejected by the validation of the executor.
+            if !visiting.insert(&**name) {
+                continue;
+            }
+            let fragment_depth = expanded_depth(fragment, fragments, depths, visiting);
+            visiting.remove(&**name);
+            depths.insert(&**name, fragment_depth);
+            fragment_depth
+        } else {
+            0
+        };
+        // The fields of the fragment are at the level of the spread.
+        depth = depth.max((level + fragment_depth).saturating_sub(1));
+    }
+    depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::depth;
+
+    #[test]
+    fn counts_nested_selection_sets() {
+        assert_eq!(depth("{ crates { name } }"), 2);
+        assert_eq!(depth("query Q { krate(name: \"a\") { versions { num } } }"), 3);
+        assert_eq!(depth("{ a } { b { c { d } } }"), 3);
+    }
+
+    #[test]
+    fn ignores_arguments_strings_and_comments() {
+        let query = r#"
+            # { { { {
+            query Q($v: In = { a: { b: 1 } }) {
+                krate(name: "{{{{", filter: { a: { b: { c: 1 } } }) { name }
+            }
+        "#;
+        assert_eq!(depth(query), 2);
+    }
+
+    #[test]
+    fn expands_fragments() {
+        let query = "
+            { krate(name: \"a\") { ...Versions } }
+            fragment Versions on Crate { versions { ...Dependencies } }
+            fragment Dependencies on Version { dependencies { krate { name } } }
+        ";
+        assert_eq!(depth(query), 5);
+        assert_eq!(depth("{ krate(name: \"a\") { ... on Crate { versions { num } } } }"), 3);
+        assert_eq!(depth("{ ...A } fragment A on Query { ...A crates { name } }"), 2);
+    }
+}
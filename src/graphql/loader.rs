@@ -0,0 +1,98 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use diesel::QueryResult;
+
+/// Loads the children of many parents at once, e.g. the versions of every
+/// crate of a list.
+///
+/// The ids of the parents are queued when they are resolved, and the children
+/// of every queued parent are loaded together the first time the children of
+/// one of them are needed. The children are then cached for the rest of the
+/// request.
+pub struct Loader<T> {
+    queued: RefCell<HashSet<i32>>,
+    loaded: RefCell<HashMap<i32, Vec<T>>>,
+}
+
+impl<T> Default for Loader<T> {
+    fn default() -> Self {
+        Loader {
+            queued: RefCell::new(HashSet::new()),
+            loaded: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Clone> Loader<T> {
+    /// Queues parents whose children may be needed later on.
+    pub fn queue<I: IntoIterator<Item = i32>>(&self, ids: I) {
+        let loaded = self.loaded.borrow();
+        self.queued
+            .borrow_mut()
+            .extend(ids.into_iter().filter(|id| !loaded.contains_key(id)));
+    }
+
+    /// Returns the children of the parent `id`. If they weren't loaded yet,
+    /// `fetch` is called with the ids of the parent and of every queued parent,
+    /// and returns their children along with the id of their parent.
+    pub fn load<F>(&self, id: i32, fetch: F) -> QueryResult<Vec<T>>
+    where
+        F: FnOnce(Vec<i32>) -> QueryResult<Vec<(i32, T)>>,
+    {
+        if !self.loaded.borrow().contains_key(&id) {
+            let mut ids = self.queued.borrow_mut().drain().collect::<Vec<_>>();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+            let children = fetch(ids.clone())?;
+
+            let mut loaded = self.loaded.borrow_mut();
+            for parent in ids {
+                loaded.entry(parent).or_insert_with(Vec::new);
+            }
+            for (parent, child) in children {
+                loaded.entry(parent).or_insert_with(Vec::new).push(child);
+            }
+        }
+        Ok(self.loaded.borrow()[&id].clone())
+    }
+}
+
+impl<T> fmt::Debug for Loader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Loader")
+            .field("queued", &self.queued.borrow().len())
+            .field("loaded", &self.loaded.borrow().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Loader;
+    use std::cell::Cell;
+
+    #[test]
+    fn loads_the_children_of_queued_parents_at_once() {
+        let loader = Loader::default();
+        let fetches = Cell::new(0);
+        let fetch = |ids: Vec<i32>| {
+            fetches.set(fetches.get() + 1);
+            Ok(ids.into_iter()
+                .filter(|&id| id != 3)
+                .map(|id| (id, id * 10))
+                .collect())
+        };
+
+        loader.queue(vec![1, 2, 3]);
+        assert_eq!(loader.load(1, &fetch).unwrap(), vec![10]);
+        assert_eq!(loader.load(2, &fetch).unwrap(), vec![20]);
+        assert_eq!(loader.load(3, &fetch).unwrap(), Vec::<i32>::new());
+        assert_eq!(fetches.get(), 1);
+
+        assert_eq!(loader.load(4, &fetch).unwrap(), vec![40]);
+        assert_eq!(fetches.get(), 2);
+    }
+}
@@ -0,0 +1,477 @@
+//! A GraphQL API over the crates, their versions, owners, keywords,
+//! categories and downloads, served next to the REST endpoints at
+//! `/api/v1/graphql`.
+//!
+//! Nested fields are resolved through the `Loader`s of the `Context`: the ids
+//! of the crates, versions and dependencies returned by a field are queued,
+//! and the first nested field resolved loads the children of all of them in a
+//! single query, instead of one query per parent.
+//!
+//! Each level of a query can multiply the rows loaded by the level above, so
+//! queries nested deeper than `MAX_DEPTH` are rejected before being executed.
+
+use std::fmt;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::dsl::{any, date, now, IntervalDsl};
+use diesel::prelude::*;
+use juniper::{self, EmptyMutation, FieldError, FieldResult, RootNode, Value};
+
+use db::DieselPooledConn;
+use models::{Category, Crate, Dependency, DependencyKind, Keyword, Owner, OwnerKind, Team, User,
+             Version, VersionDownload};
+use schema::*;
+use views::EncodableOwner;
+
+mod depth;
+mod loader;
+
+use self::loader::Loader;
+
+pub type Schema = RootNode<'static, Query, EmptyMutation<Context>>;
+
+/// The maximum number of crates a list returns.
+const MAX_CRATES: i32 = 100;
+
+/// The maximum number of nested selection sets of a query, fragments
+/// included.
+const MAX_DEPTH: usize = 10;
+
+pub fn schema() -> Schema {
+    RootNode::new(Query, EmptyMutation::new())
+}
+
+/// Checks a query isn't nested deeper than `MAX_DEPTH`, returning the error
+/// message to respond with if it is.
+pub fn check_depth(query: &str) -> Result<(), String> {
+    let depth = depth::depth(query);
+    if depth > MAX_DEPTH {
+        return Err(format!(
+            "the query is nested {} levels deep, the maximum is {}",
+            depth, MAX_DEPTH
+        ));
+    }
+    Ok(())
+}
+
+/// The state of a GraphQL request.
+pub struct Context {
+    conn: DieselPooledConn,
+    /// The versions of crates, by crate id.
+    versions: Loader<Version>,
+    /// The owners of crates, by crate id.
+    owners: Loader<EncodableOwner>,
+    /// The keywords of crates, by crate id.
+    keywords: Loader<Keyword>,
+    /// The categories of crates, by crate id.
+    categories: Loader<Category>,
+    /// The dependencies of versions, by version id.
+    dependencies: Loader<Dependency>,
+    /// The downloads of the last 90 days of versions, by version id.
+    downloads: Loader<VersionDownload>,
+    /// The crates depended on, by crate id.
+    crates: Loader<Crate>,
+}
+
+impl Context {
+    pub fn new(conn: DieselPooledConn) -> Self {
+        Context {
+            conn,
+            versions: Loader::default(),
+            owners: Loader::default(),
+            keywords: Loader::default(),
+            categories: Loader::default(),
+            dependencies: Loader::default(),
+            downloads: Loader::default(),
+            crates: Loader::default(),
+        }
+    }
+
+    fn queue_crates<'a, I: IntoIterator<Item = &'a Crate>>(&self, crates: I) {
+        let ids = crates.into_iter().map(|krate| krate.id).collect::<Vec<_>>();
+        self.versions.queue(ids.iter().cloned());
+        self.owners.queue(ids.iter().cloned());
+        self.keywords.queue(ids.iter().cloned());
+        self.categories.queue(ids);
+    }
+
+    fn queue_versions(&self, versions: &[Version]) {
+        let ids = versions.iter().map(|version| version.id).collect::<Vec<_>>();
+        self.dependencies.queue(ids.iter().cloned());
+        self.downloads.queue(ids);
+    }
+
+    fn versions(&self, crate_id: i32) -> QueryResult<Vec<Version>> {
+        let conn = &*self.conn;
+        let mut versions = self.versions.load(crate_id, |ids| {
+            Ok(versions::table
+                .filter(versions::crate_id.eq(any(ids)))
+                .load::<Version>(conn)?
+                .into_iter()
+                .map(|version| (version.crate_id, version))
+                .collect())
+        })?;
+        versions.sort_by(|a, b| b.num.cmp(&a.num));
+        self.queue_versions(&versions);
+        Ok(versions)
+    }
+
+    fn owners(&self, crate_id: i32) -> QueryResult<Vec<EncodableOwner>> {
+        let conn = &*self.conn;
+        self.owners.load(crate_id, |ids| {
+            let owners = crate_owners::table
+                .filter(crate_owners::crate_id.eq(any(ids)))
+                .filter(crate_owners::deleted.eq(false));
+            let users = owners
+                .inner_join(users::table)
+                .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+                .select((crate_owners::crate_id, users::all_columns))
+                .load::<(i32, User)>(conn)?
+                .into_iter()
+                .map(|(id, user)| (id, Owner::User(user).encodable()));
+            let teams = owners
+                .inner_join(teams::table)
+                .filter(crate_owners::owner_kind.eq(OwnerKind::Team as i32))
+                .select((crate_owners::crate_id, teams::all_columns))
+                .load::<(i32, Team)>(conn)?
+                .into_iter()
+                .map(|(id, team)| (id, Owner::Team(team).encodable()));
+            Ok(users.chain(teams).collect())
+        })
+    }
+
+    fn keywords(&self, crate_id: i32) -> QueryResult<Vec<Keyword>> {
+        let conn = &*self.conn;
+        self.keywords.load(crate_id, |ids| {
+            crates_keywords::table
+                .inner_join(keywords::table)
+                .filter(crates_keywords::crate_id.eq(any(ids)))
+                .select((crates_keywords::crate_id, keywords::all_columns))
+                .load(conn)
+        })
+    }
+
+    fn categories(&self, crate_id: i32) -> QueryResult<Vec<Category>> {
+        let conn = &*self.conn;
+        self.categories.load(crate_id, |ids| {
+            crates_categories::table
+                .inner_join(categories::table)
+                .filter(crates_categories::crate_id.eq(any(ids)))
+                .select((crates_categories::crate_id, categories::all_columns))
+                .load(conn)
+        })
+    }
+
+    fn dependencies(&self, version_id: i32) -> QueryResult<Vec<Dependency>> {
+        let conn = &*self.conn;
+        let dependencies = self.dependencies.load(version_id, |ids| {
+            Ok(dependencies::table
+                .filter(dependencies::version_id.eq(any(ids)))
+                .order(dependencies::id)
+                .load::<Dependency>(conn)?
+                .into_iter()
+                .map(|dependency| (dependency.version_id, dependency))
+                .collect())
+        })?;
+        self.crates
+            .queue(dependencies.iter().map(|dependency| dependency.crate_id));
+        Ok(dependencies)
+    }
+
+    fn downloads(&self, version_id: i32) -> QueryResult<Vec<VersionDownload>> {
+        let conn = &*self.conn;
+        self.downloads.load(version_id, |ids| {
+            Ok(version_downloads::table
+                .filter(version_downloads::version_id.eq(any(ids)))
+                .filter(version_downloads::date.gt(date(now - 90.days())))
+                .order(version_downloads::date)
+                .load::<VersionDownload>(conn)?
+                .into_iter()
+                .map(|download| (download.version_id, download))
+                .collect())
+        })
+    }
+
+    fn krate(&self, crate_id: i32) -> QueryResult<Option<Crate>> {
+        let conn = &*self.conn;
+        let krate = self.crates.load(crate_id, |ids| {
+            let crates = Crate::all()
+                .filter(crates::id.eq(any(ids)))
+                .load::<Crate>(conn)?;
+            self.queue_crates(&crates);
+            Ok(crates.into_iter().map(|krate| (krate.id, krate)).collect())
+        })?;
+        Ok(krate.into_iter().next())
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("versions", &self.versions)
+            .field("dependencies", &self.dependencies)
+            .field("crates", &self.crates)
+            .finish()
+    }
+}
+
+impl juniper::Context for Context {}
+
+/// Returns the limit and offset of a list of crates.
+fn page(first: Option<i32>, offset: Option<i32>) -> FieldResult<(i64, i64)> {
+    let first = first.unwrap_or(10);
+    let offset = offset.unwrap_or(0);
+    if first < 0 || first > MAX_CRATES || offset < 0 {
+        return Err(FieldError::new(
+            format!(
+                "`first` must be between 0 and {}, and `offset` positive",
+                MAX_CRATES
+            ),
+            Value::null(),
+        ));
+    }
+    Ok((i64::from(first), i64::from(offset)))
+}
+
+fn rfc3339(dt: NaiveDateTime) -> String {
+    DateTime::<Utc>::from_utc(dt, Utc).to_rfc3339()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Query;
+
+graphql_object!(Query: Context |&self| {
+    description: "The crates of the registry"
+
+    field krate(&executor, name: String) -> FieldResult<Option<Crate>>
+        as "Finds a crate by its name, or one of its former names"
+    {
+        let context = executor.context();
+        let krate = Crate::by_name(&name)
+            .first::<Crate>(&*context.conn)
+            .optional()?;
+        context.queue_crates(&krate);
+        Ok(krate)
+    }
+
+    field crates(&executor, first: Option<i32>, offset: Option<i32>) -> FieldResult<Vec<Crate>>
+        as "Lists the crates, the most downloaded first"
+    {
+        let context = executor.context();
+        let (limit, offset) = page(first, offset)?;
+        let crates = Crate::all()
+            .order((crates::downloads.desc(), crates::name))
+            .limit(limit)
+            .offset(offset)
+            .load::<Crate>(&*context.conn)?;
+        context.queue_crates(&crates);
+        Ok(crates)
+    }
+
+    field keyword(&executor, keyword: String) -> FieldResult<Option<Keyword>>
+        as "Finds a keyword"
+    {
+        let conn = &*executor.context().conn;
+        Ok(Keyword::find_by_keyword(conn, &keyword).optional()?)
+    }
+
+    field category(&executor, slug: String) -> FieldResult<Option<Category>>
+        as "Finds a category by its slug"
+    {
+        let conn = &*executor.context().conn;
+        Ok(categories::table
+            .filter(categories::slug.eq(slug))
+            .first::<Category>(conn)
+            .optional()?)
+    }
+});
+
+graphql_object!(Crate: Context |&self| {
+    description: "A crate published on the registry"
+
+    field id() -> i32 { self.id }
+
+    field name() -> &str { &self.name }
+
+    field description() -> Option<&str> { self.description.as_ref().map(|s| &**s) }
+
+    field homepage() -> Option<&str> { self.homepage.as_ref().map(|s| &**s) }
+
+    field documentation() -> Option<&str> { self.documentation.as_ref().map(|s| &**s) }
+
+    field repository() -> Option<&str> { self.repository.as_ref().map(|s| &**s) }
+
+    field license() -> Option<&str> { self.license.as_ref().map(|s| &**s) }
+
+    field downloads() -> i32 { self.downloads }
+
+    field created_at() -> String { rfc3339(self.created_at) }
+
+    field updated_at() -> String { rfc3339(self.updated_at) }
+
+    field default_version(&executor) -> FieldResult<Option<String>>
+        as "The highest stable version that isn't yanked, if possible"
+    {
+        let versions = executor.context().versions(self.id)?;
+        if versions.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Version::default_num(&versions).to_string()))
+    }
+
+    field versions(&executor) -> FieldResult<Vec<Version>>
+        as "The versions of the crate, the highest first"
+    {
+        Ok(executor.context().versions(self.id)?)
+    }
+
+    field owners(&executor) -> FieldResult<Vec<EncodableOwner>> {
+        Ok(executor.context().owners(self.id)?)
+    }
+
+    field keywords(&executor) -> FieldResult<Vec<Keyword>> {
+        Ok(executor.context().keywords(self.id)?)
+    }
+
+    field categories(&executor) -> FieldResult<Vec<Category>> {
+        Ok(executor.context().categories(self.id)?)
+    }
+});
+
+graphql_object!(Version: Context |&self| {
+    description: "A version of a crate"
+
+    field id() -> i32 { self.id }
+
+    field num() -> String { self.num.to_string() }
+
+    field yanked() -> bool { self.yanked }
+
+    field license() -> Option<&str> { self.license.as_ref().map(|s| &**s) }
+
+    field downloads() -> i32 { self.downloads }
+
+    field created_at() -> String { rfc3339(self.created_at) }
+
+    field updated_at() -> String { rfc3339(self.updated_at) }
+
+    field dependencies(&executor) -> FieldResult<Vec<Dependency>> {
+        Ok(executor.context().dependencies(self.id)?)
+    }
+
+    field daily_downloads(&executor) -> FieldResult<Vec<VersionDownload>>
+        as "The downloads of the version during the last 90 days"
+    {
+        Ok(executor.context().downloads(self.id)?)
+    }
+});
+
+graphql_object!(Dependency: Context |&self| {
+    description: "A dependency of a version on a crate"
+
+    field krate(&executor) -> FieldResult<Option<Crate>> as "The crate depended on" {
+        Ok(executor.context().krate(self.crate_id)?)
+    }
+
+    field req() -> String { self.req.to_string() }
+
+    field kind() -> &str {
+        match self.kind {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Build => "build",
+            DependencyKind::Dev => "dev",
+        }
+    }
+
+    field optional() -> bool { self.optional }
+
+    field default_features() -> bool { self.default_features }
+
+    field features() -> Vec<String> { self.features.clone() }
+
+    field target() -> Option<&str> { self.target.as_ref().map(|s| &**s) }
+});
+
+graphql_object!(VersionDownload: Context |&self| {
+    description: "The downloads of a version on one day"
+
+    field date() -> String { self.date.format("%Y-%m-%d").to_string() }
+
+    field downloads() -> i32 { self.downloads }
+});
+
+graphql_object!(EncodableOwner: Context as "Owner" |&self| {
+    description: "A user or a team owning a crate"
+
+    field id() -> i32 { self.id }
+
+    field login() -> &str { &self.login }
+
+    field kind() -> &str { &self.kind }
+
+    field name() -> Option<&str> { self.name.as_ref().map(|s| &**s) }
+
+    field avatar() -> Option<&str> { self.avatar.as_ref().map(|s| &**s) }
+
+    field url() -> Option<&str> { self.url.as_ref().map(|s| &**s) }
+});
+
+graphql_object!(Keyword: Context |&self| {
+    description: "A keyword crates are tagged with"
+
+    field keyword() -> &str { &self.keyword }
+
+    field crates_cnt() -> i32 { self.crates_cnt }
+
+    field created_at() -> String { rfc3339(self.created_at) }
+
+    field crates(&executor, first: Option<i32>, offset: Option<i32>) -> FieldResult<Vec<Crate>>
+        as "The crates with the keyword, the most downloaded first"
+    {
+        let context = executor.context();
+        let (limit, offset) = page(first, offset)?;
+        let crate_ids = crates_keywords::table
+            .filter(crates_keywords::keyword_id.eq(self.id))
+            .select(crates_keywords::crate_id);
+        let crates = Crate::all()
+            .filter(crates::id.eq_any(crate_ids))
+            .order((crates::downloads.desc(), crates::name))
+            .limit(limit)
+            .offset(offset)
+            .load::<Crate>(&*context.conn)?;
+        context.queue_crates(&crates);
+        Ok(crates)
+    }
+});
+
+graphql_object!(Category: Context |&self| {
+    description: "A category crates are listed in"
+
+    field slug() -> &str { &self.slug }
+
+    field category() -> &str { &self.category }
+
+    field description() -> &str { &self.description }
+
+    field crates_cnt() -> i32 { self.crates_cnt }
+
+    field created_at() -> String { rfc3339(self.created_at) }
+
+    field crates(&executor, first: Option<i32>, offset: Option<i32>) -> FieldResult<Vec<Crate>>
+        as "The crates in the category, the most downloaded first"
+    {
+        let context = executor.context();
+        let (limit, offset) = page(first, offset)?;
+        let crate_ids = crates_categories::table
+            .filter(crates_categories::category_id.eq(self.id))
+            .select(crates_categories::crate_id);
+        let crates = Crate::all()
+            .filter(crates::id.eq_any(crate_ids))
+            .order((crates::downloads.desc(), crates::name))
+            .limit(limit)
+            .offset(offset)
+            .load::<Crate>(&*context.conn)?;
+        context.queue_crates(&crates);
+        Ok(crates)
+    }
+});
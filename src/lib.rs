@@ -21,13 +21,17 @@ extern crate flate2;
 extern crate git2;
 extern crate hex;
 extern crate htmlescape;
+#[macro_use]
+extern crate juniper;
 extern crate lettre;
 extern crate license_exprs;
 #[macro_use]
 extern crate log;
 extern crate oauth2;
 extern crate openssl;
+extern crate pq_sys;
 extern crate rand;
+extern crate regex;
 extern crate s3;
 extern crate scheduled_thread_pool;
 extern crate semver;
@@ -49,7 +53,7 @@ extern crate conduit_router;
 extern crate conduit_static;
 extern crate cookie;
 
-pub use self::uploaders::{Bomb, Uploader};
+pub use self::uploaders::{Bomb, ReplicaHealth, Uploader};
 pub use app::App;
 pub use config::Config;
 
@@ -57,18 +61,37 @@ use std::sync::Arc;
 
 use conduit_middleware::MiddlewareBuilder;
 
+pub mod api_doc;
 pub mod app;
+pub mod background_migrations;
 pub mod boot;
+pub mod cdn;
 pub mod config;
 pub mod db;
+pub mod dependency_policy;
+pub mod download_dedup;
+pub mod download_routing;
 pub mod email;
 pub mod git;
 pub mod github;
+pub mod graphql;
+pub mod license;
+pub mod metrics;
 pub mod middleware;
+pub mod name_policy;
+pub mod quality;
+pub mod recount;
+pub mod related;
 pub mod render;
+pub mod scanning;
 pub mod schema;
+pub mod stale;
+pub mod totp;
+pub mod trusted_publishing;
+pub mod updates;
 pub mod uploaders;
 pub mod util;
+pub mod webhooks;
 
 pub mod controllers;
 pub mod models;
@@ -0,0 +1,315 @@
+//! Parsing of the SPDX license expressions crates are published with, such as
+//! `MIT OR Apache-2.0` or `(MIT OR Apache-2.0) AND BSD-3-Clause`.
+//!
+//! The `/` separator crates.io accepted before cargo used `OR` is read as `OR`.
+//! Expressions are stored in their normalized form, with uppercase operators
+//! and only the parentheses they need, e.g. `MIT/Apache-2.0` is stored as
+//! `MIT OR Apache-2.0`.
+
+use std::fmt;
+
+use license_exprs;
+
+use util::{human, CargoResult};
+
+/// A parsed license expression.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LicenseExpression {
+    /// A single license, possibly with an exception to it, as in
+    /// `GPL-2.0 WITH Classpath-exception-2.0`.
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+    /// All of the licenses apply.
+    And { of: Vec<LicenseExpression> },
+    /// Any of the licenses can be chosen.
+    Or { of: Vec<LicenseExpression> },
+}
+
+impl fmt::Display for LicenseExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LicenseExpression::License {
+                ref id,
+                exception: Some(ref exception),
+            } => write!(f, "{} WITH {}", id, exception),
+            LicenseExpression::License { ref id, .. } => f.write_str(id),
+            LicenseExpression::And { ref of } => {
+                for (i, expr) in of.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" AND ")?;
+                    }
+                    match *expr {
+                        LicenseExpression::Or { .. } => write!(f, "({})", expr)?,
+                        _ => write!(f, "{}", expr)?,
+                    }
+                }
+                Ok(())
+            }
+            LicenseExpression::Or { ref of } => {
+                for (i, expr) in of.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" OR ")?;
+                    }
+                    write!(f, "{}", expr)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parses and validates a license expression, the identifiers of licenses
+/// and exceptions must be on the SPDX license list.
+pub fn parse(expr: &str) -> CargoResult<LicenseExpression> {
+    parse_expression(expr).map_err(|e| {
+        human(&format_args!(
+            "{}; see http://opensource.org/licenses \
+             for options, and http://spdx.org/licenses/ \
+             for their identifiers",
+            e
+        ))
+    })
+}
+
+/// Returns the normalized form of a license expression.
+pub fn normalize(expr: &str) -> CargoResult<String> {
+    Ok(parse(expr)?.to_string())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Id(&'a str),
+}
+
+fn tokenize(expr: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in expr.char_indices() {
+        let token = match c {
+            '(' => Some(Token::Open),
+            ')' => Some(Token::Close),
+            '/' => Some(Token::Or),
+            c if c.is_whitespace() => None,
+            _ => {
+                start = start.or(Some(i));
+                continue;
+            }
+        };
+        if let Some(start) = start.take() {
+            tokens.push(word(&expr[start..i]));
+        }
+        tokens.extend(token);
+    }
+    if let Some(start) = start {
+        tokens.push(word(&expr[start..]));
+    }
+    tokens
+}
+
+fn word(word: &str) -> Token {
+    match &*word.to_uppercase() {
+        "AND" => Token::And,
+        "OR" => Token::Or,
+        "WITH" => Token::With,
+        _ => Token::Id(word),
+    }
+}
+
+fn parse_expression(expr: &str) -> Result<LicenseExpression, String> {
+    let tokens = tokenize(expr);
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+    };
+    let expr = parser.or()?;
+    match parser.next() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {} in license expression", describe(token))),
+    }
+}
+
+struct Parser<'a, 'b: 'a> {
+    tokens: &'a [Token<'b>],
+    pos: usize,
+}
+
+impl<'a, 'b: 'a> Parser<'a, 'b> {
+    fn peek(&self) -> Option<&'a Token<'b>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token<'b>> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<LicenseExpression, String> {
+        let mut of = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            of.push(self.and()?);
+        }
+        Ok(flatten(of, false))
+    }
+
+    fn and(&mut self) -> Result<LicenseExpression, String> {
+        let mut of = vec![self.license()?];
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            of.push(self.license()?);
+        }
+        Ok(flatten(of, true))
+    }
+
+    fn license(&mut self) -> Result<LicenseExpression, String> {
+        match self.next() {
+            Some(&Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(&Token::Close) => Ok(expr),
+                    Some(token) => Err(format!("expected `)` but found {}", describe(token))),
+                    None => Err("unclosed `(` in license expression".into()),
+                }
+            }
+            Some(&Token::Id(id)) => {
+                license_exprs::validate_license_expr(id).map_err(|e| e.to_string())?;
+                let exception = if self.peek() == Some(&Token::With) {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(&Token::Id(exception)) => {
+                            license_exprs::validate_license_expr(&format!(
+                                "{} WITH {}",
+                                id, exception
+                            )).map_err(|e| e.to_string())?;
+                            Some(exception.to_string())
+                        }
+                        _ => return Err("expected a license exception after `WITH`".into()),
+                    }
+                } else {
+                    None
+                };
+                Ok(LicenseExpression::License {
+                    id: id.to_string(),
+                    exception,
+                })
+            }
+            Some(token) => Err(format!(
+                "expected a license but found {}",
+                describe(token)
+            )),
+            None => Err("expected a license but the expression ended".into()),
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match *token {
+        Token::Open => "`(`".into(),
+        Token::Close => "`)`".into(),
+        Token::And => "`AND`".into(),
+        Token::Or => "`OR`".into(),
+        Token::With => "`WITH`".into(),
+        Token::Id(id) => format!("`{}`", id),
+    }
+}
+
+/// Joins expressions with `AND` or `OR`, merging the nested expressions
+/// joined with the same operator.
+fn flatten(of: Vec<LicenseExpression>, and: bool) -> LicenseExpression {
+    if of.len() == 1 {
+        return of.into_iter().next().unwrap();
+    }
+    let mut flat = Vec::new();
+    for expr in of {
+        match (and, expr) {
+            (true, LicenseExpression::And { of }) | (false, LicenseExpression::Or { of }) => {
+                flat.extend(of)
+            }
+            (_, expr) => flat.push(expr),
+        }
+    }
+    if and {
+        LicenseExpression::And { of: flat }
+    } else {
+        LicenseExpression::Or { of: flat }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(expr: &str) -> String {
+        normalize(expr).unwrap()
+    }
+
+    #[test]
+    fn normalizes_operators_and_parentheses() {
+        assert_eq!(normalized("MIT"), "MIT");
+        assert_eq!(normalized("MIT/Apache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(normalized("MIT or Apache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(normalized("(MIT OR Apache-2.0)"), "MIT OR Apache-2.0");
+        assert_eq!(
+            normalized("MIT OR (Apache-2.0 OR BSD-3-Clause)"),
+            "MIT OR Apache-2.0 OR BSD-3-Clause"
+        );
+        assert_eq!(
+            normalized("(MIT/Apache-2.0) AND BSD-3-Clause"),
+            "(MIT OR Apache-2.0) AND BSD-3-Clause"
+        );
+        assert_eq!(
+            normalized("(MIT AND BSD-3-Clause) OR Apache-2.0"),
+            "MIT AND BSD-3-Clause OR Apache-2.0"
+        );
+        assert_eq!(
+            normalized("GPL-2.0 with Classpath-exception-2.0"),
+            "GPL-2.0 WITH Classpath-exception-2.0"
+        );
+    }
+
+    #[test]
+    fn parses_into_a_tree() {
+        assert_eq!(
+            parse("MIT AND (Apache-2.0 OR BSD-3-Clause)").unwrap(),
+            LicenseExpression::And {
+                of: vec![
+                    LicenseExpression::License {
+                        id: "MIT".into(),
+                        exception: None,
+                    },
+                    LicenseExpression::Or {
+                        of: vec![
+                            LicenseExpression::License {
+                                id: "Apache-2.0".into(),
+                                exception: None,
+                            },
+                            LicenseExpression::License {
+                                id: "BSD-3-Clause".into(),
+                                exception: None,
+                            },
+                        ],
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(parse("").is_err());
+        assert!(parse("MIT OR").is_err());
+        assert!(parse("(MIT OR Apache-2.0").is_err());
+        assert!(parse("MIT Apache-2.0").is_err());
+        assert!(parse("MIT WITH").is_err());
+        assert!(parse("Not-A-License").is_err());
+    }
+}
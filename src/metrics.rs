@@ -0,0 +1,263 @@
+//! Metrics of the server in the Prometheus text format, served at
+//! `GET /metrics` when `Config::metrics_token` is set.
+//!
+//! Requests are labelled with the pattern of the route they matched in
+//! `api_doc::ROUTES` rather than their path, so that the number of series
+//! doesn't grow with the number of crates.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use api_doc::ROUTES;
+use app::App;
+use download_routing::Backend;
+
+/// The upper bounds of the buckets of the response time histograms, in seconds.
+const BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+#[derive(Debug, Default)]
+struct RouteMetrics {
+    statuses: BTreeMap<u32, u64>,
+    buckets: [u64; 10],
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// The requests served, by method and route.
+    routes: Mutex<BTreeMap<(String, String), RouteMetrics>>,
+    publishes: AtomicUsize,
+    downloads: AtomicUsize,
+    git_jobs: AtomicUsize,
+}
+
+/// Marks a change to the git index as in progress until it is dropped.
+#[derive(Debug)]
+pub struct GitJob<'a>(&'a AtomicUsize);
+
+impl<'a> Drop for GitJob<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Metrics {
+    pub fn record_request(&self, method: &str, path: &str, status: u32, time: Duration) {
+        let seconds = time.as_secs() as f64 + f64::from(time.subsec_nanos()) / 1e9;
+        let mut routes = self.routes.lock().unwrap();
+        let route = routes
+            .entry((method.to_uppercase(), route_label(method, path)))
+            .or_insert_with(RouteMetrics::default);
+        *route.statuses.entry(status).or_insert(0) += 1;
+        for (bucket, &bound) in route.buckets.iter_mut().zip(&BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        route.sum += seconds;
+        route.count += 1;
+    }
+
+    pub fn record_publish(&self) {
+        self.publishes.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn record_download(&self) {
+        self.downloads.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Counts a change to the git index as waiting for, or holding, the lock
+    /// of the repository until the returned guard is dropped.
+    pub fn git_job(&self) -> GitJob {
+        self.git_jobs.fetch_add(1, Ordering::SeqCst);
+        GitJob(&self.git_jobs)
+    }
+
+    /// Renders the metrics, along with the state of the database pool and the
+    /// download routing counters of the app, in the Prometheus text format.
+    pub fn render(&self, app: &App) -> String {
+        let mut out = String::new();
+        let routes = self.routes.lock().unwrap();
+
+        header(&mut out, "http_requests_total", "counter", "Requests served.");
+        for (&(ref method, ref route), metrics) in routes.iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, route);
+            for (status, count) in &metrics.statuses {
+                let _ = writeln!(
+                    out,
+                    "cratesio_http_requests_total{{{},status=\"{}\"}} {}",
+                    labels, status, count
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "http_request_duration_seconds",
+            "histogram",
+            "Response times.",
+        );
+        for (&(ref method, ref route), metrics) in routes.iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, route);
+            for (count, bound) in metrics.buckets.iter().zip(&BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "cratesio_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "cratesio_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, metrics.count
+            );
+            let _ = writeln!(
+                out,
+                "cratesio_http_request_duration_seconds_sum{{{}}} {}",
+                labels, metrics.sum
+            );
+            let _ = writeln!(
+                out,
+                "cratesio_http_request_duration_seconds_count{{{}}} {}",
+                labels, metrics.count
+            );
+        }
+
+        let pool = app.diesel_database.state();
+        gauge(
+            &mut out,
+            "db_pool_connections",
+            "Open database connections.",
+            pool.connections as usize,
+        );
+        gauge(
+            &mut out,
+            "db_pool_idle_connections",
+            "Idle database connections.",
+            pool.idle_connections as usize,
+        );
+        gauge(
+            &mut out,
+            "git_jobs",
+            "Changes to the git index waiting for or holding the repository lock.",
+            self.git_jobs.load(Ordering::SeqCst),
+        );
+
+        header(&mut out, "publishes_total", "counter", "Versions published.");
+        let _ = writeln!(
+            out,
+            "cratesio_publishes_total {}",
+            self.publishes.load(Ordering::SeqCst)
+        );
+        header(&mut out, "downloads_total", "counter", "Crate downloads.");
+        let _ = writeln!(
+            out,
+            "cratesio_downloads_total {}",
+            self.downloads.load(Ordering::SeqCst)
+        );
+        header(
+            &mut out,
+            "download_redirects_total",
+            "counter",
+            "Download redirects by storage backend.",
+        );
+        for &backend in &[Backend::Primary, Backend::Secondary] {
+            let _ = writeln!(
+                out,
+                "cratesio_download_redirects_total{{backend=\"{}\"}} {}",
+                backend.as_str(),
+                app.download_routing_metrics.count(backend)
+            );
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP cratesio_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE cratesio_{} {}", name, kind);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: usize) {
+    header(out, name, "gauge", help);
+    let _ = writeln!(out, "cratesio_{} {}", name, value);
+}
+
+/// Returns the route of `api_doc::ROUTES` the request matches, e.g.
+/// `/api/v1/crates/:crate_id` for `/api/v1/crates/serde`, or `other` for the
+/// requests outside of the API.
+///
+/// When several routes match, the one with the most literal segments wins,
+/// as `/crates/:crate_id/owners` over `/crates/:crate_id/:version`.
+pub fn route_label(method: &str, path: &str) -> String {
+    let path = path.split('?').next().unwrap();
+    if !path.starts_with("/api/v1/") {
+        return "other".into();
+    }
+    let segments = path["/api/v1".len()..].split('/').collect::<Vec<_>>();
+    ROUTES
+        .iter()
+        .filter(|route| route.method.eq_ignore_ascii_case(method))
+        .filter_map(|route| {
+            let pattern = route.path.split('/').collect::<Vec<_>>();
+            if pattern.len() != segments.len() {
+                return None;
+            }
+            let mut literals = 0;
+            for (p, s) in pattern.iter().zip(&segments) {
+                if p.starts_with(':') {
+                    continue;
+                } else if p == s {
+                    literals += 1;
+                } else {
+                    return None;
+                }
+            }
+            Some((literals, route.path))
+        })
+        .max_by_key(|&(literals, _)| literals)
+        .map(|(_, pattern)| format!("/api/v1{}", pattern))
+        .unwrap_or_else(|| "other".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_are_labelled_with_their_pattern() {
+        assert_eq!(route_label("GET", "/api/v1/crates"), "/api/v1/crates");
+        assert_eq!(
+            route_label("GET", "/api/v1/crates/serde?include=versions"),
+            "/api/v1/crates/:crate_id"
+        );
+        assert_eq!(
+            route_label("GET", "/api/v1/crates/serde/owners"),
+            "/api/v1/crates/:crate_id/owners"
+        );
+        assert_eq!(
+            route_label("GET", "/api/v1/crates/serde/1.0.0"),
+            "/api/v1/crates/:crate_id/:version"
+        );
+        assert_eq!(route_label("POST", "/api/v1/crates"), "other");
+        assert_eq!(route_label("GET", "/api/v1/nope/nope/nope"), "other");
+        assert_eq!(route_label("GET", "/authorize"), "other");
+    }
+
+    #[test]
+    fn response_times_are_bucketed() {
+        let metrics = Metrics::default();
+        metrics.record_request("GET", "/api/v1/crates", 200, Duration::from_millis(30));
+        metrics.record_request("GET", "/api/v1/crates", 404, Duration::from_secs(2));
+        let routes = metrics.routes.lock().unwrap();
+        let route = &routes[&("GET".to_string(), "/api/v1/crates".to_string())];
+        assert_eq!(route.count, 2);
+        assert_eq!(route.statuses[&200], 1);
+        assert_eq!(route.statuses[&404], 1);
+        assert_eq!(route.buckets, [0, 0, 0, 1, 1, 1, 1, 1, 2, 2]);
+    }
+}
@@ -0,0 +1,67 @@
+//! Middleware that sets the `Cache-Control` header of API responses, so that a
+//! CDN in front of the registry knows what it may cache.
+
+use super::prelude::*;
+
+use middleware::current_user::RequestUser;
+
+/// How long a public response may be cached. Handlers opt into caching by
+/// inserting a policy into the request extensions.
+///
+/// The requests served by the CDN never reach the registry, so handlers
+/// counting their requests, like the downloads of crate files, must not set
+/// a policy for the requests they count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Responses that change whenever a crate is published or yanked.
+    Short,
+    /// Responses that only change when a crate is yanked, which purges them.
+    Long,
+    /// Responses to the HEAD requests Cargo mirrors check their copies of
+    /// the crate files with, which never change. The responses carry the
+    /// yanked flag and are purged along with the `Long` responses.
+    Redirect,
+}
+
+impl CachePolicy {
+    pub fn max_age(&self) -> u32 {
+        match *self {
+            CachePolicy::Short => 60,
+            CachePolicy::Long => 24 * 60 * 60,
+            CachePolicy::Redirect => 365 * 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CacheControl;
+
+impl Middleware for CacheControl {
+    fn after(
+        &self,
+        req: &mut Request,
+        mut res: Result<Response, Box<Error + Send>>,
+    ) -> Result<Response, Box<Error + Send>> {
+        if let Ok(ref mut response) = res {
+            if response.headers.contains_key("Cache-Control") {
+                return res;
+            }
+
+            let policy = req.extensions().find::<CachePolicy>().cloned();
+            let value = if req.user().is_ok() {
+                // Responses to signed in users may contain private data
+                Some("private, no-store".to_string())
+            } else if let Some(policy) = policy {
+                Some(format!("public, max-age={}", policy.max_age()))
+            } else if req.path().starts_with("/api/") {
+                Some("no-cache".to_string())
+            } else {
+                None
+            };
+            if let Some(value) = value {
+                response.headers.insert("Cache-Control".into(), vec![value]);
+            }
+        }
+        res
+    }
+}
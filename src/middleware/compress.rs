@@ -0,0 +1,175 @@
+//! Middleware that compresses large JSON responses with gzip or deflate, when
+//! the client accepts them.
+
+use super::prelude::*;
+
+use conduit::WriteBody;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::{self, Write};
+
+/// Smaller responses aren't worth compressing.
+const MIN_COMPRESSED_LENGTH: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Picks the encoding of the response from the `Accept-Encoding` header,
+/// preferring gzip. Encodings with a quality of 0 are refused, and `*` only
+/// stands for the encodings that aren't listed.
+fn accepted_encoding(header: &str) -> Option<Encoding> {
+    let listed = header
+        .split(',')
+        .filter_map(|value| {
+            let mut params = value.split(';');
+            let name = params.next()?.trim().to_lowercase();
+            let refused = params.any(|param| {
+                let param = param.trim().replace(' ', "");
+                param.starts_with("q=") && param[2..].parse::<f32>().ok() == Some(0.0)
+            });
+            Some((name, !refused))
+        })
+        .collect::<Vec<_>>();
+    let accepts = |name: &str| {
+        let quality = |wanted: &str| {
+            listed
+                .iter()
+                .find(|&&(ref listed, _)| listed == wanted)
+                .map(|&(_, accepted)| accepted)
+        };
+        quality(name).or_else(|| quality("*")).unwrap_or(false)
+    };
+    if accepts("gzip") {
+        Some(Encoding::Gzip)
+    } else if accepts("deflate") {
+        Some(Encoding::Deflate)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Compress;
+
+impl Middleware for Compress {
+    fn after(
+        &self,
+        req: &mut Request,
+        res: Result<Response, Box<Error + Send>>,
+    ) -> Result<Response, Box<Error + Send>> {
+        let mut response = res?;
+        let is_json = response
+            .headers
+            .get("Content-Type")
+            .and_then(|values| values.first())
+            .map_or(false, |value| value.starts_with("application/json"));
+        let length = response
+            .headers
+            .get("Content-Length")
+            .and_then(|values| values.first())
+            .and_then(|value| value.parse::<u64>().ok());
+        let large = length.map_or(false, |length| length >= MIN_COMPRESSED_LENGTH);
+        if !is_json || !large || response.headers.contains_key("Content-Encoding") {
+            return Ok(response);
+        }
+
+        // Caches must not serve a compressed response to a client that
+        // doesn't accept it, nor the other way around
+        response
+            .headers
+            .entry("Vary".into())
+            .or_insert_with(Vec::new)
+            .push("Accept-Encoding".into());
+
+        let encoding = req.headers()
+            .find("Accept-Encoding")
+            .and_then(|values| accepted_encoding(&values.join(",")));
+        let encoding = match encoding {
+            Some(encoding) => encoding,
+            None => return Ok(response),
+        };
+
+        // The body is compressed as it's written, its length isn't known
+        response.headers.remove("Content-Length");
+        response
+            .headers
+            .insert("Content-Encoding".into(), vec![encoding.as_str().into()]);
+        let body = ::std::mem::replace(&mut response.body, Box::new(io::empty()));
+        response.body = Box::new(Compressed { body, encoding });
+        Ok(response)
+    }
+}
+
+/// A response body compressed while it's written.
+struct Compressed {
+    body: Box<WriteBody + Send>,
+    encoding: Encoding,
+}
+
+impl WriteBody for Compressed {
+    fn write_body(&mut self, out: &mut Write) -> io::Result<u64> {
+        let mut out = CountingWriter { inner: out, count: 0 };
+        match self.encoding {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(&mut out, Compression::Default);
+                self.body.write_body(&mut encoder)?;
+                encoder.finish()?;
+            }
+            // The `deflate` content coding is the zlib format, not a raw
+            // deflate stream (RFC 7230, section 4.2.2)
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(&mut out, Compression::Default);
+                self.body.write_body(&mut encoder)?;
+                encoder.finish()?;
+            }
+        }
+        Ok(out.count)
+    }
+}
+
+/// Counts the compressed bytes written.
+struct CountingWriter<'a> {
+    inner: &'a mut Write,
+    count: u64,
+}
+
+impl<'a> Write for CountingWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accepted_encoding, Encoding};
+
+    #[test]
+    fn picks_the_accepted_encoding() {
+        assert_eq!(accepted_encoding("gzip, deflate, br"), Some(Encoding::Gzip));
+        assert_eq!(accepted_encoding("deflate"), Some(Encoding::Deflate));
+        assert_eq!(accepted_encoding("gzip;q=0, deflate"), Some(Encoding::Deflate));
+        assert_eq!(accepted_encoding("*"), Some(Encoding::Gzip));
+        assert_eq!(accepted_encoding("identity"), None);
+        assert_eq!(accepted_encoding("gzip; q=0"), None);
+        assert_eq!(accepted_encoding("gzip;q=0, *"), Some(Encoding::Deflate));
+        assert_eq!(accepted_encoding("*, gzip;q=0, deflate;q=0"), None);
+    }
+}
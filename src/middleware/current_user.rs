@@ -2,16 +2,24 @@ use super::prelude::*;
 
 use conduit_cookie::RequestSession;
 use diesel::prelude::*;
+use std::net::IpAddr;
 
 use db::RequestTransaction;
-use util::errors::{std_error, CargoResult, ChainError, Unauthorized};
+use middleware::app::RequestApp;
+use util::client_ip;
+use util::errors::{human, std_error, CargoResult, ChainError, Unauthorized};
 
-use models::User;
+use models::{ApiToken, TwoFactorCredential, User};
 use schema::users;
 
 #[derive(Debug, Clone, Copy)]
 pub struct CurrentUser;
 
+/// Marks requests whose session was verified with a two-factor code, see
+/// `RequestUser::require_two_factor`.
+#[derive(Debug, Clone, Copy)]
+pub struct TwoFactorVerified;
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum AuthenticationSource {
     SessionCookie,
@@ -21,28 +29,52 @@ pub enum AuthenticationSource {
 impl Middleware for CurrentUser {
     fn before(&self, req: &mut Request) -> Result<(), Box<Error + Send>> {
         // Check if the request has a session cookie with a `user_id` property inside
-        let id = {
-            req.session()
-                .get("user_id")
-                .and_then(|s| s.parse::<i32>().ok())
+        // and whether that session was verified with a two-factor code
+        let (id, two_factor_verified) = {
+            let session = req.session();
+            let id = session.get("user_id").and_then(|s| s.parse::<i32>().ok());
+            let verified = id.map_or(false, |id| {
+                session.get("two_factor_verified") == Some(&id.to_string())
+            });
+            (id, verified)
         };
 
         let conn = req.db_conn().map_err(std_error)?;
 
         if let Some(id) = id {
-            // If it did, look for a user in the database with the given `user_id`
-            let maybe_user = users::table.find(id).first::<User>(&*conn);
+            // If it did, look for a user in the database with the given `user_id`,
+            // unless they deleted their account since
+            let maybe_user = users::table
+                .find(id)
+                .filter(users::deleted_at.is_null())
+                .first::<User>(&*conn);
             if let Ok(user) = maybe_user {
                 // Attach the `User` model from the database to the request
                 req.mut_extensions().insert(user);
                 req.mut_extensions()
                     .insert(AuthenticationSource::SessionCookie);
+                if two_factor_verified {
+                    req.mut_extensions().insert(TwoFactorVerified);
+                }
             }
         } else {
             // Otherwise, look for an `Authorization` header on the request
             // and try to find a user in the database with a matching API token
             let user = if let Some(headers) = req.headers().find("Authorization") {
-                User::find_by_api_token(&conn, headers[0]).ok()
+                // Tokens restricted to some addresses don't authenticate
+                // requests from anywhere else
+                let ip = client_ip(req).parse::<IpAddr>().ok();
+                let allowed = ApiToken::find_by_token(&conn, headers[0])
+                    .ok()
+                    .map_or(false, |token| match ip {
+                        Some(ip) => token.allows_ip(&ip),
+                        None => token.allowed_ips.is_empty(),
+                    });
+                if allowed {
+                    User::find_by_api_token(&conn, headers[0]).ok()
+                } else {
+                    None
+                }
             } else {
                 None
             };
@@ -60,6 +92,14 @@ impl Middleware for CurrentUser {
 pub trait RequestUser {
     fn user(&self) -> CargoResult<&User>;
     fn authentication_source(&self) -> CargoResult<AuthenticationSource>;
+
+    /// Returns the current user if they are an administrator of the registry.
+    fn admin(&self) -> CargoResult<&User>;
+
+    /// Checks that `user` has enabled two-factor authentication, and verified
+    /// their session with a code when signed in with a cookie, if the
+    /// registry requires it.
+    fn require_two_factor(&self, user: &User) -> CargoResult<()>;
 }
 
 impl<'a> RequestUser for Request + 'a {
@@ -75,4 +115,33 @@ impl<'a> RequestUser for Request + 'a {
             .cloned()
             .chain_error(|| Unauthorized)
     }
+
+    fn admin(&self) -> CargoResult<&User> {
+        let user = self.user()?;
+        if user.deleted_at.is_none() && self.app().config.admin_github_ids.contains(&user.gh_id) {
+            Ok(user)
+        } else {
+            Err(human("only administrators can perform this action"))
+        }
+    }
+
+    fn require_two_factor(&self, user: &User) -> CargoResult<()> {
+        if !self.app().config.require_two_factor {
+            return Ok(());
+        }
+        if !TwoFactorCredential::enabled_for(&*self.db_conn()?, user.id)? {
+            return Err(human(
+                "two-factor authentication is required to perform this action. \
+                 Visit https://crates.io/me to enable it.",
+            ));
+        }
+        let verified = self.extensions().find::<TwoFactorVerified>().is_some();
+        let source = self.authentication_source().ok();
+        if source == Some(AuthenticationSource::SessionCookie) && !verified {
+            return Err(human(
+                "this session must be verified with a two-factor authentication code first",
+            ));
+        }
+        Ok(())
+    }
 }
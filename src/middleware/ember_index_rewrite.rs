@@ -1,5 +1,5 @@
 //! Rewrite the request path to "index.html" if the path doesn't start
-//! with "/api" and the Accept header contains "html".
+//! with "/api" or isn't a sitemap, and the Accept header contains "html".
 
 use super::prelude::*;
 
@@ -34,8 +34,10 @@ impl Handler for EmberIndexRewrite {
         // If the route starts with /api, just assume they want the API
         // response and fall through.
         let is_api_path = req.path().starts_with("/api");
+        // Crawlers accept html when fetching the sitemaps too
+        let is_sitemap = req.path() == "/sitemap.xml" || req.path().starts_with("/sitemaps/");
         let handler = self.handler.as_ref().unwrap();
-        if wants_html && !is_api_path {
+        if wants_html && !is_api_path && !is_sitemap {
             handler.call(&mut RequestProxy {
                 other: req,
                 path: Some("/index.html"),
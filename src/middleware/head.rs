@@ -6,6 +6,11 @@ use conduit::Method;
 use std::io;
 use util::RequestProxy;
 
+/// Inserted in the extensions of HEAD requests, which handlers see as GET
+/// requests, for the handlers that mustn't have side effects on them.
+#[derive(Debug, Clone, Copy)]
+pub struct HeadRequest;
+
 // Can't derive debug because of Handler.
 #[allow(missing_debug_implementations)]
 #[derive(Default)]
@@ -22,6 +27,7 @@ impl AroundMiddleware for Head {
 impl Handler for Head {
     fn call(&self, req: &mut Request) -> Result<Response, Box<Error + Send>> {
         if req.method() == Method::Head {
+            req.mut_extensions().insert(HeadRequest);
             let mut req = RequestProxy {
                 other: req,
                 path: None,
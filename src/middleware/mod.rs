@@ -5,21 +5,27 @@ mod prelude {
 }
 
 pub use self::app::AppMiddleware;
+pub use self::cache_control::{CacheControl, CachePolicy};
 pub use self::current_user::CurrentUser;
 pub use self::debug::*;
 pub use self::ember_index_rewrite::EmberIndexRewrite;
-pub use self::head::Head;
+pub use self::head::{Head, HeadRequest};
+pub use self::rate_limit::RateLimit;
 pub use self::security_headers::SecurityHeaders;
 pub use self::static_or_continue::StaticOrContinue;
 
 pub mod app;
 mod blacklist_ips;
+pub mod cache_control;
+mod compress;
 pub mod current_user;
 mod debug;
 mod ember_index_rewrite;
 mod ensure_well_formed_500;
 mod head;
 mod log_request;
+mod rate_limit;
+mod record_metrics;
 mod security_headers;
 mod static_or_continue;
 
@@ -37,6 +43,7 @@ use {App, Env};
 pub fn build_middleware(app: Arc<App>, endpoints: R404) -> MiddlewareBuilder {
     let mut m = MiddlewareBuilder::new(endpoints);
     let env = app.config.env;
+    let rate_limit = RateLimit::new(app.config.rate_limit_requests, app.config.rate_limit_window);
 
     if env != Env::Test {
         m.add(ensure_well_formed_500::EnsureWellFormed500);
@@ -65,11 +72,17 @@ pub fn build_middleware(app: Arc<App>, endpoints: R404) -> MiddlewareBuilder {
     if env == Env::Production {
         m.add(SecurityHeaders::new(&app.config.uploader));
     }
-    m.add(AppMiddleware::new(app));
+    m.add(AppMiddleware::new(Arc::clone(&app)));
 
     // Sets the current user on each request.
     m.add(CurrentUser);
 
+    // Tells a fronting CDN which responses it may cache.
+    m.add(CacheControl);
+
+    // Compresses large JSON responses for the clients accepting it.
+    m.add(compress::Compress);
+
     // Serve the static files in the *dist* directory, which are the frontend assets.
     // Not needed for the backend tests.
     if env != Env::Test {
@@ -81,11 +94,16 @@ pub fn build_middleware(app: Arc<App>, endpoints: R404) -> MiddlewareBuilder {
 
     m.around(Head::default());
 
+    // Limits the number of requests authenticated users can make
+    m.around(rate_limit);
+
     if let Ok(ip_list) = env::var("BLACKLISTED_IPS") {
         let ips = ip_list.split(',').map(String::from).collect();
         m.around(blacklist_ips::BlockIps::new(ips));
     }
 
+    m.around(record_metrics::RecordMetrics::new(app));
+
     if env != Env::Test {
         m.around(log_request::LogRequests::default());
     }
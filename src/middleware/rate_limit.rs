@@ -0,0 +1,126 @@
+//! Middleware that limits how many requests an authenticated user can make in a
+//! given time window, and reports the state of that limit through the
+//! `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers.
+
+use super::prelude::*;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use models::User;
+use util::errors::{CargoError, TooManyRequests};
+
+/// The requests a user made in the current window.
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started_at: u64,
+    count: u64,
+}
+
+// Can't derive debug because of Handler.
+#[allow(missing_debug_implementations)]
+pub struct RateLimit {
+    max_requests: u64,
+    window: u64,
+    windows: Mutex<HashMap<i32, Window>>,
+    handler: Option<Box<Handler>>,
+}
+
+impl RateLimit {
+    pub fn new(max_requests: u64, window: u64) -> Self {
+        Self {
+            max_requests,
+            window,
+            windows: Mutex::new(HashMap::new()),
+            handler: None,
+        }
+    }
+
+    /// Records a request made by the given user at the given time (in seconds
+    /// since the epoch), returning the updated state of their window.
+    fn record(&self, user_id: i32, now: u64) -> Window {
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(user_id).or_insert(Window {
+            started_at: now,
+            count: 0,
+        });
+        if now >= window.started_at + self.window {
+            *window = Window {
+                started_at: now,
+                count: 0,
+            };
+        }
+        window.count += 1;
+        *window
+    }
+}
+
+impl AroundMiddleware for RateLimit {
+    fn with_handler(&mut self, handler: Box<Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+impl Handler for RateLimit {
+    fn call(&self, req: &mut Request) -> Result<Response, Box<Error + Send>> {
+        let user_id = match req.extensions().find::<User>() {
+            Some(user) => user.id,
+            None => return self.handler.as_ref().unwrap().call(req),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let window = self.record(user_id, now);
+        let reset = window.started_at + self.window;
+
+        let mut response = if window.count > self.max_requests {
+            TooManyRequests {
+                retry_after: reset - now,
+            }.response()
+                .unwrap()
+        } else {
+            self.handler.as_ref().unwrap().call(req)?
+        };
+
+        let remaining = self.max_requests.saturating_sub(window.count);
+        response.headers.insert(
+            "X-RateLimit-Limit".into(),
+            vec![self.max_requests.to_string()],
+        );
+        response
+            .headers
+            .insert("X-RateLimit-Remaining".into(), vec![remaining.to_string()]);
+        response
+            .headers
+            .insert("X-RateLimit-Reset".into(), vec![reset.to_string()]);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimit;
+
+    #[test]
+    fn windows_are_tracked_per_user() {
+        let limit = RateLimit::new(2, 60);
+        assert_eq!(limit.record(1, 100).count, 1);
+        assert_eq!(limit.record(1, 110).count, 2);
+        assert_eq!(limit.record(2, 110).count, 1);
+        assert_eq!(limit.record(1, 120).count, 3);
+    }
+
+    #[test]
+    fn windows_are_reset_once_expired() {
+        let limit = RateLimit::new(2, 60);
+        assert_eq!(limit.record(1, 100).count, 1);
+        assert_eq!(limit.record(1, 159).count, 2);
+
+        let window = limit.record(1, 160);
+        assert_eq!(window.count, 1);
+        assert_eq!(window.started_at, 160);
+    }
+}
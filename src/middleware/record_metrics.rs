@@ -0,0 +1,46 @@
+//! Records the route, status and response time of every request in
+//! `App::metrics`.
+
+use super::prelude::*;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use App;
+
+// Can't derive debug because of Handler and App.
+#[allow(missing_debug_implementations)]
+pub struct RecordMetrics {
+    app: Arc<App>,
+    handler: Option<Box<Handler>>,
+}
+
+impl RecordMetrics {
+    pub fn new(app: Arc<App>) -> Self {
+        Self { app, handler: None }
+    }
+}
+
+impl AroundMiddleware for RecordMetrics {
+    fn with_handler(&mut self, handler: Box<Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+impl Handler for RecordMetrics {
+    fn call(&self, req: &mut Request) -> Result<Response, Box<Error + Send>> {
+        let request_start = Instant::now();
+        let res = self.handler.as_ref().unwrap().call(req);
+        let status = match res {
+            Ok(ref r) => r.status.0,
+            Err(_) => 500,
+        };
+        self.app.metrics.record_request(
+            &req.method().to_string(),
+            req.path(),
+            status,
+            request_start.elapsed(),
+        );
+        res
+    }
+}
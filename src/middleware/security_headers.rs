@@ -20,7 +20,7 @@ impl SecurityHeaders {
 
         headers.insert("X-XSS-Protection".into(), vec!["1; mode=block".into()]);
 
-        let s3_host = match *uploader {
+        let s3_host = match *uploader.primary() {
             Uploader::S3 {
                 ref bucket,
                 ref cdn,
@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use schema::announcements;
+use views::EncodableAnnouncement;
+
+/// A message broadcast by the operators of the registry to all of its users,
+/// e.g. about a maintenance window or a policy change.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+pub struct Announcement {
+    pub id: i32,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "announcements"]
+pub struct NewAnnouncement<'a> {
+    pub message: &'a str,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+}
+
+impl<'a> NewAnnouncement<'a> {
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<Announcement> {
+        ::diesel::insert_into(announcements::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
+impl Announcement {
+    /// Returns the announcements that should currently be displayed, newest first.
+    pub fn active(conn: &PgConnection) -> QueryResult<Vec<Announcement>> {
+        use diesel::dsl::now;
+
+        announcements::table
+            .filter(announcements::starts_at.le(now))
+            .filter(
+                announcements::ends_at
+                    .is_null()
+                    .or(announcements::ends_at.gt(now)),
+            )
+            .order(announcements::starts_at.desc())
+            .load(conn)
+    }
+
+    pub fn encodable(self) -> EncodableAnnouncement {
+        let Announcement {
+            id,
+            message,
+            starts_at,
+            ends_at,
+            ..
+        } = self;
+        EncodableAnnouncement {
+            id,
+            message,
+            starts_at,
+            ends_at,
+        }
+    }
+}
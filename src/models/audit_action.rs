@@ -0,0 +1,71 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+use semver;
+
+use models::{Crate, User};
+use schema::audit_actions;
+use updates::{self, Update};
+use views::EncodableAuditAction;
+
+/// Something a user did to a crate: publishing, yanking or unyanking one of
+/// its versions, or changing its owners. Users can review their own actions
+/// with `GET /me/activity`.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(User)]
+pub struct AuditAction {
+    pub id: i32,
+    pub user_id: i32,
+    pub crate_id: Option<i32>,
+    /// The name of the crate at the time, kept if the crate is deleted.
+    pub crate_name: String,
+    pub version: Option<String>,
+    /// One of `publish`, `yank`, `unyank`, `owner_add` and `owner_remove`.
+    pub action: String,
+    /// The login of the owner added or removed, or how a publish was
+    /// authenticated, see `PublishSource::published_with`.
+    pub detail: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl AuditAction {
+    /// Records the action and sends it to the clients of `GET /updates/stream`
+    /// once the transaction commits.
+    pub fn record(
+        conn: &PgConnection,
+        user: &User,
+        krate: &Crate,
+        version: Option<&semver::Version>,
+        action: &str,
+        detail: Option<&str>,
+    ) -> QueryResult<()> {
+        diesel::insert_into(audit_actions::table)
+            .values((
+                audit_actions::user_id.eq(user.id),
+                audit_actions::crate_id.eq(krate.id),
+                audit_actions::crate_name.eq(&krate.name),
+                audit_actions::version.eq(version.map(|v| v.to_string())),
+                audit_actions::action.eq(action),
+                audit_actions::detail.eq(detail),
+            ))
+            .execute(conn)?;
+        updates::notify(
+            conn,
+            &Update {
+                action: action.into(),
+                krate: krate.name.clone(),
+                version: version.map(|v| v.to_string()),
+            },
+        )
+    }
+
+    pub fn encodable(self) -> EncodableAuditAction {
+        EncodableAuditAction {
+            action: self.action,
+            krate: self.crate_name,
+            version: self.version,
+            detail: self.detail,
+            created_at: self.created_at,
+        }
+    }
+}
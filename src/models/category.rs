@@ -1,7 +1,8 @@
 use chrono::NaiveDateTime;
 use diesel::*;
 
-use models::Crate;
+use models::krate::ALL_COLUMNS;
+use models::{Crate, User};
 use schema::*;
 use views::EncodableCategory;
 
@@ -16,6 +17,9 @@ pub struct Category {
     pub created_at: NaiveDateTime,
 }
 
+/// The most crates a category can feature.
+pub const MAX_FEATURED_CRATES: usize = 10;
+
 #[derive(Associations, Insertable, Identifiable, Debug, Clone, Copy)]
 #[belongs_to(Category)]
 #[belongs_to(Crate)]
@@ -26,7 +30,56 @@ pub struct CrateCategory {
     category_id: i32,
 }
 
+/// A slug a category had before it was renamed, the old links and searches
+/// by it are resolved to the category.
+#[derive(Clone, Identifiable, Queryable, Associations, Debug)]
+#[belongs_to(Category)]
+#[primary_key(old_slug)]
+#[table_name = "category_renames"]
+pub struct CategoryRename {
+    pub old_slug: String,
+    pub category_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
 impl Category {
+    /// Finds the category with the slug, or the category it was renamed to.
+    /// The subcategories of a renamed category are found by their old slug
+    /// too, e.g. `old::sub` once `old` became `new`.
+    pub fn find_by_slug(conn: &PgConnection, slug: &str) -> QueryResult<Category> {
+        let slug = slug.to_lowercase();
+        let found = categories::table
+            .filter(categories::slug.eq(&slug))
+            .first::<Category>(conn)
+            .optional()?;
+        if let Some(category) = found {
+            return Ok(category);
+        }
+
+        let renamed = category_renames::table
+            .inner_join(categories::table)
+            .filter(category_renames::old_slug.eq(&slug))
+            .select(categories::all_columns)
+            .first::<Category>(conn)
+            .optional()?;
+        if let Some(category) = renamed {
+            return Ok(category);
+        }
+
+        match slug.rfind("::") {
+            Some(i) => {
+                let parent = Category::find_by_slug(conn, &slug[..i])?;
+                if parent.slug == slug[..i] {
+                    return Err(NotFound);
+                }
+                categories::table
+                    .filter(categories::slug.eq(format!("{}{}", parent.slug, &slug[i..])))
+                    .first(conn)
+            }
+            None => Err(NotFound),
+        }
+    }
+
     pub fn encodable(self) -> EncodableCategory {
         let Category {
             crates_cnt,
@@ -115,6 +168,91 @@ impl Category {
         ))).load(conn)
     }
 
+    /// Recomputes `crates_cnt` from `crates_categories` for the categories
+    /// whose count drifted, returning how many were corrected.
+    pub fn recount_crates(conn: &PgConnection) -> QueryResult<usize> {
+        sql_query(
+            "UPDATE categories SET crates_cnt = counts.crates_cnt \
+             FROM ( \
+             SELECT categories.id, COUNT(crates_categories.crate_id)::int AS crates_cnt \
+             FROM categories \
+             LEFT JOIN crates_categories ON crates_categories.category_id = categories.id \
+             GROUP BY categories.id \
+             ) counts \
+             WHERE categories.id = counts.id AND categories.crates_cnt <> counts.crates_cnt",
+        ).execute(conn)
+    }
+
+    /// Returns whether the crate is in the category or one of its
+    /// subcategories.
+    pub fn contains_crate(&self, conn: &PgConnection, krate: &Crate) -> QueryResult<bool> {
+        select(dsl::exists(
+            crates_categories::table
+                .inner_join(categories::table)
+                .filter(crates_categories::crate_id.eq(krate.id))
+                .filter(
+                    categories::slug
+                        .eq(&self.slug)
+                        .or(categories::slug.like(format!("{}::%", self.slug))),
+                ),
+        )).get_result(conn)
+    }
+
+    /// Returns the crates featured in the category, in the order they were
+    /// featured. The crates moved out of the category since are left out.
+    pub fn featured_crates(&self, conn: &PgConnection) -> QueryResult<Vec<Crate>> {
+        let members = crates_categories::table
+            .inner_join(categories::table)
+            .filter(
+                categories::slug
+                    .eq(&self.slug)
+                    .or(categories::slug.like(format!("{}::%", self.slug))),
+            )
+            .select(crates_categories::crate_id);
+        category_featured_crates::table
+            .inner_join(crates::table)
+            .filter(category_featured_crates::category_id.eq(self.id))
+            .filter(crates::id.eq_any(members))
+            .order(category_featured_crates::position)
+            .select(ALL_COLUMNS)
+            .load(conn)
+    }
+
+    /// Features the crate in the category after the crates already featured,
+    /// returning whether it wasn't featured yet.
+    pub fn feature_crate(
+        &self,
+        conn: &PgConnection,
+        krate: &Crate,
+        admin: &User,
+    ) -> QueryResult<bool> {
+        conn.transaction(|| {
+            let position = category_featured_crates::table
+                .filter(category_featured_crates::category_id.eq(self.id))
+                .select(dsl::max(category_featured_crates::position))
+                .first::<Option<i32>>(conn)?
+                .map_or(0, |position| position + 1);
+            let inserted = insert_into(category_featured_crates::table)
+                .values((
+                    category_featured_crates::category_id.eq(self.id),
+                    category_featured_crates::crate_id.eq(krate.id),
+                    category_featured_crates::position.eq(position),
+                    category_featured_crates::created_by.eq(admin.id),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            Ok(inserted > 0)
+        })
+    }
+
+    /// Stops featuring the crate in the category, returning whether it was.
+    pub fn unfeature_crate(&self, conn: &PgConnection, krate: &Crate) -> QueryResult<bool> {
+        let featured = category_featured_crates::table
+            .filter(category_featured_crates::category_id.eq(self.id))
+            .filter(category_featured_crates::crate_id.eq(krate.id));
+        Ok(delete(featured).execute(conn)? > 0)
+    }
+
     pub fn subcategories(&self, conn: &PgConnection) -> QueryResult<Vec<Category>> {
         use diesel::sql_types::Text;
 
@@ -0,0 +1,75 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::dsl::{max, now, IntervalDsl};
+use diesel::prelude::*;
+
+use models::krate::ALL_COLUMNS;
+use models::Crate;
+use schema::{crate_dependents, crates, metadata, versions};
+
+/// How many days a crate is listed as a new dependent of another.
+const NEW_DEPENDENT_DAYS: i32 = 30;
+
+/// How many minutes the versions published are looked at again on the next
+/// run, in case a publish with a lower version id committed after the run.
+const RECORD_OVERLAP_MINUTES: i32 = 10;
+
+/// A crate depending on another, recorded by the `record-new-dependents` job
+/// so that the owners of a crate can follow who starts using it.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[primary_key(crate_id, dependent_id)]
+#[belongs_to(Crate)]
+pub struct CrateDependent {
+    pub crate_id: i32,
+    pub dependent_id: i32,
+    /// When the first version depending on the crate was published.
+    pub first_seen_at: NaiveDateTime,
+}
+
+impl CrateDependent {
+    /// Records the crates depending on others since the last run, returning
+    /// how many were recorded. A crate that stopped depending on another and
+    /// depends on it again isn't recorded a second time.
+    ///
+    /// Only the versions published since the last run are looked at, the id
+    /// of the last one being kept in `metadata.dependents_recorded_up_to`.
+    pub fn record_new(conn: &PgConnection) -> QueryResult<usize> {
+        use diesel::sql_types::Integer;
+
+        conn.transaction(|| {
+            let recorded_up_to = metadata::table
+                .select(metadata::dependents_recorded_up_to)
+                .get_result::<i32>(conn)?;
+            let recorded = diesel::sql_query(include_str!("crate_dependents_record_new.sql"))
+                .bind::<Integer, _>(recorded_up_to)
+                .execute(conn)?;
+
+            let last_version = versions::table
+                .filter(versions::id.gt(recorded_up_to))
+                .filter(versions::created_at.lt(now - RECORD_OVERLAP_MINUTES.minutes()))
+                .select(max(versions::id))
+                .get_result::<Option<i32>>(conn)?;
+            if let Some(last_version) = last_version {
+                diesel::update(metadata::table)
+                    .set(metadata::dependents_recorded_up_to.eq(last_version))
+                    .execute(conn)?;
+            }
+            Ok(recorded)
+        })
+    }
+
+    /// Returns the crates that started depending on the crate in the last
+    /// `NEW_DEPENDENT_DAYS` days, the most recent first.
+    pub fn recent(
+        conn: &PgConnection,
+        krate: &Crate,
+    ) -> QueryResult<Vec<(NaiveDateTime, Crate)>> {
+        crate_dependents::table
+            .inner_join(crates::table)
+            .filter(crate_dependents::crate_id.eq(krate.id))
+            .filter(crate_dependents::first_seen_at.gt(now - NEW_DEPENDENT_DAYS.days()))
+            .order((crate_dependents::first_seen_at.desc(), crates::name))
+            .select((crate_dependents::first_seen_at, ALL_COLUMNS))
+            .load(conn)
+    }
+}
@@ -0,0 +1,11 @@
+-- Records the crates depending on another for the first time in the versions
+-- whose id is greater than $1, seen since the earliest of these versions with
+-- such a dependency
+INSERT INTO crate_dependents (crate_id, dependent_id, first_seen_at)
+SELECT dependencies.crate_id, versions.crate_id, MIN(versions.created_at)
+FROM dependencies
+INNER JOIN versions ON versions.id = dependencies.version_id
+WHERE dependencies.crate_id <> versions.crate_id
+    AND versions.id > $1
+GROUP BY dependencies.crate_id, versions.crate_id
+ON CONFLICT DO NOTHING
@@ -0,0 +1,161 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use models::{Crate, User};
+use schema::{crate_list_items, crate_lists, crates, users};
+use util::{human, CargoResult};
+use views::EncodableCrateList;
+
+/// A named public list of crates curated by a user, such as `awesome-async`.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+#[table_name = "crate_lists"]
+pub struct CrateList {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Associations, Insertable, Identifiable, Debug, Clone, Copy)]
+#[belongs_to(CrateList, foreign_key = "list_id")]
+#[belongs_to(Crate)]
+#[table_name = "crate_list_items"]
+#[primary_key(list_id, crate_id)]
+pub struct CrateListItem {
+    list_id: i32,
+    crate_id: i32,
+}
+
+impl CrateList {
+    pub const MAX_NAME_LENGTH: usize = 64;
+
+    pub fn valid_name(name: &str) -> bool {
+        name.len() <= Self::MAX_NAME_LENGTH
+            && name.chars().next().map_or(false, |c| c.is_ascii_alphanumeric())
+            && name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    /// Creates a list, failing if the user already has one with that name.
+    pub fn create(
+        conn: &PgConnection,
+        user: &User,
+        name: &str,
+        description: Option<&str>,
+    ) -> CargoResult<CrateList> {
+        if !CrateList::valid_name(name) {
+            return Err(human(&format_args!("invalid list name: `{}`", name)));
+        }
+        diesel::insert_into(crate_lists::table)
+            .values((
+                crate_lists::user_id.eq(user.id),
+                crate_lists::name.eq(name),
+                crate_lists::description.eq(description),
+            ))
+            .on_conflict_do_nothing()
+            .get_result(conn)
+            .optional()?
+            .ok_or_else(|| human(&format_args!("you already have a list named `{}`", name)))
+    }
+
+    pub fn update(
+        &self,
+        conn: &PgConnection,
+        name: &str,
+        description: Option<&str>,
+    ) -> CargoResult<CrateList> {
+        if !CrateList::valid_name(name) {
+            return Err(human(&format_args!("invalid list name: `{}`", name)));
+        }
+        let taken = crate_lists::table
+            .filter(crate_lists::user_id.eq(self.user_id))
+            .filter(::lower(crate_lists::name).eq(name.to_lowercase()))
+            .filter(crate_lists::id.ne(self.id))
+            .count()
+            .get_result::<i64>(conn)?;
+        if taken > 0 {
+            return Err(human(&format_args!("you already have a list named `{}`", name)));
+        }
+        Ok(diesel::update(self)
+            .set((
+                crate_lists::name.eq(name),
+                crate_lists::description.eq(description),
+            ))
+            .get_result(conn)?)
+    }
+
+    pub fn add_crate(&self, conn: &PgConnection, krate: &Crate) -> QueryResult<usize> {
+        diesel::insert_into(crate_list_items::table)
+            .values(&CrateListItem {
+                list_id: self.id,
+                crate_id: krate.id,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    pub fn remove_crate(&self, conn: &PgConnection, krate: &Crate) -> QueryResult<usize> {
+        diesel::delete(
+            CrateListItem::belonging_to(self).filter(crate_list_items::crate_id.eq(krate.id)),
+        ).execute(conn)
+    }
+
+    /// Encodes the lists along with the names of their crates, in the order
+    /// they were added, and the logins of their users.
+    pub fn encode_all(
+        conn: &PgConnection,
+        lists: Vec<CrateList>,
+    ) -> QueryResult<Vec<EncodableCrateList>> {
+        let items = CrateListItem::belonging_to(&lists)
+            .inner_join(crates::table)
+            .select((crate_list_items::list_id, crates::name))
+            .order((crate_list_items::created_at, crates::name))
+            .load::<(i32, String)>(conn)?;
+        let mut crates_by_list = HashMap::<i32, Vec<String>>::new();
+        for (list_id, name) in items {
+            crates_by_list.entry(list_id).or_insert_with(Vec::new).push(name);
+        }
+
+        let user_ids = lists.iter().map(|l| l.user_id).collect::<Vec<_>>();
+        let logins = users::table
+            .filter(users::id.eq_any(user_ids))
+            .select((users::id, users::gh_login))
+            .load::<(i32, String)>(conn)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        Ok(lists
+            .into_iter()
+            .map(|list| {
+                let crates = crates_by_list.remove(&list.id).unwrap_or_default();
+                let user = logins.get(&list.user_id).cloned().unwrap_or_default();
+                list.encodable(user, crates)
+            })
+            .collect())
+    }
+
+    pub fn encodable(self, user: String, crates: Vec<String>) -> EncodableCrateList {
+        let CrateList {
+            id,
+            name,
+            description,
+            created_at,
+            updated_at,
+            ..
+        } = self;
+        EncodableCrateList {
+            id,
+            name,
+            description,
+            user,
+            crates,
+            created_at,
+            updated_at,
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+
+use models::{Crate, CrateCategory, CrateKeyword, Version};
+use schema::{categories, crate_metadata_changes, keywords};
+use views::EncodableCrateMetadataChange;
+
+/// A change of the metadata of a crate made by a publish, e.g. the keywords
+/// added to it. Owners can review them to understand changes in how their
+/// crate is found.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(Crate)]
+pub struct CrateMetadataChange {
+    pub id: i32,
+    pub crate_id: i32,
+    pub version_id: Option<i32>,
+    /// The metadata field, e.g. `keywords` or `description`.
+    pub field: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// The values of the metadata fields of a crate at some point, the single
+/// value fields being lists of zero or one value.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataSnapshot {
+    fields: Vec<(&'static str, Vec<String>)>,
+}
+
+impl MetadataSnapshot {
+    /// Takes the snapshot of a crate, or an empty one for a crate that
+    /// doesn't exist yet.
+    pub fn take(conn: &PgConnection, krate: Option<&Crate>) -> QueryResult<MetadataSnapshot> {
+        let krate = match krate {
+            Some(krate) => krate,
+            None => return Ok(MetadataSnapshot::default()),
+        };
+        let mut keywords = CrateKeyword::belonging_to(krate)
+            .inner_join(keywords::table)
+            .select(keywords::keyword)
+            .load::<String>(conn)?;
+        keywords.sort();
+        let mut categories = CrateCategory::belonging_to(krate)
+            .inner_join(categories::table)
+            .select(categories::slug)
+            .load::<String>(conn)?;
+        categories.sort();
+
+        Ok(MetadataSnapshot {
+            fields: vec![
+                ("keywords", keywords),
+                ("categories", categories),
+                ("description", single(&krate.description)),
+                ("homepage", single(&krate.homepage)),
+                ("documentation", single(&krate.documentation)),
+                ("repository", single(&krate.repository)),
+                ("license", single(&krate.license)),
+            ],
+        })
+    }
+
+    fn values(&self, field: &str) -> &[String] {
+        self.fields
+            .iter()
+            .find(|&&(name, _)| name == field)
+            .map(|&(_, ref values)| &values[..])
+            .unwrap_or(&[])
+    }
+}
+
+impl CrateMetadataChange {
+    /// Records the fields changed between the snapshots taken before and
+    /// after the version was published.
+    pub fn record(
+        conn: &PgConnection,
+        version: &Version,
+        before: &MetadataSnapshot,
+        after: &MetadataSnapshot,
+    ) -> QueryResult<()> {
+        let changes = after
+            .fields
+            .iter()
+            .filter_map(|&(field, ref values)| {
+                let (added, removed) = diff(before.values(field), values);
+                if added.is_empty() && removed.is_empty() {
+                    return None;
+                }
+                Some((
+                    crate_metadata_changes::crate_id.eq(version.crate_id),
+                    crate_metadata_changes::version_id.eq(version.id),
+                    crate_metadata_changes::field.eq(field),
+                    crate_metadata_changes::added.eq(added),
+                    crate_metadata_changes::removed.eq(removed),
+                ))
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(crate_metadata_changes::table)
+            .values(&changes)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn encodable(self, version: Option<String>) -> EncodableCrateMetadataChange {
+        EncodableCrateMetadataChange {
+            version,
+            field: self.field,
+            added: self.added,
+            removed: self.removed,
+            created_at: self.created_at,
+        }
+    }
+}
+
+fn single(value: &Option<String>) -> Vec<String> {
+    value.iter().cloned().collect()
+}
+
+/// Returns the values added to and removed from a field.
+fn diff(before: &[String], after: &[String]) -> (Vec<String>, Vec<String>) {
+    let added = after
+        .iter()
+        .filter(|v| !before.contains(v))
+        .cloned()
+        .collect();
+    let removed = before
+        .iter()
+        .filter(|v| !after.contains(v))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn diffs_values() {
+        let (added, removed) = diff(&strings(&["cli", "parser"]), &strings(&["parser", "json"]));
+        assert_eq!(added, ["json"]);
+        assert_eq!(removed, ["cli"]);
+
+        let (added, removed) = diff(&strings(&["same"]), &strings(&["same"]));
+        assert!(added.is_empty() && removed.is_empty());
+    }
+}
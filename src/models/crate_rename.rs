@@ -0,0 +1,132 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+
+use app::App;
+use git;
+use util::errors::CargoError;
+use util::{human, CargoResult};
+
+use models::krate::{canon_crate_name, ALL_COLUMNS};
+use models::Crate;
+use schema::{crate_renames, crates, versions};
+
+/// A former name of a crate. Former names keep resolving to the crate in
+/// `Crate::by_name`, and can't be used by new crates.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(Crate)]
+pub struct CrateRename {
+    pub id: i32,
+    pub crate_id: i32,
+    pub old_name: String,
+    pub new_name: String,
+    pub created_at: NaiveDateTime,
+    /// Whether the index entries of the crate were copied to the new name yet.
+    pub index_rewritten: bool,
+    /// The last version published before the rename, see `published_name`.
+    pub last_version_id: Option<i32>,
+}
+
+impl CrateRename {
+    /// Renames the crate, recording its current name as a former name. The
+    /// index entries are copied to the new name later on by
+    /// `rewrite_pending_index_entries`.
+    pub fn rename(conn: &PgConnection, krate: &Crate, new_name: &str) -> CargoResult<Crate> {
+        if !Crate::valid_name(new_name) {
+            return Err(human(&format_args!("invalid crate name: `{}`", new_name)));
+        }
+
+        conn.transaction::<_, Box<CargoError>, _>(|| {
+            let existing = Crate::by_name(new_name)
+                .select(crates::id)
+                .first::<i32>(conn)
+                .optional()?;
+            match existing {
+                Some(id) if id == krate.id && krate.moved_to(new_name).is_none() => {
+                    return Err(human(&format_args!(
+                        "crate `{}` is already named `{}`",
+                        krate.name, new_name
+                    )))
+                }
+                Some(id) if id != krate.id => {
+                    return Err(human(&format_args!(
+                        "crate `{}` already exists",
+                        new_name
+                    )))
+                }
+                _ => {}
+            }
+
+            let last_version_id = versions::table
+                .filter(versions::crate_id.eq(krate.id))
+                .select(diesel::dsl::max(versions::id))
+                .first::<Option<i32>>(conn)?;
+            diesel::insert_into(crate_renames::table)
+                .values((
+                    crate_renames::crate_id.eq(krate.id),
+                    crate_renames::old_name.eq(&krate.name),
+                    crate_renames::new_name.eq(new_name),
+                    crate_renames::last_version_id.eq(last_version_id),
+                ))
+                .execute(conn)?;
+            diesel::update(krate)
+                .set((
+                    crates::name.eq(new_name),
+                    crates::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .returning(ALL_COLUMNS)
+                .get_result(conn)
+                .map_err(Into::into)
+        })
+    }
+
+    /// Returns whether `name` is the former name of a crate, other than the
+    /// crate currently named `name` when it was renamed back to it.
+    pub fn is_former_name(conn: &PgConnection, name: &str) -> QueryResult<bool> {
+        use diesel::dsl::{exists, not};
+        use diesel::select;
+
+        let current = crates::table
+            .filter(canon_crate_name(crates::name).eq(canon_crate_name(name)))
+            .select(crates::id);
+        select(exists(
+            crate_renames::table
+                .filter(canon_crate_name(crate_renames::old_name).eq(canon_crate_name(name)))
+                .filter(not(crate_renames::crate_id.eq_any(current))),
+        )).get_result(conn)
+    }
+
+    /// Returns the name the crate had when the version was uploaded, if it was
+    /// renamed since. The files of the version are stored under that name.
+    pub fn published_name(
+        conn: &PgConnection,
+        crate_id: i32,
+        version_id: i32,
+    ) -> QueryResult<Option<String>> {
+        crate_renames::table
+            .filter(crate_renames::crate_id.eq(crate_id))
+            .filter(crate_renames::last_version_id.ge(version_id))
+            .order(crate_renames::id)
+            .select(crate_renames::old_name)
+            .first(conn)
+            .optional()
+    }
+
+    /// Copies the index entries of the renamed crates to their new names, in
+    /// the order they were renamed. Returns the number of renames handled.
+    pub fn rewrite_pending_index_entries(app: &App) -> CargoResult<usize> {
+        let conn = app.diesel_database.get()?;
+        let pending = crate_renames::table
+            .filter(crate_renames::index_rewritten.eq(false))
+            .order(crate_renames::id)
+            .load::<CrateRename>(&*conn)?;
+
+        for rename in &pending {
+            git::rename_crate(app, &rename.old_name, &rename.new_name)?;
+            diesel::update(rename)
+                .set(crate_renames::index_rewritten.eq(true))
+                .execute(&*conn)?;
+        }
+        Ok(pending.len())
+    }
+}
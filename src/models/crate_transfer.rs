@@ -0,0 +1,161 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+
+use email;
+use models::{Crate, CrateOwner, Owner, OwnerKind, User};
+use schema::{crate_owner_invitations, crate_owners, crate_transfers};
+use util::{human, CargoResult};
+use views::EncodableCrateStatus;
+
+/// A contested crate name, transferred to the user claiming it once the grace
+/// window given to its owners is over, as our dispute policy describes.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[primary_key(crate_id)]
+#[belongs_to(Crate)]
+pub struct CrateTransfer {
+    pub crate_id: i32,
+    pub claimant_id: i32,
+    pub reason: String,
+    pub transfer_at: NaiveDateTime,
+    pub created_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateTransfer {
+    pub fn find(conn: &PgConnection, krate: &Crate) -> QueryResult<Option<CrateTransfer>> {
+        crate_transfers::table
+            .find(krate.id)
+            .first(conn)
+            .optional()
+    }
+
+    /// Marks the name of the crate as contested and schedules its transfer to
+    /// the claimant in `days` days, replacing a transfer already scheduled.
+    /// The owners with a verified email address are notified when the name
+    /// wasn't contested yet.
+    pub fn schedule(
+        conn: &PgConnection,
+        krate: &Crate,
+        claimant: &User,
+        reason: &str,
+        days: i64,
+        admin: &User,
+    ) -> CargoResult<CrateTransfer> {
+        if reason.trim().is_empty() {
+            return Err(human("the reason the name is contested is required"));
+        }
+        if days < 0 {
+            return Err(human("the grace window can't be negative"));
+        }
+        let owners = krate.owners(conn)?;
+        let claimant_owns = owners.iter().any(|owner| match *owner {
+            Owner::User(ref user) => user.id == claimant.id,
+            Owner::Team(_) => false,
+        });
+        if claimant_owns {
+            return Err(human(&format_args!(
+                "{} is already an owner of crate `{}`",
+                claimant.gh_login, krate.name
+            )));
+        }
+
+        let values = (
+            crate_transfers::crate_id.eq(krate.id),
+            crate_transfers::claimant_id.eq(claimant.id),
+            crate_transfers::reason.eq(reason),
+            crate_transfers::transfer_at.eq(now + days.days()),
+            crate_transfers::created_by.eq(admin.id),
+        );
+        let created = diesel::insert_into(crate_transfers::table)
+            .values(values)
+            .on_conflict_do_nothing()
+            .get_result::<CrateTransfer>(conn)
+            .optional()?;
+        let transfer = match created {
+            Some(transfer) => transfer,
+            None => {
+                // The owners were notified when the name was first contested
+                return Ok(diesel::update(crate_transfers::table.find(krate.id))
+                    .set(values)
+                    .get_result(conn)?);
+            }
+        };
+
+        for owner in owners {
+            if let Owner::User(user) = owner {
+                if let Some(address) = user.verified_email(conn)? {
+                    email::send_crate_contested_email(
+                        &address,
+                        &krate.name,
+                        reason,
+                        transfer.transfer_at,
+                    )?;
+                }
+            }
+        }
+        Ok(transfer)
+    }
+
+    /// Cancels the transfer, the name is no longer contested.
+    pub fn cancel(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::delete(self).execute(conn)?;
+        Ok(())
+    }
+
+    /// Transfers the crates whose grace window is over, returning how many
+    /// were transferred.
+    pub fn perform_due(conn: &PgConnection) -> QueryResult<usize> {
+        let due = crate_transfers::table
+            .filter(crate_transfers::transfer_at.le(now))
+            .load::<CrateTransfer>(conn)?;
+        for transfer in &due {
+            transfer.perform(conn)?;
+        }
+        Ok(due.len())
+    }
+
+    /// Makes the claimant the only owner of the crate, dropping the pending
+    /// invitations of the former owners.
+    fn perform(&self, conn: &PgConnection) -> QueryResult<()> {
+        conn.transaction(|| {
+            diesel::update(crate_owners::table.filter(crate_owners::crate_id.eq(self.crate_id)))
+                .set(crate_owners::deleted.eq(true))
+                .execute(conn)?;
+            diesel::insert_into(crate_owners::table)
+                .values(&CrateOwner {
+                    crate_id: self.crate_id,
+                    owner_id: self.claimant_id,
+                    created_by: self.created_by.unwrap_or(self.claimant_id),
+                    owner_kind: OwnerKind::User as i32,
+                })
+                .on_conflict(crate_owners::table.primary_key())
+                .do_update()
+                .set(crate_owners::deleted.eq(false))
+                .execute(conn)?;
+            diesel::delete(
+                crate_owner_invitations::table
+                    .filter(crate_owner_invitations::crate_id.eq(self.crate_id)),
+            ).execute(conn)?;
+            diesel::delete(self).execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// The public status of the name of a crate.
+    pub fn encodable_status(transfer: Option<CrateTransfer>) -> EncodableCrateStatus {
+        match transfer {
+            Some(transfer) => EncodableCrateStatus {
+                contested: true,
+                reason: Some(transfer.reason),
+                transfer_at: Some(transfer.transfer_at),
+            },
+            None => EncodableCrateStatus {
+                contested: false,
+                reason: None,
+                transfer_at: None,
+            },
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Jsonb};
+use serde_json;
+
+use app::App;
+use git;
+use util::errors::CargoError;
+use util::{human, CargoResult};
+
+use models::krate::ALL_COLUMNS;
+use models::{Crate, User};
+use schema::{crates, deleted_crates, reserved_crate_names};
+use views::EncodableDeletedCrate;
+
+/// Restores the rows of a snapshot, parents first. Rows referencing a row that
+/// was deleted in the meantime, like the version of a dependent crate, are
+/// left out.
+const RESTORE_STATEMENTS: &[&str] = &[
+    "INSERT INTO crates SELECT * FROM jsonb_populate_record(NULL::crates, $1->'crate')",
+    "INSERT INTO versions
+     SELECT * FROM jsonb_populate_recordset(NULL::versions, $1->'versions')",
+    "INSERT INTO version_authors
+     SELECT * FROM jsonb_populate_recordset(NULL::version_authors, $1->'version_authors')",
+    "INSERT INTO dependencies
+     SELECT * FROM jsonb_populate_recordset(NULL::dependencies, $1->'dependencies') d
+     WHERE EXISTS (SELECT 1 FROM versions WHERE versions.id = d.version_id)
+     ON CONFLICT DO NOTHING",
+    "INSERT INTO crate_owners
+     SELECT * FROM jsonb_populate_recordset(NULL::crate_owners, $1->'crate_owners')",
+    "INSERT INTO crates_keywords
+     SELECT * FROM jsonb_populate_recordset(NULL::crates_keywords, $1->'crates_keywords') k
+     WHERE EXISTS (SELECT 1 FROM keywords WHERE keywords.id = k.keyword_id)",
+    "INSERT INTO crates_categories
+     SELECT * FROM jsonb_populate_recordset(NULL::crates_categories, $1->'crates_categories') c
+     WHERE EXISTS (SELECT 1 FROM categories WHERE categories.id = c.category_id)",
+];
+
+/// A crate deleted by an administrator, e.g. because it was malware. The rows
+/// removed with it are kept as evidence, and so that it can be restored.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+pub struct DeletedCrate {
+    pub id: i32,
+    pub crate_id: i32,
+    pub name: String,
+    pub reason: Option<String>,
+    pub deleted_by: Option<i32>,
+    pub snapshot: serde_json::Value,
+    pub index_entries: Option<String>,
+    pub deleted_at: NaiveDateTime,
+}
+
+#[derive(QueryableByName)]
+struct Snapshot {
+    #[sql_type = "Jsonb"]
+    snapshot: serde_json::Value,
+}
+
+impl DeletedCrate {
+    /// Deletes the crate, archiving it first. Its name is reserved so that
+    /// nobody can publish a crate under it.
+    pub fn archive(
+        conn: &PgConnection,
+        krate: &Crate,
+        deleted_by: Option<&User>,
+        reason: Option<&str>,
+    ) -> QueryResult<DeletedCrate> {
+        conn.transaction(|| {
+            let Snapshot { snapshot } =
+                diesel::sql_query(include_str!("deleted_crate_snapshot.sql"))
+                    .bind::<Integer, _>(krate.id)
+                    .get_result(conn)?;
+            let deleted = diesel::insert_into(deleted_crates::table)
+                .values((
+                    deleted_crates::crate_id.eq(krate.id),
+                    deleted_crates::name.eq(&krate.name),
+                    deleted_crates::reason.eq(reason),
+                    deleted_crates::deleted_by.eq(deleted_by.map(|u| u.id)),
+                    deleted_crates::snapshot.eq(snapshot),
+                ))
+                .get_result(conn)?;
+            diesel::insert_into(reserved_crate_names::table)
+                .values(reserved_crate_names::name.eq(&krate.name))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            diesel::delete(crates::table.find(krate.id)).execute(conn)?;
+            Ok(deleted)
+        })
+    }
+
+    /// Deletes the crate like `archive`, removing it from the index as well.
+    ///
+    /// The index is changed before the transaction commits, so that the crate
+    /// isn't deleted without its index entries being kept when git fails.
+    pub fn delete(
+        app: &App,
+        conn: &PgConnection,
+        krate: &Crate,
+        admin: &User,
+        reason: Option<&str>,
+    ) -> CargoResult<DeletedCrate> {
+        conn.transaction::<_, Box<CargoError>, _>(|| {
+            let deleted = DeletedCrate::archive(conn, krate, Some(admin), reason)?;
+            let index_entries = git::delete_crate(app, &krate.name)?;
+            diesel::update(&deleted)
+                .set(deleted_crates::index_entries.eq(index_entries))
+                .get_result(conn)
+                .map_err(Into::into)
+        })
+    }
+
+    /// Restores the archived crate along with its index entries, releasing
+    /// its reserved name.
+    pub fn restore(&self, app: &App, conn: &PgConnection) -> CargoResult<Crate> {
+        let krate = conn.transaction::<_, Box<CargoError>, _>(|| {
+            let existing = Crate::by_name(&self.name)
+                .select(crates::id)
+                .first::<i32>(conn)
+                .optional()?;
+            if existing.is_some() {
+                return Err(human(&format_args!(
+                    "crate `{}` already exists",
+                    self.name
+                )));
+            }
+
+            for statement in RESTORE_STATEMENTS {
+                diesel::sql_query(*statement)
+                    .bind::<Jsonb, _>(&self.snapshot)
+                    .execute(conn)?;
+            }
+            diesel::delete(reserved_crate_names::table.find(&self.name)).execute(conn)?;
+            diesel::delete(self).execute(conn)?;
+            crates::table
+                .find(self.crate_id)
+                .select(ALL_COLUMNS)
+                .first(conn)
+                .map_err(Into::into)
+        })?;
+
+        if let Some(ref entries) = self.index_entries {
+            git::restore_crate(app, &krate.name, entries)?;
+        }
+        Ok(krate)
+    }
+
+    pub fn encodable(self, deleted_by: Option<String>) -> EncodableDeletedCrate {
+        let versions = self.snapshot["versions"]
+            .as_array()
+            .map(|versions| {
+                versions
+                    .iter()
+                    .filter_map(|v| v["num"].as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+        EncodableDeletedCrate {
+            id: self.id,
+            name: self.name,
+            reason: self.reason,
+            deleted_by,
+            deleted_at: self.deleted_at,
+            versions,
+            snapshot: self.snapshot,
+        }
+    }
+}
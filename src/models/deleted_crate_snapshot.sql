@@ -0,0 +1,32 @@
+-- Captures the rows removed along with a crate, including the dependencies of
+-- other crates on it
+SELECT jsonb_build_object(
+    'crate', (SELECT to_jsonb(crates) - 'textsearchable_index_col' FROM crates WHERE id = $1),
+    'versions', (
+        SELECT COALESCE(jsonb_agg(versions ORDER BY id), '[]')
+        FROM versions WHERE crate_id = $1
+    ),
+    'version_authors', (
+        SELECT COALESCE(jsonb_agg(version_authors ORDER BY id), '[]')
+        FROM version_authors
+        WHERE version_id IN (SELECT id FROM versions WHERE crate_id = $1)
+    ),
+    'dependencies', (
+        SELECT COALESCE(jsonb_agg(dependencies ORDER BY id), '[]')
+        FROM dependencies
+        WHERE crate_id = $1
+        OR version_id IN (SELECT id FROM versions WHERE crate_id = $1)
+    ),
+    'crate_owners', (
+        SELECT COALESCE(jsonb_agg(crate_owners), '[]')
+        FROM crate_owners WHERE crate_id = $1
+    ),
+    'crates_keywords', (
+        SELECT COALESCE(jsonb_agg(crates_keywords), '[]')
+        FROM crates_keywords WHERE crate_id = $1
+    ),
+    'crates_categories', (
+        SELECT COALESCE(jsonb_agg(crates_categories), '[]')
+        FROM crates_categories WHERE crate_id = $1
+    )
+) AS snapshot
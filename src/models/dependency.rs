@@ -4,6 +4,7 @@ use diesel::prelude::*;
 use diesel::row::NamedRow;
 use semver;
 
+use dependency_policy::DependencyPolicy;
 use git;
 use util::{human, CargoResult};
 
@@ -11,7 +12,7 @@ use models::{Crate, Version};
 use schema::*;
 use views::EncodableDependency;
 
-#[derive(Identifiable, Associations, Debug)]
+#[derive(Identifiable, Associations, Debug, Clone)]
 #[belongs_to(Version)]
 #[belongs_to(Crate)]
 #[table_name = "dependencies"]
@@ -67,66 +68,154 @@ impl Dependency {
 }
 
 impl ReverseDependency {
+    pub fn req(&self) -> &semver::VersionReq {
+        &self.dependency.req
+    }
+
     pub fn encodable(self, crate_name: &str) -> EncodableDependency {
         self.dependency
             .encodable(crate_name, Some(self.crate_downloads))
     }
 }
 
+/// A dependency on a crate of another registry, which isn't in the `crates`
+/// table.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Version)]
+#[table_name = "registry_dependencies"]
+pub struct RegistryDependency {
+    pub id: i32,
+    pub version_id: i32,
+    pub registry: String,
+    pub name: String,
+    pub req: String,
+    pub optional: bool,
+    pub default_features: bool,
+    pub features: Vec<String>,
+    pub target: Option<String>,
+    pub kind: DependencyKind,
+}
+
+impl RegistryDependency {
+    /// Returns the dependency as it's written in the git index.
+    pub fn git_dependency(self) -> git::Dependency {
+        git::Dependency {
+            name: self.name,
+            req: self.req,
+            features: self.features,
+            optional: self.optional,
+            default_features: self.default_features,
+            target: self.target,
+            kind: Some(self.kind),
+            registry: Some(self.registry),
+        }
+    }
+}
+
+/// Records the dependencies of a new version, returning them for the index
+/// along with warnings about the requirements no version that isn't yanked
+/// matches.
+///
+/// Dependencies on crates of other registries are only checked against the
+/// policy, since their versions aren't known. They are listed after the
+/// dependencies on crates of this registry, like `Crate::index_entries` does.
 pub fn add_dependencies(
     conn: &PgConnection,
     deps: &[::views::EncodableCrateDependency],
     target_version_id: i32,
-) -> CargoResult<Vec<git::Dependency>> {
+    policy: &DependencyPolicy,
+) -> CargoResult<(Vec<git::Dependency>, Vec<String>)> {
     use self::dependencies::dsl::*;
     use diesel::insert_into;
 
-    let git_and_new_dependencies = deps.iter()
-        .map(|dep| {
-            let krate = Crate::by_name(&dep.name)
-                .first::<Crate>(&*conn)
-                .map_err(|_| human(&format_args!("no known crate named `{}`", &*dep.name)))?;
-            if dep.version_req == semver::VersionReq::parse("*").unwrap() {
-                return Err(human(
-                    "wildcard (`*`) dependency constraints are not allowed \
-                     on crates.io. See https://doc.rust-lang.org/cargo/faq.html#can-\
-                     libraries-use--as-a-version-for-their-dependencies for more \
-                     information",
-                ));
-            }
-
-            Ok((
-                git::Dependency {
-                    name: dep.name.to_string(),
-                    req: dep.version_req.to_string(),
-                    features: dep.features.iter().map(|s| s.to_string()).collect(),
-                    optional: dep.optional,
-                    default_features: dep.default_features,
-                    target: dep.target.clone(),
-                    kind: dep.kind.or(Some(DependencyKind::Normal)),
-                },
-                (
-                    version_id.eq(target_version_id),
-                    crate_id.eq(krate.id),
-                    req.eq(dep.version_req.to_string()),
-                    dep.kind.map(|k| kind.eq(k as i32)),
-                    optional.eq(dep.optional),
-                    default_features.eq(dep.default_features),
-                    features.eq(&dep.features),
-                    target.eq(dep.target.as_ref().map(|s| &**s)),
-                ),
-            ))
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+    let mut warnings = Vec::new();
+    let mut git_deps = Vec::new();
+    let mut registry_git_deps = Vec::new();
+    let mut new_dependencies = Vec::new();
+    let mut new_registry_dependencies = Vec::new();
+    for dep in deps {
+        if dep.version_req == semver::VersionReq::parse("*").unwrap() {
+            return Err(human(
+                "wildcard (`*`) dependency constraints are not allowed \
+                 on crates.io. See https://doc.rust-lang.org/cargo/faq.html#can-\
+                 libraries-use--as-a-version-for-their-dependencies for more \
+                 information",
+            ));
+        }
+
+        if let Some(ref registry) = dep.registry {
+            policy.check_registry(&dep.name, &dep.version_req, registry)?;
+            registry_git_deps.push(git::Dependency {
+                name: dep.name.to_string(),
+                req: dep.version_req.to_string(),
+                features: dep.features.iter().map(|s| s.to_string()).collect(),
+                optional: dep.optional,
+                default_features: dep.default_features,
+                target: dep.target.clone(),
+                kind: dep.kind.or(Some(DependencyKind::Normal)),
+                registry: Some(registry.clone()),
+            });
+            new_registry_dependencies.push((
+                registry_dependencies::version_id.eq(target_version_id),
+                registry_dependencies::registry.eq(registry.clone()),
+                registry_dependencies::name.eq(dep.name.to_string()),
+                registry_dependencies::req.eq(dep.version_req.to_string()),
+                dep.kind.map(|k| registry_dependencies::kind.eq(k as i32)),
+                registry_dependencies::optional.eq(dep.optional),
+                registry_dependencies::default_features.eq(dep.default_features),
+                registry_dependencies::features.eq(&dep.features),
+                registry_dependencies::target.eq(dep.target.as_ref().map(|s| &**s)),
+            ));
+            continue;
+        }
 
-    let (git_deps, new_dependencies): (Vec<_>, Vec<_>) =
-        git_and_new_dependencies.into_iter().unzip();
+        let krate = Crate::by_name(&dep.name)
+            .first::<Crate>(&*conn)
+            .map_err(|_| human(&format_args!("no known crate named `{}`", &*dep.name)))?;
+        policy.check(&krate.name, &dep.version_req)?;
+        let versions = Version::belonging_to(&krate)
+            .select((versions::num, versions::yanked))
+            .load::<(String, bool)>(conn)?
+            .into_iter()
+            .filter_map(|(num, is_yanked)| {
+                semver::Version::parse(&num).ok().map(|num| (num, is_yanked))
+            })
+            .collect::<Vec<_>>();
+        if let Some(warning) = policy.check_versions(&krate.name, &dep.version_req, &versions)? {
+            warnings.push(warning);
+        }
+
+        git_deps.push(git::Dependency {
+            name: dep.name.to_string(),
+            req: dep.version_req.to_string(),
+            features: dep.features.iter().map(|s| s.to_string()).collect(),
+            optional: dep.optional,
+            default_features: dep.default_features,
+            target: dep.target.clone(),
+            kind: dep.kind.or(Some(DependencyKind::Normal)),
+            registry: None,
+        });
+        new_dependencies.push((
+            version_id.eq(target_version_id),
+            crate_id.eq(krate.id),
+            req.eq(dep.version_req.to_string()),
+            dep.kind.map(|k| kind.eq(k as i32)),
+            optional.eq(dep.optional),
+            default_features.eq(dep.default_features),
+            features.eq(&dep.features),
+            target.eq(dep.target.as_ref().map(|s| &**s)),
+        ));
+    }
 
     insert_into(dependencies)
         .values(&new_dependencies)
         .execute(conn)?;
+    insert_into(registry_dependencies::table)
+        .values(&new_registry_dependencies)
+        .execute(conn)?;
 
-    Ok(git_deps)
+    git_deps.extend(registry_git_deps);
+    Ok((git_deps, warnings))
 }
 
 use diesel::deserialize::{self, FromSql};
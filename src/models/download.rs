@@ -15,20 +15,59 @@ pub struct VersionDownload {
     pub counted: i32,
     pub date: NaiveDate,
     pub processed: bool,
+    /// The downloads of the day made while the version was yanked, which are
+    /// also part of `downloads`.
+    pub yanked_downloads: i32,
+    /// The downloads of the day made from distinct IP addresses, when
+    /// `Config::unique_downloads` is set.
+    pub unique_downloads: i32,
 }
 
 impl VersionDownload {
-    pub fn create_or_increment(version: i32, conn: &PgConnection) -> QueryResult<()> {
+    /// Counts a download of the version for today, also counting it as a
+    /// download of a yanked version if `yanked` is set, and as a unique
+    /// download if `unique` is set.
+    pub fn create_or_increment(
+        version: i32,
+        yanked: bool,
+        unique: bool,
+        conn: &PgConnection,
+    ) -> QueryResult<()> {
         use self::version_downloads::dsl::*;
 
+        let yanked = if yanked { 1 } else { 0 };
+        let unique = if unique { 1 } else { 0 };
         // We only update the counter for *today* (the default date),
         // nothing else. We have lots of other counters, but they're
         // all updated later on via the update-downloads script.
         diesel::insert_into(version_downloads)
-            .values(version_id.eq(version))
+            .values((
+                version_id.eq(version),
+                yanked_downloads.eq(yanked),
+                unique_downloads.eq(unique),
+            ))
             .on_conflict((version_id, date))
             .do_update()
-            .set(downloads.eq(downloads + 1))
+            .set((
+                downloads.eq(downloads + 1),
+                yanked_downloads.eq(yanked_downloads + yanked),
+                unique_downloads.eq(unique_downloads + unique),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Adds downloads of the version counted elsewhere, like by a CDN, on the
+    /// given day. They are collected into the totals again even if the day
+    /// was already processed.
+    pub fn add(version: i32, day: NaiveDate, amount: i32, conn: &PgConnection) -> QueryResult<()> {
+        use self::version_downloads::dsl::*;
+
+        diesel::insert_into(version_downloads)
+            .values((version_id.eq(version), date.eq(day), downloads.eq(amount)))
+            .on_conflict((version_id, date))
+            .do_update()
+            .set((downloads.eq(downloads + amount), processed.eq(false)))
             .execute(conn)?;
         Ok(())
     }
@@ -38,6 +77,8 @@ impl VersionDownload {
             id: self.id,
             version: self.version_id,
             downloads: self.downloads,
+            yanked_downloads: self.yanked_downloads,
+            unique_downloads: self.unique_downloads,
             date: self.date.to_string(),
         }
     }
@@ -0,0 +1,122 @@
+use diesel;
+use diesel::prelude::*;
+
+use models::Crate;
+use schema::version_download_breakdowns;
+
+/// The fewest downloads a bucket of the breakdown of a crate is returned with.
+pub const MIN_BUCKET_DOWNLOADS: i64 = 10;
+
+/// The downloads of a version over the last 90 days from a cargo version and
+/// a country, counted when `Config::download_breakdown` is set.
+///
+/// Only the `major.minor` version of cargo and the country are kept, never
+/// the full user agent or the IP address of the download.
+///
+/// The breakdown is public, so the buckets with less than
+/// `MIN_BUCKET_DOWNLOADS` downloads are merged into an `other` bucket, whose
+/// cargo version and country are both `other`.
+#[derive(Debug, Serialize, Deserialize, QueryableByName)]
+pub struct DownloadBreakdown {
+    #[sql_type = "::diesel::sql_types::Text"]
+    pub version: String,
+    /// The `major.minor` version of cargo, or `other` for other clients.
+    #[sql_type = "::diesel::sql_types::Text"]
+    pub cargo_version: String,
+    /// The ISO 3166 code of the country, or `unknown`.
+    #[sql_type = "::diesel::sql_types::Text"]
+    pub country: String,
+    #[sql_type = "::diesel::sql_types::BigInt"]
+    pub downloads: i64,
+}
+
+impl DownloadBreakdown {
+    /// Counts a download of the version for today, from the client with the
+    /// given user agent and from the country given by the CDN.
+    pub fn increment(
+        conn: &PgConnection,
+        version: i32,
+        user_agent: Option<&str>,
+        country_code: Option<&str>,
+    ) -> QueryResult<()> {
+        use self::version_download_breakdowns::dsl::*;
+
+        diesel::insert_into(version_download_breakdowns)
+            .values((
+                version_id.eq(version),
+                cargo_version.eq(cargo_version_of(user_agent)),
+                country.eq(country_of(country_code)),
+            ))
+            .on_conflict((version_id, date, cargo_version, country))
+            .do_update()
+            .set(downloads.eq(downloads + 1))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the downloads of the versions of the crate over the last 90
+    /// days, the most recent versions first, the small buckets being merged.
+    pub fn for_crate(conn: &PgConnection, krate: &Crate) -> QueryResult<Vec<DownloadBreakdown>> {
+        use diesel::sql_query;
+        use diesel::sql_types::{BigInt, Integer};
+
+        sql_query(include_str!("download_breakdown_for_crate.sql"))
+            .bind::<Integer, _>(krate.id)
+            .bind::<BigInt, _>(MIN_BUCKET_DOWNLOADS)
+            .load(conn)
+    }
+}
+
+/// Returns the `major.minor` version of cargo from its user agent, like `1.26`
+/// for `cargo 1.26.0 (0e7c5a931 2018-04-06)`, or `other`.
+fn cargo_version_of(user_agent: Option<&str>) -> String {
+    let mut parts = user_agent.unwrap_or("").split(' ');
+    if parts.next() != Some("cargo") {
+        return "other".into();
+    }
+    let version = parts
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .take(2)
+        .collect::<Vec<_>>();
+    let is_number = |part: &&str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if version.len() == 2 && version.iter().all(is_number) {
+        version.join(".")
+    } else {
+        "other".into()
+    }
+}
+
+/// Returns the country code sent by the CDN if it's a valid two letter code,
+/// or `unknown`.
+fn country_of(country_code: Option<&str>) -> String {
+    match country_code {
+        Some(code) if code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()) => {
+            code.to_uppercase()
+        }
+        _ => "unknown".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cargo_version_of, country_of};
+
+    #[test]
+    fn cargo_versions_are_bucketed() {
+        assert_eq!(cargo_version_of(Some("cargo 1.26.0 (0e7c5a931 2018-04-06)")), "1.26");
+        assert_eq!(cargo_version_of(Some("cargo 1.28.0-nightly (a1b2c3d 2018-06-01)")), "1.28");
+        assert_eq!(cargo_version_of(Some("cargo 1")), "other");
+        assert_eq!(cargo_version_of(Some("curl/7.58.0")), "other");
+        assert_eq!(cargo_version_of(None), "other");
+    }
+
+    #[test]
+    fn countries_are_validated() {
+        assert_eq!(country_of(Some("de")), "DE");
+        assert_eq!(country_of(Some("T1")), "unknown");
+        assert_eq!(country_of(Some("Germany")), "unknown");
+        assert_eq!(country_of(None), "unknown");
+    }
+}
@@ -0,0 +1,27 @@
+-- Sums the downloads of the last 90 days of the versions of crate $1 per
+-- cargo version and country. The buckets with less than $2 downloads, which
+-- could single out a user, are merged per version into an `other` bucket,
+-- which is left out if it has less than $2 downloads too.
+WITH buckets AS (
+    SELECT versions.id AS version_id,
+        versions.num AS version,
+        version_download_breakdowns.cargo_version,
+        version_download_breakdowns.country,
+        SUM(version_download_breakdowns.downloads) AS downloads
+    FROM version_download_breakdowns
+    INNER JOIN versions
+        ON versions.id = version_download_breakdowns.version_id
+    WHERE versions.crate_id = $1
+        AND version_download_breakdowns.date > CURRENT_DATE - 90
+    GROUP BY versions.id, versions.num,
+        version_download_breakdowns.cargo_version,
+        version_download_breakdowns.country
+)
+SELECT version,
+    CASE WHEN downloads >= $2 THEN cargo_version ELSE 'other' END AS cargo_version,
+    CASE WHEN downloads >= $2 THEN country ELSE 'other' END AS country,
+    SUM(downloads)::BIGINT AS downloads
+FROM buckets
+GROUP BY version_id, version, 2, 3
+HAVING SUM(downloads) >= $2
+ORDER BY version_id DESC, downloads DESC, cargo_version, country
@@ -0,0 +1,86 @@
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+
+use models::{Crate, VersionDownload};
+use schema::{crates, download_ingestions, versions};
+use util::{human, CargoResult};
+
+/// The downloads of a version on a day, as counted from the logs of the CDN.
+#[derive(Clone, Debug, Deserialize)]
+pub struct IngestedDownloads {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: String,
+    pub date: NaiveDate,
+    pub count: i32,
+}
+
+/// A batch of downloads served by the CDN that was added to
+/// `version_downloads`, recorded so that sending the batch again doesn't
+/// count its downloads twice.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[primary_key(batch_id)]
+pub struct DownloadIngestion {
+    pub batch_id: String,
+    /// How many records the batch had.
+    pub records: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl DownloadIngestion {
+    /// Adds the downloads of the batch to those of the versions, unless the
+    /// batch was already ingested, in which case `None` is returned. Returns
+    /// the records of versions that don't exist otherwise, as `name#version`,
+    /// which are skipped.
+    pub fn ingest(
+        conn: &PgConnection,
+        batch_id: &str,
+        records: &[IngestedDownloads],
+    ) -> CargoResult<Option<Vec<String>>> {
+        let today = Utc::today().naive_utc();
+        for record in records {
+            if record.count < 0 {
+                return Err(human(&format_args!(
+                    "the download count of `{}#{}` can't be negative",
+                    record.krate, record.version
+                )));
+            }
+            if record.date > today {
+                return Err(human(&format_args!(
+                    "the downloads of `{}#{}` can't be in the future",
+                    record.krate, record.version
+                )));
+            }
+        }
+
+        conn.transaction(|| {
+            let inserted = diesel::insert_into(download_ingestions::table)
+                .values((
+                    download_ingestions::batch_id.eq(batch_id),
+                    download_ingestions::records.eq(records.len() as i32),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            if inserted == 0 {
+                return Ok(None);
+            }
+
+            let mut unknown = Vec::new();
+            for record in records {
+                let crate_ids = Crate::by_name(&record.krate).select(crates::id);
+                let version_id = versions::table
+                    .filter(versions::crate_id.eq_any(crate_ids))
+                    .filter(versions::num.eq(&record.version))
+                    .select(versions::id)
+                    .first::<i32>(conn)
+                    .optional()?;
+                match version_id {
+                    Some(id) => VersionDownload::add(id, record.date, record.count, conn)?,
+                    None => unknown.push(format!("{}#{}", record.krate, record.version)),
+                }
+            }
+            Ok(Some(unknown))
+        })
+    }
+}
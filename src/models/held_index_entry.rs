@@ -0,0 +1,104 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use serde_json;
+
+use app::App;
+use git;
+use scanning::ScanResult;
+use util::{internal, CargoResult, ChainError};
+
+use models::{Crate, CrateWebhook, User, Version};
+use schema::{crates, held_index_entries, versions};
+
+/// The git index entry of a version published while its crate was held for
+/// review. It is written to the index once an administrator approves the crate.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(Version)]
+#[primary_key(version_id)]
+pub struct HeldIndexEntry {
+    pub version_id: i32,
+    pub entry: serde_json::Value,
+    pub reason: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl HeldIndexEntry {
+    /// Returns why a publish should be held for review, if it should be.
+    ///
+    /// Holds only apply when `Config::publish_hold_days` is set. Publishes are
+    /// then held if the crate is already held, if the account of the publisher
+    /// is younger than that many days, or if a scanner flagged the crate file
+    /// without the scan policy blocking it.
+    pub fn hold_reason(
+        app: &App,
+        krate: &Crate,
+        user: &User,
+        scan_results: &[ScanResult],
+    ) -> Option<String> {
+        let days = app.config.publish_hold_days?;
+        if krate.publish_hold {
+            Some("the crate is held for review".into())
+        } else if Utc::now().naive_utc() - user.created_at < Duration::days(days) {
+            Some(format!("the account is younger than {} days", days))
+        } else if let Some(flagged) = scan_results.iter().find(|r| r.blocked) {
+            Some(format!("the crate file was flagged by {}", flagged.scanner))
+        } else {
+            None
+        }
+    }
+
+    /// Holds the version back from the index, and the whole crate with it.
+    pub fn create(
+        conn: &PgConnection,
+        krate: &Crate,
+        version: &Version,
+        entry: &git::Crate,
+        reason: &str,
+    ) -> CargoResult<()> {
+        conn.transaction(|| {
+            diesel::insert_into(held_index_entries::table)
+                .values((
+                    held_index_entries::version_id.eq(version.id),
+                    held_index_entries::entry.eq(serde_json::to_value(entry)?),
+                    held_index_entries::reason.eq(reason),
+                ))
+                .execute(conn)?;
+            diesel::update(krate)
+                .set(crates::publish_hold.eq(true))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Writes the held entries of the crate to the index, in the order they
+    /// were published, and lifts the hold of the crate. The webhooks of the
+    /// crate are notified of the publishes only now. Returns the number of
+    /// entries that were written.
+    pub fn release(conn: &PgConnection, app: &App, krate: &Crate) -> CargoResult<usize> {
+        let held = held_index_entries::table
+            .inner_join(versions::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .select(held_index_entries::all_columns)
+            .order(held_index_entries::created_at)
+            .load::<HeldIndexEntry>(conn)?;
+
+        // Entries are removed one at a time so that a failure to write to the
+        // index doesn't release entries that weren't written yet.
+        for entry in &held {
+            let git_crate: git::Crate = serde_json::from_value(entry.entry.clone())?;
+            git::add_crate(app, &git_crate).chain_error(|| {
+                internal(&format_args!(
+                    "could not add crate `{}` to the git repo",
+                    git_crate.name
+                ))
+            })?;
+            diesel::delete(entry).execute(conn)?;
+            CrateWebhook::enqueue(conn, krate, "publish", &git_crate.vers)?;
+        }
+        diesel::update(krate)
+            .set(crates::publish_hold.eq(false))
+            .execute(conn)?;
+        Ok(held.len())
+    }
+}
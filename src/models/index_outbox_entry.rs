@@ -0,0 +1,110 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use serde_json;
+
+use app::App;
+use git;
+use util::{internal, CargoResult, ChainError};
+
+use models::Version;
+use schema::index_outbox;
+
+/// The git index entry of a published version, recorded in the transaction of
+/// the publish. It is removed once the entry is written to the index, so an
+/// entry that wasn't written because the server stopped is written by
+/// `git::spawn_outbox_task`.
+#[derive(Clone, Debug, Queryable, QueryableByName, Identifiable, Associations)]
+#[belongs_to(Version)]
+#[table_name = "index_outbox"]
+#[primary_key(version_id)]
+pub struct IndexOutboxEntry {
+    pub version_id: i32,
+    pub entry: serde_json::Value,
+    pub created_at: NaiveDateTime,
+    /// How many times writing the entry failed.
+    pub attempts: i32,
+    pub last_error: Option<String>,
+}
+
+impl IndexOutboxEntry {
+    /// Records that the entry of the version has to be written to the index.
+    pub fn create(conn: &PgConnection, version: &Version, entry: &git::Crate) -> CargoResult<()> {
+        diesel::insert_into(index_outbox::table)
+            .values((
+                index_outbox::version_id.eq(version.id),
+                index_outbox::entry.eq(serde_json::to_value(entry)?),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Writes the entries of the outbox to the index, in the order they were
+    /// recorded. Returns the number of entries that were written.
+    ///
+    /// Each entry is claimed with a row lock, the entries claimed by another
+    /// server being skipped. An entry that can't be written is skipped too,
+    /// recording the error, so that it doesn't hold up the entries recorded
+    /// after it. `git::add_crate` doesn't write the entries whose version is
+    /// already in the index, in case the server stopped after writing an
+    /// entry but before removing it.
+    pub fn write_pending(conn: &PgConnection, app: &App) -> CargoResult<usize> {
+        let pending = index_outbox::table
+            .select(index_outbox::version_id)
+            .order(index_outbox::created_at)
+            .load::<i32>(conn)?;
+
+        let mut written = 0;
+        for version_id in pending {
+            if IndexOutboxEntry::write_entry(conn, app, version_id)? {
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Writes the entry of a version to the index, e.g. right after it is
+    /// published. Returns whether it was written, the entries claimed by
+    /// another server and those that can't be written being left to
+    /// `write_pending`.
+    pub fn write_entry(conn: &PgConnection, app: &App, version_id: i32) -> CargoResult<bool> {
+        conn.transaction(|| {
+            let entry = diesel::sql_query(
+                "SELECT * FROM index_outbox WHERE version_id = $1 FOR UPDATE SKIP LOCKED",
+            ).bind::<Integer, _>(version_id)
+                .load::<IndexOutboxEntry>(conn)?
+                .pop();
+            let entry = match entry {
+                Some(entry) => entry,
+                None => return Ok(false),
+            };
+            match entry.write(app) {
+                Ok(()) => {
+                    diesel::delete(&entry).execute(conn)?;
+                    Ok(true)
+                }
+                Err(e) => {
+                    error!("failed to write index outbox entry {}: {}", version_id, e);
+                    diesel::update(&entry)
+                        .set((
+                            index_outbox::attempts.eq(index_outbox::attempts + 1),
+                            index_outbox::last_error.eq(e.to_string()),
+                        ))
+                        .execute(conn)?;
+                    Ok(false)
+                }
+            }
+        })
+    }
+
+    fn write(&self, app: &App) -> CargoResult<()> {
+        let git_crate: git::Crate = serde_json::from_value(self.entry.clone())?;
+        git::add_crate(app, &git_crate).chain_error(|| {
+            internal(&format_args!(
+                "could not add crate `{}` to the git repo",
+                git_crate.name
+            ))
+        })
+    }
+}
@@ -1,10 +1,19 @@
+use std::collections::HashMap;
+
 use chrono::NaiveDateTime;
 use diesel;
 use diesel::prelude::*;
 
 use models::Crate;
 use schema::*;
-use views::EncodableKeyword;
+use util::{human, CargoResult};
+use views::{EncodableKeyword, EncodableKeywordAlias};
+
+/// How many days back the growth of trending keywords is computed from.
+const TRENDING_DAYS: i32 = 30;
+
+/// Keywords of fewer crates aren't trending, however much they grew.
+const TRENDING_MIN_CRATES: i32 = 5;
 
 #[derive(Clone, Identifiable, Queryable, Debug)]
 pub struct Keyword {
@@ -24,13 +33,155 @@ pub struct CrateKeyword {
     keyword_id: i32,
 }
 
+/// A keyword standing for another one, e.g. `wasm` for `webassembly`, so
+/// that crates about the same topic are found under the same keyword. Crates
+/// published with the alias get the keyword instead.
+#[derive(Clone, Identifiable, Queryable, Associations, Debug)]
+#[belongs_to(Keyword)]
+#[primary_key(alias)]
+#[table_name = "keyword_aliases"]
+pub struct KeywordAlias {
+    pub alias: String,
+    pub keyword_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl KeywordAlias {
+    /// Returns every alias along with the keyword it stands for.
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<(KeywordAlias, String)>> {
+        keyword_aliases::table
+            .inner_join(keywords::table)
+            .select((keyword_aliases::all_columns, keywords::keyword))
+            .order(keyword_aliases::alias)
+            .load(conn)
+    }
+
+    /// Makes `alias` stand for `keyword`. The crates that already have the
+    /// alias as a keyword get `keyword` instead, and the alias keyword is
+    /// removed.
+    pub fn create(conn: &PgConnection, alias: &str, keyword: &str) -> CargoResult<KeywordAlias> {
+        let alias = alias.to_lowercase();
+        let keyword = keyword.to_lowercase();
+        if !Keyword::valid_name(&alias) || !Keyword::valid_name(&keyword) {
+            return Err(human("invalid keyword"));
+        }
+        if alias == keyword {
+            return Err(human("a keyword can't be an alias of itself"));
+        }
+
+        conn.transaction(|| {
+            if let Some(other) = KeywordAlias::keyword_of(conn, &keyword)? {
+                return Err(human(&format_args!(
+                    "`{}` is an alias of `{}` already",
+                    keyword, other
+                )));
+            }
+            let target = Keyword::find_or_create_all(conn, &[&keyword])?.remove(0);
+
+            let old = keywords::table
+                .filter(keywords::keyword.eq(&alias))
+                .first::<Keyword>(conn)
+                .optional()?;
+            if let Some(old) = old {
+                let has_aliases = diesel::select(diesel::dsl::exists(
+                    KeywordAlias::belonging_to(&old),
+                )).get_result::<bool>(conn)?;
+                if has_aliases {
+                    return Err(human(&format_args!(
+                        "`{}` has aliases of its own",
+                        alias
+                    )));
+                }
+
+                let moved = CrateKeyword::belonging_to(&old)
+                    .select(crates_keywords::crate_id)
+                    .load::<i32>(conn)?
+                    .into_iter()
+                    .map(|crate_id| CrateKeyword {
+                        crate_id,
+                        keyword_id: target.id,
+                    })
+                    .collect::<Vec<_>>();
+                diesel::insert_into(crates_keywords::table)
+                    .values(&moved)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+                diesel::delete(CrateKeyword::belonging_to(&old)).execute(conn)?;
+                diesel::delete(&old).execute(conn)?;
+            }
+
+            Ok(diesel::insert_into(keyword_aliases::table)
+                .values((
+                    keyword_aliases::alias.eq(&alias),
+                    keyword_aliases::keyword_id.eq(target.id),
+                ))
+                .on_conflict(keyword_aliases::alias)
+                .do_update()
+                .set(keyword_aliases::keyword_id.eq(target.id))
+                .get_result(conn)?)
+        })
+    }
+
+    /// Removes an alias, returning whether it existed. The crates published
+    /// with it keep the keyword it stood for.
+    pub fn delete(conn: &PgConnection, alias: &str) -> QueryResult<bool> {
+        let deleted = diesel::delete(keyword_aliases::table.find(alias.to_lowercase()))
+            .execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// Returns the keyword `name` is an alias of, if it is one.
+    pub fn keyword_of(conn: &PgConnection, name: &str) -> QueryResult<Option<String>> {
+        keyword_aliases::table
+            .inner_join(keywords::table)
+            .filter(keyword_aliases::alias.eq(name.to_lowercase()))
+            .select(keywords::keyword)
+            .first(conn)
+            .optional()
+    }
+
+    pub fn encodable(self, keyword: String) -> EncodableKeywordAlias {
+        EncodableKeywordAlias {
+            alias: self.alias,
+            keyword,
+            created_at: self.created_at,
+        }
+    }
+}
+
 impl Keyword {
+    /// Finds a keyword by name, or the keyword `name` is an alias of.
     pub fn find_by_keyword(conn: &PgConnection, name: &str) -> QueryResult<Keyword> {
+        let name = KeywordAlias::keyword_of(conn, name)?.unwrap_or_else(|| name.to_string());
         keywords::table
             .filter(keywords::keyword.eq(::lower(name)))
             .first(&*conn)
     }
 
+    /// Returns the keywords `names` stand for, lowercased, with the aliases
+    /// replaced by their keyword and without duplicates.
+    pub fn canonical_names(conn: &PgConnection, names: &[&str]) -> QueryResult<Vec<String>> {
+        use diesel::dsl::any;
+
+        let lowercase_names: Vec<_> = names.iter().map(|s| s.to_lowercase()).collect();
+        let aliases = keyword_aliases::table
+            .inner_join(keywords::table)
+            .filter(keyword_aliases::alias.eq(any(&lowercase_names)))
+            .select((keyword_aliases::alias, keywords::keyword))
+            .load::<(String, String)>(conn)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let mut canonical = Vec::new();
+        for name in lowercase_names {
+            let name = aliases.get(&name).cloned().unwrap_or(name);
+            if !canonical.contains(&name) {
+                canonical.push(name);
+            }
+        }
+        Ok(canonical)
+    }
+
     pub fn find_or_create_all(conn: &PgConnection, names: &[&str]) -> QueryResult<Vec<Keyword>> {
         use diesel::dsl::any;
 
@@ -75,9 +226,73 @@ impl Keyword {
         }
     }
 
+    /// Recomputes `crates_cnt` from `crates_keywords` for the keywords whose
+    /// count drifted, returning how many were corrected.
+    pub fn recount_crates(conn: &PgConnection) -> QueryResult<usize> {
+        diesel::sql_query(
+            "UPDATE keywords SET crates_cnt = counts.crates_cnt \
+             FROM ( \
+             SELECT keywords.id, COUNT(crates_keywords.crate_id)::int AS crates_cnt \
+             FROM keywords \
+             LEFT JOIN crates_keywords ON crates_keywords.keyword_id = keywords.id \
+             GROUP BY keywords.id \
+             ) counts \
+             WHERE keywords.id = counts.id AND keywords.crates_cnt <> counts.crates_cnt",
+        ).execute(conn)
+    }
+
+    /// Records today's number of crates of every keyword, returning how many
+    /// keywords were recorded.
+    pub fn snapshot_counts(conn: &PgConnection) -> QueryResult<usize> {
+        diesel::sql_query(
+            "INSERT INTO keyword_snapshots (keyword_id, crates_cnt) \
+             SELECT id, crates_cnt FROM keywords \
+             ON CONFLICT (keyword_id, date) DO UPDATE SET crates_cnt = excluded.crates_cnt",
+        ).execute(conn)
+    }
+
+    /// Returns the keywords of at least `TRENDING_MIN_CRATES` crates whose
+    /// number of crates grew the most relative to the earliest snapshot of
+    /// the last `TRENDING_DAYS` days, along with that number of crates.
+    pub fn trending(
+        conn: &PgConnection,
+        limit: i64,
+        offset: i64,
+    ) -> QueryResult<Vec<(Keyword, i32)>> {
+        use diesel::dsl::sql;
+        use diesel::select;
+        use diesel::sql_types::Integer;
+
+        select(sql::<(keywords::SqlType, Integer)>(&format!(
+            "keywords.id, keywords.keyword, keywords.crates_cnt, keywords.created_at,
+                snapshots.crates_cnt
+             FROM keywords
+             INNER JOIN (
+                SELECT DISTINCT ON (keyword_id) keyword_id, crates_cnt
+                FROM keyword_snapshots
+                WHERE date >= CURRENT_DATE - {days}
+                ORDER BY keyword_id, date
+             ) snapshots ON snapshots.keyword_id = keywords.id
+             WHERE keywords.crates_cnt >= {min_crates}
+                AND keywords.crates_cnt > snapshots.crates_cnt
+             ORDER BY (keywords.crates_cnt - snapshots.crates_cnt)::real
+                / GREATEST(snapshots.crates_cnt, 1) DESC,
+                keywords.crates_cnt DESC, keywords.keyword
+             LIMIT {limit} OFFSET {offset}",
+            days = TRENDING_DAYS,
+            min_crates = TRENDING_MIN_CRATES,
+            limit = limit,
+            offset = offset,
+        ))).load(conn)
+    }
+
+    /// Replaces the keywords of a crate, aliases are replaced by the keyword
+    /// they stand for.
     pub fn update_crate(conn: &PgConnection, krate: &Crate, keywords: &[&str]) -> QueryResult<()> {
         conn.transaction(|| {
-            let keywords = Keyword::find_or_create_all(conn, keywords)?;
+            let names = Keyword::canonical_names(conn, keywords)?;
+            let names = names.iter().map(|s| &**s).collect::<Vec<_>>();
+            let keywords = Keyword::find_or_create_all(conn, &names)?;
             diesel::delete(CrateKeyword::belonging_to(krate)).execute(conn)?;
             let crate_keywords = keywords
                 .into_iter()
@@ -1,17 +1,19 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
 use diesel;
 use diesel::associations::Identifiable;
 use diesel::prelude::*;
-use license_exprs;
 use semver;
 use url::Url;
 
 use app::App;
+use git;
+use license;
+use name_policy::NamePolicy;
 use util::{human, CargoResult};
 
-use models::{Badge, Category, CrateOwner, Keyword, NewCrateOwnerInvitation, Owner, OwnerKind,
-             ReverseDependency, User, Version};
-use views::{EncodableCrate, EncodableCrateLinks};
+use models::{Badge, Category, CrateOwner, CrateRename, DependencyKind, Keyword,
+             NewCrateOwnerInvitation, Owner, OwnerKind, ReverseDependency, User, Version};
+use views::{EncodableCrate, EncodableCrateDownload, EncodableCrateLinks};
 
 use models::helpers::with_count::*;
 use schema::*;
@@ -20,6 +22,16 @@ use schema::*;
 /// and are possibly of malicious intent e.g. ad tracking networks, etc.
 const DOCUMENTATION_BLACKLIST: [&str; 1] = ["rust-ci.org"];
 
+/// The length of the rendered README past which it gets the full quality
+/// score.
+const QUALITY_README_LENGTH: usize = 2000;
+
+/// How many days after the latest release its quality score drops to 0.
+const QUALITY_RELEASE_DAYS: i64 = 365;
+
+/// How many crates `Crate::update_quality_scores` scores at a time.
+const QUALITY_BATCH_SIZE: i64 = 1000;
+
 #[derive(Debug, Insertable, Queryable, Identifiable, Associations, AsChangeset, Clone, Copy)]
 #[belongs_to(Crate)]
 #[primary_key(crate_id, date)]
@@ -30,6 +42,58 @@ pub struct CrateDownload {
     pub date: NaiveDate,
 }
 
+impl CrateDownload {
+    pub fn encodable(self) -> EncodableCrateDownload {
+        EncodableCrateDownload {
+            date: self.date.to_string(),
+            downloads: self.downloads,
+        }
+    }
+}
+
+/// The downloads of the versions of a crate in one major or major.minor line.
+#[derive(Debug, Serialize, Deserialize, QueryableByName)]
+pub struct VersionLineDownloads {
+    #[sql_type = "::diesel::sql_types::Text"]
+    pub line: String,
+    #[sql_type = "::diesel::sql_types::BigInt"]
+    pub downloads: i64,
+    /// The downloads of the last 90 days.
+    #[sql_type = "::diesel::sql_types::BigInt"]
+    pub recent_downloads: i64,
+}
+
+/// How many crates have a name starting with a letter, or with a digit for
+/// the `0-9` bucket.
+#[derive(Debug, Serialize, Deserialize, QueryableByName)]
+pub struct LetterBucket {
+    #[sql_type = "::diesel::sql_types::Text"]
+    pub letter: String,
+    #[sql_type = "::diesel::sql_types::BigInt"]
+    pub crates: i64,
+}
+
+#[derive(QueryableByName)]
+struct CrateId {
+    #[sql_type = "::diesel::sql_types::Integer"]
+    id: i32,
+}
+
+/// How many dependents require a range of versions of a crate, e.g. `^0.8`.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequirementDependents {
+    pub range: String,
+    pub dependents: i64,
+}
+
+#[derive(Debug, QueryableByName)]
+struct RequirementCount {
+    #[sql_type = "::diesel::sql_types::Text"]
+    req: String,
+    #[sql_type = "::diesel::sql_types::BigInt"]
+    dependents: i64,
+}
+
 #[derive(Debug, Clone, Queryable, Identifiable, Associations, AsChangeset, QueryableByName)]
 #[table_name = "crates"]
 pub struct Crate {
@@ -46,6 +110,26 @@ pub struct Crate {
     pub license: Option<String>,
     pub repository: Option<String>,
     pub max_upload_size: Option<i32>,
+    pub publish_hold: bool,
+    /// Whether new versions can't be published, e.g. during a dispute.
+    pub locked: bool,
+    pub lock_reason: Option<String>,
+    /// See `Crate::quality_score`, recomputed every night.
+    pub quality_score: Option<f64>,
+    /// Whether its owners advise against using it, see `Crate::deprecate`.
+    pub deprecated: bool,
+    pub deprecation_message: Option<String>,
+    /// The name of the crate its owners suggest using instead.
+    pub alternative: Option<String>,
+    /// Where the logo uploaded by its owners is stored, see `Uploader::upload_logo`.
+    pub logo_path: Option<String>,
+    /// The highest version that isn't yanked, kept up to date by
+    /// `Crate::update_max_versions` so that listings don't load every version.
+    pub max_version: Option<String>,
+    /// The highest stable version that isn't yanked.
+    pub max_stable_version: Option<String>,
+    /// One of `MAINTENANCE_STATUSES`, set by its owners.
+    pub maintenance_status: Option<String>,
 }
 
 /// We literally never want to select `textsearchable_index_col`
@@ -64,6 +148,17 @@ type AllColumns = (
     crates::license,
     crates::repository,
     crates::max_upload_size,
+    crates::publish_hold,
+    crates::locked,
+    crates::lock_reason,
+    crates::quality_score,
+    crates::deprecated,
+    crates::deprecation_message,
+    crates::alternative,
+    crates::logo_path,
+    crates::max_version,
+    crates::max_stable_version,
+    crates::maintenance_status,
 );
 
 pub const ALL_COLUMNS: AllColumns = (
@@ -80,14 +175,38 @@ pub const ALL_COLUMNS: AllColumns = (
     crates::license,
     crates::repository,
     crates::max_upload_size,
+    crates::publish_hold,
+    crates::locked,
+    crates::lock_reason,
+    crates::quality_score,
+    crates::deprecated,
+    crates::deprecation_message,
+    crates::alternative,
+    crates::logo_path,
+    crates::max_version,
+    crates::max_stable_version,
+    crates::maintenance_status,
 );
 
 pub const MAX_NAME_LENGTH: usize = 64;
 
+/// The maintenance statuses owners can give their crates. Deprecating a crate
+/// is done with `Crate::deprecate` instead, along with a message.
+pub const MAINTENANCE_STATUSES: &[&str] = &[
+    "actively-developed",
+    "passively-maintained",
+    "looking-for-maintainer",
+];
+
+/// How many days without a release or an action of its owners a crate is
+/// considered stale after.
+pub const STALE_AFTER_DAYS: i64 = 2 * 365;
+
 type CanonCrateName<T> = self::canon_crate_name::HelperType<T>;
 type All = diesel::dsl::Select<crates::table, AllColumns>;
 type WithName<'a> = diesel::dsl::Eq<CanonCrateName<crates::name>, CanonCrateName<&'a str>>;
-type ByName<'a> = diesel::dsl::Filter<All, WithName<'a>>;
+type Resolved<'a> = CanonCrateName<self::resolve_crate_name::HelperType<&'a str>>;
+type ByName<'a> = diesel::dsl::Filter<All, diesel::dsl::Eq<CanonCrateName<crates::name>, Resolved<'a>>>;
 
 #[derive(Insertable, AsChangeset, Default, Debug)]
 #[table_name = "crates"]
@@ -111,10 +230,11 @@ impl<'a> NewCrate<'a> {
         conn: &PgConnection,
         license_file: Option<&'a str>,
         uploader: i32,
+        name_policy: &NamePolicy,
     ) -> CargoResult<Crate> {
         use diesel::update;
 
-        self.validate(license_file)?;
+        self.validate(conn, license_file, name_policy)?;
         self.ensure_name_not_reserved(conn)?;
 
         conn.transaction(|| {
@@ -133,7 +253,12 @@ impl<'a> NewCrate<'a> {
         })
     }
 
-    fn validate(&mut self, license_file: Option<&'a str>) -> CargoResult<()> {
+    fn validate(
+        &mut self,
+        conn: &PgConnection,
+        license_file: Option<&'a str>,
+        name_policy: &NamePolicy,
+    ) -> CargoResult<()> {
         fn validate_url(url: Option<&str>, field: &str) -> CargoResult<()> {
             let url = match url {
                 Some(s) => s,
@@ -165,21 +290,44 @@ impl<'a> NewCrate<'a> {
         validate_url(self.documentation, "documentation")?;
         validate_url(self.repository, "repository")?;
         self.validate_license(license_file)?;
+        self.validate_name(conn, name_policy)?;
+        Ok(())
+    }
+
+    /// Checks the name of a new crate against the name policy of the registry,
+    /// crates that already exist keep being published under their name.
+    fn validate_name(&self, conn: &PgConnection, policy: &NamePolicy) -> CargoResult<()> {
+        use diesel::dsl::exists;
+        use diesel::select;
+
+        let existing = select(exists(crates::table.filter(Crate::with_name(self.name))))
+            .get_result::<bool>(conn)?;
+        if existing {
+            return Ok(());
+        }
+
+        policy.check(self.name)?;
+        if policy.forbid_confusable {
+            let squashed = replace(replace(::lower(crates::name), "-", ""), "_", "");
+            let confusable = crates::table
+                .select(crates::name)
+                .filter(squashed.eq(NamePolicy::squash(self.name)))
+                .first::<String>(conn)
+                .optional()?;
+            if let Some(confusable) = confusable {
+                return Err(human(&format_args!(
+                    "the crate name `{}` is too similar to the existing crate `{}`, \
+                     names can't only differ by their `-` and `_`",
+                    self.name, confusable
+                )));
+            }
+        }
         Ok(())
     }
 
     fn validate_license(&mut self, license_file: Option<&str>) -> CargoResult<()> {
         if let Some(license) = self.license {
-            for part in license.split('/') {
-                license_exprs::validate_license_expr(part).map_err(|e| {
-                    human(&format_args!(
-                        "{}; see http://opensource.org/licenses \
-                         for options, and http://spdx.org/licenses/ \
-                         for their identifiers",
-                        e
-                    ))
-                })?;
-            }
+            license::parse(license)?;
         } else if license_file.is_some() {
             // If no license is given, but a license file is given, flag this
             // crate as having a nonstandard license. Note that we don't
@@ -199,6 +347,10 @@ impl<'a> NewCrate<'a> {
         )).get_result::<bool>(conn)?;
         if reserved_name {
             Err(human("cannot upload a crate with a reserved name"))
+        } else if CrateRename::is_former_name(conn, self.name)? {
+            Err(human(
+                "cannot upload a crate with the former name of a renamed crate",
+            ))
         } else {
             Ok(())
         }
@@ -237,8 +389,34 @@ impl Crate {
         canon_crate_name(crates::name).eq(canon_crate_name(name))
     }
 
+    /// Finds the crate currently named `name`, or the crate renamed from it.
     pub fn by_name(name: &str) -> ByName {
-        Crate::all().filter(Self::with_name(name))
+        Crate::all().filter(
+            canon_crate_name(crates::name).eq(canon_crate_name(resolve_crate_name(name))),
+        )
+    }
+
+    /// Locks the crate name until the end of the current transaction, so that
+    /// concurrent publishes of the same crate run one after the other.
+    pub fn lock_name(conn: &PgConnection, name: &str) -> QueryResult<()> {
+        use diesel::sql_query;
+        use diesel::sql_types::Text;
+
+        sql_query("SELECT pg_advisory_xact_lock(hashtext(canon_crate_name($1)))")
+            .bind::<Text, _>(name)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the current name of the crate if it was found through `name`,
+    /// one of its former names.
+    pub fn moved_to(&self, name: &str) -> Option<&str> {
+        let canon = |name: &str| name.replace('-', "_").to_lowercase();
+        if canon(name) == canon(&self.name) {
+            None
+        } else {
+            Some(&self.name)
+        }
     }
 
     pub fn all() -> All {
@@ -281,32 +459,37 @@ impl Crate {
 
     pub fn minimal_encodable(
         self,
-        max_version: &semver::Version,
+        max_version: Option<&semver::Version>,
+        default_version: &semver::Version,
         badges: Option<Vec<Badge>>,
         exact_match: bool,
         recent_downloads: Option<i64>,
     ) -> EncodableCrate {
         self.encodable(
             max_version,
+            default_version,
             None,
             None,
             None,
             badges,
             exact_match,
             recent_downloads,
+            None,
         )
     }
 
     #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
     pub fn encodable(
         self,
-        max_version: &semver::Version,
+        max_version: Option<&semver::Version>,
+        default_version: &semver::Version,
         versions: Option<Vec<i32>>,
         keywords: Option<&[Keyword]>,
         categories: Option<&[Category]>,
         badges: Option<Vec<Badge>>,
         exact_match: bool,
         recent_downloads: Option<i64>,
+        dependency_freshness: Option<f64>,
     ) -> EncodableCrate {
         let Crate {
             name,
@@ -317,8 +500,22 @@ impl Crate {
             homepage,
             documentation,
             repository,
+            license,
+            quality_score,
+            deprecated,
+            deprecation_message,
+            alternative,
+            logo_path,
+            maintenance_status,
             ..
         } = self;
+        // The `0.0.0` max version shown for crates whose versions are all
+        // yanked can't be told apart from an actual `0.0.0` version
+        let all_versions_yanked = max_version.is_none();
+        let max_version = max_version.map_or_else(Version::none, |v| v.clone());
+        let license_expression = license
+            .as_ref()
+            .and_then(|license| license::parse(license).ok());
         let versions_link = match versions {
             Some(..) => None,
             None => Some(format!("/api/v1/crates/{}/versions", name)),
@@ -327,6 +524,7 @@ impl Crate {
         let category_ids = categories.map(|cats| cats.iter().map(|cat| cat.slug.clone()).collect());
         let badges = badges.map(|bs| bs.into_iter().map(|b| b.encodable()).collect());
         let documentation = Crate::remove_blacklisted_documentation_urls(documentation);
+        let logo_url = logo_path.map(|_| format!("/api/v1/crates/{}/logo", name));
 
         EncodableCrate {
             id: name.clone(),
@@ -335,16 +533,27 @@ impl Crate {
             created_at,
             downloads,
             recent_downloads,
+            dependency_freshness,
+            quality_score,
             versions,
             keywords: keyword_ids,
             categories: category_ids,
             badges,
             max_version: max_version.to_string(),
+            default_version: default_version.to_string(),
+            all_versions_yanked,
             documentation,
             homepage,
             exact_match,
             description,
             repository,
+            license,
+            license_expression,
+            deprecated,
+            deprecation_message,
+            alternative,
+            maintenance_status,
+            logo_url,
             links: EncodableCrateLinks {
                 version_downloads: format!("/api/v1/crates/{}/downloads", name),
                 versions: versions_link,
@@ -384,24 +593,375 @@ impl Crate {
         }
     }
 
-    pub fn max_version(&self, conn: &PgConnection) -> CargoResult<semver::Version> {
+    /// Returns the highest version of the crate that isn't yanked, or `None`
+    /// if every version is yanked.
+    pub fn max_version(&self, conn: &PgConnection) -> CargoResult<Option<semver::Version>> {
         use schema::versions::dsl::*;
 
-        let vs = Version::belonging_to(self)
+        Ok(Version::belonging_to(self)
             .select(num)
             .filter(yanked.eq(false))
             .load::<String>(conn)?
             .into_iter()
-            .map(|s| semver::Version::parse(&s).unwrap());
-        Ok(Version::max(vs))
+            .filter_map(|s| semver::Version::parse(&s).ok())
+            .max())
     }
 
+    /// Recomputes the cached `max_version` and `max_stable_version` of the
+    /// crate, after one of its versions is published, yanked or deleted.
+    pub fn update_max_versions(&self, conn: &PgConnection) -> QueryResult<()> {
+        let nums = Version::belonging_to(self)
+            .select(versions::num)
+            .filter(versions::yanked.eq(false))
+            .load::<String>(conn)?
+            .into_iter()
+            .filter_map(|num| semver::Version::parse(&num).ok())
+            .collect::<Vec<_>>();
+        let max = nums.iter().max().map(|num| num.to_string());
+        let max_stable = nums.iter()
+            .filter(|num| !num.is_prerelease())
+            .max()
+            .map(|num| num.to_string());
+        diesel::update(self)
+            .set((
+                crates::max_version.eq(max),
+                crates::max_stable_version.eq(max_stable),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the max version and the default version of the crate from its
+    /// cached columns, unless they aren't filled in yet or all its versions
+    /// are yanked.
+    pub fn cached_versions(&self) -> Option<(semver::Version, semver::Version)> {
+        let max = self.max_version
+            .as_ref()
+            .and_then(|num| semver::Version::parse(num).ok())?;
+        let default = self.max_stable_version
+            .as_ref()
+            .and_then(|num| semver::Version::parse(num).ok())
+            .unwrap_or_else(|| max.clone());
+        Some((max, default))
+    }
+
+    /// Returns the max version and the default version of each of the crates,
+    /// only loading the versions of the crates without cached ones. The max
+    /// version is `None` when every version is yanked.
+    pub fn max_versions(
+        conn: &PgConnection,
+        krates: &[Crate],
+    ) -> QueryResult<Vec<(Option<semver::Version>, semver::Version)>> {
+        use std::collections::HashMap;
+
+        let uncached = krates
+            .iter()
+            .filter(|krate| krate.cached_versions().is_none())
+            .map(|krate| krate.id)
+            .collect::<Vec<_>>();
+        let mut versions = HashMap::new();
+        if !uncached.is_empty() {
+            for version in versions::table
+                .filter(versions::crate_id.eq_any(uncached))
+                .load::<Version>(conn)?
+            {
+                versions
+                    .entry(version.crate_id)
+                    .or_insert_with(Vec::new)
+                    .push(version);
+            }
+        }
+        Ok(krates
+            .iter()
+            .map(|krate| {
+                match krate.cached_versions() {
+                    Some((max, default)) => (Some(max), default),
+                    None => {
+                        let versions = versions.get(&krate.id).map_or(&[][..], |vs| &vs[..]);
+                        let max = versions
+                            .iter()
+                            .filter(|v| !v.yanked)
+                            .map(|v| v.num.clone())
+                            .max();
+                        (max, Version::default_num(versions))
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the version this crate is presented with, see
+    /// `Version::default_num`.
+    pub fn default_version(&self, conn: &PgConnection) -> CargoResult<semver::Version> {
+        let versions = Version::belonging_to(self).load::<Version>(conn)?;
+        Ok(Version::default_num(&versions))
+    }
+
+    /// Sets the license of this crate to the license of its latest version, so
+    /// that publishing an older version doesn't overwrite it.
+    pub fn update_license(
+        &self,
+        conn: &PgConnection,
+        max_version: &semver::Version,
+    ) -> CargoResult<Crate> {
+        let license = Version::belonging_to(self)
+            .filter(versions::num.eq(max_version.to_string()))
+            .select(versions::license)
+            .first::<Option<String>>(conn)
+            .optional()?;
+        match license {
+            Some(license) => Ok(diesel::update(self)
+                .set(crates::license.eq(license))
+                .returning(ALL_COLUMNS)
+                .get_result(conn)?),
+            None => Ok(self.clone()),
+        }
+    }
+
+    /// Marks the crate as deprecated, shown with `message` and the name of the
+    /// crate to use instead, if any. Unlike yanking every version, crates
+    /// depending on it can still be published.
+    pub fn deprecate(
+        &self,
+        conn: &PgConnection,
+        message: &str,
+        alternative: Option<&str>,
+    ) -> CargoResult<()> {
+        let message = message.trim();
+        if message.is_empty() {
+            return Err(human("a deprecation message is required"));
+        }
+        let alternative = match alternative {
+            Some(name) => {
+                let alternative = Crate::by_name(name)
+                    .select(crates::name)
+                    .first::<String>(conn)
+                    .optional()?
+                    .ok_or_else(|| human(&format_args!("no crate named `{}`", name)))?;
+                if alternative == self.name {
+                    return Err(human("a crate can't be its own alternative"));
+                }
+                Some(alternative)
+            }
+            None => None,
+        };
+
+        diesel::update(self)
+            .set((
+                crates::deprecated.eq(true),
+                crates::deprecation_message.eq(message),
+                crates::alternative.eq(alternative),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn undeprecate(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::update(self)
+            .set((
+                crates::deprecated.eq(false),
+                crates::deprecation_message.eq(None::<String>),
+                crates::alternative.eq(None::<String>),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Sets the path of the logo of the crate, `None` removing it.
+    pub fn set_logo_path(&self, conn: &PgConnection, path: Option<&str>) -> QueryResult<()> {
+        diesel::update(self)
+            .set(crates::logo_path.eq(path))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Sets the maintenance status of the crate, one of `MAINTENANCE_STATUSES`,
+    /// or clears it.
+    pub fn set_maintenance_status(
+        &self,
+        conn: &PgConnection,
+        status: Option<&str>,
+    ) -> CargoResult<()> {
+        if let Some(status) = status {
+            if !MAINTENANCE_STATUSES.contains(&status) {
+                return Err(human(&format_args!(
+                    "unknown maintenance status `{}`, expected one of `{}`",
+                    status,
+                    MAINTENANCE_STATUSES.join("`, `")
+                )));
+            }
+        }
+        diesel::update(self)
+            .set(crates::maintenance_status.eq(status))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Recomputes the list of stale crates, the ones without a release or an
+    /// action of their owners for `STALE_AFTER_DAYS`. Returns how many crates
+    /// are stale.
+    pub fn update_stale(conn: &PgConnection) -> QueryResult<usize> {
+        use diesel::sql_types::Timestamp;
+
+        let since = Utc::now().naive_utc() - Duration::days(STALE_AFTER_DAYS);
+        conn.transaction(|| {
+            diesel::delete(stale_crates::table).execute(conn)?;
+            diesel::sql_query(include_str!("update_stale_crates.sql"))
+                .bind::<Timestamp, _>(since)
+                .execute(conn)
+        })
+    }
+
+    /// Returns whether the crate was stale when the list of stale crates was
+    /// last computed, see `update_stale`.
+    pub fn is_stale(&self, conn: &PgConnection) -> QueryResult<bool> {
+        diesel::select(diesel::dsl::exists(stale_crates::table.find(self.id))).get_result(conn)
+    }
+
+    /// Returns the share of the dependencies of this crate's latest version
+    /// whose requirement accepts the latest release of the dependency, or
+    /// `None` if that version has no dependencies.
+    pub fn dependency_freshness(&self, conn: &PgConnection) -> CargoResult<Option<f64>> {
+        let max_version = match self.max_version(conn)? {
+            Some(max_version) => max_version.to_string(),
+            None => return Ok(None),
+        };
+        let version = Version::belonging_to(self)
+            .filter(versions::num.eq(max_version))
+            .first::<Version>(conn)
+            .optional()?;
+        let deps = match version {
+            Some(version) => version.dependencies(conn)?,
+            None => return Ok(None),
+        };
+        if deps.is_empty() {
+            return Ok(None);
+        }
+
+        let mut fresh = 0;
+        for &(ref dep, _) in &deps {
+            let latest_release = Version::max(
+                versions::table
+                    .filter(versions::crate_id.eq(dep.crate_id))
+                    .filter(versions::yanked.eq(false))
+                    .select(versions::num)
+                    .load::<String>(conn)?
+                    .into_iter()
+                    .filter_map(|s| semver::Version::parse(&s).ok())
+                    .filter(|v| !v.is_prerelease()),
+            );
+            if dep.req.matches(&latest_release) {
+                fresh += 1;
+            }
+        }
+
+        Ok(Some(f64::from(fresh) / deps.len() as f64))
+    }
+
+    /// Returns a score between 0 and 1 of how well documented and maintained
+    /// this crate looks, the average of:
+    ///
+    /// - whether it links to its documentation,
+    /// - how recent its latest release is, counting down over a year,
+    /// - the length of its README,
+    /// - the share of its versions that aren't yanked,
+    /// - whether it has the badge of a CI service running its tests.
+    pub fn quality_score(&self, conn: &PgConnection) -> CargoResult<f64> {
+        let krate = (self.id, self.documentation.clone(), self.readme.clone());
+        Ok(quality_scores(conn, &[krate])?[0].1)
+    }
+
+    /// Recomputes the quality score of every crate, `QUALITY_BATCH_SIZE`
+    /// crates at a time. Returns how many crates were scored.
+    pub fn update_quality_scores(conn: &PgConnection) -> CargoResult<usize> {
+        use diesel::sql_types::{Array, Double, Integer};
+
+        let mut scored = 0;
+        let mut last_id = 0;
+        loop {
+            let krates = crates::table
+                .filter(crates::id.gt(last_id))
+                .order(crates::id)
+                .limit(QUALITY_BATCH_SIZE)
+                .select((crates::id, crates::documentation, crates::readme))
+                .load::<(i32, Option<String>, Option<String>)>(conn)?;
+            last_id = match krates.last() {
+                Some(&(id, _, _)) => id,
+                None => return Ok(scored),
+            };
+
+            let (ids, scores): (Vec<i32>, Vec<f64>) =
+                quality_scores(conn, &krates)?.into_iter().unzip();
+            diesel::sql_query(include_str!("update_quality_scores.sql"))
+                .bind::<Array<Integer>, _>(ids)
+                .bind::<Array<Double>, _>(scores)
+                .execute(conn)?;
+            scored += krates.len();
+        }
+    }
+
+    /// Returns the entries of the crate in the git index, as they'd be written
+    /// from the database. Versions held for review aren't in the index yet,
+    /// and the checksums of old versions are unknown and left empty.
+    pub fn index_entries(&self, conn: &PgConnection) -> QueryResult<Vec<git::Crate>> {
+        use models::{Dependency, RegistryDependency};
+
+        let versions = versions::table
+            .left_join(held_index_entries::table)
+            .filter(versions::crate_id.eq(self.id))
+            .filter(held_index_entries::version_id.is_null())
+            .select(versions::all_columns)
+            .order(versions::id)
+            .load::<Version>(conn)?;
+        let deps = Dependency::belonging_to(&versions)
+            .inner_join(crates::table)
+            .select((dependencies::all_columns, crates::name))
+            .order(dependencies::id)
+            .load::<(Dependency, String)>(conn)?
+            .grouped_by(&versions);
+        let registry_deps = RegistryDependency::belonging_to(&versions)
+            .order(registry_dependencies::id)
+            .load::<RegistryDependency>(conn)?
+            .grouped_by(&versions);
+
+        Ok(versions
+            .into_iter()
+            .zip(deps)
+            .zip(registry_deps)
+            .map(|((version, deps), registry_deps)| git::Crate {
+                name: self.name.clone(),
+                vers: version.num.to_string(),
+                deps: deps.into_iter()
+                    .map(|(dep, name)| git::Dependency {
+                        name,
+                        req: dep.req.to_string(),
+                        features: dep.features,
+                        optional: dep.optional,
+                        default_features: dep.default_features,
+                        target: dep.target,
+                        kind: Some(dep.kind),
+                        registry: None,
+                    })
+                    .chain(registry_deps.into_iter().map(|dep| dep.git_dependency()))
+                    .collect(),
+                cksum: version.checksum.unwrap_or_default(),
+                features: version.features,
+                yanked: Some(version.yanked),
+                links: version.links,
+                edition: version.edition,
+            })
+            .collect())
+    }
+
+    /// Returns the users owning the crate then its teams, each ordered by
+    /// their login.
     pub fn owners(&self, conn: &PgConnection) -> CargoResult<Vec<Owner>> {
         let base_query = CrateOwner::belonging_to(self).filter(crate_owners::deleted.eq(false));
         let users = base_query
             .inner_join(users::table)
             .select(users::all_columns)
             .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+            .order(users::gh_login)
             .load(conn)?
             .into_iter()
             .map(Owner::User);
@@ -409,6 +969,7 @@ impl Crate {
             .inner_join(teams::table)
             .select(teams::all_columns)
             .filter(crate_owners::owner_kind.eq(OwnerKind::Team as i32))
+            .order(teams::login)
             .load(conn)?
             .into_iter()
             .map(Owner::Team);
@@ -489,33 +1050,290 @@ impl Crate {
             .load(conn)
     }
 
+    /// Returns the downloads of the crate per major version line, or per
+    /// major.minor line if `by_minor` is set, the highest line first.
+    pub fn downloads_by_semver(
+        &self,
+        conn: &PgConnection,
+        by_minor: bool,
+    ) -> QueryResult<Vec<VersionLineDownloads>> {
+        use diesel::sql_query;
+        use diesel::sql_types::{Bool, Integer};
+
+        sql_query(include_str!("krate_downloads_by_semver.sql"))
+            .bind::<Integer, _>(self.id)
+            .bind::<Bool, _>(by_minor)
+            .load(conn)
+    }
+
     /// Returns (dependency, dependent crate name, dependent crate downloads)
+    ///
+    /// Only the dependencies of the given kind or target are considered when
+    /// they're given.
     pub fn reverse_dependencies(
         &self,
         conn: &PgConnection,
+        kind: Option<DependencyKind>,
+        target: Option<&str>,
         offset: i64,
         limit: i64,
     ) -> QueryResult<(Vec<ReverseDependency>, i64)> {
         use diesel::sql_query;
-        use diesel::sql_types::{BigInt, Integer};
+        use diesel::sql_types::{BigInt, Integer, Nullable, Text};
 
         let rows = sql_query(include_str!("krate_reverse_dependencies.sql"))
             .bind::<Integer, _>(self.id)
             .bind::<BigInt, _>(offset)
             .bind::<BigInt, _>(limit)
+            .bind::<Nullable<Integer>, _>(kind.map(|k| k as i32))
+            .bind::<Nullable<Text>, _>(target)
             .load::<WithCount<ReverseDependency>>(conn)?;
 
         Ok(rows.records_and_total())
     }
+
+    /// Same as `reverse_dependencies`, but only returns the dependents whose
+    /// version requirement is satisfied by at least one published version of
+    /// this crate that also matches `req`.
+    pub fn reverse_dependencies_matching(
+        &self,
+        conn: &PgConnection,
+        req: &semver::VersionReq,
+        kind: Option<DependencyKind>,
+        target: Option<&str>,
+        offset: i64,
+        limit: i64,
+    ) -> CargoResult<(Vec<ReverseDependency>, i64)> {
+        let candidates = Version::belonging_to(self)
+            .select(versions::num)
+            .load::<String>(conn)?
+            .into_iter()
+            .filter_map(|s| semver::Version::parse(&s).ok())
+            .filter(|v| req.matches(v))
+            .collect::<Vec<_>>();
+
+        // The requirements can't be compared in SQL, so we need to filter
+        // every reverse dependency before applying the pagination ourselves
+        let (rev_deps, _) = self.reverse_dependencies(conn, kind, target, 0, i64::max_value())?;
+        let rev_deps = rev_deps
+            .into_iter()
+            .filter(|dep| candidates.iter().any(|v| dep.req().matches(v)))
+            .collect::<Vec<_>>();
+        let total = rev_deps.len() as i64;
+        let rev_deps = rev_deps
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+
+        Ok((rev_deps, total))
+    }
+
+    /// Counts the crates per first letter of their name, in alphabetical order.
+    pub fn letter_buckets(conn: &PgConnection) -> QueryResult<Vec<LetterBucket>> {
+        diesel::sql_query(include_str!("krate_letter_buckets.sql")).load(conn)
+    }
+
+    /// Returns the ids of the crates having all of the keywords, which must be
+    /// lowercase and distinct.
+    pub fn ids_with_all_keywords(
+        conn: &PgConnection,
+        keywords: &[String],
+    ) -> QueryResult<Vec<i32>> {
+        use diesel::sql_types::{Array, Text};
+
+        let ids = diesel::sql_query(include_str!("krate_ids_with_all_keywords.sql"))
+            .bind::<Array<Text>, _>(keywords)
+            .load::<CrateId>(conn)?;
+        Ok(ids.into_iter().map(|c| c.id).collect())
+    }
+
+    /// Returns the ids of the crates in all of the categories, or in their
+    /// subcategories. The slugs must be distinct.
+    pub fn ids_in_all_categories(
+        conn: &PgConnection,
+        slugs: &[String],
+    ) -> QueryResult<Vec<i32>> {
+        use diesel::sql_types::{Array, Text};
+
+        let ids = diesel::sql_query(include_str!("krate_ids_in_all_categories.sql"))
+            .bind::<Array<Text>, _>(slugs)
+            .load::<CrateId>(conn)?;
+        Ok(ids.into_iter().map(|c| c.id).collect())
+    }
+
+    /// Counts the dependents of this crate per range of versions they require,
+    /// the most required range first.
+    ///
+    /// A requirement is counted in the range compatible with the highest
+    /// version of this crate it matches, so that `0.8`, `^0.8.1` and `~0.8.3`
+    /// all count towards `^0.8`. Requirements matching no published version
+    /// are kept as is.
+    pub fn dependents_by_requirement(
+        &self,
+        conn: &PgConnection,
+    ) -> CargoResult<Vec<RequirementDependents>> {
+        use diesel::sql_query;
+        use diesel::sql_types::Integer;
+        use std::collections::HashMap;
+
+        let counts = sql_query(include_str!("krate_dependents_by_requirement.sql"))
+            .bind::<Integer, _>(self.id)
+            .load::<RequirementCount>(conn)?;
+        let versions = Version::belonging_to(self)
+            .filter(versions::yanked.eq(false))
+            .select(versions::num)
+            .load::<String>(conn)?
+            .into_iter()
+            .filter_map(|s| semver::Version::parse(&s).ok())
+            .collect::<Vec<_>>();
+
+        let mut ranges = HashMap::new();
+        for count in counts {
+            let range = semver::VersionReq::parse(&count.req)
+                .ok()
+                .and_then(|req| versions.iter().filter(|v| req.matches(v)).max())
+                .map(compatible_range)
+                .unwrap_or(count.req);
+            *ranges.entry(range).or_insert(0) += count.dependents;
+        }
+        let mut ranges = ranges
+            .into_iter()
+            .map(|(range, dependents)| RequirementDependents { range, dependents })
+            .collect::<Vec<_>>();
+        ranges.sort_by(|a, b| {
+            b.dependents
+                .cmp(&a.dependents)
+                .then_with(|| a.range.cmp(&b.range))
+        });
+        Ok(ranges)
+    }
 }
 
-use diesel::sql_types::{Date, Text};
+/// The caret requirement of the versions semver compatible with `version`.
+fn compatible_range(version: &semver::Version) -> String {
+    match (version.major, version.minor) {
+        (0, 0) => format!("^0.0.{}", version.patch),
+        (0, minor) => format!("^0.{}", minor),
+        (major, _) => format!("^{}", major),
+    }
+}
+
+/// Returns the quality score of each crate, given as its id, documentation
+/// URL, and README, loading their versions and badges with one query each.
+fn quality_scores(
+    conn: &PgConnection,
+    krates: &[(i32, Option<String>, Option<String>)],
+) -> QueryResult<Vec<(i32, f64)>> {
+    use std::collections::{HashMap, HashSet};
+
+    // The badges of the CI services running the tests of a crate
+    const TEST_BADGES: [&str; 4] = ["travis-ci", "appveyor", "gitlab", "circle-ci"];
+
+    let ids = krates.iter().map(|&(id, _, _)| id).collect::<Vec<_>>();
+    let versions = versions::table
+        .filter(versions::crate_id.eq_any(&ids))
+        .select((versions::crate_id, versions::yanked, versions::created_at))
+        .load::<(i32, bool, NaiveDateTime)>(conn)?;
+    let tested = badges::table
+        .filter(badges::crate_id.eq_any(&ids))
+        .filter(badges::badge_type.eq_any(&TEST_BADGES[..]))
+        .select(badges::crate_id)
+        .distinct()
+        .load::<i32>(conn)?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let mut releases = HashMap::new();
+    for (crate_id, yanked, created_at) in versions {
+        let entry = releases.entry(crate_id).or_insert((0, 0, None));
+        entry.0 += 1;
+        if yanked {
+            entry.1 += 1;
+        } else {
+            entry.2 = entry.2.max(Some(created_at));
+        }
+    }
+
+    let now = Utc::now().naive_utc();
+    Ok(krates
+        .iter()
+        .map(|&(id, ref documentation, ref readme)| {
+            let (versions, yanked, released) = releases.get(&id).cloned().unwrap_or((0, 0, None));
+            let documented =
+                Crate::remove_blacklisted_documentation_urls(documentation.clone()).is_some();
+            let readme_length = readme.as_ref().map_or(0, |readme| readme.chars().count());
+            let score = quality_score(
+                documented,
+                released.map(|released| (now - released).num_days()),
+                readme_length,
+                (versions, yanked),
+                tested.contains(&id),
+            );
+            (id, score)
+        })
+        .collect())
+}
+
+/// See `Crate::quality_score`, `versions` is the number of versions and how
+/// many of them are yanked.
+fn quality_score(
+    documented: bool,
+    days_since_release: Option<i64>,
+    readme_length: usize,
+    versions: (usize, usize),
+    tested: bool,
+) -> f64 {
+    fn flag(b: bool) -> f64 {
+        if b {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    let release = days_since_release.map_or(0.0, |days| {
+        1.0 - days.max(0).min(QUALITY_RELEASE_DAYS) as f64 / QUALITY_RELEASE_DAYS as f64
+    });
+    let readme = readme_length.min(QUALITY_README_LENGTH) as f64 / QUALITY_README_LENGTH as f64;
+    let (versions, yanked) = versions;
+    let unyanked = if versions == 0 {
+        0.0
+    } else {
+        1.0 - yanked as f64 / versions as f64
+    };
+
+    (flag(documented) + release + readme + unyanked + flag(tested)) / 5.0
+}
+
+use diesel::sql_types::{Date, Integer, Text};
 sql_function!(fn canon_crate_name(x: Text) -> Text);
+sql_function!(fn left(x: Text, n: Integer) -> Text);
+sql_function!(fn replace(x: Text, from: Text, to: Text) -> Text);
+sql_function!(fn resolve_crate_name(x: Text) -> Text);
 sql_function!(fn to_char(a: Date, b: Text) -> Text);
 
 #[cfg(test)]
 mod tests {
+    use super::{compatible_range, quality_score};
     use models::Crate;
+    use semver::Version;
+
+    #[test]
+    fn compatible_ranges() {
+        let range = |v: &str| compatible_range(&Version::parse(v).unwrap());
+        assert_eq!(range("1.2.3"), "^1");
+        assert_eq!(range("0.8.1"), "^0.8");
+        assert_eq!(range("0.0.3"), "^0.0.3");
+    }
+
+    #[test]
+    fn quality_scores() {
+        assert_eq!(quality_score(false, None, 0, (0, 0), false), 0.0);
+        assert_eq!(quality_score(true, Some(0), 5000, (4, 0), true), 1.0);
+        assert_eq!(quality_score(true, Some(400), 1000, (4, 2), false), 0.4);
+        assert_eq!(quality_score(false, Some(0), 500, (1, 0), true), 0.65);
+    }
 
     #[test]
     fn documentation_blacklist_no_url_provided() {
@@ -0,0 +1,24 @@
+-- Counts the dependents of a crate per version requirement, only looking at
+-- the latest version of each dependent like the reverse dependencies do
+SELECT dependencies.req, COUNT(DISTINCT versions.crate_id) AS dependents
+FROM dependencies
+INNER JOIN (
+    SELECT versions.id, versions.crate_id,
+    row_number() OVER (
+        PARTITION BY crate_id
+        ORDER BY to_semver_no_prerelease(num) DESC NULLS LAST
+    ) rn
+    FROM versions
+    WHERE NOT yanked
+    AND crate_id = ANY(
+        SELECT versions.crate_id
+        FROM versions
+        INNER JOIN dependencies
+        ON dependencies.version_id = versions.id
+        WHERE dependencies.crate_id = $1
+    )
+) versions
+  ON versions.id = dependencies.version_id
+WHERE dependencies.crate_id = $1
+  AND rn = 1
+GROUP BY dependencies.req
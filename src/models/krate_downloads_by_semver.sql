@@ -0,0 +1,19 @@
+-- Sums the downloads of the versions of a crate per major version line, or
+-- per major.minor line when $2 is true
+SELECT line,
+    COALESCE(SUM(version_downloads.downloads), 0) AS downloads,
+    COALESCE(SUM(version_downloads.downloads)
+        FILTER (WHERE version_downloads.date > CURRENT_DATE - 90), 0) AS recent_downloads
+FROM (
+    SELECT id,
+        CASE WHEN $2
+            THEN split_part(num, '.', 1) || '.' || split_part(num, '.', 2)
+            ELSE split_part(num, '.', 1)
+        END AS line
+    FROM versions
+    WHERE crate_id = $1
+) versions
+LEFT JOIN version_downloads
+    ON version_downloads.version_id = versions.id
+GROUP BY line
+ORDER BY string_to_array(line, '.')::int[] DESC
@@ -0,0 +1,9 @@
+-- The crates in all of the categories in $1, which are distinct, or in their
+-- subcategories
+SELECT crates_categories.crate_id AS id
+FROM crates_categories
+INNER JOIN categories ON categories.id = crates_categories.category_id
+INNER JOIN unnest($1::text[]) AS wanted (slug)
+    ON categories.slug = wanted.slug OR categories.slug LIKE wanted.slug || '::%'
+GROUP BY crates_categories.crate_id
+HAVING COUNT(DISTINCT wanted.slug) = array_length($1, 1)
@@ -0,0 +1,8 @@
+-- The crates having all of the keywords in $1, which are lowercase and
+-- distinct
+SELECT crates_keywords.crate_id AS id
+FROM crates_keywords
+INNER JOIN keywords ON keywords.id = crates_keywords.keyword_id
+WHERE lower(keywords.keyword) = ANY($1)
+GROUP BY crates_keywords.crate_id
+HAVING COUNT(DISTINCT lower(keywords.keyword)) = array_length($1, 1)
@@ -0,0 +1,12 @@
+-- Counts the crates per first letter of their name, crates starting with a
+-- digit being counted together in the `0-9` bucket
+SELECT letter, COUNT(*) AS crates
+FROM (
+    SELECT CASE WHEN left(canon_crate_name(name), 1) BETWEEN '0' AND '9'
+        THEN '0-9'
+        ELSE left(canon_crate_name(name), 1)
+    END AS letter
+    FROM crates
+) crates
+GROUP BY letter
+ORDER BY letter
@@ -31,6 +31,9 @@ SELECT *, COUNT(*) OVER () as total FROM (
       ON crates.id = versions.crate_id
     WHERE dependencies.crate_id = $1
       AND rn = 1
+      -- Optional filters on the kind and the target of the dependencies
+      AND ($4::int IS NULL OR dependencies.kind = $4)
+      AND ($5::text IS NULL OR dependencies.target = $5)
     ORDER BY crate_downloads DESC
 ) t
 OFFSET $2
@@ -1,33 +1,81 @@
+pub use self::announcement::{Announcement, NewAnnouncement};
+pub use self::audit_action::AuditAction;
 pub use self::badge::{Badge, CrateBadge, MaintenanceStatus};
-pub use self::category::{Category, CrateCategory, NewCategory};
+pub use self::category::{Category, CategoryRename, CrateCategory, NewCategory};
+pub use self::crate_dependent::CrateDependent;
+pub use self::crate_list::{CrateList, CrateListItem};
+pub use self::crate_metadata_change::{CrateMetadataChange, MetadataSnapshot};
 pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitation};
-pub use self::dependency::{Dependency, DependencyKind, ReverseDependency};
+pub use self::crate_rename::CrateRename;
+pub use self::crate_transfer::CrateTransfer;
+pub use self::deleted_crate::DeletedCrate;
+pub use self::dependency::{Dependency, DependencyKind, RegistryDependency, ReverseDependency};
 pub use self::download::VersionDownload;
+pub use self::download_breakdown::DownloadBreakdown;
+pub use self::download_ingestion::{DownloadIngestion, IngestedDownloads};
 pub use self::email::{Email, NewEmail};
 pub use self::follow::Follow;
-pub use self::keyword::{CrateKeyword, Keyword};
+pub use self::held_index_entry::HeldIndexEntry;
+pub use self::index_outbox_entry::IndexOutboxEntry;
+pub use self::keyword::{CrateKeyword, Keyword, KeywordAlias};
 pub use self::krate::{Crate, CrateDownload, NewCrate};
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::pending_publish::PendingPublish;
+pub use self::registry_flag::RegistryFlag;
+pub use self::related_crate::RelatedCrate;
 pub use self::rights::Rights;
+pub use self::scan_result::VersionScanResult;
+pub use self::sitemap::Sitemap;
+pub use self::spotlight::CrateSpotlight;
 pub use self::team::{NewTeam, Team};
-pub use self::token::ApiToken;
+pub use self::token::{ApiToken, TeamToken};
+pub use self::trusted_publisher::{TrustedPublisher, TrustedPublishingToken};
+pub use self::two_factor::TwoFactorCredential;
 pub use self::user::{NewUser, User};
-pub use self::version::{NewVersion, Version};
+pub use self::version::{Binary, NewVersion, PublishSource, Version};
+pub use self::version_file::{VersionDiff, VersionFile};
+pub use self::version_signature::VersionSignature;
+pub use self::version_source::VersionSource;
+pub use self::webhook::{CrateWebhook, WebhookDelivery};
 
 pub mod helpers;
 
+mod announcement;
+mod audit_action;
 mod badge;
 mod category;
+mod crate_dependent;
+mod crate_list;
+mod crate_metadata_change;
 mod crate_owner_invitation;
+mod crate_rename;
+mod crate_transfer;
+mod deleted_crate;
 pub mod dependency;
 mod download;
+mod download_breakdown;
+mod download_ingestion;
 mod email;
 mod follow;
+mod held_index_entry;
+mod index_outbox_entry;
 mod keyword;
 pub mod krate;
 mod owner;
+pub mod pending_publish;
+pub mod registry_flag;
+mod related_crate;
 mod rights;
+mod scan_result;
+pub mod sitemap;
+mod spotlight;
 mod team;
 mod token;
+mod trusted_publisher;
+mod two_factor;
 mod user;
 mod version;
+mod version_file;
+pub mod version_signature;
+mod version_source;
+mod webhook;
@@ -1,7 +1,6 @@
 use diesel::prelude::*;
 
 use app::App;
-use github;
 use util::{human, CargoResult};
 
 use models::{Crate, Team, User};
@@ -82,42 +81,47 @@ impl Owner {
         }
     }
 
+    /// `user` or `team`, as the kind of owner is serialized.
+    pub fn kind_name(&self) -> &'static str {
+        match *self {
+            Owner::User(_) => "user",
+            Owner::Team(_) => "team",
+        }
+    }
+
+    /// The GitHub profile of the user, or the page of the organization of the
+    /// team.
+    pub fn url(&self) -> String {
+        match *self {
+            Owner::User(ref user) => user.url(),
+            Owner::Team(ref team) => team.url(),
+        }
+    }
+
+    /// The avatar of the user or of the organization of the team.
+    pub fn avatar_url(&self) -> String {
+        match *self {
+            Owner::User(ref user) => user.avatar_url(),
+            Owner::Team(ref team) => team.avatar_url(),
+        }
+    }
+
+    /// Encodes users and teams alike, both always having an avatar and a URL.
     pub fn encodable(self) -> EncodableOwner {
-        match self {
-            Owner::User(User {
-                id,
-                name,
-                gh_login,
-                gh_avatar,
-                ..
-            }) => {
-                let url = format!("https://github.com/{}", gh_login);
-                EncodableOwner {
-                    id,
-                    login: gh_login,
-                    avatar: gh_avatar,
-                    url: Some(url),
-                    name,
-                    kind: String::from("user"),
-                }
-            }
-            Owner::Team(Team {
-                id,
-                name,
-                login,
-                avatar,
-                ..
-            }) => {
-                let url = github::team_url(&login);
-                EncodableOwner {
-                    id,
-                    login,
-                    url: Some(url),
-                    avatar,
-                    name,
-                    kind: String::from("team"),
-                }
-            }
+        let kind = self.kind_name().to_string();
+        let url = self.url();
+        let avatar = self.avatar_url();
+        let (id, login, name) = match self {
+            Owner::User(User { id, name, gh_login, .. }) => (id, gh_login, name),
+            Owner::Team(Team { id, name, login, .. }) => (id, login, name),
+        };
+        EncodableOwner {
+            id,
+            login,
+            kind,
+            url: Some(url),
+            name,
+            avatar: Some(avatar),
         }
     }
 }
@@ -0,0 +1,77 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+
+use models::User;
+use schema::pending_publishes;
+
+/// How long a user has to confirm the publication of a new crate name and to
+/// publish it again once confirmed, in minutes.
+pub const CONFIRMATION_WINDOW: i32 = 15;
+
+/// A request to publish a brand-new crate name, made by a user who chose to
+/// confirm such publications through an emailed link.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(User)]
+pub struct PendingPublish {
+    pub id: i32,
+    pub user_id: i32,
+    pub crate_name: String,
+    pub token: String,
+    pub created_at: NaiveDateTime,
+    pub confirmed_at: Option<NaiveDateTime>,
+}
+
+impl PendingPublish {
+    pub fn create(conn: &PgConnection, user: &User, crate_name: &str) -> QueryResult<Self> {
+        diesel::insert_into(pending_publishes::table)
+            .values((
+                pending_publishes::user_id.eq(user.id),
+                pending_publishes::crate_name.eq(crate_name),
+            ))
+            .get_result(conn)
+    }
+
+    /// Marks the request with the given token as confirmed, returning whether
+    /// a request that can still be confirmed was found.
+    pub fn confirm(conn: &PgConnection, token: &str) -> QueryResult<bool> {
+        use diesel::dsl::now;
+
+        let updated = diesel::update(
+            pending_publishes::table
+                .filter(pending_publishes::token.eq(token))
+                .filter(pending_publishes::confirmed_at.is_null())
+                .filter(pending_publishes::created_at.gt(now - CONFIRMATION_WINDOW.minutes())),
+        ).set(pending_publishes::confirmed_at.eq(now.nullable()))
+            .execute(conn)?;
+        Ok(updated > 0)
+    }
+
+    /// Returns whether the user confirmed a request to publish the given
+    /// crate name, without consuming it.
+    pub fn is_confirmed(conn: &PgConnection, user: &User, crate_name: &str) -> QueryResult<bool> {
+        use diesel::dsl::{exists, now, select};
+
+        select(exists(
+            PendingPublish::belonging_to(user)
+                .filter(pending_publishes::crate_name.eq(crate_name))
+                .filter(pending_publishes::confirmed_at.is_not_null())
+                .filter(pending_publishes::created_at.gt(now - CONFIRMATION_WINDOW.minutes())),
+        )).get_result(conn)
+    }
+
+    /// Consumes the confirmed requests of the user to publish the given crate
+    /// name, returning whether there was any. Called in the transaction of the
+    /// publish, so that the confirmation is kept if the publish fails.
+    pub fn take_confirmed(conn: &PgConnection, user: &User, crate_name: &str) -> QueryResult<bool> {
+        use diesel::dsl::now;
+
+        let deleted = diesel::delete(
+            PendingPublish::belonging_to(user)
+                .filter(pending_publishes::crate_name.eq(crate_name))
+                .filter(pending_publishes::confirmed_at.is_not_null())
+                .filter(pending_publishes::created_at.gt(now - CONFIRMATION_WINDOW.minutes())),
+        ).execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+
+use schema::registry_flags;
+
+/// The flag refusing publishes, yanks, owner changes and follows, see
+/// `App::read_only`.
+pub const READ_ONLY: &str = "read_only";
+
+/// A switch toggled by the administrators at runtime. It is stored in the
+/// database so that every server sees it, and survives restarts.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[primary_key(name)]
+pub struct RegistryFlag {
+    pub name: String,
+    pub enabled: bool,
+    pub updated_at: NaiveDateTime,
+}
+
+impl RegistryFlag {
+    /// Returns whether the flag is on, flags that were never set are off.
+    pub fn is_enabled(conn: &PgConnection, name: &str) -> QueryResult<bool> {
+        registry_flags::table
+            .find(name)
+            .select(registry_flags::enabled)
+            .first(conn)
+            .optional()
+            .map(|enabled| enabled.unwrap_or(false))
+    }
+
+    pub fn set(conn: &PgConnection, name: &str, enabled: bool) -> QueryResult<()> {
+        diesel::insert_into(registry_flags::table)
+            .values((
+                registry_flags::name.eq(name),
+                registry_flags::enabled.eq(enabled),
+            ))
+            .on_conflict(registry_flags::name)
+            .do_update()
+            .set(registry_flags::enabled.eq(enabled))
+            .execute(conn)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+
+use models::krate::ALL_COLUMNS;
+use models::Crate;
+use schema::{crates, related_crates};
+
+/// How many related crates are kept for each crate.
+pub const RELATED_CRATES: i64 = 10;
+
+/// A crate related to another by the keywords and categories they share and
+/// the crates depending on both of them, recomputed every day by the server.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[primary_key(crate_id, related_id)]
+#[belongs_to(Crate)]
+pub struct RelatedCrate {
+    pub crate_id: i32,
+    pub related_id: i32,
+    /// How many keywords, categories and dependents the crates share.
+    pub score: i32,
+}
+
+impl RelatedCrate {
+    /// Replaces the related crates of every crate, returning how many were
+    /// recorded.
+    pub fn recompute(conn: &PgConnection) -> QueryResult<usize> {
+        conn.transaction(|| {
+            diesel::delete(related_crates::table).execute(conn)?;
+            diesel::sql_query(include_str!("related_crates_recompute.sql"))
+                .bind::<BigInt, _>(RELATED_CRATES)
+                .execute(conn)
+        })
+    }
+
+    /// Returns the crates related to the crate, the most related first.
+    pub fn related(conn: &PgConnection, krate: &Crate) -> QueryResult<Vec<Crate>> {
+        related_crates::table
+            .inner_join(crates::table)
+            .filter(related_crates::crate_id.eq(krate.id))
+            .order((related_crates::score.desc(), crates::name))
+            .select(ALL_COLUMNS)
+            .load(conn)
+    }
+}
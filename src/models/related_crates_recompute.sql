@@ -0,0 +1,40 @@
+-- Scores how related each pair of crates sharing a keyword or a category is,
+-- one point for each keyword and category they share and for each crate
+-- depending on both of them, keeping the `$1` most related crates of each
+-- crate. The dependents are only compared for these pairs, as pairing all the
+-- crates depended on together would be quadratic in the dependencies.
+INSERT INTO related_crates (crate_id, related_id, score)
+WITH shared AS (
+    SELECT a.crate_id, b.crate_id AS related_id, COUNT(*) AS score
+    FROM crates_keywords a
+    INNER JOIN crates_keywords b
+        ON b.keyword_id = a.keyword_id AND b.crate_id <> a.crate_id
+    GROUP BY a.crate_id, b.crate_id
+    UNION ALL
+    SELECT a.crate_id, b.crate_id, COUNT(*)
+    FROM crates_categories a
+    INNER JOIN crates_categories b
+        ON b.category_id = a.category_id AND b.crate_id <> a.crate_id
+    GROUP BY a.crate_id, b.crate_id
+), pairs AS (
+    SELECT DISTINCT crate_id, related_id FROM shared
+)
+SELECT crate_id, related_id, score
+FROM (
+    SELECT crate_id, related_id, SUM(score)::int AS score,
+        ROW_NUMBER() OVER (
+            PARTITION BY crate_id ORDER BY SUM(score) DESC, related_id
+        ) AS rank
+    FROM (
+        SELECT crate_id, related_id, score FROM shared
+        UNION ALL
+        SELECT pairs.crate_id, pairs.related_id, COUNT(*)
+        FROM pairs
+        INNER JOIN crate_dependents a ON a.crate_id = pairs.crate_id
+        INNER JOIN crate_dependents b
+            ON b.crate_id = pairs.related_id AND b.dependent_id = a.dependent_id
+        GROUP BY pairs.crate_id, pairs.related_id
+    ) scores
+    GROUP BY crate_id, related_id
+) ranked
+WHERE rank <= $1
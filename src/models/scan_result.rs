@@ -0,0 +1,61 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use models::Version;
+use scanning::ScanResult;
+use schema::version_scan_results;
+use views::EncodableVersionScanResult;
+
+/// The verdict a scanner gave about the crate file of a version when it was
+/// published.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[belongs_to(Version)]
+pub struct VersionScanResult {
+    pub id: i32,
+    pub version_id: i32,
+    pub scanner: String,
+    pub blocked: bool,
+    pub detail: Option<String>,
+    pub scanned_at: NaiveDateTime,
+}
+
+impl VersionScanResult {
+    pub fn save_all(
+        conn: &PgConnection,
+        version_id: i32,
+        results: &[ScanResult],
+    ) -> QueryResult<usize> {
+        use schema::version_scan_results::dsl;
+
+        let rows = results
+            .iter()
+            .map(|result| {
+                (
+                    dsl::version_id.eq(version_id),
+                    dsl::scanner.eq(&result.scanner),
+                    dsl::blocked.eq(result.blocked),
+                    dsl::detail.eq(result.detail.as_ref()),
+                )
+            })
+            .collect::<Vec<_>>();
+        ::diesel::insert_into(version_scan_results::table)
+            .values(&rows)
+            .execute(conn)
+    }
+
+    pub fn encodable(self) -> EncodableVersionScanResult {
+        let VersionScanResult {
+            scanner,
+            blocked,
+            detail,
+            scanned_at,
+            ..
+        } = self;
+        EncodableVersionScanResult {
+            scanner,
+            blocked,
+            detail,
+            scanned_at,
+        }
+    }
+}
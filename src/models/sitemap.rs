@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+
+use schema::{categories, crates, sitemaps};
+
+/// Where the pages listed by the sitemaps are served.
+const SITE_URL: &str = "https://crates.io";
+
+/// The most URLs a sitemap can list.
+const MAX_URLS: usize = 50_000;
+
+/// The name of the index of the other sitemaps, served at `/sitemap.xml`.
+pub const SITEMAP_INDEX: &str = "sitemap.xml";
+
+/// A sitemap of the crates starting with a letter, of the categories, or the
+/// index of the other sitemaps. They are regenerated daily by the
+/// `update-sitemaps` job.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[primary_key(name)]
+pub struct Sitemap {
+    /// The file name of the sitemap, served under `/sitemaps/`.
+    pub name: String,
+    pub body: String,
+    pub generated_at: NaiveDateTime,
+}
+
+impl Sitemap {
+    pub fn find(conn: &PgConnection, name: &str) -> QueryResult<Sitemap> {
+        sitemaps::table.find(name).first(conn)
+    }
+
+    /// Replaces the sitemaps with new ones, returning how many there are
+    /// besides the index.
+    ///
+    /// The crates are split by their first letter, in chunks of `MAX_URLS`
+    /// named `crates-a.xml`, `crates-a-2.xml` and so on.
+    pub fn regenerate(conn: &PgConnection) -> QueryResult<usize> {
+        let krates = crates::table
+            .select((crates::name, crates::updated_at))
+            .order(crates::name)
+            .load::<(String, NaiveDateTime)>(conn)?;
+        let mut letters = BTreeMap::new();
+        for (name, updated_at) in krates {
+            let letter = name.chars().next().map_or('_', |c| c.to_ascii_lowercase());
+            letters
+                .entry(letter)
+                .or_insert_with(Vec::new)
+                .push((format!("{}/crates/{}", SITE_URL, name), updated_at));
+        }
+
+        let mut files = Vec::new();
+        for (letter, urls) in letters {
+            for (i, chunk) in urls.chunks(MAX_URLS).enumerate() {
+                let name = match i {
+                    0 => format!("crates-{}.xml", letter),
+                    _ => format!("crates-{}-{}.xml", letter, i + 1),
+                };
+                files.push((name, chunk.to_vec()));
+            }
+        }
+        let categories = categories::table
+            .select((categories::slug, categories::created_at))
+            .order(categories::slug)
+            .load::<(String, NaiveDateTime)>(conn)?
+            .into_iter()
+            .map(|(slug, created_at)| (format!("{}/categories/{}", SITE_URL, slug), created_at))
+            .collect::<Vec<_>>();
+        if !categories.is_empty() {
+            files.push(("categories.xml".into(), categories));
+        }
+
+        let mut index = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+        let mut sitemaps = Vec::new();
+        for &(ref name, ref urls) in &files {
+            let mut body = String::from(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+            );
+            for &(ref loc, lastmod) in urls {
+                body.push_str(&format!(
+                    "<url><loc>{}</loc><lastmod>{}</lastmod></url>\n",
+                    loc,
+                    lastmod.format("%F")
+                ));
+            }
+            body.push_str("</urlset>\n");
+            let lastmod = urls.iter().map(|&(_, lastmod)| lastmod).max().unwrap();
+            index.push_str(&format!(
+                "<sitemap><loc>{}/sitemaps/{}</loc><lastmod>{}</lastmod></sitemap>\n",
+                SITE_URL,
+                name,
+                lastmod.format("%F")
+            ));
+            sitemaps.push((sitemaps::name.eq(name.clone()), sitemaps::body.eq(body)));
+        }
+        index.push_str("</sitemapindex>\n");
+        sitemaps.push((sitemaps::name.eq(SITEMAP_INDEX.to_string()), sitemaps::body.eq(index)));
+
+        conn.transaction(|| {
+            diesel::delete(sitemaps::table).execute(conn)?;
+            diesel::insert_into(sitemaps::table)
+                .values(&sitemaps)
+                .execute(conn)
+        })?;
+        Ok(files.len())
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use diesel;
+use diesel::dsl::all;
+use diesel::prelude::*;
+
+use models::krate::ALL_COLUMNS;
+use models::{Crate, User};
+use schema::{crate_spotlights, crates, recent_crate_downloads};
+use views::{EncodableCrate, EncodableCrateSpotlight};
+
+/// How long a crate is left out of the automatic picks after being featured.
+const FEATURED_AGAIN_AFTER_DAYS: i64 = 365;
+/// Crates without a release in this many days aren't picked automatically.
+const RECENTLY_UPDATED_DAYS: i64 = 90;
+
+/// The crate featured on the front page on a given day, chosen by an
+/// administrator or picked automatically.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations)]
+#[belongs_to(Crate)]
+#[table_name = "crate_spotlights"]
+pub struct CrateSpotlight {
+    pub id: i32,
+    pub crate_id: i32,
+    pub featured_on: NaiveDate,
+    pub rationale: String,
+    pub created_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateSpotlight {
+    /// Features the crate on the day, replacing the crate already featured.
+    pub fn feature(
+        conn: &PgConnection,
+        krate: &Crate,
+        date: NaiveDate,
+        rationale: &str,
+        admin: &User,
+    ) -> QueryResult<CrateSpotlight> {
+        let values = (
+            crate_spotlights::crate_id.eq(krate.id),
+            crate_spotlights::featured_on.eq(date),
+            crate_spotlights::rationale.eq(rationale),
+            crate_spotlights::created_by.eq(admin.id),
+        );
+        diesel::insert_into(crate_spotlights::table)
+            .values(values)
+            .on_conflict(crate_spotlights::featured_on)
+            .do_update()
+            .set(values)
+            .get_result(conn)
+    }
+
+    /// Returns the spotlight of the day along with its crate. When no
+    /// administrator chose one, the most downloaded of the recently updated
+    /// crates that weren't featured in the last year is picked.
+    pub fn of_the_day(
+        conn: &PgConnection,
+        date: NaiveDate,
+    ) -> QueryResult<Option<(CrateSpotlight, Crate)>> {
+        if let Some(spotlight) = CrateSpotlight::on(conn, date)? {
+            return Ok(Some(spotlight));
+        }
+
+        let featured_since = date - Duration::days(FEATURED_AGAIN_AFTER_DAYS);
+        let recently_featured = crate_spotlights::table
+            .filter(crate_spotlights::featured_on.gt(featured_since))
+            .select(crate_spotlights::crate_id)
+            .load::<i32>(conn)?;
+        let updated_since = date.and_hms(0, 0, 0) - Duration::days(RECENTLY_UPDATED_DAYS);
+        let pick = crates::table
+            .inner_join(recent_crate_downloads::table)
+            .filter(crates::id.ne(all(recently_featured)))
+            .filter(crates::updated_at.gt(updated_since))
+            .filter(crates::publish_hold.eq(false))
+            .order(recent_crate_downloads::downloads.desc())
+            .select((crates::id, recent_crate_downloads::downloads))
+            .first::<(i32, i64)>(conn)
+            .optional()?;
+        let (crate_id, downloads) = match pick {
+            Some(pick) => pick,
+            None => return Ok(None),
+        };
+
+        let rationale = format!(
+            "Actively maintained and downloaded {} times in the last 90 days",
+            downloads
+        );
+        // Concurrent requests may pick a crate at the same time, the first
+        // one wins
+        diesel::insert_into(crate_spotlights::table)
+            .values((
+                crate_spotlights::crate_id.eq(crate_id),
+                crate_spotlights::featured_on.eq(date),
+                crate_spotlights::rationale.eq(rationale),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        CrateSpotlight::on(conn, date)
+    }
+
+    fn on(conn: &PgConnection, date: NaiveDate) -> QueryResult<Option<(CrateSpotlight, Crate)>> {
+        crate_spotlights::table
+            .inner_join(crates::table)
+            .filter(crate_spotlights::featured_on.eq(date))
+            .select((crate_spotlights::all_columns, ALL_COLUMNS))
+            .first(conn)
+            .optional()
+    }
+
+    pub fn encodable(self, krate: EncodableCrate) -> EncodableCrateSpotlight {
+        EncodableCrateSpotlight {
+            krate,
+            featured_on: self.featured_on.to_string(),
+            rationale: self.rationale,
+        }
+    }
+}
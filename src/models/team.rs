@@ -181,6 +181,50 @@ impl Team {
         team_with_gh_id_contains_user(app, self.github_id, user)
     }
 
+    /// Phones home to Github to ask if this User is an admin of the given
+    /// team, with the same caveat as `contains_user`.
+    pub fn is_maintainer(&self, app: &App, user: &User) -> CargoResult<bool> {
+        Ok(team_membership(app, self.github_id, user)?
+            .map_or(false, |m| m.state == "active" && m.role == "maintainer"))
+    }
+
+    /// Returns the teams owning crates that the user is a member of. GitHub
+    /// is only asked for the teams of the user if some team owns a crate.
+    pub fn owning_crates_with_member(
+        app: &App,
+        conn: &PgConnection,
+        user: &User,
+    ) -> CargoResult<Vec<Team>> {
+        use diesel::dsl::exists;
+        use diesel::select;
+
+        let team_owners = crate_owners::table
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::Team as i32));
+        if !select(exists(team_owners)).get_result::<bool>(conn)? {
+            return Ok(Vec::new());
+        }
+
+        #[derive(Deserialize)]
+        struct GithubTeam {
+            id: i32,
+        }
+
+        // FIXME: like `create_or_update_github_team`, this doesn't chase
+        // pagination links past the first hundred teams of the user.
+        let token = github::token(user.gh_access_token.clone());
+        let (handle, data) = github::github(app, "/user/teams?per_page=100", &token)?;
+        let github_ids = github::parse_github_response::<Vec<GithubTeam>>(handle, &data)?
+            .into_iter()
+            .map(|team| team.id)
+            .collect::<Vec<_>>();
+
+        Ok(teams::table
+            .filter(teams::github_id.eq_any(github_ids))
+            .filter(teams::id.eq_any(team_owners.select(crate_owners::owner_id)))
+            .load(conn)?)
+    }
+
     pub fn owning(krate: &Crate, conn: &PgConnection) -> CargoResult<Vec<Owner>> {
         let base_query = CrateOwner::belonging_to(krate).filter(crate_owners::deleted.eq(false));
         let teams = base_query
@@ -194,47 +238,62 @@ impl Team {
         Ok(teams.collect())
     }
 
+    /// The GitHub page of the organization of the team.
+    pub fn url(&self) -> String {
+        github::team_url(&self.login)
+    }
+
+    /// The avatar of the organization of the team, or the one GitHub
+    /// redirects to from its page for the teams whose avatar wasn't recorded,
+    /// like the ones added before it was.
+    pub fn avatar_url(&self) -> String {
+        self.avatar
+            .clone()
+            .unwrap_or_else(|| format!("{}.png", self.url()))
+    }
+
     pub fn encodable(self) -> EncodableTeam {
-        let Team {
-            id,
-            name,
-            login,
-            avatar,
-            ..
-        } = self;
-        let url = github::team_url(&login);
+        let url = self.url();
+        let avatar = self.avatar_url();
+        let Team { id, name, login, .. } = self;
+        let org = login.split(':').nth(1).map(String::from);
 
         EncodableTeam {
             id,
             login,
+            kind: String::from("team"),
+            org,
             name,
-            avatar,
+            avatar: Some(avatar),
             url: Some(url),
         }
     }
 }
 
 fn team_with_gh_id_contains_user(app: &App, github_id: i32, user: &User) -> CargoResult<bool> {
-    // GET teams/:team_id/memberships/:user_name
-    // check that "state": "active"
+    // There is also `state: pending` for which we could possibly give
+    // some feedback, but it's not obvious how that should work.
+    Ok(team_membership(app, github_id, user)?.map_or(false, |m| m.state == "active"))
+}
 
-    #[derive(Deserialize)]
-    struct Membership {
-        state: String,
-    }
+#[derive(Deserialize)]
+struct Membership {
+    state: String,
+    /// `maintainer` for the admins of the team, `member` otherwise.
+    #[serde(default)]
+    role: String,
+}
 
+fn team_membership(app: &App, github_id: i32, user: &User) -> CargoResult<Option<Membership>> {
+    // GET teams/:team_id/memberships/:user_name
     let url = format!("/teams/{}/memberships/{}", &github_id, &user.gh_login);
     let token = github::token(user.gh_access_token.clone());
     let (mut handle, resp) = github::github(app, &url, &token)?;
 
     // Officially how `false` is returned
     if handle.response_code().unwrap() == 404 {
-        return Ok(false);
+        return Ok(None);
     }
 
-    let membership: Membership = github::parse_github_response(handle, &resp)?;
-
-    // There is also `state: pending` for which we could possibly give
-    // some feedback, but it's not obvious how that should work.
-    Ok(membership.state == "active")
+    Ok(Some(github::parse_github_response(handle, &resp)?))
 }
@@ -1,9 +1,11 @@
 use chrono::NaiveDateTime;
 use diesel;
 use diesel::prelude::*;
+use std::net::IpAddr;
 
-use models::User;
-use schema::api_tokens;
+use models::{Owner, Rights, Team, User};
+use schema::{api_tokens, team_tokens};
+use util::{CargoResult, Cidr};
 use views::EncodableApiTokenWithToken;
 
 /// The model representing a row in the `api_tokens` database table.
@@ -20,6 +22,8 @@ pub struct ApiToken {
     pub created_at: NaiveDateTime,
     #[serde(with = "::util::rfc3339::option")]
     pub last_used_at: Option<NaiveDateTime>,
+    /// The address ranges the token can be used from, anywhere if empty.
+    pub allowed_ips: Vec<String>,
 }
 
 impl ApiToken {
@@ -30,10 +34,117 @@ impl ApiToken {
             .get_result::<ApiToken>(conn)
     }
 
+    pub fn find_by_token(conn: &PgConnection, token: &str) -> QueryResult<ApiToken> {
+        api_tokens::table
+            .filter(api_tokens::token.eq(token))
+            .first(conn)
+    }
+
+    /// Parses a list of IP address ranges in CIDR notation, normalizing them.
+    pub fn parse_allowed_ips(ranges: &[String]) -> CargoResult<Vec<String>> {
+        ranges
+            .iter()
+            .map(|range| Ok(range.trim().parse::<Cidr>()?.to_string()))
+            .collect()
+    }
+
+    /// Restricts the addresses the token can be used from.
+    pub fn set_allowed_ips(
+        &self,
+        conn: &PgConnection,
+        ranges: &[String],
+    ) -> QueryResult<ApiToken> {
+        diesel::update(self)
+            .set(api_tokens::allowed_ips.eq(ranges))
+            .get_result(conn)
+    }
+
+    /// Whether the token can be used from the address.
+    pub fn allows_ip(&self, ip: &IpAddr) -> bool {
+        let mut ranges = self.allowed_ips
+            .iter()
+            .filter_map(|range| range.parse::<Cidr>().ok());
+        self.allowed_ips.is_empty() || ranges.any(|range| range.contains(ip))
+    }
+
     /// Converts this `ApiToken` model into an `EncodableApiToken` including
     /// the actual token value for JSON serialization.  This should only be
     /// used when initially creating a new token to minimize the chance of
     /// token leaks.
+    pub fn encodable_with_token(self) -> EncodableApiTokenWithToken {
+        EncodableApiTokenWithToken {
+            id: self.id,
+            name: self.name,
+            token: self.token,
+            created_at: self.created_at,
+            last_used_at: self.last_used_at,
+            allowed_ips: self.allowed_ips,
+        }
+    }
+}
+
+/// An API token belonging to a team rather than to a user. It can publish
+/// the crates owned by the team, and nothing else.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations, Serialize)]
+#[belongs_to(Team)]
+pub struct TeamToken {
+    pub id: i32,
+    #[serde(skip)]
+    pub team_id: i32,
+    /// The team admin who created the token, publishes are attributed to them.
+    #[serde(skip)]
+    pub created_by: i32,
+    #[serde(skip)]
+    pub token: String,
+    pub name: String,
+    #[serde(with = "::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "::util::rfc3339::option")]
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+impl TeamToken {
+    /// Generates a new named API token for a team
+    pub fn insert(
+        conn: &PgConnection,
+        team: &Team,
+        user: &User,
+        name: &str,
+    ) -> QueryResult<TeamToken> {
+        diesel::insert_into(team_tokens::table)
+            .values((
+                team_tokens::team_id.eq(team.id),
+                team_tokens::created_by.eq(user.id),
+                team_tokens::name.eq(name),
+            ))
+            .get_result(conn)
+    }
+
+    /// Finds the team token with the given value, recording that it was used.
+    pub fn find_by_token(conn: &PgConnection, token: &str) -> QueryResult<Option<TeamToken>> {
+        use diesel::dsl::now;
+
+        diesel::update(team_tokens::table.filter(team_tokens::token.eq(token)))
+            .set(team_tokens::last_used_at.eq(now.nullable()))
+            .get_result(conn)
+            .optional()
+    }
+
+    /// The rights the token has on a crate with the given owners: `Publish`
+    /// if the team of the token is one of them.
+    pub fn rights(&self, owners: &[Owner]) -> Rights {
+        let owned = owners.iter().any(|owner| match *owner {
+            Owner::Team(ref team) => team.id == self.team_id,
+            Owner::User(_) => false,
+        });
+        if owned {
+            Rights::Publish
+        } else {
+            Rights::None
+        }
+    }
+
+    /// Same as `ApiToken::encodable_with_token`.
     pub fn encodable_with_token(self) -> EncodableApiTokenWithToken {
         EncodableApiTokenWithToken {
             id: self.id,
@@ -60,6 +171,7 @@ mod tests {
             name: "".to_string(),
             created_at: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 11),
             last_used_at: Some(NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12)),
+            allowed_ips: Vec::new(),
         };
         let json = serde_json::to_string(&tok).unwrap();
         assert!(
@@ -82,6 +194,7 @@ mod tests {
             token: "".to_string(),
             created_at: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 11),
             last_used_at: Some(NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12)),
+            allowed_ips: Vec::new(),
         };
         let json = serde_json::to_string(&tok).unwrap();
         assert!(
@@ -0,0 +1,128 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use trusted_publishing::{GitHubClaims, TOKEN_LIFETIME};
+
+use models::{Crate, User};
+use schema::{crates, trusted_publishers, trusted_publishing_tokens, users};
+
+/// A GitHub Actions workflow allowed to publish a crate.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations, Serialize)]
+#[belongs_to(Crate)]
+pub struct TrustedPublisher {
+    pub id: i32,
+    #[serde(skip)]
+    pub crate_id: i32,
+    pub repository_owner: String,
+    pub repository_name: String,
+    pub workflow_filename: String,
+    /// The owner who registered the workflow, publishes are made on their behalf.
+    #[serde(skip)]
+    pub created_by: i32,
+    #[serde(with = "::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl TrustedPublisher {
+    pub fn create(
+        conn: &PgConnection,
+        krate: &Crate,
+        user: &User,
+        repository_owner: &str,
+        repository_name: &str,
+        workflow_filename: &str,
+    ) -> QueryResult<TrustedPublisher> {
+        diesel::insert_into(trusted_publishers::table)
+            .values((
+                trusted_publishers::crate_id.eq(krate.id),
+                trusted_publishers::repository_owner.eq(repository_owner),
+                trusted_publishers::repository_name.eq(repository_name),
+                trusted_publishers::workflow_filename.eq(workflow_filename),
+                trusted_publishers::created_by.eq(user.id),
+            ))
+            .get_result(conn)
+    }
+
+    /// Returns whether any crate can be published by the workflow.
+    pub fn any_matching(
+        conn: &PgConnection,
+        repository_owner: &str,
+        repository_name: &str,
+        workflow_filename: &str,
+    ) -> QueryResult<bool> {
+        use diesel::dsl::exists;
+        use diesel::select;
+
+        select(exists(
+            trusted_publishers::table
+                .filter(trusted_publishers::repository_owner.eq(repository_owner))
+                .filter(trusted_publishers::repository_name.eq(repository_name))
+                .filter(trusted_publishers::workflow_filename.eq(workflow_filename)),
+        )).get_result(conn)
+    }
+}
+
+/// A short-lived token allowing a workflow to publish the crates it is a
+/// trusted publisher of.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+pub struct TrustedPublishingToken {
+    pub id: i32,
+    pub repository_owner: String,
+    pub repository_name: String,
+    pub workflow_filename: String,
+    pub token: String,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+impl TrustedPublishingToken {
+    /// Creates a token for the workflow the OIDC token was issued to, valid
+    /// for `TOKEN_LIFETIME` minutes.
+    pub fn create(
+        conn: &PgConnection,
+        claims: &GitHubClaims,
+        workflow_filename: &str,
+    ) -> QueryResult<TrustedPublishingToken> {
+        diesel::insert_into(trusted_publishing_tokens::table)
+            .values((
+                trusted_publishing_tokens::repository_owner.eq(&claims.repository_owner),
+                trusted_publishing_tokens::repository_name.eq(claims.repository_name()),
+                trusted_publishing_tokens::workflow_filename.eq(workflow_filename),
+                trusted_publishing_tokens::expires_at.eq(now + TOKEN_LIFETIME.minutes()),
+            ))
+            .get_result(conn)
+    }
+
+    /// Returns the user a publish of the given crate authenticated with the
+    /// token is made on behalf of, if the token is valid and the workflow it
+    /// was issued to is a trusted publisher of the crate.
+    pub fn publisher_user(
+        conn: &PgConnection,
+        token: &str,
+        crate_name: &str,
+    ) -> QueryResult<Option<User>> {
+        let token = trusted_publishing_tokens::table
+            .filter(trusted_publishing_tokens::token.eq(token))
+            .filter(trusted_publishing_tokens::expires_at.gt(now))
+            .first::<TrustedPublishingToken>(conn)
+            .optional()?;
+        let token = match token {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+
+        trusted_publishers::table
+            .inner_join(users::table)
+            .filter(trusted_publishers::crate_id.eq_any(
+                Crate::by_name(crate_name).select(crates::id),
+            ))
+            .filter(trusted_publishers::repository_owner.eq(&token.repository_owner))
+            .filter(trusted_publishers::repository_name.eq(&token.repository_name))
+            .filter(trusted_publishers::workflow_filename.eq(&token.workflow_filename))
+            .select(users::all_columns)
+            .first(conn)
+            .optional()
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use hex;
+use openssl::hash::{Hasher, MessageDigest};
+use rand::{thread_rng, Rng};
+
+use models::User;
+use schema::{two_factor_credentials, two_factor_recovery_codes};
+use totp;
+use util::errors::TooManyRequests;
+use util::{human, CargoResult};
+
+/// How many recovery codes are generated when two-factor authentication is
+/// enabled.
+const RECOVERY_CODES: usize = 10;
+
+/// How many invalid codes in a row lock the second factor of a user.
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+
+/// How long the second factor stays locked after too many invalid codes.
+const LOCKOUT_MINUTES: i64 = 15;
+
+/// The TOTP secret a user enrolled with. The second factor is only required
+/// once the enrollment was confirmed with a first code.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations)]
+#[primary_key(user_id)]
+#[belongs_to(User)]
+pub struct TwoFactorCredential {
+    pub user_id: i32,
+    pub secret: String,
+    pub enabled_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    /// The step of the last code accepted, see `totp::matching_step`.
+    pub last_used_step: Option<i64>,
+    pub failed_attempts: i32,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+impl TwoFactorCredential {
+    pub fn find(conn: &PgConnection, user_id: i32) -> QueryResult<Option<TwoFactorCredential>> {
+        two_factor_credentials::table
+            .find(user_id)
+            .first(conn)
+            .optional()
+    }
+
+    /// Whether the user has confirmed their enrollment.
+    pub fn enabled_for(conn: &PgConnection, user_id: i32) -> QueryResult<bool> {
+        Ok(Self::find(conn, user_id)?.map_or(false, |credential| credential.is_enabled()))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+
+    /// Starts an enrollment with a new secret, replacing an unconfirmed one.
+    pub fn begin(conn: &PgConnection, user_id: i32) -> CargoResult<TwoFactorCredential> {
+        use diesel::pg::upsert::excluded;
+        use schema::two_factor_credentials::{created_at, secret};
+
+        if Self::enabled_for(conn, user_id)? {
+            return Err(human("two-factor authentication is already enabled"));
+        }
+        let credential = diesel::insert_into(two_factor_credentials::table)
+            .values((
+                two_factor_credentials::user_id.eq(user_id),
+                secret.eq(totp::generate_secret()),
+            ))
+            .on_conflict(two_factor_credentials::user_id)
+            .do_update()
+            .set((secret.eq(excluded(secret)), created_at.eq(diesel::dsl::now)))
+            .get_result(conn)?;
+        Ok(credential)
+    }
+
+    /// Whether `code` is the current code of the authenticator app. Each code
+    /// is only accepted once, and too many invalid codes in a row lock the
+    /// second factor for `LOCKOUT_MINUTES`.
+    pub fn verify(&self, conn: &PgConnection, code: &str) -> CargoResult<bool> {
+        use schema::two_factor_credentials::last_used_step;
+
+        self.check_not_locked()?;
+        let valid = match totp::matching_step(&self.secret, code, Utc::now().timestamp()) {
+            Some(step) => {
+                let unused = two_factor_credentials::table
+                    .find(self.user_id)
+                    .filter(last_used_step.is_null().or(last_used_step.lt(step)));
+                diesel::update(unused)
+                    .set(last_used_step.eq(step))
+                    .execute(conn)? > 0
+            }
+            None => false,
+        };
+        self.record_attempt(conn, valid)?;
+        Ok(valid)
+    }
+
+    fn check_not_locked(&self) -> CargoResult<()> {
+        let now = Utc::now().naive_utc();
+        match self.locked_until {
+            Some(locked_until) if locked_until > now => Err(Box::new(TooManyRequests {
+                retry_after: (locked_until - now).num_seconds() as u64 + 1,
+            })),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts the invalid codes sent in a row, locking the second factor once
+    /// there are `MAX_FAILED_ATTEMPTS` of them.
+    fn record_attempt(&self, conn: &PgConnection, valid: bool) -> QueryResult<()> {
+        use schema::two_factor_credentials::{failed_attempts, locked_until};
+
+        let credential = two_factor_credentials::table.find(self.user_id);
+        if valid {
+            diesel::update(credential)
+                .set(failed_attempts.eq(0))
+                .execute(conn)?;
+            return Ok(());
+        }
+        let attempts = diesel::update(credential)
+            .set(failed_attempts.eq(failed_attempts + 1))
+            .returning(failed_attempts)
+            .get_result::<i32>(conn)?;
+        if attempts >= MAX_FAILED_ATTEMPTS {
+            let until = Utc::now().naive_utc() + Duration::minutes(LOCKOUT_MINUTES);
+            diesel::update(credential)
+                .set((failed_attempts.eq(0), locked_until.eq(until)))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Confirms the enrollment with a first code, returning the recovery
+    /// codes to show the user. Only their hashes are stored.
+    pub fn enable(&self, conn: &PgConnection, code: &str) -> CargoResult<Vec<String>> {
+        if self.is_enabled() {
+            return Err(human("two-factor authentication is already enabled"));
+        }
+        if !self.verify(conn, code)? {
+            return Err(human("invalid two-factor authentication code"));
+        }
+
+        let codes = (0..RECOVERY_CODES)
+            .map(|_| {
+                let code = thread_rng()
+                    .gen_ascii_chars()
+                    .take(10)
+                    .collect::<String>()
+                    .to_lowercase();
+                format!("{}-{}", &code[..5], &code[5..])
+            })
+            .collect::<Vec<_>>();
+        let rows = codes
+            .iter()
+            .map(|code| {
+                (
+                    two_factor_recovery_codes::user_id.eq(self.user_id),
+                    two_factor_recovery_codes::code_hash.eq(hash_recovery_code(code)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        conn.transaction(|| {
+            diesel::update(self)
+                .set(two_factor_credentials::enabled_at.eq(diesel::dsl::now.nullable()))
+                .execute(conn)?;
+            diesel::delete(
+                two_factor_recovery_codes::table
+                    .filter(two_factor_recovery_codes::user_id.eq(self.user_id)),
+            ).execute(conn)?;
+            diesel::insert_into(two_factor_recovery_codes::table)
+                .values(&rows)
+                .execute(conn)?;
+            Ok(codes)
+        })
+    }
+
+    /// Disables two-factor authentication, removing the secret and the
+    /// remaining recovery codes.
+    pub fn disable(&self, conn: &PgConnection) -> QueryResult<()> {
+        conn.transaction(|| {
+            diesel::delete(
+                two_factor_recovery_codes::table
+                    .filter(two_factor_recovery_codes::user_id.eq(self.user_id)),
+            ).execute(conn)?;
+            diesel::delete(self).execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Uses up one of the recovery codes of the user, returning whether it
+    /// was valid. Invalid recovery codes count towards the lockout like the
+    /// invalid codes of the authenticator app.
+    pub fn use_recovery_code(&self, conn: &PgConnection, code: &str) -> CargoResult<bool> {
+        self.check_not_locked()?;
+        let code = code.trim().to_lowercase();
+        let used = diesel::update(
+            two_factor_recovery_codes::table
+                .filter(two_factor_recovery_codes::user_id.eq(self.user_id))
+                .filter(two_factor_recovery_codes::code_hash.eq(hash_recovery_code(&code)))
+                .filter(two_factor_recovery_codes::used_at.is_null()),
+        ).set(two_factor_recovery_codes::used_at.eq(diesel::dsl::now.nullable()))
+            .execute(conn)?;
+        self.record_attempt(conn, used > 0)?;
+        Ok(used > 0)
+    }
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let mut hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+    hasher.update(code.as_bytes()).unwrap();
+    hex::encode(hasher.finish2().unwrap())
+}
@@ -0,0 +1,5 @@
+-- Sets the quality score of the crates whose ids are in $1 to the scores in $2
+UPDATE crates
+SET quality_score = scores.quality_score
+FROM UNNEST($1::INTEGER[], $2::FLOAT8[]) AS scores (id, quality_score)
+WHERE crates.id = scores.id
@@ -0,0 +1,16 @@
+-- Lists the crates without a release or an action of their owners since $1
+INSERT INTO stale_crates (crate_id, last_activity_at)
+SELECT id, last_activity_at FROM (
+    SELECT crates.id,
+        GREATEST(
+            crates.created_at,
+            (SELECT MAX(versions.created_at) FROM versions
+                WHERE versions.crate_id = crates.id),
+            (SELECT MAX(audit_actions.created_at) FROM audit_actions
+                WHERE audit_actions.crate_id = crates.id),
+            (SELECT MAX(crate_owners.created_at) FROM crate_owners
+                WHERE crate_owners.crate_id = crates.id)
+        ) AS last_activity_at
+    FROM crates
+) activity
+WHERE last_activity_at < $1
@@ -1,12 +1,15 @@
+use chrono::NaiveDateTime;
 use diesel::dsl::now;
 use diesel::prelude::*;
 use std::borrow::Cow;
 
 use app::App;
-use util::CargoResult;
+use util::errors::CargoError;
+use util::{human, internal, CargoResult};
 
-use models::{Crate, CrateOwner, NewEmail, Owner, OwnerKind, Rights};
-use schema::{crate_owners, emails, users};
+use models::{Crate, CrateOwner, Email, NewEmail, Owner, OwnerKind, Rights, Team};
+use schema::{api_tokens, crate_lists, crate_owner_invitations, crate_owners, crates, emails,
+             follows, pending_publishes, teams, users};
 use views::{EncodablePrivateUser, EncodablePublicUser};
 
 /// The model representing a row in the `users` database table.
@@ -19,6 +22,9 @@ pub struct User {
     pub name: Option<String>,
     pub gh_avatar: Option<String>,
     pub gh_id: i32,
+    pub confirm_new_crates: bool,
+    pub created_at: NaiveDateTime,
+    pub deleted_at: Option<NaiveDateTime>,
 }
 
 #[derive(Insertable, Debug)]
@@ -155,6 +161,148 @@ impl User {
         Ok(best)
     }
 
+    /// Deletes the account of the user. The user row is kept for the crates
+    /// and versions referencing it, but anonymized, and their tokens, follows,
+    /// emails and invitations are removed. The sessions of the user, on any
+    /// device, don't authenticate anymore once `deleted_at` is set.
+    ///
+    /// The crates the user is the only owner of are transferred to the
+    /// `orphan_team` if there is one. Otherwise the account can't be deleted
+    /// until someone else owns them.
+    pub fn delete_account(
+        &self,
+        conn: &PgConnection,
+        orphan_team: Option<&str>,
+    ) -> CargoResult<()> {
+        use diesel::dsl::any;
+        use diesel::{delete, insert_into, update};
+
+        conn.transaction::<_, Box<CargoError>, _>(|| {
+            let owned = crate_owners::table
+                .filter(crate_owners::owner_id.eq(self.id))
+                .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+                .filter(crate_owners::deleted.eq(false))
+                .select(crate_owners::crate_id)
+                .load::<i32>(conn)?;
+            let other_owners = crate_owners::table
+                .filter(crate_owners::crate_id.eq(any(&owned)))
+                .filter(crate_owners::deleted.eq(false))
+                .filter(
+                    crate_owners::owner_id
+                        .ne(self.id)
+                        .or(crate_owners::owner_kind.ne(OwnerKind::User as i32)),
+                )
+                .select(crate_owners::crate_id)
+                .load::<i32>(conn)?;
+            let solely_owned = owned
+                .into_iter()
+                .filter(|id| !other_owners.contains(id))
+                .collect::<Vec<_>>();
+
+            if !solely_owned.is_empty() {
+                let orphan_team = match orphan_team {
+                    Some(login) => teams::table
+                        .filter(teams::login.eq(login))
+                        .first::<Team>(conn)
+                        .optional()?
+                        .ok_or_else(|| {
+                            internal(&format_args!("orphan team `{}` doesn't exist", login))
+                        })?,
+                    None => {
+                        let names = crates::table
+                            .filter(crates::id.eq(any(&solely_owned)))
+                            .select(crates::name)
+                            .order(crates::name)
+                            .load::<String>(conn)?;
+                        return Err(human(&format_args!(
+                            "you are the only owner of {}, transfer their \
+                             ownership before deleting your account",
+                            names
+                                .iter()
+                                .map(|name| format!("`{}`", name))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )));
+                    }
+                };
+                let new_owners = solely_owned
+                    .iter()
+                    .map(|&crate_id| CrateOwner {
+                        crate_id,
+                        owner_id: orphan_team.id,
+                        created_by: self.id,
+                        owner_kind: OwnerKind::Team as i32,
+                    })
+                    .collect::<Vec<_>>();
+                insert_into(crate_owners::table)
+                    .values(&new_owners)
+                    .on_conflict(crate_owners::table.primary_key())
+                    .do_update()
+                    .set(crate_owners::deleted.eq(false))
+                    .execute(conn)?;
+            }
+
+            update(
+                crate_owners::table
+                    .filter(crate_owners::owner_id.eq(self.id))
+                    .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32)),
+            ).set(crate_owners::deleted.eq(true))
+                .execute(conn)?;
+            delete(api_tokens::table.filter(api_tokens::user_id.eq(self.id))).execute(conn)?;
+            delete(follows::table.filter(follows::user_id.eq(self.id))).execute(conn)?;
+            delete(crate_lists::table.filter(crate_lists::user_id.eq(self.id))).execute(conn)?;
+            delete(emails::table.filter(emails::user_id.eq(self.id))).execute(conn)?;
+            delete(pending_publishes::table.filter(pending_publishes::user_id.eq(self.id)))
+                .execute(conn)?;
+            delete(
+                crate_owner_invitations::table.filter(
+                    crate_owner_invitations::invited_user_id
+                        .eq(self.id)
+                        .or(crate_owner_invitations::invited_by_user_id.eq(self.id)),
+                ),
+            ).execute(conn)?;
+
+            // GitHub ids are only unique when positive, so that logging in
+            // again with the same GitHub account creates a new user. No
+            // GitHub account has the id 0, unlike the negative ids
+            // administrators can be given in tests.
+            update(self)
+                .set((
+                    users::gh_login.eq(format!("deleted-user-{}", self.id)),
+                    users::gh_id.eq(0),
+                    users::deleted_at.eq(now.nullable()),
+                    users::gh_access_token.eq(""),
+                    users::name.eq(None::<String>),
+                    users::gh_avatar.eq(None::<String>),
+                    users::email.eq(None::<String>),
+                ))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Returns the email address of this user if they verified it.
+    pub fn verified_email(&self, conn: &PgConnection) -> CargoResult<Option<String>> {
+        Ok(Email::belonging_to(self)
+            .select(emails::email)
+            .filter(emails::verified.eq(true))
+            .first::<String>(conn)
+            .optional()?)
+    }
+
+    /// The GitHub profile of the user.
+    pub fn url(&self) -> String {
+        format!("https://github.com/{}", self.gh_login)
+    }
+
+    /// The avatar of the user, or the one GitHub redirects to from their
+    /// profile for the users whose avatar wasn't recorded.
+    pub fn avatar_url(&self) -> String {
+        self.gh_avatar
+            .clone()
+            .unwrap_or_else(|| format!("{}.png", self.url()))
+    }
+
     /// Converts this `User` model into an `EncodablePrivateUser` for JSON serialization.
     pub fn encodable_private(
         self,
@@ -167,6 +315,7 @@ impl User {
             name,
             gh_login,
             gh_avatar,
+            confirm_new_crates,
             ..
         } = self;
         let url = format!("https://github.com/{}", gh_login);
@@ -175,6 +324,7 @@ impl User {
             email,
             email_verified,
             email_verification_sent,
+            confirm_new_crates,
             avatar: gh_avatar,
             login: gh_login,
             name,
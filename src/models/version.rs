@@ -7,7 +7,7 @@ use diesel::prelude::*;
 use semver;
 use serde_json;
 
-use license_exprs;
+use license;
 use util::{human, CargoResult};
 
 use models::{Crate, Dependency};
@@ -27,8 +27,47 @@ pub struct Version {
     pub features: HashMap<String, Vec<String>>,
     pub yanked: bool,
     pub license: Option<String>,
+    /// The SHA256 checksum of the crate file, unknown for old versions.
+    pub checksum: Option<String>,
+    pub links: Option<String>,
+    /// The Rust edition the version is compiled with, unknown for old versions.
+    pub edition: Option<String>,
+    pub binaries: Vec<Binary>,
+    /// Where the version was published from, see `PublishSource`. Only shown
+    /// to the owners of the crate.
+    pub published_by: Option<i32>,
+    pub cargo_version: Option<String>,
+    pub ci_provider: Option<String>,
+    /// How the publish was authenticated, see `PublishSource::published_with`.
+    pub published_with: Option<String>,
 }
 
+/// What a publish tells about where it is made from, in the `X-Cargo-Version`
+/// and `X-CI-Provider` headers, and the credentials it's authenticated with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PublishSource {
+    /// The version of cargo, e.g. `1.26.0 (0e7c5a931 2018-04-06)`.
+    pub cargo_version: Option<String>,
+    /// The CI service, e.g. `travis-ci` or `github-actions`.
+    pub ci_provider: Option<String>,
+    /// How the publish was authenticated: `session`, `trusted_publishing`,
+    /// or the kind and name of the token, e.g. `api_token:laptop` or
+    /// `team_token:ci`.
+    pub published_with: Option<String>,
+}
+
+/// A binary target of a version, along with the features it needs to be
+/// built.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binary {
+    pub name: String,
+    #[serde(default)]
+    pub required_features: Vec<String>,
+}
+
+/// The editions versions can be published with.
+pub const EDITIONS: &[&str] = &["2015", "2018"];
+
 #[derive(Insertable, Debug)]
 #[table_name = "versions"]
 pub struct NewVersion {
@@ -49,6 +88,8 @@ impl Version {
             features,
             yanked,
             license,
+            edition,
+            binaries,
             ..
         } = self;
         let num = num.to_string();
@@ -64,6 +105,10 @@ impl Version {
             features,
             yanked,
             license,
+            edition,
+            binaries,
+            published_by: None,
+            published_with: None,
             links: EncodableVersionLinks {
                 dependencies: format!("/api/v1/crates/{}/{}/dependencies", crate_name, num),
                 version_downloads: format!("/api/v1/crates/{}/{}/downloads", crate_name, num),
@@ -81,20 +126,123 @@ impl Version {
             .load(conn)
     }
 
+    /// Returns the highest of the versions, or `Version::none()` if there
+    /// are none.
     pub fn max<T>(versions: T) -> semver::Version
     where
         T: IntoIterator<Item = semver::Version>,
     {
-        versions
-            .into_iter()
-            .max()
-            .unwrap_or_else(|| semver::Version {
-                major: 0,
-                minor: 0,
-                patch: 0,
-                pre: vec![],
-                build: vec![],
-            })
+        versions.into_iter().max().unwrap_or_else(Version::none)
+    }
+
+    /// The `0.0.0` max version of a crate whose versions are all yanked.
+    pub fn none() -> semver::Version {
+        semver::Version {
+            major: 0,
+            minor: 0,
+            patch: 0,
+            pre: vec![],
+            build: vec![],
+        }
+    }
+
+    /// Returns the version a crate with these versions is presented with: its
+    /// highest stable version that isn't yanked, falling back to its highest
+    /// prerelease that isn't yanked, and then to its highest version.
+    pub fn default_num<'a, T>(versions: T) -> semver::Version
+    where
+        T: IntoIterator<Item = &'a Version>,
+    {
+        let versions = versions.into_iter().collect::<Vec<_>>();
+        let highest = |pred: &Fn(&Version) -> bool| {
+            versions
+                .iter()
+                .filter(|v| pred(v))
+                .map(|v| v.num.clone())
+                .max()
+        };
+        highest(&|v| !v.yanked && !v.num.is_prerelease())
+            .or_else(|| highest(&|v| !v.yanked))
+            .unwrap_or_else(|| Version::max(versions.iter().map(|v| v.num.clone())))
+    }
+
+    /// Records the parts of the index entry of the version that are only
+    /// known once the crate file is uploaded.
+    pub fn record_index_fields(
+        &self,
+        conn: &PgConnection,
+        checksum: &str,
+        links: Option<&str>,
+    ) -> QueryResult<usize> {
+        diesel::update(self)
+            .set((versions::checksum.eq(checksum), versions::links.eq(links)))
+            .execute(conn)
+    }
+
+    /// Records the edition and binaries the version was published with,
+    /// checking that the binaries only require features of the version.
+    pub fn record_build_metadata(
+        &self,
+        conn: &PgConnection,
+        edition: Option<&str>,
+        binaries: &[Binary],
+    ) -> CargoResult<()> {
+        if let Some(edition) = edition {
+            if !EDITIONS.contains(&edition) {
+                return Err(human(&format_args!(
+                    "unknown edition `{}`, expected one of {}",
+                    edition,
+                    EDITIONS.join(", ")
+                )));
+            }
+        }
+
+        // Optional dependencies are features as well, and `dep/feature`
+        // enables a feature of any dependency
+        let deps = self.dependencies(conn)?;
+        for binary in binaries {
+            for feature in &binary.required_features {
+                let known = match feature.find('/') {
+                    Some(i) => deps.iter().any(|&(_, ref name)| *name == feature[..i]),
+                    None => {
+                        self.features.contains_key(feature)
+                            || deps.iter()
+                                .any(|&(ref dep, ref name)| dep.optional && name == feature)
+                    }
+                };
+                if !known {
+                    return Err(human(&format_args!(
+                        "binary `{}` requires the unknown feature `{}`",
+                        binary.name, feature
+                    )));
+                }
+            }
+        }
+
+        diesel::update(self)
+            .set((
+                versions::edition.eq(edition),
+                versions::binaries.eq(serde_json::to_value(binaries)?),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records who published the version, and from where.
+    pub fn record_publish_source(
+        &self,
+        conn: &PgConnection,
+        published_by: i32,
+        source: &PublishSource,
+    ) -> QueryResult<usize> {
+        diesel::update(self)
+            .set((
+                versions::published_by.eq(published_by),
+                versions::cargo_version.eq(&source.cargo_version),
+                versions::ci_provider.eq(&source.ci_provider),
+                versions::published_with.eq(&source.published_with),
+            ))
+            .execute(conn)
     }
 
     pub fn record_readme_rendering(&self, conn: &PgConnection) -> QueryResult<usize> {
@@ -168,16 +316,7 @@ impl NewVersion {
 
     fn validate_license(&mut self, license_file: Option<&str>) -> CargoResult<()> {
         if let Some(ref license) = self.license {
-            for part in license.split('/') {
-                license_exprs::validate_license_expr(part).map_err(|e| {
-                    human(&format_args!(
-                        "{}; see http://opensource.org/licenses \
-                         for options, and http://spdx.org/licenses/ \
-                         for their identifiers",
-                        e
-                    ))
-                })?;
-            }
+            license::parse(license)?;
         } else if license_file.is_some() {
             // If no license is given, but a license file is given, flag this
             // crate as having a nonstandard license. Note that we don't
@@ -200,6 +339,14 @@ impl Queryable<versions::SqlType, Pg> for Version {
         Option<String>,
         bool,
         Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        serde_json::Value,
+        Option<i32>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
     );
 
     fn build(row: Self::Row) -> Self {
@@ -216,6 +363,14 @@ impl Queryable<versions::SqlType, Pg> for Version {
             features,
             yanked: row.7,
             license: row.8,
+            checksum: row.9,
+            links: row.10,
+            edition: row.11,
+            binaries: serde_json::from_value(row.12).unwrap_or_default(),
+            published_by: row.13,
+            cargo_version: row.14,
+            ci_provider: row.15,
+            published_with: row.16,
         }
     }
 }
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use diesel;
+use diesel::prelude::*;
+
+use models::Version;
+use schema::version_files;
+use uploaders::TarballFile;
+use views::{EncodableFileChange, EncodableVersionFile};
+
+/// How many files are inserted at once, to stay below the limit of bind
+/// parameters of a query.
+const INSERT_BATCH_SIZE: usize = 5000;
+
+/// A file of the crate file of a version, listed when it's published. Unknown
+/// for the versions published before files were listed.
+#[derive(Clone, Debug, PartialEq, Queryable, Identifiable, Associations, Insertable)]
+#[primary_key(version_id, path)]
+#[belongs_to(Version)]
+pub struct VersionFile {
+    pub version_id: i32,
+    /// The path of the file relative to the directory of the crate, e.g.
+    /// `src/lib.rs`.
+    pub path: String,
+    pub size: i64,
+    /// The SHA-256 checksum of the file, in hex.
+    pub sha256: String,
+}
+
+/// The files added, removed and changed from a version to another.
+#[derive(Debug, Default)]
+pub struct VersionDiff {
+    pub added: Vec<VersionFile>,
+    pub removed: Vec<VersionFile>,
+    /// The files changed, as they were and as they are.
+    pub changed: Vec<(VersionFile, VersionFile)>,
+}
+
+impl VersionFile {
+    pub fn save_all(
+        conn: &PgConnection,
+        version_id: i32,
+        files: &[TarballFile],
+    ) -> QueryResult<()> {
+        let files = files
+            .iter()
+            .map(|file| VersionFile {
+                version_id,
+                path: file.path.clone(),
+                size: file.size as i64,
+                sha256: file.sha256.clone(),
+            })
+            .collect::<Vec<_>>();
+        for batch in files.chunks(INSERT_BATCH_SIZE) {
+            diesel::insert_into(version_files::table)
+                .values(batch)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the files of the version ordered by path, or `None` if they
+    /// weren't listed when it was published.
+    pub fn for_version(
+        conn: &PgConnection,
+        version: &Version,
+    ) -> QueryResult<Option<Vec<VersionFile>>> {
+        let files = VersionFile::belonging_to(version)
+            .order(version_files::path)
+            .load::<VersionFile>(conn)?;
+        // Every crate file has at least a `Cargo.toml`
+        Ok(if files.is_empty() { None } else { Some(files) })
+    }
+
+    /// Compares the files of two versions, ordered by path.
+    pub fn diff(from: Vec<VersionFile>, to: Vec<VersionFile>) -> VersionDiff {
+        let mut from = from.into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect::<BTreeMap<_, _>>();
+        let mut diff = VersionDiff::default();
+        for file in to {
+            match from.remove(&file.path) {
+                None => diff.added.push(file),
+                Some(old) => {
+                    if old.size != file.size || old.sha256 != file.sha256 {
+                        diff.changed.push((old, file));
+                    }
+                }
+            }
+        }
+        diff.removed = from.into_iter().map(|(_, file)| file).collect();
+        diff
+    }
+
+    pub fn encodable(self) -> EncodableVersionFile {
+        EncodableVersionFile {
+            path: self.path,
+            size: self.size,
+            sha256: self.sha256,
+        }
+    }
+
+    pub fn encodable_change(from: VersionFile, to: VersionFile) -> EncodableFileChange {
+        EncodableFileChange {
+            path: to.path,
+            from_size: from.size,
+            to_size: to.size,
+            from_sha256: from.sha256,
+            to_sha256: to.sha256,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VersionFile;
+
+    fn file(path: &str, sha256: &str) -> VersionFile {
+        VersionFile {
+            version_id: 1,
+            path: path.into(),
+            size: 10,
+            sha256: sha256.into(),
+        }
+    }
+
+    #[test]
+    fn files_are_diffed_by_path() {
+        let diff = VersionFile::diff(
+            vec![file("Cargo.toml", "a"), file("src/lib.rs", "b"), file("build.rs", "c")],
+            vec![file("Cargo.toml", "a"), file("src/lib.rs", "d"), file("src/main.rs", "e")],
+        );
+        assert_eq!(diff.added, [file("src/main.rs", "e")]);
+        assert_eq!(diff.removed, [file("build.rs", "c")]);
+        assert_eq!(diff.changed, [(file("src/lib.rs", "b"), file("src/lib.rs", "d"))]);
+    }
+}
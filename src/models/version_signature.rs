@@ -0,0 +1,41 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+
+use models::Version;
+use schema::version_signatures;
+
+/// The longest signature accepted, in bytes. ASCII armored signatures are
+/// well under a kilobyte.
+pub const MAX_SIGNATURE_SIZE: usize = 16 * 1024;
+
+/// The detached signature of the crate file of a version, sent along with it
+/// by its publisher. The registry doesn't verify it, since it doesn't know the
+/// keys of the publishers, but serves it so that others can.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations)]
+#[primary_key(version_id)]
+#[belongs_to(Version)]
+pub struct VersionSignature {
+    pub version_id: i32,
+    pub signature: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionSignature {
+    pub fn save(conn: &PgConnection, version: &Version, signature: &str) -> QueryResult<()> {
+        diesel::insert_into(version_signatures::table)
+            .values((
+                version_signatures::version_id.eq(version.id),
+                version_signatures::signature.eq(signature),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn find(conn: &PgConnection, version: &Version) -> QueryResult<Option<VersionSignature>> {
+        version_signatures::table
+            .find(version.id)
+            .first(conn)
+            .optional()
+    }
+}
@@ -0,0 +1,40 @@
+use diesel;
+use diesel::prelude::*;
+
+use models::Version;
+use schema::version_sources;
+
+/// The readme and the `Cargo.toml` of a version as published, kept so that
+/// they can be served without unpacking its crate file. Unknown for the
+/// versions published before they were kept.
+#[derive(Clone, Debug, Queryable, Identifiable, Associations, Insertable, AsChangeset)]
+#[primary_key(version_id)]
+#[belongs_to(Version)]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct VersionSource {
+    pub version_id: i32,
+    /// The readme as written, usually in Markdown.
+    pub readme: Option<String>,
+    /// The path of the readme in the crate, e.g. `README.md`.
+    pub readme_file: Option<String>,
+    pub manifest: Option<String>,
+}
+
+impl VersionSource {
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(version_sources::table)
+            .values(self)
+            .on_conflict(version_sources::version_id)
+            .do_update()
+            .set(self)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn find(conn: &PgConnection, version: &Version) -> QueryResult<Option<VersionSource>> {
+        version_sources::table
+            .find(version.id)
+            .first(conn)
+            .optional()
+    }
+}
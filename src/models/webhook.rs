@@ -0,0 +1,78 @@
+use chrono::NaiveDateTime;
+use diesel;
+use diesel::prelude::*;
+use serde_json::Value;
+
+use models::Crate;
+use schema::{crate_webhooks, webhook_deliveries};
+
+/// A URL notified of the publications and yanks of a crate's versions.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations, Serialize)]
+#[belongs_to(Crate)]
+#[table_name = "crate_webhooks"]
+pub struct CrateWebhook {
+    pub id: i32,
+    #[serde(skip)]
+    pub crate_id: i32,
+    pub url: String,
+    /// The key the payloads are signed with, only shown when the webhook is created.
+    #[serde(skip)]
+    pub secret: String,
+    #[serde(with = "::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateWebhook {
+    pub fn create(conn: &PgConnection, krate: &Crate, url: &str) -> QueryResult<CrateWebhook> {
+        diesel::insert_into(crate_webhooks::table)
+            .values((
+                crate_webhooks::crate_id.eq(krate.id),
+                crate_webhooks::url.eq(url),
+            ))
+            .get_result(conn)
+    }
+
+    /// Queues a delivery of the event to every webhook of the crate. The
+    /// deliveries are sent by `webhooks::deliver_pending`.
+    pub fn enqueue(
+        conn: &PgConnection,
+        krate: &Crate,
+        event: &str,
+        version: &str,
+    ) -> QueryResult<usize> {
+        let webhooks = CrateWebhook::belonging_to(krate).load::<CrateWebhook>(conn)?;
+        let payload = json!({
+            "event": event,
+            "crate": krate.name,
+            "version": version,
+        });
+        let deliveries = webhooks
+            .iter()
+            .map(|webhook| {
+                (
+                    webhook_deliveries::webhook_id.eq(webhook.id),
+                    webhook_deliveries::event.eq(event),
+                    webhook_deliveries::payload.eq(&payload),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(webhook_deliveries::table)
+            .values(&deliveries)
+            .execute(conn)
+    }
+}
+
+/// A notification of an event sent, or yet to be sent, to a webhook.
+#[derive(Clone, Debug, PartialEq, Identifiable, Queryable, Associations)]
+#[belongs_to(CrateWebhook, foreign_key = "webhook_id")]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: Value,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
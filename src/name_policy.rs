@@ -0,0 +1,118 @@
+//! The names new crates can be published with, for internal registries.
+//!
+//! crates.io accepts the names of at most 64 ASCII letters, digits, `-` and
+//! `_` starting with a letter. Deployments can lower the length limit, reject
+//! the names that only differ from an existing crate by their `-` and `_`,
+//! like `foobar` and `foo-bar`, and reject the names matching a denylist.
+//! Crates that already exist keep being published under their name.
+
+use regex::Regex;
+
+use models::krate::MAX_NAME_LENGTH;
+use util::{human, CargoResult};
+
+#[derive(Clone, Debug)]
+pub struct NamePolicy {
+    /// The longest name accepted, can't be more than `MAX_NAME_LENGTH`.
+    pub max_length: usize,
+    /// Reject the names that only differ from an existing crate by their `-`
+    /// and `_`.
+    pub forbid_confusable: bool,
+    /// The names matching this expression are rejected.
+    pub denylist: Option<Regex>,
+}
+
+impl Default for NamePolicy {
+    fn default() -> NamePolicy {
+        NamePolicy {
+            max_length: MAX_NAME_LENGTH,
+            forbid_confusable: false,
+            denylist: None,
+        }
+    }
+}
+
+impl NamePolicy {
+    /// Checks the name of a new crate, returning why it isn't allowed. Names
+    /// confusable with existing crates are checked by `NewCrate::validate`.
+    pub fn check(&self, name: &str) -> CargoResult<()> {
+        match name.chars().next() {
+            None => return Err(human("the crate name can't be empty")),
+            Some(c) if !c.is_ascii() || !c.is_alphabetic() => {
+                return Err(human(&format_args!(
+                    "the crate name `{}` must start with a letter",
+                    name
+                )))
+            }
+            Some(_) => {}
+        }
+        let valid_char = |c: char| c.is_ascii() && (c.is_alphanumeric() || c == '-' || c == '_');
+        if !name.chars().all(valid_char) {
+            return Err(human(&format_args!(
+                "the crate name `{}` can only contain letters, numbers, `-` and `_`",
+                name
+            )));
+        }
+        let max_length = self.max_length.min(MAX_NAME_LENGTH);
+        if name.len() > max_length {
+            return Err(human(&format_args!(
+                "the crate name `{}` is too long, the maximum length is {} characters",
+                name, max_length
+            )));
+        }
+        if let Some(ref denylist) = self.denylist {
+            if denylist.is_match(name) {
+                return Err(human(&format_args!(
+                    "the crate name `{}` isn't allowed on this registry",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the name lowercased without its `-` and `_`, which is the same
+    /// for all the names confusable with it.
+    pub fn squash(name: &str) -> String {
+        name.to_lowercase().replace('-', "").replace('_', "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NamePolicy;
+    use regex::Regex;
+
+    #[test]
+    fn names_are_checked() {
+        let policy = NamePolicy::default();
+        assert!(policy.check("foo_bar-2").is_ok());
+        assert!(policy.check("").is_err());
+        assert!(policy.check("2foo").is_err());
+        assert!(policy.check("_foo").is_err());
+        assert!(policy.check("foo.bar").is_err());
+        assert!(policy.check("fóo").is_err());
+        assert!(policy.check(&"a".repeat(64)).is_ok());
+        assert!(policy.check(&"a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn names_can_be_limited_and_denied() {
+        let policy = NamePolicy {
+            max_length: 8,
+            denylist: Some(Regex::new("^(rust|cargo)[-_]").unwrap()),
+            ..NamePolicy::default()
+        };
+        assert!(policy.check("foo").is_ok());
+        assert!(policy.check("foo_barbaz").is_err());
+        assert!(policy.check("rust-foo").is_err());
+        assert!(policy.check("rustfoo").is_ok());
+    }
+
+    #[test]
+    fn confusable_names_squash_the_same() {
+        assert_eq!(NamePolicy::squash("Foo-Bar"), NamePolicy::squash("foo_bar"));
+        assert_eq!(NamePolicy::squash("foo-bar"), NamePolicy::squash("foobar"));
+        assert_ne!(NamePolicy::squash("foo-bar"), NamePolicy::squash("foo-baz"));
+    }
+}
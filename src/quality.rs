@@ -0,0 +1,27 @@
+//! Scoring of the quality of crates, see `Crate::quality_score`. The scores
+//! are recomputed periodically and boost the relevance of search results.
+
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+use models::Crate;
+use util::CargoResult;
+use App;
+
+fn run(app: &App) -> CargoResult<usize> {
+    let conn = app.diesel_database.get()?;
+    Crate::update_quality_scores(&conn)
+}
+
+/// Spawns a thread recomputing the quality score of every crate every
+/// `interval`.
+pub fn spawn_quality_task(app: Arc<App>, interval: time::Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        match run(&app) {
+            Ok(scored) => info!("updated the quality score of {} crates", scored),
+            Err(e) => error!("failed to update the quality scores: {}", e),
+        }
+        thread::sleep(interval);
+    })
+}
@@ -0,0 +1,56 @@
+//! Reconciliation of the `crates_cnt` columns of keywords and categories.
+//!
+//! The counts are maintained by triggers on `crates_keywords` and
+//! `crates_categories`, but drift when rows are changed around them, e.g. by
+//! manual deletions. This recomputes them from scratch, either periodically
+//! or on demand through `POST /admin/jobs/recount`.
+
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+use diesel::prelude::*;
+
+use models::{Category, Keyword};
+use util::CargoResult;
+use App;
+
+/// The number of keywords and categories whose count was corrected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recount {
+    pub keywords: usize,
+    pub categories: usize,
+}
+
+/// Recomputes the counts of every keyword and category in one transaction.
+pub fn recount(conn: &PgConnection) -> QueryResult<Recount> {
+    conn.transaction(|| {
+        Ok(Recount {
+            keywords: Keyword::recount_crates(conn)?,
+            categories: Category::recount_crates(conn)?,
+        })
+    })
+}
+
+fn run(app: &App) -> CargoResult<Recount> {
+    let conn = app.diesel_database.get()?;
+    Ok(recount(&conn)?)
+}
+
+/// Spawns a thread recomputing the counts every `interval`.
+pub fn spawn_recount_task(app: Arc<App>, interval: time::Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        match run(&app) {
+            Ok(Recount {
+                keywords: 0,
+                categories: 0,
+            }) => {}
+            Ok(r) => info!(
+                "corrected the crate counts of {} keywords and {} categories",
+                r.keywords, r.categories
+            ),
+            Err(e) => error!("failed to recount the crates of keywords and categories: {}", e),
+        }
+    })
+}
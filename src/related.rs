@@ -0,0 +1,27 @@
+//! Recommendation of the crates related to each crate, see
+//! `RelatedCrate::recompute`. The recommendations are recomputed
+//! periodically.
+
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+use models::RelatedCrate;
+use util::CargoResult;
+use App;
+
+fn run(app: &App) -> CargoResult<usize> {
+    let conn = app.diesel_database.get()?;
+    Ok(RelatedCrate::recompute(&conn)?)
+}
+
+/// Spawns a thread recomputing the related crates every `interval`.
+pub fn spawn_related_task(app: Arc<App>, interval: time::Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        match run(&app) {
+            Ok(recorded) => info!("recorded {} related crates", recorded),
+            Err(e) => error!("failed to recompute the related crates: {}", e),
+        }
+        thread::sleep(interval);
+    })
+}
@@ -1,30 +1,93 @@
 use std::error::Error;
+use std::io::Cursor;
 use std::sync::Arc;
 
 use conduit::{Handler, Request, Response};
 use conduit_git_http_backend;
 use conduit_router::{RequestParams, RouteBuilder};
+use serde_json;
 
 use controllers::*;
-use util::errors::{std_error, CargoError, CargoResult, NotFound};
+use db::RequestTransaction;
+use middleware::app::RequestApp;
+use util::errors::{std_error, CargoError, CargoResult, NotFound, ReadOnlyMode};
 use util::RequestProxy;
+use views;
 use {App, Env};
 
 pub fn build_router(app: &App) -> R404 {
+    let api_router = Arc::new(R404(build_api_router()));
+
+    let mut router = RouteBuilder::new();
+
+    // Mount the router under the /api/v1 path so we're at least somewhat at the
+    // liberty to change things in the future!
+    router.get("/api/v1/*path", R(Arc::clone(&api_router)));
+    router.put("/api/v1/*path", R(Arc::clone(&api_router)));
+    router.post("/api/v1/*path", R(Arc::clone(&api_router)));
+    router.head("/api/v1/*path", R(Arc::clone(&api_router)));
+    router.delete("/api/v1/*path", R(Arc::clone(&api_router)));
+
+    // The same routes with consistent responses, see `views::v2`
+    router.get("/api/v2/*path", V2(Arc::clone(&api_router)));
+    router.put("/api/v2/*path", V2(Arc::clone(&api_router)));
+    router.post("/api/v2/*path", V2(Arc::clone(&api_router)));
+    router.head("/api/v2/*path", V2(Arc::clone(&api_router)));
+    router.delete("/api/v2/*path", V2(api_router));
+
+    // The public key the commits of the git index are signed with
+    router.get("/index_signing_key.pem", C(site_metadata::index_signing_key));
+
+    // The OpenAPI description of the routes mounted under /api/v1
+    router.get("/api/openapi.json", C(site_metadata::openapi));
+
+    // The sitemaps of the crates and categories, for search engines
+    router.get("/sitemap.xml", C(site_metadata::sitemap_index));
+    router.get("/sitemaps/:name", C(site_metadata::sitemap));
+
+    // Health checks of the load balancers
+    router.get("/healthz", C(health::healthz));
+    router.get("/readyz", C(health::readyz));
+
+    // Prometheus metrics, only served when an authorization token is configured
+    router.get("/metrics", C(metrics::prometheus));
+
+    router.get("/authorize_url", C(user::session::github_authorize));
+    router.get("/authorize", C(user::session::github_access_token));
+    router.delete("/logout", C(user::session::logout));
+
+    // Only serve the local checkout of the git index in development mode.
+    // In production, for crates.io, cargo gets the index from
+    // https://github.com/rust-lang/crates.io-index directly.
+    if app.config.env == Env::Development {
+        let s = conduit_git_http_backend::Serve(app.git_repo_checkout.clone());
+        let s = Arc::new(s);
+        router.get("/git/index/*path", R(Arc::clone(&s)));
+        router.post("/git/index/*path", R(s));
+    }
+
+    R404(router)
+}
+
+/// Builds the router of the routes mounted under `/api/v1`.
+///
+/// Routes added here should also be documented in `api_doc::ROUTES`.
+fn build_api_router() -> RouteBuilder {
     let mut api_router = RouteBuilder::new();
 
     // Route used by both `cargo search` and the frontend
     api_router.get("/crates", C(krate::search::search));
 
     // Routes used by `cargo`
-    api_router.put("/crates/new", C(krate::publish::publish));
+    api_router.put("/crates/new", W(krate::publish::publish));
+    api_router.get("/crates/suggest", C(krate::search::suggest));
     api_router.get("/crates/:crate_id/owners", C(krate::owners::owners));
-    api_router.put("/crates/:crate_id/owners", C(krate::owners::add_owners));
-    api_router.delete("/crates/:crate_id/owners", C(krate::owners::remove_owners));
-    api_router.delete("/crates/:crate_id/:version/yank", C(version::yank::yank));
+    api_router.put("/crates/:crate_id/owners", W(krate::owners::add_owners));
+    api_router.delete("/crates/:crate_id/owners", W(krate::owners::remove_owners));
+    api_router.delete("/crates/:crate_id/:version/yank", W(version::yank::yank));
     api_router.put(
         "/crates/:crate_id/:version/unyank",
-        C(version::yank::unyank),
+        W(version::yank::unyank),
     );
     api_router.get(
         "/crates/:crate_id/:version/download",
@@ -42,6 +105,26 @@ pub fn build_router(app: &App) -> R404 {
         "/crates/:crate_id/:version/readme",
         C(krate::metadata::readme),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/readme_raw",
+        C(version::sources::readme_raw),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/readme_rendered",
+        C(version::sources::readme_rendered),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/manifest",
+        C(version::sources::manifest),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/signature",
+        C(version::sources::signature),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/files",
+        C(version::files::files),
+    );
     api_router.get(
         "/crates/:crate_id/:version/dependencies",
         C(version::metadata::dependencies),
@@ -54,76 +137,203 @@ pub fn build_router(app: &App) -> R404 {
         "/crates/:crate_id/:version/authors",
         C(version::metadata::authors),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/scan_results",
+        C(version::metadata::scan_results),
+    );
+    api_router.get("/crates/:crate_id/diff", C(version::files::diff));
     api_router.get(
         "/crates/:crate_id/downloads",
         C(krate::downloads::downloads),
     );
+    api_router.get(
+        "/crates/:crate_id/downloads.csv",
+        C(krate::downloads::downloads_csv),
+    );
+    api_router.get(
+        "/crates/:crate_id/downloads/total",
+        C(krate::downloads::downloads_total),
+    );
+    api_router.get(
+        "/crates/:crate_id/downloads/by_semver",
+        C(krate::downloads::downloads_by_semver),
+    );
+    api_router.get(
+        "/crates/:crate_id/downloads/breakdown",
+        C(krate::downloads::downloads_breakdown),
+    );
     api_router.get("/crates/:crate_id/versions", C(krate::metadata::versions));
-    api_router.put("/crates/:crate_id/follow", C(krate::follow::follow));
-    api_router.delete("/crates/:crate_id/follow", C(krate::follow::unfollow));
+    api_router.get(
+        "/crates/:crate_id/versions/match",
+        C(krate::metadata::versions_match),
+    );
+    api_router.get(
+        "/crates/:crate_id/new_dependents",
+        C(krate::dependents::new_dependents),
+    );
+    api_router.get(
+        "/crates/:crate_id/new_dependents.rss",
+        C(krate::dependents::new_dependents_rss),
+    );
+    api_router.get("/crates/:crate_id/related", C(krate::related::related));
+    api_router.put("/crates/:crate_id/follow", W(krate::follow::follow));
+    api_router.delete("/crates/:crate_id/follow", W(krate::follow::unfollow));
     api_router.get("/crates/:crate_id/following", C(krate::follow::following));
+    api_router.put("/crates/:crate_id/keywords", C(keyword::update_crate));
+    api_router.put(
+        "/crates/:crate_id/deprecate",
+        C(krate::deprecation::deprecate),
+    );
+    api_router.delete(
+        "/crates/:crate_id/deprecate",
+        C(krate::deprecation::undeprecate),
+    );
+    api_router.put(
+        "/crates/:crate_id/maintenance",
+        C(krate::deprecation::set_maintenance_status),
+    );
+    api_router.get("/crates/:crate_id/logo", C(krate::logo::show));
+    api_router.put("/crates/:crate_id/logo", C(krate::logo::upload));
+    api_router.delete("/crates/:crate_id/logo", C(krate::logo::delete));
+    api_router.get(
+        "/crates/:crate_id/trusted_publishers",
+        C(trusted_publishing::list),
+    );
+    api_router.put(
+        "/crates/:crate_id/trusted_publishers",
+        C(trusted_publishing::create),
+    );
+    api_router.delete(
+        "/crates/:crate_id/trusted_publishers/:id",
+        C(trusted_publishing::delete),
+    );
+    api_router.get("/crates/:crate_id/audit", C(krate::audit::audit));
+    api_router.get(
+        "/crates/:crate_id/metadata_changes",
+        C(krate::audit::metadata_changes),
+    );
+    api_router.get("/crates/:crate_id/webhooks", C(webhooks::list));
+    api_router.put("/crates/:crate_id/webhooks", C(webhooks::create));
+    api_router.delete("/crates/:crate_id/webhooks/:id", C(webhooks::delete));
+    api_router.get("/crates/:crate_id/lists", C(crate_list::list_for_crate));
     api_router.get("/crates/:crate_id/owner_team", C(krate::owners::owner_team));
     api_router.get("/crates/:crate_id/owner_user", C(krate::owners::owner_user));
+    api_router.get("/crates/:crate_id/index", C(krate::metadata::index));
     api_router.get(
         "/crates/:crate_id/reverse_dependencies",
         C(krate::metadata::reverse_dependencies),
     );
+    api_router.get(
+        "/crates/:crate_id/dependents/version_requirements",
+        C(krate::metadata::dependents_by_requirement),
+    );
     api_router.get("/keywords", C(keyword::index));
+    api_router.get("/keywords/trending", C(keyword::trending));
     api_router.get("/keywords/:keyword_id", C(keyword::show));
     api_router.get("/categories", C(category::index));
     api_router.get("/categories/:category_id", C(category::show));
+    api_router.get("/categories/:category_id/crates", C(category::crates));
     api_router.get("/category_slugs", C(category::slugs));
     api_router.get("/users/:user_id", C(user::other::show));
     api_router.put("/users/:user_id", C(user::me::update_user));
     api_router.get("/users/:user_id/stats", C(user::other::stats));
+    api_router.get("/users/:user_id/lists", C(crate_list::list_for_user));
     api_router.get("/teams/:team_id", C(team::show_team));
+    api_router.get("/teams/:team_id/crates", C(team::crates));
+    api_router.get("/teams/:team_id/tokens", C(team::tokens));
+    api_router.put("/teams/:team_id/tokens", C(team::new_token));
+    api_router.delete("/teams/:team_id/tokens/:id", C(team::revoke_token));
     api_router.get("/me", C(user::me::me));
+    api_router.delete("/me", C(user::me::delete));
     api_router.get("/me/updates", C(user::me::updates));
+    api_router.get("/me/crates", C(user::me::crates));
+    api_router.get("/me/activity", C(user::me::activity));
+    api_router.put("/me/email", C(user::me::update_email));
+    api_router.put("/me/email/resend", C(user::me::resend_email));
+    api_router.put("/me/settings", C(user::me::update_settings));
+    api_router.post("/me/2fa", C(user::two_factor::begin));
+    api_router.put("/me/2fa", C(user::two_factor::enable));
+    api_router.delete("/me/2fa", C(user::two_factor::disable));
+    api_router.put("/me/2fa/verify", C(user::two_factor::verify));
     api_router.get("/me/tokens", C(token::list));
     api_router.put("/me/tokens", C(token::new));
+    api_router.put("/me/tokens/:id", C(token::update));
     api_router.delete("/me/tokens/:id", C(token::revoke));
+    api_router.put("/me/lists", C(crate_list::new));
+    api_router.get("/lists/:list_id", C(crate_list::show));
+    api_router.put("/lists/:list_id", C(crate_list::update));
+    api_router.delete("/lists/:list_id", C(crate_list::delete));
+    api_router.put("/lists/:list_id/crates/:crate_id", C(crate_list::add_crate));
+    api_router.delete(
+        "/lists/:list_id/crates/:crate_id",
+        C(crate_list::remove_crate),
+    );
     api_router.get(
         "/me/crate_owner_invitations",
         C(crate_owner_invitation::list),
     );
     api_router.put(
         "/me/crate_owner_invitations/:crate_id",
-        C(crate_owner_invitation::handle_invite),
+        W(crate_owner_invitation::handle_invite),
     );
     api_router.get("/summary", C(krate::metadata::summary));
+    api_router.get("/spotlight", C(spotlight::show));
+    api_router.get("/graphql", C(graphql::query_from_url));
+    api_router.post("/graphql", C(graphql::query));
     api_router.put("/confirm/:email_token", C(user::me::confirm_user_email));
+    api_router.put("/confirm_publish/:token", C(user::me::confirm_publish));
+    api_router.get("/confirm_publish/:token", C(user::me::confirm_publish_link));
+    api_router.put("/admin/crates/:crate_id/approve", C(admin::approve_crate));
+    api_router.put("/admin/crates/:crate_id/rename", C(admin::rename_crate));
+    api_router.put("/admin/crates/:crate_id/lock", C(admin::lock_crate));
+    api_router.delete("/admin/crates/:crate_id/lock", C(admin::unlock_crate));
+    api_router.put(
+        "/admin/crates/:crate_id/transfer",
+        C(admin::schedule_transfer),
+    );
+    api_router.delete(
+        "/admin/crates/:crate_id/transfer",
+        C(admin::cancel_transfer),
+    );
+    api_router.post(
+        "/admin/crates/:crate_id/sync_index",
+        C(admin::sync_index),
+    );
+    api_router.put("/admin/crates/:crate_id/spotlight", C(admin::feature_crate));
+    api_router.put(
+        "/admin/categories/:category_id/featured/:crate_id",
+        C(admin::feature_category_crate),
+    );
+    api_router.delete(
+        "/admin/categories/:category_id/featured/:crate_id",
+        C(admin::unfeature_category_crate),
+    );
+    api_router.delete("/admin/crates/:crate_id", C(admin::delete_crate));
+    api_router.get("/admin/deleted_crates", C(admin::deleted_crates));
+    api_router.put(
+        "/admin/deleted_crates/:id/restore",
+        C(admin::restore_crate),
+    );
+    api_router.get("/admin/keyword_aliases", C(admin::keyword_aliases));
+    api_router.put("/admin/keyword_aliases", C(admin::new_keyword_alias));
+    api_router.delete(
+        "/admin/keyword_aliases/:alias",
+        C(admin::delete_keyword_alias),
+    );
+    api_router.post("/admin/jobs/recount", C(admin::recount_crates));
+    api_router.post("/admin/downloads/ingest", C(admin::ingest_downloads));
+    api_router.get("/admin/read_only", C(admin::read_only));
+    api_router.put("/admin/read_only", C(admin::enable_read_only));
+    api_router.delete("/admin/read_only", C(admin::disable_read_only));
+    api_router.put("/trusted_publishing/tokens", C(trusted_publishing::exchange));
     api_router.put(
         "/users/:user_id/resend",
         C(user::me::regenerate_token_and_send),
     );
     api_router.get("/site_metadata", C(site_metadata::show_deployed_sha));
-    let api_router = Arc::new(R404(api_router));
-
-    let mut router = RouteBuilder::new();
-
-    // Mount the router under the /api/v1 path so we're at least somewhat at the
-    // liberty to change things in the future!
-    router.get("/api/v1/*path", R(Arc::clone(&api_router)));
-    router.put("/api/v1/*path", R(Arc::clone(&api_router)));
-    router.post("/api/v1/*path", R(Arc::clone(&api_router)));
-    router.head("/api/v1/*path", R(Arc::clone(&api_router)));
-    router.delete("/api/v1/*path", R(api_router));
-
-    router.get("/authorize_url", C(user::session::github_authorize));
-    router.get("/authorize", C(user::session::github_access_token));
-    router.delete("/logout", C(user::session::logout));
-
-    // Only serve the local checkout of the git index in development mode.
-    // In production, for crates.io, cargo gets the index from
-    // https://github.com/rust-lang/crates.io-index directly.
-    if app.config.env == Env::Development {
-        let s = conduit_git_http_backend::Serve(app.git_repo_checkout.clone());
-        let s = Arc::new(s);
-        router.get("/git/index/*path", R(Arc::clone(&s)));
-        router.post("/git/index/*path", R(s));
-    }
-
-    R404(router)
+    api_router.get("/announcements", C(announcement::list));
+    api_router.get("/updates/stream", C(updates::stream));
+    api_router
 }
 
 struct C(pub fn(&mut Request) -> CargoResult<Response>);
@@ -141,6 +351,22 @@ impl Handler for C {
     }
 }
 
+/// Handlers of routes that write, refused while the registry is in read-only
+/// mode, see `App::read_only`.
+struct W(pub fn(&mut Request) -> CargoResult<Response>);
+
+impl Handler for W {
+    fn call(&self, req: &mut Request) -> Result<Response, Box<Error + Send>> {
+        let read_only = req.db_conn()
+            .and_then(|conn| req.app().read_only(&conn))
+            .map_err(std_error)?;
+        if read_only {
+            return Ok(ReadOnlyMode.response().unwrap());
+        }
+        C(self.0).call(req)
+    }
+}
+
 struct R<H>(pub Arc<H>);
 
 impl<H: Handler> Handler for R<H> {
@@ -155,6 +381,47 @@ impl<H: Handler> Handler for R<H> {
     }
 }
 
+/// Serves the routes of a sub router like `R`, encoding their JSON responses
+/// for `/api/v2`.
+struct V2<H>(pub Arc<H>);
+
+impl<H: Handler> Handler for V2<H> {
+    fn call(&self, req: &mut Request) -> Result<Response, Box<Error + Send>> {
+        let V2(ref sub_router) = *self;
+        let mut response = R(Arc::clone(sub_router)).call(req)?;
+        let is_json = response
+            .headers
+            .get("Content-Type")
+            .and_then(|values| values.first())
+            .map_or(false, |value| value.starts_with("application/json"));
+        if !is_json {
+            return Ok(response);
+        }
+
+        let mut body = Vec::new();
+        response
+            .body
+            .write_body(&mut body)
+            .map_err(|e| Box::new(e) as Box<Error + Send>)?;
+        let (body, errors) = match serde_json::from_slice(&body) {
+            Ok(json) => views::v2::envelope(json),
+            Err(_) => {
+                response.body = Box::new(Cursor::new(body));
+                return Ok(response);
+            }
+        };
+        let body = serde_json::to_vec(&body).map_err(|e| Box::new(e) as Box<Error + Send>)?;
+        if errors && response.status.0 == 200 {
+            response.status = (400, "Bad Request");
+        }
+        response
+            .headers
+            .insert("Content-Length".to_string(), vec![body.len().to_string()]);
+        response.body = Box::new(Cursor::new(body));
+        Ok(response)
+    }
+}
+
 // Can't derive Debug because of RouteBuilder.
 #[allow(missing_debug_implementations)]
 pub struct R404(pub RouteBuilder);
@@ -228,4 +495,32 @@ mod tests {
                 .is_err()
         );
     }
+
+    #[test]
+    fn documented_routes_are_routed() {
+        use conduit::Method;
+
+        let router = build_api_router();
+        for route in ::api_doc::ROUTES {
+            let method = match route.method {
+                "get" => Method::Get,
+                "put" => Method::Put,
+                "post" => Method::Post,
+                "delete" => Method::Delete,
+                m => panic!("unexpected method {}", m),
+            };
+            let path = route
+                .path
+                .split('/')
+                .map(|s| if s.starts_with(':') { "foo" } else { s })
+                .collect::<Vec<_>>()
+                .join("/");
+            assert!(
+                router.recognize(&method, &path).is_ok(),
+                "{} {} is documented but not routed",
+                route.method,
+                route.path
+            );
+        }
+    }
 }
@@ -40,11 +40,11 @@ impl Bucket {
         }
     }
 
-    pub fn put<'a, 'b>(
+    pub fn put<'a, 'b, R: Read + 'b>(
         &self,
         easy: &'a mut Easy,
         path: &str,
-        mut content: &'b [u8],
+        mut content: R,
         content_type: &str,
         content_length: u64,
     ) -> Transfer<'a, 'b> {
@@ -108,6 +108,35 @@ impl Bucket {
         easy.transfer()
     }
 
+    /// Returns a URL granting read access to the object at `path` until
+    /// `expires`, a unix timestamp, through query string authentication.
+    pub fn presigned_url(&self, path: &str, expires: i64) -> String {
+        let path = if path.starts_with('/') {
+            &path[1..]
+        } else {
+            path
+        };
+        let string = format!("GET\n\n\n{}\n/{}/{}", expires, self.name, path);
+        let signature = self.sign(&string)
+            .replace('+', "%2B")
+            .replace('/', "%2F")
+            .replace('=', "%3D");
+        format!(
+            "{}://{}/{}?AWSAccessKeyId={}&Expires={}&Signature={}",
+            self.proto,
+            self.host(),
+            path,
+            self.access_key,
+            expires,
+            signature
+        )
+    }
+
+    /// Returns the URL of the root of the bucket.
+    pub fn url(&self) -> String {
+        format!("{}://{}/", self.proto, self.host())
+    }
+
     pub fn host(&self) -> String {
         format!(
             "{}.s3{}.amazonaws.com",
@@ -130,12 +159,13 @@ impl Bucket {
             headers = "",
             resource = format!("/{}/{}", self.name, path)
         );
-        let signature = {
-            let key = PKey::hmac(self.secret_key.as_bytes()).unwrap();
-            let mut signer = Signer::new(MessageDigest::sha1(), &key).unwrap();
-            signer.update(string.as_bytes()).unwrap();
-            encode(&signer.sign_to_vec().unwrap()[..])
-        };
-        format!("AWS {}:{}", self.access_key, signature)
+        format!("AWS {}:{}", self.access_key, self.sign(&string))
+    }
+
+    fn sign(&self, string: &str) -> String {
+        let key = PKey::hmac(self.secret_key.as_bytes()).unwrap();
+        let mut signer = Signer::new(MessageDigest::sha1(), &key).unwrap();
+        signer.update(string.as_bytes()).unwrap();
+        encode(&signer.sign_to_vec().unwrap()[..])
     }
 }
@@ -0,0 +1,203 @@
+//! This module implements scanning uploaded crate files with external scanners
+//! (e.g. anti-virus services) before accepting them.
+//!
+//! Every configured scanner receives the tarball in the body of a `POST`
+//! request and is expected to answer with a JSON object such as
+//! `{"blocked": true, "detail": "matched signature Foo"}`. The verdicts of all
+//! the scanners are then aggregated according to the configured `ScanPolicy`.
+
+use std::io::Read;
+use std::str;
+
+use curl::easy::{List, ReadError};
+use serde_json;
+
+use app::App;
+use uploaders::SpooledFile;
+use util::{internal, CargoResult, ChainError};
+
+/// An external service uploaded crate files are sent to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scanner {
+    pub name: String,
+    pub url: String,
+}
+
+impl Scanner {
+    /// Parses a list of scanners formatted as `name=url,other-name=other-url`.
+    pub fn parse_list(s: &str) -> Vec<Scanner> {
+        s.split(',')
+            .filter_map(|scanner| {
+                let mut parts = scanner.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(name), Some(url)) => Some(Scanner {
+                        name: name.trim().to_string(),
+                        url: url.trim().to_string(),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// How the verdicts of the scanners are combined to decide whether an upload
+/// is blocked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanPolicy {
+    /// The upload is blocked as soon as one scanner blocks it.
+    AnyBlock,
+    /// The upload is blocked if at least this many scanners block it.
+    Quorum(usize),
+}
+
+impl ScanPolicy {
+    /// Parses either `any-block` or `quorum:N`.
+    pub fn parse(s: &str) -> Option<ScanPolicy> {
+        if s == "any-block" {
+            return Some(ScanPolicy::AnyBlock);
+        }
+        if s.starts_with("quorum:") {
+            return s["quorum:".len()..].parse().ok().map(ScanPolicy::Quorum);
+        }
+        None
+    }
+
+    pub fn blocks(&self, results: &[ScanResult]) -> bool {
+        let blocked = results.iter().filter(|r| r.blocked).count();
+        match *self {
+            ScanPolicy::AnyBlock => blocked > 0,
+            ScanPolicy::Quorum(n) => blocked >= n,
+        }
+    }
+}
+
+/// The verdict of a single scanner.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ScanResult {
+    #[serde(skip_deserializing)]
+    pub scanner: String,
+    pub blocked: bool,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+/// Sends the tarball to every configured scanner and collects their verdicts.
+/// The tarball is streamed from its spooled file to each scanner.
+///
+/// A scanner that can't be reached or that sends an invalid response doesn't
+/// block the upload, but the failure is recorded in the detail of its result.
+pub fn scan(app: &App, tarball: &SpooledFile) -> Vec<ScanResult> {
+    app.config
+        .scanners
+        .iter()
+        .map(|scanner| {
+            send(app, scanner, tarball).unwrap_or_else(|e| {
+                println!("scanner {} failed: {}", scanner.name, e);
+                ScanResult {
+                    scanner: scanner.name.clone(),
+                    blocked: false,
+                    detail: Some(format!("scan failed: {}", e)),
+                }
+            })
+        })
+        .collect()
+}
+
+fn send(app: &App, scanner: &Scanner, tarball: &SpooledFile) -> CargoResult<ScanResult> {
+    let mut file = tarball.open()?;
+    let length = file.metadata()?.len();
+
+    let mut headers = List::new();
+    headers.append("Content-Type: application/x-tar").unwrap();
+    headers.append("Accept: application/json").unwrap();
+
+    let mut handle = app.handle();
+    handle.url(&scanner.url)?;
+    handle.post(true)?;
+    handle.post_field_size(length)?;
+    handle.http_headers(headers)?;
+
+    let mut data = Vec::new();
+    {
+        let mut transfer = handle.transfer();
+        transfer.read_function(|buf| file.read(buf).map_err(|_| ReadError::Abort))?;
+        transfer.write_function(|buf| {
+            data.extend_from_slice(buf);
+            Ok(buf.len())
+        })?;
+        transfer.perform()?;
+    }
+
+    match handle.response_code()? {
+        200 => {}
+        n => {
+            return Err(internal(&format_args!(
+                "didn't get a 200 result from the scanner, got {}",
+                n
+            )))
+        }
+    }
+
+    let json = str::from_utf8(&data)
+        .ok()
+        .chain_error(|| internal("the scanner didn't send a utf8-response"))?;
+    let mut result: ScanResult = serde_json::from_str(json)
+        .chain_error(|| internal("the scanner didn't send a valid json response"))?;
+    result.scanner = scanner.name.clone();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScanPolicy, ScanResult, Scanner};
+
+    fn result(blocked: bool) -> ScanResult {
+        ScanResult {
+            scanner: String::new(),
+            blocked,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn parse_scanner_list() {
+        assert_eq!(
+            Scanner::parse_list("foo=https://foo.example/scan, bar=http://bar.example"),
+            vec![
+                Scanner {
+                    name: "foo".into(),
+                    url: "https://foo.example/scan".into(),
+                },
+                Scanner {
+                    name: "bar".into(),
+                    url: "http://bar.example".into(),
+                },
+            ]
+        );
+        assert_eq!(Scanner::parse_list(""), vec![]);
+    }
+
+    #[test]
+    fn parse_policy() {
+        assert_eq!(ScanPolicy::parse("any-block"), Some(ScanPolicy::AnyBlock));
+        assert_eq!(ScanPolicy::parse("quorum:2"), Some(ScanPolicy::Quorum(2)));
+        assert_eq!(ScanPolicy::parse("quorum:two"), None);
+        assert_eq!(ScanPolicy::parse("majority"), None);
+    }
+
+    #[test]
+    fn any_block_policy() {
+        let policy = ScanPolicy::AnyBlock;
+        assert!(!policy.blocks(&[]));
+        assert!(!policy.blocks(&[result(false), result(false)]));
+        assert!(policy.blocks(&[result(false), result(true)]));
+    }
+
+    #[test]
+    fn quorum_policy() {
+        let policy = ScanPolicy::Quorum(2);
+        assert!(!policy.blocks(&[result(true), result(false)]));
+        assert!(policy.blocks(&[result(true), result(false), result(true)]));
+    }
+}
@@ -1,5 +1,46 @@
 #![allow(unused_imports)]
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `announcements` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    announcements (id) {
+        /// The `id` column of the `announcements` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `message` column of the `announcements` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        message -> Text,
+        /// The `created_at` column of the `announcements` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `starts_at` column of the `announcements` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        starts_at -> Timestamp,
+        /// The `ends_at` column of the `announcements` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        ends_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -44,6 +85,124 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         last_used_at -> Nullable<Timestamp>,
+        /// The `allowed_ips` column of the `api_tokens` table.
+        ///
+        /// Its SQL type is `Array<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        allowed_ips -> Array<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `audit_actions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    audit_actions (id) {
+        /// The `id` column of the `audit_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `audit_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `crate_id` column of the `audit_actions` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Nullable<Int4>,
+        /// The `crate_name` column of the `audit_actions` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_name -> Varchar,
+        /// The `version` column of the `audit_actions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version -> Nullable<Varchar>,
+        /// The `action` column of the `audit_actions` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        action -> Varchar,
+        /// The `detail` column of the `audit_actions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        detail -> Nullable<Varchar>,
+        /// The `created_at` column of the `audit_actions` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `background_migrations` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    background_migrations (name) {
+        /// The `name` column of the `background_migrations` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `last_id` column of the `background_migrations` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_id -> Int4,
+        /// The `processed` column of the `background_migrations` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        processed -> Int8,
+        /// The `total` column of the `background_migrations` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total -> Int8,
+        /// The `started_at` column of the `background_migrations` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        started_at -> Timestamp,
+        /// The `updated_at` column of the `background_migrations` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+        /// The `finished_at` column of the `background_migrations` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        finished_at -> Nullable<Timestamp>,
     }
 }
 
@@ -127,28 +286,40 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crate_downloads` table.
+    /// Representation of the `category_featured_crates` table.
     ///
     /// (Automatically generated by Diesel.)
-    crate_downloads (crate_id, date) {
-        /// The `crate_id` column of the `crate_downloads` table.
+    category_featured_crates (category_id, crate_id) {
+        /// The `category_id` column of the `category_featured_crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        category_id -> Int4,
+        /// The `crate_id` column of the `category_featured_crates` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-        /// The `downloads` column of the `crate_downloads` table.
+        /// The `position` column of the `category_featured_crates` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        downloads -> Int4,
-        /// The `date` column of the `crate_downloads` table.
+        position -> Int4,
+        /// The `created_by` column of the `category_featured_crates` table.
         ///
-        /// Its SQL type is `Date`.
+        /// Its SQL type is `Nullable<Int4>`.
         ///
         /// (Automatically generated by Diesel.)
-        date -> Date,
+        created_by -> Nullable<Int4>,
+        /// The `created_at` column of the `category_featured_crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
     }
 }
 
@@ -156,29 +327,23 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crate_owner_invitations` table.
+    /// Representation of the `category_renames` table.
     ///
     /// (Automatically generated by Diesel.)
-    crate_owner_invitations (invited_user_id, crate_id) {
-        /// The `invited_user_id` column of the `crate_owner_invitations` table.
-        ///
-        /// Its SQL type is `Int4`.
-        ///
-        /// (Automatically generated by Diesel.)
-        invited_user_id -> Int4,
-        /// The `invited_by_user_id` column of the `crate_owner_invitations` table.
+    category_renames (old_slug) {
+        /// The `old_slug` column of the `category_renames` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        invited_by_user_id -> Int4,
-        /// The `crate_id` column of the `crate_owner_invitations` table.
+        old_slug -> Varchar,
+        /// The `category_id` column of the `category_renames` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `created_at` column of the `crate_owner_invitations` table.
+        category_id -> Int4,
+        /// The `created_at` column of the `category_renames` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
@@ -191,52 +356,86 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crate_owners` table.
+    /// Representation of the `crate_dependents` table.
     ///
     /// (Automatically generated by Diesel.)
-    crate_owners (crate_id, owner_id, owner_kind) {
-        /// The `crate_id` column of the `crate_owners` table.
+    crate_dependents (crate_id, dependent_id) {
+        /// The `crate_id` column of the `crate_dependents` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-        /// The `owner_id` column of the `crate_owners` table.
+        /// The `dependent_id` column of the `crate_dependents` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        owner_id -> Int4,
-        /// The `created_at` column of the `crate_owners` table.
+        dependent_id -> Int4,
+        /// The `first_seen_at` column of the `crate_dependents` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `created_by` column of the `crate_owners` table.
+        first_seen_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_downloads` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_downloads (crate_id, date) {
+        /// The `crate_id` column of the `crate_downloads` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        created_by -> Nullable<Int4>,
-        /// The `deleted` column of the `crate_owners` table.
+        crate_id -> Int4,
+        /// The `downloads` column of the `crate_downloads` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        deleted -> Bool,
-        /// The `updated_at` column of the `crate_owners` table.
+        downloads -> Int4,
+        /// The `date` column of the `crate_downloads` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Date`.
         ///
         /// (Automatically generated by Diesel.)
-        updated_at -> Timestamp,
-        /// The `owner_kind` column of the `crate_owners` table.
+        date -> Date,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_freshness` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_freshness (crate_id) {
+        /// The `crate_id` column of the `crate_freshness` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        owner_kind -> Int4,
+        crate_id -> Int4,
+        /// The `freshness` column of the `crate_freshness` table.
+        ///
+        /// Its SQL type is `Float8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        freshness -> Float8,
+        /// The `computed_at` column of the `crate_freshness` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        computed_at -> Timestamp,
     }
 }
 
@@ -244,94 +443,1243 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates` table.
+    /// Representation of the `crate_list_items` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates (id) {
-        /// The `id` column of the `crates` table.
+    crate_list_items (list_id, crate_id) {
+        /// The `list_id` column of the `crate_list_items` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `name` column of the `crates` table.
+        list_id -> Int4,
+        /// The `crate_id` column of the `crate_list_items` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Varchar,
-        /// The `updated_at` column of the `crates` table.
+        crate_id -> Int4,
+        /// The `created_at` column of the `crate_list_items` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        updated_at -> Timestamp,
-        /// The `created_at` column of the `crates` table.
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_lists` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_lists (id) {
+        /// The `id` column of the `crate_lists` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `downloads` column of the `crates` table.
+        id -> Int4,
+        /// The `user_id` column of the `crate_lists` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        downloads -> Int4,
-        /// The `description` column of the `crates` table.
+        user_id -> Int4,
+        /// The `name` column of the `crate_lists` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        description -> Nullable<Varchar>,
-        /// The `homepage` column of the `crates` table.
+        name -> Varchar,
+        /// The `description` column of the `crate_lists` table.
         ///
         /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        homepage -> Nullable<Varchar>,
-        /// The `documentation` column of the `crates` table.
+        description -> Nullable<Varchar>,
+        /// The `created_at` column of the `crate_lists` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        documentation -> Nullable<Varchar>,
-        /// The `readme` column of the `crates` table.
+        created_at -> Timestamp,
+        /// The `updated_at` column of the `crate_lists` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        readme -> Nullable<Varchar>,
-        /// The `textsearchable_index_col` column of the `crates` table.
-        ///
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_metadata_changes` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_metadata_changes (id) {
+        /// The `id` column of the `crate_metadata_changes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `crate_metadata_changes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `version_id` column of the `crate_metadata_changes` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Nullable<Int4>,
+        /// The `field` column of the `crate_metadata_changes` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        field -> Varchar,
+        /// The `added` column of the `crate_metadata_changes` table.
+        ///
+        /// Its SQL type is `Array<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        added -> Array<Varchar>,
+        /// The `removed` column of the `crate_metadata_changes` table.
+        ///
+        /// Its SQL type is `Array<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        removed -> Array<Varchar>,
+        /// The `created_at` column of the `crate_metadata_changes` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_owner_invitations` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_owner_invitations (invited_user_id, crate_id) {
+        /// The `invited_user_id` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        invited_user_id -> Int4,
+        /// The `invited_by_user_id` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        invited_by_user_id -> Int4,
+        /// The `crate_id` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `created_at` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_owners` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_owners (crate_id, owner_id, owner_kind) {
+        /// The `crate_id` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `owner_id` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_id -> Int4,
+        /// The `created_at` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `created_by` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Int4>,
+        /// The `deleted` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deleted -> Bool,
+        /// The `updated_at` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+        /// The `owner_kind` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_kind -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_renames` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_renames (id) {
+        /// The `id` column of the `crate_renames` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `crate_renames` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `old_name` column of the `crate_renames` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        old_name -> Varchar,
+        /// The `new_name` column of the `crate_renames` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_name -> Varchar,
+        /// The `created_at` column of the `crate_renames` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `index_rewritten` column of the `crate_renames` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        index_rewritten -> Bool,
+        /// The `last_version_id` column of the `crate_renames` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_version_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_spotlights` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_spotlights (id) {
+        /// The `id` column of the `crate_spotlights` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `crate_spotlights` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `featured_on` column of the `crate_spotlights` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        featured_on -> Date,
+        /// The `rationale` column of the `crate_spotlights` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rationale -> Varchar,
+        /// The `created_by` column of the `crate_spotlights` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Int4>,
+        /// The `created_at` column of the `crate_spotlights` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_transfers` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_transfers (crate_id) {
+        /// The `crate_id` column of the `crate_transfers` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `claimant_id` column of the `crate_transfers` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        claimant_id -> Int4,
+        /// The `reason` column of the `crate_transfers` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Varchar,
+        /// The `transfer_at` column of the `crate_transfers` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        transfer_at -> Timestamp,
+        /// The `created_by` column of the `crate_transfers` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Int4>,
+        /// The `created_at` column of the `crate_transfers` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_webhooks` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_webhooks (id) {
+        /// The `id` column of the `crate_webhooks` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `crate_webhooks` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `url` column of the `crate_webhooks` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Varchar,
+        /// The `secret` column of the `crate_webhooks` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        secret -> Varchar,
+        /// The `created_at` column of the `crate_webhooks` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates (id) {
+        /// The `id` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `name` column of the `crates` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `updated_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+        /// The `created_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `downloads` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int4,
+        /// The `description` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Nullable<Varchar>,
+        /// The `homepage` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        homepage -> Nullable<Varchar>,
+        /// The `documentation` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        documentation -> Nullable<Varchar>,
+        /// The `readme` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme -> Nullable<Varchar>,
+        /// The `textsearchable_index_col` column of the `crates` table.
+        ///
         /// Its SQL type is `Tsvector`.
         ///
         /// (Automatically generated by Diesel.)
-        textsearchable_index_col -> Tsvector,
-        /// The `license` column of the `crates` table.
+        textsearchable_index_col -> Tsvector,
+        /// The `license` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        license -> Nullable<Varchar>,
+        /// The `repository` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository -> Nullable<Varchar>,
+        /// The `max_upload_size` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        max_upload_size -> Nullable<Int4>,
+        /// The `readme_file` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme_file -> Nullable<Varchar>,
+        /// The `publish_hold` column of the `crates` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        publish_hold -> Bool,
+        /// The `locked` column of the `crates` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        locked -> Bool,
+        /// The `lock_reason` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        lock_reason -> Nullable<Text>,
+        /// The `quality_score` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Float8>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        quality_score -> Nullable<Float8>,
+        /// The `deprecated` column of the `crates` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deprecated -> Bool,
+        /// The `deprecation_message` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deprecation_message -> Nullable<Varchar>,
+        /// The `alternative` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        alternative -> Nullable<Varchar>,
+        /// The `logo_path` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        logo_path -> Nullable<Varchar>,
+        /// The `max_version` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        max_version -> Nullable<Varchar>,
+        /// The `max_stable_version` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        max_stable_version -> Nullable<Varchar>,
+        /// The `maintenance_status` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        maintenance_status -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates_categories` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates_categories (crate_id, category_id) {
+        /// The `crate_id` column of the `crates_categories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `category_id` column of the `crates_categories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        category_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates_keywords` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates_keywords (crate_id, keyword_id) {
+        /// The `crate_id` column of the `crates_keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `keyword_id` column of the `crates_keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `deleted_crates` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    deleted_crates (id) {
+        /// The `id` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `name` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `reason` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Nullable<Varchar>,
+        /// The `deleted_by` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deleted_by -> Nullable<Int4>,
+        /// The `snapshot` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        snapshot -> Jsonb,
+        /// The `index_entries` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        index_entries -> Nullable<Text>,
+        /// The `deleted_at` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deleted_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `dependencies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    dependencies (id) {
+        /// The `id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `crate_id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `req` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        req -> Varchar,
+        /// The `optional` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        optional -> Bool,
+        /// The `default_features` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        default_features -> Bool,
+        /// The `features` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        features -> Array<Text>,
+        /// The `target` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        target -> Nullable<Varchar>,
+        /// The `kind` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `download_ingestions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    download_ingestions (batch_id) {
+        /// The `batch_id` column of the `download_ingestions` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        batch_id -> Varchar,
+        /// The `records` column of the `download_ingestions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        records -> Int4,
+        /// The `created_at` column of the `download_ingestions` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `emails` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    emails (id) {
+        /// The `id` column of the `emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `email` column of the `emails` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email -> Varchar,
+        /// The `verified` column of the `emails` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verified -> Bool,
+        /// The `token` column of the `emails` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token -> Text,
+        /// The `token_generated_at` column of the `emails` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token_generated_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `follows` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    follows (user_id, crate_id) {
+        /// The `user_id` column of the `follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `crate_id` column of the `follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `held_index_entries` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    held_index_entries (version_id) {
+        /// The `version_id` column of the `held_index_entries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `entry` column of the `held_index_entries` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        entry -> Jsonb,
+        /// The `reason` column of the `held_index_entries` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Varchar,
+        /// The `created_at` column of the `held_index_entries` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `index_outbox` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    index_outbox (version_id) {
+        /// The `version_id` column of the `index_outbox` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `entry` column of the `index_outbox` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        entry -> Jsonb,
+        /// The `created_at` column of the `index_outbox` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `attempts` column of the `index_outbox` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        attempts -> Int4,
+        /// The `last_error` column of the `index_outbox` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_error -> Nullable<Text>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `keyword_aliases` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    keyword_aliases (alias) {
+        /// The `alias` column of the `keyword_aliases` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        alias -> Varchar,
+        /// The `keyword_id` column of the `keyword_aliases` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword_id -> Int4,
+        /// The `created_at` column of the `keyword_aliases` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `keyword_snapshots` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    keyword_snapshots (keyword_id, date) {
+        /// The `keyword_id` column of the `keyword_snapshots` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword_id -> Int4,
+        /// The `date` column of the `keyword_snapshots` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `crates_cnt` column of the `keyword_snapshots` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates_cnt -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `keywords` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    keywords (id) {
+        /// The `id` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `keyword` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword -> Varchar,
+        /// The `crates_cnt` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates_cnt -> Int4,
+        /// The `created_at` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `metadata` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    metadata (total_downloads) {
+        /// The `total_downloads` column of the `metadata` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total_downloads -> Int8,
+        /// The `dependents_recorded_up_to` column of the `metadata` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        dependents_recorded_up_to -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `pending_publishes` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    pending_publishes (id) {
+        /// The `id` column of the `pending_publishes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `pending_publishes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `crate_name` column of the `pending_publishes` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_name -> Varchar,
+        /// The `token` column of the `pending_publishes` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token -> Text,
+        /// The `created_at` column of the `pending_publishes` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `confirmed_at` column of the `pending_publishes` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        confirmed_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `readme_renderings` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    readme_renderings (version_id) {
+        /// The `version_id` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `rendered_at` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rendered_at -> Timestamp,
+    }
+}
+
+table! {
+    /// Representation of the `recent_crate_downloads` view.
+    ///
+    /// This data represents the downloads in the last 90 days.
+    /// This view does not contain realtime data.
+    /// It is refreshed by the `update-downloads` script.
+    recent_crate_downloads (crate_id) {
+        /// The `crate_id` column of the `recent_crate_downloads` view.
+        ///
+        /// Its SQL type is `Integer`.
+        crate_id -> Integer,
+        /// The `downloads` column of the `recent_crate_downloads` table.
+        ///
+        /// Its SQL type is `BigInt`.
+        downloads -> BigInt,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `registry_dependencies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    registry_dependencies (id) {
+        /// The `id` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `registry` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        registry -> Varchar,
+        /// The `name` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `req` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        req -> Varchar,
+        /// The `optional` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        optional -> Bool,
+        /// The `default_features` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        default_features -> Bool,
+        /// The `features` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        features -> Array<Text>,
+        /// The `target` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        target -> Nullable<Varchar>,
+        /// The `kind` column of the `registry_dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `registry_flags` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    registry_flags (name) {
+        /// The `name` column of the `registry_flags` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `enabled` column of the `registry_flags` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        enabled -> Bool,
+        /// The `updated_at` column of the `registry_flags` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        license -> Nullable<Varchar>,
-        /// The `repository` column of the `crates` table.
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `related_crates` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    related_crates (crate_id, related_id) {
+        /// The `crate_id` column of the `related_crates` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        repository -> Nullable<Varchar>,
-        /// The `max_upload_size` column of the `crates` table.
+        crate_id -> Int4,
+        /// The `related_id` column of the `related_crates` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        max_upload_size -> Nullable<Int4>,
-        /// The `readme_file` column of the `crates` table.
+        related_id -> Int4,
+        /// The `score` column of the `related_crates` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        readme_file -> Nullable<Varchar>,
+        score -> Int4,
     }
 }
 
@@ -339,22 +1687,45 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates_categories` table.
+    /// Representation of the `reserved_crate_names` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates_categories (crate_id, category_id) {
-        /// The `crate_id` column of the `crates_categories` table.
+    reserved_crate_names (name) {
+        /// The `name` column of the `reserved_crate_names` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Text`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `category_id` column of the `crates_categories` table.
+        name -> Text,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `sitemaps` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    sitemaps (name) {
+        /// The `name` column of the `sitemaps` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        category_id -> Int4,
+        name -> Varchar,
+        /// The `body` column of the `sitemaps` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        body -> Text,
+        /// The `generated_at` column of the `sitemaps` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        generated_at -> Timestamp,
     }
 }
 
@@ -362,22 +1733,22 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates_keywords` table.
+    /// Representation of the `stale_crates` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates_keywords (crate_id, keyword_id) {
-        /// The `crate_id` column of the `crates_keywords` table.
+    stale_crates (crate_id) {
+        /// The `crate_id` column of the `stale_crates` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-        /// The `keyword_id` column of the `crates_keywords` table.
+        /// The `last_activity_at` column of the `stale_crates` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        keyword_id -> Int4,
+        last_activity_at -> Timestamp,
     }
 }
 
@@ -385,64 +1756,52 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `dependencies` table.
+    /// Representation of the `team_tokens` table.
     ///
     /// (Automatically generated by Diesel.)
-    dependencies (id) {
-        /// The `id` column of the `dependencies` table.
+    team_tokens (id) {
+        /// The `id` column of the `team_tokens` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `version_id` column of the `dependencies` table.
+        /// The `team_id` column of the `team_tokens` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `crate_id` column of the `dependencies` table.
+        team_id -> Int4,
+        /// The `created_by` column of the `team_tokens` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `req` column of the `dependencies` table.
+        created_by -> Int4,
+        /// The `token` column of the `team_tokens` table.
         ///
         /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        req -> Varchar,
-        /// The `optional` column of the `dependencies` table.
-        ///
-        /// Its SQL type is `Bool`.
-        ///
-        /// (Automatically generated by Diesel.)
-        optional -> Bool,
-        /// The `default_features` column of the `dependencies` table.
-        ///
-        /// Its SQL type is `Bool`.
-        ///
-        /// (Automatically generated by Diesel.)
-        default_features -> Bool,
-        /// The `features` column of the `dependencies` table.
+        token -> Varchar,
+        /// The `name` column of the `team_tokens` table.
         ///
-        /// Its SQL type is `Array<Text>`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        features -> Array<Text>,
-        /// The `target` column of the `dependencies` table.
+        name -> Varchar,
+        /// The `created_at` column of the `team_tokens` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        target -> Nullable<Varchar>,
-        /// The `kind` column of the `dependencies` table.
+        created_at -> Timestamp,
+        /// The `last_used_at` column of the `team_tokens` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Nullable<Timestamp>`.
         ///
         /// (Automatically generated by Diesel.)
-        kind -> Int4,
+        last_used_at -> Nullable<Timestamp>,
     }
 }
 
@@ -450,46 +1809,40 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `emails` table.
+    /// Representation of the `teams` table.
     ///
     /// (Automatically generated by Diesel.)
-    emails (id) {
-        /// The `id` column of the `emails` table.
+    teams (id) {
+        /// The `id` column of the `teams` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `user_id` column of the `emails` table.
-        ///
-        /// Its SQL type is `Int4`.
-        ///
-        /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `email` column of the `emails` table.
+        /// The `login` column of the `teams` table.
         ///
         /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        email -> Varchar,
-        /// The `verified` column of the `emails` table.
+        login -> Varchar,
+        /// The `github_id` column of the `teams` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        verified -> Bool,
-        /// The `token` column of the `emails` table.
+        github_id -> Int4,
+        /// The `name` column of the `teams` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        token -> Text,
-        /// The `token_generated_at` column of the `emails` table.
+        name -> Nullable<Varchar>,
+        /// The `avatar` column of the `teams` table.
         ///
-        /// Its SQL type is `Nullable<Timestamp>`.
+        /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        token_generated_at -> Nullable<Timestamp>,
+        avatar -> Nullable<Varchar>,
     }
 }
 
@@ -497,52 +1850,47 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `follows` table.
+    /// Representation of the `trusted_publishers` table.
     ///
     /// (Automatically generated by Diesel.)
-    follows (user_id, crate_id) {
-        /// The `user_id` column of the `follows` table.
+    trusted_publishers (id) {
+        /// The `id` column of the `trusted_publishers` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `crate_id` column of the `follows` table.
+        id -> Int4,
+        /// The `crate_id` column of the `trusted_publishers` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-    }
-}
-
-table! {
-    use diesel::sql_types::*;
-    use diesel_full_text_search::{TsVector as Tsvector};
-
-    /// Representation of the `keywords` table.
-    ///
-    /// (Automatically generated by Diesel.)
-    keywords (id) {
-        /// The `id` column of the `keywords` table.
+        /// The `repository_owner` column of the `trusted_publishers` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `keyword` column of the `keywords` table.
+        repository_owner -> Varchar,
+        /// The `repository_name` column of the `trusted_publishers` table.
         ///
         /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        keyword -> Varchar,
-        /// The `crates_cnt` column of the `keywords` table.
+        repository_name -> Varchar,
+        /// The `workflow_filename` column of the `trusted_publishers` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        workflow_filename -> Varchar,
+        /// The `created_by` column of the `trusted_publishers` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crates_cnt -> Int4,
-        /// The `created_at` column of the `keywords` table.
+        created_by -> Int4,
+        /// The `created_at` column of the `trusted_publishers` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
@@ -555,16 +1903,52 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `metadata` table.
+    /// Representation of the `trusted_publishing_tokens` table.
     ///
     /// (Automatically generated by Diesel.)
-    metadata (total_downloads) {
-        /// The `total_downloads` column of the `metadata` table.
+    trusted_publishing_tokens (id) {
+        /// The `id` column of the `trusted_publishing_tokens` table.
         ///
-        /// Its SQL type is `Int8`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        total_downloads -> Int8,
+        id -> Int4,
+        /// The `repository_owner` column of the `trusted_publishing_tokens` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository_owner -> Varchar,
+        /// The `repository_name` column of the `trusted_publishing_tokens` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository_name -> Varchar,
+        /// The `workflow_filename` column of the `trusted_publishing_tokens` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        workflow_filename -> Varchar,
+        /// The `token` column of the `trusted_publishing_tokens` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token -> Varchar,
+        /// The `expires_at` column of the `trusted_publishing_tokens` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        expires_at -> Timestamp,
+        /// The `created_at` column of the `trusted_publishing_tokens` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
     }
 }
 
@@ -572,57 +1956,52 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `readme_renderings` table.
+    /// Representation of the `two_factor_credentials` table.
     ///
     /// (Automatically generated by Diesel.)
-    readme_renderings (version_id) {
-        /// The `version_id` column of the `readme_renderings` table.
+    two_factor_credentials (user_id) {
+        /// The `user_id` column of the `two_factor_credentials` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `rendered_at` column of the `readme_renderings` table.
+        user_id -> Int4,
+        /// The `secret` column of the `two_factor_credentials` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        secret -> Varchar,
+        /// The `enabled_at` column of the `two_factor_credentials` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        enabled_at -> Nullable<Timestamp>,
+        /// The `created_at` column of the `two_factor_credentials` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        rendered_at -> Timestamp,
-    }
-}
-
-table! {
-    /// Representation of the `recent_crate_downloads` view.
-    ///
-    /// This data represents the downloads in the last 90 days.
-    /// This view does not contain realtime data.
-    /// It is refreshed by the `update-downloads` script.
-    recent_crate_downloads (crate_id) {
-        /// The `crate_id` column of the `recent_crate_downloads` view.
+        created_at -> Timestamp,
+        /// The `last_used_step` column of the `two_factor_credentials` table.
         ///
-        /// Its SQL type is `Integer`.
-        crate_id -> Integer,
-        /// The `downloads` column of the `recent_crate_downloads` table.
+        /// Its SQL type is `Nullable<Int8>`.
         ///
-        /// Its SQL type is `BigInt`.
-        downloads -> BigInt,
-    }
-}
-
-table! {
-    use diesel::sql_types::*;
-    use diesel_full_text_search::{TsVector as Tsvector};
-
-    /// Representation of the `reserved_crate_names` table.
-    ///
-    /// (Automatically generated by Diesel.)
-    reserved_crate_names (name) {
-        /// The `name` column of the `reserved_crate_names` table.
+        /// (Automatically generated by Diesel.)
+        last_used_step -> Nullable<Int8>,
+        /// The `failed_attempts` column of the `two_factor_credentials` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Text,
+        failed_attempts -> Int4,
+        /// The `locked_until` column of the `two_factor_credentials` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        locked_until -> Nullable<Timestamp>,
     }
 }
 
@@ -630,40 +2009,34 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `teams` table.
+    /// Representation of the `two_factor_recovery_codes` table.
     ///
     /// (Automatically generated by Diesel.)
-    teams (id) {
-        /// The `id` column of the `teams` table.
+    two_factor_recovery_codes (id) {
+        /// The `id` column of the `two_factor_recovery_codes` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `login` column of the `teams` table.
-        ///
-        /// Its SQL type is `Varchar`.
-        ///
-        /// (Automatically generated by Diesel.)
-        login -> Varchar,
-        /// The `github_id` column of the `teams` table.
+        /// The `user_id` column of the `two_factor_recovery_codes` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        github_id -> Int4,
-        /// The `name` column of the `teams` table.
+        user_id -> Int4,
+        /// The `code_hash` column of the `two_factor_recovery_codes` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Nullable<Varchar>,
-        /// The `avatar` column of the `teams` table.
+        code_hash -> Varchar,
+        /// The `used_at` column of the `two_factor_recovery_codes` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Nullable<Timestamp>`.
         ///
         /// (Automatically generated by Diesel.)
-        avatar -> Nullable<Varchar>,
+        used_at -> Nullable<Timestamp>,
     }
 }
 
@@ -717,6 +2090,24 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         gh_id -> Int4,
+        /// The `confirm_new_crates` column of the `users` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        confirm_new_crates -> Bool,
+        /// The `created_at` column of the `users` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `deleted_at` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deleted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -755,6 +2146,47 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_download_breakdowns` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_download_breakdowns (version_id, date, cargo_version, country) {
+        /// The `version_id` column of the `version_download_breakdowns` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `date` column of the `version_download_breakdowns` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `cargo_version` column of the `version_download_breakdowns` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        cargo_version -> Varchar,
+        /// The `country` column of the `version_download_breakdowns` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        country -> Varchar,
+        /// The `downloads` column of the `version_download_breakdowns` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int4,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -799,6 +2231,164 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         processed -> Bool,
+        /// The `yanked_downloads` column of the `version_downloads` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        yanked_downloads -> Int4,
+        /// The `unique_downloads` column of the `version_downloads` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        unique_downloads -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_files` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_files (version_id, path) {
+        /// The `version_id` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `path` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        path -> Varchar,
+        /// The `size` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        size -> Int8,
+        /// The `sha256` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        sha256 -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_scan_results` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_scan_results (id) {
+        /// The `id` column of the `version_scan_results` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `version_scan_results` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `scanner` column of the `version_scan_results` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        scanner -> Varchar,
+        /// The `blocked` column of the `version_scan_results` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        blocked -> Bool,
+        /// The `detail` column of the `version_scan_results` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        detail -> Nullable<Varchar>,
+        /// The `scanned_at` column of the `version_scan_results` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        scanned_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_signatures` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_signatures (version_id) {
+        /// The `version_id` column of the `version_signatures` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `signature` column of the `version_signatures` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        signature -> Text,
+        /// The `created_at` column of the `version_signatures` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_sources` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_sources (version_id) {
+        /// The `version_id` column of the `version_sources` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `readme` column of the `version_sources` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme -> Nullable<Text>,
+        /// The `readme_file` column of the `version_sources` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme_file -> Nullable<Varchar>,
+        /// The `manifest` column of the `version_sources` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        manifest -> Nullable<Text>,
     }
 }
 
@@ -864,52 +2454,237 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         license -> Nullable<Varchar>,
+        /// The `checksum` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checksum -> Nullable<Varchar>,
+        /// The `links` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        links -> Nullable<Varchar>,
+        /// The `edition` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        edition -> Nullable<Varchar>,
+        /// The `binaries` column of the `versions` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        binaries -> Jsonb,
+        /// The `published_by` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        published_by -> Nullable<Int4>,
+        /// The `cargo_version` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        cargo_version -> Nullable<Varchar>,
+        /// The `ci_provider` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        ci_provider -> Nullable<Varchar>,
+        /// The `published_with` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        published_with -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `webhook_deliveries` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    webhook_deliveries (id) {
+        /// The `id` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `webhook_id` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        webhook_id -> Int4,
+        /// The `event` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        event -> Varchar,
+        /// The `payload` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        payload -> Jsonb,
+        /// The `attempts` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        attempts -> Int4,
+        /// The `last_error` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_error -> Nullable<Varchar>,
+        /// The `delivered_at` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        delivered_at -> Nullable<Timestamp>,
+        /// The `created_at` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
     }
 }
 
 joinable!(api_tokens -> users (user_id));
+joinable!(audit_actions -> crates (crate_id));
+joinable!(audit_actions -> users (user_id));
+joinable!(category_featured_crates -> categories (category_id));
+joinable!(category_featured_crates -> crates (crate_id));
+joinable!(category_featured_crates -> users (created_by));
+joinable!(category_renames -> categories (category_id));
+joinable!(crate_dependents -> crates (dependent_id));
 joinable!(crate_downloads -> crates (crate_id));
+joinable!(crate_freshness -> crates (crate_id));
+joinable!(crate_list_items -> crate_lists (list_id));
+joinable!(crate_list_items -> crates (crate_id));
+joinable!(crate_lists -> users (user_id));
+joinable!(crate_metadata_changes -> crates (crate_id));
+joinable!(crate_metadata_changes -> versions (version_id));
 joinable!(crate_owner_invitations -> crates (crate_id));
 joinable!(crate_owners -> crates (crate_id));
 joinable!(crate_owners -> teams (owner_id));
 joinable!(crate_owners -> users (owner_id));
+joinable!(crate_renames -> crates (crate_id));
+joinable!(crate_renames -> versions (last_version_id));
+joinable!(crate_spotlights -> crates (crate_id));
+joinable!(crate_spotlights -> users (created_by));
+joinable!(crate_transfers -> crates (crate_id));
+joinable!(crate_webhooks -> crates (crate_id));
 joinable!(crates_categories -> categories (category_id));
 joinable!(crates_categories -> crates (crate_id));
 joinable!(crates_keywords -> crates (crate_id));
 joinable!(crates_keywords -> keywords (keyword_id));
+joinable!(deleted_crates -> users (deleted_by));
 joinable!(dependencies -> crates (crate_id));
 joinable!(dependencies -> versions (version_id));
 joinable!(emails -> users (user_id));
 joinable!(follows -> crates (crate_id));
 joinable!(follows -> users (user_id));
+joinable!(held_index_entries -> versions (version_id));
+joinable!(index_outbox -> versions (version_id));
+joinable!(keyword_aliases -> keywords (keyword_id));
+joinable!(keyword_snapshots -> keywords (keyword_id));
+joinable!(pending_publishes -> users (user_id));
 joinable!(readme_renderings -> versions (version_id));
 joinable!(recent_crate_downloads -> crates (crate_id));
+joinable!(registry_dependencies -> versions (version_id));
+joinable!(related_crates -> crates (related_id));
+joinable!(stale_crates -> crates (crate_id));
+joinable!(team_tokens -> teams (team_id));
+joinable!(team_tokens -> users (created_by));
+joinable!(trusted_publishers -> crates (crate_id));
+joinable!(trusted_publishers -> users (created_by));
+joinable!(two_factor_credentials -> users (user_id));
+joinable!(two_factor_recovery_codes -> users (user_id));
 joinable!(version_authors -> users (user_id));
 joinable!(version_authors -> versions (version_id));
+joinable!(version_download_breakdowns -> versions (version_id));
 joinable!(version_downloads -> versions (version_id));
+joinable!(version_files -> versions (version_id));
+joinable!(version_scan_results -> versions (version_id));
+joinable!(version_signatures -> versions (version_id));
+joinable!(version_sources -> versions (version_id));
 joinable!(versions -> crates (crate_id));
+joinable!(versions -> users (published_by));
+joinable!(webhook_deliveries -> crate_webhooks (webhook_id));
 
 allow_tables_to_appear_in_same_query!(
+    announcements,
     api_tokens,
+    audit_actions,
+    background_migrations,
     badges,
     categories,
+    category_featured_crates,
+    category_renames,
+    crate_dependents,
     crate_downloads,
+    crate_freshness,
+    crate_list_items,
+    crate_lists,
+    crate_metadata_changes,
     crate_owner_invitations,
     crate_owners,
+    crate_renames,
+    crate_spotlights,
+    crate_transfers,
+    crate_webhooks,
     crates,
     crates_categories,
     crates_keywords,
+    deleted_crates,
     dependencies,
+    download_ingestions,
     emails,
     follows,
+    held_index_entries,
+    index_outbox,
+    keyword_aliases,
+    keyword_snapshots,
     keywords,
     metadata,
+    pending_publishes,
     readme_renderings,
     recent_crate_downloads,
+    registry_dependencies,
+    registry_flags,
+    related_crates,
     reserved_crate_names,
+    sitemaps,
+    stale_crates,
+    team_tokens,
     teams,
+    trusted_publishers,
+    trusted_publishing_tokens,
+    two_factor_credentials,
+    two_factor_recovery_codes,
     users,
     version_authors,
+    version_download_breakdowns,
     version_downloads,
+    version_files,
+    version_scan_results,
+    version_signatures,
+    version_sources,
     versions,
+    webhook_deliveries,
 );
@@ -0,0 +1,27 @@
+//! Detection of the stale crates, the ones without a release or an action of
+//! their owners for a long time, see `Crate::update_stale`. The list is
+//! recomputed periodically.
+
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+use models::Crate;
+use util::CargoResult;
+use App;
+
+fn run(app: &App) -> CargoResult<usize> {
+    let conn = app.diesel_database.get()?;
+    Ok(Crate::update_stale(&conn)?)
+}
+
+/// Spawns a thread recomputing the list of stale crates every `interval`.
+pub fn spawn_stale_task(app: Arc<App>, interval: time::Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        match run(&app) {
+            Ok(stale) => info!("{} crates are stale", stale),
+            Err(e) => error!("failed to list the stale crates: {}", e),
+        }
+        thread::sleep(interval);
+    })
+}
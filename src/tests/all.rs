@@ -11,6 +11,7 @@ extern crate diesel;
 extern crate dotenv;
 extern crate flate2;
 extern crate git2;
+extern crate openssl;
 extern crate s3;
 extern crate semver;
 extern crate serde;
@@ -30,6 +31,7 @@ use std::sync::Arc;
 
 use cargo_registry::app::App;
 use cargo_registry::middleware::current_user::AuthenticationSource;
+use cargo_registry::scanning::ScanPolicy;
 use cargo_registry::Replica;
 use chrono::Utc;
 use conduit::{Method, Request};
@@ -90,19 +92,27 @@ struct Bad {
     errors: Vec<Error>,
 }
 
+mod announcement;
+mod background_migrations;
 mod badge;
 mod categories;
 mod category;
+mod crate_list;
 mod git;
+mod graphql;
+mod health;
 mod keyword;
 mod krate;
+mod metrics;
 mod owners;
 mod record;
 mod schema_details;
 mod team;
 mod token;
+mod trusted_publishing;
 mod user;
 mod version;
+mod webhooks;
 
 #[derive(Deserialize, Debug)]
 struct GoodCrate {
@@ -130,6 +140,18 @@ fn app() -> (
     Arc<App>,
     conduit_middleware::MiddlewareBuilder,
 ) {
+    app_with_config(|_| ())
+}
+
+/// Same as `app`, with the test configuration modified by `f` first.
+fn app_with_config<F>(f: F) -> (
+    record::Bomb,
+    Arc<App>,
+    conduit_middleware::MiddlewareBuilder,
+)
+where
+    F: FnOnce(&mut cargo_registry::Config),
+{
     dotenv::dotenv().ok();
     git::init();
 
@@ -149,9 +171,10 @@ fn app() -> (
         ),
         proxy: Some(proxy),
         cdn: None,
+        signed_urls_ttl: None,
     };
 
-    let config = cargo_registry::Config {
+    let mut config = cargo_registry::Config {
         uploader: uploader,
         session_key: "test this has to be over 32 bytes long".to_string(),
         git_repo_checkout: git::checkout(),
@@ -163,7 +186,29 @@ fn app() -> (
         max_unpack_size: 2000,
         mirror: Replica::Primary,
         api_protocol: api_protocol,
+        rate_limit_requests: 1000,
+        rate_limit_window: 60,
+        scanners: Vec::new(),
+        scan_policy: ScanPolicy::AnyBlock,
+        admin_github_ids: vec![ADMIN_GITHUB_ID],
+        download_routing: None,
+        require_verified_email: false,
+        publish_hold_days: None,
+        metrics_token: None,
+        warn_yanked_downloads: false,
+        unique_downloads: false,
+        download_breakdown: false,
+        download_country_header: None,
+        orphan_team: None,
+        cdn_purge_url: None,
+        require_two_factor: false,
+        dependency_policy: Default::default(),
+        name_policy: Default::default(),
+        read_only: false,
+        max_update_subscribers: 10,
+        index_signing_key: None,
     };
+    f(&mut config);
     let app = App::new(&config);
     t!(t!(app.diesel_database.get()).begin_test_transaction());
     let app = Arc::new(app);
@@ -215,6 +260,9 @@ where
 
 static NEXT_ID: AtomicUsize = ATOMIC_USIZE_INIT;
 
+/// The GitHub id of the user configured as an administrator of the test app
+const ADMIN_GITHUB_ID: i32 = -1;
+
 fn new_user(login: &str) -> NewUser {
     NewUser {
         gh_id: NEXT_ID.fetch_add(1, Ordering::SeqCst) as i32,
@@ -226,6 +274,13 @@ fn new_user(login: &str) -> NewUser {
     }
 }
 
+fn new_admin(login: &str) -> NewUser {
+    NewUser {
+        gh_id: ADMIN_GITHUB_ID,
+        ..new_user(login)
+    }
+}
+
 fn user(login: &str) -> User {
     User {
         id: NEXT_ID.fetch_add(1, Ordering::SeqCst) as i32,
@@ -235,6 +290,9 @@ fn user(login: &str) -> User {
         name: None,
         gh_avatar: None,
         gh_access_token: "some random token".into(),
+        confirm_new_crates: false,
+        created_at: Utc::now().naive_utc(),
+        deleted_at: None,
     }
 }
 
@@ -272,7 +330,7 @@ struct VersionBuilder<'a> {
     license: Option<&'a str>,
     license_file: Option<&'a str>,
     features: HashMap<String, Vec<String>>,
-    dependencies: Vec<(i32, Option<&'static str>)>,
+    dependencies: Vec<(i32, Option<&'static str>, &'static str)>,
 }
 
 impl<'a> VersionBuilder<'a> {
@@ -296,7 +354,12 @@ impl<'a> VersionBuilder<'a> {
     }
 
     fn dependency(mut self, dependency: &Crate, target: Option<&'static str>) -> Self {
-        self.dependencies.push((dependency.id, target));
+        self.dependencies.push((dependency.id, target, ">= 0"));
+        self
+    }
+
+    fn dependency_with_req(mut self, dependency: &Crate, req: &'static str) -> Self {
+        self.dependencies.push((dependency.id, None, req));
         self
     }
 
@@ -318,10 +381,10 @@ impl<'a> VersionBuilder<'a> {
 
         let new_deps = self.dependencies
             .into_iter()
-            .map(|(crate_id, target)| {
+            .map(|(crate_id, target, req)| {
                 (
                     dependencies::version_id.eq(vers.id),
-                    dependencies::req.eq(">= 0"),
+                    dependencies::req.eq(req),
                     dependencies::crate_id.eq(crate_id),
                     dependencies::target.eq(target),
                     dependencies::optional.eq(false),
@@ -417,7 +480,7 @@ impl<'a> CrateBuilder<'a> {
         use diesel::{insert_into, select, update};
 
         let mut krate = self.krate
-            .create_or_update(connection, None, self.owner_id)?;
+            .create_or_update(connection, None, self.owner_id, &Default::default())?;
 
         // Since we are using `NewCrate`, we can't set all the
         // crate properties in a single DB call.
@@ -498,6 +561,17 @@ fn krate(name: &str) -> Crate {
         license: None,
         repository: None,
         max_upload_size: None,
+        publish_hold: false,
+        locked: false,
+        lock_reason: None,
+        quality_score: None,
+        deprecated: false,
+        deprecation_message: None,
+        alternative: None,
+        logo_path: None,
+        max_version: None,
+        max_stable_version: None,
+        maintenance_status: None,
     }
 }
 
@@ -681,6 +755,9 @@ fn new_req_body(
             repository: krate.repository,
             badges: Some(badges),
             links: None,
+            edition: None,
+            binaries: Vec::new(),
+            signature: None,
         },
         &[],
     )
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use conduit::{Handler, Method};
+
+use models::NewAnnouncement;
+use views::EncodableAnnouncement;
+
+#[derive(Deserialize)]
+struct AnnouncementList {
+    announcements: Vec<EncodableAnnouncement>,
+}
+
+#[test]
+fn list_only_includes_active_announcements() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let now = Utc::now().naive_utc();
+        NewAnnouncement {
+            message: "active",
+            starts_at: None,
+            ends_at: Some(now + Duration::days(1)),
+        }.create(&conn)
+            .unwrap();
+        NewAnnouncement {
+            message: "no end",
+            starts_at: Some(now - Duration::days(1)),
+            ends_at: None,
+        }.create(&conn)
+            .unwrap();
+        NewAnnouncement {
+            message: "expired",
+            starts_at: Some(now - Duration::days(2)),
+            ends_at: Some(now - Duration::days(1)),
+        }.create(&conn)
+            .unwrap();
+        NewAnnouncement {
+            message: "scheduled",
+            starts_at: Some(now + Duration::days(1)),
+            ends_at: None,
+        }.create(&conn)
+            .unwrap();
+    }
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/announcements");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: AnnouncementList = ::json(&mut response);
+    let messages = json.announcements
+        .iter()
+        .map(|a| &*a.message)
+        .collect::<Vec<_>>();
+    assert_eq!(messages, vec!["active", "no end"]);
+}
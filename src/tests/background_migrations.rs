@@ -0,0 +1,174 @@
+use diesel;
+use diesel::prelude::*;
+
+use cargo_registry::background_migrations::{self, RebuildCrateSearchIndex};
+use schema::background_migrations as progress;
+
+#[test]
+fn run_processes_every_row_in_batches() {
+    let (_b, app, _) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let user = ::new_user("foo").create_or_update(&conn).unwrap();
+    ::CrateBuilder::new("foo_migrate_1", user.id).expect_build(&conn);
+    ::CrateBuilder::new("foo_migrate_2", user.id).expect_build(&conn);
+    ::CrateBuilder::new("foo_migrate_3", user.id).expect_build(&conn);
+
+    let progress = background_migrations::run(&conn, &RebuildCrateSearchIndex, 2).unwrap();
+    assert_eq!(progress.processed, 3);
+    assert_eq!(progress.total, 3);
+    assert!(progress.finished_at.is_some());
+
+    // Running a finished migration again doesn't process anything
+    let progress = background_migrations::run(&conn, &RebuildCrateSearchIndex, 2).unwrap();
+    assert_eq!(progress.processed, 3);
+}
+
+#[test]
+fn run_resumes_from_recorded_progress() {
+    let (_b, app, _) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let user = ::new_user("foo").create_or_update(&conn).unwrap();
+    ::CrateBuilder::new("foo_resume_1", user.id).expect_build(&conn);
+    let second = ::CrateBuilder::new("foo_resume_2", user.id).expect_build(&conn);
+    ::CrateBuilder::new("foo_resume_3", user.id).expect_build(&conn);
+
+    diesel::insert_into(progress::table)
+        .values((
+            progress::name.eq("rebuild_crate_search_index"),
+            progress::last_id.eq(second.id),
+            progress::processed.eq(2),
+            progress::total.eq(3),
+        ))
+        .execute(&*conn)
+        .unwrap();
+
+    let progress = background_migrations::run(&conn, &RebuildCrateSearchIndex, 1).unwrap();
+    assert_eq!(progress.processed, 3);
+    assert!(progress.finished_at.is_some());
+}
+
+#[test]
+fn rebuild_crate_search_index_recomputes_the_search_index() {
+    use diesel::dsl::sql;
+    use diesel::sql_types::Bool;
+    use models::Crate;
+    use schema::crates;
+
+    let (_b, app, _) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let user = ::new_user("foo").create_or_update(&conn).unwrap();
+    let krate = ::CrateBuilder::new("foo_reindexed", user.id).expect_build(&conn);
+    let matching = || {
+        crates::table
+            .filter(sql::<Bool>(
+                "textsearchable_index_col @@ plainto_tsquery('english', 'foo_reindexed')",
+            ))
+            .count()
+            .get_result::<i64>(&*conn)
+            .unwrap()
+    };
+    assert_eq!(matching(), 1);
+
+    // Updating the index directly doesn't fire the trigger
+    diesel::sql_query("UPDATE crates SET textsearchable_index_col = ''::tsvector")
+        .execute(&*conn)
+        .unwrap();
+    assert_eq!(matching(), 0);
+
+    background_migrations::run(&conn, &RebuildCrateSearchIndex, 10).unwrap();
+    assert_eq!(matching(), 1);
+    let reindexed = Crate::by_name("foo_reindexed")
+        .first::<Crate>(&*conn)
+        .unwrap();
+    assert_eq!(reindexed.updated_at, krate.updated_at);
+}
+
+#[test]
+fn backfill_crate_downloads_sums_counted_version_downloads() {
+    use cargo_registry::background_migrations::BackfillCrateDownloads;
+    use chrono::{Duration, Utc};
+    use models::Version;
+    use schema::{crate_downloads, version_downloads};
+
+    let (_b, app, _) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let user = ::new_user("foo").create_or_update(&conn).unwrap();
+    let krate = ::CrateBuilder::new("foo_backfill", user.id)
+        .version("1.0.0")
+        .version("1.1.0")
+        .expect_build(&conn);
+    let versions = Version::belonging_to(&krate).load::<Version>(&*conn).unwrap();
+    let day = Utc::today().naive_utc() - Duration::days(200);
+    for (version, counted) in versions.iter().zip(&[3, 4]) {
+        diesel::insert_into(version_downloads::table)
+            .values((
+                version_downloads::version_id.eq(version.id),
+                version_downloads::date.eq(day),
+                version_downloads::downloads.eq(counted + 1),
+                version_downloads::counted.eq(counted),
+            ))
+            .execute(&*conn)
+            .unwrap();
+    }
+
+    background_migrations::run(&conn, &BackfillCrateDownloads, 10).unwrap();
+    let downloads = crate_downloads::table
+        .filter(crate_downloads::crate_id.eq(krate.id))
+        .filter(crate_downloads::date.eq(day))
+        .select(crate_downloads::downloads)
+        .first::<i32>(&*conn)
+        .unwrap();
+    assert_eq!(downloads, 7);
+}
+
+#[test]
+fn backfill_crate_max_versions_caches_the_max_versions() {
+    use cargo_registry::background_migrations::BackfillCrateMaxVersions;
+    use models::Crate;
+
+    let (_b, app, _) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let user = ::new_user("foo").create_or_update(&conn).unwrap();
+    let krate = ::CrateBuilder::new("foo_max_versions", user.id)
+        .version("1.0.0")
+        .version("1.1.0")
+        .version("2.0.0-beta.1")
+        .expect_build(&conn);
+    assert_eq!(krate.cached_versions(), None);
+
+    background_migrations::run(&conn, &BackfillCrateMaxVersions, 10).unwrap();
+    let krate = Crate::by_name("foo_max_versions")
+        .first::<Crate>(&*conn)
+        .unwrap();
+    assert_eq!(krate.max_version.as_ref().map(|s| &**s), Some("2.0.0-beta.1"));
+    assert_eq!(krate.max_stable_version.as_ref().map(|s| &**s), Some("1.1.0"));
+    let (max, default) = krate.cached_versions().unwrap();
+    assert_eq!(max.to_string(), "2.0.0-beta.1");
+    assert_eq!(default.to_string(), "1.1.0");
+}
+
+#[test]
+fn mark_deleted_users_marks_the_accounts_deleted_before() {
+    use cargo_registry::background_migrations::MarkDeletedUsers;
+    use models::User;
+    use schema::users;
+
+    let (_b, app, _) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let deleted = ::new_user("foo").create_or_update(&conn).unwrap();
+    let other = ::new_user("bar").create_or_update(&conn).unwrap();
+    diesel::update(&deleted)
+        .set((
+            users::gh_id.eq(-1),
+            users::gh_login.eq(format!("deleted-user-{}", deleted.id)),
+        ))
+        .execute(&*conn)
+        .unwrap();
+
+    background_migrations::run(&conn, &MarkDeletedUsers, 1).unwrap();
+    let deleted = users::table.find(deleted.id).first::<User>(&*conn).unwrap();
+    assert_eq!(deleted.gh_id, 0);
+    assert!(deleted.deleted_at.is_some());
+    let other = users::table.find(other.id).first::<User>(&*conn).unwrap();
+    assert!(other.deleted_at.is_none());
+}
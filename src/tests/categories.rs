@@ -3,7 +3,7 @@ use std::env;
 use diesel::*;
 use dotenv::dotenv;
 
-use schema::categories;
+use schema::{categories, category_renames};
 
 const ALGORITHMS: &str = r#"
 [algorithms]
@@ -40,6 +40,17 @@ name = "Another"
 description = "Another category ho hum"
 "#;
 
+const ALGOS_RENAMED: &str = r#"
+[algos]
+name = "Algorithms"
+description = "Core algorithms"
+renamed_from = ["algorithms"]
+
+[algos.categories.such]
+name = "Such"
+description = "Other stuff"
+"#;
+
 fn pg_connection() -> PgConnection {
     let _ = dotenv();
     let database_url =
@@ -89,3 +100,40 @@ fn sync_adds_and_removes() {
     let categories = select_slugs(&conn);
     assert_eq!(categories, vec!["algorithms", "another"]);
 }
+
+#[test]
+fn sync_renames_categories() {
+    use models::Category;
+
+    let conn = pg_connection();
+
+    ::cargo_registry::boot::categories::sync_with_connection(ALGORITHMS_AND_SUCH, &conn).unwrap();
+    let ids = categories::table
+        .select(categories::id)
+        .order(categories::slug)
+        .load::<i32>(&conn)
+        .unwrap();
+    ::cargo_registry::boot::categories::sync_with_connection(ALGOS_RENAMED, &conn).unwrap();
+    // Syncing again leaves the renamed categories as they are
+    ::cargo_registry::boot::categories::sync_with_connection(ALGOS_RENAMED, &conn).unwrap();
+
+    let categories = select_slugs(&conn);
+    assert_eq!(categories, vec!["algos", "algos::such"]);
+    let renamed_ids = categories::table
+        .select(categories::id)
+        .order(categories::slug)
+        .load::<i32>(&conn)
+        .unwrap();
+    assert_eq!(renamed_ids, ids);
+    let renames = category_renames::table
+        .select(category_renames::old_slug)
+        .load::<String>(&conn)
+        .unwrap();
+    assert_eq!(renames, vec!["algorithms"]);
+
+    let category = Category::find_by_slug(&conn, "Algorithms").unwrap();
+    assert_eq!(category.slug, "algos");
+    let category = Category::find_by_slug(&conn, "algorithms::such").unwrap();
+    assert_eq!(category.slug, "algos::such");
+    assert!(Category::find_by_slug(&conn, "algorithms::nope").is_err());
+}
@@ -1,7 +1,7 @@
 use conduit::{Handler, Method};
 
 use models::Category;
-use views::{EncodableCategory, EncodableCategoryWithSubcategories};
+use views::{EncodableCategory, EncodableCategoryWithSubcategories, EncodableCrate};
 
 #[derive(Deserialize)]
 struct CategoryList {
@@ -19,6 +19,18 @@ struct GoodCategory {
 #[derive(Deserialize)]
 struct CategoryWithSubcategories {
     category: EncodableCategoryWithSubcategories,
+    featured: Vec<EncodableCrate>,
+    recently_updated: Vec<EncodableCrate>,
+}
+#[derive(Deserialize)]
+struct FeaturedCrates {
+    featured: Vec<EncodableCrate>,
+}
+#[derive(Deserialize)]
+struct CategoryCrates {
+    category: EncodableCategory,
+    crates: Vec<EncodableCrate>,
+    meta: CategoryMeta,
 }
 
 #[cfg(test)]
@@ -79,6 +91,93 @@ fn show() {
     assert_eq!(json.category.slug, "foo-bar");
     assert_eq!(json.category.subcategories.len(), 1);
     assert_eq!(json.category.subcategories[0].category, "Foo Bar::Baz");
+    assert_eq!(json.featured.len(), 0);
+    assert_eq!(json.recently_updated.len(), 0);
+}
+
+#[test]
+fn show_recently_updated() {
+    use chrono::{Duration, Utc};
+    use diesel::prelude::*;
+    use diesel::update;
+    use schema::crates;
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/categories/cat1");
+    {
+        let conn = t!(app.diesel_database.get());
+        let user = t!(::new_user("foo").create_or_update(&conn));
+        t!(::new_category("cat1", "cat1").create_or_update(&conn));
+        t!(::new_category("cat1::sub", "cat1::sub").create_or_update(&conn));
+        t!(::new_category("cat2", "cat2").create_or_update(&conn));
+        let a = ::CrateBuilder::new("updated_a", user.id).expect_build(&conn);
+        let b = ::CrateBuilder::new("updated_b", user.id).expect_build(&conn);
+        let c = ::CrateBuilder::new("updated_c", user.id).expect_build(&conn);
+        let d = ::CrateBuilder::new("never_updated", user.id).expect_build(&conn);
+        Category::update_crate(&conn, &a, &["cat1"]).unwrap();
+        Category::update_crate(&conn, &b, &["cat1::sub"]).unwrap();
+        Category::update_crate(&conn, &c, &["cat2"]).unwrap();
+        Category::update_crate(&conn, &d, &["cat1"]).unwrap();
+
+        let now = Utc::now().naive_utc();
+        for (krate, hours) in vec![(&a, 1), (&b, 2), (&c, 3)] {
+            update(krate)
+                .set(crates::updated_at.eq(now + Duration::hours(hours)))
+                .execute(&*conn)
+                .unwrap();
+        }
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: CategoryWithSubcategories = ::json(&mut response);
+    let names = json.recently_updated
+        .iter()
+        .map(|c| &*c.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["updated_b", "updated_a"]);
+}
+
+#[test]
+fn crates() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/categories/cat1/crates",
+    );
+    {
+        let conn = t!(app.diesel_database.get());
+        let user = t!(::new_user("foo").create_or_update(&conn));
+        t!(::new_category("cat1", "cat1").create_or_update(&conn));
+        t!(::new_category("cat1::sub", "cat1::sub").create_or_update(&conn));
+        t!(::new_category("cat2", "cat2").create_or_update(&conn));
+        let a = ::CrateBuilder::new("cat_a", user.id)
+            .downloads(10)
+            .expect_build(&conn);
+        let b = ::CrateBuilder::new("cat_b", user.id)
+            .downloads(20)
+            .expect_build(&conn);
+        let c = ::CrateBuilder::new("cat_c", user.id).expect_build(&conn);
+        Category::update_crate(&conn, &a, &["cat1"]).unwrap();
+        Category::update_crate(&conn, &b, &["cat1::sub"]).unwrap();
+        Category::update_crate(&conn, &c, &["cat2"]).unwrap();
+    }
+
+    let mut response = ok_resp!(middle.call(req.with_query("sort=alpha")));
+    let json: CategoryCrates = ::json(&mut response);
+    assert_eq!(json.category.slug, "cat1");
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates.len(), 2);
+    assert_eq!(json.crates[0].name, "cat_a");
+    assert_eq!(json.crates[1].name, "cat_b");
+
+    let mut response = ok_resp!(middle.call(req.with_query("sort=downloads")));
+    let json: CategoryCrates = ::json(&mut response);
+    assert_eq!(json.crates[0].name, "cat_b");
+    assert_eq!(json.crates[1].name, "cat_a");
+
+    let response = t_resp!(middle.call(req.with_path("/api/v1/categories/nope/crates")));
+    assert_eq!(response.status.0, 404);
 }
 
 #[test]
@@ -210,3 +309,136 @@ fn category_slugs_returns_all_slugs_in_alphabetical_order() {
     };
     assert_eq!(expected_response, response);
 }
+
+#[test]
+fn renamed_categories_resolve_to_their_new_slug() {
+    use diesel::prelude::*;
+    use diesel::insert_into;
+    use schema::category_renames;
+
+    #[derive(Deserialize)]
+    struct ShownCategory {
+        category: EncodableCategoryWithSubcategories,
+        meta: ShownMeta,
+    }
+    #[derive(Deserialize)]
+    struct ShownMeta {
+        canonical_slug: String,
+    }
+    #[derive(Deserialize)]
+    struct SearchedCrates {
+        crates: Vec<EncodableCrate>,
+        meta: SearchedMeta,
+    }
+    #[derive(Deserialize)]
+    struct SearchedMeta {
+        canonical_category: String,
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/categories/old-cat");
+    {
+        let conn = t!(app.diesel_database.get());
+        let user = t!(::new_user("foo").create_or_update(&conn));
+        let cat = t!(::new_category("New Cat", "new-cat").create_or_update(&conn));
+        t!(::new_category("New Cat::Sub", "new-cat::sub").create_or_update(&conn));
+        insert_into(category_renames::table)
+            .values((
+                category_renames::old_slug.eq("old-cat"),
+                category_renames::category_id.eq(cat.id),
+            ))
+            .execute(&*conn)
+            .unwrap();
+        let krate = ::CrateBuilder::new("renamed_cat_crate", user.id).expect_build(&conn);
+        Category::update_crate(&conn, &krate, &["new-cat::sub"]).unwrap();
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: ShownCategory = ::json(&mut response);
+    assert_eq!(json.category.slug, "new-cat");
+    assert_eq!(json.meta.canonical_slug, "new-cat");
+
+    let mut response = ok_resp!(middle.call(req.with_path("/api/v1/categories/old-cat::sub")));
+    let json: ShownCategory = ::json(&mut response);
+    assert_eq!(json.meta.canonical_slug, "new-cat::sub");
+
+    let mut response = ok_resp!(
+        middle.call(
+            req.with_path("/api/v1/crates")
+                .with_query("category=old-cat")
+        )
+    );
+    let json: SearchedCrates = ::json(&mut response);
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].name, "renamed_cat_crate");
+    assert_eq!(json.meta.canonical_category, "new-cat");
+
+    let response = t_resp!(middle.call(req.with_path("/api/v1/categories/old-dog")));
+    assert_eq!(response.status.0, 404);
+}
+
+#[test]
+fn admins_feature_crates_in_categories() {
+    let (_b, app, middle) = ::app();
+    let (user, admin, a) = {
+        let conn = t!(app.diesel_database.get());
+        let user = t!(::new_user("foo").create_or_update(&conn));
+        let admin = t!(::new_admin("admin").create_or_update(&conn));
+        t!(::new_category("cat1", "cat1").create_or_update(&conn));
+        t!(::new_category("cat1::sub", "cat1::sub").create_or_update(&conn));
+        let a = ::CrateBuilder::new("featured_a", user.id).expect_build(&conn);
+        let b = ::CrateBuilder::new("featured_b", user.id).expect_build(&conn);
+        ::CrateBuilder::new("elsewhere", user.id).expect_build(&conn);
+        Category::update_crate(&conn, &a, &["cat1"]).unwrap();
+        Category::update_crate(&conn, &b, &["cat1::sub"]).unwrap();
+        (user, admin, a)
+    };
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/admin/categories/cat1/featured/featured_b",
+    );
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+    let mut response = ok_resp!(middle.call(
+        req.with_path("/api/v1/admin/categories/cat1/featured/featured_a")
+    ));
+    let json: FeaturedCrates = ::json(&mut response);
+    let names = json.featured.iter().map(|c| &*c.name).collect::<Vec<_>>();
+    assert_eq!(names, ["featured_b", "featured_a"]);
+
+    // Only the crates of the category can be featured
+    let json = bad_resp!(middle.call(
+        req.with_path("/api/v1/admin/categories/cat1/featured/elsewhere")
+    ));
+    assert!(
+        json.errors[0].detail.contains("not in category"),
+        "{:?}",
+        json.errors
+    );
+
+    let mut show = ::req(Arc::clone(&app), Method::Get, "/api/v1/categories/cat1");
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CategoryWithSubcategories = ::json(&mut response);
+    let names = json.featured.iter().map(|c| &*c.name).collect::<Vec<_>>();
+    assert_eq!(names, ["featured_b", "featured_a"]);
+
+    req.with_method(Method::Delete)
+        .with_path("/api/v1/admin/categories/cat1/featured/featured_b");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: FeaturedCrates = ::json(&mut response);
+    assert_eq!(json.featured.len(), 1);
+    bad_resp!(middle.call(&mut req));
+
+    // The crates moved out of the category are no longer featured
+    {
+        let conn = t!(app.diesel_database.get());
+        Category::update_crate(&conn, &a, &[]).unwrap();
+    }
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CategoryWithSubcategories = ::json(&mut response);
+    assert_eq!(json.featured.len(), 0);
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use conduit::{Handler, Method};
+
+use views::EncodableCrateList;
+
+#[derive(Deserialize)]
+struct ListResponse {
+    list: EncodableCrateList,
+}
+#[derive(Deserialize)]
+struct ListsResponse {
+    lists: Vec<EncodableCrateList>,
+    meta: Meta,
+}
+#[derive(Deserialize)]
+struct Meta {
+    total: i64,
+}
+
+#[test]
+fn manage_crate_lists() {
+    let (_b, app, middle) = ::app();
+    let (owner, other) = {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_listed", owner.id).expect_build(&conn);
+        ::CrateBuilder::new("bar_listed", other.id).expect_build(&conn);
+        (owner, other)
+    };
+
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/me/lists");
+    ::sign_in_as(&mut req, &owner);
+    req.with_body(br#"{"name":"awesome-async","description":"Async crates"}"#);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let list = ::json::<ListResponse>(&mut response).list;
+    assert_eq!(list.name, "awesome-async");
+    assert_eq!(list.user, "foo");
+    assert!(list.crates.is_empty());
+
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("already have a list"),
+        "{:?}",
+        json.errors
+    );
+    req.with_body(br#"{"name":"not a name"}"#);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("invalid list name"),
+        "{:?}",
+        json.errors
+    );
+
+    let path = format!("/api/v1/lists/{}", list.id);
+    for name in &["bar_listed", "foo_listed"] {
+        req.with_path(&format!("{}/crates/{}", path, name))
+            .with_method(Method::Put);
+        ok_resp!(middle.call(&mut req));
+    }
+    req.with_path(&path)
+        .with_method(Method::Put)
+        .with_body(br#"{"name":"async"}"#);
+    ok_resp!(middle.call(&mut req));
+
+    req.with_path(&path).with_method(Method::Get);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let list = ::json::<ListResponse>(&mut response).list;
+    assert_eq!(list.name, "async");
+    assert_eq!(list.description, None);
+    assert_eq!(list.crates, vec!["bar_listed", "foo_listed"]);
+
+    req.with_path("/api/v1/users/foo/lists");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let lists = ::json::<ListsResponse>(&mut response);
+    assert_eq!(lists.meta.total, 1);
+    assert_eq!(lists.lists[0].id, list.id);
+
+    req.with_path("/api/v1/crates/bar_listed/lists");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let lists = ::json::<ListsResponse>(&mut response);
+    assert_eq!(lists.meta.total, 1);
+    assert_eq!(lists.lists[0].name, "async");
+
+    // Only the creator of a list can change it
+    ::sign_in_as(&mut req, &other);
+    req.with_path(&format!("{}/crates/bar_listed", path))
+        .with_method(Method::Delete);
+    bad_resp!(middle.call(&mut req));
+
+    ::sign_in_as(&mut req, &owner);
+    ok_resp!(middle.call(&mut req));
+    req.with_path(&path).with_method(Method::Get);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let list = ::json::<ListResponse>(&mut response).list;
+    assert_eq!(list.crates, vec!["foo_listed"]);
+
+    req.with_method(Method::Delete);
+    ok_resp!(middle.call(&mut req));
+    req.with_path("/api/v1/crates/foo_listed/lists")
+        .with_method(Method::Get);
+    let mut response = ok_resp!(middle.call(&mut req));
+    assert_eq!(::json::<ListsResponse>(&mut response).meta.total, 0);
+}
@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use std::sync::{Once, ONCE_INIT};
 use std::thread;
 
+use cargo_registry::git::SigningKey;
 use git2;
 use url::Url;
 
@@ -65,3 +66,121 @@ pub fn init() {
     let mut origin = checkout.find_remote("origin").unwrap();
     origin.push(&["refs/heads/master"], None).unwrap();
 }
+
+fn commit_file_at(repo: &git2::Repository, name: &str, time: i64) {
+    commit_signed_file_at(repo, name, time, None)
+}
+
+fn commit_signed_file_at(
+    repo: &git2::Repository,
+    name: &str,
+    time: i64,
+    key: Option<&SigningKey>,
+) {
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+
+    File::create(checkout().join(name))
+        .unwrap()
+        .write_all(name.as_bytes())
+        .unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(name)).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::new("name", "email", &git2::Time::new(time, 0)).unwrap();
+    let head = repo.head().unwrap().target().unwrap();
+    let parent = repo.find_commit(head).unwrap();
+    let msg = match key {
+        Some(key) => key.sign_message(tree.id(), &[parent.id()], name).unwrap(),
+        None => name.to_string(),
+    };
+    repo.commit(Some("HEAD"), &sig, &sig, &msg, &tree, &[&parent])
+        .unwrap();
+}
+
+#[test]
+fn squash_history_collapses_old_commits() {
+    use cargo_registry::git::squash_history;
+    use chrono::{Duration, Utc};
+
+    let (_b, app, _middle) = ::app();
+    let now = Utc::now().naive_utc();
+    {
+        let checkout = app.git_repo.lock().unwrap();
+        commit_file_at(&checkout, "old", (now + Duration::days(1)).timestamp());
+        commit_file_at(&checkout, "new", (now + Duration::days(3)).timestamp());
+        let mut origin = checkout.find_remote("origin").unwrap();
+        origin.push(&["refs/heads/master"], None).unwrap();
+    }
+
+    let squashed = squash_history(&app, now + Duration::days(2)).unwrap();
+    assert!(squashed);
+
+    let bare = git2::Repository::open(&bare()).unwrap();
+    let head = bare.refname_to_id("refs/heads/master").unwrap();
+    let head = bare.find_commit(head).unwrap();
+    assert_eq!(head.message(), Some("new"));
+    assert!(head.tree().unwrap().get_name("old").is_some());
+    assert!(head.tree().unwrap().get_name("new").is_some());
+    let root = head.parent(0).unwrap();
+    assert_eq!(root.parent_count(), 0);
+    assert!(root.tree().unwrap().get_name("old").is_some());
+    assert!(root.tree().unwrap().get_name("new").is_none());
+
+    // Nothing is left to squash
+    let squashed = squash_history(&app, now + Duration::days(2)).unwrap();
+    assert!(!squashed);
+}
+
+#[test]
+fn squash_history_checks_the_signatures() {
+    use cargo_registry::git::{squash_history, verify_commit};
+    use chrono::{Duration, Utc};
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+
+    let pem = PKey::from_rsa(Rsa::generate(2048).unwrap())
+        .unwrap()
+        .private_key_to_pem()
+        .unwrap();
+    let key = SigningKey::from_pem(&String::from_utf8(pem).unwrap()).unwrap();
+    let (_b, app, _middle) = ::app_with_config(|config| {
+        config.index_signing_key = Some(key.clone());
+    });
+    let now = Utc::now().naive_utc();
+    let push = || {
+        let checkout = app.git_repo.lock().unwrap();
+        let mut origin = checkout.find_remote("origin").unwrap();
+        origin.push(&["+refs/heads/master"], None).unwrap();
+    };
+    {
+        // The commits made before the key was configured aren't signed
+        let checkout = app.git_repo.lock().unwrap();
+        commit_file_at(&checkout, "unsigned", (now + Duration::days(1)).timestamp());
+        let time = (now + Duration::days(3)).timestamp();
+        commit_signed_file_at(&checkout, "signed", time, Some(&key));
+        commit_file_at(&checkout, "forged", (now + Duration::days(4)).timestamp());
+    }
+    push();
+    assert!(squash_history(&app, now + Duration::days(2)).is_err());
+
+    // Without the forged commit, the retained commits are signed again
+    {
+        let checkout = app.git_repo.lock().unwrap();
+        let head = checkout.head().unwrap().peel_to_commit().unwrap();
+        let parent = head.parent(0).unwrap();
+        checkout
+            .reset(parent.as_object(), git2::ResetType::Hard, None)
+            .unwrap();
+    }
+    push();
+    assert!(squash_history(&app, now + Duration::days(2)).unwrap());
+
+    let bare = git2::Repository::open(&bare()).unwrap();
+    let head = bare.refname_to_id("refs/heads/master").unwrap();
+    let head = bare.find_commit(head).unwrap();
+    assert!(verify_commit(key.public_key(), &head).unwrap());
+    assert!(verify_commit(key.public_key(), &head.parent(0).unwrap()).unwrap());
+}
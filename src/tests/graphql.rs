@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use conduit::{Handler, Method};
+use serde_json::Value;
+
+#[test]
+fn nested_query() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c1 = ::CrateBuilder::new("foo_graphql_dep", user.id)
+            .version("1.0.0")
+            .keyword("graphql")
+            .expect_build(&conn);
+        ::CrateBuilder::new("foo_graphql", user.id)
+            .version(::VersionBuilder::new("0.1.0"))
+            .version(::VersionBuilder::new("0.2.0").dependency(&c1, None))
+            .keyword("graphql")
+            .expect_build(&conn);
+    }
+
+    let mut req = ::req(Arc::clone(&app), Method::Post, "/api/v1/graphql");
+    let query = r#"{
+        krate(name: "foo_graphql") {
+            name
+            defaultVersion
+            versions {
+                num
+                dependencies { kind krate { name owners { login kind } } }
+            }
+        }
+        keyword(keyword: "graphql") { crates { name } }
+    }"#;
+    req.with_body(json!({ "query": query }).to_string().as_bytes());
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Value = ::json(&mut response);
+    assert!(json.get("errors").map_or(true, |e| e == &json!([])), "{}", json);
+
+    let krate = &json["data"]["krate"];
+    assert_eq!(krate["name"], "foo_graphql");
+    assert_eq!(krate["defaultVersion"], "0.2.0");
+    let versions = krate["versions"].as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0]["num"], "0.2.0");
+    assert_eq!(
+        versions[0]["dependencies"],
+        json!([{
+            "kind": "normal",
+            "krate": {
+                "name": "foo_graphql_dep",
+                "owners": [{ "login": "foo", "kind": "user" }],
+            },
+        }])
+    );
+    assert_eq!(versions[1]["dependencies"], json!([]));
+
+    let mut names = json["data"]["keyword"]["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|krate| krate["name"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["foo_graphql", "foo_graphql_dep"]);
+}
+
+#[test]
+fn query_from_url() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_graphql", user.id).expect_build(&conn);
+    }
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/graphql");
+    req.with_query("query={crates{name}}");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Value = ::json(&mut response);
+    assert_eq!(json["data"]["crates"], json!([{ "name": "foo_graphql" }]));
+
+    // Lists are paginated
+    req.with_query("query={crates(first:1000){name}}");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Value = ::json(&mut response);
+    assert!(json["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("`first` must be between 0 and 100"));
+}
+
+#[test]
+fn deeply_nested_query_is_rejected() {
+    let (_b, app, middle) = ::app();
+
+    let mut req = ::req(Arc::clone(&app), Method::Post, "/api/v1/graphql");
+    let query = r#"
+        { krate(name: "foo") { ...Versions } }
+        fragment Versions on Crate { versions { dependencies { krate { ...Dependencies } } } }
+        fragment Dependencies on Crate {
+            versions { dependencies { krate { versions { dependencies { krate { name } } } } } }
+        }
+    "#;
+    req.with_body(json!({ "query": query }).to_string().as_bytes());
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Value = ::json(&mut response);
+    assert!(json.get("data").is_none(), "{}", json);
+    assert!(json["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("the query is nested 11 levels deep"));
+}
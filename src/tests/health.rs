@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use conduit::{Handler, Method};
+use serde_json::Value;
+
+use cargo_registry::Uploader;
+
+#[test]
+fn healthz_is_ok() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/healthz");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Value = ::json(&mut response);
+    assert_eq!(json["ok"], true);
+}
+
+#[test]
+fn readyz_checks_the_database_git_index_and_uploader() {
+    let (_b, app, middle) = ::app_with_config(|config| config.uploader = Uploader::NoOp);
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/readyz");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Value = ::json(&mut response);
+    assert_eq!(json["ok"], true);
+    for check in &["database", "git_index", "uploader"] {
+        assert_eq!(json["checks"][*check]["ok"], true, "{}", json);
+        assert!(json["checks"][*check].get("error").is_none(), "{}", json);
+    }
+
+    // The git index is still ready while it's being written to
+    let _repo = app.git_repo.lock().unwrap();
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Value = ::json(&mut response);
+    assert_eq!(json["checks"]["git_index"]["ok"], true, "{}", json);
+}
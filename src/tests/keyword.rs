@@ -4,7 +4,7 @@ use conduit::{Handler, Method};
 use conduit_test::MockRequest;
 
 use models::Keyword;
-use views::EncodableKeyword;
+use views::{EncodableCrate, EncodableKeyword};
 
 #[derive(Deserialize)]
 struct KeywordList {
@@ -18,6 +18,7 @@ struct KeywordMeta {
 #[derive(Deserialize)]
 struct GoodKeyword {
     keyword: EncodableKeyword,
+    recently_updated: Vec<EncodableCrate>,
 }
 
 #[test]
@@ -54,6 +55,50 @@ fn show() {
     let mut response = ok_resp!(middle.call(&mut req));
     let json: GoodKeyword = ::json(&mut response);
     assert_eq!(json.keyword.keyword, "foo".to_string());
+    assert_eq!(json.recently_updated.len(), 0);
+}
+
+#[test]
+fn show_recently_updated() {
+    use chrono::{Duration, Utc};
+    use diesel::prelude::*;
+    use diesel::update;
+    use schema::crates;
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/keywords/kw1");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let a = ::CrateBuilder::new("updated_a", user.id)
+            .keyword("kw1")
+            .expect_build(&conn);
+        let b = ::CrateBuilder::new("updated_b", user.id)
+            .keyword("kw1")
+            .expect_build(&conn);
+        let c = ::CrateBuilder::new("updated_c", user.id)
+            .keyword("kw2")
+            .expect_build(&conn);
+        ::CrateBuilder::new("never_updated", user.id)
+            .keyword("kw1")
+            .expect_build(&conn);
+
+        let now = Utc::now().naive_utc();
+        for (krate, hours) in vec![(&a, 1), (&b, 2), (&c, 3)] {
+            update(krate)
+                .set(crates::updated_at.eq(now + Duration::hours(hours)))
+                .execute(&*conn)
+                .unwrap();
+        }
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: GoodKeyword = ::json(&mut response);
+    let names = json.recently_updated
+        .iter()
+        .map(|c| &*c.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["updated_b", "updated_a"]);
 }
 
 #[test]
@@ -129,3 +174,232 @@ fn update_crate() {
     assert_eq!(cnt(&mut req, "kw1"), 0);
     assert_eq!(cnt(&mut req, "kw2"), 0);
 }
+
+#[test]
+fn update_crate_keywords_without_publishing() {
+    #[derive(Deserialize)]
+    struct R {
+        keywords: Vec<EncodableKeyword>,
+    }
+
+    let (_b, app, middle) = ::app();
+    let (owner, other) = {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_rekeyword", owner.id)
+            .keyword("kw1")
+            .expect_build(&conn);
+        (owner, other)
+    };
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/foo_rekeyword/keywords",
+    );
+    req.with_body(br#"{"keywords":["kw2","kw3"]}"#);
+    ::sign_in_as(&mut req, &other);
+    bad_resp!(middle.call(&mut req));
+
+    ::sign_in_as(&mut req, &owner);
+    req.with_body(br#"{"keywords":["not a keyword"]}"#);
+    bad_resp!(middle.call(&mut req));
+
+    req.with_body(br#"{"keywords":["kw2","kw3"]}"#);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    let mut names = json.keywords.into_iter().map(|k| k.keyword).collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["kw2", "kw3"]);
+
+    // The search index follows the new keywords
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates");
+    req.with_query("q=kw3");
+    let mut response = ok_resp!(middle.call(&mut req));
+    assert_eq!(::json::<::CrateList>(&mut response).meta.total, 1);
+}
+
+#[test]
+fn admins_recount_crates() {
+    use diesel::prelude::*;
+    use diesel::update;
+    use models::Category;
+    use recount::Recount;
+    use schema::{categories, keywords};
+
+    #[derive(Deserialize)]
+    struct R {
+        corrected: Recount,
+    }
+
+    let (_b, app, middle) = ::app();
+    let (user, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::new_category("Recounted", "recounted")
+            .create_or_update(&conn)
+            .unwrap();
+        ::CrateBuilder::new("foo_recount", user.id)
+            .keyword("kw_recount")
+            .expect_build(&conn);
+        update(keywords::table.filter(keywords::keyword.eq("kw_recount")))
+            .set(keywords::crates_cnt.eq(5))
+            .execute(&*conn)
+            .unwrap();
+        update(categories::table.filter(categories::slug.eq("recounted")))
+            .set(categories::crates_cnt.eq(3))
+            .execute(&*conn)
+            .unwrap();
+        (user, admin)
+    };
+
+    // Only administrators can recount
+    let mut req = ::req(Arc::clone(&app), Method::Post, "/api/v1/admin/jobs/recount");
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json = ::json::<R>(&mut response);
+    assert_eq!(
+        json.corrected,
+        Recount {
+            keywords: 1,
+            categories: 1,
+        }
+    );
+
+    let conn = app.diesel_database.get().unwrap();
+    let keyword = Keyword::find_by_keyword(&conn, "kw_recount").unwrap();
+    assert_eq!(keyword.crates_cnt, 1);
+    let category = categories::table
+        .filter(categories::slug.eq("recounted"))
+        .first::<Category>(&*conn)
+        .unwrap();
+    assert_eq!(category.crates_cnt, 0);
+}
+
+#[test]
+fn admins_alias_keywords() {
+    use views::EncodableKeywordAlias;
+
+    #[derive(Deserialize)]
+    struct R {
+        keyword_aliases: Vec<EncodableKeywordAlias>,
+    }
+
+    let (_b, app, middle) = ::app();
+    let (user, admin, krate) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_wasm", user.id)
+            .keyword("wasm")
+            .expect_build(&conn);
+        ::CrateBuilder::new("foo_webassembly", user.id)
+            .keyword("webassembly")
+            .expect_build(&conn);
+        let krate = ::CrateBuilder::new("foo_web", user.id).expect_build(&conn);
+        (user, admin, krate)
+    };
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/admin/keyword_aliases",
+    );
+    req.with_body(br#"{"alias":"WASM","keyword":"webassembly"}"#);
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+    req.with_body(br#"{"alias":"web-assembly","keyword":"webassembly"}"#);
+    ok_resp!(middle.call(&mut req));
+    // Aliases can't stand for other aliases
+    req.with_body(br#"{"alias":"wa","keyword":"wasm"}"#);
+    bad_resp!(middle.call(&mut req));
+
+    req.with_method(Method::Get);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json = ::json::<R>(&mut response);
+    let aliases = json.keyword_aliases
+        .iter()
+        .map(|a| (&*a.alias, &*a.keyword))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        aliases,
+        [("wasm", "webassembly"), ("web-assembly", "webassembly")]
+    );
+
+    // The crates with the alias were moved to the keyword, and new crates get
+    // the keyword as well
+    {
+        let conn = app.diesel_database.get().unwrap();
+        Keyword::update_crate(&conn, &krate, &["Web-Assembly", "webassembly"]).unwrap();
+    }
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/keywords/wasm");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json = ::json::<GoodKeyword>(&mut response);
+    assert_eq!(json.keyword.keyword, "webassembly");
+    assert_eq!(json.keyword.crates_cnt, 3);
+
+    req.with_path("/api/v1/crates").with_query("keyword=web-assembly");
+    let mut response = ok_resp!(middle.call(&mut req));
+    assert_eq!(::json::<::CrateList>(&mut response).meta.total, 3);
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Delete,
+        "/api/v1/admin/keyword_aliases/wasm",
+    );
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+    bad_resp!(middle.call(&mut req));
+}
+
+#[test]
+fn trending() {
+    use chrono::{Duration, Utc};
+    use diesel::prelude::*;
+    use diesel::update;
+    use schema::{keyword_snapshots, keywords};
+    use views::EncodableTrendingKeyword;
+
+    #[derive(Deserialize)]
+    struct R {
+        keywords: Vec<EncodableTrendingKeyword>,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let set_counts = |counts: &[(&str, i32)]| {
+            for &(keyword, crates_cnt) in counts {
+                update(keywords::table.filter(keywords::keyword.eq(keyword)))
+                    .set(keywords::crates_cnt.eq(crates_cnt))
+                    .execute(&*conn)
+                    .unwrap();
+            }
+        };
+        Keyword::find_or_create_all(&conn, &["fast", "slow", "tiny", "flat"]).unwrap();
+        set_counts(&[("fast", 5), ("slow", 10), ("tiny", 1), ("flat", 8)]);
+        assert_eq!(Keyword::snapshot_counts(&conn).unwrap(), 4);
+        update(keyword_snapshots::table)
+            .set(keyword_snapshots::date.eq(Utc::today().naive_utc() - Duration::days(20)))
+            .execute(&*conn)
+            .unwrap();
+        set_counts(&[("fast", 10), ("slow", 12), ("tiny", 3)]);
+        Keyword::snapshot_counts(&conn).unwrap();
+    }
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/keywords/trending");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    let keywords = json.keywords
+        .iter()
+        .map(|k| (&*k.keyword.keyword, k.keyword.crates_cnt, k.previous_crates_cnt))
+        .collect::<Vec<_>>();
+    assert_eq!(keywords, vec![("fast", 10, 5), ("slow", 12, 10)]);
+}
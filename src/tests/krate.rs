@@ -9,21 +9,23 @@ use std::sync::Arc;
 use self::diesel::prelude::*;
 use chrono::Utc;
 use conduit::{Handler, Method};
-use diesel::update;
+use diesel::{insert_into, update};
 use git2;
 use semver;
 use serde_json;
 
 use cargo_registry::git;
-use cargo_registry::models::krate::MAX_NAME_LENGTH;
+use cargo_registry::models::krate::{LetterBucket, RequirementDependents, MAX_NAME_LENGTH};
 
 use {CrateList, CrateMeta, GoodCrate};
 
-use models::{ApiToken, Category, Crate};
-use schema::{crates, metadata, versions};
+use models::{ApiToken, Category, Crate, CrateDependent, CrateTransfer, Owner};
+use schema::{crates, metadata, registry_dependencies, versions};
 use views::krate_publish as u;
-use views::{EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword,
-            EncodableVersion, EncodableVersionDownload};
+use views::{EncodableCategory, EncodableCrate, EncodableCrateMetadataChange,
+            EncodableCrateSpotlight, EncodableCrateStatus, EncodableDeletedCrate,
+            EncodableDependency, EncodableKeyword, EncodableNewDependent, EncodableVersion,
+            EncodableVersionDownload, EncodableVersionPublish};
 
 #[derive(Deserialize)]
 struct VersionsList {
@@ -47,6 +49,10 @@ struct RevDeps {
     meta: CrateMeta,
 }
 #[derive(Deserialize)]
+struct RequirementsResponse {
+    requirements: Vec<RequirementDependents>,
+}
+#[derive(Deserialize)]
 struct Downloads {
     version_downloads: Vec<EncodableVersionDownload>,
 }
@@ -61,6 +67,11 @@ struct SummaryResponse {
     just_updated: Vec<EncodableCrate>,
     popular_keywords: Vec<EncodableKeyword>,
     popular_categories: Vec<EncodableCategory>,
+    spotlight: Option<EncodableCrateSpotlight>,
+}
+#[derive(Deserialize)]
+struct Spotlight {
+    spotlight: Option<EncodableCrateSpotlight>,
 }
 
 fn new_crate(name: &str) -> u::NewCrate {
@@ -82,6 +93,9 @@ fn new_crate(name: &str) -> u::NewCrate {
         repository: None,
         badges: None,
         links: None,
+        edition: None,
+        binaries: Vec::new(),
+        signature: None,
     }
 }
 
@@ -215,6 +229,104 @@ fn index_queries() {
     assert_eq!(cl.meta.total, 0);
 }
 
+#[test]
+fn index_by_all_keywords_and_categories() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let both = ::CrateBuilder::new("foo_both", u.id)
+            .keyword("async")
+            .keyword("http")
+            .expect_build(&conn);
+        let one = ::CrateBuilder::new("foo_one", u.id)
+            .keyword("async")
+            .expect_build(&conn);
+
+        ::new_category("Web", "web")
+            .create_or_update(&conn)
+            .unwrap();
+        ::new_category("Web::Server", "web::server")
+            .create_or_update(&conn)
+            .unwrap();
+        ::new_category("Network", "network")
+            .create_or_update(&conn)
+            .unwrap();
+        Category::update_crate(&conn, &both, &["web::server", "network"]).unwrap();
+        Category::update_crate(&conn, &one, &["web"]).unwrap();
+    }
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates");
+    let mut response = ok_resp!(middle.call(req.with_query("all_keywords=async")));
+    assert_eq!(::json::<CrateList>(&mut response).meta.total, 2);
+    let mut response = ok_resp!(middle.call(req.with_query("all_keywords=async,HTTP")));
+    let cl = ::json::<CrateList>(&mut response);
+    assert_eq!(cl.meta.total, 1);
+    assert_eq!(cl.crates[0].name, "foo_both");
+    let mut response = ok_resp!(middle.call(req.with_query("all_keywords=async,http,io")));
+    assert_eq!(::json::<CrateList>(&mut response).meta.total, 0);
+
+    // Subcategories count as their parent category
+    let mut response = ok_resp!(middle.call(req.with_query("categories=web")));
+    assert_eq!(::json::<CrateList>(&mut response).meta.total, 2);
+    let mut response = ok_resp!(middle.call(req.with_query("categories=web,network")));
+    let cl = ::json::<CrateList>(&mut response);
+    assert_eq!(cl.meta.total, 1);
+    assert_eq!(cl.crates[0].name, "foo_both");
+    let query = "categories=web&all_keywords=async,http";
+    let mut response = ok_resp!(middle.call(req.with_query(query)));
+    assert_eq!(::json::<CrateList>(&mut response).meta.total, 1);
+}
+
+#[test]
+fn index_by_letter_prefix() {
+    #[derive(Deserialize)]
+    struct ByLetter {
+        crates: Vec<EncodableCrate>,
+        meta: ByLetterMeta,
+    }
+    #[derive(Deserialize)]
+    struct ByLetterMeta {
+        total: i32,
+        letters: Vec<LetterBucket>,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo-bar", u.id).expect_build(&conn);
+        ::CrateBuilder::new("Foo_Baz", u.id).expect_build(&conn);
+        ::CrateBuilder::new("foobar", u.id).expect_build(&conn);
+        ::CrateBuilder::new("3d_engine", u.id).expect_build(&conn);
+    }
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates");
+    let mut response = ok_resp!(middle.call(req.with_query("letter=FOO-&sort=alpha")));
+    let json = ::json::<ByLetter>(&mut response);
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "Foo_Baz");
+    assert_eq!(json.crates[1].name, "foo-bar");
+
+    let mut response = ok_resp!(middle.call(req.with_query("letter=0-9")));
+    let json = ::json::<ByLetter>(&mut response);
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "3d_engine");
+    let letters = json.meta
+        .letters
+        .iter()
+        .map(|b| (&*b.letter, b.crates))
+        .collect::<Vec<_>>();
+    assert_eq!(letters, vec![("0-9", 1), ("f", 3)]);
+
+    let json = bad_resp!(middle.call(req.with_query("letter=")));
+    assert!(
+        json.errors[0].detail.contains("cannot be empty"),
+        "{:?}",
+        json.errors
+    );
+}
+
 #[test]
 fn search_includes_crates_where_name_is_stopword() {
     let (_b, app, middle) = ::app();
@@ -234,6 +346,32 @@ fn search_includes_crates_where_name_is_stopword() {
     assert_eq!(json.meta.total, 1);
 }
 
+#[test]
+fn search_indexes_truncated_readmes() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+
+        let long_readme = format!("{} zeppelin", "filler ".repeat(10_000));
+        ::CrateBuilder::new("airship", u.id)
+            .readme("a crate about dirigibles")
+            .expect_build(&conn);
+        ::CrateBuilder::new("long_readme", u.id)
+            .readme(&long_readme)
+            .expect_build(&conn);
+    }
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates");
+    let mut response = ok_resp!(middle.call(req.with_query("q=dirigibles")));
+    let json = ::json::<CrateList>(&mut response);
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "airship");
+
+    let mut response = ok_resp!(middle.call(req.with_query("q=zeppelin")));
+    let json = ::json::<CrateList>(&mut response);
+    assert_eq!(json.meta.total, 0);
+}
+
 #[test]
 fn exact_match_first_on_queries() {
     let (_b, app, middle) = ::app();
@@ -418,489 +556,1039 @@ fn show() {
 }
 
 #[test]
-fn versions() {
+fn show_through_api_v2() {
     let (_b, app, middle) = ::app();
-
-    let mut req = ::req(
-        Arc::clone(&app),
-        Method::Get,
-        "/api/v1/crates/foo_versions/versions",
-    );
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v2/crates/foo_v2");
     {
         let conn = app.diesel_database.get().unwrap();
-        let u = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::CrateBuilder::new("foo_versions", u.id)
-            .version("0.5.1")
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_v2", user.id)
             .version("1.0.0")
-            .version("0.5.0")
             .expect_build(&conn);
     }
 
     let mut response = ok_resp!(middle.call(&mut req));
-    let json: VersionsList = ::json(&mut response);
-
-    assert_eq!(json.versions.len(), 3);
-    assert_eq!(json.versions[0].num, "1.0.0");
-    assert_eq!(json.versions[1].num, "0.5.1");
-    assert_eq!(json.versions[2].num, "0.5.0");
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["data"]["crate"]["maxVersion"], "1.0.0");
+    assert_eq!(json["data"]["versions"][0]["dlPath"], "/api/v1/crates/foo_v2/1.0.0/download");
+    assert_eq!(json["errors"], json!([]));
+
+    // Single lists are the data, whatever their name in v1
+    let mut response = ok_resp!(middle.call(req.with_path("/api/v2/crates/foo_v2/versions")));
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["data"][0]["num"], "1.0.0");
+
+    let mut response = ok_resp!(middle.call(req.with_path("/api/v2/crates/foo_v2/owners")));
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["data"]["users"][0]["login"], "foo");
+    assert_eq!(json["data"]["invitations"], json!([]));
+
+    let mut response = t_resp!(middle.call(req.with_path("/api/v2/crates/foo_missing")));
+    assert_eq!(response.status.0, 404);
+    let json: serde_json::Value = ::json(&mut response);
+    assert!(json["data"].is_null());
+    assert_eq!(json["errors"][0]["detail"], "Not Found");
 }
 
 #[test]
-fn uploading_new_version_touches_crate() {
-    use diesel::dsl::*;
-
+fn show_with_include() {
     let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_include");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_include", user.id)
+            .version("1.0.0")
+            .keyword("kw1")
+            .recent_downloads(10)
+            .expect_build(&conn);
+    }
 
-    let mut upload_req = ::new_req(Arc::clone(&app), "foo_versions_updated_at", "1.0.0");
-    let u = ::sign_in(&mut upload_req, &app);
-    ok_resp!(middle.call(&mut upload_req));
+    req.with_query("include=keywords");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["crate"]["keywords"], json!(["kw1"]));
+    assert_eq!(json["crate"]["max_version"], "1.0.0");
+    assert!(json["crate"]["versions"].is_null());
+    assert!(json["crate"]["recent_downloads"].is_null());
+    assert_eq!(json["keywords"].as_array().unwrap().len(), 1);
+    assert!(json.get("versions").is_none());
+    assert!(json.get("categories").is_none());
+
+    req.with_query("include=full");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: CrateResponse = ::json(&mut response);
+    assert_eq!(json.versions.len(), 1);
+    assert_eq!(json.krate.recent_downloads, Some(10));
+
+    req.with_query("include=everything");
+    bad_resp!(middle.call(&mut req));
+}
 
+#[test]
+fn default_version_skips_yanked_and_prereleases() {
+    let (_b, app, middle) = ::app();
     {
         let conn = app.diesel_database.get().unwrap();
-        diesel::update(crates::table)
-            .set(crates::updated_at.eq(crates::updated_at - 1.hour()))
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("foo_default", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .version("2.0.0-beta.1")
+            .expect_build(&conn);
+        update(versions::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::num.eq("1.1.0"))
+            .set(versions::yanked.eq(true))
             .execute(&*conn)
             .unwrap();
     }
 
-    let mut show_req = ::req(
-        Arc::clone(&app),
-        Method::Get,
-        "/api/v1/crates/foo_versions_updated_at",
-    );
-    let mut response = ok_resp!(middle.call(&mut show_req));
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_default");
+    let mut response = ok_resp!(middle.call(&mut req));
     let json: CrateResponse = ::json(&mut response);
-    let updated_at_before = json.krate.updated_at;
+    assert_eq!(json.krate.max_version, "2.0.0-beta.1");
+    assert_eq!(json.krate.default_version, "1.0.0");
 
-    let mut upload_req = ::new_req(Arc::clone(&app), "foo_versions_updated_at", "2.0.0");
-    ::sign_in_as(&mut upload_req, &u);
-    ok_resp!(middle.call(&mut upload_req));
+    req.with_path("/api/v1/crates");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: CrateList = ::json(&mut response);
+    assert_eq!(json.crates[0].default_version, "1.0.0");
+}
 
-    let mut response = ok_resp!(middle.call(&mut show_req));
+#[test]
+fn show_includes_dependency_freshness() {
+    use schema::crate_freshness;
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/c3");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c1 = ::CrateBuilder::new("c1", u.id)
+            .version("1.0.0")
+            .version("2.0.0")
+            .version("3.0.0-beta")
+            .expect_build(&conn);
+        let c2 = ::CrateBuilder::new("c2", u.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        let c3 = ::CrateBuilder::new("c3", u.id)
+            .version(
+                ::VersionBuilder::new("1.0.0")
+                    .dependency_with_req(&c1, "^1.0")
+                    .dependency_with_req(&c2, "^1.0"),
+            )
+            .expect_build(&conn);
+
+        assert_eq!(c1.dependency_freshness(&conn).unwrap(), None);
+        let freshness = c3.dependency_freshness(&conn).unwrap();
+        assert_eq!(freshness, Some(0.5));
+
+        diesel::insert_into(crate_freshness::table)
+            .values((
+                crate_freshness::crate_id.eq(c3.id),
+                crate_freshness::freshness.eq(freshness.unwrap()),
+            ))
+            .execute(&*conn)
+            .unwrap();
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
     let json: CrateResponse = ::json(&mut response);
-    let updated_at_after = json.krate.updated_at;
+    assert_eq!(json.krate.dependency_freshness, Some(0.5));
 
-    assert_ne!(updated_at_before, updated_at_after);
+    req.with_path("/api/v1/crates/c1");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: CrateResponse = ::json(&mut response);
+    assert_eq!(json.krate.dependency_freshness, None);
 }
 
 #[test]
-fn new_wrong_token() {
+fn deprecate_crate() {
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo", "1.0.0");
-    bad_resp!(middle.call(&mut req));
-    drop(req);
+    let (owner, other) = {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("old_crate", owner.id)
+            .description("deprecated")
+            .version("1.0.0")
+            .expect_build(&conn);
+        ::CrateBuilder::new("new_crate", owner.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        (owner, other)
+    };
 
-    let mut req = ::new_req(Arc::clone(&app), "foo", "1.0.0");
-    req.header("Authorization", "bad");
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/old_crate/deprecate",
+    );
+    req.with_body(br#"{"message":"Unmaintained","alternative":"new-crate"}"#);
+    ::sign_in_as(&mut req, &other);
     bad_resp!(middle.call(&mut req));
-    drop(req);
 
-    let mut req = ::new_req(Arc::clone(&app), "foo", "1.0.0");
-    ::sign_in(&mut req, &app);
-    ::logout(&mut req);
-    req.header("Authorization", "bad");
+    ::sign_in_as(&mut req, &owner);
+    req.with_body(br#"{"message":"Unmaintained","alternative":"nonexistent"}"#);
+    bad_resp!(middle.call(&mut req));
+    req.with_body(br#"{"message":"Unmaintained","alternative":"old_crate"}"#);
+    bad_resp!(middle.call(&mut req));
+    req.with_body(br#"{"message":" "}"#);
     bad_resp!(middle.call(&mut req));
-}
 
-#[test]
-fn new_bd_names() {
-    fn bad_name(name: &str) {
-        println!("testing: `{}`", name);
-        let (_b, app, middle) = ::app();
-        let mut req = ::new_req(Arc::clone(&app), name, "1.0.0");
-        ::sign_in(&mut req, &app);
-        let json = bad_resp!(middle.call(&mut req));
-        assert!(
-            json.errors[0]
-                .detail
-                .contains("expected a valid crate name",),
-            "{:?}",
-            json.errors
-        );
-    }
+    req.with_body(br#"{"message":"Unmaintained","alternative":"new-crate"}"#);
+    ok_resp!(middle.call(&mut req));
 
-    bad_name("");
-    bad_name("foo bar");
-    bad_name(&"a".repeat(MAX_NAME_LENGTH + 1));
-}
+    let mut show = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/old_crate");
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CrateResponse = ::json(&mut response);
+    assert!(json.krate.deprecated);
+    assert_eq!(json.krate.deprecation_message.unwrap(), "Unmaintained");
+    assert_eq!(json.krate.alternative.unwrap(), "new_crate");
 
-#[test]
-fn new_krate() {
-    let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo_new", "1.0.0");
-    ::sign_in(&mut req, &app);
-    let mut response = ok_resp!(middle.call(&mut req));
-    let json: GoodCrate = ::json(&mut response);
-    assert_eq!(json.krate.name, "foo_new");
-    assert_eq!(json.krate.max_version, "1.0.0");
+    let mut search = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates");
+    let mut response = ok_resp!(middle.call(search.with_query("q=deprecated")));
+    let json: CrateList = ::json(&mut response);
+    assert!(json.crates[0].deprecated);
+    assert_eq!(json.crates[0].alternative, Some("new_crate".to_string()));
+
+    req.with_method(Method::Delete);
+    ok_resp!(middle.call(&mut req));
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CrateResponse = ::json(&mut response);
+    assert!(!json.krate.deprecated);
+    assert_eq!(json.krate.deprecation_message, None);
+    assert_eq!(json.krate.alternative, None);
 }
 
 #[test]
-fn new_krate_with_token() {
+fn set_maintenance_status() {
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo_new", "1.0.0");
+    let (owner, other) = {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("orphan", owner.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        ::CrateBuilder::new("maintained", owner.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        (owner, other)
+    };
 
-    {
-        let conn = t!(app.diesel_database.get());
-        let user = t!(::new_user("foo").create_or_update(&conn));
-        let token = t!(ApiToken::insert(&conn, user.id, "bar"));
-        req.header("Authorization", &token.token);
-    }
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/orphan/maintenance",
+    );
+    req.with_body(br#"{"status":"looking-for-maintainer"}"#);
+    ::sign_in_as(&mut req, &other);
+    bad_resp!(middle.call(&mut req));
 
-    let mut response = ok_resp!(middle.call(&mut req));
-    let json: GoodCrate = ::json(&mut response);
-    assert_eq!(json.krate.name, "foo_new");
-    assert_eq!(json.krate.max_version, "1.0.0");
+    ::sign_in_as(&mut req, &owner);
+    req.with_body(br#"{"status":"abandoned"}"#);
+    bad_resp!(middle.call(&mut req));
+    // Crates are deprecated with `PUT /crates/:crate_id/deprecate` instead
+    req.with_body(br#"{"status":"deprecated"}"#);
+    bad_resp!(middle.call(&mut req));
+    req.with_body(br#"{"status":"looking-for-maintainer"}"#);
+    ok_resp!(middle.call(&mut req));
+
+    let mut show = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/orphan");
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CrateResponse = ::json(&mut response);
+    assert_eq!(
+        json.krate.maintenance_status,
+        Some("looking-for-maintainer".to_string())
+    );
+
+    let mut search = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates");
+    let mut response = ok_resp!(middle.call(
+        search.with_query("maintenance=looking-for-maintainer")
+    ));
+    let json: CrateList = ::json(&mut response);
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].name, "orphan");
+
+    req.with_body(br#"{"status":null}"#);
+    ok_resp!(middle.call(&mut req));
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CrateResponse = ::json(&mut response);
+    assert_eq!(json.krate.maintenance_status, None);
 }
 
 #[test]
-fn new_krate_with_reserved_name() {
-    fn test_bad_name(name: &str) {
-        let (_b, app, middle) = ::app();
-        let mut req = ::new_req(Arc::clone(&app), name, "1.0.0");
-        ::sign_in(&mut req, &app);
-        let json = bad_resp!(middle.call(&mut req));
-        assert!(
-            json.errors[0]
-                .detail
-                .contains("cannot upload a crate with a reserved name",)
-        );
+fn crates_without_activity_are_stale() {
+    use schema::crate_owners;
+    use chrono::Duration;
+    use serde_json::Value;
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let old = ::CrateBuilder::new("foo_stale", user.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        ::CrateBuilder::new("foo_active", user.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+
+        let long_ago = Utc::now().naive_utc() - Duration::days(3 * 365);
+        update(crates::table.find(old.id))
+            .set(crates::created_at.eq(long_ago))
+            .execute(&*conn)
+            .unwrap();
+        update(versions::table.filter(versions::crate_id.eq(old.id)))
+            .set(versions::created_at.eq(long_ago))
+            .execute(&*conn)
+            .unwrap();
+        update(crate_owners::table.filter(crate_owners::crate_id.eq(old.id)))
+            .set(crate_owners::created_at.eq(long_ago))
+            .execute(&*conn)
+            .unwrap();
+        assert_eq!(Crate::update_stale(&conn).unwrap(), 1);
     }
 
-    test_bad_name("std");
-    test_bad_name("STD");
-    test_bad_name("compiler-rt");
-    test_bad_name("compiler_rt");
-    test_bad_name("coMpiLer_Rt");
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_stale");
+    let mut response = ok_resp!(middle.call(&mut req));
+    assert_eq!(::json::<Value>(&mut response)["stale"], true);
+    req.with_path("/api/v1/crates/foo_active");
+    let mut response = ok_resp!(middle.call(&mut req));
+    assert_eq!(::json::<Value>(&mut response)["stale"], false);
+
+    req.with_path("/api/v1/crates").with_query("maintenance=stale");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: CrateList = ::json(&mut response);
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].name, "foo_stale");
 }
 
 #[test]
-fn new_krate_weird_version() {
+fn upload_and_remove_logo() {
+    #[derive(Deserialize)]
+    struct R {
+        url: String,
+    }
+
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo_weird", "0.0.0-pre");
-    ::sign_in(&mut req, &app);
-    let mut response = ok_resp!(middle.call(&mut req));
-    let json: GoodCrate = ::json(&mut response);
-    assert_eq!(json.krate.name, "foo_weird");
-    assert_eq!(json.krate.max_version, "0.0.0-pre");
+    let (owner, other) = {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_logo", owner.id).expect_build(&conn);
+        (owner, other)
+    };
+
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/crates/foo_logo/logo");
+    req.header("Content-Type", "image/png");
+    req.with_body(b"\x89PNG\r\n\x1a\nlogo");
+    ::sign_in_as(&mut req, &other);
+    bad_resp!(middle.call(&mut req));
+
+    ::sign_in_as(&mut req, &owner);
+    bad_resp!(middle.call(req.with_body(b"GIF89a")));
+    req.header("Content-Type", "image/svg+xml");
+    bad_resp!(middle.call(req.with_body(b"<svg onload=\"alert(1)\"></svg>")));
+    req.header("Content-Type", "image/png");
+    ok_resp!(middle.call(req.with_body(b"\x89PNG\r\n\x1a\nlogo")));
+
+    let mut show = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_logo");
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CrateResponse = ::json(&mut response);
+    assert_eq!(json.krate.logo_url.unwrap(), "/api/v1/crates/foo_logo/logo");
+
+    let mut logo = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_logo/logo");
+    logo.header("Accept", "application/json");
+    let mut response = ok_resp!(middle.call(&mut logo));
+    let json: R = ::json(&mut response);
+    assert_eq!(
+        json.url,
+        "https://alexcrichton-test.s3.amazonaws.com/logos/foo_logo/foo_logo-de8da2e3171d849e.png"
+    );
+
+    ok_resp!(middle.call(req.with_method(Method::Delete)));
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CrateResponse = ::json(&mut response);
+    assert_eq!(json.krate.logo_url, None);
+    bad_resp!(middle.call(&mut logo));
 }
 
 #[test]
-fn new_krate_with_dependency() {
+fn suggest_crate_names() {
+    #[derive(Deserialize)]
+    struct Suggestions {
+        crates: Vec<String>,
+    }
+
     let (_b, app, middle) = ::app();
-    let dep = u::CrateDependency {
-        name: u::CrateName("foo_dep".to_string()),
-        optional: false,
-        default_features: true,
-        features: Vec::new(),
-        version_req: u::CrateVersionReq(semver::VersionReq::parse(">= 0").unwrap()),
-        target: None,
-        kind: None,
-    };
-    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_dep"), "1.0.0", vec![dep]);
     {
         let conn = app.diesel_database.get().unwrap();
-        let user = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &user);
-        ::CrateBuilder::new("foo_dep", user.id).expect_build(&conn);
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("serde", u.id)
+            .downloads(10)
+            .expect_build(&conn);
+        ::CrateBuilder::new("serde_json", u.id)
+            .downloads(100)
+            .expect_build(&conn);
+        ::CrateBuilder::new("serial", u.id)
+            .downloads(5)
+            .expect_build(&conn);
+        ::CrateBuilder::new("userdata", u.id).expect_build(&conn);
     }
 
-    let mut response = ok_resp!(middle.call(&mut req));
-    ::json::<GoodCrate>(&mut response);
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/suggest");
+    let mut response = ok_resp!(middle.call(req.with_query("q=ser")));
+    assert_eq!(
+        ::json::<Suggestions>(&mut response).crates,
+        ["serde_json", "serde", "serial"]
+    );
 
-    let path = ::git::checkout().join("ne/w_/new_dep");
-    assert!(path.exists());
-    let mut contents = String::new();
-    File::open(&path)
-        .unwrap()
-        .read_to_string(&mut contents)
-        .unwrap();
-    let p: git::Crate = serde_json::from_str(&contents).unwrap();
-    assert_eq!(p.name, "new_dep");
-    assert_eq!(p.vers, "1.0.0");
-    assert_eq!(p.deps.len(), 1);
-    assert_eq!(p.deps[0].name, "foo_dep");
+    let mut response = ok_resp!(middle.call(req.with_query("q=Serde")));
+    assert_eq!(
+        ::json::<Suggestions>(&mut response).crates,
+        ["serde", "serde_json"]
+    );
+
+    let mut response = ok_resp!(middle.call(req.with_query("q=serde-j")));
+    assert_eq!(::json::<Suggestions>(&mut response).crates, ["serde_json"]);
+
+    let mut response = ok_resp!(middle.call(req.with_query("q=%25")));
+    assert!(::json::<Suggestions>(&mut response).crates.is_empty());
 }
 
 #[test]
-fn new_krate_non_canon_crate_name_dependencies() {
+fn relevance_is_boosted_by_quality_score() {
     let (_b, app, middle) = ::app();
-    let deps = vec![u::CrateDependency {
-        name: u::CrateName("foo-dep".to_string()),
-        optional: false,
-        default_features: true,
-        features: Vec::new(),
-        version_req: u::CrateVersionReq(semver::VersionReq::parse(">= 0").unwrap()),
-        target: None,
-        kind: None,
-    }];
-    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_dep"), "1.0.0", deps);
     {
         let conn = app.diesel_database.get().unwrap();
-        let user = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &user);
-        ::CrateBuilder::new("foo-dep", user.id).expect_build(&conn);
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let plain = ::CrateBuilder::new("plain_crate", u.id)
+            .description("quality")
+            .version("1.0.0")
+            .expect_build(&conn);
+        let kept = ::CrateBuilder::new("kept_crate", u.id)
+            .description("quality")
+            .documentation("https://docs.rs/kept_crate")
+            .readme("a README long enough to count for something")
+            .version("1.0.0")
+            .expect_build(&conn);
+        ::CrateBuilder::new("new_crate", u.id)
+            .description("quality")
+            .expect_build(&conn);
+
+        for krate in &[plain, kept] {
+            let score = krate.quality_score(&conn).unwrap();
+            diesel::update(krate)
+                .set(crates::quality_score.eq(score))
+                .execute(&*conn)
+                .unwrap();
+        }
     }
 
-    let mut response = ok_resp!(middle.call(&mut req));
-    ::json::<GoodCrate>(&mut response);
+    let mut req = ::req(app, Method::Get, "/api/v1/crates");
+    let mut response = ok_resp!(middle.call(req.with_query("q=quality")));
+    let json: CrateList = ::json(&mut response);
+    assert_eq!(json.meta.total, 3);
+    assert_eq!(json.crates[0].name, "kept_crate");
+    assert_eq!(json.crates[1].name, "plain_crate");
+    assert_eq!(json.crates[2].name, "new_crate");
+    assert!(json.crates[0].quality_score > json.crates[1].quality_score);
+    assert_eq!(json.crates[1].quality_score, Some(0.4));
+    assert_eq!(json.crates[2].quality_score, None);
+
+    let mut response = ok_resp!(middle.call(req.with_query("sort=relevance")));
+    let json: CrateList = ::json(&mut response);
+    let names = json.crates.iter().map(|c| &*c.name).collect::<Vec<_>>();
+    assert_eq!(names, ["kept_crate", "plain_crate", "new_crate"]);
 }
 
 #[test]
-fn new_krate_with_wildcard_dependency() {
+fn quality_scores_are_updated_for_every_crate() {
+    let (_b, app, _middle) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let u = ::new_user("foo").create_or_update(&conn).unwrap();
+    let plain = ::CrateBuilder::new("plain_crate", u.id)
+        .version("1.0.0")
+        .expect_build(&conn);
+    let kept = ::CrateBuilder::new("kept_crate", u.id)
+        .documentation("https://docs.rs/kept_crate")
+        .readme("a README long enough to count for something")
+        .version("1.0.0")
+        .expect_build(&conn);
+
+    assert_eq!(Crate::update_quality_scores(&conn).unwrap(), 2);
+    for krate in &[plain, kept] {
+        let score = crates::table
+            .find(krate.id)
+            .select(crates::quality_score)
+            .first::<Option<f64>>(&*conn)
+            .unwrap();
+        assert_eq!(score, Some(krate.quality_score(&conn).unwrap()));
+    }
+}
+
+#[test]
+fn crate_license_follows_latest_version() {
     let (_b, app, middle) = ::app();
-    let dep = u::CrateDependency {
-        name: u::CrateName("foo_wild".to_string()),
-        optional: false,
-        default_features: true,
-        features: Vec::new(),
-        version_req: u::CrateVersionReq(semver::VersionReq::parse("*").unwrap()),
-        target: None,
-        kind: None,
-    };
-    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_wild"), "1.0.0", vec![dep]);
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_license/versions",
+    );
     {
         let conn = app.diesel_database.get().unwrap();
-        let user = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &user);
-        ::CrateBuilder::new("foo_wild", user.id).expect_build(&conn);
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("foo_license", u.id)
+            .version(::VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .version(::VersionBuilder::new("0.9.0").license(Some("Apache-2.0")))
+            .expect_build(&conn);
+
+        let max_version = krate.max_version(&conn).unwrap().unwrap();
+        let krate = krate.update_license(&conn, &max_version).unwrap();
+        assert_eq!(krate.license, Some("MIT".to_string()));
     }
-    let json = bad_resp!(middle.call(&mut req));
-    assert!(
-        json.errors[0].detail.contains("dependency constraints"),
-        "{:?}",
-        json.errors
-    );
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: VersionsList = ::json(&mut response);
+    assert_eq!(json.versions[0].num, "1.0.0");
+    assert_eq!(json.versions[0].license, Some("MIT".to_string()));
+    assert_eq!(json.versions[1].num, "0.9.0");
+    assert_eq!(json.versions[1].license, Some("Apache-2.0".to_string()));
 }
 
 #[test]
-fn new_krate_twice() {
+fn versions() {
     let (_b, app, middle) = ::app();
-    let mut krate = ::krate("foo_twice");
-    krate.description = Some("description".to_string());
-    let mut req = ::new_req_full(Arc::clone(&app), krate.clone(), "2.0.0", Vec::new());
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_versions/versions",
+    );
     {
         let conn = app.diesel_database.get().unwrap();
-        let user = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &user);
-        ::CrateBuilder::new("foo_twice", user.id).expect_build(&conn);
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_versions", u.id)
+            .version("0.5.1")
+            .version("1.0.0")
+            .version("0.5.0")
+            .expect_build(&conn);
     }
+
     let mut response = ok_resp!(middle.call(&mut req));
-    let json: GoodCrate = ::json(&mut response);
-    assert_eq!(json.krate.name, krate.name);
-    assert_eq!(json.krate.description, krate.description);
+    let json: VersionsList = ::json(&mut response);
+
+    assert_eq!(json.versions.len(), 3);
+    assert_eq!(json.versions[0].num, "1.0.0");
+    assert_eq!(json.versions[1].num, "0.5.1");
+    assert_eq!(json.versions[2].num, "0.5.0");
 }
 
 #[test]
-fn new_krate_wrong_user() {
-    let (_b, app, middle) = ::app();
+fn versions_filtered_sorted_and_paginated() {
+    use chrono::Duration;
 
-    let mut req = ::new_req(Arc::clone(&app), "foo_wrong", "2.0.0");
+    let (_b, app, middle) = ::app();
 
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_versions_query/versions",
+    );
     {
-        // Create the 'foo' crate with one user
         let conn = app.diesel_database.get().unwrap();
-        let user = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::CrateBuilder::new("foo_wrong", user.id).expect_build(&conn);
-
-        // But log in another
-        let user = ::new_user("bar").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &user);
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("foo_versions_query", u.id)
+            .version("0.5.1")
+            .version("1.0.0-beta.2")
+            .version("1.0.0")
+            .version("1.0.0-beta.11")
+            .version("0.5.0")
+            .expect_build(&conn);
+        update(versions::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::num.eq("1.0.0"))
+            .set(versions::yanked.eq(true))
+            .execute(&*conn)
+            .unwrap();
+        // Published on different days, in the order they were built
+        let nums = ["0.5.1", "1.0.0-beta.2", "1.0.0", "1.0.0-beta.11", "0.5.0"];
+        for (days_ago, num) in nums.iter().rev().enumerate() {
+            let created_at = Utc::now().naive_utc() - Duration::days(days_ago as i64);
+            update(versions::table)
+                .filter(versions::crate_id.eq(krate.id))
+                .filter(versions::num.eq(num))
+                .set(versions::created_at.eq(created_at))
+                .execute(&*conn)
+                .unwrap();
+        }
     }
 
-    let json = bad_resp!(middle.call(&mut req));
-    assert!(
-        json.errors[0]
-            .detail
-            .contains("this crate exists but you don't seem to be an owner.",),
-        "{:?}",
-        json.errors
+    let nums = |req: &mut ::conduit_test::MockRequest, query: &str| {
+        let mut response = ok_resp!(middle.call(req.with_query(query)));
+        ::json::<VersionsList>(&mut response)
+            .versions
+            .into_iter()
+            .map(|v| v.num)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        nums(&mut req, "include_yanked=false"),
+        ["1.0.0-beta.11", "1.0.0-beta.2", "0.5.1", "0.5.0"]
+    );
+    assert_eq!(
+        nums(&mut req, "sort=date"),
+        ["0.5.0", "1.0.0-beta.11", "1.0.0", "1.0.0-beta.2", "0.5.1"]
     );
+    assert_eq!(nums(&mut req, "per_page=2"), ["1.0.0", "1.0.0-beta.11"]);
+    assert_eq!(nums(&mut req, "per_page=2&page=3"), ["0.5.0"]);
+    let mut response = ok_resp!(middle.call(req.with_query("per_page=2&page=2")));
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["meta"]["total"], 5);
+    bad_resp!(middle.call(req.with_query("sort=size")));
 }
 
 #[test]
-fn new_krate_bad_name() {
+fn crates_with_every_version_yanked_are_flagged() {
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foobar", "2.0.0");
-    let user = ::sign_in(&mut req, &app);
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_all_yanked",
+    );
     {
-        let mut req = ::new_req(Arc::clone(&app), "snow☃", "2.0.0");
-        ::sign_in_as(&mut req, &user);
-        let json = bad_resp!(middle.call(&mut req));
-        assert!(
-            json.errors[0]
-                .detail
-                .contains("expected a valid crate name",),
-            "{:?}",
-            json.errors
-        );
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("foo_all_yanked", u.id)
+            .version("0.9.0")
+            .version("1.0.0")
+            .expect_build(&conn);
+        ::CrateBuilder::new("foo_some_yanked", u.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        // Its max version isn't cached yet, and is `0.0.0`
+        ::CrateBuilder::new("foo_zero", u.id)
+            .version("0.0.0")
+            .expect_build(&conn);
+        update(versions::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .set(versions::yanked.eq(true))
+            .execute(&*conn)
+            .unwrap();
+        krate.update_max_versions(&conn).unwrap();
     }
-    {
-        let mut req = ::new_req(Arc::clone(&app), "áccênts", "2.0.0");
-        ::sign_in_as(&mut req, &user);
-        let json = bad_resp!(middle.call(&mut req));
-        assert!(
-            json.errors[0]
-                .detail
-                .contains("expected a valid crate name",),
-            "{:?}",
-            json.errors
-        );
-    }
-}
 
-#[test]
-fn valid_feature_names() {
-    assert!(Crate::valid_feature("foo"));
-    assert!(!Crate::valid_feature(""));
-    assert!(!Crate::valid_feature("/"));
-    assert!(!Crate::valid_feature("%/%"));
-    assert!(Crate::valid_feature("a/a"));
-    assert!(Crate::valid_feature("32-column-tables"));
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: CrateResponse = ::json(&mut response);
+    assert!(json.krate.all_versions_yanked);
+    assert_eq!(json.krate.max_version, "0.0.0");
+    assert_eq!(json.krate.default_version, "1.0.0");
+
+    req.with_path("/api/v1/crates").with_query("letter=foo_");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: CrateList = ::json(&mut response);
+    let flags = json.crates
+        .iter()
+        .map(|c| (&*c.name, c.all_versions_yanked))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        flags,
+        [
+            ("foo_all_yanked", true),
+            ("foo_some_yanked", false),
+            ("foo_zero", false),
+        ]
+    );
 }
 
 #[test]
-fn new_krate_too_big() {
+fn versions_matching_a_requirement() {
+    #[derive(Deserialize)]
+    struct Matches {
+        best_match: Option<EncodableVersion>,
+        matches: Vec<EncodableVersion>,
+    }
+
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo_big", "1.0.0");
-    ::sign_in(&mut req, &app);
-    let files = [("foo_big-1.0.0/big", &[b'a'; 2000] as &[_])];
-    let body = ::new_crate_to_body(&new_crate("foo_big"), &files);
-    bad_resp!(middle.call(req.with_body(&body)));
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_versions_match/versions/match",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("foo_versions_match", u.id)
+            .version("1.1.0")
+            .version("1.2.0")
+            .version("1.2.5")
+            .version("1.3.0-beta.1")
+            .version("1.4.0")
+            .version("2.0.0")
+            .expect_build(&conn);
+        update(versions::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::num.eq("1.4.0"))
+            .set(versions::yanked.eq(true))
+            .execute(&*conn)
+            .unwrap();
+    }
+
+    let matching = |req: &mut ::conduit_test::MockRequest, query: &str| {
+        let mut response = ok_resp!(middle.call(req.with_query(query)));
+        let json = ::json::<Matches>(&mut response);
+        let nums = json.matches.into_iter().map(|v| v.num).collect::<Vec<_>>();
+        (json.best_match.map(|v| v.num), nums)
+    };
+    assert_eq!(
+        matching(&mut req, "req=%5E1.2"),
+        (Some("1.2.5".to_string()), vec!["1.2.5".to_string(), "1.2.0".to_string()])
+    );
+    assert_eq!(
+        matching(&mut req, "req=%3E%3D1.3.0-beta.1"),
+        (
+            Some("2.0.0".to_string()),
+            vec!["2.0.0".to_string(), "1.3.0-beta.1".to_string()]
+        )
+    );
+    assert_eq!(matching(&mut req, "req=%5E3"), (None, vec![]));
+    bad_resp!(middle.call(req.with_query("req=one")));
+    bad_resp!(middle.call(req.with_query("")));
 }
 
 #[test]
-fn new_krate_too_big_but_whitelisted() {
+fn uploading_new_version_touches_crate() {
+    use diesel::dsl::*;
+
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo_whitelist", "1.1.0");
+
+    let mut upload_req = ::new_req(Arc::clone(&app), "foo_versions_updated_at", "1.0.0");
+    let u = ::sign_in(&mut upload_req, &app);
+    ok_resp!(middle.call(&mut upload_req));
+
     {
         let conn = app.diesel_database.get().unwrap();
-        let user = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &user);
-        ::CrateBuilder::new("foo_whitelist", user.id)
-            .max_upload_size(2_000_000)
-            .expect_build(&conn);
+        diesel::update(crates::table)
+            .set(crates::updated_at.eq(crates::updated_at - 1.hour()))
+            .execute(&*conn)
+            .unwrap();
     }
-    let files = [("foo_whitelist-1.1.0/big", &[b'a'; 2000] as &[_])];
-    let body = ::new_crate_to_body(&new_crate("foo_whitelist"), &files);
-    let mut response = ok_resp!(middle.call(req.with_body(&body)));
-    ::json::<GoodCrate>(&mut response);
+
+    let mut show_req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_versions_updated_at",
+    );
+    let mut response = ok_resp!(middle.call(&mut show_req));
+    let json: CrateResponse = ::json(&mut response);
+    let updated_at_before = json.krate.updated_at;
+
+    let mut upload_req = ::new_req(Arc::clone(&app), "foo_versions_updated_at", "2.0.0");
+    ::sign_in_as(&mut upload_req, &u);
+    ok_resp!(middle.call(&mut upload_req));
+
+    let mut response = ok_resp!(middle.call(&mut show_req));
+    let json: CrateResponse = ::json(&mut response);
+    let updated_at_after = json.krate.updated_at;
+
+    assert_ne!(updated_at_before, updated_at_after);
 }
 
 #[test]
-fn new_krate_wrong_files() {
+fn new_wrong_token() {
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo", "1.1.0");
+    let mut req = ::new_req(Arc::clone(&app), "foo", "1.0.0");
+    bad_resp!(middle.call(&mut req));
+    drop(req);
+
+    let mut req = ::new_req(Arc::clone(&app), "foo", "1.0.0");
+    req.header("Authorization", "bad");
+    bad_resp!(middle.call(&mut req));
+    drop(req);
+
+    let mut req = ::new_req(Arc::clone(&app), "foo", "1.0.0");
     ::sign_in(&mut req, &app);
-    let data: &[u8] = &[1];
-    let files = [("foo-1.1.0/a", data), ("bar-1.1.0/a", data)];
-    let body = ::new_crate_to_body(&new_crate("foo"), &files);
-    bad_resp!(middle.call(req.with_body(&body)));
+    ::logout(&mut req);
+    req.header("Authorization", "bad");
+    bad_resp!(middle.call(&mut req));
 }
 
 #[test]
-fn new_krate_gzip_bomb() {
+fn new_bd_names() {
+    fn bad_name(name: &str) {
+        println!("testing: `{}`", name);
+        let (_b, app, middle) = ::app();
+        let mut req = ::new_req(Arc::clone(&app), name, "1.0.0");
+        ::sign_in(&mut req, &app);
+        let json = bad_resp!(middle.call(&mut req));
+        assert!(
+            json.errors[0]
+                .detail
+                .contains("expected a valid crate name",),
+            "{:?}",
+            json.errors
+        );
+    }
+
+    bad_name("");
+    bad_name("foo bar");
+    bad_name(&"a".repeat(MAX_NAME_LENGTH + 1));
+}
+
+#[test]
+fn new_krate() {
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo", "1.1.0");
+    let mut req = ::new_req(Arc::clone(&app), "foo_new", "1.0.0");
     ::sign_in(&mut req, &app);
-    let len = 512 * 1024;
-    let mut body = io::repeat(0).take(len);
-    let body =
-        ::new_crate_to_body_with_io(&new_crate("foo"), &mut [("foo-1.1.0/a", &mut body, len)]);
-    let json = bad_resp!(middle.call(req.with_body(&body)));
-    assert!(
-        json.errors[0]
-            .detail
-            .contains("too large when decompressed"),
-        "{:?}",
-        json.errors
-    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: GoodCrate = ::json(&mut response);
+    assert_eq!(json.krate.name, "foo_new");
+    assert_eq!(json.krate.max_version, "1.0.0");
 }
 
 #[test]
-fn new_krate_duplicate_version() {
+fn publish_source_is_audited() {
+    #[derive(Deserialize)]
+    struct R {
+        versions: Vec<EncodableVersionPublish>,
+    }
+
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo_dupe", "1.0.0");
-    {
-        let conn = app.diesel_database.get().unwrap();
-        let user = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &user);
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/crates/new");
+    let user = ::sign_in(&mut req, &app);
+    req.header("X-Cargo-Version", "1.26.0 (0e7c5a931 2018-04-06)")
+        .header("X-CI-Provider", "Travis-CI")
+        .with_body(&::new_crate_to_body(&new_crate("foo_source"), &[]));
+    ok_resp!(middle.call(&mut req));
 
-        ::CrateBuilder::new("foo_dupe", user.id)
-            .version(::VersionBuilder::new("1.0.0"))
-            .expect_build(&conn);
-    }
-    let json = bad_resp!(middle.call(&mut req));
-    assert!(
-        json.errors[0].detail.contains("already uploaded"),
-        "{:?}",
-        json.errors
+    req.with_method(Method::Get)
+        .with_path("/api/v1/crates/foo_source/audit");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    assert_eq!(json.versions.len(), 1);
+    let publish = &json.versions[0];
+    assert_eq!(publish.num, "1.1.0");
+    assert_eq!(publish.published_by, Some(user.gh_login.clone()));
+    assert_eq!(
+        publish.cargo_version,
+        Some("1.26.0 (0e7c5a931 2018-04-06)".to_string())
     );
+    assert_eq!(publish.ci_provider, Some("travis-ci".to_string()));
+    assert_eq!(publish.published_with, Some("session".to_string()));
+
+    req.with_path("/api/v1/crates/foo_source/versions");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: VersionsList = ::json(&mut response);
+    assert_eq!(json.versions[0].published_by, Some(user.gh_login.clone()));
+    assert_eq!(json.versions[0].published_with, Some("session".to_string()));
+
+    // Only the owners see where the versions were published from
+    let other = {
+        let conn = app.diesel_database.get().unwrap();
+        ::new_user("bar").create_or_update(&conn).unwrap()
+    };
+    ::sign_in_as(&mut req, &other);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: VersionsList = ::json(&mut response);
+    assert_eq!(json.versions[0].published_by, None);
+    assert_eq!(json.versions[0].published_with, None);
+
+    req.with_path("/api/v1/crates/foo_source/audit");
+    bad_resp!(middle.call(&mut req));
 }
 
 #[test]
-fn new_crate_similar_name() {
-    let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo_similar", "1.1.0");
-    {
-        let conn = app.diesel_database.get().unwrap();
-        let u = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &u);
-        ::CrateBuilder::new("Foo_similar", u.id).expect_build(&conn);
+fn metadata_changes_are_recorded() {
+    #[derive(Deserialize)]
+    struct R {
+        changes: Vec<EncodableCrateMetadataChange>,
     }
-    let json = bad_resp!(middle.call(&mut req));
-    assert!(
-        json.errors[0].detail.contains("previously named"),
-        "{:?}",
-        json.errors
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req_with_keywords(
+        Arc::clone(&app),
+        ::krate("foo_history"),
+        "1.0.0",
+        vec!["cli".into(), "parser".into()],
+    );
+    ::sign_in(&mut req, &app);
+    ok_resp!(middle.call(&mut req));
+    let body = ::new_req_body(
+        ::krate("foo_history"),
+        "1.1.0",
+        Vec::new(),
+        vec!["parser".into(), "json".into()],
+        Vec::new(),
+        HashMap::new(),
+    );
+    ok_resp!(middle.call(req.with_body(&body)));
+
+    req.with_method(Method::Get)
+        .with_path("/api/v1/crates/foo_history/metadata_changes");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    let changes = json.changes
+        .iter()
+        .filter(|c| c.field == "keywords")
+        .collect::<Vec<_>>();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].version, Some("1.1.0".to_string()));
+    assert_eq!(changes[0].added, ["json"]);
+    assert_eq!(changes[0].removed, ["cli"]);
+    assert_eq!(changes[1].version, Some("1.0.0".to_string()));
+    assert_eq!(changes[1].added, ["cli", "parser"]);
+    // The description didn't change with the second version
+    assert_eq!(
+        json.changes
+            .iter()
+            .filter(|c| c.field == "description")
+            .count(),
+        1
     );
 }
 
 #[test]
-fn new_crate_similar_name_hyphen() {
+fn new_krate_with_edition_and_binaries() {
+    use models::Binary;
+
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo-bar-hyphen", "1.1.0");
-    {
-        let conn = app.diesel_database.get().unwrap();
-        let u = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &u);
-        ::CrateBuilder::new("foo_bar_hyphen", u.id).expect_build(&conn);
-    }
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/crates/new");
+    ::sign_in(&mut req, &app);
+
+    let mut new_crate = new_crate("foo_edition");
+    new_crate
+        .features
+        .insert(u::FeatureName("cli".into()), Vec::new());
+    new_crate.edition = Some("2018".into());
+    new_crate.binaries = vec![Binary {
+        name: "foo-cli".into(),
+        required_features: vec!["cli".into()],
+    }];
+    req.with_body(&::new_crate_to_body(&new_crate, &[]));
+    ok_resp!(middle.call(&mut req));
+
+    let mut response = ok_resp!(middle.call(req.with_method(Method::Get).with_path(
+        "/api/v1/crates/foo_edition/1.1.0",
+    )));
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["version"]["edition"], "2018");
+    assert_eq!(
+        json["version"]["binaries"],
+        json!([{ "name": "foo-cli", "required_features": ["cli"] }])
+    );
+
+    let mut contents = String::new();
+    File::open(::git::checkout().join("fo/o_/foo_edition"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    let entry: git::Crate = serde_json::from_str(&contents).unwrap();
+    assert_eq!(entry.edition, Some("2018".to_string()));
+
+    new_crate.vers = u::CrateVersion(semver::Version::parse("1.2.0").unwrap());
+    new_crate.edition = Some("2017".into());
+    req.with_method(Method::Put)
+        .with_path("/api/v1/crates/new")
+        .with_body(&::new_crate_to_body(&new_crate, &[]));
     let json = bad_resp!(middle.call(&mut req));
     assert!(
-        json.errors[0].detail.contains("previously named"),
+        json.errors[0].detail.contains("unknown edition `2017`"),
         "{:?}",
         json.errors
     );
-}
 
-#[test]
-fn new_crate_similar_name_underscore() {
-    let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "foo_bar_underscore", "1.1.0");
-    {
-        let conn = app.diesel_database.get().unwrap();
-        let u = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::sign_in_as(&mut req, &u);
-        ::CrateBuilder::new("foo-bar-underscore", u.id).expect_build(&conn);
-    }
+    new_crate.edition = None;
+    new_crate.binaries[0].required_features = vec!["missing".into()];
+    req.with_body(&::new_crate_to_body(&new_crate, &[]));
     let json = bad_resp!(middle.call(&mut req));
     assert!(
-        json.errors[0].detail.contains("previously named"),
+        json.errors[0]
+            .detail
+            .contains("binary `foo-cli` requires the unknown feature `missing`"),
         "{:?}",
         json.errors
     );
 }
 
 #[test]
-fn new_krate_git_upload() {
+fn new_krate_with_token() {
     let (_b, app, middle) = ::app();
-    let mut req = ::new_req(Arc::clone(&app), "fgt", "1.0.0");
-    ::sign_in(&mut req, &app);
-    let mut response = ok_resp!(middle.call(&mut req));
+    let mut req = ::new_req(Arc::clone(&app), "foo_new", "1.0.0");
+
+    {
+        let conn = t!(app.diesel_database.get());
+        let user = t!(::new_user("foo").create_or_update(&conn));
+        let token = t!(ApiToken::insert(&conn, user.id, "bar"));
+        req.header("Authorization", &token.token);
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: GoodCrate = ::json(&mut response);
+    assert_eq!(json.krate.name, "foo_new");
+    assert_eq!(json.krate.max_version, "1.0.0");
+}
+
+#[test]
+fn new_krate_with_reserved_name() {
+    fn test_bad_name(name: &str) {
+        let (_b, app, middle) = ::app();
+        let mut req = ::new_req(Arc::clone(&app), name, "1.0.0");
+        ::sign_in(&mut req, &app);
+        let json = bad_resp!(middle.call(&mut req));
+        assert!(
+            json.errors[0]
+                .detail
+                .contains("cannot upload a crate with a reserved name",)
+        );
+    }
+
+    test_bad_name("std");
+    test_bad_name("STD");
+    test_bad_name("compiler-rt");
+    test_bad_name("compiler_rt");
+    test_bad_name("coMpiLer_Rt");
+}
+
+#[test]
+fn new_krate_weird_version() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo_weird", "0.0.0-pre");
+    ::sign_in(&mut req, &app);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: GoodCrate = ::json(&mut response);
+    assert_eq!(json.krate.name, "foo_weird");
+    assert_eq!(json.krate.max_version, "0.0.0-pre");
+}
+
+#[test]
+fn new_krate_with_dependency() {
+    let (_b, app, middle) = ::app();
+    let dep = u::CrateDependency {
+        name: u::CrateName("foo_dep".to_string()),
+        optional: false,
+        default_features: true,
+        features: Vec::new(),
+        version_req: u::CrateVersionReq(semver::VersionReq::parse(">= 0").unwrap()),
+        target: None,
+        kind: None,
+        registry: None,
+    };
+    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_dep"), "1.0.0", vec![dep]);
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        ::CrateBuilder::new("foo_dep", user.id).expect_build(&conn);
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
     ::json::<GoodCrate>(&mut response);
 
-    let path = ::git::checkout().join("3/f/fgt");
+    let path = ::git::checkout().join("ne/w_/new_dep");
     assert!(path.exists());
     let mut contents = String::new();
     File::open(&path)
@@ -908,252 +1596,2161 @@ fn new_krate_git_upload() {
         .read_to_string(&mut contents)
         .unwrap();
     let p: git::Crate = serde_json::from_str(&contents).unwrap();
-    assert_eq!(p.name, "fgt");
+    assert_eq!(p.name, "new_dep");
     assert_eq!(p.vers, "1.0.0");
-    assert!(p.deps.is_empty());
+    assert_eq!(p.deps.len(), 1);
+    assert_eq!(p.deps[0].name, "foo_dep");
+}
+
+#[test]
+fn new_krate_non_canon_crate_name_dependencies() {
+    let (_b, app, middle) = ::app();
+    let deps = vec![u::CrateDependency {
+        name: u::CrateName("foo-dep".to_string()),
+        optional: false,
+        default_features: true,
+        features: Vec::new(),
+        version_req: u::CrateVersionReq(semver::VersionReq::parse(">= 0").unwrap()),
+        target: None,
+        kind: None,
+        registry: None,
+    }];
+    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_dep"), "1.0.0", deps);
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        ::CrateBuilder::new("foo-dep", user.id).expect_build(&conn);
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    ::json::<GoodCrate>(&mut response);
+}
+
+#[test]
+fn new_krate_with_wildcard_dependency() {
+    let (_b, app, middle) = ::app();
+    let dep = u::CrateDependency {
+        name: u::CrateName("foo_wild".to_string()),
+        optional: false,
+        default_features: true,
+        features: Vec::new(),
+        version_req: u::CrateVersionReq(semver::VersionReq::parse("*").unwrap()),
+        target: None,
+        kind: None,
+        registry: None,
+    };
+    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_wild"), "1.0.0", vec![dep]);
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        ::CrateBuilder::new("foo_wild", user.id).expect_build(&conn);
+    }
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("dependency constraints"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_with_name_outside_name_policy() {
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.name_policy.max_length = 12;
+        config.name_policy.forbid_confusable = true;
+    });
+    let user = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo-policy", user.id).expect_build(&conn);
+        user
+    };
+
+    let mut req = ::new_req(Arc::clone(&app), "foo_policy_too_long", "1.0.0");
+    ::sign_in_as(&mut req, &user);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("the maximum length is 12 characters"),
+        "{:?}",
+        json.errors
+    );
+
+    let mut req = ::new_req(Arc::clone(&app), "foopolicy", "1.0.0");
+    ::sign_in_as(&mut req, &user);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("too similar to the existing crate `foo-policy`"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_with_dependency_outside_allowlist() {
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.dependency_policy.allowed_crates = Some(vec!["foo_allowed".to_string()])
+    });
+    let dep = u::CrateDependency {
+        name: u::CrateName("foo_denied".to_string()),
+        optional: false,
+        default_features: true,
+        features: Vec::new(),
+        version_req: u::CrateVersionReq(semver::VersionReq::parse("^1.0").unwrap()),
+        target: None,
+        kind: None,
+        registry: None,
+    };
+    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_denied"), "1.0.0", vec![dep]);
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        ::CrateBuilder::new("foo_denied", user.id).expect_build(&conn);
+    }
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("allowlist of dependencies"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_with_dependency_on_other_registry() {
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.dependency_policy.allowed_registries = vec!["https://example.com/index".into()]
+    });
+    let dep = u::CrateDependency {
+        name: u::CrateName("foo_elsewhere".to_string()),
+        optional: false,
+        default_features: true,
+        features: Vec::new(),
+        version_req: u::CrateVersionReq(semver::VersionReq::parse("^1.0").unwrap()),
+        target: None,
+        kind: None,
+        registry: Some("https://other.example.com/index".to_string()),
+    };
+    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_other"), "1.0.0", vec![dep]);
+    ::sign_in(&mut req, &app);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("allowlist of registries"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn index_entries_include_dependencies_on_other_registries() {
+    let (_b, app, _middle) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let user = ::new_user("foo").create_or_update(&conn).unwrap();
+    let krate = ::CrateBuilder::new("foo_registries", user.id).expect_build(&conn);
+    let version_id = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .select(versions::id)
+        .first::<i32>(&*conn)
+        .unwrap();
+    insert_into(registry_dependencies::table)
+        .values((
+            registry_dependencies::version_id.eq(version_id),
+            registry_dependencies::registry.eq("https://example.com/index"),
+            registry_dependencies::name.eq("bar"),
+            registry_dependencies::req.eq("^1.0"),
+            registry_dependencies::optional.eq(false),
+            registry_dependencies::default_features.eq(true),
+            registry_dependencies::features.eq(Vec::<String>::new()),
+        ))
+        .execute(&*conn)
+        .unwrap();
+
+    let entries = krate.index_entries(&conn).unwrap();
+    assert_eq!(entries[0].deps.len(), 1);
     assert_eq!(
-        p.cksum,
-        "acb5604b126ac894c1eb11c4575bf2072fea61232a888e453770c79d7ed56419"
+        entries[0].deps[0].registry,
+        Some("https://example.com/index".to_string())
+    );
+    let line = serde_json::to_string(&entries[0]).unwrap();
+    assert!(line.contains("\"registry\":\"https://example.com/index\""), "{}", line);
+}
+
+#[test]
+fn new_krate_with_dependency_on_yanked_versions() {
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.dependency_policy.reject_yanked = true;
+    });
+    let dep = u::CrateDependency {
+        name: u::CrateName("foo_yanked_dep".to_string()),
+        optional: false,
+        default_features: true,
+        features: Vec::new(),
+        version_req: u::CrateVersionReq(semver::VersionReq::parse("^1.0").unwrap()),
+        target: None,
+        kind: None,
+        registry: None,
+    };
+    let mut req = ::new_req_full(Arc::clone(&app), ::krate("new_yanked"), "1.0.0", vec![dep]);
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        let dep = ::CrateBuilder::new("foo_yanked_dep", user.id)
+            .version("1.0.0")
+            .version("2.0.0")
+            .expect_build(&conn);
+        let v1 = versions::table
+            .filter(versions::crate_id.eq(dep.id))
+            .filter(versions::num.eq("1.0.0"));
+        update(v1)
+            .set(versions::yanked.eq(true))
+            .execute(&*conn)
+            .unwrap();
+    }
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("every version of `foo_yanked_dep` matching the requirement"),
+        "{:?}",
+        json.errors
     );
 }
 
 #[test]
-fn new_krate_git_upload_appends() {
+fn new_krate_twice() {
+    let (_b, app, middle) = ::app();
+    let mut krate = ::krate("foo_twice");
+    krate.description = Some("description".to_string());
+    let mut req = ::new_req_full(Arc::clone(&app), krate.clone(), "2.0.0", Vec::new());
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        ::CrateBuilder::new("foo_twice", user.id).expect_build(&conn);
+    }
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: GoodCrate = ::json(&mut response);
+    assert_eq!(json.krate.name, krate.name);
+    assert_eq!(json.krate.description, krate.description);
+}
+
+#[test]
+fn new_krate_wrong_user() {
+    let (_b, app, middle) = ::app();
+
+    let mut req = ::new_req(Arc::clone(&app), "foo_wrong", "2.0.0");
+
+    {
+        // Create the 'foo' crate with one user
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_wrong", user.id).expect_build(&conn);
+
+        // But log in another
+        let user = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+    }
+
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("this crate exists but you don't seem to be an owner.",),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_requires_verified_email_when_configured() {
+    let (_b, app, middle) = ::app_with_config(|config| config.require_verified_email = true);
+    let mut req = ::new_req(Arc::clone(&app), "foo_unverified", "1.0.0");
+    ::sign_in(&mut req, &app);
+
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("A verified email address is required to publish crates."),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_requires_two_factor_when_configured() {
+    use cargo_registry::middleware::current_user::TwoFactorVerified;
+    use cargo_registry::totp;
+    use conduit::Request;
+    use models::TwoFactorCredential;
+
+    let (_b, app, middle) = ::app_with_config(|config| config.require_two_factor = true);
+    let mut req = ::new_req(Arc::clone(&app), "foo_two_factor", "1.0.0");
+    let user = ::sign_in(&mut req, &app);
+
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("two-factor authentication is required"),
+        "{:?}",
+        json.errors
+    );
+
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let credential = TwoFactorCredential::begin(&conn, user.id).unwrap();
+        let code = totp::code_at(&credential.secret, Utc::now().timestamp()).unwrap();
+        credential.enable(&conn, &code).unwrap();
+    }
+    let mut req = ::new_req(Arc::clone(&app), "foo_two_factor", "1.0.0");
+    ::sign_in_as(&mut req, &user);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("must be verified with a two-factor authentication code"),
+        "{:?}",
+        json.errors
+    );
+
+    let mut req = ::new_req(Arc::clone(&app), "foo_two_factor", "1.0.0");
+    ::sign_in_as(&mut req, &user);
+    req.mut_extensions().insert(TwoFactorVerified);
+    ok_resp!(middle.call(&mut req));
+}
+
+#[test]
+fn new_krate_requires_confirmation_when_enabled() {
+    use models::{NewUser, PendingPublish, User};
+    use schema::{emails, pending_publishes, users};
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo_confirm", "1.0.0");
+    let user = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = NewUser {
+            email: Some("foo@example.com"),
+            ..::new_user("foo")
+        }.create_or_update(&conn)
+            .unwrap();
+        update(emails::table)
+            .set(emails::verified.eq(true))
+            .execute(&*conn)
+            .unwrap();
+        let user = update(&user)
+            .set(users::confirm_new_crates.eq(true))
+            .get_result::<User>(&*conn)
+            .unwrap();
+        ::sign_in_as(&mut req, &user);
+        user
+    };
+
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("publishing the new crate `foo_confirm` must be confirmed"),
+        "{:?}",
+        json.errors
+    );
+
+    let token = {
+        let conn = app.diesel_database.get().unwrap();
+        pending_publishes::table
+            .select(pending_publishes::token)
+            .first::<String>(&*conn)
+            .unwrap()
+    };
+    // The emailed link confirms the publication too
+    let mut confirm = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/confirm_publish/unknown",
+    );
+    assert_eq!(t_resp!(middle.call(&mut confirm)).status.0, 404);
+    confirm.with_path(&format!("/api/v1/confirm_publish/{}", token));
+    let mut response = ok_resp!(middle.call(&mut confirm));
+    let mut body = Vec::new();
+    response.body.write_body(&mut body).unwrap();
+    assert!(String::from_utf8(body).unwrap().contains("publication is confirmed"));
+
+    let conn = app.diesel_database.get().unwrap();
+    assert!(PendingPublish::is_confirmed(&conn, &user, "foo_confirm").unwrap());
+    assert!(PendingPublish::take_confirmed(&conn, &user, "foo_confirm").unwrap());
+    assert!(!PendingPublish::take_confirmed(&conn, &user, "foo_confirm").unwrap());
+}
+
+#[test]
+fn new_krate_bad_name() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foobar", "2.0.0");
+    let user = ::sign_in(&mut req, &app);
+    {
+        let mut req = ::new_req(Arc::clone(&app), "snow☃", "2.0.0");
+        ::sign_in_as(&mut req, &user);
+        let json = bad_resp!(middle.call(&mut req));
+        assert!(
+            json.errors[0]
+                .detail
+                .contains("expected a valid crate name",),
+            "{:?}",
+            json.errors
+        );
+    }
+    {
+        let mut req = ::new_req(Arc::clone(&app), "áccênts", "2.0.0");
+        ::sign_in_as(&mut req, &user);
+        let json = bad_resp!(middle.call(&mut req));
+        assert!(
+            json.errors[0]
+                .detail
+                .contains("expected a valid crate name",),
+            "{:?}",
+            json.errors
+        );
+    }
+}
+
+#[test]
+fn valid_feature_names() {
+    assert!(Crate::valid_feature("foo"));
+    assert!(!Crate::valid_feature(""));
+    assert!(!Crate::valid_feature("/"));
+    assert!(!Crate::valid_feature("%/%"));
+    assert!(Crate::valid_feature("a/a"));
+    assert!(Crate::valid_feature("32-column-tables"));
+}
+
+#[test]
+fn new_krate_too_big() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo_big", "1.0.0");
+    ::sign_in(&mut req, &app);
+    let files = [("foo_big-1.0.0/big", &[b'a'; 2000] as &[_])];
+    let body = ::new_crate_to_body(&new_crate("foo_big"), &files);
+    bad_resp!(middle.call(req.with_body(&body)));
+}
+
+#[test]
+fn new_krate_too_big_but_whitelisted() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo_whitelist", "1.1.0");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        ::CrateBuilder::new("foo_whitelist", user.id)
+            .max_upload_size(2_000_000)
+            .expect_build(&conn);
+    }
+    let files = [("foo_whitelist-1.1.0/big", &[b'a'; 2000] as &[_])];
+    let body = ::new_crate_to_body(&new_crate("foo_whitelist"), &files);
+    let mut response = ok_resp!(middle.call(req.with_body(&body)));
+    ::json::<GoodCrate>(&mut response);
+}
+
+#[test]
+fn new_krate_wrong_files() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo", "1.1.0");
+    ::sign_in(&mut req, &app);
+    let data: &[u8] = &[1];
+    let files = [("foo-1.1.0/a", data), ("bar-1.1.0/a", data)];
+    let body = ::new_crate_to_body(&new_crate("foo"), &files);
+    bad_resp!(middle.call(req.with_body(&body)));
+}
+
+#[test]
+fn new_krate_gzip_bomb() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo", "1.1.0");
+    ::sign_in(&mut req, &app);
+    let len = 512 * 1024;
+    let mut body = io::repeat(0).take(len);
+    let body =
+        ::new_crate_to_body_with_io(&new_crate("foo"), &mut [("foo-1.1.0/a", &mut body, len)]);
+    let json = bad_resp!(middle.call(req.with_body(&body)));
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("too large when decompressed"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_duplicate_version() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo_dupe", "1.0.0");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+
+        ::CrateBuilder::new("foo_dupe", user.id)
+            .version(::VersionBuilder::new("1.0.0"))
+            .expect_build(&conn);
+    }
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("already uploaded"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_crate_similar_name() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo_similar", "1.1.0");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &u);
+        ::CrateBuilder::new("Foo_similar", u.id).expect_build(&conn);
+    }
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("previously named"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_crate_similar_name_hyphen() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo-bar-hyphen", "1.1.0");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &u);
+        ::CrateBuilder::new("foo_bar_hyphen", u.id).expect_build(&conn);
+    }
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("previously named"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_crate_similar_name_underscore() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "foo_bar_underscore", "1.1.0");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &u);
+        ::CrateBuilder::new("foo-bar-underscore", u.id).expect_build(&conn);
+    }
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("previously named"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_git_upload() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::new_req(Arc::clone(&app), "fgt", "1.0.0");
+    ::sign_in(&mut req, &app);
+    let mut response = ok_resp!(middle.call(&mut req));
+    ::json::<GoodCrate>(&mut response);
+
+    let path = ::git::checkout().join("3/f/fgt");
+    assert!(path.exists());
+    let mut contents = String::new();
+    File::open(&path)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    let p: git::Crate = serde_json::from_str(&contents).unwrap();
+    assert_eq!(p.name, "fgt");
+    assert_eq!(p.vers, "1.0.0");
+    assert!(p.deps.is_empty());
+    assert_eq!(
+        p.cksum,
+        "acb5604b126ac894c1eb11c4575bf2072fea61232a888e453770c79d7ed56419"
+    );
+}
+
+#[test]
+fn index_entries_match_the_git_index() {
+    let (_b, app, middle) = ::app();
+    let user = {
+        let conn = app.diesel_database.get().unwrap();
+        ::new_user("foo").create_or_update(&conn).unwrap()
+    };
+    for version in &["1.0.0", "1.1.0"] {
+        let mut req = ::new_req(Arc::clone(&app), "fgt_index", version);
+        ::sign_in_as(&mut req, &user);
+        ok_resp!(middle.call(&mut req));
+    }
+    let mut contents = String::new();
+    File::open(::git::checkout().join("fg/t_/fgt_index"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/fgt_index/index",
+    );
+    let mut resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 200);
+    let mut body = Vec::new();
+    resp.body.write_body(&mut body).unwrap();
+    assert_eq!(String::from_utf8(body).unwrap(), contents);
+}
+
+#[test]
+fn new_krate_held_for_review() {
+    let (_b, app, middle) = ::app_with_config(|config| config.publish_hold_days = Some(7));
+    let mut req = ::new_req(Arc::clone(&app), "foo_held", "1.0.0");
+    let user = ::sign_in(&mut req, &app);
+    let mut response = ok_resp!(middle.call(&mut req));
+    ::json::<GoodCrate>(&mut response);
+
+    // The account was just created, so the crate is held back from the index
+    let path = ::git::checkout().join("fo/o_/foo_held");
+    assert!(!path.exists());
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let krate = Crate::by_name("foo_held").first::<Crate>(&*conn).unwrap();
+        assert!(krate.publish_hold);
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_held/1.0.0/download",
+    );
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 404);
+    {
+        use cargo_registry::models::CrateWebhook;
+
+        let conn = app.diesel_database.get().unwrap();
+        let krate = Crate::by_name("foo_held").first::<Crate>(&*conn).unwrap();
+        CrateWebhook::create(&conn, &krate, "https://example.com/hook").unwrap();
+    }
+
+    // Only administrators can approve it
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/admin/crates/foo_held/approve",
+    );
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+
+    let admin = {
+        let conn = app.diesel_database.get().unwrap();
+        ::new_admin("admin").create_or_update(&conn).unwrap()
+    };
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+    assert!(path.exists());
+    {
+        use cargo_registry::models::WebhookDelivery;
+        use cargo_registry::schema::webhook_deliveries;
+
+        // The webhooks are only notified once the crate is approved
+        let conn = app.diesel_database.get().unwrap();
+        let deliveries = webhook_deliveries::table
+            .load::<WebhookDelivery>(&*conn)
+            .unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].event, "publish");
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_held/1.0.0/download",
+    );
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 302);
+}
+
+#[test]
+fn held_crates_are_not_downloaded_from_mirrors() {
+    use cargo_registry::Replica;
+
+    let (_b, app, middle) = ::app_with_config(|config| config.mirror = Replica::ReadOnlyMirror);
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("foo_held", user.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        update(&krate)
+            .set(crates::publish_hold.eq(true))
+            .execute(&*conn)
+            .unwrap();
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_held/1.0.0/download",
+    );
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 404);
+}
+
+#[test]
+fn admins_rename_crates() {
+    use cargo_registry::models::CrateRename;
+
+    let (_b, app, middle) = ::app();
+    let (user, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_old", user.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        (user, admin)
+    };
+    let old_path = ::git::checkout().join("fo/o_/foo_old");
+    fs::create_dir_all(old_path.parent().unwrap()).unwrap();
+    File::create(&old_path)
+        .unwrap()
+        .write_all(
+            br#"{"name":"foo_old","vers":"1.0.0","deps":[],"features":{},"cksum":"3j3"}
+"#,
+        )
+        .unwrap();
+
+    // Only administrators can rename crates
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/admin/crates/foo_old/rename",
+    );
+    req.with_body(br#"{"name":"foo_new"}"#);
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+
+    // The former name resolves to the renamed crate
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_old");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["crate"]["name"], "foo_new");
+    assert_eq!(json["moved_to"], "foo_new");
+    req.with_path("/api/v1/crates/foo_new");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: serde_json::Value = ::json(&mut response);
+    assert!(json.get("moved_to").is_none());
+
+    // Files published before the rename are still downloaded from their path
+    req.with_path("/api/v1/crates/foo_new/1.0.0/download");
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 302);
+    assert!(resp.headers["Location"][0].ends_with("/crates/foo_old/foo_old-1.0.0.crate"));
+
+    // The former name can't be taken by a new crate
+    let mut req = ::new_req(Arc::clone(&app), "foo-old", "1.0.0");
+    ::sign_in_as(&mut req, &user);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(json.errors[0].detail.contains("former name"));
+
+    // The index entries are copied in the background, the crates depending
+    // on the former name keep resolving
+    assert_eq!(CrateRename::rewrite_pending_index_entries(&app).unwrap(), 1);
+    assert!(old_path.exists());
+    let mut contents = String::new();
+    File::open(::git::checkout().join("fo/o_/foo_new"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    let entry: git::Crate = serde_json::from_str(contents.trim()).unwrap();
+    assert_eq!(entry.name, "foo_new");
+    assert_eq!(CrateRename::rewrite_pending_index_entries(&app).unwrap(), 0);
+
+    // A crate renamed back to a former name can keep being published
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/admin/crates/foo_new/rename",
+    );
+    req.with_body(br#"{"name":"foo_old"}"#);
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+    let conn = app.diesel_database.get().unwrap();
+    assert!(!CrateRename::is_former_name(&conn, "foo_old").unwrap());
+    assert!(CrateRename::is_former_name(&conn, "foo_new").unwrap());
+}
+
+#[test]
+fn admins_sync_index_entries() {
+    #[derive(Deserialize)]
+    struct R {
+        changed: bool,
+        versions: usize,
+    }
+
+    let (_b, app, middle) = ::app();
+    let (user, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_sync", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(&conn);
+        (user, admin)
+    };
+    // The index lost 1.1.0 and still has a version that doesn't exist
+    let path = ::git::checkout().join("fo/o_/foo_sync");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    File::create(&path)
+        .unwrap()
+        .write_all(
+            br#"{"name":"foo_sync","vers":"0.9.0","deps":[],"features":{},"cksum":"1a1"}
+{"name":"foo_sync","vers":"1.0.0","deps":[],"features":{},"cksum":"3j3"}
+"#,
+        )
+        .unwrap();
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Post,
+        "/api/v1/admin/crates/foo_sync/sync_index",
+    );
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    assert!(json.changed);
+    assert_eq!(json.versions, 2);
+
+    let mut contents = String::new();
+    File::open(::git::checkout().join("fo/o_/foo_sync"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    let entries = contents
+        .lines()
+        .map(|line| serde_json::from_str::<git::Crate>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].vers, "1.0.0");
+    assert_eq!(entries[0].cksum, "3j3");
+    assert_eq!(entries[1].vers, "1.1.0");
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    assert!(!json.changed);
+}
+
+#[test]
+fn index_commits_are_signed() {
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+
+    let key = |rsa| PKey::from_rsa(rsa).unwrap();
+    let signing_key = key(Rsa::generate(2048).unwrap());
+    let other_key = key(Rsa::generate(2048).unwrap());
+    let private_key = String::from_utf8(signing_key.private_key_to_pem().unwrap()).unwrap();
+    let public_key = String::from_utf8(signing_key.public_key_to_pem().unwrap()).unwrap();
+    let other_public_key = String::from_utf8(other_key.public_key_to_pem().unwrap()).unwrap();
+
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.index_signing_key = Some(git::SigningKey::from_pem(&private_key).unwrap());
+    });
+    let admin = {
+        let conn = app.diesel_database.get().unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_signed", admin.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        admin
+    };
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Post,
+        "/api/v1/admin/crates/foo_signed/sync_index",
+    );
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+
+    let repo = git2::Repository::open(&::git::bare()).unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert!(head.message().unwrap().contains(git::SIGNATURE_TRAILER));
+    assert!(git::verify_commit(&public_key, &head).unwrap());
+    assert!(!git::verify_commit(&other_public_key, &head).unwrap());
+    // The commits made before the key was configured aren't signed
+    let parent = head.parent(0).unwrap();
+    assert!(!git::verify_commit(&public_key, &parent).unwrap());
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/index_signing_key.pem");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let mut body = Vec::new();
+    response.body.write_body(&mut body).unwrap();
+    assert_eq!(String::from_utf8(body).unwrap(), public_key);
+}
+
+#[test]
+fn index_outbox_entries_are_written_once() {
+    use cargo_registry::models::{IndexOutboxEntry, Version};
+
+    let (_b, app, _middle) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let user = ::new_user("foo").create_or_update(&conn).unwrap();
+    let krate = ::CrateBuilder::new("foo_outbox", user.id)
+        .version("1.0.0")
+        .version("1.1.0")
+        .expect_build(&conn);
+    let versions = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .order(versions::id)
+        .load::<Version>(&*conn)
+        .unwrap();
+    let entry = |vers: &str| git::Crate {
+        name: "foo_outbox".into(),
+        vers: vers.into(),
+        deps: Vec::new(),
+        cksum: "3j3".into(),
+        features: HashMap::new(),
+        yanked: Some(false),
+        links: None,
+        edition: None,
+    };
+
+    // 1.0.0 was written to the index before the server stopped, but not
+    // removed from the outbox
+    let path = ::git::checkout().join("fo/o_/foo_outbox");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    File::create(&path)
+        .unwrap()
+        .write_all(
+            br#"{"name":"foo_outbox","vers":"1.0.0","deps":[],"features":{},"cksum":"3j3"}
+"#,
+        )
+        .unwrap();
+    IndexOutboxEntry::create(&conn, &versions[0], &entry("1.0.0")).unwrap();
+    IndexOutboxEntry::create(&conn, &versions[1], &entry("1.1.0")).unwrap();
+
+    assert_eq!(IndexOutboxEntry::write_pending(&conn, &app).unwrap(), 2);
+    assert_eq!(IndexOutboxEntry::write_pending(&conn, &app).unwrap(), 0);
+
+    let mut contents = String::new();
+    File::open(&path)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    let entries = contents
+        .lines()
+        .map(|line| serde_json::from_str::<git::Crate>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].vers, "1.0.0");
+    assert_eq!(entries[1].vers, "1.1.0");
+}
+
+#[test]
+fn index_outbox_entries_that_fail_are_skipped() {
+    use cargo_registry::models::{IndexOutboxEntry, Version};
+    use schema::index_outbox;
+
+    let (_b, app, _middle) = ::app();
+    let conn = app.diesel_database.get().unwrap();
+    let user = ::new_user("foo").create_or_update(&conn).unwrap();
+    let krate = ::CrateBuilder::new("foo_outbox_skip", user.id)
+        .version("1.0.0")
+        .version("1.1.0")
+        .expect_build(&conn);
+    let versions = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .order(versions::id)
+        .load::<Version>(&*conn)
+        .unwrap();
+
+    // The entry of 1.0.0 can't be read back
+    insert_into(index_outbox::table)
+        .values((
+            index_outbox::version_id.eq(versions[0].id),
+            index_outbox::entry.eq(json!({ "name": "foo_outbox_skip" })),
+        ))
+        .execute(&*conn)
+        .unwrap();
+    let entry = git::Crate {
+        name: "foo_outbox_skip".into(),
+        vers: "1.1.0".into(),
+        deps: Vec::new(),
+        cksum: "3j3".into(),
+        features: HashMap::new(),
+        yanked: Some(false),
+        links: None,
+        edition: None,
+    };
+    IndexOutboxEntry::create(&conn, &versions[1], &entry).unwrap();
+
+    assert_eq!(IndexOutboxEntry::write_pending(&conn, &app).unwrap(), 1);
+    let failed = index_outbox::table
+        .load::<IndexOutboxEntry>(&*conn)
+        .unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].version_id, versions[0].id);
+    assert_eq!(failed[0].attempts, 1);
+    assert!(failed[0].last_error.is_some());
+
+    let mut contents = String::new();
+    File::open(::git::checkout().join("fo/o_/foo_outbox_skip"))
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert!(contents.contains(r#""vers":"1.1.0""#));
+}
+
+#[test]
+fn admins_lock_crates() {
+    let (_b, app, middle) = ::app();
+    let (user, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_locked", user.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        (user, admin)
+    };
+
+    // Only administrators can lock crates
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/admin/crates/foo_locked/lock",
+    );
+    req.with_body(br#"{"reason":"ownership dispute"}"#);
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+
+    // Publishes to the locked crate are rejected with the reason
+    let mut publish = ::new_req(Arc::clone(&app), "foo_locked", "1.1.0");
+    ::sign_in_as(&mut publish, &user);
+    let json = bad_resp!(middle.call(&mut publish));
+    assert!(
+        json.errors[0].detail.contains("ownership dispute"),
+        "{:?}",
+        json.errors
+    );
+
+    req.with_method(Method::Delete);
+    ok_resp!(middle.call(&mut req));
+    let mut publish = ::new_req(Arc::clone(&app), "foo_locked", "1.1.0");
+    ::sign_in_as(&mut publish, &user);
+    ok_resp!(middle.call(&mut publish));
+}
+
+#[test]
+fn admins_toggle_read_only_mode() {
+    let (_b, app, middle) = ::app();
+    let (user, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_read_only", user.id).expect_build(&conn);
+        (user, admin)
+    };
+
+    // Only administrators can turn the read-only mode on
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/admin/read_only");
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+
+    // The mode is stored for every server
+    {
+        use models::registry_flag::{RegistryFlag, READ_ONLY};
+
+        let conn = app.diesel_database.get().unwrap();
+        assert!(RegistryFlag::is_enabled(&conn, READ_ONLY).unwrap());
+    }
+
+    // Writes are refused, reads keep working
+    let mut follow = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/foo_read_only/follow",
+    );
+    ::sign_in_as(&mut follow, &user);
+    let mut response = t_resp!(middle.call(&mut follow));
+    assert_eq!(response.status.0, 503);
+    let json = ::bad_resp(&mut response).unwrap();
+    assert!(json.errors[0].detail.contains("read-only mode"));
+    let mut publish = ::new_req(Arc::clone(&app), "foo_read_only", "1.1.0");
+    ::sign_in_as(&mut publish, &user);
+    assert_eq!(t_resp!(middle.call(&mut publish)).status.0, 503);
+    follow
+        .with_method(Method::Get)
+        .with_path("/api/v1/crates/foo_read_only");
+    ok_resp!(middle.call(&mut follow));
+
+    req.with_method(Method::Delete);
+    ok_resp!(middle.call(&mut req));
+    follow
+        .with_method(Method::Put)
+        .with_path("/api/v1/crates/foo_read_only/follow");
+    ok_resp!(middle.call(&mut follow));
+}
+
+#[test]
+fn admins_transfer_contested_crates() {
+    #[derive(Deserialize)]
+    struct CrateStatus {
+        status: EncodableCrateStatus,
+    }
+
+    let (_b, app, middle) = ::app();
+    let (user, claimant, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let claimant = ::new_user("claimant").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_contested", user.id).expect_build(&conn);
+        (user, claimant, admin)
+    };
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_contested");
+    let mut response = ok_resp!(middle.call(&mut req));
+    assert!(!::json::<CrateStatus>(&mut response).status.contested);
+
+    // Only administrators can contest names, and the claimant must exist
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/admin/crates/foo_contested/transfer",
+    );
+    req.with_body(br#"{"claimant":"nobody","reason":"squatted","days":0}"#);
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    bad_resp!(middle.call(&mut req));
+    req.with_body(br#"{"claimant":"claimant","reason":"squatted","days":0}"#);
+    ok_resp!(middle.call(&mut req));
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_contested");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let status = ::json::<CrateStatus>(&mut response).status;
+    assert!(status.contested);
+    assert_eq!(status.reason.unwrap(), "squatted");
+
+    // Scheduling the transfer again replaces it
+    req.with_method(Method::Put)
+        .with_path("/api/v1/admin/crates/foo_contested/transfer")
+        .with_body(br#"{"claimant":"claimant","reason":"still squatted","days":0}"#);
+    ::sign_in_as(&mut req, &admin);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let status = ::json::<CrateStatus>(&mut response).status;
+    assert_eq!(status.reason.unwrap(), "still squatted");
+
+    // Once the grace window is over, the claimant is the only owner
+    let conn = app.diesel_database.get().unwrap();
+    assert_eq!(CrateTransfer::perform_due(&conn).unwrap(), 1);
+    let krate = Crate::by_name("foo_contested").first::<Crate>(&*conn).unwrap();
+    let owners = krate.owners(&conn).unwrap();
+    assert_eq!(owners.len(), 1);
+    match owners[0] {
+        Owner::User(ref owner) => assert_eq!(owner.id, claimant.id),
+        Owner::Team(_) => panic!("the crate was transferred to a team"),
+    }
+    assert!(CrateTransfer::find(&conn, &krate).unwrap().is_none());
+}
+
+#[test]
+fn admins_feature_crates_in_the_spotlight() {
+    let (_b, app, middle) = ::app();
+    let (user, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("popular", user.id)
+            .version("1.0.0")
+            .recent_downloads(100)
+            .expect_build(&conn);
+        ::CrateBuilder::new("hidden_gem", user.id)
+            .version("1.0.0")
+            .recent_downloads(10)
+            .expect_build(&conn);
+        (user, admin)
+    };
+
+    // Without a choice of the administrators, the most downloaded crate is
+    // picked and stays featured for the day
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/spotlight");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Spotlight = ::json(&mut response);
+    let spotlight = json.spotlight.unwrap();
+    assert_eq!(spotlight.krate.name, "popular");
+    assert!(spotlight.rationale.contains("100"), "{}", spotlight.rationale);
+
+    // Only administrators can choose the crate of the day
+    let today = Utc::today().format("%F").to_string();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/admin/crates/hidden_gem/spotlight",
+    );
+    let body = json!({ "date": today, "rationale": "Small and well documented" });
+    req.with_body(body.to_string().as_bytes());
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/spotlight");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: Spotlight = ::json(&mut response);
+    let spotlight = json.spotlight.unwrap();
+    assert_eq!(spotlight.krate.name, "hidden_gem");
+    assert_eq!(spotlight.featured_on, today);
+    assert_eq!(spotlight.rationale, "Small and well documented");
+}
+
+#[test]
+fn admins_delete_and_restore_crates() {
+    #[derive(Deserialize)]
+    struct DeletedCrates {
+        deleted_crates: Vec<EncodableDeletedCrate>,
+        meta: CrateMeta,
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut publish = ::new_req(Arc::clone(&app), "fdel", "1.0.0");
+    let user = ::sign_in(&mut publish, &app);
+    ok_resp!(middle.call(&mut publish));
+    let admin = {
+        let conn = app.diesel_database.get().unwrap();
+        ::new_admin("admin").create_or_update(&conn).unwrap()
+    };
+    let index_path = ::git::checkout().join("fd/el/fdel");
+    assert!(index_path.exists());
+
+    // Only administrators can delete crates
+    let mut req = ::req(Arc::clone(&app), Method::Delete, "/api/v1/admin/crates/fdel");
+    req.with_body(br#"{"reason":"malware"}"#);
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    ok_resp!(middle.call(&mut req));
+    assert!(!index_path.exists());
+
+    let mut show = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/fdel");
+    let resp = t_resp!(middle.call(&mut show));
+    assert_eq!(resp.status.0, 404);
+
+    // The name of the deleted crate can't be taken
+    let mut publish = ::new_req(Arc::clone(&app), "fdel", "2.0.0");
+    ::sign_in_as(&mut publish, &user);
+    let json = bad_resp!(middle.call(&mut publish));
+    assert!(
+        json.errors[0].detail.contains("reserved name"),
+        "{:?}",
+        json.errors
+    );
+
+    let mut list = ::req(Arc::clone(&app), Method::Get, "/api/v1/admin/deleted_crates");
+    ::sign_in_as(&mut list, &admin);
+    let mut response = ok_resp!(middle.call(&mut list));
+    let json: DeletedCrates = ::json(&mut response);
+    assert_eq!(json.meta.total, 1);
+    let deleted = &json.deleted_crates[0];
+    assert_eq!(deleted.name, "fdel");
+    assert_eq!(deleted.reason, Some("malware".to_string()));
+    assert_eq!(deleted.deleted_by, Some("admin".to_string()));
+    assert_eq!(deleted.versions, vec!["1.0.0"]);
+
+    let url = format!("/api/v1/admin/deleted_crates/{}/restore", deleted.id);
+    let mut restore = ::req(Arc::clone(&app), Method::Put, &url);
+    ::sign_in_as(&mut restore, &admin);
+    ok_resp!(middle.call(&mut restore));
+    assert!(index_path.exists());
+
+    let mut response = ok_resp!(middle.call(&mut show));
+    let json: CrateResponse = ::json(&mut response);
+    assert_eq!(json.krate.max_version, "1.0.0");
+    let mut publish = ::new_req(Arc::clone(&app), "fdel", "2.0.0");
+    ::sign_in_as(&mut publish, &user);
+    ok_resp!(middle.call(&mut publish));
+}
+
+#[test]
+fn new_krate_git_upload_appends() {
+    let (_b, app, middle) = ::app();
+    let path = ::git::checkout().join("3/f/fpp");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    File::create(&path)
+        .unwrap()
+        .write_all(
+            br#"{"name":"FPP","vers":"0.0.1","deps":[],"features":{},"cksum":"3j3"}
+"#,
+        )
+        .unwrap();
+
+    let mut req = ::new_req(Arc::clone(&app), "FPP", "1.0.0");
+    ::sign_in(&mut req, &app);
+    let mut response = ok_resp!(middle.call(&mut req));
+    ::json::<GoodCrate>(&mut response);
+
+    let mut contents = String::new();
+    File::open(&path)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    let mut lines = contents.lines();
+    let p1: git::Crate = serde_json::from_str(lines.next().unwrap().trim()).unwrap();
+    let p2: git::Crate = serde_json::from_str(lines.next().unwrap().trim()).unwrap();
+    assert!(lines.next().is_none());
+    assert_eq!(p1.name, "FPP");
+    assert_eq!(p1.vers, "0.0.1");
+    assert!(p1.deps.is_empty());
+    assert_eq!(p2.name, "FPP");
+    assert_eq!(p2.vers, "1.0.0");
+    assert!(p2.deps.is_empty());
+}
+
+#[test]
+fn new_krate_git_upload_with_conflicts() {
+    let (_b, app, middle) = ::app();
+
+    {
+        let repo = git2::Repository::open(&::git::bare()).unwrap();
+        let target = repo.head().unwrap().target().unwrap();
+        let sig = repo.signature().unwrap();
+        let parent = repo.find_commit(target).unwrap();
+        let tree = repo.find_tree(parent.tree_id()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "empty commit", &tree, &[&parent])
+            .unwrap();
+    }
+
+    let mut req = ::new_req(Arc::clone(&app), "foo_conflicts", "1.0.0");
+    ::sign_in(&mut req, &app);
+    let mut response = ok_resp!(middle.call(&mut req));
+    ::json::<GoodCrate>(&mut response);
+}
+
+#[test]
+fn new_krate_dependency_missing() {
+    let (_b, app, middle) = ::app();
+    let dep = u::CrateDependency {
+        optional: false,
+        default_features: true,
+        name: u::CrateName("bar_missing".to_string()),
+        features: Vec::new(),
+        version_req: u::CrateVersionReq(semver::VersionReq::parse(">= 0.0.0").unwrap()),
+        target: None,
+        kind: None,
+        registry: None,
+    };
+    let mut req = ::new_req_full(Arc::clone(&app), ::krate("foo_missing"), "1.0.0", vec![dep]);
+    ::sign_in(&mut req, &app);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json = ::json::<::Bad>(&mut response);
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("no known crate named `bar_missing`",)
+    );
+}
+
+#[test]
+fn new_krate_with_readme() {
+    let (_b, app, middle) = ::app();
+    let mut krate = ::krate("foo_readme");
+    krate.readme = Some("".to_owned());
+    let mut req = ::new_req_full(Arc::clone(&app), krate, "1.0.0", vec![]);
+    ::sign_in(&mut req, &app);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: GoodCrate = ::json(&mut response);
+    assert_eq!(json.krate.name, "foo_readme");
+    assert_eq!(json.krate.max_version, "1.0.0");
+}
+
+/// Compresses the JSON metadata of a publish body with gzip.
+fn gzip_metadata(body: &[u8]) -> Vec<u8> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let len = body[..4]
+        .iter()
+        .rev()
+        .fold(0, |len, &b| (len << 8) | b as usize);
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::Default);
+    encoder.write_all(&body[4..4 + len]).unwrap();
+    let json = encoder.finish().unwrap();
+
+    let mut gzipped = Vec::new();
+    gzipped.extend(&[
+        json.len() as u8,
+        (json.len() >> 8) as u8,
+        (json.len() >> 16) as u8,
+        (json.len() >> 24) as u8,
+    ]);
+    gzipped.extend(json);
+    gzipped.extend(&body[4 + len..]);
+    gzipped
+}
+
+#[test]
+fn new_krate_with_gzipped_metadata() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/crates/new");
+    req.with_body(&gzip_metadata(&::new_req_body_version_2(::krate("foo_gzip"))));
+    req.header("Content-Encoding", "gzip");
+    ::sign_in(&mut req, &app);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: GoodCrate = ::json(&mut response);
+    assert_eq!(json.krate.name, "foo_gzip");
+    assert_eq!(json.krate.max_version, "2.0.0");
+}
+
+#[test]
+fn new_krate_gzipped_metadata_too_big_when_decompressed() {
+    let (_b, app, middle) = ::app();
+    let mut krate = ::krate("foo_gzip_bomb");
+    krate.readme = Some(" ".repeat(5000));
+    let body = ::new_req_body(
+        krate,
+        "1.0.0",
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+        HashMap::new(),
+    );
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/crates/new");
+    req.with_body(&gzip_metadata(&body));
+    req.header("Content-Encoding", "gzip");
+    ::sign_in(&mut req, &app);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("larger than 1000 bytes"),
+        "{:?}",
+        json.errors
+    );
+
+    req.header("Content-Encoding", "br");
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("unsupported content encoding"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn summary_doesnt_die() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(app, Method::Get, "/api/v1/summary");
+    ok_resp!(middle.call(&mut req));
+}
+
+#[test]
+fn summary_new_crates() {
+    let (_b, app, middle) = ::app();
+    let u;
+    let krate;
+    let krate2;
+    let krate3;
+    {
+        let conn = app.diesel_database.get().unwrap();
+        u = ::new_user("foo").create_or_update(&conn).unwrap();
+
+        krate = ::CrateBuilder::new("some_downloads", u.id)
+            .version(::VersionBuilder::new("0.1.0"))
+            .description("description")
+            .keyword("popular")
+            .downloads(20)
+            .recent_downloads(10)
+            .expect_build(&conn);
+
+        krate2 = ::CrateBuilder::new("most_recent_downloads", u.id)
+            .version(::VersionBuilder::new("0.2.0"))
+            .keyword("popular")
+            .downloads(5000)
+            .recent_downloads(50)
+            .expect_build(&conn);
+
+        krate3 = ::CrateBuilder::new("just_updated", u.id)
+            .version(::VersionBuilder::new("0.1.0"))
+            .expect_build(&conn);
+
+        ::CrateBuilder::new("with_downloads", u.id)
+            .version(::VersionBuilder::new("0.3.0"))
+            .keyword("popular")
+            .downloads(1000)
+            .expect_build(&conn);
+
+        ::new_category("Category 1", "cat1")
+            .create_or_update(&conn)
+            .unwrap();
+        Category::update_crate(&conn, &krate, &["cat1"]).unwrap();
+        Category::update_crate(&conn, &krate2, &["cat1"]).unwrap();
+
+        // set total_downloads global value for `num_downloads` prop
+        update(metadata::table)
+            .set(metadata::total_downloads.eq(6000))
+            .execute(&*conn)
+            .unwrap();
+
+        // update 'just_updated' krate. Others won't appear because updated_at == created_at.
+        let updated = Utc::now().naive_utc();
+        update(&krate3)
+            .set(crates::updated_at.eq(updated))
+            .execute(&*conn)
+            .unwrap();
+    }
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/summary");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: SummaryResponse = ::json(&mut response);
+
+    assert_eq!(json.num_crates, 4);
+    assert_eq!(json.num_downloads, 6000);
+    assert_eq!(json.most_downloaded[0].name, "most_recent_downloads");
+    assert_eq!(
+        json.most_recently_downloaded[0].name,
+        "most_recent_downloads"
+    );
+    assert_eq!(json.popular_keywords[0].keyword, "popular");
+    assert_eq!(json.popular_categories[0].category, "Category 1");
+    assert_eq!(json.just_updated.len(), 1);
+    assert_eq!(json.just_updated[0].name, "just_updated");
+    assert_eq!(json.new_crates.len(), 4);
+    assert_eq!(
+        json.spotlight.unwrap().krate.name,
+        "most_recent_downloads"
+    );
+}
+
+#[test]
+fn summary_is_cached_until_invalidated() {
+    let (_b, app, middle) = ::app();
+    let u = {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("first", u.id).expect_build(&conn);
+        u
+    };
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/summary");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: SummaryResponse = ::json(&mut response);
+    assert_eq!(json.num_crates, 1);
+
+    {
+        let conn = app.diesel_database.get().unwrap();
+        ::CrateBuilder::new("second", u.id).expect_build(&conn);
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: SummaryResponse = ::json(&mut response);
+    assert_eq!(json.num_crates, 1);
+
+    app.invalidate_summary_cache();
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: SummaryResponse = ::json(&mut response);
+    assert_eq!(json.num_crates, 2);
+    assert_eq!(json.new_crates.len(), 2);
+}
+
+#[test]
+fn downloads_csv() {
+    use chrono::Duration;
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_csv/1.0.0/download",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_csv", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(&conn);
+    }
+    t_resp!(middle.call(&mut req));
+    t_resp!(middle.call(&mut req));
+    req.with_path("/api/v1/crates/foo_csv/1.1.0/download");
+    t_resp!(middle.call(&mut req));
+
+    let today = Utc::today().format("%F");
+    req.with_path("/api/v1/crates/foo_csv/downloads.csv");
+    let mut resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 200);
+    assert_eq!(
+        resp.headers["Content-Type"],
+        vec!["text/csv; charset=utf-8".to_string()]
+    );
+    let mut body = Vec::new();
+    resp.body.write_body(&mut body).unwrap();
+    let mut lines = String::from_utf8(body)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    assert_eq!(lines.remove(0), "date,version,downloads");
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![format!("{},1.0.0,2", today), format!("{},1.1.0,1", today)]
+    );
+
+    let tomorrow = Utc::today() + Duration::days(1);
+    req.with_query(&format!("since={}", tomorrow.format("%F")));
+    let mut resp = t_resp!(middle.call(&mut req));
+    let mut body = Vec::new();
+    resp.body.write_body(&mut body).unwrap();
+    assert_eq!(body, b"date,version,downloads\n");
+
+    req.with_query("since=yesterday");
+    bad_resp!(middle.call(&mut req));
+}
+
+#[test]
+fn downloads_total() {
+    use views::EncodableCrateDownload;
+
+    #[derive(Deserialize)]
+    struct Totals {
+        crate_downloads: Vec<EncodableCrateDownload>,
+        meta: CrateMeta,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_totals", user.id)
+            .version("1.0.0")
+            .downloads(20)
+            .recent_downloads(10)
+            .expect_build(&conn);
+    }
+
+    // Only the downloads of the last 90 days are listed
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_totals/downloads/total",
+    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json = ::json::<Totals>(&mut response);
+    assert_eq!(json.meta.total, 20);
+    assert_eq!(json.crate_downloads.len(), 1);
+    assert_eq!(json.crate_downloads[0].downloads, 10);
+    assert_eq!(
+        json.crate_downloads[0].date,
+        Utc::today().format("%F").to_string()
+    );
+}
+
+#[test]
+fn downloads_by_semver() {
+    use cargo_registry::models::krate::VersionLineDownloads;
+
+    #[derive(Deserialize)]
+    struct Lines {
+        lines: Vec<VersionLineDownloads>,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_lines", user.id)
+            .version("0.9.0")
+            .version("1.0.0")
+            .version("1.1.0")
+            .version("2.0.0-beta.1")
+            .expect_build(&conn);
+    }
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/");
+    for version in &["1.0.0", "1.0.0", "1.1.0", "0.9.0"] {
+        req.with_path(&format!("/api/v1/crates/foo_lines/{}/download", version));
+        t_resp!(middle.call(&mut req));
+    }
+
+    req.with_path("/api/v1/crates/foo_lines/downloads/by_semver");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json = ::json::<Lines>(&mut response);
+    let lines = json.lines
+        .iter()
+        .map(|l| (&*l.line, l.downloads, l.recent_downloads))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![("2", 0, 0), ("1", 3, 3), ("0", 1, 1)]);
+
+    let mut response = ok_resp!(middle.call(req.with_query("group=minor")));
+    let json = ::json::<Lines>(&mut response);
+    let lines = json.lines.iter().map(|l| &*l.line).collect::<Vec<_>>();
+    assert_eq!(lines, vec!["2.0", "1.1", "1.0", "0.9"]);
+
+    bad_resp!(middle.call(req.with_query("group=patch")));
+}
+
+#[test]
+fn download() {
+    use chrono::{Duration, Utc};
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_download/1.0.0/download",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_download", user.id)
+            .version(::VersionBuilder::new("1.0.0"))
+            .expect_build(&conn);
+    }
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 302);
+
+    req.with_path("/api/v1/crates/foo_download/1.0.0/downloads");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 1);
+    req.with_path("/api/v1/crates/foo_download/downloads");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 1);
+
+    req.with_path("/api/v1/crates/FOO_DOWNLOAD/1.0.0/download");
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 302);
+    // Variants of the name are sent to the files of the crate
+    assert!(
+        resp.headers["Location"][0].ends_with("/crates/foo_download/foo_download-1.0.0.crate")
+    );
+
+    req.with_path("/api/v1/crates/FOO_DOWNLOAD/1.0.0/downloads");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 1);
+    req.with_path("/api/v1/crates/FOO_DOWNLOAD/downloads");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 1);
+
+    let yesterday = Utc::today() + Duration::days(-1);
+    req.with_path("/api/v1/crates/FOO_DOWNLOAD/1.0.0/downloads");
+    req.with_query(&format!("before_date={}", yesterday.format("%F")));
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 0);
+    req.with_path("/api/v1/crates/FOO_DOWNLOAD/downloads");
+    req.with_query(&format!("before_date={}", yesterday.format("%F")));
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    // crate/downloads always returns the last 90 days and ignores `before_date`
+    assert_eq!(downloads.version_downloads.len(), 1);
+
+    let tomorrow = Utc::today() + Duration::days(1);
+    req.with_path("/api/v1/crates/FOO_DOWNLOAD/1.0.0/downloads");
+    req.with_query(&format!("before_date={}", tomorrow.format("%F")));
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 1);
+    req.with_path("/api/v1/crates/FOO_DOWNLOAD/downloads");
+    req.with_query(&format!("before_date={}", tomorrow.format("%F")));
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 1);
+
+    // Only the downloads since the given date are sent
+    req.with_query(&format!("since={}", yesterday.format("%F")));
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 1);
+    req.with_query(&format!("since={}", tomorrow.format("%F")));
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads.len(), 0);
+    bad_resp!(middle.call(req.with_query("since=yesterday")));
+}
+
+#[test]
+fn show_reports_name_variants() {
     let (_b, app, middle) = ::app();
-    let path = ::git::checkout().join("3/f/fpp");
-    fs::create_dir_all(path.parent().unwrap()).unwrap();
-    File::create(&path)
-        .unwrap()
-        .write_all(
-            br#"{"name":"FPP","vers":"0.0.1","deps":[],"features":{},"cksum":"3j3"}
-"#,
-        )
-        .unwrap();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_variant", user.id).expect_build(&conn);
+    }
 
-    let mut req = ::new_req(Arc::clone(&app), "FPP", "1.0.0");
-    ::sign_in(&mut req, &app);
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_variant");
     let mut response = ok_resp!(middle.call(&mut req));
-    ::json::<GoodCrate>(&mut response);
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["crate"]["exact_match"], true);
+    assert_eq!(json["canonical_name"], "foo_variant");
 
-    let mut contents = String::new();
-    File::open(&path)
-        .unwrap()
-        .read_to_string(&mut contents)
-        .unwrap();
-    let mut lines = contents.lines();
-    let p1: git::Crate = serde_json::from_str(lines.next().unwrap().trim()).unwrap();
-    let p2: git::Crate = serde_json::from_str(lines.next().unwrap().trim()).unwrap();
-    assert!(lines.next().is_none());
-    assert_eq!(p1.name, "FPP");
-    assert_eq!(p1.vers, "0.0.1");
-    assert!(p1.deps.is_empty());
-    assert_eq!(p2.name, "FPP");
-    assert_eq!(p2.vers, "1.0.0");
-    assert!(p2.deps.is_empty());
+    req.with_path("/api/v1/crates/Foo-Variant");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: serde_json::Value = ::json(&mut response);
+    assert_eq!(json["crate"]["exact_match"], false);
+    assert_eq!(json["canonical_name"], "foo_variant");
+    assert!(json.get("moved_to").is_none());
 }
 
 #[test]
-fn new_krate_git_upload_with_conflicts() {
+fn cache_control_headers() {
     let (_b, app, middle) = ::app();
-
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/foo_cached");
     {
-        let repo = git2::Repository::open(&::git::bare()).unwrap();
-        let target = repo.head().unwrap().target().unwrap();
-        let sig = repo.signature().unwrap();
-        let parent = repo.find_commit(target).unwrap();
-        let tree = repo.find_tree(parent.tree_id()).unwrap();
-        repo.commit(Some("HEAD"), &sig, &sig, "empty commit", &tree, &[&parent])
-            .unwrap();
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_cached", user.id)
+            .version(::VersionBuilder::new("1.0.0"))
+            .expect_build(&conn);
     }
 
-    let mut req = ::new_req(Arc::clone(&app), "foo_conflicts", "1.0.0");
+    let resp = ok_resp!(middle.call(&mut req));
+    assert_eq!(resp.headers["Cache-Control"], vec!["public, max-age=60"]);
+
+    // Downloads are counted, so only HEAD requests are cached
+    req.with_path("/api/v1/crates/foo_cached/1.0.0/download");
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.headers["Cache-Control"], vec!["no-cache"]);
+
+    req.with_path("/api/v1/crates/foo_cached/downloads");
+    let resp = ok_resp!(middle.call(&mut req));
+    assert_eq!(resp.headers["Cache-Control"], vec!["no-cache"]);
+
     ::sign_in(&mut req, &app);
-    let mut response = ok_resp!(middle.call(&mut req));
-    ::json::<GoodCrate>(&mut response);
+    req.with_path("/api/v1/crates/foo_cached");
+    let resp = ok_resp!(middle.call(&mut req));
+    assert_eq!(resp.headers["Cache-Control"], vec!["private, no-store"]);
 }
 
 #[test]
-fn new_krate_dependency_missing() {
+fn large_responses_are_compressed() {
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
     let (_b, app, middle) = ::app();
-    let dep = u::CrateDependency {
-        optional: false,
-        default_features: true,
-        name: u::CrateName("bar_missing".to_string()),
-        features: Vec::new(),
-        version_req: u::CrateVersionReq(semver::VersionReq::parse(">= 0.0.0").unwrap()),
-        target: None,
-        kind: None,
-    };
-    let mut req = ::new_req_full(Arc::clone(&app), ::krate("foo_missing"), "1.0.0", vec![dep]);
-    ::sign_in(&mut req, &app);
-    let mut response = ok_resp!(middle.call(&mut req));
-    let json = ::json::<::Bad>(&mut response);
-    assert!(
-        json.errors[0]
-            .detail
-            .contains("no known crate named `bar_missing`",)
-    );
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/openapi.json");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    assert!(!resp.headers.contains_key("Content-Encoding"));
+    assert_eq!(resp.headers["Vary"], vec!["Accept-Encoding"]);
+    let mut plain = Vec::new();
+    resp.body.write_body(&mut plain).unwrap();
+
+    req.header("Accept-Encoding", "gzip, deflate");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    assert_eq!(resp.headers["Content-Encoding"], vec!["gzip"]);
+    assert_eq!(resp.headers["Vary"], vec!["Accept-Encoding"]);
+    assert!(!resp.headers.contains_key("Content-Length"));
+    let mut compressed = Vec::new();
+    resp.body.write_body(&mut compressed).unwrap();
+    assert!(compressed.len() < plain.len());
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&compressed[..])
+        .unwrap()
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, plain);
+
+    req.header("Accept-Encoding", "deflate");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    assert_eq!(resp.headers["Content-Encoding"], vec!["deflate"]);
+    let mut compressed = Vec::new();
+    resp.body.write_body(&mut compressed).unwrap();
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(&compressed[..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, plain);
 }
 
 #[test]
-fn new_krate_with_readme() {
-    let (_b, app, middle) = ::app();
-    let mut krate = ::krate("foo_readme");
-    krate.readme = Some("".to_owned());
-    let mut req = ::new_req_full(Arc::clone(&app), krate, "1.0.0", vec![]);
-    ::sign_in(&mut req, &app);
-    let mut response = ok_resp!(middle.call(&mut req));
-    let json: GoodCrate = ::json(&mut response);
-    assert_eq!(json.krate.name, "foo_readme");
-    assert_eq!(json.krate.max_version, "1.0.0");
+fn download_signed_for_private_registries() {
+    let (_b, app, middle) = ::app_with_config(|config| {
+        if let cargo_registry::Uploader::S3 {
+            ref mut signed_urls_ttl,
+            ..
+        } = config.uploader
+        {
+            *signed_urls_ttl = Some(300);
+        }
+    });
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_signed/1.0.0/download",
+    );
+    let user = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_signed", user.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        user
+    };
+
+    // Anonymous users can't download from a private registry
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 403);
+
+    ::sign_in_as(&mut req, &user);
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 302);
+    let location = &resp.headers["Location"][0];
+    assert!(location.starts_with(
+        "http://alexcrichton-test.s3.amazonaws.com/crates/foo_signed/foo_signed-1.0.0.crate?"
+    ));
+    assert!(location.contains("&Expires="));
+    assert!(location.contains("&Signature="));
 }
 
 #[test]
-fn summary_doesnt_die() {
+fn download_head_is_not_counted() {
     let (_b, app, middle) = ::app();
-    let mut req = ::req(app, Method::Get, "/api/v1/summary");
-    ok_resp!(middle.call(&mut req));
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Head,
+        "/api/v1/crates/foo_head_dl/1.0.0/download",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_head_dl", user.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+    }
+
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 302);
+    assert!(
+        resp.headers["Location"][0].ends_with("/crates/foo_head_dl/foo_head_dl-1.0.0.crate")
+    );
+    assert_eq!(resp.headers["Cache-Control"], vec!["public, max-age=31536000"]);
+
+    req.with_method(Method::Get);
+    let resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.headers["Cache-Control"], vec!["no-cache"]);
+
+    req.with_path("/api/v1/crates/foo_head_dl/1.0.0/downloads");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads[0].downloads, 1);
 }
 
 #[test]
-fn summary_new_crates() {
-    let (_b, app, middle) = ::app();
-    let u;
-    let krate;
-    let krate2;
-    let krate3;
+fn download_counts_unique_ips() {
+    let (_b, app, middle) = ::app_with_config(|config| config.unique_downloads = true);
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_unique_dl/1.0.0/download",
+    );
     {
         let conn = app.diesel_database.get().unwrap();
-        u = ::new_user("foo").create_or_update(&conn).unwrap();
-
-        krate = ::CrateBuilder::new("some_downloads", u.id)
-            .version(::VersionBuilder::new("0.1.0"))
-            .description("description")
-            .keyword("popular")
-            .downloads(20)
-            .recent_downloads(10)
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_unique_dl", user.id)
+            .version("1.0.0")
             .expect_build(&conn);
+    }
 
-        krate2 = ::CrateBuilder::new("most_recent_downloads", u.id)
-            .version(::VersionBuilder::new("0.2.0"))
-            .keyword("popular")
-            .downloads(5000)
-            .recent_downloads(50)
-            .expect_build(&conn);
+    req.header("X-Forwarded-For", "10.0.0.1, 10.1.0.1");
+    t_resp!(middle.call(&mut req));
+    t_resp!(middle.call(&mut req));
+    // The leading entries are set by the client, only the one appended by
+    // the router counts
+    req.header("X-Forwarded-For", "10.0.0.3, 10.1.0.1");
+    t_resp!(middle.call(&mut req));
+    req.header("X-Forwarded-For", "10.0.0.2");
+    t_resp!(middle.call(&mut req));
+
+    req.with_path("/api/v1/crates/foo_unique_dl/1.0.0/downloads");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut resp);
+    assert_eq!(downloads.version_downloads[0].downloads, 4);
+    assert_eq!(downloads.version_downloads[0].unique_downloads, 2);
+}
 
-        krate3 = ::CrateBuilder::new("just_updated", u.id)
-            .version(::VersionBuilder::new("0.1.0"))
-            .expect_build(&conn);
+#[test]
+fn admins_ingest_cdn_downloads_once() {
+    #[derive(Deserialize)]
+    struct R {
+        ingested: bool,
+        unknown: Vec<String>,
+    }
 
-        ::CrateBuilder::new("with_downloads", u.id)
-            .version(::VersionBuilder::new("0.3.0"))
-            .keyword("popular")
-            .downloads(1000)
+    let (_b, app, middle) = ::app();
+    let (user, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_ingest", user.id)
+            .version("1.0.0")
             .expect_build(&conn);
+        (user, admin)
+    };
 
-        ::new_category("Category 1", "cat1")
-            .create_or_update(&conn)
-            .unwrap();
-        Category::update_crate(&conn, &krate, &["cat1"]).unwrap();
-        Category::update_crate(&conn, &krate2, &["cat1"]).unwrap();
+    let today = Utc::today().naive_utc();
+    let yesterday = today.pred();
+    let body = format!(
+        r#"{{"batch_id":"cdn-1","downloads":[
+            {{"crate":"foo_ingest","version":"1.0.0","date":"{}","count":5}},
+            {{"crate":"foo_ingest","version":"1.0.0","date":"{}","count":3}},
+            {{"crate":"foo_ingest","version":"2.0.0","date":"{}","count":1}}
+        ]}}"#,
+        yesterday, today, today
+    );
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Post,
+        "/api/v1/admin/downloads/ingest",
+    );
+    req.with_body(body.as_bytes());
+    ::sign_in_as(&mut req, &user);
+    bad_resp!(middle.call(&mut req));
+    ::sign_in_as(&mut req, &admin);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    assert!(json.ingested);
+    assert_eq!(json.unknown, ["foo_ingest#2.0.0"]);
 
-        // set total_downloads global value for `num_downloads` prop
-        update(metadata::table)
-            .set(metadata::total_downloads.eq(6000))
-            .execute(&*conn)
-            .unwrap();
+    // Sending the same batch again doesn't count it twice
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    assert!(!json.ingested);
 
-        // update 'just_updated' krate. Others won't appear because updated_at == created_at.
-        let updated = Utc::now().naive_utc();
-        update(&krate3)
-            .set(crates::updated_at.eq(updated))
-            .execute(&*conn)
-            .unwrap();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_ingest/1.0.0/downloads",
+    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let downloads = ::json::<Downloads>(&mut response).version_downloads;
+    let counts = downloads
+        .iter()
+        .map(|d| (d.date.clone(), d.downloads))
+        .collect::<Vec<_>>();
+    assert_eq!(counts, [(yesterday.to_string(), 5), (today.to_string(), 3)]);
+}
+
+#[test]
+fn download_breakdown_by_cargo_version_and_country() {
+    use cargo_registry::models::DownloadBreakdown;
+
+    #[derive(Deserialize)]
+    struct R {
+        breakdown: Vec<DownloadBreakdown>,
     }
 
-    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/summary");
-    let mut response = ok_resp!(middle.call(&mut req));
-    let json: SummaryResponse = ::json(&mut response);
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.download_breakdown = true;
+        config.download_country_header = Some("CF-IPCountry".into());
+    });
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_breakdown/1.0.0/download",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_breakdown", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(&conn);
+    }
 
-    assert_eq!(json.num_crates, 4);
-    assert_eq!(json.num_downloads, 6000);
-    assert_eq!(json.most_downloaded[0].name, "most_recent_downloads");
+    let download = |req: &mut ::conduit_test::MockRequest, times, user_agent, country| {
+        req.header("User-Agent", user_agent);
+        req.header("CF-IPCountry", country);
+        for _ in 0..times {
+            t_resp!(middle.call(req));
+        }
+    };
+    download(&mut req, 10, "cargo 1.26.0 (0e7c5a931 2018-04-06)", "de");
+    // Merged into an `other` bucket
+    download(&mut req, 3, "cargo 1.20.0 (a60d185c8 2017-07-13)", "de");
+    download(&mut req, 7, "cargo 1.20.0 (a60d185c8 2017-07-13)", "fr");
+    req.with_path("/api/v1/crates/foo_breakdown/1.1.0/download");
+    // Left out, even once merged
+    download(&mut req, 1, "curl/7.58.0", "XX1");
+
+    req.with_path("/api/v1/crates/foo_breakdown/downloads/breakdown");
+    let mut resp = ok_resp!(middle.call(&mut req));
+    let json = ::json::<R>(&mut resp);
+    let rows = json.breakdown
+        .iter()
+        .map(|b| (&*b.version, &*b.cargo_version, &*b.country, b.downloads))
+        .collect::<Vec<_>>();
     assert_eq!(
-        json.most_recently_downloaded[0].name,
-        "most_recent_downloads"
+        rows,
+        vec![("1.0.0", "1.26", "DE", 10), ("1.0.0", "other", "other", 10)]
     );
-    assert_eq!(json.popular_keywords[0].keyword, "popular");
-    assert_eq!(json.popular_categories[0].category, "Category 1");
-    assert_eq!(json.just_updated.len(), 1);
-    assert_eq!(json.just_updated[0].name, "just_updated");
-    assert_eq!(json.new_crates.len(), 4);
 }
 
 #[test]
-fn download() {
-    use chrono::{Duration, Utc};
-    let (_b, app, middle) = ::app();
+fn download_yanked_version_is_flagged() {
+    let (_b, app, middle) = ::app_with_config(|config| config.warn_yanked_downloads = true);
     let mut req = ::req(
         Arc::clone(&app),
         Method::Get,
-        "/api/v1/crates/foo_download/1.0.0/download",
+        "/api/v1/crates/foo_yanked_dl/1.0.0/download",
     );
     {
         let conn = app.diesel_database.get().unwrap();
         let user = ::new_user("foo").create_or_update(&conn).unwrap();
-        ::CrateBuilder::new("foo_download", user.id)
-            .version(::VersionBuilder::new("1.0.0"))
+        let krate = ::CrateBuilder::new("foo_yanked_dl", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
             .expect_build(&conn);
+        update(versions::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::num.eq("1.0.0"))
+            .set(versions::yanked.eq(true))
+            .execute(&*conn)
+            .unwrap();
     }
+
     let resp = t_resp!(middle.call(&mut req));
     assert_eq!(resp.status.0, 302);
-
-    req.with_path("/api/v1/crates/foo_download/1.0.0/downloads");
-    let mut resp = ok_resp!(middle.call(&mut req));
-    let downloads = ::json::<Downloads>(&mut resp);
-    assert_eq!(downloads.version_downloads.len(), 1);
-    req.with_path("/api/v1/crates/foo_download/downloads");
-    let mut resp = ok_resp!(middle.call(&mut req));
-    let downloads = ::json::<Downloads>(&mut resp);
-    assert_eq!(downloads.version_downloads.len(), 1);
-
-    req.with_path("/api/v1/crates/FOO_DOWNLOAD/1.0.0/download");
+    assert!(resp.headers.contains_key("X-Cargo-Yanked"));
+    req.with_path("/api/v1/crates/foo_yanked_dl/1.1.0/download");
     let resp = t_resp!(middle.call(&mut req));
     assert_eq!(resp.status.0, 302);
+    assert!(!resp.headers.contains_key("X-Cargo-Yanked"));
 
-    req.with_path("/api/v1/crates/FOO_DOWNLOAD/1.0.0/downloads");
-    let mut resp = ok_resp!(middle.call(&mut req));
-    let downloads = ::json::<Downloads>(&mut resp);
-    assert_eq!(downloads.version_downloads.len(), 1);
-    req.with_path("/api/v1/crates/FOO_DOWNLOAD/downloads");
-    let mut resp = ok_resp!(middle.call(&mut req));
-    let downloads = ::json::<Downloads>(&mut resp);
-    assert_eq!(downloads.version_downloads.len(), 1);
-
-    let yesterday = Utc::today() + Duration::days(-1);
-    req.with_path("/api/v1/crates/FOO_DOWNLOAD/1.0.0/downloads");
-    req.with_query(&format!("before_date={}", yesterday.format("%F")));
-    let mut resp = ok_resp!(middle.call(&mut req));
-    let downloads = ::json::<Downloads>(&mut resp);
-    assert_eq!(downloads.version_downloads.len(), 0);
-    req.with_path("/api/v1/crates/FOO_DOWNLOAD/downloads");
-    req.with_query(&format!("before_date={}", yesterday.format("%F")));
-    let mut resp = ok_resp!(middle.call(&mut req));
-    let downloads = ::json::<Downloads>(&mut resp);
-    // crate/downloads always returns the last 90 days and ignores date params
-    assert_eq!(downloads.version_downloads.len(), 1);
-
-    let tomorrow = Utc::today() + Duration::days(1);
-    req.with_path("/api/v1/crates/FOO_DOWNLOAD/1.0.0/downloads");
-    req.with_query(&format!("before_date={}", tomorrow.format("%F")));
+    req.with_path("/api/v1/crates/foo_yanked_dl/1.0.0/downloads");
     let mut resp = ok_resp!(middle.call(&mut req));
     let downloads = ::json::<Downloads>(&mut resp);
-    assert_eq!(downloads.version_downloads.len(), 1);
-    req.with_path("/api/v1/crates/FOO_DOWNLOAD/downloads");
-    req.with_query(&format!("before_date={}", tomorrow.format("%F")));
+    assert_eq!(downloads.version_downloads[0].downloads, 1);
+    assert_eq!(downloads.version_downloads[0].yanked_downloads, 1);
+    req.with_path("/api/v1/crates/foo_yanked_dl/1.1.0/downloads");
     let mut resp = ok_resp!(middle.call(&mut req));
     let downloads = ::json::<Downloads>(&mut resp);
-    assert_eq!(downloads.version_downloads.len(), 1);
+    assert_eq!(downloads.version_downloads[0].downloads, 1);
+    assert_eq!(downloads.version_downloads[0].yanked_downloads, 0);
 }
 
 #[test]
@@ -1834,6 +4431,308 @@ fn reverse_dependencies() {
     assert_eq!(deps.meta.total, 0);
 }
 
+#[test]
+fn reverse_dependencies_filtered_by_req() {
+    let (_b, app, middle) = ::app();
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/c1/reverse_dependencies",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c1 = ::CrateBuilder::new("c1", u.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .version("2.0.0")
+            .expect_build(&conn);
+        ::CrateBuilder::new("c2", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency_with_req(&c1, "^1.1"))
+            .expect_build(&conn);
+        ::CrateBuilder::new("c3", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency_with_req(&c1, "^2.0"))
+            .expect_build(&conn);
+    }
+
+    let mut response = ok_resp!(middle.call(req.with_query("req=^1.0")));
+    let deps = ::json::<RevDeps>(&mut response);
+    assert_eq!(deps.dependencies.len(), 1);
+    assert_eq!(deps.meta.total, 1);
+    assert_eq!(deps.versions[0].krate, "c2");
+
+    let mut response = ok_resp!(middle.call(req.with_query("req=>=1.0.0")));
+    let deps = ::json::<RevDeps>(&mut response);
+    assert_eq!(deps.dependencies.len(), 2);
+    assert_eq!(deps.meta.total, 2);
+
+    let json = bad_resp!(middle.call(req.with_query("req=not-a-req")));
+    assert!(
+        json.errors[0].detail.contains("is not a valid version requirement"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn reverse_dependencies_filtered_by_kind_and_target() {
+    use cargo_registry::schema::dependencies;
+
+    let (_b, app, middle) = ::app();
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/c1/reverse_dependencies",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c1 = ::CrateBuilder::new("c1", u.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        let c2 = ::CrateBuilder::new("c2", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(&conn);
+        ::CrateBuilder::new("c3", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency(&c1, Some("cfg(windows)")))
+            .expect_build(&conn);
+        let c2_versions = versions::table
+            .filter(versions::crate_id.eq(c2.id))
+            .select(versions::id);
+        update(dependencies::table.filter(dependencies::version_id.eq_any(c2_versions)))
+            .set(dependencies::kind.eq(2))
+            .execute(&*conn)
+            .unwrap();
+    }
+
+    let mut response = ok_resp!(middle.call(req.with_query("kind=dev")));
+    let deps = ::json::<RevDeps>(&mut response);
+    assert_eq!(deps.meta.total, 1);
+    assert_eq!(deps.versions[0].krate, "c2");
+
+    let mut response = ok_resp!(middle.call(req.with_query("kind=normal")));
+    let deps = ::json::<RevDeps>(&mut response);
+    assert_eq!(deps.meta.total, 1);
+    assert_eq!(deps.versions[0].krate, "c3");
+
+    let mut response = ok_resp!(middle.call(req.with_query("target=cfg(windows)")));
+    let deps = ::json::<RevDeps>(&mut response);
+    assert_eq!(deps.meta.total, 1);
+    assert_eq!(deps.dependencies[0].target, Some("cfg(windows)".to_string()));
+
+    let json = bad_resp!(middle.call(req.with_query("kind=optional")));
+    assert!(
+        json.errors[0].detail.contains("is not a valid dependency kind"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_dependents() {
+    use chrono::Duration;
+
+    #[derive(Deserialize)]
+    struct R {
+        dependents: Vec<EncodableNewDependent>,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c1 = ::CrateBuilder::new("c1", u.id)
+            .version("1.0.0")
+            .expect_build(&conn);
+        ::CrateBuilder::new("c2", u.id)
+            .description("A <new> dependent")
+            .version(::VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(&conn);
+        let c3 = ::CrateBuilder::new("c3", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(&conn);
+        update(versions::table.filter(versions::crate_id.eq(c3.id)))
+            .set(versions::created_at.eq(Utc::now().naive_utc() - Duration::days(60)))
+            .execute(&*conn)
+            .unwrap();
+        assert_eq!(CrateDependent::record_new(&conn).unwrap(), 2);
+        assert_eq!(CrateDependent::record_new(&conn).unwrap(), 0);
+
+        // The versions published recently are looked at again on the next run
+        let c3_version = versions::table
+            .filter(versions::crate_id.eq(c3.id))
+            .select(versions::id)
+            .first::<i32>(&*conn)
+            .unwrap();
+        let recorded_up_to = metadata::table
+            .select(metadata::dependents_recorded_up_to)
+            .get_result::<i32>(&*conn)
+            .unwrap();
+        assert_eq!(recorded_up_to, c3_version);
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/c1/new_dependents",
+    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    assert_eq!(json.dependents.len(), 1);
+    assert_eq!(json.dependents[0].name, "c2");
+
+    let mut resp = t_resp!(middle.call(req.with_path("/api/v1/crates/c1/new_dependents.rss")));
+    assert_eq!(
+        resp.headers["Content-Type"],
+        vec!["application/rss+xml; charset=utf-8".to_string()]
+    );
+    let mut body = Vec::new();
+    resp.body.write_body(&mut body).unwrap();
+    let rss = String::from_utf8(body).unwrap();
+    assert!(rss.contains("<title>c2 depends on c1</title>"), "{}", rss);
+    assert!(rss.contains("A &lt;new&gt; dependent"), "{}", rss);
+    assert!(!rss.contains("c3"), "{}", rss);
+}
+
+#[test]
+fn related_crates() {
+    use models::RelatedCrate;
+
+    #[derive(Deserialize)]
+    struct R {
+        crates: Vec<EncodableCrate>,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::new_category("Parsing", "parsing")
+            .create_or_update(&conn)
+            .unwrap();
+        let c1 = ::CrateBuilder::new("c1", u.id)
+            .keyword("parser")
+            .keyword("json")
+            .expect_build(&conn);
+        let c2 = ::CrateBuilder::new("c2", u.id)
+            .keyword("parser")
+            .expect_build(&conn);
+        ::CrateBuilder::new("c3", u.id)
+            .keyword("parser")
+            .keyword("json")
+            .expect_build(&conn);
+        let c4 = ::CrateBuilder::new("c4", u.id)
+            .keyword("json")
+            .expect_build(&conn);
+        let c5 = ::CrateBuilder::new("c5", u.id).expect_build(&conn);
+        Category::update_crate(&conn, &c1, &["parsing"]).unwrap();
+        Category::update_crate(&conn, &c2, &["parsing"]).unwrap();
+        // c1, c4 and c5 are depended on together, but c5 shares no keyword or
+        // category with them
+        ::CrateBuilder::new("c6", u.id)
+            .version(
+                ::VersionBuilder::new("1.0.0")
+                    .dependency(&c1, None)
+                    .dependency(&c4, None)
+                    .dependency(&c5, None),
+            )
+            .expect_build(&conn);
+        CrateDependent::record_new(&conn).unwrap();
+        assert!(RelatedCrate::recompute(&conn).unwrap() > 0);
+    }
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates/c1/related");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    let names = json.crates.iter().map(|c| &*c.name).collect::<Vec<_>>();
+    assert_eq!(names, ["c2", "c3", "c4"]);
+
+    let mut response = ok_resp!(middle.call(req.with_path("/api/v1/crates/c5/related")));
+    assert_eq!(::json::<R>(&mut response).crates.len(), 0);
+}
+
+#[test]
+fn sitemaps() {
+    use cargo_registry::models::Sitemap;
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::new_category("Parsing", "parsing")
+            .create_or_update(&conn)
+            .unwrap();
+        ::CrateBuilder::new("foo_sitemap", u.id).expect_build(&conn);
+        ::CrateBuilder::new("Foo_other", u.id).expect_build(&conn);
+        ::CrateBuilder::new("bar_sitemap", u.id).expect_build(&conn);
+        assert_eq!(Sitemap::regenerate(&conn).unwrap(), 3);
+    }
+
+    let fetch = |path: &str| {
+        let mut req = ::req(Arc::clone(&app), Method::Get, path);
+        let mut response = t_resp!(middle.call(&mut req));
+        assert_eq!(response.status.0, 200);
+        let mut body = Vec::new();
+        response.body.write_body(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    };
+    let index = fetch("/sitemap.xml");
+    assert!(index.contains("<loc>https://crates.io/sitemaps/crates-b.xml</loc>"));
+    assert!(index.contains("<loc>https://crates.io/sitemaps/crates-f.xml</loc>"));
+    assert!(index.contains("<loc>https://crates.io/sitemaps/categories.xml</loc>"));
+    let crates = fetch("/sitemaps/crates-f.xml");
+    assert!(crates.contains("<loc>https://crates.io/crates/foo_sitemap</loc>"));
+    assert!(crates.contains("<loc>https://crates.io/crates/Foo_other</loc>"));
+    assert!(!crates.contains("bar_sitemap"));
+    let categories = fetch("/sitemaps/categories.xml");
+    assert!(categories.contains("<loc>https://crates.io/categories/parsing</loc>"));
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/sitemaps/crates-z.xml");
+    let response = t_resp!(middle.call(&mut req));
+    assert_eq!(response.status.0, 404);
+}
+
+#[test]
+fn dependents_counted_by_version_requirement() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c1 = ::CrateBuilder::new("c1", u.id)
+            .version("0.8.0")
+            .version("0.8.3")
+            .version("1.0.0")
+            .expect_build(&conn);
+        ::CrateBuilder::new("c2", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency_with_req(&c1, "^0.8"))
+            .expect_build(&conn);
+        ::CrateBuilder::new("c3", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency_with_req(&c1, "~0.8.3"))
+            .expect_build(&conn);
+        ::CrateBuilder::new("c4", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency_with_req(&c1, "^1.0"))
+            .expect_build(&conn);
+        ::CrateBuilder::new("c5", u.id)
+            .version(::VersionBuilder::new("1.0.0").dependency_with_req(&c1, "^2.0"))
+            .expect_build(&conn);
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/c1/dependents/version_requirements",
+    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: RequirementsResponse = ::json(&mut response);
+    let ranges = json.requirements
+        .iter()
+        .map(|r| (&*r.range, r.dependents))
+        .collect::<Vec<_>>();
+    assert_eq!(ranges, vec![("^0.8", 2), ("^1", 1), ("^2.0", 1)]);
+}
+
 #[test]
 fn reverse_dependencies_when_old_version_doesnt_depend_but_new_does() {
     let (_b, app, middle) = ::app();
@@ -1959,6 +4858,49 @@ fn yanked_versions_not_included_in_reverse_dependencies() {
     assert_eq!(deps.meta.total, 0);
 }
 
+#[test]
+fn license_expressions_are_normalized() {
+    use cargo_registry::license::LicenseExpression;
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/crates/new");
+    ::sign_in(&mut req, &app);
+
+    let mut new_crate = new_crate("foo_spdx");
+    new_crate.license = Some("(MIT/Apache-2.0) and BSD-3-Clause".to_string());
+    req.with_body(&::new_crate_to_body(&new_crate, &[]));
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: GoodCrate = ::json(&mut response);
+    assert_eq!(
+        json.krate.license,
+        Some("(MIT OR Apache-2.0) AND BSD-3-Clause".to_string())
+    );
+    let license = |id: &str| LicenseExpression::License {
+        id: id.to_string(),
+        exception: None,
+    };
+    assert_eq!(
+        json.krate.license_expression,
+        Some(LicenseExpression::And {
+            of: vec![
+                LicenseExpression::Or {
+                    of: vec![license("MIT"), license("Apache-2.0")],
+                },
+                license("BSD-3-Clause"),
+            ],
+        })
+    );
+
+    new_crate.license = Some("MIT OR (Apache-2.0".to_string());
+    req.with_body(&::new_crate_to_body(&new_crate, &[]));
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("unclosed `(`"),
+        "{:?}",
+        json.errors
+    );
+}
+
 #[test]
 fn author_license_and_description_required() {
     let (_b, app, middle) = ::app();
@@ -2230,3 +5172,67 @@ fn test_cargo_invite_owners() {
 //     assert_eq!(json.krate.name, "foo_new");
 //     assert_eq!(json.krate.max_version, "1.0.0");
 // }
+
+#[test]
+fn updates_stream() {
+    use cargo_registry::updates::Update;
+
+    /// Keeps what is written until the end of the first event.
+    struct FirstEvent(Vec<u8>);
+
+    impl Write for FirstEvent {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.extend_from_slice(buf);
+            if self.0.ends_with(b"\n\n") {
+                Err(io::Error::new(io::ErrorKind::Other, "first event received"))
+            } else {
+                Ok(buf.len())
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/updates/stream");
+    let mut resp = t_resp!(middle.call(&mut req));
+    assert_eq!(resp.status.0, 200);
+    assert_eq!(
+        resp.headers["Content-Type"],
+        vec!["text/event-stream".to_string()]
+    );
+
+    app.update_subscribers.broadcast(&Update {
+        action: "yank".into(),
+        krate: "foo".into(),
+        version: Some("1.0.0".into()),
+    });
+    let mut event = FirstEvent(Vec::new());
+    assert!(resp.body.write_body(&mut event).is_err());
+    assert_eq!(
+        String::from_utf8(event.0).unwrap(),
+        "event: yank\ndata: {\"action\":\"yank\",\"crate\":\"foo\",\"version\":\"1.0.0\"}\n\n"
+    );
+}
+
+#[test]
+fn updates_stream_is_capped() {
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.max_update_subscribers = 1;
+    });
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/updates/stream");
+    let first = t_resp!(middle.call(&mut req));
+    assert_eq!(first.status.0, 200);
+
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/updates/stream");
+    let mut response = t_resp!(middle.call(&mut req));
+    assert_eq!(response.status.0, 503);
+    let json = ::json::<::Bad>(&mut response);
+    assert!(json.errors[0].detail.contains("too many clients"));
+
+    drop(first);
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/updates/stream");
+    assert_eq!(t_resp!(middle.call(&mut req)).status.0, 200);
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use conduit::{Handler, Method};
+
+#[test]
+fn metrics_require_the_configured_token() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/metrics");
+    let response = t_resp!(middle.call(&mut req));
+    assert_eq!(response.status.0, 404);
+
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.metrics_token = Some("secret".into());
+    });
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/metrics");
+    let response = t_resp!(middle.call(&mut req));
+    assert_eq!(response.status.0, 403);
+
+    req.header("Authorization", "Bearer wrong");
+    let response = t_resp!(middle.call(&mut req));
+    assert_eq!(response.status.0, 403);
+    req.header("Authorization", "Bearer secreT");
+    let response = t_resp!(middle.call(&mut req));
+    assert_eq!(response.status.0, 403);
+}
+
+#[test]
+fn requests_are_counted_by_route() {
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.metrics_token = Some("secret".into());
+    });
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/crates");
+    ok_resp!(middle.call(&mut req));
+    t_resp!(middle.call(req.with_path("/api/v1/crates/missing")));
+
+    req.with_path("/metrics")
+        .header("Authorization", "Bearer secret");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let mut body = Vec::new();
+    response.body.write_body(&mut body).unwrap();
+    let body = String::from_utf8(body).unwrap();
+    assert!(body.contains(
+        r#"cratesio_http_requests_total{method="GET",route="/api/v1/crates",status="200"} 1"#
+    ));
+    assert!(body.contains(
+        r#"cratesio_http_requests_total{method="GET",route="/api/v1/crates/:crate_id",status="404"} 1"#
+    ));
+    assert!(body.contains("cratesio_publishes_total 0"));
+    assert!(body.contains("cratesio_git_jobs 0"));
+}
@@ -8,7 +8,8 @@ use diesel::prelude::*;
 
 use models::{Crate, NewCrateOwnerInvitation};
 use schema::crate_owner_invitations;
-use views::{EncodableCrateOwnerInvitation, EncodableOwner, EncodablePublicUser, InvitationResponse};
+use views::{EncodableCrateOwner, EncodableCrateOwnerInvitation, EncodableOwner,
+            EncodableOwnerChange, EncodablePublicUser, InvitationResponse};
 
 #[derive(Deserialize)]
 struct TeamResponse {
@@ -226,6 +227,64 @@ fn owners_can_remove_self() {
     );
 }
 
+#[test]
+fn modifying_owners_reports_each_login() {
+    #[derive(Deserialize)]
+    struct O {
+        ok: bool,
+        results: Vec<EncodableOwnerChange>,
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/owners_batch/owners",
+    );
+    let invited = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("batchowner").create_or_update(&conn).unwrap();
+        let invited = ::new_user("batchinvited").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        ::CrateBuilder::new("owners_batch", user.id).expect_build(&conn);
+        invited
+    };
+
+    // The login that's already an owner doesn't prevent inviting the other
+    let body = r#"{"owners":["batchowner","batchinvited"]}"#;
+    let mut response = ok_resp!(middle.call(req.with_body(body.as_bytes())));
+    let json = ::json::<O>(&mut response);
+    assert!(json.ok);
+    assert_eq!(
+        json.results,
+        vec![
+            EncodableOwnerChange {
+                login: "batchowner".into(),
+                status: "error".into(),
+                msg: "`batchowner` is already an owner".into(),
+            },
+            EncodableOwnerChange {
+                login: "batchinvited".into(),
+                status: "added".into(),
+                msg: "user batchinvited has been invited to be an owner of crate owners_batch"
+                    .into(),
+            },
+        ]
+    );
+
+    let conn = app.diesel_database.get().unwrap();
+    let invitations = crate_owner_invitations::table
+        .filter(crate_owner_invitations::invited_user_id.eq(invited.id))
+        .count()
+        .get_result::<i64>(&*conn)
+        .unwrap();
+    assert_eq!(invitations, 1);
+
+    // Nothing could be changed, so the request fails
+    let json = bad_resp!(middle.call(req.with_body(br#"{"owners":["batchowner"]}"#)));
+    assert_eq!(json.errors[0].detail, "`batchowner` is already an owner");
+}
+
 /*  Testing the crate ownership between two crates and one team.
     Given two crates, one crate owned by both a team and a user,
     one only owned by a user, check that the CrateList returned
@@ -315,6 +374,48 @@ fn check_ownership_one_crate() {
     assert_eq!(json.users[0].name, user.name);
 }
 
+#[test]
+fn owners_have_avatars_and_urls() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("user_avatar").create_or_update(&conn).unwrap();
+        let t = ::new_team("github:avatar_org:team_avatar")
+            .create_or_update(&conn)
+            .unwrap();
+        let krate = ::CrateBuilder::new("avatar_crate", u.id).expect_build(&conn);
+        ::add_team_to_crate(&t, &krate, &u, &conn).unwrap();
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/avatar_crate/owner_team",
+    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: TeamResponse = ::json(&mut response);
+    let team = &json.teams[0];
+    assert_eq!(team.kind, "team");
+    assert_eq!(team.url, Some("https://github.com/avatar_org".to_string()));
+    // The team was added without an avatar, so GitHub's one is used
+    assert_eq!(
+        team.avatar,
+        Some("https://github.com/avatar_org.png".to_string())
+    );
+
+    let mut response = ok_resp!(middle.call(
+        req.with_path("/api/v1/crates/avatar_crate/owner_user")
+    ));
+    let json: UserResponse = ::json(&mut response);
+    let user = &json.users[0];
+    assert_eq!(user.kind, "user");
+    assert_eq!(user.url, Some("https://github.com/user_avatar".to_string()));
+    assert_eq!(
+        user.avatar,
+        Some("https://github.com/user_avatar.png".to_string())
+    );
+}
+
 #[test]
 fn invitations_are_empty_by_default() {
     #[derive(Deserialize)]
@@ -388,6 +489,98 @@ fn invitations_list() {
     assert_eq!(json.crate_owner_invitations[0].crate_id, krate.id);
 }
 
+#[test]
+fn owners_include_pending_invitations() {
+    #[derive(Deserialize)]
+    struct R {
+        users: Vec<EncodableCrateOwner>,
+        invitations: Vec<EncodableCrateOwner>,
+        meta: Meta,
+    }
+    #[derive(Deserialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/invited_crate/owners",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("inviting_user").create_or_update(&conn).unwrap();
+        let user = ::new_user("invited_user").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("invited_crate", owner.id).expect_build(&conn);
+        diesel::insert_into(crate_owner_invitations::table)
+            .values(&NewCrateOwnerInvitation {
+                invited_by_user_id: owner.id,
+                invited_user_id: user.id,
+                crate_id: krate.id,
+            })
+            .execute(&*conn)
+            .unwrap();
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    // `cargo owner --list` prints every user as an owner
+    assert_eq!(json.users.len(), 1);
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.users[0].login, "inviting_user");
+    assert!(!json.users[0].pending);
+    assert_eq!(json.users[0].invited_by, None);
+    assert_eq!(json.invitations.len(), 1);
+    assert_eq!(json.invitations[0].login, "invited_user");
+    assert!(json.invitations[0].pending);
+    assert_eq!(json.invitations[0].invited_by, Some("inviting_user".to_string()));
+}
+
+#[test]
+fn owners_are_ordered_and_paginated() {
+    #[derive(Deserialize)]
+    struct R {
+        users: Vec<EncodableCrateOwner>,
+        meta: Meta,
+    }
+    #[derive(Deserialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("zed_owner").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("paginated_owners", u.id).expect_build(&conn);
+        for login in &["github:owners_org:beta", "github:owners_org:alpha"] {
+            let t = ::new_team(login).create_or_update(&conn).unwrap();
+            ::add_team_to_crate(&t, &krate, &u, &conn).unwrap();
+        }
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/paginated_owners/owners",
+    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: R = ::json(&mut response);
+    let logins = json.users.iter().map(|o| &*o.login).collect::<Vec<_>>();
+    assert_eq!(
+        logins,
+        ["zed_owner", "github:owners_org:alpha", "github:owners_org:beta"]
+    );
+    assert_eq!(json.meta.total, 3);
+
+    let mut response = ok_resp!(middle.call(req.with_query("page=2&per_page=2")));
+    let json: R = ::json(&mut response);
+    assert_eq!(json.users.len(), 1);
+    assert_eq!(json.users[0].login, "github:owners_org:beta");
+    assert_eq!(json.meta.total, 3);
+}
+
 /*  Given a user inviting a different user to be a crate
     owner, check that the user invited can accept their
     invitation, the invitation will be deleted from
@@ -1,10 +1,10 @@
 use conduit::{Handler, Method};
 use diesel::*;
 use record::GhUser;
-use std::sync::ONCE_INIT;
+use std::sync::{Arc, ONCE_INIT};
 
-use models::{Crate, NewUser};
-use views::EncodableCrate;
+use models::{Crate, NewTeam, NewUser};
+use views::{EncodableApiTokenWithToken, EncodableCrate};
 
 // Users: `crates-tester-1` and `crates-tester-2`
 // Passwords: ask acrichto or gankro
@@ -406,6 +406,51 @@ fn publish_owned() {
     );
 }
 
+// Test publishing with a team token, which can only be created by the
+// maintainers of the team
+#[test]
+fn team_tokens_publish_owned_crates() {
+    #[derive(Deserialize)]
+    struct R {
+        team_token: EncodableApiTokenWithToken,
+    }
+
+    let (_b, app, middle) = ::app();
+    let (maintainer, member) = {
+        let conn = app.diesel_database.get().unwrap();
+        let maintainer = mock_user_on_x_and_y().create_or_update(&conn).unwrap();
+        let member = mock_user_on_only_x().create_or_update(&conn).unwrap();
+        let team = NewTeam::new("github:crates-test-org:core", 1_699_377, None, None)
+            .create_or_update(&conn)
+            .unwrap();
+        let krate = ::CrateBuilder::new("foo_team_token", maintainer.id).expect_build(&conn);
+        ::add_team_to_crate(&team, &krate, &maintainer, &conn).unwrap();
+        ::CrateBuilder::new("foo_no_team_token", maintainer.id).expect_build(&conn);
+        (maintainer, member)
+    };
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/teams/github:crates-test-org:core/tokens",
+    );
+    req.with_body(br#"{"team_token":{"name":"ci"}}"#);
+    ::sign_in_as(&mut req, &member);
+    bad_resp!(middle.call(&mut req));
+
+    ::sign_in_as(&mut req, &maintainer);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let token = ::json::<R>(&mut response).team_token.token;
+
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/crates/new");
+    req.header("Authorization", &token);
+    let body = ::new_req_body_version_2(::krate("foo_team_token"));
+    ok_resp!(middle.call(req.with_body(&body)));
+
+    let body = ::new_req_body_version_2(::krate("foo_no_team_token"));
+    bad_resp!(middle.call(req.with_body(&body)));
+}
+
 // Test trying to change owners (when only on an owning team)
 #[test]
 fn add_owners_as_team_owner() {
@@ -502,3 +547,43 @@ fn crates_by_team_id_not_including_deleted_owners() {
     let response: Response = ::json(&mut response);
     assert_eq!(response.crates.len(), 0);
 }
+
+#[test]
+fn team_page_lists_owned_crates() {
+    #[derive(Deserialize)]
+    struct Response {
+        crates: Vec<EncodableCrate>,
+        meta: Meta,
+    }
+    #[derive(Deserialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user(GH_USER_2.login).create_or_update(&conn).unwrap();
+        let t = ::new_team("github:crates-test-org:core")
+            .create_or_update(&conn)
+            .unwrap();
+        let popular = ::CrateBuilder::new("foo_team_popular", u.id)
+            .downloads(100)
+            .expect_build(&conn);
+        let other = ::CrateBuilder::new("foo_team_other", u.id).expect_build(&conn);
+        ::CrateBuilder::new("foo_not_team", u.id).expect_build(&conn);
+        ::add_team_to_crate(&t, &popular, &u, &conn).unwrap();
+        ::add_team_to_crate(&t, &other, &u, &conn).unwrap();
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/teams/github:crates-test-org:core/crates",
+    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let response: Response = ::json(&mut response);
+    assert_eq!(response.meta.total, 2);
+    assert_eq!(response.crates[0].name, "foo_team_popular");
+    assert_eq!(response.crates[1].name, "foo_team_other");
+}
@@ -97,6 +97,38 @@ fn create_token_logged_out() {
     assert_eq!(response.status.0, 403);
 }
 
+#[test]
+fn create_token_requires_verified_session_when_configured() {
+    use cargo_registry::middleware::current_user::TwoFactorVerified;
+    use cargo_registry::totp;
+    use chrono::Utc;
+    use conduit::Request;
+    use models::TwoFactorCredential;
+
+    let (_b, app, middle) = ::app_with_config(|config| config.require_two_factor = true);
+    let user = {
+        let conn = t!(app.diesel_database.get());
+        let user = t!(::new_user("foo").create_or_update(&conn));
+        let credential = t!(TwoFactorCredential::begin(&conn, user.id));
+        let code = totp::code_at(&credential.secret, Utc::now().timestamp()).unwrap();
+        t!(credential.enable(&conn, &code));
+        user
+    };
+
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/me/tokens");
+    ::sign_in_as(&mut req, &user);
+    req.with_body(br#"{ "api_token": { "name": "bar" } }"#);
+    let json = bad_resp!(middle.call(&mut req));
+    assert_contains!(
+        json.errors[0].detail,
+        "must be verified with a two-factor authentication code"
+    );
+
+    req.mut_extensions().insert(TwoFactorVerified);
+    let mut response = ok_resp!(middle.call(&mut req));
+    ::json::<NewResponse>(&mut response);
+}
+
 #[test]
 fn create_token_invalid_request() {
     let (_b, app, middle) = ::app();
@@ -426,3 +458,57 @@ fn using_token_updates_last_used_at() {
     // based on the start of the database transaction so it doesn't work in
     // this test framework.
 }
+
+#[test]
+fn tokens_restricted_to_addresses() {
+    let (_b, app, middle) = ::app();
+    let user = {
+        let conn = t!(app.diesel_database.get());
+        t!(::new_user("foo").create_or_update(&conn))
+    };
+
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/me/tokens");
+    ::sign_in_as(&mut req, &user);
+    req.with_body(br#"{ "api_token": { "name": "ci", "allowed_ips": ["10.0.0.0/8"] } }"#);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let token = ::json::<NewResponse>(&mut response).api_token;
+    assert_eq!(token.allowed_ips, vec!["10.0.0.0/8"]);
+
+    let mut me = ::req(Arc::clone(&app), Method::Get, "/api/v1/me");
+    me.header("Authorization", &token.token);
+    me.header("X-Forwarded-For", "10.1.2.3");
+    ok_resp!(middle.call(&mut me));
+    me.header("X-Forwarded-For", "192.168.0.1");
+    let response = t_resp!(middle.call(&mut me));
+    assert_eq!(response.status.0, 403);
+    // Only the address appended by the router is trusted
+    me.header("X-Forwarded-For", "10.1.2.3, 192.168.0.1");
+    let response = t_resp!(middle.call(&mut me));
+    assert_eq!(response.status.0, 403);
+
+    // Lifting the restriction takes a session, not the token itself
+    let mut update = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        &format!("/api/v1/me/tokens/{}", token.id),
+    );
+    update.header("Authorization", &token.token);
+    update.header("X-Forwarded-For", "10.1.2.3");
+    update.with_body(br#"{ "api_token": { "allowed_ips": [] } }"#);
+    let response = t_resp!(middle.call(&mut update));
+    assert_eq!(response.status.0, 400);
+
+    let mut update = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        &format!("/api/v1/me/tokens/{}", token.id),
+    );
+    ::sign_in_as(&mut update, &user);
+    update.with_body(br#"{ "api_token": { "allowed_ips": ["10.0.0.0/33"] } }"#);
+    let json = bad_resp!(middle.call(&mut update));
+    assert_contains!(json.errors[0].detail, "invalid IP address range");
+    update.with_body(br#"{ "api_token": { "allowed_ips": [] } }"#);
+    ok_resp!(middle.call(&mut update));
+
+    ok_resp!(middle.call(&mut me));
+}
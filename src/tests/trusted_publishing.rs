@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use conduit::{Handler, Method};
+use diesel;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use models::TrustedPublishingToken;
+use schema::trusted_publishing_tokens;
+
+#[derive(Deserialize)]
+struct TrustedPublisher {
+    id: i32,
+    repository_owner: String,
+    repository_name: String,
+    workflow_filename: String,
+}
+#[derive(Deserialize)]
+struct ListResponse {
+    trusted_publishers: Vec<TrustedPublisher>,
+}
+#[derive(Deserialize)]
+struct CreateResponse {
+    trusted_publisher: TrustedPublisher,
+}
+
+#[test]
+fn manage_trusted_publishers() {
+    let (_b, app, middle) = ::app();
+    let (owner, other) = {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_trusted", owner.id).expect_build(&conn);
+        (owner, other)
+    };
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/foo_trusted/trusted_publishers",
+    );
+    req.with_body(
+        br#"{"repository_owner":"foo","repository_name":"trusted","workflow_filename":"release.yml"}"#,
+    );
+    ::sign_in_as(&mut req, &other);
+    bad_resp!(middle.call(&mut req));
+
+    ::sign_in_as(&mut req, &owner);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let created = ::json::<CreateResponse>(&mut response).trusted_publisher;
+    assert_eq!(created.repository_owner, "foo");
+    assert_eq!(created.repository_name, "trusted");
+    assert_eq!(created.workflow_filename, "release.yml");
+
+    req.with_method(Method::Get);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: ListResponse = ::json(&mut response);
+    assert_eq!(json.trusted_publishers.len(), 1);
+    assert_eq!(json.trusted_publishers[0].id, created.id);
+
+    req.with_method(Method::Delete).with_path(&format!(
+        "/api/v1/crates/foo_trusted/trusted_publishers/{}",
+        created.id
+    ));
+    ok_resp!(middle.call(&mut req));
+
+    req.with_method(Method::Get)
+        .with_path("/api/v1/crates/foo_trusted/trusted_publishers");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: ListResponse = ::json(&mut response);
+    assert!(json.trusted_publishers.is_empty());
+}
+
+#[test]
+fn publish_tokens_resolve_to_the_registering_owner() {
+    let (_b, app, middle) = ::app();
+    let owner = {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_trusted", owner.id).expect_build(&conn);
+        ::CrateBuilder::new("foo_untrusted", owner.id).expect_build(&conn);
+        owner
+    };
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/foo_trusted/trusted_publishers",
+    );
+    req.with_body(
+        br#"{"repository_owner":"foo","repository_name":"trusted","workflow_filename":"release.yml"}"#,
+    );
+    ::sign_in_as(&mut req, &owner);
+    ok_resp!(middle.call(&mut req));
+
+    let conn = app.diesel_database.get().unwrap();
+    let insert_token = |workflow: &str, valid: bool| {
+        let expires_at = if valid {
+            now + 30.minutes()
+        } else {
+            now - 1.minutes()
+        };
+        diesel::insert_into(trusted_publishing_tokens::table)
+            .values((
+                trusted_publishing_tokens::repository_owner.eq("foo"),
+                trusted_publishing_tokens::repository_name.eq("trusted"),
+                trusted_publishing_tokens::workflow_filename.eq(workflow),
+                trusted_publishing_tokens::expires_at.eq(expires_at),
+            ))
+            .get_result::<TrustedPublishingToken>(&*conn)
+            .unwrap()
+            .token
+    };
+
+    let token = insert_token("release.yml", true);
+    let user = TrustedPublishingToken::publisher_user(&conn, &token, "foo_trusted").unwrap();
+    assert_eq!(user.map(|u| u.id), Some(owner.id));
+    let user = TrustedPublishingToken::publisher_user(&conn, &token, "foo_untrusted").unwrap();
+    assert!(user.is_none());
+
+    let other_workflow = insert_token("ci.yml", true);
+    let user =
+        TrustedPublishingToken::publisher_user(&conn, &other_workflow, "foo_trusted").unwrap();
+    assert!(user.is_none());
+
+    let expired = insert_token("release.yml", false);
+    let user = TrustedPublishingToken::publisher_user(&conn, &expired, "foo_trusted").unwrap();
+    assert!(user.is_none());
+}
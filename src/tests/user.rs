@@ -3,9 +3,11 @@ use std::sync::Arc;
 
 use conduit::{Handler, Method};
 use diesel::prelude::*;
+use semver;
 
-use models::{ApiToken, Email, NewUser, User};
-use views::{EncodableCrate, EncodablePrivateUser, EncodablePublicUser, EncodableVersion};
+use models::{ApiToken, AuditAction, Email, NewUser, User};
+use views::{EncodableAuditAction, EncodableCrate, EncodableCrateOwnerInvitation,
+            EncodablePrivateUser, EncodablePublicUser, EncodableVersion};
 
 #[derive(Deserialize)]
 struct AuthResponse {
@@ -23,6 +25,13 @@ pub struct UserShowPrivateResponse {
     pub user: EncodablePrivateUser,
 }
 
+#[derive(Deserialize)]
+struct MeResponse {
+    owned_crates: i64,
+    pending_invitations: i64,
+    api_tokens: i64,
+}
+
 #[test]
 fn auth_gives_a_token() {
     let (_b, app, middle) = ::app();
@@ -56,6 +65,211 @@ fn me() {
     assert_eq!(json.user.email, user.email);
 }
 
+#[test]
+fn me_includes_account_summary() {
+    use diesel::insert_into;
+    use models::NewCrateOwnerInvitation;
+    use schema::crate_owner_invitations;
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/me");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_me_1", user.id).expect_build(&conn);
+        ::CrateBuilder::new("foo_me_2", user.id).expect_build(&conn);
+        let krate = ::CrateBuilder::new("bar_me", other.id).expect_build(&conn);
+        insert_into(crate_owner_invitations::table)
+            .values(&NewCrateOwnerInvitation {
+                invited_by_user_id: other.id,
+                invited_user_id: user.id,
+                crate_id: krate.id,
+            })
+            .execute(&*conn)
+            .unwrap();
+        ApiToken::insert(&conn, user.id, "token").unwrap();
+        ::sign_in_as(&mut req, &user);
+    }
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: MeResponse = ::json(&mut response);
+    assert_eq!(json.owned_crates, 2);
+    assert_eq!(json.pending_invitations, 1);
+    assert_eq!(json.api_tokens, 1);
+}
+
+#[test]
+fn delete_account() {
+    use diesel::insert_into;
+    use models::CrateOwner;
+    use schema::{api_tokens, crate_owners, users};
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Delete, "/api/v1/me");
+    let (user, krate) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("foo_deleted", user.id).expect_build(&conn);
+        ApiToken::insert(&conn, user.id, "token").unwrap();
+        ::sign_in_as(&mut req, &user);
+        (user, krate)
+    };
+    // Signed in on another device too
+    let mut other_device = ::req(Arc::clone(&app), Method::Get, "/api/v1/me");
+    ::sign_in_as(&mut other_device, &user);
+
+    // Crates can't be left without owners
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("only owner of `foo_deleted`"),
+        "{:?}",
+        json.errors
+    );
+
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: krate.id,
+                owner_id: other.id,
+                created_by: user.id,
+                owner_kind: 0,
+            })
+            .execute(&*conn)
+            .unwrap();
+    }
+    ok_resp!(middle.call(&mut req));
+    let response = t_resp!(middle.call(&mut other_device));
+    assert_eq!(response.status.0, 403);
+
+    let conn = app.diesel_database.get().unwrap();
+    let deleted = users::table.find(user.id).first::<User>(&*conn).unwrap();
+    assert_eq!(deleted.gh_login, format!("deleted-user-{}", user.id));
+    assert_eq!(deleted.gh_id, 0);
+    assert!(deleted.deleted_at.is_some());
+    let tokens = api_tokens::table
+        .filter(api_tokens::user_id.eq(user.id))
+        .count()
+        .get_result::<i64>(&*conn)
+        .unwrap();
+    assert_eq!(tokens, 0);
+    let owners = User::owning(&krate, &conn).unwrap();
+    assert_eq!(owners.len(), 1);
+}
+
+#[test]
+fn delete_account_orphans_crates() {
+    use models::Owner;
+
+    let (_b, app, middle) = ::app_with_config(|config| {
+        config.orphan_team = Some("github:test-org:orphans".into());
+    });
+    let mut req = ::req(Arc::clone(&app), Method::Delete, "/api/v1/me");
+    let krate = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        ::new_team("github:test-org:orphans")
+            .create_or_update(&conn)
+            .unwrap();
+        ::sign_in_as(&mut req, &user);
+        ::CrateBuilder::new("foo_orphaned", user.id).expect_build(&conn)
+    };
+    ok_resp!(middle.call(&mut req));
+
+    let conn = app.diesel_database.get().unwrap();
+    let owners = krate.owners(&conn).unwrap();
+    assert_eq!(owners.len(), 1);
+    match owners[0] {
+        Owner::Team(ref team) => assert_eq!(team.login, "github:test-org:orphans"),
+        Owner::User(_) => panic!("the crate should be owned by the orphan team"),
+    }
+}
+
+#[test]
+fn my_crates() {
+    use chrono::{Duration, Utc};
+    use diesel::insert_into;
+    use models::{CrateDownload, NewCrateOwnerInvitation};
+    use schema::{crate_downloads, crate_owner_invitations};
+
+    #[derive(Deserialize)]
+    struct OwnedCrate {
+        name: String,
+        role: String,
+        downloads: i32,
+        downloads_last_week: i64,
+        downloads_previous_week: i64,
+    }
+    #[derive(Deserialize)]
+    struct R {
+        crates: Vec<OwnedCrate>,
+        pending_invitations: Vec<EncodableCrateOwnerInvitation>,
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/me/crates");
+    let user = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        let krate = ::CrateBuilder::new("foo_mine", user.id)
+            .downloads(100)
+            .recent_downloads(10)
+            .expect_build(&conn);
+        insert_into(crate_downloads::table)
+            .values(&CrateDownload {
+                crate_id: krate.id,
+                downloads: 5,
+                date: Utc::today().naive_utc() - Duration::days(8),
+            })
+            .execute(&*conn)
+            .unwrap();
+        ::CrateBuilder::new("bar_theirs", other.id).expect_build(&conn);
+        let invited = ::CrateBuilder::new("bar_invited", other.id).expect_build(&conn);
+        insert_into(crate_owner_invitations::table)
+            .values(&NewCrateOwnerInvitation {
+                invited_by_user_id: other.id,
+                invited_user_id: user.id,
+                crate_id: invited.id,
+            })
+            .execute(&*conn)
+            .unwrap();
+        user
+    };
+    ::sign_in_as(&mut req, &user);
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json = ::json::<R>(&mut response);
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].name, "foo_mine");
+    assert_eq!(json.crates[0].role, "owner");
+    assert_eq!(json.crates[0].downloads, 100);
+    assert_eq!(json.crates[0].downloads_last_week, 10);
+    assert_eq!(json.crates[0].downloads_previous_week, 5);
+    assert_eq!(json.pending_invitations.len(), 1);
+    assert_eq!(json.pending_invitations[0].crate_name, "bar_invited");
+}
+
+#[test]
+fn authenticated_responses_include_rate_limit_headers() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Get, "/api/v1/summary");
+    let response = ok_resp!(middle.call(&mut req));
+    assert!(response.headers.get("X-RateLimit-Limit").is_none());
+
+    ::sign_in(&mut req, &app);
+
+    let response = ok_resp!(middle.call(&mut req));
+    assert_eq!(response.headers["X-RateLimit-Limit"], vec!["1000"]);
+    assert_eq!(response.headers["X-RateLimit-Remaining"], vec!["999"]);
+    assert!(response.headers.contains_key("X-RateLimit-Reset"));
+
+    let response = ok_resp!(middle.call(&mut req));
+    assert_eq!(response.headers["X-RateLimit-Remaining"], vec!["998"]);
+}
+
 #[test]
 fn show() {
     let (_b, app, middle) = ::app();
@@ -454,6 +668,51 @@ fn test_email_get_and_put() {
     assert!(r.user.email_verification_sent);
 }
 
+#[test]
+fn test_email_put_on_me() {
+    #[derive(Deserialize)]
+    struct R {
+        user: EncodablePrivateUser,
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/me/email");
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("papaya").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+    }
+
+    let body = r#"{"email":"papaya@papayas.papaya"}"#;
+    ok_resp!(middle.call(req.with_body(body.as_bytes())));
+
+    let body = r#"{"email":"  "}"#;
+    let json = bad_resp!(middle.call(req.with_body(body.as_bytes())));
+    assert!(json.errors[0].detail.contains("empty email rejected"));
+
+    ok_resp!(middle.call(req.with_path("/api/v1/me/email/resend")));
+
+    let mut response = ok_resp!(middle.call(req.with_path("/api/v1/me").with_method(Method::Get),));
+    let r = ::json::<R>(&mut response);
+    assert_eq!(r.user.email.unwrap(), "papaya@papayas.papaya");
+    assert!(!r.user.email_verified);
+    assert!(r.user.email_verification_sent);
+}
+
+#[test]
+fn update_settings() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/me/settings");
+    ::sign_in(&mut req, &app);
+
+    let body = r#"{"confirm_new_crates":true}"#;
+    ok_resp!(middle.call(req.with_body(body.as_bytes())));
+
+    let mut response = ok_resp!(middle.call(req.with_path("/api/v1/me").with_method(Method::Get),));
+    let json: UserShowPrivateResponse = ::json(&mut response);
+    assert!(json.user.confirm_new_crates);
+}
+
 /*  Given a crates.io user, check to make sure that the user
     cannot add to the database an empty string or null as
     their email. If an attempt is made, update_user.rs will
@@ -770,3 +1029,138 @@ fn test_existing_user_email() {
     assert!(!r.user.email_verified);
     assert!(!r.user.email_verification_sent);
 }
+
+#[test]
+fn two_factor_enrollment() {
+    use cargo_registry::totp;
+    use chrono::Utc;
+    use models::TwoFactorCredential;
+
+    #[derive(Deserialize)]
+    struct Enrollment {
+        secret: String,
+        otpauth_url: String,
+    }
+    #[derive(Deserialize)]
+    struct Enabled {
+        recovery_codes: Vec<String>,
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Post, "/api/v1/me/2fa");
+    let user = ::sign_in(&mut req, &app);
+
+    let mut response = ok_resp!(middle.call(&mut req));
+    let enrollment = ::json::<Enrollment>(&mut response);
+    assert!(
+        enrollment
+            .otpauth_url
+            .starts_with("otpauth://totp/crates.io%3Afoo?")
+    );
+
+    req.with_method(Method::Put)
+        .with_body(br#"{ "code": "abcdef" }"#);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(json.errors[0].detail.contains("invalid two-factor"));
+
+    let code = totp::code_at(&enrollment.secret, Utc::now().timestamp()).unwrap();
+    let body = format!(r#"{{ "code": "{}" }}"#, code);
+    let mut response = ok_resp!(middle.call(req.with_body(body.as_bytes())));
+    let enabled = ::json::<Enabled>(&mut response);
+    assert_eq!(enabled.recovery_codes.len(), 10);
+
+    // Each recovery code can only be used once
+    let body = format!(r#"{{ "recovery_code": "{}" }}"#, enabled.recovery_codes[0]);
+    req.with_path("/api/v1/me/2fa/verify")
+        .with_body(body.as_bytes());
+    ok_resp!(middle.call(&mut req));
+    let json = bad_resp!(middle.call(req.with_body(body.as_bytes())));
+    assert!(json.errors[0].detail.contains("invalid two-factor"));
+
+    let body = format!(r#"{{ "recovery_code": "{}" }}"#, enabled.recovery_codes[1]);
+    req.with_path("/api/v1/me/2fa")
+        .with_method(Method::Delete)
+        .with_body(body.as_bytes());
+    ok_resp!(middle.call(&mut req));
+
+    let conn = app.diesel_database.get().unwrap();
+    assert_eq!(TwoFactorCredential::find(&conn, user.id).unwrap(), None);
+}
+
+#[test]
+fn two_factor_codes_are_used_once_and_locked_out() {
+    use cargo_registry::totp;
+    use chrono::Utc;
+    use models::TwoFactorCredential;
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(Arc::clone(&app), Method::Put, "/api/v1/me/2fa/verify");
+    let user = ::sign_in(&mut req, &app);
+    let (code, recovery_codes) = {
+        let conn = app.diesel_database.get().unwrap();
+        let credential = TwoFactorCredential::begin(&conn, user.id).unwrap();
+        let code = totp::code_at(&credential.secret, Utc::now().timestamp()).unwrap();
+        let recovery_codes = credential.enable(&conn, &code).unwrap();
+        (code, recovery_codes)
+    };
+
+    // The code was already used to enable two-factor authentication
+    let body = format!(r#"{{ "code": "{}" }}"#, code);
+    let json = bad_resp!(middle.call(req.with_body(body.as_bytes())));
+    assert!(json.errors[0].detail.contains("invalid two-factor"));
+
+    for _ in 0..4 {
+        bad_resp!(middle.call(req.with_body(br#"{ "code": "abcdef" }"#)));
+    }
+    let body = format!(r#"{{ "recovery_code": "{}" }}"#, recovery_codes[0]);
+    let response = t_resp!(middle.call(req.with_body(body.as_bytes())));
+    assert_eq!(response.status.0, 429);
+}
+
+#[test]
+fn me_activity() {
+    #[derive(Deserialize)]
+    struct R {
+        activity: Vec<EncodableAuditAction>,
+        meta: Meta,
+    }
+    #[derive(Deserialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/foo_activity/owners",
+    );
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("other").create_or_update(&conn).unwrap();
+        ::new_user("bar").create_or_update(&conn).unwrap();
+        ::sign_in_as(&mut req, &user);
+        let krate = ::CrateBuilder::new("foo_activity", user.id).expect_build(&conn);
+        let version = semver::Version::parse("1.0.0").unwrap();
+        AuditAction::record(&conn, &user, &krate, Some(&version), "publish", None).unwrap();
+        AuditAction::record(&conn, &other, &krate, None, "owner_add", Some("foo")).unwrap();
+    }
+
+    ok_resp!(middle.call(req.with_body(br#"{"users":["bar"]}"#)));
+
+    let mut response = ok_resp!(middle.call(
+        req.with_method(Method::Get)
+            .with_path("/api/v1/me/activity")
+            .with_body(b"")
+    ));
+    let json = ::json::<R>(&mut response);
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.activity.len(), 2);
+    assert_eq!(json.activity[0].action, "owner_add");
+    assert_eq!(json.activity[0].krate, "foo_activity");
+    assert_eq!(json.activity[0].version, None);
+    assert_eq!(json.activity[0].detail, Some("bar".to_string()));
+    assert_eq!(json.activity[1].action, "publish");
+    assert_eq!(json.activity[1].version, Some("1.0.0".to_string()));
+}
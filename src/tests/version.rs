@@ -6,10 +6,12 @@ extern crate serde_json;
 use serde_json::Value;
 
 use self::diesel::prelude::*;
-use conduit::{Handler, Method};
+use conduit::{Handler, Method, Response};
 
+use cargo_registry::scanning::ScanResult;
+use models::{VersionScanResult, VersionSignature, VersionSource};
 use schema::versions;
-use views::EncodableVersion;
+use views::{EncodableVersion, EncodableVersionScanResult};
 
 #[derive(Deserialize)]
 struct VersionList {
@@ -19,6 +21,10 @@ struct VersionList {
 struct VersionResponse {
     version: EncodableVersion,
 }
+#[derive(Deserialize)]
+struct ScanResults {
+    scan_results: Vec<EncodableVersionScanResult>,
+}
 
 #[test]
 fn index() {
@@ -109,3 +115,216 @@ fn record_rerendered_readme_time() {
         version.record_readme_rendering(&conn).unwrap();
     }
 }
+
+#[test]
+fn readme_and_manifest_sources() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c = ::CrateBuilder::new("foo_sources", u.id).expect_build(&conn);
+        let version = ::new_version(c.id, "1.0.0").save(&conn, &[]).unwrap();
+        ::new_version(c.id, "0.9.0").save(&conn, &[]).unwrap();
+        VersionSource {
+            version_id: version.id,
+            readme: Some("# foo_sources".into()),
+            readme_file: Some("README.md".into()),
+            manifest: Some("[package]\nname = \"foo_sources\"\n".into()),
+        }.save(&conn)
+            .unwrap();
+    }
+
+    let body = |resp: &mut Response| {
+        let mut body = Vec::new();
+        resp.body.write_body(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    };
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_sources/1.0.0/readme_raw",
+    );
+    let mut resp = t_resp!(middle.call(&mut req));
+    assert_eq!(body(&mut resp), "# foo_sources");
+
+    req.with_path("/api/v1/crates/foo_sources/1.0.0/readme_rendered");
+    let mut resp = t_resp!(middle.call(&mut req));
+    assert_eq!(
+        resp.headers["Content-Type"],
+        vec!["text/html; charset=utf-8".to_string()]
+    );
+    assert!(body(&mut resp).contains("foo_sources</h1>"));
+
+    req.with_path("/api/v1/crates/foo_sources/1.0.0/manifest");
+    let mut resp = t_resp!(middle.call(&mut req));
+    assert!(body(&mut resp).starts_with("[package]"));
+
+    // The sources of older versions weren't kept
+    req.with_path("/api/v1/crates/foo_sources/0.9.0/manifest");
+    bad_resp!(middle.call(&mut req));
+}
+
+#[test]
+fn published_signatures() {
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c = ::CrateBuilder::new("foo_signature", u.id).expect_build(&conn);
+        let version = ::new_version(c.id, "1.0.0").save(&conn, &[]).unwrap();
+        ::new_version(c.id, "0.9.0").save(&conn, &[]).unwrap();
+        VersionSignature::save(&conn, &version, "-----BEGIN PGP SIGNATURE-----").unwrap();
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_signature/1.0.0/signature",
+    );
+    let mut resp = t_resp!(middle.call(&mut req));
+    let mut body = Vec::new();
+    resp.body.write_body(&mut body).unwrap();
+    assert_eq!(body, b"-----BEGIN PGP SIGNATURE-----");
+
+    // Versions published without a signature
+    req.with_path("/api/v1/crates/foo_signature/0.9.0/signature");
+    bad_resp!(middle.call(&mut req));
+}
+
+#[test]
+fn files_and_diffs() {
+    use cargo_registry::uploaders::TarballFile;
+    use models::VersionFile;
+    use views::{EncodableFileChange, EncodableVersionFile};
+
+    #[derive(Deserialize)]
+    struct Files {
+        files: Vec<EncodableVersionFile>,
+    }
+    #[derive(Deserialize)]
+    struct Diff {
+        added: Vec<EncodableVersionFile>,
+        removed: Vec<EncodableVersionFile>,
+        changed: Vec<EncodableFileChange>,
+    }
+
+    let (_b, app, middle) = ::app();
+    {
+        let conn = app.diesel_database.get().unwrap();
+        let u = ::new_user("foo").create_or_update(&conn).unwrap();
+        let c = ::CrateBuilder::new("foo_files", u.id).expect_build(&conn);
+        let file = |path: &str, size: u64, sha256: &str| TarballFile {
+            path: path.into(),
+            size,
+            sha256: sha256.into(),
+        };
+        let v1 = ::new_version(c.id, "1.0.0").save(&conn, &[]).unwrap();
+        let v2 = ::new_version(c.id, "1.1.0").save(&conn, &[]).unwrap();
+        ::new_version(c.id, "0.9.0").save(&conn, &[]).unwrap();
+        VersionFile::save_all(
+            &conn,
+            v1.id,
+            &[
+                file("Cargo.toml", 120, "aa"),
+                file("build.rs", 80, "bb"),
+                file("src/lib.rs", 500, "cc"),
+            ],
+        ).unwrap();
+        VersionFile::save_all(
+            &conn,
+            v2.id,
+            &[
+                file("Cargo.toml", 120, "aa"),
+                file("src/lib.rs", 650, "dd"),
+                file("src/util.rs", 40, "ee"),
+            ],
+        ).unwrap();
+    }
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_files/1.0.0/files",
+    );
+    let mut response = ok_resp!(middle.call(&mut req));
+    let files = ::json::<Files>(&mut response).files;
+    let paths = files.iter().map(|f| &*f.path).collect::<Vec<_>>();
+    assert_eq!(paths, ["Cargo.toml", "build.rs", "src/lib.rs"]);
+    assert_eq!(files[2].size, 500);
+    assert_eq!(files[2].sha256, "cc");
+
+    // The files of older versions weren't listed
+    req.with_path("/api/v1/crates/foo_files/0.9.0/files");
+    bad_resp!(middle.call(&mut req));
+
+    req.with_path("/api/v1/crates/foo_files/diff")
+        .with_query("from=1.0.0&to=1.1.0");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let diff = ::json::<Diff>(&mut response);
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].path, "src/util.rs");
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[0].path, "build.rs");
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].path, "src/lib.rs");
+    assert_eq!((diff.changed[0].from_size, diff.changed[0].to_size), (500, 650));
+
+    req.with_query("from=1.0.0&to=2.0.0");
+    bad_resp!(middle.call(&mut req));
+    req.with_query("from=1.0.0");
+    bad_resp!(middle.call(&mut req));
+}
+
+#[test]
+fn scan_results_are_only_visible_to_admins() {
+    let (_b, app, middle) = ::app();
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Get,
+        "/api/v1/crates/foo_scanned/1.0.0/scan_results",
+    );
+    let (user, admin) = {
+        let conn = app.diesel_database.get().unwrap();
+        let user = ::new_user("foo").create_or_update(&conn).unwrap();
+        let admin = ::new_admin("admin").create_or_update(&conn).unwrap();
+        let c = ::CrateBuilder::new("foo_scanned", user.id).expect_build(&conn);
+        let version = ::new_version(c.id, "1.0.0").save(&conn, &[]).unwrap();
+        let results = vec![
+            ScanResult {
+                scanner: "clam".into(),
+                blocked: false,
+                detail: None,
+            },
+            ScanResult {
+                scanner: "other".into(),
+                blocked: true,
+                detail: Some("suspicious build script".into()),
+            },
+        ];
+        VersionScanResult::save_all(&conn, version.id, &results).unwrap();
+        (user, admin)
+    };
+
+    ::sign_in_as(&mut req, &user);
+    let json = bad_resp!(middle.call(&mut req));
+    assert!(
+        json.errors[0].detail.contains("only administrators"),
+        "{:?}",
+        json.errors
+    );
+
+    ::logout(&mut req);
+    ::sign_in_as(&mut req, &admin);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: ScanResults = ::json(&mut response);
+    assert_eq!(json.scan_results.len(), 2);
+    let blocked = json.scan_results
+        .iter()
+        .find(|r| r.scanner == "other")
+        .unwrap();
+    assert!(blocked.blocked);
+    assert_eq!(
+        blocked.detail,
+        Some(String::from("suspicious build script"))
+    );
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use conduit::{Handler, Method};
+use diesel::prelude::*;
+
+use models::WebhookDelivery;
+use schema::webhook_deliveries;
+
+#[derive(Deserialize)]
+struct Webhook {
+    id: i32,
+    url: String,
+}
+#[derive(Deserialize)]
+struct ListResponse {
+    webhooks: Vec<Webhook>,
+}
+#[derive(Deserialize)]
+struct CreateResponse {
+    webhook: Webhook,
+    secret: String,
+}
+
+#[test]
+fn manage_webhooks() {
+    let (_b, app, middle) = ::app();
+    let (owner, other) = {
+        let conn = app.diesel_database.get().unwrap();
+        let owner = ::new_user("foo").create_or_update(&conn).unwrap();
+        let other = ::new_user("bar").create_or_update(&conn).unwrap();
+        ::CrateBuilder::new("foo_webhooks", owner.id).expect_build(&conn);
+        (owner, other)
+    };
+
+    let mut req = ::req(
+        Arc::clone(&app),
+        Method::Put,
+        "/api/v1/crates/foo_webhooks/webhooks",
+    );
+    req.with_body(br#"{"url":"https://example.com/hook"}"#);
+    ::sign_in_as(&mut req, &other);
+    bad_resp!(middle.call(&mut req));
+
+    ::sign_in_as(&mut req, &owner);
+    req.with_body(br#"{"url":"ftp://example.com/hook"}"#);
+    bad_resp!(middle.call(&mut req));
+    req.with_body(br#"{"url":"http://169.254.169.254/latest/meta-data"}"#);
+    bad_resp!(middle.call(&mut req));
+
+    req.with_body(br#"{"url":"https://example.com/hook"}"#);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let created = ::json::<CreateResponse>(&mut response);
+    assert_eq!(created.webhook.url, "https://example.com/hook");
+    assert_eq!(created.secret.len(), 32);
+
+    req.with_method(Method::Get);
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: ListResponse = ::json(&mut response);
+    assert_eq!(json.webhooks.len(), 1);
+    assert_eq!(json.webhooks[0].id, created.webhook.id);
+
+    req.with_method(Method::Delete).with_path(&format!(
+        "/api/v1/crates/foo_webhooks/webhooks/{}",
+        created.webhook.id
+    ));
+    ok_resp!(middle.call(&mut req));
+
+    req.with_method(Method::Get)
+        .with_path("/api/v1/crates/foo_webhooks/webhooks");
+    let mut response = ok_resp!(middle.call(&mut req));
+    let json: ListResponse = ::json(&mut response);
+    assert!(json.webhooks.is_empty());
+}
+
+#[test]
+fn yank_queues_deliveries() {
+    let (_b, app, middle) = ::app();
+
+    let mut req = ::new_req(Arc::clone(&app), "fwh", "1.0.0");
+    ::sign_in(&mut req, &app);
+    ok_resp!(middle.call(&mut req));
+
+    req.with_method(Method::Put)
+        .with_path("/api/v1/crates/fwh/webhooks")
+        .with_body(br#"{"url":"https://example.com/hook"}"#);
+    ok_resp!(middle.call(&mut req));
+
+    req.with_method(Method::Delete)
+        .with_path("/api/v1/crates/fwh/1.0.0/yank");
+    ok_resp!(middle.call(&mut req));
+    req.with_method(Method::Put)
+        .with_path("/api/v1/crates/fwh/1.0.0/unyank");
+    ok_resp!(middle.call(&mut req));
+
+    let conn = app.diesel_database.get().unwrap();
+    let deliveries = webhook_deliveries::table
+        .order(webhook_deliveries::id)
+        .load::<WebhookDelivery>(&*conn)
+        .unwrap();
+    let events = deliveries.iter().map(|d| &*d.event).collect::<Vec<_>>();
+    assert_eq!(events, ["yank", "unyank"]);
+    assert_eq!(
+        deliveries[0].payload,
+        json!({ "event": "yank", "crate": "fwh", "version": "1.0.0" })
+    );
+    assert!(deliveries.iter().all(|d| d.delivered_at.is_none()));
+}
+
+#[test]
+fn publish_queues_deliveries() {
+    let (_b, app, middle) = ::app();
+
+    let mut req = ::new_req(Arc::clone(&app), "fwh_publish", "1.0.0");
+    let user = ::sign_in(&mut req, &app);
+    ok_resp!(middle.call(&mut req));
+
+    req.with_method(Method::Put)
+        .with_path("/api/v1/crates/fwh_publish/webhooks")
+        .with_body(br#"{"url":"https://example.com/hook"}"#);
+    ok_resp!(middle.call(&mut req));
+
+    let mut req = ::new_req(Arc::clone(&app), "fwh_publish", "1.1.0");
+    ::sign_in_as(&mut req, &user);
+    ok_resp!(middle.call(&mut req));
+
+    let conn = app.diesel_database.get().unwrap();
+    let deliveries = webhook_deliveries::table
+        .load::<WebhookDelivery>(&*conn)
+        .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].event, "publish");
+    assert_eq!(
+        deliveries[0].payload,
+        json!({ "event": "publish", "crate": "fwh_publish", "version": "1.1.0" })
+    );
+}
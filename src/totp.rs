@@ -0,0 +1,145 @@
+//! Time-based one-time passwords (RFC 6238), the second factor users enroll
+//! with an authenticator app.
+//!
+//! Secrets are shared with the app base32 encoded, in an `otpauth://` URL
+//! usually shown as a QR code. Codes are 6 digits long and change every 30
+//! seconds.
+
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use rand::{thread_rng, Rng};
+use url::form_urlencoded;
+
+/// How long a code is valid, in seconds.
+const STEP: i64 = 30;
+
+/// How many steps a code may be ahead or behind, to account for clock drift
+/// and for codes typed just before they changed.
+const SKEW: i64 = 1;
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Returns a new random secret, base32 encoded.
+pub fn generate_secret() -> String {
+    let mut secret = [0; 20];
+    thread_rng().fill_bytes(&mut secret);
+    base32_encode(&secret)
+}
+
+/// The URL authenticator apps are given to register the secret.
+pub fn otpauth_url(secret: &str, login: &str) -> String {
+    let label = form_urlencoded::byte_serialize(format!("crates.io:{}", login).as_bytes())
+        .collect::<String>();
+    format!(
+        "otpauth://totp/{}?secret={}&issuer=crates.io",
+        label, secret
+    )
+}
+
+/// Returns the code for the given step of 30 seconds since the Unix epoch.
+pub fn code(secret: &[u8], step: i64) -> u32 {
+    let key = PKey::hmac(secret).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha1(), &key).unwrap();
+    let counter = (0..8).rev().map(|i| (step >> (i * 8)) as u8).collect::<Vec<_>>();
+    signer.update(&counter).unwrap();
+    let hash = signer.sign_to_vec().unwrap();
+
+    // Dynamic truncation, see section 5.3 of RFC 4226
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let binary = (u32::from(hash[offset]) & 0x7f) << 24 | u32::from(hash[offset + 1]) << 16
+        | u32::from(hash[offset + 2]) << 8 | u32::from(hash[offset + 3]);
+    binary % 1_000_000
+}
+
+/// Returns the code shown at `timestamp` for the base32 encoded secret.
+pub fn code_at(secret: &str, timestamp: i64) -> Option<String> {
+    base32_decode(secret).map(|secret| format!("{:06}", code(&secret, timestamp / STEP)))
+}
+
+/// Returns whether `code` is valid at `timestamp` for the base32 encoded secret.
+pub fn verify(secret: &str, code: &str, timestamp: i64) -> bool {
+    matching_step(secret, code, timestamp).is_some()
+}
+
+/// Returns the step `code` was generated for if it is valid at `timestamp`,
+/// so that callers can refuse codes that were already used.
+pub fn matching_step(secret: &str, code: &str, timestamp: i64) -> Option<i64> {
+    let secret = base32_decode(secret)?;
+    let code = code.trim();
+    if code.len() != 6 {
+        return None;
+    }
+    let code = code.parse::<u32>().ok()?;
+    let step = timestamp / STEP;
+    (step - SKEW..step + SKEW + 1).find(|&s| self::code(&secret, s) == code)
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in encoded.trim_right_matches('=').bytes() {
+        let value = ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_6238_test_vectors() {
+        let secret = b"12345678901234567890";
+        assert_eq!(code(secret, 59 / STEP), 287_082);
+        assert_eq!(code(secret, 1_111_111_109 / STEP), 81_804);
+        assert_eq!(code(secret, 1_234_567_890 / STEP), 5_924);
+        assert_eq!(code(secret, 2_000_000_000 / STEP), 279_037);
+    }
+
+    #[test]
+    fn base32_round_trip() {
+        assert_eq!(base32_encode(b"12345678901234567890"), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        let secret = generate_secret();
+        assert_eq!(base32_encode(&base32_decode(&secret).unwrap()), secret);
+        assert_eq!(base32_decode("not base32!"), None);
+    }
+
+    #[test]
+    fn verify_accepts_adjacent_steps() {
+        let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        assert!(verify(secret, "287082", 59));
+        assert!(verify(secret, "287082", 59 + STEP));
+        assert!(!verify(secret, "287082", 59 + 2 * STEP));
+        assert!(!verify(secret, "28708", 59));
+        assert!(!verify(secret, "abcdef", 59));
+        assert_eq!(matching_step(secret, "287082", 59 + STEP), Some(1));
+    }
+}
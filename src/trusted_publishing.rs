@@ -0,0 +1,287 @@
+//! Verification of the OIDC tokens GitHub Actions issues to workflows, used to
+//! publish crates from CI without storing a long-lived API token there.
+//!
+//! The owners of a crate register the repository and workflow allowed to
+//! publish it. A run of that workflow then exchanges its OIDC token, a JWT
+//! signed by GitHub, for a short-lived publish token.
+
+use chrono::Utc;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::sign::Verifier;
+use serde_json;
+
+use app::App;
+use util::{human, internal, CargoResult, ChainError};
+
+/// The issuer of the OIDC tokens of GitHub Actions.
+pub const GITHUB_ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+/// The audience the OIDC tokens must be requested for.
+pub const AUDIENCE: &str = "crates.io";
+
+/// How long a publish token obtained with an OIDC token is valid, in minutes.
+pub const TOKEN_LIFETIME: i32 = 30;
+
+/// The claims of a GitHub Actions OIDC token used to identify the workflow.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct GitHubClaims {
+    pub iss: String,
+    pub aud: String,
+    pub exp: i64,
+    pub repository_owner: String,
+    /// The repository the workflow runs in, as `owner/name`.
+    pub repository: String,
+    /// The workflow file and the ref it ran from, e.g.
+    /// `owner/name/.github/workflows/release.yml@refs/heads/main`.
+    pub job_workflow_ref: String,
+}
+
+impl GitHubClaims {
+    /// The name of the repository, without its owner.
+    pub fn repository_name(&self) -> &str {
+        self.repository
+            .splitn(2, '/')
+            .nth(1)
+            .unwrap_or(&self.repository)
+    }
+
+    /// The file name of the workflow, e.g. `release.yml`, if it belongs to
+    /// `repository`. Reusable workflows of other repositories don't identify
+    /// the repository they run in, since any repository can call them.
+    pub fn workflow_filename(&self) -> Option<&str> {
+        let path = self.job_workflow_ref.splitn(2, '@').next()?;
+        let prefix = format!("{}/.github/workflows/", self.repository);
+        if path.starts_with(&prefix) {
+            Some(&path[prefix.len()..])
+        } else {
+            None
+        }
+    }
+}
+
+/// A public key of the issuer, as published in its JWKS document.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Jwk {
+    pub kid: String,
+    pub kty: String,
+    pub n: String,
+    pub e: String,
+}
+
+impl Jwk {
+    fn public_key(&self) -> CargoResult<PKey> {
+        if self.kty != "RSA" {
+            return Err(human("unsupported OIDC signing key"));
+        }
+        let n = base64url_decode(&self.n).chain_error(|| human("invalid OIDC signing key"))?;
+        let e = base64url_decode(&self.e).chain_error(|| human("invalid OIDC signing key"))?;
+        let rsa = Rsa::from_public_components(BigNum::from_slice(&n)?, BigNum::from_slice(&e)?)?;
+        Ok(PKey::from_rsa(rsa)?)
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: String,
+}
+
+/// Fetches the signing keys of GitHub Actions and verifies the token with them.
+pub fn verify(app: &App, jwt: &str) -> CargoResult<GitHubClaims> {
+    #[derive(Deserialize)]
+    struct Jwks {
+        keys: Vec<Jwk>,
+    }
+
+    let url = format!("{}/.well-known/jwks", GITHUB_ISSUER);
+    let mut handle = app.handle();
+    handle.url(&url)?;
+    handle.get(true)?;
+    let mut data = Vec::new();
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|buf| {
+            data.extend_from_slice(buf);
+            Ok(buf.len())
+        })?;
+        transfer.perform()?;
+    }
+    if handle.response_code()? != 200 {
+        return Err(internal("couldn't fetch the OIDC signing keys"));
+    }
+    let jwks: Jwks = serde_json::from_slice(&data)?;
+    verify_with_keys(jwt, &jwks.keys, Utc::now().timestamp())
+}
+
+/// Verifies the signature and the claims of a token, `now` being the current
+/// unix timestamp.
+pub fn verify_with_keys(jwt: &str, keys: &[Jwk], now: i64) -> CargoResult<GitHubClaims> {
+    let invalid = || human("invalid OIDC token");
+
+    let mut parts = jwt.split('.');
+    let (header, payload, signature) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(h), Some(p), Some(s)) if parts.next().is_none() => (h, p, s),
+        _ => return Err(invalid()),
+    };
+    let decoded = base64url_decode(header).chain_error(&invalid)?;
+    let header: Header = serde_json::from_slice(&decoded).chain_error(&invalid)?;
+    if header.alg != "RS256" {
+        return Err(human("unsupported OIDC token algorithm"));
+    }
+    let key = keys.iter()
+        .find(|k| k.kid == header.kid)
+        .chain_error(|| human("the OIDC token was signed by an unknown key"))?;
+    let signature = base64url_decode(signature).chain_error(&invalid)?;
+    let signing_input = &jwt[..jwt.rfind('.').unwrap()];
+    if !verify_rs256(&key.public_key()?, signing_input.as_bytes(), &signature)? {
+        return Err(human("the signature of the OIDC token is invalid"));
+    }
+
+    let decoded = base64url_decode(payload).chain_error(&invalid)?;
+    let claims: GitHubClaims = serde_json::from_slice(&decoded).chain_error(&invalid)?;
+    if claims.iss != GITHUB_ISSUER {
+        return Err(human("the OIDC token wasn't issued by GitHub Actions"));
+    }
+    if claims.aud != AUDIENCE {
+        return Err(human(&format_args!(
+            "the OIDC token must be requested for the `{}` audience",
+            AUDIENCE
+        )));
+    }
+    if claims.exp <= now {
+        return Err(human("the OIDC token has expired"));
+    }
+    Ok(claims)
+}
+
+fn verify_rs256(key: &PKey, data: &[u8], signature: &[u8]) -> CargoResult<bool> {
+    let mut verifier = Verifier::new(MessageDigest::sha256(), key)?;
+    verifier.update(data)?;
+    Ok(verifier.verify(signature).unwrap_or(false))
+}
+
+/// Decodes unpadded base64 with the URL-safe alphabet, as used by JWTs.
+fn base64url_decode(s: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'...b'Z' => Some(u32::from(c - b'A')),
+            b'a'...b'z' => Some(u32::from(c - b'a') + 26),
+            b'0'...b'9' => Some(u32::from(c - b'0') + 52),
+            b'-' => Some(62),
+            b'_' => Some(63),
+            _ => None,
+        }
+    }
+
+    let s = s.trim_right_matches('=').as_bytes();
+    if s.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.chunks(4) {
+        let mut acc = 0;
+        for &c in chunk {
+            acc = (acc << 6) | value(c)?;
+        }
+        acc <<= 6 * (4 - chunk.len() as u32);
+        let bytes = [(acc >> 16) as u8, (acc >> 8) as u8, acc as u8];
+        out.extend_from_slice(&bytes[..chunk.len() - 1]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::sign::Signer;
+
+    fn base64url_encode(data: &[u8]) -> String {
+        const ALPHABET: &[u8] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        let mut out = String::new();
+        for chunk in data.chunks(3) {
+            let mut acc = 0u32;
+            for (i, &b) in chunk.iter().enumerate() {
+                acc |= u32::from(b) << (16 - 8 * i);
+            }
+            for i in 0..chunk.len() + 1 {
+                out.push(ALPHABET[(acc >> (18 - 6 * i) & 63) as usize] as char);
+            }
+        }
+        out
+    }
+
+    fn claims() -> serde_json::Value {
+        json!({
+            "iss": GITHUB_ISSUER,
+            "aud": AUDIENCE,
+            "exp": 2000,
+            "repository_owner": "rust-lang",
+            "repository": "rust-lang/regex",
+            "job_workflow_ref": "rust-lang/regex/.github/workflows/release.yml@refs/tags/1.0.0",
+        })
+    }
+
+    fn sign(key: &PKey, kid: &str, claims: &serde_json::Value) -> String {
+        let header = json!({ "alg": "RS256", "kid": kid });
+        let input = format!(
+            "{}.{}",
+            base64url_encode(header.to_string().as_bytes()),
+            base64url_encode(claims.to_string().as_bytes())
+        );
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(input.as_bytes()).unwrap();
+        format!("{}.{}", input, base64url_encode(&signer.sign_to_vec().unwrap()))
+    }
+
+    #[test]
+    fn base64url() {
+        assert_eq!(base64url_decode("").unwrap(), b"");
+        assert_eq!(base64url_decode("Zg").unwrap(), b"f");
+        assert_eq!(base64url_decode("Zm8").unwrap(), b"fo");
+        assert_eq!(base64url_decode("Zm9v").unwrap(), b"foo");
+        assert_eq!(base64url_decode("Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(base64url_decode("-_8").unwrap(), [0xfb, 0xff]);
+        assert!(base64url_decode("Zm9v+").is_none());
+        assert_eq!(base64url_encode(b"foob"), "Zm9vYg");
+    }
+
+    #[test]
+    fn workflow_claims() {
+        let claims: GitHubClaims = serde_json::from_value(claims()).unwrap();
+        assert_eq!(claims.repository_name(), "regex");
+        assert_eq!(claims.workflow_filename(), Some("release.yml"));
+
+        let mut reusable = claims();
+        reusable["repository"] = "someone/regex".into();
+        let claims: GitHubClaims = serde_json::from_value(reusable).unwrap();
+        assert_eq!(claims.workflow_filename(), None);
+    }
+
+    #[test]
+    fn signature_is_verified() {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let other = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let jwt = sign(&key, "key", &claims());
+        let input = &jwt[..jwt.rfind('.').unwrap()];
+        let signature = base64url_decode(&jwt[jwt.rfind('.').unwrap() + 1..]).unwrap();
+
+        assert!(verify_rs256(&key, input.as_bytes(), &signature).unwrap());
+        assert!(!verify_rs256(&other, input.as_bytes(), &signature).unwrap());
+        assert!(!verify_rs256(&key, b"tampered", &signature).unwrap());
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        assert!(verify_with_keys("", &[], 1000).is_err());
+        assert!(verify_with_keys("a.b", &[], 1000).is_err());
+        assert!(verify_with_keys("a.b.c.d", &[], 1000).is_err());
+
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let jwt = sign(&key, "unknown", &claims());
+        assert!(verify_with_keys(&jwt, &[], 1000).is_err());
+    }
+}
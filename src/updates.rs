@@ -0,0 +1,259 @@
+//! A stream of the publishes, yanks and owner changes of crates, so that the
+//! operators of mirrors can follow the registry without polling the index.
+//!
+//! Recording an `AuditAction` notifies the `registry_updates` Postgres
+//! channel, which is only delivered once the transaction of the handler
+//! commits. Each server listens on the channel and forwards the updates to
+//! the clients of `GET /updates/stream`, whichever server made the change.
+
+use std::cmp;
+use std::ffi::{CStr, CString};
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use pq_sys::*;
+use serde_json;
+
+use util::errors::TooManySubscribers;
+use util::{internal, CargoResult};
+use App;
+
+/// The Postgres channel the updates are sent on.
+pub const CHANNEL: &str = "registry_updates";
+
+/// How many seconds the stream waits for an update before sending a comment,
+/// so that proxies don't close the idle connection.
+const KEEPALIVE_SECS: u64 = 15;
+
+/// A change to a crate, sent as an event named after its `action`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Update {
+    /// One of `publish`, `yank`, `unyank`, `owner_add` and `owner_remove`.
+    pub action: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: Option<String>,
+}
+
+/// Notifies the servers of the update, once the current transaction commits.
+pub fn notify(conn: &PgConnection, update: &Update) -> QueryResult<()> {
+    let payload = serde_json::to_string(update)
+        .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+    diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(CHANNEL)
+        .bind::<Text, _>(payload)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// The clients of the stream connected to this server.
+///
+/// Each client holds a server thread blocked on its `Stream`, so only `max`
+/// of them are accepted at once to keep threads for the other requests.
+#[derive(Debug)]
+pub struct Subscribers {
+    senders: Mutex<Vec<Sender<Update>>>,
+    active: Arc<AtomicUsize>,
+    max: usize,
+}
+
+impl Subscribers {
+    pub fn new(max: usize) -> Subscribers {
+        Subscribers {
+            senders: Mutex::new(Vec::new()),
+            active: Arc::new(AtomicUsize::new(0)),
+            max,
+        }
+    }
+
+    /// Returns the stream of the updates sent from now on, or an error if
+    /// `max` clients are already connected.
+    pub fn subscribe(&self) -> CargoResult<Stream> {
+        if self.active.fetch_add(1, Ordering::SeqCst) >= self.max {
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            return Err(Box::new(TooManySubscribers));
+        }
+        let (sender, receiver) = mpsc::channel();
+        self.senders.lock().unwrap().push(sender);
+        Ok(Stream {
+            updates: receiver,
+            buf: Vec::new(),
+            pos: 0,
+            active: Arc::clone(&self.active),
+        })
+    }
+
+    /// Sends the update to every client, forgetting the disconnected ones.
+    pub fn broadcast(&self, update: &Update) {
+        self.senders
+            .lock()
+            .unwrap()
+            .retain(|sender| sender.send(update.clone()).is_ok());
+    }
+}
+
+/// The body of `GET /updates/stream`, as server-sent events. Reading blocks
+/// until the next update.
+#[derive(Debug)]
+pub struct Stream {
+    updates: Receiver<Update>,
+    buf: Vec<u8>,
+    pos: usize,
+    active: Arc<AtomicUsize>,
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            self.buf = match self.updates.recv_timeout(Duration::from_secs(KEEPALIVE_SECS)) {
+                Ok(update) => format!(
+                    "event: {}\ndata: {}\n\n",
+                    update.action,
+                    serde_json::to_string(&update)?
+                ).into_bytes(),
+                Err(RecvTimeoutError::Timeout) => b": keepalive\n\n".to_vec(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.pos = 0;
+        }
+        let n = cmp::min(out.len(), self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// A connection listening on `CHANNEL`. It uses libpq directly since diesel
+/// doesn't expose the notifications received.
+struct Listener(*mut PGconn);
+
+impl Listener {
+    fn connect(url: &str) -> CargoResult<Listener> {
+        let url = CString::new(url)?;
+        let query = CString::new(format!("LISTEN {}", CHANNEL))?;
+        unsafe {
+            let listener = Listener(PQconnectdb(url.as_ptr()));
+            match PQstatus(listener.0) {
+                ConnStatusType::CONNECTION_OK => {}
+                _ => return Err(internal(&listener.error())),
+            }
+            let result = PQexec(listener.0, query.as_ptr());
+            let status = PQresultStatus(result);
+            PQclear(result);
+            match status {
+                ExecStatusType::PGRES_COMMAND_OK => Ok(listener),
+                _ => Err(internal(&listener.error())),
+            }
+        }
+    }
+
+    fn error(&self) -> String {
+        unsafe {
+            CStr::from_ptr(PQerrorMessage(self.0))
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    /// Returns the payloads of the notifications received since the last call.
+    fn payloads(&mut self) -> CargoResult<Vec<String>> {
+        let mut payloads = Vec::new();
+        unsafe {
+            if PQconsumeInput(self.0) != 1 {
+                return Err(internal(&self.error()));
+            }
+            loop {
+                let notify = PQnotifies(self.0);
+                if notify.is_null() {
+                    break;
+                }
+                payloads.push(CStr::from_ptr((*notify).extra).to_string_lossy().into_owned());
+                PQfreemem(notify as *mut _);
+            }
+        }
+        Ok(payloads)
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        unsafe { PQfinish(self.0) }
+    }
+}
+
+fn listen(app: &App, interval: Duration) -> CargoResult<()> {
+    let mut listener = Listener::connect(&app.config.db_url)?;
+    loop {
+        for payload in listener.payloads()? {
+            match serde_json::from_str(&payload) {
+                Ok(update) => app.update_subscribers.broadcast(&update),
+                Err(e) => error!("invalid registry update `{}`: {}", payload, e),
+            }
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Spawns a thread forwarding the updates to the clients of the stream,
+/// checking for new ones every `interval` and reconnecting when the
+/// connection is lost.
+pub fn spawn_listener(app: Arc<App>, interval: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        if let Err(e) = listen(&app, interval) {
+            error!("failed to listen for registry updates: {}", e);
+        }
+        thread::sleep(Duration::from_secs(5));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_are_sent_as_events() {
+        let subscribers = Subscribers::new(1);
+        let mut stream = subscribers.subscribe().unwrap();
+        subscribers.broadcast(&Update {
+            action: "publish".into(),
+            krate: "foo".into(),
+            version: Some("1.0.0".into()),
+        });
+
+        let mut buf = [0; 256];
+        let n = stream.read(&mut buf).unwrap();
+        assert_eq!(
+            ::std::str::from_utf8(&buf[..n]).unwrap(),
+            concat!(
+                "event: publish\n",
+                "data: {\"action\":\"publish\",\"crate\":\"foo\",\"version\":\"1.0.0\"}\n\n"
+            )
+        );
+
+        drop(subscribers);
+        assert_eq!(stream.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn subscribers_are_capped() {
+        let subscribers = Subscribers::new(1);
+        let stream = subscribers.subscribe().unwrap();
+        assert!(subscribers.subscribe().is_err());
+
+        drop(stream);
+        assert!(subscribers.subscribe().is_ok());
+    }
+}
@@ -1,6 +1,8 @@
+use chrono::Utc;
 use conduit::Request;
 use curl::easy::Easy;
 use flate2::read::GzDecoder;
+use hex;
 use openssl::hash::{Hasher, MessageDigest};
 use s3;
 use semver;
@@ -9,14 +11,20 @@ use tar;
 use util::{human, internal, CargoResult, ChainError};
 use util::{read_le_u32, LimitErrorReader};
 
+use rand::{thread_rng, Rng};
+use std::collections::HashSet;
 use std::env;
 use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::sync::Arc;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use app::App;
 use middleware::app::RequestApp;
 use models::Crate;
+use scanning::{self, ScanResult};
 
 #[derive(Clone, Debug)]
 pub enum Uploader {
@@ -26,6 +34,9 @@ pub enum Uploader {
         bucket: s3::Bucket,
         cdn: Option<String>,
         proxy: Option<String>,
+        /// When set, downloads require authentication and redirect to URLs
+        /// presigned for this many seconds rather than to public links.
+        signed_urls_ttl: Option<i64>,
     },
 
     /// For development usage only: "uploads" crate files to `dist` and serves them
@@ -34,6 +45,19 @@ pub enum Uploader {
 
     /// For one-off scripts where creating a Config is needed, but uploading is not.
     NoOp,
+
+    /// Uploads files to a primary uploader and copies them to replicas, e.g.
+    /// buckets in other regions. Files are served from the nearest healthy
+    /// uploader having them, falling back to the primary and then to the
+    /// other replicas.
+    Replicated {
+        primary: Box<Uploader>,
+        replicas: Vec<Uploader>,
+        /// The uploader nearest to this instance, 0 being the primary and the
+        /// next ones the replicas.
+        nearest: usize,
+        health: Arc<ReplicaHealth>,
+    },
 }
 
 impl Uploader {
@@ -41,15 +65,38 @@ impl Uploader {
         match *self {
             Uploader::S3 { ref proxy, .. } => proxy.as_ref().map(String::as_str),
             Uploader::Local | Uploader::NoOp => None,
+            Uploader::Replicated { ref primary, .. } => primary.proxy(),
+        }
+    }
+
+    /// Returns whether downloads are redirected to time-limited signed URLs,
+    /// in which case only authenticated users may download crates.
+    pub fn signs_downloads(&self) -> bool {
+        match *self {
+            Uploader::S3 {
+                signed_urls_ttl, ..
+            } => signed_urls_ttl.is_some(),
+            Uploader::Local | Uploader::NoOp => false,
+            Uploader::Replicated { ref primary, .. } => primary.signs_downloads(),
         }
     }
 
     /// Returns the URL of an uploaded crate's version archive.
     ///
     /// The function doesn't check for the existence of the file.
-    /// It returns `None` if the current `Uploader` is `NoOp`.
+    /// It returns `None` if the current `Uploader` is `NoOp`. When signing
+    /// downloads, the URL is presigned on the bucket and bypasses the CDN.
     pub fn crate_location(&self, crate_name: &str, version: &str) -> Option<String> {
         match *self {
+            Uploader::S3 {
+                ref bucket,
+                signed_urls_ttl: Some(ttl),
+                ..
+            } => {
+                let path = Uploader::crate_path(crate_name, version);
+                let expires = Utc::now().timestamp() + ttl;
+                Some(bucket.presigned_url(&path, expires))
+            }
             Uploader::S3 {
                 ref bucket,
                 ref cdn,
@@ -64,6 +111,10 @@ impl Uploader {
             }
             Uploader::Local => Some(format!("/{}", Uploader::crate_path(crate_name, version))),
             Uploader::NoOp => None,
+            Uploader::Replicated { .. } => {
+                let path = Uploader::crate_path(crate_name, version);
+                self.serving(&path).crate_location(crate_name, version)
+            }
         }
     }
 
@@ -87,11 +138,87 @@ impl Uploader {
             }
             Uploader::Local => Some(format!("/{}", Uploader::readme_path(crate_name, version))),
             Uploader::NoOp => None,
+            Uploader::Replicated { .. } => {
+                let path = Uploader::readme_path(crate_name, version);
+                self.serving(&path).readme_location(crate_name, version)
+            }
+        }
+    }
+
+    /// Returns the URL of an uploaded crate logo, from the path returned by
+    /// `upload_logo`.
+    ///
+    /// It returns `None` if the current `Uploader` is `NoOp`.
+    pub fn logo_location(&self, path: &str) -> Option<String> {
+        match *self {
+            Uploader::S3 {
+                ref bucket,
+                ref cdn,
+                ..
+            } => {
+                let host = match *cdn {
+                    Some(ref s) => s.clone(),
+                    None => bucket.host(),
+                };
+                Some(format!("https://{}/{}", host, path))
+            }
+            Uploader::Local => Some(format!("/{}", path)),
+            Uploader::NoOp => None,
+            Uploader::Replicated { .. } => self.serving(path).logo_location(path),
+        }
+    }
+
+    /// Wraps the primary uploader to copy the files uploaded to replicas.
+    /// Files are preferably served from the uploader at index `nearest`, 0
+    /// being the primary and the next ones the replicas.
+    pub fn replicated(primary: Uploader, replicas: Vec<Uploader>, nearest: usize) -> Uploader {
+        let health = Arc::new(ReplicaHealth::new(replicas.len() + 1));
+        Uploader::Replicated {
+            primary: Box::new(primary),
+            replicas,
+            nearest,
+            health,
+        }
+    }
+
+    /// Returns the uploader files are stored to first, the primary one of a
+    /// replicated uploader.
+    pub fn primary(&self) -> &Uploader {
+        match *self {
+            Uploader::Replicated { ref primary, .. } => primary.primary(),
+            _ => self,
+        }
+    }
+
+    /// Returns the uploader the file at `path` is served from: the nearest
+    /// healthy one of a replicated uploader that didn't miss the upload of the
+    /// file, or the primary one when there is none.
+    fn serving(&self, path: &str) -> &Uploader {
+        match *self {
+            Uploader::Replicated {
+                ref primary,
+                ref replicas,
+                nearest,
+                ref health,
+            } => {
+                let backends = Some(&**primary)
+                    .into_iter()
+                    .chain(replicas)
+                    .collect::<Vec<_>>();
+                let order = Some(nearest).into_iter().chain(0..backends.len());
+                for i in order {
+                    if i < backends.len() && health.is_healthy(i) && health.has(i, path) {
+                        return backends[i];
+                    }
+                }
+                primary
+            }
+            _ => self,
         }
     }
 
     /// Returns the interna path of an uploaded crate's version archive.
-    fn crate_path(name: &str, version: &str) -> String {
+    pub fn crate_path(name: &str, version: &str) -> String {
         // No slash in front so we can use join
         format!("crates/{}/{}-{}.crate", name, name, version)
     }
@@ -101,26 +228,43 @@ impl Uploader {
         format!("readmes/{}/{}-{}.html", name, name, version)
     }
 
+    /// Returns the internal path of a crate logo. It contains the start of the
+    /// checksum of the logo, so that a new logo isn't hidden by the CDN caching
+    /// the previous one.
+    fn logo_path(name: &str, logo: &[u8], extension: &str) -> String {
+        let mut hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+        hasher.update(logo).unwrap();
+        let cksum = hasher.finish2().unwrap();
+        format!(
+            "logos/{}/{}-{}.{}",
+            name,
+            name,
+            hex::encode(&cksum[..8]),
+            extension
+        )
+    }
+
     /// Uploads a file using the configured uploader (either `S3`, `Local` or `NoOp`).
     ///
-    /// It returns a a tuple containing the path of the uploaded file
+    /// The body is streamed to the uploader and its checksum is computed along
+    /// the way. It returns a a tuple containing the path of the uploaded file
     /// and its checksum.
-    pub fn upload(
+    pub fn upload<R: Read>(
         &self,
         mut handle: Easy,
         path: &str,
-        body: &[u8],
+        body: R,
         content_type: &str,
         content_length: u64,
     ) -> CargoResult<(Option<String>, Vec<u8>)> {
-        let hash = hash(body);
+        let mut body = HashingReader::new(body);
         match *self {
             Uploader::S3 { ref bucket, .. } => {
                 let (response, cksum) = {
                     let mut response = Vec::new();
                     {
                         let mut s3req =
-                            bucket.put(&mut handle, path, body, content_type, content_length);
+                            bucket.put(&mut handle, path, &mut body, content_type, content_length);
                         s3req
                             .write_function(|data| {
                                 response.extend(data);
@@ -131,7 +275,7 @@ impl Uploader {
                             internal(&format_args!("failed to upload to S3: `{}`", path))
                         })?;
                     }
-                    (response, hash)
+                    (response, body.finish())
                 };
                 if handle.response_code().unwrap() != 200 {
                     let response = String::from_utf8_lossy(&response);
@@ -147,15 +291,84 @@ impl Uploader {
                 let dir = filename.parent().unwrap();
                 fs::create_dir_all(dir)?;
                 let mut file = File::create(&filename)?;
-                file.write_all(body)?;
-                Ok((filename.to_str().map(String::from), hash))
+                io::copy(&mut body, &mut file)?;
+                Ok((filename.to_str().map(String::from), body.finish()))
             }
             Uploader::NoOp => Ok((None, vec![])),
+            Uploader::Replicated {
+                ref primary,
+                ref replicas,
+                ref health,
+                ..
+            } => {
+                // The body is read once and sent to each uploader
+                let mut buf = Vec::new();
+                body.read_to_end(&mut buf)?;
+                let uploaded =
+                    primary.upload(handle, path, &buf[..], content_type, content_length);
+                health.record(0, path, uploaded.is_ok());
+                let uploaded = uploaded?;
+                for (i, replica) in replicas.iter().enumerate() {
+                    let res = replica.upload(
+                        replica.handle(),
+                        path,
+                        &buf[..],
+                        content_type,
+                        content_length,
+                    );
+                    if let Err(ref e) = res {
+                        println!("unable to upload {} to replica {}, {:?}", path, i + 1, e);
+                    }
+                    health.record(i + 1, path, res.is_ok());
+                }
+                Ok((uploaded.0, body.finish()))
+            }
+        }
+    }
+
+    /// Checks that files can be uploaded, for the readiness check of the load
+    /// balancers. Any response from S3 will do, even an error. The replicas of
+    /// a replicated uploader aren't checked since files are served from the
+    /// primary when they are unhealthy.
+    pub fn check_reachable(&self) -> CargoResult<()> {
+        match *self {
+            Uploader::S3 { ref bucket, .. } => {
+                let mut handle = self.handle();
+                handle.url(&bucket.url())?;
+                handle.nobody(true)?;
+                handle.timeout(Duration::from_secs(5))?;
+                handle.perform().chain_error(|| {
+                    internal(&format_args!("failed to reach S3 at `{}`", bucket.host()))
+                })?;
+                Ok(())
+            }
+            Uploader::Local => {
+                // Files can be written to the directory they're uploaded to
+                let dir = env::current_dir()?.join("local_uploads");
+                fs::create_dir_all(&dir)?;
+                let probe = dir.join(format!(".readyz-{}", thread_rng().gen::<u32>()));
+                File::create(&probe)?;
+                fs::remove_file(&probe)?;
+                Ok(())
+            }
+            Uploader::NoOp => Ok(()),
+            Uploader::Replicated { ref primary, .. } => primary.check_reachable(),
         }
     }
 
+    /// Returns a handle for requests to this uploader.
+    fn handle(&self) -> Easy {
+        let mut handle = Easy::new();
+        if let Some(proxy) = self.proxy() {
+            handle.proxy(proxy).unwrap();
+        }
+        handle
+    }
+
     /// Uploads a crate and its readme. Returns the checksum of the uploaded crate
-    /// file, and bombs for the uploaded crate and the uploaded readme.
+    /// file, bombs for the uploaded crate and the uploaded readme, the verdicts
+    /// of the scanners the crate file was sent to, and the `Cargo.toml` and the
+    /// files found in the crate file.
     pub fn upload_crate(
         &self,
         req: &mut Request,
@@ -164,21 +377,29 @@ impl Uploader {
         max: u64,
         max_unpack: u64,
         vers: &semver::Version,
-    ) -> CargoResult<(Vec<u8>, Bomb, Bomb)> {
+    ) -> CargoResult<(Vec<u8>, Bomb, Bomb, Vec<ScanResult>, VerifiedTarball)> {
         let app = Arc::clone(req.app());
-        let (crate_path, checksum) = {
+        let (crate_path, checksum, scan_results, verified) = {
             let path = Uploader::crate_path(&krate.name, &vers.to_string());
             let length = read_le_u32(req.body())?;
-            let mut body = Vec::new();
-            LimitErrorReader::new(req.body(), max).read_to_end(&mut body)?;
-            verify_tarball(krate, vers, &body, max_unpack)?;
-            self.upload(
+            let tarball = SpooledFile::create(LimitErrorReader::new(req.body(), max))?;
+            let verified = verify_tarball(krate, vers, tarball.open()?, max_unpack)?;
+            let scan_results = scanning::scan(&app, &tarball);
+            if app.config.scan_policy.blocks(&scan_results) {
+                return Err(human(
+                    "this crate was flagged by our security scanners and cannot be \
+                     published. If you believe this is a mistake, please contact \
+                     help@crates.io",
+                ));
+            }
+            let (path, checksum) = self.upload(
                 app.handle(),
                 &path,
-                &body,
+                tarball.open()?,
                 "application/x-tar",
                 u64::from(length),
-            )?
+            )?;
+            (path, checksum, scan_results, verified)
         };
         // We create the bomb for the crate file before uploading the readme so that if the
         // readme upload fails, the uploaded crate file is automatically deleted.
@@ -206,9 +427,27 @@ impl Uploader {
                 app: Arc::clone(&app),
                 path: readme_path,
             },
+            scan_results,
+            verified,
         ))
     }
 
+    /// Uploads the logo of a crate, already validated, returning the path to
+    /// give to `logo_location` or `None` if the current `Uploader` is `NoOp`.
+    pub fn upload_logo(
+        &self,
+        app: &Arc<App>,
+        krate: &Crate,
+        logo: &[u8],
+        content_type: &str,
+        extension: &str,
+    ) -> CargoResult<Option<String>> {
+        let path = Uploader::logo_path(&krate.name, logo, extension);
+        let length = logo.len() as u64;
+        let (uploaded, _) = self.upload(app.handle(), &path, logo, content_type, length)?;
+        Ok(uploaded.map(|_| path))
+    }
+
     /// Deletes an uploaded file.
     fn delete(&self, app: &Arc<App>, path: &str) -> CargoResult<()> {
         match *self {
@@ -222,6 +461,60 @@ impl Uploader {
                 Ok(())
             }
             Uploader::NoOp => Ok(()),
+            Uploader::Replicated {
+                ref primary,
+                ref replicas,
+                ..
+            } => {
+                for replica in replicas {
+                    if let Err(e) = replica.delete(app, path) {
+                        println!("unable to delete {} from a replica, {:?}", path, e);
+                    }
+                }
+                primary.delete(app, path)
+            }
+        }
+    }
+}
+
+/// Whether each uploader of a replicated uploader is healthy, that is whether
+/// the last upload to it succeeded, and the files each one missed.
+///
+/// An uploader that is healthy again still lacks the files uploaded while it
+/// wasn't, so they keep being served from another uploader.
+#[derive(Debug)]
+pub struct ReplicaHealth {
+    unhealthy: Vec<AtomicBool>,
+    /// The paths of the files whose upload to each uploader failed.
+    missed: Vec<Mutex<HashSet<String>>>,
+}
+
+impl ReplicaHealth {
+    /// All the `count` uploaders, the primary included, start healthy.
+    pub fn new(count: usize) -> ReplicaHealth {
+        ReplicaHealth {
+            unhealthy: (0..count).map(|_| AtomicBool::new(false)).collect(),
+            missed: (0..count).map(|_| Mutex::new(HashSet::new())).collect(),
+        }
+    }
+
+    pub fn is_healthy(&self, i: usize) -> bool {
+        !self.unhealthy[i].load(Ordering::SeqCst)
+    }
+
+    /// Returns whether the uploader didn't miss the upload of the file at
+    /// `path`.
+    pub fn has(&self, i: usize, path: &str) -> bool {
+        !self.missed[i].lock().unwrap().contains(path)
+    }
+
+    fn record(&self, i: usize, path: &str, success: bool) {
+        self.unhealthy[i].store(!success, Ordering::SeqCst);
+        let mut missed = self.missed[i].lock().unwrap();
+        if success {
+            missed.remove(path);
+        } else {
+            missed.insert(path.to_string());
         }
     }
 }
@@ -243,12 +536,35 @@ impl Drop for Bomb {
     }
 }
 
-fn verify_tarball(
+/// The largest `Cargo.toml` kept from a crate file, in bytes.
+const MAX_MANIFEST_SIZE: u64 = 512 * 1024;
+
+/// A file found in a crate file.
+#[derive(Clone, Debug)]
+pub struct TarballFile {
+    /// The path of the file relative to the directory of the crate.
+    pub path: String,
+    pub size: u64,
+    /// The SHA-256 checksum of the file, in hex.
+    pub sha256: String,
+}
+
+/// What was found in a crate file while verifying it.
+#[derive(Debug, Default)]
+pub struct VerifiedTarball {
+    /// The text of its `Cargo.toml`, unless it's too large.
+    pub manifest: Option<String>,
+    pub files: Vec<TarballFile>,
+}
+
+/// Checks that all the files of the crate file are in its directory, returning
+/// the text of its `Cargo.toml` and the list of its files.
+fn verify_tarball<R: Read>(
     krate: &Crate,
     vers: &semver::Version,
-    tarball: &[u8],
+    tarball: R,
     max_unpack: u64,
-) -> CargoResult<()> {
+) -> CargoResult<VerifiedTarball> {
     // All our data is currently encoded with gzip
     let decoder = GzDecoder::new(tarball)?;
 
@@ -259,8 +575,10 @@ fn verify_tarball(
     // Use this I/O object now to take a peek inside
     let mut archive = tar::Archive::new(decoder);
     let prefix = format!("{}-{}", krate.name, vers);
+    let manifest_path = Path::new(&prefix).join("Cargo.toml");
+    let mut verified = VerifiedTarball::default();
     for entry in archive.entries()? {
-        let entry = entry
+        let mut entry = entry
             .chain_error(|| human("uploaded tarball is malformed or too large when decompressed"))?;
 
         // Verify that all entries actually start with `$name-$vers/`.
@@ -268,15 +586,137 @@ fn verify_tarball(
         // upload a tarball that contains both `foo-0.1.0/` source code as well
         // as `bar-0.1.0/` source code, and this could overwrite other crates in
         // the registry!
-        if !entry.path()?.starts_with(&prefix) {
+        let path = entry.path()?.into_owned();
+        if !path.starts_with(&prefix) {
             return Err(human("invalid tarball uploaded"));
         }
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let size = entry.header().size()?;
+        let mut reader = HashingReader::new(&mut entry);
+        if path == manifest_path && size <= MAX_MANIFEST_SIZE {
+            let mut text = Vec::new();
+            reader.read_to_end(&mut text)?;
+            verified.manifest = String::from_utf8(text).ok();
+        } else {
+            io::copy(&mut reader, &mut io::sink())?;
+        }
+        verified.files.push(TarballFile {
+            path: path.strip_prefix(&prefix)
+                .unwrap_or(&*path)
+                .to_string_lossy()
+                .into_owned(),
+            size,
+            sha256: hex::encode(reader.finish()),
+        });
     }
-    Ok(())
+    Ok(verified)
 }
 
-fn hash(data: &[u8]) -> Vec<u8> {
-    let mut hasher = Hasher::new(MessageDigest::sha256()).unwrap();
-    hasher.update(data).unwrap();
-    hasher.finish2().unwrap().to_vec()
+/// A crate file received from a client, written to a temporary file so that it
+/// isn't kept in memory while it's verified, scanned and uploaded. The file is
+/// deleted when this is dropped.
+#[derive(Debug)]
+pub struct SpooledFile {
+    path: PathBuf,
+}
+
+impl SpooledFile {
+    fn create<R: Read>(mut body: R) -> CargoResult<SpooledFile> {
+        let name = thread_rng().gen_ascii_chars().take(16).collect::<String>();
+        let spooled = SpooledFile {
+            path: env::temp_dir().join(format!("crate-upload-{}", name)),
+        };
+        let mut file = File::create(&spooled.path)?;
+        io::copy(&mut body, &mut file)?;
+        Ok(spooled)
+    }
+
+    /// Opens the file for reading from its beginning.
+    pub fn open(&self) -> io::Result<File> {
+        File::open(&self.path)
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Computes the SHA-256 checksum of the data read through it.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Hasher::new(MessageDigest::sha256()).unwrap(),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.hasher.finish2().unwrap().to_vec()
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher
+            .update(&buf[..n])
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Uploader;
+    use s3;
+
+    fn bucket(name: &str) -> Uploader {
+        Uploader::S3 {
+            bucket: s3::Bucket::new(name.into(), None, String::new(), String::new(), "https"),
+            cdn: None,
+            proxy: None,
+            signed_urls_ttl: None,
+        }
+    }
+
+    #[test]
+    fn serves_from_the_nearest_healthy_uploader() {
+        let uploader = Uploader::replicated(bucket("primary"), vec![bucket("replica")], 1);
+        let location = |uploader: &Uploader| uploader.crate_location("foo", "1.0.0").unwrap();
+        assert!(location(&uploader).contains("replica"));
+
+        let path = Uploader::crate_path("bar", "1.0.0");
+        if let Uploader::Replicated { ref health, .. } = uploader {
+            health.record(1, &path, false);
+        }
+        assert!(location(&uploader).contains("primary"));
+
+        if let Uploader::Replicated { ref health, .. } = uploader {
+            health.record(0, &path, false);
+        }
+        assert!(location(&uploader).contains("primary"));
+    }
+
+    #[test]
+    fn serves_missed_files_from_the_primary() {
+        let uploader = Uploader::replicated(bucket("primary"), vec![bucket("replica")], 1);
+        let location = |uploader: &Uploader, name| uploader.crate_location(name, "1.0.0").unwrap();
+
+        if let Uploader::Replicated { ref health, .. } = uploader {
+            health.record(1, &Uploader::crate_path("foo", "1.0.0"), false);
+            health.record(1, &Uploader::crate_path("bar", "1.0.0"), true);
+        }
+        assert!(location(&uploader, "foo").contains("primary"));
+        assert!(location(&uploader, "bar").contains("replica"));
+    }
 }
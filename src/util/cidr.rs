@@ -0,0 +1,109 @@
+//! IP address ranges in CIDR notation, such as `10.0.0.0/8`.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use util::errors::{human, CargoError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, *ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: &[u8], ip: &[u8], prefix: u8) -> bool {
+    let bytes = (prefix / 8) as usize;
+    let bits = prefix % 8;
+    if net[..bytes] != ip[..bytes] {
+        return false;
+    }
+    let mask = !(0xffu8 >> bits);
+    bits == 0 || net[bytes] & mask == ip[bytes] & mask
+}
+
+/// Parses `address/prefix`, or a single address.
+impl FromStr for Cidr {
+    type Err = Box<CargoError>;
+
+    fn from_str(s: &str) -> Result<Cidr, Box<CargoError>> {
+        let invalid = || human(&format_args!("invalid IP address range: `{}`", s));
+        let mut parts = s.splitn(2, '/');
+        let addr = parts
+            .next()
+            .and_then(|addr| addr.parse::<IpAddr>().ok())
+            .ok_or_else(invalid)?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Cidr { addr, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn contains() {
+        let cidr = "10.1.0.0/16".parse::<Cidr>().unwrap();
+        assert!(cidr.contains(&ip("10.1.255.3")));
+        assert!(!cidr.contains(&ip("10.2.0.1")));
+        assert!(!cidr.contains(&ip("::1")));
+
+        let cidr = "192.168.1.128/25".parse::<Cidr>().unwrap();
+        assert!(cidr.contains(&ip("192.168.1.200")));
+        assert!(!cidr.contains(&ip("192.168.1.127")));
+
+        let cidr = "2001:db8::/32".parse::<Cidr>().unwrap();
+        assert!(cidr.contains(&ip("2001:db8:1::1")));
+        assert!(!cidr.contains(&ip("2001:db9::1")));
+
+        assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains(&ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn single_addresses() {
+        let cidr = "10.0.0.1".parse::<Cidr>().unwrap();
+        assert_eq!(cidr.to_string(), "10.0.0.1/32");
+        assert!(cidr.contains(&ip("10.0.0.1")));
+        assert!(!cidr.contains(&ip("10.0.0.2")));
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/x".parse::<Cidr>().is_err());
+        assert!("".parse::<Cidr>().is_err());
+    }
+}
@@ -281,6 +281,97 @@ impl fmt::Display for Unauthorized {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct TooManyRequests {
+    pub retry_after: u64,
+}
+
+impl CargoError for TooManyRequests {
+    fn description(&self) -> &str {
+        "too many requests"
+    }
+
+    fn response(&self) -> Option<Response> {
+        let mut response = json_response(&Bad {
+            errors: vec![StringError {
+                detail: format!(
+                    "You have made too many requests. Please try again in {} seconds.",
+                    self.retry_after
+                ),
+            }],
+        });
+        response.status = (429, "Too Many Requests");
+        response
+            .headers
+            .insert("Retry-After".into(), vec![self.retry_after.to_string()]);
+        Some(response)
+    }
+}
+
+impl fmt::Display for TooManyRequests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "too many requests".fmt(f)
+    }
+}
+
+/// Writes refused while the registry is in read-only mode, see
+/// `App::read_only`.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOnlyMode;
+
+impl CargoError for ReadOnlyMode {
+    fn description(&self) -> &str {
+        "read-only mode"
+    }
+
+    fn response(&self) -> Option<Response> {
+        let mut response = json_response(&Bad {
+            errors: vec![StringError {
+                detail: "crates.io is in read-only mode for maintenance, \
+                         please try again in a few minutes"
+                    .to_string(),
+            }],
+        });
+        response.status = (503, "Service Unavailable");
+        Some(response)
+    }
+}
+
+impl fmt::Display for ReadOnlyMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "crates.io is in read-only mode for maintenance".fmt(f)
+    }
+}
+
+/// A `GET /updates/stream` refused since this server already streams to as
+/// many clients as `Config::max_update_subscribers`.
+#[derive(Debug, Clone, Copy)]
+pub struct TooManySubscribers;
+
+impl CargoError for TooManySubscribers {
+    fn description(&self) -> &str {
+        "too many subscribers"
+    }
+
+    fn response(&self) -> Option<Response> {
+        let mut response = json_response(&Bad {
+            errors: vec![StringError {
+                detail: "too many clients are following the updates, \
+                         please try again later"
+                    .to_string(),
+            }],
+        });
+        response.status = (503, "Service Unavailable");
+        Some(response)
+    }
+}
+
+impl fmt::Display for TooManySubscribers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "too many clients are following the updates".fmt(f)
+    }
+}
+
 struct BadRequest(String);
 
 impl CargoError for BadRequest {
@@ -6,12 +6,14 @@ use serde_json;
 
 use conduit::Response;
 
+pub use self::cidr::Cidr;
 pub use self::errors::{bad_request, human, internal, internal_error, CargoError, CargoResult};
 pub use self::errors::{std_error, ChainError};
 pub use self::io_util::{read_fill, read_le_u32, LimitErrorReader};
 pub use self::request_helpers::*;
 pub use self::request_proxy::RequestProxy;
 
+mod cidr;
 pub mod errors;
 mod io_util;
 mod request_helpers;
@@ -16,3 +16,19 @@ pub fn request_header<'a>(req: &'a Request, header_name: &str) -> &'a str {
         .and_then(|x| x.first().map(|&s| s))
         .unwrap_or_default()
 }
+
+/// Returns the address of the client, which is the last one listed in
+/// `X-Forwarded-For` behind the router of Heroku.
+///
+/// The router appends the address it was connected from to the header, the
+/// entries before it were sent by the client and can't be trusted.
+pub fn client_ip(req: &Request) -> String {
+    let forwarded = req.headers()
+        .find("X-Forwarded-For")
+        .and_then(|values| values.last().and_then(|v| v.split(',').last()))
+        .map(|ip| ip.trim());
+    match forwarded {
+        Some(ip) if !ip.is_empty() => ip.to_string(),
+        _ => req.remote_addr().ip().to_string(),
+    }
+}
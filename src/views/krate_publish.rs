@@ -9,7 +9,7 @@ use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use models::krate::MAX_NAME_LENGTH;
 
-use models::Crate;
+use models::{Binary, Crate};
 use models::DependencyKind;
 use models::Keyword as CrateKeyword;
 
@@ -33,6 +33,13 @@ pub struct NewCrate {
     pub badges: Option<HashMap<String, HashMap<String, String>>>,
     #[serde(default)]
     pub links: Option<String>,
+    #[serde(default)]
+    pub edition: Option<String>,
+    #[serde(default)]
+    pub binaries: Vec<Binary>,
+    /// A detached signature of the crate file, e.g. ASCII armored.
+    #[serde(default)]
+    pub signature: Option<String>,
 }
 
 #[derive(PartialEq, Eq, Hash, Serialize, Debug, Deref)]
@@ -63,6 +70,10 @@ pub struct CrateDependency {
     pub version_req: CrateVersionReq,
     pub target: Option<String>,
     pub kind: Option<DependencyKind>,
+    /// The URL of the index of the registry of the crate, if it's not this
+    /// registry.
+    #[serde(default)]
+    pub registry: Option<String>,
 }
 
 impl<'de> Deserialize<'de> for CrateName {
@@ -1,7 +1,19 @@
 use chrono::NaiveDateTime;
+use serde_json::Value;
 use std::collections::HashMap;
 
-use models::DependencyKind;
+use license::LicenseExpression;
+use models::{Binary, DependencyKind};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAnnouncement {
+    pub id: i32,
+    pub message: String,
+    #[serde(with = "::util::rfc3339")]
+    pub starts_at: NaiveDateTime,
+    #[serde(with = "::util::rfc3339::option")]
+    pub ends_at: Option<NaiveDateTime>,
+}
 
 #[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct EncodableBadge {
@@ -32,6 +44,21 @@ pub struct EncodableCategoryWithSubcategories {
     pub subcategories: Vec<EncodableCategory>,
 }
 
+/// The serialization format for the `CrateList` model.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateList {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    /// The login of the user curating the list.
+    pub user: String,
+    pub crates: Vec<String>,
+    #[serde(with = "::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "::util::rfc3339")]
+    pub updated_at: NaiveDateTime,
+}
+
 /// The serialization format for the `CrateOwnerInvitation` model.
 #[derive(Deserialize, Serialize, Debug)]
 pub struct EncodableCrateOwnerInvitation {
@@ -48,6 +75,19 @@ pub struct InvitationResponse {
     pub accepted: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDeletedCrate {
+    pub id: i32,
+    pub name: String,
+    pub reason: Option<String>,
+    /// The login of the administrator who deleted the crate.
+    pub deleted_by: Option<String>,
+    #[serde(with = "::util::rfc3339")]
+    pub deleted_at: NaiveDateTime,
+    pub versions: Vec<String>,
+    pub snapshot: Value,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableDependency {
     pub id: i32,
@@ -67,9 +107,71 @@ pub struct EncodableVersionDownload {
     pub id: i32,
     pub version: i32,
     pub downloads: i32,
+    pub yanked_downloads: i32,
+    pub unique_downloads: i32,
     pub date: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateDownload {
+    pub date: String,
+    pub downloads: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateSpotlight {
+    #[serde(rename = "crate")]
+    pub krate: EncodableCrate,
+    pub featured_on: String,
+    pub rationale: String,
+}
+
+/// A change of the metadata of a crate, as listed to its owners by
+/// `GET /crates/:crate_id/metadata_changes`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateMetadataChange {
+    /// The version whose publish changed the metadata.
+    pub version: Option<String>,
+    pub field: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    #[serde(with = "::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+/// An action of the current user, as listed by `GET /me/activity`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAuditAction {
+    pub action: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: Option<String>,
+    pub detail: Option<String>,
+    #[serde(with = "::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+/// A crate that started depending on another recently, as listed by
+/// `GET /crates/:crate_id/new_dependents`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableNewDependent {
+    pub name: String,
+    pub description: Option<String>,
+    /// When its first version depending on the crate was published.
+    #[serde(with = "::util::rfc3339")]
+    pub first_seen_at: NaiveDateTime,
+}
+
+/// Whether the name of a crate is contested, and when it will be transferred
+/// to the user claiming it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateStatus {
+    pub contested: bool,
+    pub reason: Option<String>,
+    #[serde(with = "::util::rfc3339::option")]
+    pub transfer_at: Option<NaiveDateTime>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableKeyword {
     pub id: String,
@@ -79,6 +181,25 @@ pub struct EncodableKeyword {
     pub crates_cnt: i32,
 }
 
+/// A keyword listed by `GET /keywords/trending`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTrendingKeyword {
+    pub keyword: EncodableKeyword,
+    /// The number of crates of the keyword 30 days ago, or when it was first
+    /// counted if later.
+    pub previous_crates_cnt: i32,
+}
+
+/// An alias of a keyword, as listed by `GET /admin/keyword_aliases`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableKeywordAlias {
+    pub alias: String,
+    /// The keyword the alias stands for.
+    pub keyword: String,
+    #[serde(with = "::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableCrate {
     pub id: String,
@@ -93,11 +214,31 @@ pub struct EncodableCrate {
     pub created_at: NaiveDateTime,
     pub downloads: i32,
     pub recent_downloads: Option<i64>,
+    pub dependency_freshness: Option<f64>,
+    /// Between 0 and 1, `None` until it is first computed.
+    pub quality_score: Option<f64>,
     pub max_version: String,
+    /// The version the crate is presented with, see `Version::default_num`.
+    pub default_version: String,
+    /// Whether every version of the crate is yanked, `max_version` then being
+    /// `0.0.0`.
+    pub all_versions_yanked: bool,
     pub description: Option<String>,
     pub homepage: Option<String>,
     pub documentation: Option<String>,
     pub repository: Option<String>,
+    /// The license expression of the latest version, normalized.
+    pub license: Option<String>,
+    /// The license expression parsed, `None` for non-standard licenses.
+    pub license_expression: Option<LicenseExpression>,
+    pub deprecated: bool,
+    pub deprecation_message: Option<String>,
+    /// The name of the crate to use instead of this deprecated one.
+    pub alternative: Option<String>,
+    /// How its owners maintain it, e.g. `looking-for-maintainer`.
+    pub maintenance_status: Option<String>,
+    /// Redirects to the logo of the crate, if its owners uploaded one.
+    pub logo_url: Option<String>,
     pub links: EncodableCrateLinks,
     pub exact_match: bool,
 }
@@ -112,7 +253,37 @@ pub struct EncodableCrateLinks {
     pub reverse_dependencies: String,
 }
 
+/// An owner of a crate, or a user invited to become one, as listed by
+/// `GET /crates/:crate_id/owners`.
 #[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateOwner {
+    pub id: i32,
+    pub login: String,
+    pub kind: String,
+    pub url: Option<String>,
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+    /// The login of the owner who added or invited them.
+    pub invited_by: Option<String>,
+    /// When they were added, or invited if `pending` is set.
+    #[serde(with = "::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+    /// Whether they still have to accept their invitation.
+    pub pending: bool,
+}
+
+/// What happened to one of the logins sent to `PUT` or
+/// `DELETE /crates/:crate_id/owners`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableOwnerChange {
+    pub login: String,
+    /// `added` (or invited), `removed` or `error`.
+    pub status: String,
+    /// Describes the change, or why it couldn't be made.
+    pub msg: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EncodableOwner {
     pub id: i32,
     pub login: String,
@@ -126,6 +297,10 @@ pub struct EncodableOwner {
 pub struct EncodableTeam {
     pub id: i32,
     pub login: String,
+    /// Always `team`, like the teams listed as owners.
+    pub kind: String,
+    /// The GitHub organization of the team.
+    pub org: Option<String>,
     pub name: Option<String>,
     pub avatar: Option<String>,
     pub url: Option<String>,
@@ -143,6 +318,7 @@ pub struct EncodableApiTokenWithToken {
     pub created_at: NaiveDateTime,
     #[serde(with = "::util::rfc3339::option")]
     pub last_used_at: Option<NaiveDateTime>,
+    pub allowed_ips: Vec<String>,
 }
 
 /// The serialization format for the `User` model.
@@ -155,6 +331,7 @@ pub struct EncodablePrivateUser {
     pub email: Option<String>,
     pub email_verified: bool,
     pub email_verification_sent: bool,
+    pub confirm_new_crates: bool,
     pub name: Option<String>,
     pub avatar: Option<String>,
     pub url: Option<String>,
@@ -171,7 +348,7 @@ pub struct EncodablePublicUser {
     pub url: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EncodableVersion {
     pub id: i32,
     #[serde(rename = "crate")]
@@ -187,10 +364,66 @@ pub struct EncodableVersion {
     pub features: HashMap<String, Vec<String>>,
     pub yanked: bool,
     pub license: Option<String>,
+    pub edition: Option<String>,
+    pub binaries: Vec<Binary>,
+    /// The login of the user the version was published by, only shown to the
+    /// owners of the crate and administrators.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_by: Option<String>,
+    /// How the publish was authenticated, e.g. `api_token:laptop`, only shown
+    /// to the owners of the crate and administrators.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_with: Option<String>,
     pub links: EncodableVersionLinks,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionScanResult {
+    pub scanner: String,
+    pub blocked: bool,
+    pub detail: Option<String>,
+    #[serde(with = "::util::rfc3339")]
+    pub scanned_at: NaiveDateTime,
+}
+
+/// A file of the crate file of a version.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionFile {
+    pub path: String,
+    pub size: i64,
+    pub sha256: String,
+}
+
+/// A file that differs between two versions of a crate.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableFileChange {
+    pub path: String,
+    pub from_size: i64,
+    pub to_size: i64,
+    pub from_sha256: String,
+    pub to_sha256: String,
+}
+
+/// Who published a version and from where, as listed to the owners of the
+/// crate by `GET /crates/:crate_id/audit`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionPublish {
+    pub num: String,
+    #[serde(with = "::util::rfc3339")]
+    pub published_at: NaiveDateTime,
+    /// The login of the user the version was published by, unknown for old
+    /// versions.
+    pub published_by: Option<String>,
+    /// How the publish was authenticated, e.g. `session` or `api_token:laptop`.
+    pub published_with: Option<String>,
+    /// The `X-Cargo-Version` header of the publish.
+    pub cargo_version: Option<String>,
+    /// The `X-CI-Provider` header of the publish.
+    pub ci_provider: Option<String>,
+    pub yanked: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EncodableVersionLinks {
     pub dependencies: String,
     pub version_downloads: String,
@@ -199,6 +432,7 @@ pub struct EncodableVersionLinks {
 
 // TODO: Prefix many of these with `Encodable` then clean up the reexports
 pub mod krate_publish;
+pub mod v2;
 pub use self::krate_publish::CrateDependency as EncodableCrateDependency;
 pub use self::krate_publish::NewCrate as EncodableCrateUpload;
 
@@ -276,6 +510,10 @@ mod tests {
             features: HashMap::new(),
             yanked: false,
             license: None,
+            edition: None,
+            binaries: Vec::new(),
+            published_by: None,
+            published_with: None,
             links: EncodableVersionLinks {
                 dependencies: "".to_string(),
                 version_downloads: "".to_string(),
@@ -308,11 +546,22 @@ mod tests {
             created_at: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12),
             downloads: 0,
             recent_downloads: None,
+            dependency_freshness: None,
+            quality_score: None,
             max_version: "".to_string(),
+            default_version: "".to_string(),
+            all_versions_yanked: false,
             description: None,
             homepage: None,
             documentation: None,
             repository: None,
+            license: None,
+            license_expression: None,
+            deprecated: false,
+            deprecation_message: None,
+            alternative: None,
+            maintenance_status: None,
+            logo_url: None,
             links: EncodableCrateLinks {
                 version_downloads: "".to_string(),
                 versions: None,
@@ -0,0 +1,141 @@
+//! The encoding of the responses served under `/api/v2`.
+//!
+//! The v2 routes are the v1 routes, with their JSON responses rewritten into
+//! the same envelope:
+//!
+//! ```json
+//! { "data": ..., "meta": { ... }, "errors": [ { "detail": "..." } ] }
+//! ```
+//!
+//! Field names are camelCased. A response holding a single resource or list,
+//! like `{ "crate": ... }` or the `versions` of a crate, has it as `data`
+//! directly. Errors are sent with a 400 status instead of the
+//! 200 cargo needs to display them.
+//!
+//! Request bodies and query parameters are the same as in v1.
+
+use serde_json::{Map, Value};
+
+/// The fields whose value is keyed by data rather than by field names, such
+/// as feature names, and is left as is.
+const OPAQUE_FIELDS: &[&str] = &["attributes", "features", "warnings"];
+
+/// Converts a `snake_case` field name to `camelCase`.
+pub fn camel_case(name: &str) -> String {
+    let mut camel = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' && !camel.is_empty() {
+            upper = true;
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+fn camel_case_fields(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let value = if OPAQUE_FIELDS.contains(&&*name) {
+                        value
+                    } else {
+                        camel_case_fields(value)
+                    };
+                    (camel_case(&name), value)
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(camel_case_fields).collect()),
+        value => value,
+    }
+}
+
+/// Wraps the body of a v1 response in the v2 envelope, returning whether it
+/// holds errors.
+pub fn envelope(body: Value) -> (Value, bool) {
+    let mut fields = match body {
+        Value::Object(fields) => fields,
+        data => return (wrap(camel_case_fields(data), Map::new(), Vec::new()), false),
+    };
+
+    if let Some(errors) = fields.remove("errors") {
+        let errors = match errors {
+            Value::Array(errors) => errors,
+            error => vec![error],
+        };
+        return (wrap(Value::Null, Map::new(), errors), true);
+    }
+
+    let meta = match fields.remove("meta") {
+        Some(Value::Object(meta)) => meta,
+        _ => Map::new(),
+    };
+    let meta = match camel_case_fields(Value::Object(meta)) {
+        Value::Object(meta) => meta,
+        _ => unreachable!(),
+    };
+    let data = if fields.len() == 1 {
+        fields.into_iter().next().unwrap().1
+    } else {
+        Value::Object(fields)
+    };
+    (wrap(camel_case_fields(data), meta, Vec::new()), false)
+}
+
+fn wrap(data: Value, meta: Map<String, Value>, errors: Vec<Value>) -> Value {
+    json!({
+        "data": data,
+        "meta": meta,
+        "errors": errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_names_are_camel_cased() {
+        assert_eq!(camel_case("max_version"), "maxVersion");
+        assert_eq!(camel_case("dl_path"), "dlPath");
+        assert_eq!(camel_case("id"), "id");
+        assert_eq!(camel_case("_private"), "_private");
+    }
+
+    #[test]
+    fn single_resources_are_unwrapped() {
+        let (body, errors) = envelope(json!({
+            "crate": { "max_version": "1.0.0", "badges": [{ "attributes": { "some_key": "a" } }] },
+            "meta": { "total": 1 },
+        }));
+        assert!(!errors);
+        assert_eq!(
+            body,
+            json!({
+                "data": { "maxVersion": "1.0.0", "badges": [{ "attributes": { "some_key": "a" } }] },
+                "meta": { "total": 1 },
+                "errors": [],
+            })
+        );
+
+        let (body, _) = envelope(json!({ "crate": {}, "versions": [] }));
+        assert_eq!(body["data"], json!({ "crate": {}, "versions": [] }));
+    }
+
+    #[test]
+    fn errors_have_no_data() {
+        let (body, errors) = envelope(json!({ "errors": [{ "detail": "not found" }] }));
+        assert!(errors);
+        assert_eq!(
+            body,
+            json!({ "data": null, "meta": {}, "errors": [{ "detail": "not found" }] })
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Delivery of the webhooks owners register to be notified of the
+//! publications and yanks of their crates.
+//!
+//! Events are queued as `webhook_deliveries` rows in the same transaction as
+//! the change they describe, and POSTed by a background task. Every payload is
+//! signed with the secret of the webhook, sent in the `X-Crates-Io-Signature`
+//! header as `sha256=<hex HMAC-SHA256 of the body>`.
+//!
+//! Webhooks can't point at the loopback interface or at private networks,
+//! which would let owners reach the internal services of the registry. The
+//! host is checked when the webhook is registered, and resolved again before
+//! every delivery since its DNS records can change.
+
+use std::net::{IpAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+use curl::easy::List;
+use diesel;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use hex;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use url::{Host, Url};
+
+use app::App;
+use models::{CrateWebhook, WebhookDelivery};
+use schema::{crate_webhooks, webhook_deliveries};
+use util::{human, internal, CargoResult, Cidr};
+
+/// How many times a delivery is attempted before it is given up on.
+pub const MAX_ATTEMPTS: i32 = 5;
+
+/// How many deliveries are sent on every run of the background task.
+const BATCH_SIZE: i64 = 100;
+
+/// The address ranges webhooks can't be delivered to: this network, loopback,
+/// private, shared (carrier-grade NAT) and link-local addresses, and their
+/// IPv6 counterparts.
+const INTERNAL_RANGES: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "::/128",
+    "::1/128",
+    "fc00::/7",
+    "fe80::/10",
+];
+
+fn is_internal(ip: &IpAddr) -> bool {
+    // IPv4 addresses can also be written as IPv6 ones, like `::ffff:127.0.0.1`
+    let ip = match *ip {
+        IpAddr::V6(v6) => v6.to_ipv4().map_or(*ip, IpAddr::V4),
+        ip => ip,
+    };
+    INTERNAL_RANGES
+        .iter()
+        .filter_map(|range| range.parse::<Cidr>().ok())
+        .any(|range| range.contains(&ip))
+}
+
+/// Checks that the URL can be notified: it must be an http or https URL
+/// whose host isn't an internal address. Domains are only resolved by
+/// `check_resolved_host`, when delivering.
+pub fn check_url(url: &str) -> CargoResult<Url> {
+    let url = Url::parse(url).map_err(|_| human("the webhook url must be an http or https URL"))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(human("the webhook url must be an http or https URL"));
+    }
+    let internal = match url.host() {
+        Some(Host::Ipv4(ip)) => is_internal(&IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_internal(&IpAddr::V6(ip)),
+        Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+        None => true,
+    };
+    if internal {
+        return Err(human(
+            "the webhook url can't point at a loopback or private address",
+        ));
+    }
+    Ok(url)
+}
+
+/// Checks that none of the addresses the host of the URL resolves to is
+/// internal.
+fn check_resolved_host(url: &Url) -> CargoResult<()> {
+    let domain = match url.host() {
+        Some(Host::Domain(domain)) => domain,
+        _ => return Ok(()),
+    };
+    let port = url.port_or_known_default().unwrap_or(80);
+    let mut addrs = (domain, port).to_socket_addrs()?;
+    if addrs.any(|addr| is_internal(&addr.ip())) {
+        return Err(internal(&format_args!(
+            "the webhook host `{}` resolves to a loopback or private address",
+            domain
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the hex encoded HMAC-SHA256 of the body with the secret as key.
+pub fn sign(secret: &str, body: &[u8]) -> CargoResult<String> {
+    let key = PKey::hmac(secret.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(body)?;
+    Ok(hex::encode(signer.sign_to_vec()?))
+}
+
+/// Sends the pending deliveries, returning how many of them succeeded.
+///
+/// A failed delivery is retried on the next run until it has been attempted
+/// `MAX_ATTEMPTS` times, the last error being kept on the delivery.
+pub fn deliver_pending(app: &App) -> CargoResult<usize> {
+    let conn = app.diesel_database.get()?;
+    let pending = webhook_deliveries::table
+        .inner_join(crate_webhooks::table)
+        .filter(webhook_deliveries::delivered_at.is_null())
+        .filter(webhook_deliveries::attempts.lt(MAX_ATTEMPTS))
+        .order(webhook_deliveries::id)
+        .limit(BATCH_SIZE)
+        .load::<(WebhookDelivery, CrateWebhook)>(&*conn)?;
+
+    let mut delivered = 0;
+    for (delivery, webhook) in pending {
+        let result = deliver(app, &delivery, &webhook);
+        let attempts = webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1);
+        match result {
+            Ok(()) => {
+                diesel::update(&delivery)
+                    .set((attempts, webhook_deliveries::delivered_at.eq(now.nullable())))
+                    .execute(&*conn)?;
+                delivered += 1;
+            }
+            Err(e) => {
+                diesel::update(&delivery)
+                    .set((attempts, webhook_deliveries::last_error.eq(e.to_string())))
+                    .execute(&*conn)?;
+            }
+        }
+    }
+    Ok(delivered)
+}
+
+fn deliver(app: &App, delivery: &WebhookDelivery, webhook: &CrateWebhook) -> CargoResult<()> {
+    check_resolved_host(&check_url(&webhook.url)?)?;
+    let body = delivery.payload.to_string();
+    let mut headers = List::new();
+    headers.append("Content-Type: application/json")?;
+    headers.append(&format!("X-Crates-Io-Event: {}", delivery.event))?;
+    headers.append(&format!(
+        "X-Crates-Io-Signature: sha256={}",
+        sign(&webhook.secret, body.as_bytes())?
+    ))?;
+
+    let mut handle = app.handle();
+    handle.url(&webhook.url)?;
+    handle.post(true)?;
+    handle.post_fields_copy(body.as_bytes())?;
+    handle.http_headers(headers)?;
+    handle.timeout(time::Duration::from_secs(10))?;
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|buf| Ok(buf.len()))?;
+        transfer.perform()?;
+    }
+    match handle.response_code()? {
+        200...299 => Ok(()),
+        code => Err(internal(&format_args!("the webhook responded with {}", code))),
+    }
+}
+
+/// Spawns a thread sending the pending deliveries every `interval`.
+pub fn spawn_delivery_task(app: Arc<App>, interval: time::Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        match deliver_pending(&app) {
+            Ok(0) => {}
+            Ok(n) => info!("delivered {} webhook events", n),
+            Err(e) => error!("failed to deliver webhook events: {}", e),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_addresses() {
+        assert!(check_url("http://127.0.0.1/hook").is_err());
+        assert!(check_url("http://10.1.2.3:8080/hook").is_err());
+        assert!(check_url("http://169.254.169.254/latest/meta-data").is_err());
+        assert!(check_url("http://[::1]/hook").is_err());
+        assert!(check_url("http://[::ffff:192.168.0.1]/hook").is_err());
+        assert!(check_url("http://localhost/hook").is_err());
+        assert!(check_url("ftp://93.184.216.34/hook").is_err());
+        assert!(check_url("https://93.184.216.34/hook").is_ok());
+        assert!(check_url("http://[2606:2800:220:1::]/hook").is_ok());
+    }
+
+    #[test]
+    fn signature() {
+        // Test case 2 of RFC 4231.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?").unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}